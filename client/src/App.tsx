@@ -21,6 +21,9 @@ import NotFound from "@/pages/not-found";
 import Sidebar from "@/components/sidebar";
 import BurstSelectionPage from "./pages/burst-selection";
 import { GlobalUploadProgress } from "@/components/global-upload-progress";
+import { ShutdownNotice } from "@/components/shutdown-notice";
+import { ShareImportNotice } from "@/components/share-import-notice";
+import PhotoViewer from "@/pages/photo-viewer";
 
 function Router() {
   return (
@@ -46,6 +49,8 @@ function Router() {
           <Route component={NotFound} />
         </Switch>
         <GlobalUploadProgress />
+        <ShutdownNotice />
+        <ShareImportNotice />
       </main>
     </div>
   );
@@ -57,7 +62,11 @@ function App() {
       <ThemeProvider>
         <TooltipProvider>
           <Toaster />
-          <Router />
+          <Switch>
+            {/* Viewer windows are bare: no sidebar or app chrome */}
+            <Route path="/viewer/:id" component={PhotoViewer} />
+            <Route component={Router} />
+          </Switch>
         </TooltipProvider>
       </ThemeProvider>
     </QueryClientProvider>
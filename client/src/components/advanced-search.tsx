@@ -16,7 +16,12 @@ export interface SearchFilters {
   query?: string;
   tier?: 'silver' | 'gold';
   rating?: { min?: number; max?: number };
-  dateRange?: { start?: Date; end?: Date };
+  dateRange?: {
+    start?: Date;
+    end?: Date;
+    relative?: RelativeDateRange; // worked out on the server each time the search runs
+    field?: 'capturedAt' | 'importedAt' | 'modifiedAt';
+  };
   keywords?: string[];
   eventType?: string[];
   eventName?: string;
@@ -31,6 +36,25 @@ export interface SearchFilters {
   isReviewed?: boolean;
 }
 
+type RelativeDateRange =
+  | 'today' | 'yesterday' | 'last_7_days' | 'last_30_days' | 'last_90_days' | 'last_365_days'
+  | 'this_week' | 'this_month' | 'this_year' | 'last_week' | 'last_month' | 'last_year';
+
+const RELATIVE_RANGE_LABELS: Record<RelativeDateRange, string> = {
+  today: 'Today',
+  yesterday: 'Yesterday',
+  last_7_days: 'Last 7 days',
+  last_30_days: 'Last 30 days',
+  last_90_days: 'Last 90 days',
+  last_365_days: 'Last 365 days',
+  this_week: 'This week',
+  last_week: 'Last week',
+  this_month: 'This month',
+  last_month: 'Last month',
+  this_year: 'This year',
+  last_year: 'Last year',
+};
+
 interface AdvancedSearchProps {
   filters: SearchFilters;
   onFiltersChange: (filters: SearchFilters) => void;
@@ -53,7 +77,7 @@ export function AdvancedSearch({
     if (filters.query) active.push('query');
     if (filters.tier) active.push('tier');
     if (filters.rating?.min || filters.rating?.max) active.push('rating');
-    if (filters.dateRange?.start || filters.dateRange?.end) active.push('date');
+    if (filters.dateRange?.start || filters.dateRange?.end || filters.dateRange?.relative) active.push('date');
     if (filters.keywords?.length) active.push('keywords');
     if (filters.eventType?.length) active.push('events');
     if (filters.location) active.push('location');
@@ -369,36 +393,78 @@ export function AdvancedSearch({
 
               <div className="space-y-3">
                 <div>
-                  <Label htmlFor="startDate">From</Label>
-                  <Input
-                    id="startDate"
-                    type="date"
-                    value={filters.dateRange?.start?.toISOString().split('T')[0] || ""}
-                    onChange={(e) => {
-                      const date = e.target.value ? new Date(e.target.value) : undefined;
-                      updateFilter('dateRange', {
-                        ...filters.dateRange,
-                        start: date
-                      });
-                    }}
-                  />
+                  <Label htmlFor="dateField">Date of</Label>
+                  <Select
+                    value={filters.dateRange?.field ?? 'capturedAt'}
+                    onValueChange={(value: any) => updateFilter('dateRange', { ...filters.dateRange, field: value })}
+                  >
+                    <SelectTrigger id="dateField">
+                      <SelectValue />
+                    </SelectTrigger>
+                    <SelectContent>
+                      <SelectItem value="capturedAt">Capture</SelectItem>
+                      <SelectItem value="importedAt">Import</SelectItem>
+                      <SelectItem value="modifiedAt">Last modification</SelectItem>
+                    </SelectContent>
+                  </Select>
                 </div>
 
                 <div>
-                  <Label htmlFor="endDate">To</Label>
-                  <Input
-                    id="endDate"
-                    type="date"
-                    value={filters.dateRange?.end?.toISOString().split('T')[0] || ""}
-                    onChange={(e) => {
-                      const date = e.target.value ? new Date(e.target.value) : undefined;
-                      updateFilter('dateRange', {
-                        ...filters.dateRange,
-                        end: date
-                      });
-                    }}
-                  />
+                  <Label htmlFor="relativeRange">Period</Label>
+                  <Select
+                    value={filters.dateRange?.relative ?? 'custom'}
+                    onValueChange={(value) => updateFilter('dateRange', {
+                      ...filters.dateRange,
+                      relative: value === 'custom' ? undefined : value as RelativeDateRange
+                    })}
+                  >
+                    <SelectTrigger id="relativeRange">
+                      <SelectValue />
+                    </SelectTrigger>
+                    <SelectContent>
+                      <SelectItem value="custom">Custom dates</SelectItem>
+                      {(Object.keys(RELATIVE_RANGE_LABELS) as RelativeDateRange[]).map(range => (
+                        <SelectItem key={range} value={range}>{RELATIVE_RANGE_LABELS[range]}</SelectItem>
+                      ))}
+                    </SelectContent>
+                  </Select>
                 </div>
+
+                {!filters.dateRange?.relative && (
+                  <>
+                    <div>
+                      <Label htmlFor="startDate">From</Label>
+                      <Input
+                        id="startDate"
+                        type="date"
+                        value={filters.dateRange?.start?.toISOString().split('T')[0] || ""}
+                        onChange={(e) => {
+                          const date = e.target.value ? new Date(e.target.value) : undefined;
+                          updateFilter('dateRange', {
+                            ...filters.dateRange,
+                            start: date
+                          });
+                        }}
+                      />
+                    </div>
+
+                    <div>
+                      <Label htmlFor="endDate">To</Label>
+                      <Input
+                        id="endDate"
+                        type="date"
+                        value={filters.dateRange?.end?.toISOString().split('T')[0] || ""}
+                        onChange={(e) => {
+                          const date = e.target.value ? new Date(e.target.value) : undefined;
+                          updateFilter('dateRange', {
+                            ...filters.dateRange,
+                            end: date
+                          });
+                        }}
+                      />
+                    </div>
+                  </>
+                )}
               </div>
             </div>
 
@@ -119,12 +119,11 @@ export function AISettingsModal({ open, onOpenChange }: AISettingsModalProps) {
           visionModel: formData.ollamaVisionModel,
           textModel: formData.ollamaTextModel
         },
-        ...(formData.openaiApiKey && {
-          openai: {
-            apiKey: formData.openaiApiKey,
-            model: formData.openaiModel
-          }
-        })
+        openai: {
+          model: formData.openaiModel,
+          // Left out when empty, keeping the saved key
+          ...(formData.openaiApiKey && { apiKey: formData.openaiApiKey })
+        }
       };
 
       const response = await fetch("/api/ai/config", {
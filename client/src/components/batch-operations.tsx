@@ -179,8 +179,8 @@ export default function BatchOperations({
       const result = await response.json();
 
       toast({
-        title: "Operation Complete",
-        description: `Added AI analysis to ${result.processed} photos.`,
+        title: "Queued for AI Analysis",
+        description: `${result.queued} photos will be analyzed in the background.`,
       });
       queryClient.invalidateQueries({ queryKey: ["/api/photos"] });
       onClose();
@@ -4,8 +4,9 @@ import { Switch } from "@/components/ui/switch";
 import { Button } from "@/components/ui/button";
 import { Badge } from "@/components/ui/badge";
 import { Separator } from "@/components/ui/separator";
-import { Calendar, Globe, Users, Save } from "lucide-react";
-import { useState, useEffect } from "react";
+import { Input } from "@/components/ui/input";
+import { Calendar, Globe, Users, Save, Upload, Trash2 } from "lucide-react";
+import { useState, useEffect, useRef, type ChangeEvent } from "react";
 import { useQuery, useMutation, useQueryClient } from "@tanstack/react-query";
 import { useToast } from "@/hooks/use-toast";
 import { apiRequest } from "@/lib/queryClient";
@@ -14,10 +15,17 @@ interface HolidaySet {
   code: string;
   name: string;
   count: number;
+  source: 'bundled' | 'imported';
+}
+
+interface ImportedHolidaySet extends HolidaySet {
+  skipped: string[];
 }
 
 export default function EventSettings() {
   const [enabledHolidays, setEnabledHolidays] = useState<string[]>(['US']);
+  const [calendarName, setCalendarName] = useState('');
+  const fileInputRef = useRef<HTMLInputElement>(null);
   const queryClient = useQueryClient();
   const { toast } = useToast();
 
@@ -71,6 +79,70 @@ export default function EventSettings() {
     }
   });
 
+  // Import a custom holiday set from an ICS file; it is enabled right away
+  const importCalendar = useMutation({
+    mutationFn: async (file: File): Promise<ImportedHolidaySet> => {
+      const formData = new FormData();
+      formData.append('file', file);
+      if (calendarName.trim()) {
+        formData.append('name', calendarName.trim());
+      }
+      const response = await fetch('/api/events/holiday-sets/import', {
+        method: 'POST',
+        body: formData,
+      });
+      const result = await response.json();
+      if (!response.ok) {
+        throw new Error(result.message || 'Import failed');
+      }
+      return result;
+    },
+    onSuccess: (calendar) => {
+      setCalendarName('');
+      queryClient.invalidateQueries({ queryKey: ['/api/events/holiday-sets'] });
+      queryClient.invalidateQueries({ queryKey: ['/api/settings/enabled_holidays'] });
+      toast({
+        title: "Calendar Imported",
+        description: calendar.skipped.length > 0
+          ? `${calendar.name}: ${calendar.count} holidays imported, ${calendar.skipped.length} skipped.`
+          : `${calendar.name}: ${calendar.count} holidays imported.`,
+      });
+    },
+    onError: (error: Error) => {
+      toast({
+        title: "Import Failed",
+        description: error.message,
+        variant: "destructive"
+      });
+    }
+  });
+
+  const deleteCalendar = useMutation({
+    mutationFn: async (code: string) => {
+      const response = await apiRequest('DELETE', `/api/events/holiday-sets/${encodeURIComponent(code)}`);
+      return response.json();
+    },
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ['/api/events/holiday-sets'] });
+      queryClient.invalidateQueries({ queryKey: ['/api/settings/enabled_holidays'] });
+    },
+    onError: () => {
+      toast({
+        title: "Delete Failed",
+        description: "Failed to delete the holiday set.",
+        variant: "destructive"
+      });
+    }
+  });
+
+  const handleCalendarFile = (event: ChangeEvent<HTMLInputElement>) => {
+    const file = event.target.files?.[0];
+    if (file) {
+      importCalendar.mutate(file);
+    }
+    event.target.value = '';
+  };
+
   const handleHolidayToggle = (countryCode: string, enabled: boolean) => {
     if (enabled) {
       setEnabledHolidays(prev => [...prev, countryCode]);
@@ -101,7 +173,7 @@ export default function EventSettings() {
             Holiday Detection
           </h3>
           <p className="text-sm text-muted-foreground mb-4">
-            Choose which country's holidays to automatically detect in your photos.
+            Choose which holiday calendars to automatically detect in your photos.
           </p>
           
           <div className="space-y-3">
@@ -118,12 +190,49 @@ export default function EventSettings() {
                       <Badge variant="secondary" className="text-xs">
                         {holidaySet.count} holidays
                       </Badge>
+                      {holidaySet.source === 'imported' && (
+                        <Badge variant="outline" className="text-xs">Imported</Badge>
+                      )}
                     </div>
                   </div>
                 </div>
+                {holidaySet.source === 'imported' && (
+                  <Button
+                    variant="ghost"
+                    size="sm"
+                    onClick={() => deleteCalendar.mutate(holidaySet.code)}
+                    disabled={deleteCalendar.isPending}
+                  >
+                    <Trash2 className="w-4 h-4" />
+                  </Button>
+                )}
               </div>
             ))}
           </div>
+
+          <div className="flex items-center gap-2 mt-4">
+            <Input
+              placeholder="Calendar name (optional)"
+              value={calendarName}
+              onChange={(e) => setCalendarName(e.target.value)}
+            />
+            <input
+              ref={fileInputRef}
+              type="file"
+              accept=".ics,text/calendar"
+              className="hidden"
+              onChange={handleCalendarFile}
+            />
+            <Button
+              variant="outline"
+              onClick={() => fileInputRef.current?.click()}
+              disabled={importCalendar.isPending}
+              className="flex items-center gap-2 shrink-0"
+            >
+              <Upload className="w-4 h-4" />
+              {importCalendar.isPending ? 'Importing...' : 'Import ICS'}
+            </Button>
+          </div>
         </div>
 
         <Separator />
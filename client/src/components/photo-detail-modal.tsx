@@ -23,13 +23,15 @@ import {
   Minimize2,
   Archive,
   RefreshCw,
-  Users
+  Users,
+  ExternalLink
 } from "lucide-react";
 import { cn } from "@/lib/utils";
 import { useState, useEffect, useRef } from "react";
 import { useMutation, useQueryClient, useQuery } from "@tanstack/react-query";
 import { useToast } from "@/hooks/use-toast";
 import { apiRequest } from "@/lib/queryClient";
+import { openPhotoWindow } from "@/lib/windows";
 import type { Photo } from "@shared/types";
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { FaceDetectionBadge, getFaceDetectionStatus } from "@/components/ui/processing-state-badge";
@@ -609,14 +611,25 @@ export default function PhotoDetailModal({
                   </Badge>
                 )}
               </div>
-              <Button
-                variant="ghost"
-                size="sm"
-                onClick={() => onOpenChange(false)}
-                className="text-gray-500 hover:text-gray-700"
-              >
-                <X className="w-5 h-5" />
-              </Button>
+              <div className="flex items-center gap-1">
+                <Button
+                  variant="ghost"
+                  size="sm"
+                  title="Open in viewer window"
+                  onClick={() => openPhotoWindow(photo.id).catch(() => {})}
+                  className="text-gray-500 hover:text-gray-700"
+                >
+                  <ExternalLink className="w-5 h-5" />
+                </Button>
+                <Button
+                  variant="ghost"
+                  size="sm"
+                  onClick={() => onOpenChange(false)}
+                  className="text-gray-500 hover:text-gray-700"
+                >
+                  <X className="w-5 h-5" />
+                </Button>
+              </div>
             </div>
 
             {/* Main Image */}
@@ -901,7 +914,7 @@ export default function PhotoDetailModal({
               )}
 
               {/* Camera Information */}
-              {(photo.metadata?.exif?.camera || photo.metadata?.exif?.lens || photo.metadata?.exif?.aperture || photo.metadata?.exif?.shutter || photo.metadata?.exif?.iso) && (
+              {(photo.metadata?.exif?.camera || photo.metadata?.exif?.lens || photo.metadata?.exif?.aperture || photo.metadata?.exif?.shutter || photo.metadata?.exif?.iso || photo.metadata?.exif?.colorSpace) && (
                 <div className="bg-gray-50 dark:bg-gray-800 p-4 rounded-lg border">
                   <h3 className="text-base font-semibold text-gray-800 dark:text-gray-200 mb-3 flex items-center">
                     <Camera className="w-5 h-5 mr-2" />
@@ -938,6 +951,14 @@ export default function PhotoDetailModal({
                         <span className="text-gray-800 dark:text-gray-200 font-medium">{photo.metadata.exif.iso}</span>
                       </div>
                     )}
+                    {photo.metadata?.exif?.colorSpace && (
+                      <div>
+                        <span className="text-gray-600 dark:text-gray-400 block">Color Space</span>
+                        <span className="text-gray-800 dark:text-gray-200 font-medium" title={photo.metadata.exif.colorProfile}>
+                          {photo.metadata.exif.colorSpace}{photo.metadata.exif.wideGamut ? ' (wide gamut)' : ''}
+                        </span>
+                      </div>
+                    )}
                   </div>
                 </div>
               )}
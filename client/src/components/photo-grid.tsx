@@ -1,7 +1,7 @@
 import { Button } from "@/components/ui/button";
 import { Badge } from "@/components/ui/badge";
 import { Card, CardContent } from "@/components/ui/card";
-import { Heart, Bot, Eye, Star, MoreVertical } from "lucide-react";
+import { Heart, Bot, Eye, Star, MoreVertical, Layers } from "lucide-react";
 import { cn } from "@/lib/utils";
 import { OptimizedImage } from "@/components/optimized-image";
 
@@ -10,6 +10,12 @@ import type { Photo } from "@shared/types";
 // Helper function to extract photo date from EXIF or filename
 const extractPhotoDate = (photo: Photo): Date => {
   try {
+    // Capture date (or file modification time) stored by the server
+    if (photo.takenAt) {
+      const date = new Date(photo.takenAt);
+      if (!isNaN(date.getTime())) return date;
+    }
+
     // First try EXIF metadata
     if (photo.metadata?.exif) {
       const exif = photo.metadata.exif as any;
@@ -279,6 +285,16 @@ export default function PhotoGrid({
                 </div>
               )}
               
+              {/* Stack Badge */}
+              {photo.stackSize && photo.stackSize > 1 && (
+                <div className="absolute bottom-2 right-2">
+                  <Badge variant="secondary" className="bg-black/70 text-white text-xs">
+                    <Layers className="w-3 h-3 mr-1" />
+                    {photo.stackSize}
+                  </Badge>
+                </div>
+              )}
+              
               {/* Selection Checkbox */}
               {onPhotoSelect && (
                 <div className="absolute top-2 left-2 opacity-0 group-hover:opacity-100 transition-opacity">
@@ -0,0 +1,56 @@
+import { useEffect } from 'react';
+import { useQueryClient } from "@tanstack/react-query";
+import { toast } from "@/hooks/use-toast";
+
+interface ShareEvent {
+  importId: string;
+  total: number;
+  fileNames: string[];
+}
+
+interface ImportFinishedEvent {
+  importId: string;
+  status: string;
+  imported: number;
+  duplicates: number;
+  failed: number;
+}
+
+/**
+ * Toasts for files shared with, dropped on or opened with the desktop app:
+ * one when the import is queued and one with the outcome.
+ */
+export function ShareImportNotice() {
+  const queryClient = useQueryClient();
+
+  useEffect(() => {
+    const shared = new Set<string>();
+    const events = new EventSource('/api/imports/events');
+
+    events.addEventListener('shared', (event) => {
+      const data: ShareEvent = JSON.parse((event as MessageEvent).data);
+      shared.add(data.importId);
+      const more = data.total - data.fileNames.length;
+      toast({
+        title: `Importing ${data.total} shared file${data.total === 1 ? '' : 's'}`,
+        description: data.fileNames.join(', ') + (more > 0 ? ` and ${more} more` : ''),
+      });
+    });
+
+    events.addEventListener('finished', (event) => {
+      const data: ImportFinishedEvent = JSON.parse((event as MessageEvent).data);
+      if (!shared.delete(data.importId)) return;
+      queryClient.invalidateQueries({ queryKey: ['/api/photos'] });
+      queryClient.invalidateQueries({ queryKey: ['/api/stats'] });
+      toast({
+        title: data.status === 'completed' ? "Shared files imported" : "Shared import stopped",
+        description: `${data.imported} new, ${data.duplicates} already in the library${data.failed > 0 ? `, ${data.failed} failed` : ''}`,
+        variant: data.failed > 0 ? "destructive" : "default",
+      });
+    });
+
+    return () => events.close();
+  }, [queryClient]);
+
+  return null;
+}
@@ -0,0 +1,57 @@
+import { useEffect } from 'react';
+import { toast } from "@/hooks/use-toast";
+
+interface ShutdownEvent {
+  phase: 'started' | 'completed';
+  reason: string;
+  interruptedOperations?: number;
+}
+
+interface InterruptedOperation {
+  id: string;
+  label: string;
+  completed: number;
+  total: number | null;
+}
+
+/**
+ * Tells the user when the server is pausing work before exit, and on the next
+ * launch lists the operations that were interrupted so they can be restarted.
+ */
+export function ShutdownNotice() {
+  useEffect(() => {
+    const events = new EventSource('/api/system/events');
+    events.addEventListener('shutdown', (event) => {
+      const data: ShutdownEvent = JSON.parse((event as MessageEvent).data);
+      if (data.phase === 'started') {
+        toast({
+          title: "Closing Pictallion",
+          description: "Finishing the current item and saving progress...",
+        });
+      }
+      // The stream is closed on purpose after shutdown; don't reconnect to a stopping server
+      if (data.phase === 'completed') {
+        events.close();
+      }
+    });
+
+    fetch('/api/system/interrupted-operations')
+      .then(response => response.ok ? response.json() : null)
+      .then((checkpoint: { interruptedOperations: InterruptedOperation[] } | null) => {
+        const interrupted = checkpoint?.interruptedOperations ?? [];
+        if (interrupted.length === 0) return;
+        toast({
+          title: "Work was interrupted last time",
+          description: interrupted
+            .map(operation => `${operation.label} (${operation.completed}${operation.total ? `/${operation.total}` : ''} done)`)
+            .join(', '),
+        });
+        fetch('/api/system/interrupted-operations', { method: 'DELETE' });
+      })
+      .catch(() => {});
+
+    return () => events.close();
+  }, []);
+
+  return null;
+}
@@ -36,6 +36,12 @@ interface PhotoCellProps {
 // Helper function to extract photo date
 const extractPhotoDate = (photo: Photo): Date => {
   try {
+    // Capture date (or file modification time) stored by the server
+    if (photo.takenAt) {
+      const date = new Date(photo.takenAt);
+      if (!isNaN(date.getTime())) return date;
+    }
+
     if (photo.metadata?.exif) {
       const exif = photo.metadata.exif as any;
       
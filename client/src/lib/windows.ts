@@ -0,0 +1,39 @@
+import { apiRequest } from "./queryClient";
+
+export type WindowKind = 'gallery' | 'viewer';
+export type WindowEvent = 'photo_shown' | 'photo_updated';
+
+// sessionStorage is per browser window, so each window keeps its id across reloads
+const WINDOW_ID_KEY = 'pictallion-window-id';
+
+export function getWindowId(): string {
+  let id = sessionStorage.getItem(WINDOW_ID_KEY);
+  if (!id) {
+    id = crypto.randomUUID();
+    sessionStorage.setItem(WINDOW_ID_KEY, id);
+  }
+  return id;
+}
+
+export function connectWindowEvents(kind: WindowKind): EventSource {
+  const params = new URLSearchParams({ windowId: getWindowId(), kind });
+  return new EventSource(`/api/windows/events?${params}`);
+}
+
+/**
+ * Show a photo in the viewer window, opening one when none is open
+ */
+export async function openPhotoWindow(photoId: string): Promise<void> {
+  const response = await apiRequest('POST', '/api/windows/open-photo', { photoId, sourceWindowId: getWindowId() });
+  const result: { url?: string } = await response.json();
+  if (result.url) {
+    window.open(result.url, `pictallion-viewer-${photoId}`, 'popup');
+  }
+}
+
+/**
+ * Tell the other windows (or just one) about a change made in this one
+ */
+export async function publishWindowEvent(event: WindowEvent, data: Record<string, unknown>, targetWindowId?: string): Promise<void> {
+  await apiRequest('POST', '/api/windows/publish', { event, data, sourceWindowId: getWindowId(), targetWindowId });
+}
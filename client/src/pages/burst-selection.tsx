@@ -20,11 +20,14 @@ interface BurstGroup {
     createdAt: string;
     fileSize?: number;
     fileHash: string;
+    capturedAt: string;
+    sharpness: number | null;
   }>;
   suggestedBest: string;
-  averageSimilarity: number;
+  camera: string | null;
   timeSpan: number;
   groupReason: string;
+  stackId: string | null;
 }
 
 interface BurstAnalysis {
@@ -39,6 +42,7 @@ export default function BurstSelectionPage() {
   const [processing, setProcessing] = useState(false);
   const [selectedPhotos, setSelectedPhotos] = useState<Map<string, string[]>>(new Map()); // groupId -> photoIds[]
   const [scanProgress, setScanProgress] = useState(0);
+  const [measuringGroup, setMeasuringGroup] = useState<string | null>(null);
   const queryClient = useQueryClient();
   const { toast } = useToast();
 
@@ -162,6 +166,43 @@ export default function BurstSelectionPage() {
     setSelectedPhotos(newSelections);
   };
 
+  // Measure every frame, keep the sharpest and stack the rest under it
+  const keepSharpest = async (groupId: string) => {
+    setMeasuringGroup(groupId);
+    try {
+      const response = await apiRequest('POST', `/api/bursts/${groupId}/select-best`, {
+        body: JSON.stringify({ stack: true }),
+        headers: { 'Content-Type': 'application/json' }
+      });
+      const result = await response.json();
+
+      setAnalysis(prev => prev && {
+        ...prev,
+        groups: prev.groups.map(group => group.id === groupId ? result.burst : group)
+      });
+      const newSelections = new Map(selectedPhotos);
+      newSelections.set(groupId, [result.bestPhotoId]);
+      setSelectedPhotos(newSelections);
+      queryClient.invalidateQueries({ queryKey: ["/api/photos"] });
+
+      toast({
+        title: "Sharpest Frame Selected",
+        description: result.skipped.length > 0
+          ? `${result.skipped.length} frames could not be measured.`
+          : "The other frames are stacked under it.",
+      });
+    } catch (error) {
+      console.error('Failed to select sharpest frame:', error);
+      toast({
+        title: "Selection Failed",
+        description: "Failed to measure the frames of this burst.",
+        variant: "destructive"
+      });
+    } finally {
+      setMeasuringGroup(null);
+    }
+  };
+
   const formatTimeSpan = (ms: number) => {
     if (ms < 1000) return `${ms}ms`;
     if (ms < 60000) return `${Math.round(ms / 1000)}s`;
@@ -186,13 +227,13 @@ export default function BurstSelectionPage() {
         <Card>
           <CardHeader>
             <CardTitle>Analyzing Photos for Burst Sequences...</CardTitle>
-            <CardDescription>Looking for similar photos taken within short time periods</CardDescription>
+            <CardDescription>Looking for frames shot in quick succession by the same camera</CardDescription>
           </CardHeader>
           <CardContent>
             <Progress value={scanProgress} className="w-full" />
             <p className="text-sm text-muted-foreground mt-2">
               {scanProgress < 30 ? 'Reading photo metadata...' : 
-               scanProgress < 60 ? 'Comparing timestamps and similarity...' : 
+               scanProgress < 60 ? 'Comparing capture times...' : 
                scanProgress < 90 ? 'Grouping burst sequences...' :
                'Finalizing analysis...'}
             </p>
@@ -284,12 +325,24 @@ export default function BurstSelectionPage() {
                           <Clock className="w-3 h-3 mr-1" />
                           {formatTimeSpan(group.timeSpan)} apart
                         </Badge>
-                        <Badge variant="outline">
-                          {Math.round(group.averageSimilarity * 100)}% similar
-                        </Badge>
+                        {group.camera && (
+                          <Badge variant="outline">
+                            <Camera className="w-3 h-3 mr-1" />
+                            {group.camera}
+                          </Badge>
+                        )}
                       </CardDescription>
                     </div>
                     <div className="flex items-center space-x-2">
+                      <Button
+                        size="sm"
+                        variant="outline"
+                        onClick={() => keepSharpest(group.id)}
+                        disabled={measuringGroup === group.id}
+                      >
+                        <Zap className="w-3 h-3 mr-1" />
+                        {measuringGroup === group.id ? 'Measuring...' : 'Keep Sharpest'}
+                      </Button>
                       <Button
                         size="sm"
                         variant="outline"
@@ -17,7 +17,10 @@ import {
   Trash2, 
   Eye,
   Share,
-  Download
+  Download,
+  Sparkles,
+  Check,
+  X
 } from "lucide-react";
 import { apiRequest } from "@/lib/queryClient";
 import { useToast } from "@/hooks/use-toast";
@@ -35,6 +38,17 @@ interface Collection {
   updatedAt: string;
 }
 
+interface AlbumSuggestion {
+  id: string;
+  kind: 'event' | 'place' | 'people';
+  name: string;
+  summary: string;
+  photoCount: number;
+  coverPhotoId: string | null;
+  startedAt: string;
+  endedAt: string;
+}
+
 export default function Collections() {
   const [selectedCollection, setSelectedCollection] = useState<Collection | null>(null);
   const [showCreateDialog, setShowCreateDialog] = useState(false);
@@ -51,6 +65,11 @@ export default function Collections() {
     queryKey: ["/api/collections"],
   });
 
+  // Albums suggested for photos that are in no collection yet
+  const { data: suggestions } = useQuery<AlbumSuggestion[]>({
+    queryKey: ["/api/collections/suggestions"],
+  });
+
   // Fetch photos in selected collection
   const { data: collectionPhotos, isLoading: photosLoading } = useQuery<Photo[]>({
     queryKey: ["/api/collections", selectedCollection?.id, "photos"],
@@ -112,6 +131,61 @@ export default function Collections() {
     },
   });
 
+  const suggestAlbumsMutation = useMutation({
+    mutationFn: async () => {
+      const response = await apiRequest('POST', '/api/collections/suggestions', {});
+      return response.json();
+    },
+    onSuccess: (result: { suggestions: AlbumSuggestion[] }) => {
+      queryClient.invalidateQueries({ queryKey: ["/api/collections/suggestions"] });
+      toast({
+        title: "Albums Suggested",
+        description: result.suggestions.length > 0
+          ? `${result.suggestions.length} album${result.suggestions.length !== 1 ? 's' : ''} suggested from unorganized photos.`
+          : "No new albums to suggest.",
+      });
+    },
+    onError: (error) => {
+      toast({
+        title: "Suggestion Failed",
+        description: error.message,
+        variant: "destructive"
+      });
+    },
+  });
+
+  const acceptSuggestionMutation = useMutation({
+    mutationFn: async (suggestionId: string) => {
+      const response = await apiRequest('POST', `/api/collections/suggestions/${suggestionId}/accept`, {});
+      return response.json();
+    },
+    onSuccess: (result: { collection: { name: string }; photoCount: number }) => {
+      queryClient.invalidateQueries({ queryKey: ["/api/collections/suggestions"] });
+      queryClient.invalidateQueries({ queryKey: ["/api/collections"] });
+      toast({
+        title: "Collection Created",
+        description: `"${result.collection.name}" was created with ${result.photoCount} photos.`,
+      });
+    },
+    onError: (error) => {
+      toast({
+        title: "Creation Failed",
+        description: error.message,
+        variant: "destructive"
+      });
+    },
+  });
+
+  const dismissSuggestionMutation = useMutation({
+    mutationFn: async (suggestionId: string) => {
+      const response = await apiRequest('POST', `/api/collections/suggestions/${suggestionId}/dismiss`, {});
+      return response.json();
+    },
+    onSuccess: () => {
+      queryClient.invalidateQueries({ queryKey: ["/api/collections/suggestions"] });
+    },
+  });
+
   const handleCreateCollection = () => {
     if (!newCollection.name.trim()) {
       toast({
@@ -125,7 +199,7 @@ export default function Collections() {
   };
 
   const handleDeleteCollection = (collection: Collection) => {
-    if (confirm(`Are you sure you want to delete "${collection.name}"? This action cannot be undone.`)) {
+    if (confirm(`Are you sure you want to delete "${collection.name}"? It can be restored until it is purged.`)) {
       deleteCollectionMutation.mutate(collection.id);
     }
   };
@@ -227,6 +301,71 @@ export default function Collections() {
         <p className="text-sm text-muted-foreground dark:text-gray-400">Organize your photos into custom collections</p>
       </div>
 
+      {/* Suggested Albums */}
+      <div className="px-6">
+        <div className="flex items-center justify-between mb-3">
+          <h2 className="text-lg font-semibold text-card-foreground dark:text-white">Suggested Albums</h2>
+          <Button
+            size="sm"
+            variant="outline"
+            onClick={() => suggestAlbumsMutation.mutate()}
+            disabled={suggestAlbumsMutation.isPending}
+          >
+            <Sparkles className="h-4 w-4 mr-1" />
+            {suggestAlbumsMutation.isPending ? "Looking..." : "Suggest Albums"}
+          </Button>
+        </div>
+        {suggestions && suggestions.length > 0 ? (
+          <div className="grid grid-cols-1 sm:grid-cols-2 md:grid-cols-3 lg:grid-cols-4 gap-4">
+            {suggestions.map((suggestion) => (
+              <Card key={suggestion.id} className="bg-card dark:bg-gray-800 overflow-hidden">
+                {suggestion.coverPhotoId && (
+                  <div className="aspect-video overflow-hidden">
+                    <img
+                      src={`/api/photos/${suggestion.coverPhotoId}/thumbnail`}
+                      alt={suggestion.name}
+                      className="w-full h-full object-cover"
+                    />
+                  </div>
+                )}
+                <CardContent className="pt-4">
+                  <p className="font-medium text-card-foreground dark:text-white">{suggestion.name}</p>
+                  <div className="flex items-center gap-2 mt-1 mb-3">
+                    <Badge variant="secondary" className="capitalize">{suggestion.kind}</Badge>
+                    <span className="text-xs text-muted-foreground">
+                      {suggestion.photoCount} Photo{suggestion.photoCount !== 1 ? 's' : ''} · {new Date(suggestion.startedAt).toLocaleDateString()}
+                    </span>
+                  </div>
+                  <div className="flex space-x-2">
+                    <Button
+                      size="sm"
+                      onClick={() => acceptSuggestionMutation.mutate(suggestion.id)}
+                      disabled={acceptSuggestionMutation.isPending}
+                    >
+                      <Check className="h-4 w-4 mr-1" />
+                      Create
+                    </Button>
+                    <Button
+                      size="sm"
+                      variant="ghost"
+                      onClick={() => dismissSuggestionMutation.mutate(suggestion.id)}
+                      disabled={dismissSuggestionMutation.isPending}
+                    >
+                      <X className="h-4 w-4 mr-1" />
+                      Dismiss
+                    </Button>
+                  </div>
+                </CardContent>
+              </Card>
+            ))}
+          </div>
+        ) : (
+          <p className="text-sm text-muted-foreground">
+            Group photos that are in no collection yet by trip, place and people.
+          </p>
+        )}
+      </div>
+
       {/* Collections Grid */}
       <div className="grid grid-cols-1 sm:grid-cols-2 md:grid-cols-3 lg:grid-cols-4 gap-6 p-6">
         {collections && collections.length > 0 ? (
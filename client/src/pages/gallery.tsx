@@ -17,6 +17,7 @@ import { useInfiniteScroll } from "@/hooks/use-infinite-scroll";
 import { useImagePreloader } from "@/hooks/use-image-preloader";
 import { ProcessingStateBadge, getProcessingState } from "@/components/ui/processing-state-badge";
 import { apiRequest } from "@/lib/queryClient";
+import { connectWindowEvents } from "@/lib/windows";
 import { useToast } from "@/hooks/use-toast";
 
 import type { Photo } from "@shared/types";
@@ -47,6 +48,15 @@ export default function Gallery() {
     }
   }, [location, peopleFilter]);
 
+  // Changes made in a viewer window show up here too
+  useEffect(() => {
+    const events = connectWindowEvents('gallery');
+    events.addEventListener('photo_updated', () => {
+      queryClient.invalidateQueries({ queryKey: ["/api/photos"] });
+    });
+    return () => events.close();
+  }, [queryClient]);
+
   const { data: photos, isLoading } = useQuery<Photo[]>({
     queryKey: ["/api/photos", tierFilter !== 'all' ? { tier: tierFilter } : {}],
     refetchInterval: isBatchProcessing ? 2000 : false, // Auto-refresh every 2 seconds during batch processing
@@ -49,8 +49,19 @@ interface Person {
   photoCount?: number;
   coverPhoto?: string;
   selectedThumbnailFaceId?: string;
+  excludeFromExports?: boolean;
+  excludeFromShares?: boolean;
+  excludeFromAi?: boolean;
 }
 
+type PrivacyFlags = Pick<Person, 'excludeFromExports' | 'excludeFromShares' | 'excludeFromAi'>;
+
+const PRIVACY_OPTIONS: Array<{ key: keyof PrivacyFlags; label: string }> = [
+  { key: 'excludeFromExports', label: 'Never include in exports' },
+  { key: 'excludeFromShares', label: 'Never include in shares or photo frames' },
+  { key: 'excludeFromAi', label: 'Never upload to AI providers' },
+];
+
 interface Face {
   id: string;
   photoId: string;
@@ -84,6 +95,7 @@ export default function PeoplePage() {
   const [newPersonName, setNewPersonName] = useState('');
   const [newPersonNotes, setNewPersonNotes] = useState('');
   const [newPersonBirthdate, setNewPersonBirthdate] = useState('');
+  const [personPrivacy, setPersonPrivacy] = useState<PrivacyFlags>({});
 
   const queryClient = useQueryClient();
   const { toast } = useToast();
@@ -181,7 +193,7 @@ export default function PeoplePage() {
 
   // Update person mutation
   const updatePersonMutation = useMutation({
-    mutationFn: async ({ id, data }: { id: string; data: { name: string; notes?: string; birthdate?: string } }) => {
+    mutationFn: async ({ id, data }: { id: string; data: { name: string; notes?: string; birthdate?: string } & PrivacyFlags }) => {
       return await apiRequest('PUT', `/api/people/${id}`, data);
     },
     onSuccess: () => {
@@ -333,7 +345,8 @@ export default function PeoplePage() {
         data: {
           name: newPersonName.trim(),
           notes: newPersonNotes.trim() || undefined,
-          birthdate: newPersonBirthdate || undefined
+          birthdate: newPersonBirthdate || undefined,
+          ...personPrivacy
         }
       });
     }
@@ -523,6 +536,11 @@ export default function PeoplePage() {
                       setNewPersonName(person.name);
                       setNewPersonNotes(person.notes || '');
                       setNewPersonBirthdate(person.birthdate ? person.birthdate.split('T')[0] : '');
+                      setPersonPrivacy({
+                        excludeFromExports: !!person.excludeFromExports,
+                        excludeFromShares: !!person.excludeFromShares,
+                        excludeFromAi: !!person.excludeFromAi,
+                      });
                       setIsEditPersonOpen(true);
                     }}
                   >
@@ -1086,6 +1104,22 @@ export default function PeoplePage() {
                   onChange={(e) => setNewPersonNotes(e.target.value)}
                 />
               </div>
+              <div className="space-y-2">
+                <Label>Privacy</Label>
+                <p className="text-sm text-muted-foreground">
+                  Photos showing this person are held back from the pathways you tick.
+                </p>
+                {PRIVACY_OPTIONS.map(option => (
+                  <div key={option.key} className="flex items-center space-x-2">
+                    <Checkbox
+                      id={`privacy-${option.key}`}
+                      checked={!!personPrivacy[option.key]}
+                      onCheckedChange={(checked) => setPersonPrivacy(prev => ({ ...prev, [option.key]: !!checked }))}
+                    />
+                    <Label htmlFor={`privacy-${option.key}`}>{option.label}</Label>
+                  </div>
+                ))}
+              </div>
               <div className="flex justify-end space-x-2">
                 <Button variant="outline" onClick={() => setIsEditPersonOpen(false)}>
                   Cancel
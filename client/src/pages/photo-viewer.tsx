@@ -0,0 +1,100 @@
+import { useEffect } from "react";
+import { useLocation, useRoute } from "wouter";
+import { useMutation, useQuery, useQueryClient } from "@tanstack/react-query";
+import { Star } from "lucide-react";
+import { apiRequest } from "@/lib/queryClient";
+import { connectWindowEvents, publishWindowEvent } from "@/lib/windows";
+import { useToast } from "@/hooks/use-toast";
+import { cn } from "@/lib/utils";
+import type { Photo } from "@shared/types";
+
+/**
+ * Dedicated photo viewer for a second window or monitor. The gallery sends
+ * it photos to show; rating here with the 0-5 keys is reported back so the
+ * gallery stays in sync.
+ */
+export default function PhotoViewer() {
+  const [, params] = useRoute("/viewer/:id");
+  const [, setLocation] = useLocation();
+  const queryClient = useQueryClient();
+  const { toast } = useToast();
+  const photoId = params?.id;
+
+  const { data: photo } = useQuery<Photo & { rating?: number }>({
+    queryKey: [`/api/photos/${photoId}`],
+    enabled: !!photoId,
+  });
+
+  const ratingMutation = useMutation({
+    mutationFn: async (rating: number) => {
+      await apiRequest('PATCH', `/api/photos/${photoId}/rating`, { rating });
+      return rating;
+    },
+    onSuccess: (rating) => {
+      queryClient.invalidateQueries({ queryKey: [`/api/photos/${photoId}`] });
+      publishWindowEvent('photo_updated', { photoId, rating }).catch(() => {});
+    },
+    onError: (error: Error) => {
+      toast({ title: "Rating not saved", description: error.message, variant: "destructive" });
+    },
+  });
+
+  // Follow the photos the gallery asks us to show
+  useEffect(() => {
+    const events = connectWindowEvents('viewer');
+    events.addEventListener('show_photo', (event) => {
+      const { photoId: nextId } = JSON.parse((event as MessageEvent).data);
+      setLocation(`/viewer/${nextId}`);
+      window.focus();
+    });
+    events.addEventListener('photo_updated', (event) => {
+      const { photoId: updatedId } = JSON.parse((event as MessageEvent).data);
+      queryClient.invalidateQueries({ queryKey: [`/api/photos/${updatedId}`] });
+    });
+    return () => events.close();
+  }, [setLocation, queryClient]);
+
+  useEffect(() => {
+    if (!photoId) return;
+    document.title = photo?.mediaAsset?.originalFilename ?? 'Pictallion Viewer';
+    publishWindowEvent('photo_shown', { photoId }).catch(() => {});
+  }, [photoId, photo?.mediaAsset?.originalFilename]);
+
+  useEffect(() => {
+    const onKeyDown = (event: KeyboardEvent) => {
+      if (/^[0-5]$/.test(event.key) && !ratingMutation.isPending) {
+        ratingMutation.mutate(Number(event.key));
+      }
+    };
+    window.addEventListener('keydown', onKeyDown);
+    return () => window.removeEventListener('keydown', onKeyDown);
+  }, [ratingMutation]);
+
+  if (!photo) {
+    return <div className="h-screen bg-black" />;
+  }
+
+  return (
+    <div className="h-screen bg-black flex flex-col">
+      <div className="flex-1 flex items-center justify-center overflow-hidden">
+        <img
+          src={`/api/files/${photo.filePath}`}
+          alt={photo.mediaAsset?.originalFilename}
+          className="max-w-full max-h-full object-contain"
+        />
+      </div>
+      <div className="flex items-center justify-between px-4 py-2 text-sm text-gray-300">
+        <span className="truncate">{photo.mediaAsset?.originalFilename}</span>
+        <div className="flex items-center gap-1">
+          {[1, 2, 3, 4, 5].map(star => (
+            <Star
+              key={star}
+              className={cn("w-4 h-4 cursor-pointer", star <= (photo.rating ?? 0) ? "fill-yellow-400 text-yellow-400" : "text-gray-500")}
+              onClick={() => ratingMutation.mutate(star === photo.rating ? 0 : star)}
+            />
+          ))}
+        </div>
+      </div>
+    </div>
+  );
+}
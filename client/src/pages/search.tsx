@@ -24,6 +24,7 @@ interface Photo {
   location?: string;
   eventType?: string;
   eventName?: string;
+  takenAt?: string | null;
   createdAt: string;
   mediaAsset: {
     id: string;
@@ -35,7 +36,7 @@ export default function Search() {
   const [, setLocation] = useLocation();
   const [searchFilters, setSearchFilters] = useState<SearchFilters>({});
   const [viewMode, setViewMode] = useState<'grid' | 'list'>('grid');
-  const [sortBy, setSortBy] = useState('createdAt');
+  const [sortBy, setSortBy] = useState('takenAt');
   const [sortDirection, setSortDirection] = useState<'asc' | 'desc'>('desc');
   const [selectedPhoto, setSelectedPhoto] = useState<Photo | null>(null);
 
@@ -143,7 +144,7 @@ export default function Search() {
             <div className="space-y-2 text-xs text-muted-foreground">
               <div className="flex items-center gap-4">
                 <span>{formatFileSize(photo.fileSize)}</span>
-                <span>{new Date(photo.createdAt).toLocaleDateString()}</span>
+                <span>{new Date(photo.takenAt || photo.createdAt).toLocaleDateString()}</span>
               </div>
 
               {/* AI description */}
@@ -237,7 +238,7 @@ export default function Search() {
 
               <div className="grid grid-cols-2 md:grid-cols-4 gap-2 text-sm text-muted-foreground mb-2">
                 <div>{formatFileSize(photo.fileSize)}</div>
-                <div>{new Date(photo.createdAt).toLocaleDateString()}</div>
+                <div>{new Date(photo.takenAt || photo.createdAt).toLocaleDateString()}</div>
                 {photo.location && (
                   <div className="flex items-center gap-1">
                     <MapPin className="h-3 w-3" />
@@ -298,7 +299,8 @@ export default function Search() {
               <SelectValue />
             </SelectTrigger>
             <SelectContent>
-              <SelectItem value="createdAt">Date</SelectItem>
+              <SelectItem value="takenAt">Date Taken</SelectItem>
+              <SelectItem value="createdAt">Date Imported</SelectItem>
               <SelectItem value="rating">Rating</SelectItem>
               <SelectItem value="fileSize">File Size</SelectItem>
               <SelectItem value="eventName">Event</SelectItem>
@@ -435,6 +437,18 @@ export default function Search() {
                   ))}
                 </div>
               )}
+
+              {facets.daylight && Object.keys(facets.daylight).length > 0 && (
+                <div>
+                  <span className="text-muted-foreground">By Light:</span>
+                  {Object.entries(facets.daylight as Record<string, number>).map(([phase, count]) => (
+                    <div key={phase} className="flex justify-between">
+                      <span className="capitalize">{phase.replace('_', ' ')}:</span>
+                      <span>{String(count)}</span>
+                    </div>
+                  ))}
+                </div>
+              )}
             </div>
           </CardContent>
         </Card>
@@ -59,9 +59,12 @@ interface AIConfig {
       textModel: string;
     };
     openai: {
+      baseUrl: string;
       model: string;
       hasApiKey: boolean;
     };
+    requestsPerMinute: number;
+    maxRetries: number;
   };
 }
 
@@ -103,8 +106,10 @@ export default function SettingsPage() {
     ollamaBaseUrl: "http://localhost:11434",
     ollamaVisionModel: "llava:latest",
     ollamaTextModel: "llama3.2:latest",
+    openaiBaseUrl: "https://api.openai.com/v1",
     openaiApiKey: "",
-    openaiModel: "gpt-4o"
+    openaiModel: "gpt-4o",
+    requestsPerMinute: 60
   });
 
   // Update local state when settings are loaded from server
@@ -137,8 +142,10 @@ export default function SettingsPage() {
         ollamaBaseUrl: data.config.ollama.baseUrl,
         ollamaVisionModel: data.config.ollama.visionModel,
         ollamaTextModel: data.config.ollama.textModel,
+        openaiBaseUrl: data.config.openai.baseUrl,
         openaiApiKey: "",
-        openaiModel: data.config.openai.model
+        openaiModel: data.config.openai.model,
+        requestsPerMinute: data.config.requestsPerMinute
       });
     } catch (error) {
       toast({
@@ -311,12 +318,13 @@ export default function SettingsPage() {
           visionModel: aiFormData.ollamaVisionModel,
           textModel: aiFormData.ollamaTextModel
         },
-        ...(aiFormData.openaiApiKey && {
-          openai: {
-            apiKey: aiFormData.openaiApiKey,
-            model: aiFormData.openaiModel
-          }
-        })
+        openai: {
+          baseUrl: aiFormData.openaiBaseUrl,
+          model: aiFormData.openaiModel,
+          // Left out when empty, keeping the saved key
+          ...(aiFormData.openaiApiKey && { apiKey: aiFormData.openaiApiKey })
+        },
+        requestsPerMinute: aiFormData.requestsPerMinute
       };
 
       const response = await fetch("/api/ai/config", {
@@ -609,7 +617,7 @@ export default function SettingsPage() {
                 <TabsList className="grid w-full grid-cols-3">
                   <TabsTrigger value="general">General</TabsTrigger>
                   <TabsTrigger value="ollama">Ollama</TabsTrigger>
-                  <TabsTrigger value="openai">OpenAI</TabsTrigger>
+                  <TabsTrigger value="openai">OpenAI-compatible</TabsTrigger>
                 </TabsList>
 
                 <TabsContent value="general" className="space-y-4">
@@ -621,12 +629,26 @@ export default function SettingsPage() {
                       </SelectTrigger>
                       <SelectContent>
                         <SelectItem value="ollama">Ollama Only (Local)</SelectItem>
-                        <SelectItem value="openai">OpenAI Only (Cloud)</SelectItem>
-                        <SelectItem value="both">Both (Ollama First, OpenAI Fallback)</SelectItem>
+                        <SelectItem value="openai">OpenAI-compatible Only</SelectItem>
+                        <SelectItem value="both">Both (OpenAI-compatible First, Ollama Fallback)</SelectItem>
                       </SelectContent>
                     </Select>
                     <p className="text-sm text-muted-foreground">
-                      Choose which AI provider to use for photo analysis. "Both" tries Ollama first, then falls back to OpenAI.
+                      Choose which AI provider to use for photo analysis. "Both" tries the OpenAI-compatible endpoint first, then falls back to Ollama.
+                    </p>
+                  </div>
+                  <div className="space-y-2">
+                    <Label htmlFor="requestsPerMinute">Requests per Minute</Label>
+                    <Input
+                      id="requestsPerMinute"
+                      type="number"
+                      min={1}
+                      max={1000}
+                      value={aiFormData.requestsPerMinute}
+                      onChange={(e) => setAiFormData(prev => ({ ...prev, requestsPerMinute: Math.max(1, Number(e.target.value) || 1) }))}
+                    />
+                    <p className="text-sm text-muted-foreground">
+                      Requests are spaced to stay under this limit; rate-limited requests are retried after the delay the server asks for.
                     </p>
                   </div>
                 </TabsContent>
@@ -662,6 +684,18 @@ export default function SettingsPage() {
                 </TabsContent>
 
                 <TabsContent value="openai" className="space-y-4">
+                  <div className="space-y-2">
+                    <Label htmlFor="openaiBaseUrl">Base URL</Label>
+                    <Input
+                      id="openaiBaseUrl"
+                      value={aiFormData.openaiBaseUrl}
+                      onChange={(e) => setAiFormData(prev => ({ ...prev, openaiBaseUrl: e.target.value }))}
+                      placeholder="https://api.openai.com/v1"
+                    />
+                    <p className="text-sm text-muted-foreground">
+                      OpenAI, or any server with an OpenAI-compatible API such as LM Studio, vLLM or LocalAI
+                    </p>
+                  </div>
                   <div className="space-y-2">
                     <Label htmlFor="openaiApiKey">API Key</Label>
                     <Input
@@ -669,7 +703,7 @@ export default function SettingsPage() {
                       type="password"
                       value={aiFormData.openaiApiKey}
                       onChange={(e) => setAiFormData(prev => ({ ...prev, openaiApiKey: e.target.value }))}
-                      placeholder="Enter your OpenAI API key"
+                      placeholder="Enter your API key (not needed by most local servers)"
                     />
                     <p className="text-sm text-muted-foreground">
                       {aiConfig?.config.openai.hasApiKey ? "API key is configured" : "No API key set"}
@@ -677,16 +711,15 @@ export default function SettingsPage() {
                   </div>
                   <div className="space-y-2">
                     <Label htmlFor="openaiModel">Model</Label>
-                    <Select value={aiFormData.openaiModel} onValueChange={(value) => setAiFormData(prev => ({ ...prev, openaiModel: value }))}>
-                      <SelectTrigger>
-                        <SelectValue />
-                      </SelectTrigger>
-                      <SelectContent>
-                        <SelectItem value="gpt-4o">GPT-4o (Recommended)</SelectItem>
-                        <SelectItem value="gpt-4o-mini">GPT-4o Mini (Faster)</SelectItem>
-                        <SelectItem value="gpt-4-turbo">GPT-4 Turbo</SelectItem>
-                      </SelectContent>
-                    </Select>
+                    <Input
+                      id="openaiModel"
+                      value={aiFormData.openaiModel}
+                      onChange={(e) => setAiFormData(prev => ({ ...prev, openaiModel: e.target.value }))}
+                      placeholder="gpt-4o"
+                    />
+                    <p className="text-sm text-muted-foreground">
+                      A model that accepts images, e.g. gpt-4o, gpt-4o-mini, or the name a local server lists
+                    </p>
                   </div>
                 </TabsContent>
               </Tabs>
@@ -246,17 +246,14 @@ export default function SilverReview() {
   // Batch AI processing mutation
   const batchAiProcessMutation = useMutation({
     mutationFn: async (photoIds: string[]) => {
-      return await apiRequest('POST', '/api/photos/batch-ai-process', { photoIds });
+      const response = await apiRequest('POST', '/api/photos/batch-ai-process', { photoIds });
+      return response.json();
     },
     onSuccess: (data: any) => {
-      queryClient.invalidateQueries({ queryKey: ['/api/photos/search'] });
-      toast({ title: `AI processing completed for ${data.processed} photos!` });
-      if (data.errors.length > 0) {
-        toast({ 
-          title: `${data.errors.length} photos had errors`, 
-          variant: "destructive" 
-        });
-      }
+      toast({
+        title: `Queued ${data.queued} photos for AI processing`,
+        description: data.status?.pausedReason ? "Processing will start once the schedule allows it" : undefined,
+      });
     }
   });
 
@@ -1,12 +1,30 @@
 // Electron main process entry point
 
-const { app, BrowserWindow, Menu, shell } = require('electron');
+const { app, BrowserWindow, Menu, Tray, nativeImage, shell } = require('electron');
 const path = require('path');
 const { spawn } = require('child_process');
 const fs = require('fs');
+const http = require('http');
 
 let serverProcess = null;
 let mainWindow = null;
+let tray = null;
+let isQuitting = false;
+let serverReady = false;
+
+// Files the OS handed over before the server was up
+let pendingSharedPaths = [];
+
+// Last status from the server; decides whether closing the window keeps the app in the tray
+let backgroundStatus = { paused: false, runInBackground: true, operations: [], recentImports: [] };
+
+const SERVER_URL = 'http://localhost:5000';
+
+// How long to wait for the server to checkpoint operations before killing it
+const SERVER_SHUTDOWN_TIMEOUT = 15000;
+
+// How often the tray menu refreshes running operations and recent imports
+const TRAY_REFRESH_INTERVAL = 30000;
 
 // Enable live reload for development
 if (process.env.NODE_ENV === 'development') {
@@ -33,7 +51,9 @@ function startServer() {
         ...process.env, 
         NODE_ENV: 'production',
         PORT: '5000',
-        ELECTRON_MODE: 'true'
+        ELECTRON_MODE: 'true',
+        // The install folder is read-only once packaged; keep the library and scratch files per user
+        ...(app.isPackaged && !process.env.PICTALLION_APP_DATA_DIR && { PICTALLION_APP_DATA_DIR: app.getPath('userData') })
       },
       stdio: 'pipe'
     });
@@ -63,6 +83,84 @@ function startServer() {
   });
 }
 
+// Ask the server to pause work and checkpoint, then wait for the process to exit
+function stopServer() {
+  const child = serverProcess;
+  if (!child) {
+    return Promise.resolve();
+  }
+
+  return new Promise((resolve) => {
+    const forceKill = setTimeout(() => {
+      console.error('Server did not shut down in time, killing it');
+      child.kill('SIGKILL');
+    }, SERVER_SHUTDOWN_TIMEOUT);
+
+    child.once('exit', () => {
+      clearTimeout(forceKill);
+      resolve();
+    });
+
+    // Signals are not delivered on Windows, so run the sequence over HTTP first
+    const request = http.request({
+      host: '127.0.0.1',
+      port: 5000,
+      path: '/api/system/shutdown',
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' }
+    }, (response) => {
+      response.resume();
+      response.on('end', () => child.kill('SIGTERM'));
+    });
+    request.on('error', () => child.kill('SIGTERM'));
+    request.end(JSON.stringify({ reason: 'app-quit' }));
+  });
+}
+
+// Files passed on the command line by Send To or a file association, minus the app and its flags
+function sharedPathsFromArgv(argv) {
+  return argv
+    .slice(app.isPackaged ? 1 : 2)
+    .filter((arg) => !arg.startsWith('-') && fs.existsSync(arg));
+}
+
+// Queue shared files through the import pipeline; the window shows a toast for them
+function importSharedPaths(paths) {
+  if (paths.length === 0) {
+    return;
+  }
+  if (!serverReady) {
+    pendingSharedPaths.push(...paths);
+    return;
+  }
+  fetch(`${SERVER_URL}/api/imports/os-share`, {
+    method: 'POST',
+    headers: { 'Content-Type': 'application/json' },
+    body: JSON.stringify({ paths })
+  })
+    .then((response) => {
+      if (!response.ok) {
+        throw new Error(`status ${response.status}`);
+      }
+    })
+    .catch((error) => console.error('Failed to import shared files:', error.message));
+  showMainWindow();
+}
+
+// Windows: add Pictallion to the Explorer "Send to" menu
+function installSendToShortcut() {
+  if (process.platform !== 'win32' || !app.isPackaged) {
+    return;
+  }
+  const shortcut = path.join(app.getPath('appData'), 'Microsoft', 'Windows', 'SendTo', 'Pictallion.lnk');
+  if (!fs.existsSync(shortcut)) {
+    shell.writeShortcutLink(shortcut, 'create', {
+      target: process.execPath,
+      description: 'Import into Pictallion'
+    });
+  }
+}
+
 function createWindow() {
   // Create the browser window
   mainWindow = new BrowserWindow({
@@ -82,7 +180,7 @@ function createWindow() {
   });
 
   // Load the app
-  mainWindow.loadURL('http://localhost:5000');
+  mainWindow.loadURL(SERVER_URL);
 
   // Show window when ready to prevent visual flash
   mainWindow.once('ready-to-show', () => {
@@ -95,6 +193,21 @@ function createWindow() {
     mainWindow.focus();
   });
 
+  // Keep running in the tray when background mode is on; otherwise quit through
+  // before-quit while the window is still open, so it can show the shutdown notice
+  mainWindow.on('close', (event) => {
+    if (isQuitting) {
+      return;
+    }
+    if (tray && backgroundStatus.runInBackground) {
+      event.preventDefault();
+      mainWindow.hide();
+    } else if (process.platform !== 'darwin') {
+      event.preventDefault();
+      app.quit();
+    }
+  });
+
   // Handle window closed
   mainWindow.on('closed', () => {
     mainWindow = null;
@@ -112,6 +225,88 @@ function createWindow() {
   }
 }
 
+function showMainWindow() {
+  if (!mainWindow) {
+    createWindow();
+    return;
+  }
+  mainWindow.show();
+  mainWindow.focus();
+}
+
+// Open a photo in its own viewer window
+function openPhotoViewer(photoId) {
+  const viewer = new BrowserWindow({
+    width: 1200,
+    height: 800,
+    icon: path.join(__dirname, '../build/icon.png'),
+    webPreferences: {
+      nodeIntegration: false,
+      contextIsolation: true,
+      webSecurity: true
+    }
+  });
+  viewer.loadURL(`${SERVER_URL}/viewer/${encodeURIComponent(photoId)}`);
+}
+
+async function requestBackground(method, endpoint) {
+  const response = await fetch(`${SERVER_URL}/api/background/${endpoint}`, { method });
+  if (!response.ok) {
+    throw new Error(`Background ${endpoint} failed with status ${response.status}`);
+  }
+  backgroundStatus = await response.json();
+  updateTrayMenu();
+}
+
+function refreshTray() {
+  requestBackground('GET', 'status').catch((error) => console.error('Failed to refresh tray:', error.message));
+}
+
+function updateTrayMenu() {
+  if (!tray) {
+    return;
+  }
+  const { paused, operations, recentImports } = backgroundStatus;
+  const activity = paused
+    ? 'Background work paused'
+    : operations.length > 0
+      ? `${operations.length} operation(s) running`
+      : 'Idle';
+
+  const recentItems = recentImports.length > 0
+    ? recentImports.map((entry) => ({
+        label: entry.filename,
+        click: () => openPhotoViewer(entry.photoId)
+      }))
+    : [{ label: 'No imports yet', enabled: false }];
+
+  tray.setToolTip(`Pictallion - ${activity}`);
+  tray.setContextMenu(Menu.buildFromTemplate([
+    { label: 'Open Library', click: showMainWindow },
+    { label: 'Recent Imports', submenu: recentItems },
+    { type: 'separator' },
+    { label: activity, enabled: false },
+    {
+      label: paused ? 'Resume Background Work' : 'Pause Background Work',
+      click: () => {
+        requestBackground('POST', paused ? 'resume' : 'pause')
+          .catch((error) => console.error('Failed to change background state:', error.message));
+      }
+    },
+    { type: 'separator' },
+    { label: 'Quit Pictallion', click: () => app.quit() }
+  ]));
+}
+
+function createTray() {
+  const icon = nativeImage.createFromPath(path.join(__dirname, '../build/icon.png')).resize({ width: 16, height: 16 });
+  tray = new Tray(icon);
+  tray.on('click', showMainWindow);
+  updateTrayMenu();
+  refreshTray();
+  setInterval(refreshTray, TRAY_REFRESH_INTERVAL).unref();
+}
+
 function createMenu() {
   const template = [
     {
@@ -224,15 +419,40 @@ function createMenu() {
   Menu.setApplicationMenu(menu);
 }
 
+// A second launch (Send To, opening a file) hands its files to the running instance
+const hasInstanceLock = app.requestSingleInstanceLock();
+if (!hasInstanceLock) {
+  app.quit();
+}
+
+app.on('second-instance', (event, argv) => {
+  importSharedPaths(sharedPathsFromArgv(argv));
+  showMainWindow();
+});
+
+// macOS: files opened with the app or dropped on its Dock icon
+app.on('open-file', (event, filePath) => {
+  event.preventDefault();
+  importSharedPaths([filePath]);
+});
+
 // App event handlers
 app.whenReady().then(async () => {
+  if (!hasInstanceLock) {
+    return;
+  }
   try {
     console.log('Starting Pictallion server...');
     await startServer();
     console.log('Server started, creating window...');
-    
+    serverReady = true;
+
     createMenu();
     createWindow();
+    createTray();
+    installSendToShortcut();
+    importSharedPaths([...pendingSharedPaths, ...sharedPathsFromArgv(process.argv)]);
+    pendingSharedPaths = [];
   } catch (error) {
     console.error('Failed to start application:', error);
     app.quit();
@@ -240,30 +460,28 @@ app.whenReady().then(async () => {
 });
 
 app.on('window-all-closed', () => {
-  // Kill server process
-  if (serverProcess) {
-    serverProcess.kill();
-    serverProcess = null;
-  }
-  
-  // Quit app (except on macOS)
+  // Quit app (except on macOS); before-quit stops the server
   if (process.platform !== 'darwin') {
     app.quit();
   }
 });
 
 app.on('activate', () => {
-  if (BrowserWindow.getAllWindows().length === 0) {
-    createWindow();
-  }
+  showMainWindow();
 });
 
-app.on('before-quit', () => {
-  // Cleanup server process
-  if (serverProcess) {
-    serverProcess.kill();
-    serverProcess = null;
+app.on('before-quit', (event) => {
+  if (isQuitting || !serverProcess) {
+    return;
   }
+
+  // Hold the quit until the server has checkpointed running operations
+  event.preventDefault();
+  isQuitting = true;
+  stopServer().finally(() => {
+    serverProcess = null;
+    app.quit();
+  });
 });
 
 // Handle certificate errors (for development)
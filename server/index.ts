@@ -1,6 +1,8 @@
 import express, { type Request, Response, NextFunction } from "express";
 import { registerRoutes } from "./routes";
 import { applyViteFix } from "./vite-fix";
+import { shutdownService } from "./services/shutdown";
+import { startupMetrics } from "./services/startupMetrics";
 
 // Apply the fix for path-to-regexp issue with * wildcard
 applyViteFix();
@@ -110,5 +112,9 @@ app.use((req, res, next) => {
     reusePort: true,
   }, () => {
     log(`serving on port ${port}`);
+    startupMetrics.markListening();
   });
+
+  // Checkpoint running operations and drain the database before exiting
+  shutdownService.installSignalHandlers(server);
 })();
@@ -0,0 +1,3 @@
+-- Add stored pixel dimensions to file versions for print info and dimension-based search
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS width INTEGER;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS height INTEGER;
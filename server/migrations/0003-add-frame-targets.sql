@@ -0,0 +1,15 @@
+-- Add digital photo frame publish targets kept filled with a rolling photo selection
+CREATE TABLE IF NOT EXISTS frame_targets (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  name TEXT NOT NULL,
+  destination_path TEXT NOT NULL,
+  photo_count INTEGER DEFAULT 500 NOT NULL,
+  max_width INTEGER DEFAULT 1920 NOT NULL,
+  max_height INTEGER DEFAULT 1080 NOT NULL,
+  filters JSONB,
+  refresh_interval_days INTEGER DEFAULT 7 NOT NULL,
+  is_enabled BOOLEAN DEFAULT TRUE NOT NULL,
+  last_synced_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  updated_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Store reviewed duplicate groups so resolved pairs are not re-surfaced by later scans
+CREATE TABLE IF NOT EXISTS duplicate_decisions (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  photo_ids TEXT[] NOT NULL,
+  decision TEXT NOT NULL,
+  kept_photo_id VARCHAR,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,12 @@
+-- Record every import source for a photo so cross-device duplicates can be traced
+CREATE TABLE IF NOT EXISTS photo_sources (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  media_asset_id VARCHAR NOT NULL REFERENCES media_assets(id),
+  source_path TEXT NOT NULL,
+  source_device TEXT,
+  file_hash TEXT NOT NULL,
+  is_duplicate BOOLEAN DEFAULT FALSE NOT NULL,
+  imported_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_photo_sources_media_asset ON photo_sources(media_asset_id);
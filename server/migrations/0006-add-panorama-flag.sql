@@ -0,0 +1,2 @@
+-- Flag spherical panoramas detected from XMP GPano metadata
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS is_panorama BOOLEAN DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Built-in system smart albums (Screenshots, Documents, Receipts, Whiteboards)
+ALTER TABLE collections ADD COLUMN IF NOT EXISTS system_key TEXT UNIQUE;
+ALTER TABLE collections ADD COLUMN IF NOT EXISTS exclude_from_timeline BOOLEAN DEFAULT FALSE;
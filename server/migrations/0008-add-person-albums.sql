@@ -0,0 +1,2 @@
+-- Generated person albums store their person and selection options for refreshes
+ALTER TABLE collections ADD COLUMN IF NOT EXISTS person_album JSONB;
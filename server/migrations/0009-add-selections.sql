@@ -0,0 +1,15 @@
+-- Persistent selection sets that survive app restarts
+CREATE TABLE IF NOT EXISTS selections (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  name TEXT NOT NULL,
+  description TEXT,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  updated_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS selection_photos (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  selection_id VARCHAR NOT NULL REFERENCES selections(id) ON DELETE CASCADE,
+  photo_id VARCHAR NOT NULL REFERENCES file_versions(id),
+  added_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,5 @@
+-- Custom events: recurrence rules, multi-day spans and associated people/places
+ALTER TABLE events ADD COLUMN IF NOT EXISTS recurrence_rule JSONB;
+ALTER TABLE events ADD COLUMN IF NOT EXISTS duration_days INTEGER DEFAULT 1;
+ALTER TABLE events ADD COLUMN IF NOT EXISTS person_ids TEXT[];
+ALTER TABLE events ADD COLUMN IF NOT EXISTS location_ids TEXT[];
@@ -0,0 +1,17 @@
+-- Stacks group burst frames, RAW+JPEG pairs and edits so listings can collapse them to one cover
+CREATE TABLE IF NOT EXISTS photo_stacks (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  primary_asset_id VARCHAR NOT NULL REFERENCES media_assets(id),
+  kind TEXT NOT NULL DEFAULT 'manual',
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS photo_stack_members (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  stack_id VARCHAR NOT NULL REFERENCES photo_stacks(id) ON DELETE CASCADE,
+  media_asset_id VARCHAR NOT NULL UNIQUE REFERENCES media_assets(id),
+  position INTEGER NOT NULL DEFAULT 0,
+  added_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS photo_stack_members_stack_id_idx ON photo_stack_members (stack_id);
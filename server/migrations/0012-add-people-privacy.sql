@@ -0,0 +1,4 @@
+-- Per-person privacy flags enforced at the export, share and AI boundaries
+ALTER TABLE people ADD COLUMN IF NOT EXISTS exclude_from_exports BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE people ADD COLUMN IF NOT EXISTS exclude_from_shares BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE people ADD COLUMN IF NOT EXISTS exclude_from_ai BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Face embeddings record the model that produced them, so only comparable vectors are matched
+ALTER TABLE faces ADD COLUMN IF NOT EXISTS embedding_model TEXT;
@@ -0,0 +1,16 @@
+-- Persistent background job queue (face recognition on imported photos)
+CREATE TABLE IF NOT EXISTS jobs (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  kind TEXT NOT NULL,
+  status TEXT DEFAULT 'queued' NOT NULL,
+  photo_ids TEXT[] NOT NULL,
+  completed INTEGER DEFAULT 0 NOT NULL,
+  failed INTEGER DEFAULT 0 NOT NULL,
+  result JSONB,
+  error TEXT,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  started_at TIMESTAMP,
+  finished_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS jobs_status_idx ON jobs (status, created_at);
@@ -0,0 +1,11 @@
+-- Persistent clusters of unassigned faces for naming whole groups at once
+CREATE TABLE IF NOT EXISTS face_clusters (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  centroid JSONB NOT NULL,
+  face_count INTEGER DEFAULT 0 NOT NULL,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  updated_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+ALTER TABLE faces ADD COLUMN IF NOT EXISTS cluster_id VARCHAR REFERENCES face_clusters(id) ON DELETE SET NULL;
+CREATE INDEX IF NOT EXISTS faces_cluster_id_idx ON faces (cluster_id);
@@ -0,0 +1,5 @@
+-- Per-photo provenance: which version an asset history entry concerns and structured details
+ALTER TABLE asset_history ADD COLUMN IF NOT EXISTS file_version_id VARCHAR REFERENCES file_versions(id) ON DELETE SET NULL;
+ALTER TABLE asset_history ADD COLUMN IF NOT EXISTS data JSONB;
+
+CREATE INDEX IF NOT EXISTS idx_asset_history_media_asset ON asset_history(media_asset_id);
@@ -0,0 +1,4 @@
+-- Soft-delete for people, albums and library tags: hidden until restored or purged
+ALTER TABLE people ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;
+ALTER TABLE collections ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;
+ALTER TABLE global_tag_library ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;
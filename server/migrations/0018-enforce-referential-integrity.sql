@@ -0,0 +1,62 @@
+-- Referential integrity: remove dangling rows, then enforce foreign keys with explicit ON DELETE behaviour.
+-- Tags and face embeddings are stored on the photo and face rows themselves, so they need no keys of their own.
+
+-- Orphan cleanup
+DELETE FROM faces WHERE photo_id NOT IN (SELECT id FROM file_versions);
+UPDATE faces SET person_id = NULL WHERE person_id IS NOT NULL AND person_id NOT IN (SELECT id FROM people);
+UPDATE faces SET cluster_id = NULL WHERE cluster_id IS NOT NULL AND cluster_id NOT IN (SELECT id FROM face_clusters);
+DELETE FROM collection_photos
+  WHERE collection_id NOT IN (SELECT id FROM collections)
+     OR photo_id NOT IN (SELECT id FROM file_versions);
+DELETE FROM selection_photos
+  WHERE selection_id NOT IN (SELECT id FROM selections)
+     OR photo_id NOT IN (SELECT id FROM file_versions);
+DELETE FROM relationships
+  WHERE person1_id NOT IN (SELECT id FROM people)
+     OR person2_id NOT IN (SELECT id FROM people);
+UPDATE events SET person_id = NULL WHERE person_id IS NOT NULL AND person_id NOT IN (SELECT id FROM people);
+UPDATE asset_history SET file_version_id = NULL
+  WHERE file_version_id IS NOT NULL AND file_version_id NOT IN (SELECT id FROM file_versions);
+DELETE FROM photo_sources WHERE media_asset_id NOT IN (SELECT id FROM media_assets);
+DELETE FROM photo_stacks WHERE primary_asset_id NOT IN (SELECT id FROM media_assets);
+DELETE FROM photo_stack_members
+  WHERE stack_id NOT IN (SELECT id FROM photo_stacks)
+     OR media_asset_id NOT IN (SELECT id FROM media_assets);
+
+-- Replace whatever key a column has (named by drizzle or by an earlier migration) with one of known behaviour
+CREATE OR REPLACE FUNCTION pg_temp.enforce_foreign_key(tbl TEXT, col TEXT, ref_tbl TEXT, on_delete TEXT) RETURNS VOID AS $$
+DECLARE
+  existing RECORD;
+BEGIN
+  FOR existing IN
+    SELECT con.conname
+    FROM pg_constraint con
+    JOIN pg_attribute att ON att.attrelid = con.conrelid AND att.attnum = ANY (con.conkey)
+    WHERE con.contype = 'f' AND con.conrelid = tbl::regclass AND att.attname = col
+  LOOP
+    EXECUTE format('ALTER TABLE %I DROP CONSTRAINT %I', tbl, existing.conname);
+  END LOOP;
+  EXECUTE format('ALTER TABLE %I ADD CONSTRAINT %I FOREIGN KEY (%I) REFERENCES %I(id) ON DELETE %s',
+    tbl, tbl || '_' || col || '_fk', col, ref_tbl, on_delete);
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT pg_temp.enforce_foreign_key('faces', 'photo_id', 'file_versions', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('faces', 'person_id', 'people', 'SET NULL');
+SELECT pg_temp.enforce_foreign_key('faces', 'cluster_id', 'face_clusters', 'SET NULL');
+SELECT pg_temp.enforce_foreign_key('collection_photos', 'collection_id', 'collections', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('collection_photos', 'photo_id', 'file_versions', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('selection_photos', 'selection_id', 'selections', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('selection_photos', 'photo_id', 'file_versions', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('relationships', 'person1_id', 'people', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('relationships', 'person2_id', 'people', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('events', 'person_id', 'people', 'SET NULL');
+SELECT pg_temp.enforce_foreign_key('asset_history', 'file_version_id', 'file_versions', 'SET NULL');
+SELECT pg_temp.enforce_foreign_key('photo_sources', 'media_asset_id', 'media_assets', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('photo_stacks', 'primary_asset_id', 'media_assets', 'CASCADE');
+SELECT pg_temp.enforce_foreign_key('photo_stack_members', 'media_asset_id', 'media_assets', 'CASCADE');
+
+CREATE INDEX IF NOT EXISTS idx_faces_photo_id ON faces(photo_id);
+CREATE INDEX IF NOT EXISTS idx_faces_person_id ON faces(person_id);
+CREATE INDEX IF NOT EXISTS idx_collection_photos_collection_id ON collection_photos(collection_id);
+CREATE INDEX IF NOT EXISTS idx_collection_photos_photo_id ON collection_photos(photo_id);
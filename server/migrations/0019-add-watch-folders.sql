@@ -0,0 +1,10 @@
+-- Folders watched for new files to auto-import into the bronze tier
+CREATE TABLE IF NOT EXISTS watch_folders (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  path TEXT NOT NULL UNIQUE,
+  recursive BOOLEAN DEFAULT TRUE NOT NULL,
+  import_existing BOOLEAN DEFAULT TRUE NOT NULL,
+  is_enabled BOOLEAN DEFAULT TRUE NOT NULL,
+  last_import_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,2 @@
+-- Locked photos are protected from deletion, demotion, edits and metadata write-back
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS is_locked BOOLEAN DEFAULT FALSE;
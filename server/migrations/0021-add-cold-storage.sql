@@ -0,0 +1,14 @@
+-- Originals copied to external or cold storage volumes; an evicted photo's original lives only there
+CREATE TABLE IF NOT EXISTS cold_storage_copies (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  photo_id VARCHAR NOT NULL UNIQUE REFERENCES file_versions(id) ON DELETE CASCADE,
+  volume_label TEXT NOT NULL,
+  archive_root TEXT NOT NULL,
+  relative_path TEXT NOT NULL,
+  file_hash TEXT NOT NULL,
+  file_size INTEGER NOT NULL,
+  archived_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  evicted_at TIMESTAMP,
+  verified_at TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_cold_storage_copies_volume_label ON cold_storage_copies(volume_label);
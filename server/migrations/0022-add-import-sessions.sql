@@ -0,0 +1,18 @@
+-- Resumable import sessions for files already on disk
+CREATE TABLE IF NOT EXISTS import_sessions (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  status TEXT DEFAULT 'queued' NOT NULL,
+  paths TEXT[] NOT NULL,
+  files TEXT[] NOT NULL,
+  source_device TEXT,
+  completed INTEGER DEFAULT 0 NOT NULL,
+  imported INTEGER DEFAULT 0 NOT NULL,
+  duplicates INTEGER DEFAULT 0 NOT NULL,
+  failed INTEGER DEFAULT 0 NOT NULL,
+  error TEXT,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  started_at TIMESTAMP,
+  finished_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS import_sessions_status_idx ON import_sessions (status, created_at);
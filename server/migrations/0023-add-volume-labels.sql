@@ -0,0 +1,3 @@
+-- Volume each watch folder and frame destination lives on, so disconnected drives can be reported by name
+ALTER TABLE watch_folders ADD COLUMN IF NOT EXISTS volume_label TEXT;
+ALTER TABLE frame_targets ADD COLUMN IF NOT EXISTS volume_label TEXT;
@@ -0,0 +1,15 @@
+-- Key EXIF fields as columns, so search filters on them instead of digging through the metadata JSON.
+-- Existing photos are filled in from their metadata when the library starts.
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS capture_date TIMESTAMP;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS camera_make TEXT;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS camera_model TEXT;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS camera TEXT;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS lens TEXT;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS iso INTEGER;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS aperture REAL;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS exposure_time REAL;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS focal_length REAL;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS gps_latitude DOUBLE PRECISION;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS gps_longitude DOUBLE PRECISION;
+CREATE INDEX IF NOT EXISTS idx_file_versions_capture_date ON file_versions(capture_date);
+CREATE INDEX IF NOT EXISTS idx_file_versions_camera ON file_versions(camera);
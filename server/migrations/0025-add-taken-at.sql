@@ -0,0 +1,4 @@
+-- When each photo was taken (capture date, else file modification time), for date filters, sorting and the timeline.
+-- Existing photos are filled in from their metadata when the library starts.
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS taken_at TIMESTAMP;
+CREATE INDEX IF NOT EXISTS idx_file_versions_taken_at ON file_versions(taken_at);
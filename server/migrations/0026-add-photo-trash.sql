@@ -0,0 +1,4 @@
+-- Photo trash: deleted photos keep their row and have their file moved under trash/ until restored or purged
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS trash_path TEXT;
+CREATE INDEX IF NOT EXISTS idx_file_versions_deleted_at ON file_versions(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,11 @@
+-- Journal of undoable user actions (tier promotions, deletions, tag, album and person changes)
+CREATE TABLE IF NOT EXISTS operation_journal (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  kind TEXT NOT NULL,
+  label TEXT NOT NULL,
+  changes JSONB NOT NULL,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL,
+  undone_at TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS operation_journal_created_idx ON operation_journal (created_at);
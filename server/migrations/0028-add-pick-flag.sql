@@ -0,0 +1,4 @@
+-- Pick/reject culling flag on photos, alongside the star rating
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS pick_flag TEXT;
+
+CREATE INDEX IF NOT EXISTS file_versions_pick_flag_idx ON file_versions (pick_flag);
@@ -0,0 +1,11 @@
+-- Which library photo each asset of an external service (such as an Immich server) became, so imports can resume
+CREATE TABLE IF NOT EXISTS external_assets (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  source TEXT NOT NULL,
+  source_key TEXT NOT NULL,
+  external_id TEXT NOT NULL,
+  photo_id VARCHAR NOT NULL REFERENCES file_versions(id) ON DELETE CASCADE,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS external_assets_source_idx ON external_assets (source, source_key, external_id);
@@ -0,0 +1,5 @@
+-- Album cover photo and manual photo order
+ALTER TABLE collections ADD COLUMN IF NOT EXISTS cover_photo_id VARCHAR REFERENCES file_versions(id) ON DELETE SET NULL;
+ALTER TABLE collection_photos ADD COLUMN IF NOT EXISTS sort_order INTEGER;
+
+CREATE INDEX IF NOT EXISTS collection_photos_order_idx ON collection_photos (collection_id, sort_order);
@@ -0,0 +1,16 @@
+-- Share links with per-link permissions: originals or resized copies, password, expiry and a view limit
+CREATE TABLE IF NOT EXISTS shares (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  token TEXT NOT NULL UNIQUE,
+  title TEXT,
+  collection_id VARCHAR REFERENCES collections(id) ON DELETE CASCADE,
+  photo_ids JSONB,
+  allow_originals BOOLEAN DEFAULT FALSE NOT NULL,
+  password_hash TEXT,
+  expires_at TIMESTAMP,
+  max_views INTEGER,
+  view_count INTEGER DEFAULT 0 NOT NULL,
+  last_viewed_at TIMESTAMP,
+  revoked_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Nested tags: each library tag points at the tag one level up its "/" path
+ALTER TABLE global_tag_library ADD COLUMN IF NOT EXISTS parent_tag_id VARCHAR REFERENCES global_tag_library(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS global_tag_library_parent_idx ON global_tag_library (parent_tag_id);
@@ -0,0 +1,4 @@
+-- Which detector found each face, and when a borderline detection was confirmed in the review queue
+ALTER TABLE faces ADD COLUMN IF NOT EXISTS detector TEXT;
+ALTER TABLE faces ADD COLUMN IF NOT EXISTS reviewed_at TIMESTAMP;
+CREATE INDEX IF NOT EXISTS faces_review_idx ON faces (confidence) WHERE person_id IS NULL AND ignored = false AND reviewed_at IS NULL;
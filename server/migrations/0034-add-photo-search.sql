@@ -0,0 +1,115 @@
+-- Full-text search over photos. Each photo's document is rebuilt by triggers
+-- whenever the photo, its people, its albums or their names change.
+CREATE TABLE IF NOT EXISTS photo_search_index (
+  photo_id VARCHAR PRIMARY KEY REFERENCES file_versions(id) ON DELETE CASCADE,
+  document TSVECTOR NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+CREATE INDEX IF NOT EXISTS photo_search_index_document_idx ON photo_search_index USING GIN (document);
+
+-- Words are indexed as written ('simple'), so prefixes and names match without stemming.
+-- Weights: A file name, tags, keywords and people; B albums, captions, place and event; C AI descriptions.
+CREATE OR REPLACE FUNCTION photo_search_document(pid VARCHAR) RETURNS TSVECTOR AS $$
+  SELECT
+    setweight(to_tsvector('simple', regexp_replace(COALESCE(ma.original_filename, fv.file_path), '[^[:alnum:]]+', ' ', 'g')), 'A') ||
+    setweight(to_tsvector('simple', concat_ws(' ',
+      (SELECT string_agg(replace(tag, '/', ' '), ' ')
+         FROM jsonb_array_elements_text(CASE WHEN jsonb_typeof(fv.metadata->'ai'->'aiTags') = 'array' THEN fv.metadata->'ai'->'aiTags' ELSE '[]'::jsonb END) AS tag),
+      array_to_string(fv.keywords, ' '),
+      (SELECT string_agg(DISTINCT p.name, ' ')
+         FROM faces f JOIN people p ON p.id = f.person_id
+        WHERE f.photo_id = fv.id AND p.deleted_at IS NULL)
+    )), 'A') ||
+    setweight(to_tsvector('simple', concat_ws(' ',
+      (SELECT string_agg(c.name, ' ')
+         FROM collection_photos cp JOIN collections c ON c.id = cp.collection_id
+        WHERE cp.photo_id = fv.id AND c.deleted_at IS NULL),
+      fv.metadata->'exif'->>'description',
+      fv.metadata->'exif'->>'caption',
+      fv.location,
+      fv.event_name
+    )), 'B') ||
+    setweight(to_tsvector('simple', concat_ws(' ',
+      fv.ai_short_description,
+      fv.metadata->'ai'->>'shortDescription',
+      fv.metadata->'ai'->>'longDescription'
+    )), 'C')
+  FROM file_versions fv
+  LEFT JOIN media_assets ma ON ma.id = fv.media_asset_id
+  WHERE fv.id = pid
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION refresh_photo_search(pid VARCHAR) RETURNS VOID AS $$
+BEGIN
+  -- The photo may be going away with the row that fired the trigger
+  IF pid IS NULL OR NOT EXISTS (SELECT 1 FROM file_versions WHERE id = pid) THEN
+    RETURN;
+  END IF;
+  INSERT INTO photo_search_index (photo_id, document, updated_at)
+  VALUES (pid, photo_search_document(pid), NOW())
+  ON CONFLICT (photo_id) DO UPDATE SET document = EXCLUDED.document, updated_at = EXCLUDED.updated_at;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION photo_search_photo_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  PERFORM refresh_photo_search(NEW.id);
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION photo_search_link_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  IF TG_OP IN ('UPDATE', 'DELETE') THEN
+    PERFORM refresh_photo_search(OLD.photo_id);
+  END IF;
+  IF TG_OP IN ('INSERT', 'UPDATE') AND (TG_OP = 'INSERT' OR NEW.photo_id IS DISTINCT FROM OLD.photo_id) THEN
+    PERFORM refresh_photo_search(NEW.photo_id);
+  END IF;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION photo_search_person_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  PERFORM refresh_photo_search(photo_id) FROM (SELECT DISTINCT photo_id FROM faces WHERE person_id = NEW.id) AS photos;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION photo_search_collection_trigger() RETURNS TRIGGER AS $$
+BEGIN
+  PERFORM refresh_photo_search(photo_id) FROM collection_photos WHERE collection_id = NEW.id;
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS photo_search_photo ON file_versions;
+CREATE TRIGGER photo_search_photo
+  AFTER INSERT OR UPDATE OF file_path, media_asset_id, metadata, keywords, location, event_name, ai_short_description ON file_versions
+  FOR EACH ROW EXECUTE FUNCTION photo_search_photo_trigger();
+
+DROP TRIGGER IF EXISTS photo_search_face ON faces;
+CREATE TRIGGER photo_search_face
+  AFTER INSERT OR DELETE OR UPDATE OF person_id, photo_id ON faces
+  FOR EACH ROW EXECUTE FUNCTION photo_search_link_trigger();
+
+DROP TRIGGER IF EXISTS photo_search_collection_photo ON collection_photos;
+CREATE TRIGGER photo_search_collection_photo
+  AFTER INSERT OR DELETE OR UPDATE OF photo_id ON collection_photos
+  FOR EACH ROW EXECUTE FUNCTION photo_search_link_trigger();
+
+DROP TRIGGER IF EXISTS photo_search_person ON people;
+CREATE TRIGGER photo_search_person
+  AFTER UPDATE OF name, deleted_at ON people
+  FOR EACH ROW EXECUTE FUNCTION photo_search_person_trigger();
+
+DROP TRIGGER IF EXISTS photo_search_collection ON collections;
+CREATE TRIGGER photo_search_collection
+  AFTER UPDATE OF name, deleted_at ON collections
+  FOR EACH ROW EXECUTE FUNCTION photo_search_collection_trigger();
+
+-- Index the photos already in the library
+INSERT INTO photo_search_index (photo_id, document)
+SELECT id, photo_search_document(id) FROM file_versions
+ON CONFLICT (photo_id) DO NOTHING;
@@ -0,0 +1,67 @@
+-- Daylight at capture as its own column, derived from GPS and capture time
+-- on every write like the EXIF columns, so every located photo is classified
+-- without an enrichment run. Existing photos are filled in when the library
+-- starts. Sorts after extend-photo-search-environment.sql, whose search
+-- terms it replaces.
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS daylight_phase TEXT;
+CREATE INDEX IF NOT EXISTS idx_file_versions_daylight_phase ON file_versions(daylight_phase) WHERE daylight_phase IS NOT NULL;
+
+CREATE OR REPLACE FUNCTION photo_environment_terms(daylight_phase TEXT, environment JSONB) RETURNS TEXT AS $$
+  SELECT concat_ws(' ',
+    CASE daylight_phase
+      WHEN 'golden_hour' THEN 'golden hour'
+      WHEN 'blue_hour' THEN 'blue hour twilight'
+      WHEN 'night' THEN 'night'
+      WHEN 'day' THEN 'day daylight'
+      WHEN 'midday' THEN 'midday noon day daylight'
+    END,
+    CASE environment->'weather'->>'condition'
+      WHEN 'clear' THEN 'clear sunny'
+      WHEN 'partly_cloudy' THEN 'partly cloudy'
+      WHEN 'cloudy' THEN 'cloudy overcast'
+      WHEN 'fog' THEN 'fog foggy'
+      WHEN 'drizzle' THEN 'drizzle rain rainy'
+      WHEN 'rain' THEN 'rain rainy'
+      WHEN 'snow' THEN 'snow snowy'
+      WHEN 'thunderstorm' THEN 'thunderstorm storm stormy'
+    END
+  )
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE OR REPLACE FUNCTION photo_search_document(pid VARCHAR) RETURNS TSVECTOR AS $$
+  SELECT
+    setweight(to_tsvector('simple', regexp_replace(COALESCE(ma.original_filename, fv.file_path), '[^[:alnum:]]+', ' ', 'g')), 'A') ||
+    setweight(to_tsvector('simple', concat_ws(' ',
+      (SELECT string_agg(replace(tag, '/', ' '), ' ')
+         FROM jsonb_array_elements_text(CASE WHEN jsonb_typeof(fv.metadata->'ai'->'aiTags') = 'array' THEN fv.metadata->'ai'->'aiTags' ELSE '[]'::jsonb END) AS tag),
+      array_to_string(fv.keywords, ' '),
+      (SELECT string_agg(DISTINCT p.name, ' ')
+         FROM faces f JOIN people p ON p.id = f.person_id
+        WHERE f.photo_id = fv.id AND p.deleted_at IS NULL)
+    )), 'A') ||
+    setweight(to_tsvector('simple', concat_ws(' ',
+      (SELECT string_agg(c.name, ' ')
+         FROM collection_photos cp JOIN collections c ON c.id = cp.collection_id
+        WHERE cp.photo_id = fv.id AND c.deleted_at IS NULL),
+      fv.metadata->'exif'->>'description',
+      fv.metadata->'exif'->>'caption',
+      fv.location,
+      fv.event_name,
+      photo_environment_terms(fv.daylight_phase, fv.environment)
+    )), 'B') ||
+    setweight(to_tsvector('simple', concat_ws(' ',
+      fv.ai_short_description,
+      fv.metadata->'ai'->>'shortDescription',
+      fv.metadata->'ai'->>'longDescription'
+    )), 'C')
+  FROM file_versions fv
+  LEFT JOIN media_assets ma ON ma.id = fv.media_asset_id
+  WHERE fv.id = pid
+$$ LANGUAGE sql STABLE;
+
+DROP FUNCTION IF EXISTS photo_environment_terms(JSONB);
+
+DROP TRIGGER IF EXISTS photo_search_photo ON file_versions;
+CREATE TRIGGER photo_search_photo
+  AFTER INSERT OR UPDATE OF file_path, media_asset_id, metadata, keywords, location, event_name, ai_short_description, environment, daylight_phase ON file_versions
+  FOR EACH ROW EXECUTE FUNCTION photo_search_photo_trigger();
@@ -0,0 +1,7 @@
+-- Promotion score: how good a candidate a photo is for the next tier, from
+-- local sharpness and exposure measurements, its faces and an optional AI
+-- aesthetic rating. Kept on the photo so smart collections and searches can
+-- reuse it; promotion_signals holds the parts it was made from.
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS promotion_score REAL;
+ALTER TABLE file_versions ADD COLUMN IF NOT EXISTS promotion_signals JSONB;
+CREATE INDEX IF NOT EXISTS idx_file_versions_promotion_score ON file_versions(promotion_score) WHERE promotion_score IS NOT NULL;
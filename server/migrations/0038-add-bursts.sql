@@ -0,0 +1,24 @@
+-- Burst sequences grouped by EXIF capture time (DateTimeOriginal plus
+-- SubSecTimeOriginal) per camera. A burst is resolved once its best frame
+-- has been chosen; the other frames can then be stacked under it.
+CREATE TABLE IF NOT EXISTS bursts (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  camera TEXT,
+  started_at TIMESTAMP(3) NOT NULL,
+  ended_at TIMESTAMP(3) NOT NULL,
+  best_photo_id VARCHAR REFERENCES file_versions(id) ON DELETE SET NULL,
+  stack_id VARCHAR REFERENCES photo_stacks(id) ON DELETE SET NULL,
+  resolved_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS burst_frames (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  burst_id VARCHAR NOT NULL REFERENCES bursts(id) ON DELETE CASCADE,
+  photo_id VARCHAR NOT NULL UNIQUE REFERENCES file_versions(id) ON DELETE CASCADE,
+  position INTEGER NOT NULL DEFAULT 0,
+  captured_at TIMESTAMP(3) NOT NULL,
+  sharpness REAL
+);
+
+CREATE INDEX IF NOT EXISTS burst_frames_burst_id_idx ON burst_frames (burst_id);
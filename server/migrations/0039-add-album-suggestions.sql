@@ -0,0 +1,21 @@
+-- Albums suggested from unorganized photos, grouped by gaps in capture time,
+-- by place, or by people photographed together. Pending suggestions are
+-- replaced on every run; accepted and dismissed ones are kept so the same
+-- photos are not suggested again.
+CREATE TABLE IF NOT EXISTS album_suggestions (
+  id VARCHAR PRIMARY KEY DEFAULT gen_random_uuid(),
+  kind TEXT NOT NULL,
+  name TEXT NOT NULL,
+  photo_ids TEXT[] NOT NULL,
+  cover_photo_id VARCHAR REFERENCES file_versions(id) ON DELETE SET NULL,
+  person_ids TEXT[] NOT NULL DEFAULT '{}',
+  place_name TEXT,
+  started_at TIMESTAMP NOT NULL,
+  ended_at TIMESTAMP NOT NULL,
+  status TEXT NOT NULL DEFAULT 'pending',
+  collection_id VARCHAR REFERENCES collections(id) ON DELETE SET NULL,
+  resolved_at TIMESTAMP,
+  created_at TIMESTAMP DEFAULT NOW() NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS album_suggestions_status_idx ON album_suggestions (status);
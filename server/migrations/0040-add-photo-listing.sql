@@ -0,0 +1,21 @@
+-- Photo listings and searches page in SQL, ordered by when each photo was
+-- taken with its id breaking ties; this index serves the default order.
+CREATE INDEX IF NOT EXISTS idx_file_versions_listing ON file_versions ((COALESCE(taken_at, created_at)) DESC, id) WHERE deleted_at IS NULL;
+CREATE INDEX IF NOT EXISTS idx_file_versions_media_asset ON file_versions(media_asset_id);
+CREATE INDEX IF NOT EXISTS idx_photo_stack_members_stack ON photo_stack_members(stack_id, position);
+
+-- A date from photo metadata, "YYYY:MM:DD HH:MM:SS" or ISO, as parseMetadataDate
+-- reads it; null when it is missing or unreadable, so searches can filter on it
+CREATE OR REPLACE FUNCTION photo_metadata_date(value TEXT) RETURNS TIMESTAMP AS $$
+DECLARE
+  parsed TIMESTAMP;
+BEGIN
+  IF value IS NULL OR value !~ '^\d{4}[:-]\d{2}[:-]\d{2}' THEN
+    RETURN NULL;
+  END IF;
+  parsed := regexp_replace(value, '^(\d{4}):(\d{2}):(\d{2})', '\1-\2-\3')::TIMESTAMP;
+  RETURN CASE WHEN EXTRACT(YEAR FROM parsed) > 1900 THEN parsed END;
+EXCEPTION WHEN OTHERS THEN
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
@@ -0,0 +1,13 @@
+-- Key duplicate decisions by media asset, so a decision still applies once a
+-- reviewed photo is promoted or replaced by another version of the same asset
+ALTER TABLE duplicate_decisions ADD COLUMN IF NOT EXISTS media_asset_ids TEXT[];
+
+UPDATE duplicate_decisions decision
+SET media_asset_ids = ARRAY(
+  SELECT DISTINCT photo.media_asset_id FROM file_versions photo
+  WHERE photo.id = ANY(decision.photo_ids)
+  ORDER BY photo.media_asset_id
+)
+WHERE media_asset_ids IS NULL;
+
+ALTER TABLE duplicate_decisions ALTER COLUMN media_asset_ids SET NOT NULL;
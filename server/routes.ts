@@ -7,20 +7,106 @@ import fs from "fs/promises";
 import crypto from "crypto";
 import { storage } from "./storage";
 import { aiService, AIProvider } from "./services/ai";
+import { aiProviderConfigSchema, aiProviderRegistry, AI_PROVIDER_SETTING } from "./services/aiProviders";
 import { fileManager } from "./services/fileManager.js";
 import { advancedSearch } from "./services/advancedSearch";
+import { photoTextSearchService, textSearchQuerySchema, TextSearchError } from "./services/photoTextSearch";
+import { searchPresetService } from "./services/searchPresets";
 import { metadataEmbedding } from "./services/metadataEmbedding";
-import { faceDetectionService } from "./services/faceDetection.js";
-import { burstPhotoService } from "./services/burstPhotoDetection";
+import { faceDetectionService, FACE_EMBEDDING_MODEL } from "./services/faceDetection.js";
+import { provenanceService } from "./services/provenance";
+import { burstPhotoService, selectBurstBestSchema, BurstError } from "./services/burstPhotoDetection";
 import { generateSilverFilename } from "./services/aiNaming";
 import { eventDetectionService } from "./services/eventDetection";
-import { insertMediaAssetSchema, insertFileVersionSchema, insertAssetHistorySchema, type Face, type Person } from "@shared/schema";
+import { insertMediaAssetSchema, insertFileVersionSchema, insertAssetHistorySchema, insertEventSchema, type Face, type Person, type CombinedMetadata } from "@shared/schema";
 import { sql } from "drizzle-orm";
-import { db } from "./db";
+import { db, pool } from "./db";
 import { promptManager } from "./services/promptManager";
 import locationRoutes from "./routes/locations";
+import frameTargetRoutes from "./routes/frameTargets";
+import frameRoutes from "./routes/frame";
+import shareRoutes from "./routes/shares";
+import publicShareRoutes from "./routes/publicShares";
+import emailRoutes from "./routes/email";
+import duplicateRoutes from "./routes/duplicates";
+import selectionRoutes from "./routes/selections";
+import workingSetRoutes from "./routes/workingSet";
+import windowRoutes from "./routes/windows";
+import operationRoutes from "./routes/operations";
+import systemRoutes from "./routes/system";
+import libraryRoutes from "./routes/library";
+import { libraryBackupService } from "./services/libraryBackup";
+import jobRoutes from "./routes/jobs";
+import deletedRoutes from "./routes/deleted";
+import watchFolderRoutes from "./routes/watchFolders";
+import coldStorageRoutes from "./routes/coldStorage";
+import importRoutes from "./routes/imports";
+import volumeRoutes from "./routes/volumes";
+import backgroundRoutes from "./routes/background";
+import journalRoutes from "./routes/journal";
 import { logger } from "./utils/logger";
-import { thumbnailService } from "./services/thumbnailService";
+import { thumbnailService, THUMBNAIL_ENCODER_SETTINGS } from "./services/thumbnailService";
+import { externalEditorService, OriginalOfflineError } from "./services/externalEditor";
+import { calculatePrintInfo } from "./utils/printInfo";
+import { slideshowService, slideshowNextSchema } from "./services/slideshow";
+import { frameSyncService } from "./services/frameSync";
+import { getCaptureDate, extractPhotoDate } from "./utils/photoDates";
+import { findCoverageGaps, calculateYearlyCoverage } from "./utils/coverage";
+import { formatRegistry, SUPPORTED_FORMATS_SETTING } from "./services/formatRegistry";
+import { formatMetadataExtractor } from "./services/formatMetadata";
+import { systemAlbumService } from "./services/systemAlbums";
+import { documentExportService } from "./services/documentExport";
+import { faceDatasetExportService, faceDatasetExportSchema } from "./services/faceDatasetExport";
+import { personFaceExportService, personFaceExportSchema, PersonFaceExportError } from "./services/personFaceExport";
+import { personTimelapseService, personTimelapseSchema, PersonTimelapseError } from "./services/personTimelapse";
+import { photoExportService, photoExportSchema } from "./services/photoExport";
+import { libraryArchiveService, archiveExportSchema, LibraryArchiveError } from "./services/libraryArchive";
+import { faceClusteringService, assignClusterSchema } from "./services/faceClustering";
+import { faceReviewService, faceReviewQuerySchema, faceReviewDecisionSchema } from "./services/faceReview";
+import { personPrivacySchema } from "./services/privacy";
+import { personAlbumService } from "./services/personAlbum";
+import { smartCollectionService, smartCollectionRulesSchema, SmartCollectionError } from "./services/smartCollectionService";
+import { tagHierarchyService, TagHierarchyError } from "./services/tagHierarchy";
+import { batchOperationService, applyOperationsSchema, pickFlagSchema, batchRatingSchema, batchPickFlagSchema } from "./services/batchOperations";
+import { operationRegistry, requestedOperationId, OperationCancelledError } from "./services/operations";
+import { shutdownService } from "./services/shutdown";
+import { libraryInitService } from "./services/libraryInit";
+import { appSettingsService, updateSettingsSchema, SettingsError } from "./services/appSettings";
+import { peopleMergeService, previewMergePeopleSchema, mergePeopleSchema, suggestMergesQuerySchema, PeopleMergeError } from "./services/peopleMerge";
+import { keywordPeopleService, applyKeywordPeopleSchema, KeywordPeopleError } from "./services/keywordPeople";
+import { getLibraryRoot, libraryPath, scratchPath } from "./utils/libraryPaths";
+import { getOccurrencesBetween } from "./utils/eventRecurrence";
+import { holidayCalendarService, HolidayCalendarError } from "./services/holidayCalendars";
+import { redactSetting, keepSavedSecrets } from "./utils/settingSecrets";
+import { albumSuggestionService, suggestAlbumsSchema, albumSuggestionsQuerySchema, acceptAlbumSuggestionSchema, AlbumSuggestionError } from "./services/albumSuggestions";
+import { photoStackService, createStackSchema, setStackPrimarySchema, PhotoStackError } from "./services/photoStacks";
+import { PhotoLockedError, assertUnlocked, setPhotoLockSchema } from "./services/photoLock";
+import { propagationService, propagationScopeSchema, propagationRulesSchema } from "./services/propagation";
+import { aiBatchScheduler, aiScheduleSettingsSchema } from "./services/aiBatchScheduler";
+import { jobQueue } from "./services/jobQueue";
+import { softDeleteService } from "./services/softDelete";
+import { folderWatcherService } from "./services/folderWatcher";
+import { importSessionService } from "./services/importSessions";
+import { backgroundService } from "./services/background";
+import { startupMetrics } from "./services/startupMetrics";
+import { operationJournal, type JournalKind } from "./services/operationJournal";
+import { counterConsistencyService } from "./services/counterConsistency";
+import { volumeStatusService } from "./services/volumeStatus";
+import { cameraCanonicalizationService, cameraAliasesSchema } from "./services/cameraCanonicalization";
+import { photoMetadataEditor, setCaptureDateSchema, setGpsSchema, setDescriptionSchema, setKeywordsSchema } from "./services/photoMetadataEditor";
+import { photoEnvironmentService, enrichEnvironmentSchema } from "./services/photoEnvironment";
+import { promotionScoringService, scorePhotosSchema, promotionCandidatesQuerySchema } from "./services/promotionScoring";
+import { pageQuerySchema, wantsPage } from "./utils/pagination";
+import {
+  photoListingService,
+  timelineListing,
+  tierListing,
+  personListing,
+  collectionListing,
+  byCreatedAt,
+  type PhotoListing,
+  type ListedPhoto,
+} from "./services/photoListing";
 
 // Helper function to calculate bounding box overlap (Intersection over Union)
 function calculateBoundingBoxOverlap(
@@ -51,78 +137,16 @@ interface EnhancedFace extends Face {
   ageInPhoto?: number | null;
 }
 
-// Helper function to extract photo date from metadata or filename
-function extractPhotoDate(photo: any): Date | undefined {
-  try {
-    // Extract date from photo metadata or filename
-
-    // First try EXIF datetime fields with various formats
-    if (photo.metadata?.exif) {
-      const exif = photo.metadata.exif;
-
-      // Try DateTimeOriginal first (most accurate)
-      if (exif.dateTimeOriginal) {
-        const date = new Date(exif.dateTimeOriginal);
-        if (!isNaN(date.getTime())) {
-          console.log('Using EXIF DateTimeOriginal:', date.toISOString());
-          return date;
-        }
-      }
-
-      // Try CreateDate
-      if (exif.createDate) {
-        const date = new Date(exif.createDate);
-        if (!isNaN(date.getTime())) {
-          console.log('Using EXIF CreateDate:', date.toISOString());
-          return date;
-        }
-      }
-
-      // Try DateTime
-      if (exif.dateTime) {
-        const date = new Date(exif.dateTime);
-        if (!isNaN(date.getTime())) {
-          console.log('Using EXIF DateTime:', date.toISOString());
-          return date;
-        }
-      }
-    }
-
-    // Try to extract from filename if it has timestamp format (YYYYMMDD_HHMMSS)
-    const filename = photo.mediaAsset?.originalFilename || '';
-    const timestampMatch = filename.match(/^(\d{8})_(\d{6})/);
-    if (timestampMatch) {
-      const dateStr = timestampMatch[1]; // YYYYMMDD
-      const timeStr = timestampMatch[2]; // HHMMSS
-      const year = parseInt(dateStr.substring(0, 4));
-      const month = parseInt(dateStr.substring(4, 6)) - 1; // Month is 0-indexed
-      const day = parseInt(dateStr.substring(6, 8));
-      const hour = parseInt(timeStr.substring(0, 2));
-      const minute = parseInt(timeStr.substring(2, 4));
-      const second = parseInt(timeStr.substring(4, 6));
-
-      const extractedDate = new Date(year, month, day, hour, minute, second);
-      if (!isNaN(extractedDate.getTime())) {
-        console.log('Using filename timestamp:', extractedDate.toISOString());
-        return extractedDate;
-      }
-    }
-
-    // Fall back to file creation time
-    if (photo.createdAt) {
-      const date = new Date(photo.createdAt);
-      if (!isNaN(date.getTime())) {
-        console.log('Using createdAt:', date.toISOString());
-        return date;
-      }
+// One capture date per media asset, skipping photos without EXIF dates
+async function getLibraryCaptureDates(): Promise<Date[]> {
+  const datesByAsset = new Map<string, Date>();
+  for (const photo of await storage.getAllFileVersions()) {
+    const date = getCaptureDate(photo);
+    if (date && !datesByAsset.has(photo.mediaAssetId)) {
+      datesByAsset.set(photo.mediaAssetId, date);
     }
-
-    console.log('No valid date found, returning undefined');
-    return undefined;
-  } catch (error) {
-    console.error('Error extracting photo date:', error);
-    return undefined;
   }
+  return Array.from(datesByAsset.values());
 }
 
 // Similarity detection function
@@ -213,13 +237,12 @@ async function findSimilarPhotos(photos: any[]): Promise<any[]> {
 
 // Configure multer for file uploads
 const upload = multer({
-  dest: 'uploads/temp/',
+  dest: scratchPath(),
   limits: {
     fileSize: 50 * 1024 * 1024, // 50MB limit
   },
   fileFilter: (req: any, file: Express.Multer.File, cb: multer.FileFilterCallback) => {
-    const allowedTypes = ['image/jpeg', 'image/png', 'image/tiff', 'video/mp4', 'video/mov', 'video/avi'];
-    if (allowedTypes.includes(file.mimetype)) {
+    if (formatRegistry.isImportable(file.originalname)) {
       cb(null, true);
     } else {
       cb(new Error('Unsupported file type'));
@@ -227,12 +250,54 @@ const upload = multer({
   },
 });
 
+// ICS holiday calendars are small text files, kept in memory until parsed
+const calendarUpload = multer({
+  storage: multer.memoryStorage(),
+  limits: {
+    fileSize: 2 * 1024 * 1024, // 2MB limit
+  },
+});
+
 export async function registerRoutes(app: Express): Promise<Server> {
+  // Time the first gallery page and thumbnails against the startup budget
+  app.use(startupMetrics.trackFirstRequests());
+
+  await startupMetrics.measure('pool', () => pool.query('SELECT 1'));
+
+  // EXIF column backfill can take minutes on a big library; it runs once the server is listening
+  const libraryReport = await libraryInitService.initializeLibrary(await libraryInitService.resolveConfiguredRoot(), { backfillExif: false });
+  let stepStart = new Date(libraryReport.startedAt).getTime();
+  for (const step of libraryReport.steps) {
+    console.log(`Library ${step.name}: ${step.status}${step.message ? ` - ${step.message}` : ''}`);
+    if (step.status !== 'skipped') {
+      startupMetrics.addPhase(`library_${step.name}`, stepStart, step.durationMs, step.status);
+    }
+    stepStart += step.durationMs;
+  }
+  const folderStep = libraryReport.steps.find(step => step.name === 'folders');
+  if (folderStep?.status !== 'completed') {
+    throw new Error(`Library at ${libraryReport.root} could not be initialized`);
+  }
+  startupMetrics.defer('exif_backfill', async () => {
+    const indexed = await storage.backfillExifColumns();
+    if (indexed > 0) console.log(`Indexed EXIF fields of ${indexed} photos`);
+    const classified = await storage.backfillDaylightPhases();
+    if (classified > 0) console.log(`Classified daylight of ${classified} photos`);
+  });
+
   // Initialize services
-  await Promise.all([
+  await startupMetrics.measure('services', () => Promise.all([
     promptManager.initialize(),
-    fileManager.initializeDirectories()
-  ]);
+    systemAlbumService.ensureSystemAlbums().catch(error => console.error("Failed to create system albums:", error)),
+  ]));
+
+  // Refuse new writes once shutdown has begun so nothing starts that cannot be checkpointed
+  app.use("/api", (req, res, next) => {
+    if (shutdownService.isShuttingDown && req.method !== 'GET') {
+      return res.status(503).json({ message: "Server is shutting down" });
+    }
+    next();
+  });
 
   // Serve uploaded files with thumbnail support
   app.get("/api/files/media/:tier/:date/:filename", async (req, res) => {
@@ -241,22 +306,32 @@ export async function registerRoutes(app: Express): Promise<Server> {
       const { quality, w, h } = req.query as { quality?: string; w?: string; h?: string };
       
       const filePath = `${tier}/${date}/${filename}`;
-      const fullPath = path.join(process.cwd(), 'data', 'media', filePath);
+      const fullPath = libraryPath('media', filePath);
 
       // Security check to prevent directory traversal
-      if (!fullPath.startsWith(path.join(process.cwd(), 'data'))) {
+      if (!fullPath.startsWith(getLibraryRoot())) {
         return res.status(403).json({ message: "Access denied" });
       }
 
-      // Check if file exists
+      // Check if file exists; an evicted original is read from its cold storage copy
+      let sourcePath = fullPath;
+      let photoId: string | undefined;
+      let offlineVolume: string | null = null;
       try {
         await fs.access(fullPath);
       } catch {
-        return res.status(404).json({ message: "File not found" });
+        const photo = await storage.getFileVersionByPath(`media/${filePath}`);
+        if (!photo) {
+          return res.status(404).json({ message: "File not found" });
+        }
+        const original = await volumeStatusService.locateOriginal(photo);
+        sourcePath = original.path;
+        photoId = photo.id;
+        if (!original.available) offlineVolume = original.volumeLabel;
       }
 
       // Handle thumbnail requests with width/height parameters
-      if (w || h || quality !== undefined) {
+      if ((w || h || quality !== undefined) && formatRegistry.supports(fullPath, 'thumbnail')) {
         try {
           const thumbnailSize = parseInt(w || h || '300');
           const thumbnailQuality = quality === 'low' ? 60 : quality === 'high' ? 90 : 80;
@@ -267,7 +342,7 @@ export async function registerRoutes(app: Express): Promise<Server> {
             format: 'jpeg' as const
           };
 
-          const thumbnailPath = await thumbnailService.generateThumbnail(fullPath, thumbnailOptions);
+          const thumbnailPath = await thumbnailService.generateThumbnail(sourcePath, thumbnailOptions, photoId);
           
           if (thumbnailPath) {
             // Set appropriate headers for caching
@@ -281,9 +356,17 @@ export async function registerRoutes(app: Express): Promise<Server> {
         }
       }
 
+      if (offlineVolume) {
+        return res.status(503).json({
+          message: `Photo is on volume ${offlineVolume}, which is not connected`,
+          offline: true,
+          volumeLabel: offlineVolume,
+        });
+      }
+
       // Serve original file
       res.setHeader('Cache-Control', 'public, max-age=86400'); // 1 day for originals
-      res.sendFile(fullPath);
+      res.sendFile(sourcePath);
     } catch (error) {
       console.error("Error serving file:", error);
       res.status(500).json({ message: "Failed to serve file" });
@@ -291,9 +374,9 @@ export async function registerRoutes(app: Express): Promise<Server> {
   });
 
   // Serve static files fallback
-  app.use("/api/files", express.static(path.join(process.cwd(), "data")));
+  app.use("/api/files", (req, res, next) => express.static(getLibraryRoot())(req, res, next));
   // Serve temporary files (face crops)  
-  app.use("/api/files/temp", express.static(path.join(process.cwd(), "uploads", "temp")));
+  app.use("/api/files/temp", express.static(scratchPath()));
 
   // Get collection statistics
   app.get("/api/stats", async (req, res) => {
@@ -322,8 +405,10 @@ export async function registerRoutes(app: Express): Promise<Server> {
   app.get("/api/photos/recent", async (req, res) => {
     try {
       const limit = parseInt(req.query.limit as string) || 6;
-      const photos = await storage.getRecentPhotos(limit);
-      res.json(photos);
+      const expandStacks = photoStackService.shouldExpand(req.query.expandStacks);
+      // Read ahead so a burst collapsing to one cover still leaves `limit` photos
+      const photos = await storage.getRecentPhotos(expandStacks ? limit : limit * 5);
+      res.json((await photoStackService.applyStackMode(photos, expandStacks)).slice(0, limit));
     } catch (error) {
       console.error("Error fetching recent photos:", error);
       res.status(500).json({ message: "Failed to fetch recent photos" });
@@ -333,12 +418,17 @@ export async function registerRoutes(app: Express): Promise<Server> {
   // Global tag library endpoints
   app.get("/api/tags/library", async (req, res) => {
     try {
-      const result = await db.execute(sql`
-        SELECT tag, usage_count, created_at 
-        FROM global_tag_library 
-        ORDER BY usage_count DESC, tag ASC
-      `);
-      res.json(result.rows);
+      // Most used first; photo_count is how many photos carry the tag now
+      const usage = (await storage.getTagUsage())
+        .sort((a, b) => b.photoCount - a.photoCount || b.usageCount - a.usageCount || a.tag.localeCompare(b.tag));
+      res.json(usage.map(entry => ({
+        id: entry.id,
+        tag: entry.tag,
+        parent_tag_id: entry.parentTagId,
+        usage_count: entry.usageCount,
+        photo_count: entry.photoCount,
+        created_at: entry.createdAt,
+      })));
     } catch (error) {
       console.error("Error fetching tag library:", error);
       res.status(500).json({ message: "Failed to fetch tag library" });
@@ -402,15 +492,13 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
-  // Get photo history
+  // Processing history: import source, detection and AI models, exports
   app.get("/api/photos/:id/history", async (req, res) => {
     try {
-      const photo = await storage.getFileVersion(req.params.id);
-      if (!photo) {
+      const history = await provenanceService.getPhotoHistory(req.params.id);
+      if (!history) {
         return res.status(404).json({ message: "Photo not found" });
       }
-
-      const history = await storage.getAssetHistory(photo.mediaAssetId);
       res.json(history);
     } catch (error) {
       console.error("Error fetching photo history:", error);
@@ -460,72 +548,30 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
-  // Get burst photo analysis
+  // Stored bursts, resolved ones included, for showing which burst a photo belongs to
   app.get("/api/photos/burst-analysis", async (req, res) => {
     try {
-      const limit = parseInt(req.query.limit as string) || 100;
-
-      // Get recent photos to analyze for burst sequences
-      const recentPhotos = await storage.getRecentPhotos(limit);
-
-      // Group photos by time clusters (photos taken within 5 seconds of each other)
-      const burstGroups = [];
-      const timeThreshold = 5000; // 5 seconds in milliseconds
-
-      const sortedPhotos = recentPhotos.sort((a, b) => {
-        const aTime = new Date((a.metadata as any)?.exif?.dateTime || a.createdAt).getTime();
-        const bTime = new Date((b.metadata as any)?.exif?.dateTime || b.createdAt).getTime();
-        return aTime - bTime;
-      });
-
-      let currentGroup: any[] = [];
-      for (let i = 0; i < sortedPhotos.length; i++) {
-        const photo = sortedPhotos[i];
-        const photoTime = new Date((photo.metadata as any)?.exif?.dateTime || photo.createdAt).getTime();
-
-        if (currentGroup.length === 0) {
-          currentGroup.push(photo);
-        } else {
-          const lastPhotoTime = new Date(
-            (currentGroup[currentGroup.length - 1].metadata as any)?.exif?.dateTime || 
-            currentGroup[currentGroup.length - 1].createdAt
-          ).getTime();
-
-          if (photoTime - lastPhotoTime <= timeThreshold) {
-            currentGroup.push(photo);
-          } else {
-            if (currentGroup.length >= 3) { // Consider 3+ photos as a burst
-              burstGroups.push({
-                id: `burst-${currentGroup[0].id}`,
-                photos: currentGroup,
-                startTime: (currentGroup[0].metadata as any)?.exif?.dateTime || currentGroup[0].createdAt,
-                endTime: (currentGroup[currentGroup.length - 1].metadata as any)?.exif?.dateTime || currentGroup[currentGroup.length - 1].createdAt,
-                count: currentGroup.length
-              });
-            }
-            currentGroup = [photo];
-          }
-        }
-      }
-
-      // Don't forget the last group
-      if (currentGroup.length >= 3) {
-        burstGroups.push({
-          id: `burst-${currentGroup[0].id}`,
-          photos: currentGroup,
-          startTime: (currentGroup[0].metadata as any)?.exif?.dateTime || currentGroup[0].createdAt,
-          endTime: (currentGroup[currentGroup.length - 1].metadata as any)?.exif?.dateTime || currentGroup[currentGroup.length - 1].createdAt,
-          count: currentGroup.length
-        });
-      }
-
-      res.json(burstGroups);
+      res.json(await burstPhotoService.getAnalysis({ includeResolved: true }));
     } catch (error) {
       console.error("Error analyzing burst photos:", error);
       res.status(500).json({ message: "Failed to analyze burst photos" });
     }
   });
 
+  // Scored photos suggested for the next tier, best first; registered before /api/photos/:id
+  app.get("/api/photos/promotion-candidates", async (req, res) => {
+    try {
+      const query = promotionCandidatesQuerySchema.safeParse(req.query);
+      if (!query.success) {
+        return res.status(400).json({ message: "Invalid candidates query", errors: query.error.errors });
+      }
+      res.json(await promotionScoringService.getCandidates(query.data.tier, query.data.limit));
+    } catch (error) {
+      console.error("Error listing promotion candidates:", error);
+      res.status(500).json({ message: "Failed to list promotion candidates" });
+    }
+  });
+
   app.post("/api/tags/library", async (req, res) => {
     try {
       const { tags } = req.body;
@@ -547,112 +593,47 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
-  // Get all photos with optional filters
+  // Get all photos with optional filters. With limit, offset or cursor the
+  // response is a page: { photos, totalCount, nextCursor }
   app.get("/api/photos", async (req, res) => {
     try {
       const tier = req.query.tier as "silver" | "gold" | "unprocessed" | "all_versions" | undefined;
       const showAllVersions = req.query.showAllVersions === 'true';
+      const expandStacks = photoStackService.shouldExpand(req.query.expandStacks);
+      const page = pageQuerySchema.safeParse(req.query);
+      if (!page.success) {
+        return res.status(400).json({ message: "Invalid paging parameters", errors: page.error.errors });
+      }
+      const collapseStacks = !expandStacks;
 
+      let listing: PhotoListing;
       if (tier === 'unprocessed') {
-        // Get silver photos that haven't been promoted to gold
-        const allAssets = await storage.getAllMediaAssets();
-        const unprocessedPhotos = [];
-
-        for (const asset of allAssets) {
-          const versions = await storage.getFileVersionsByAsset(asset.id);
-          const hasSilver = versions.some(v => v.tier === 'silver');
-          const hasGold = versions.some(v => v.tier === 'gold');
-
-          // Add silver if no gold exists
-          if (hasSilver && !hasGold) {
-            const silverVersion = versions.find(v => v.tier === 'silver');
-            if (silverVersion) {
-              const enhancedAsset = {
-                ...asset,
-                displayFilename: path.basename(silverVersion.filePath)
-              };
-              unprocessedPhotos.push({ ...silverVersion, mediaAsset: enhancedAsset });
-            }
-          }
-        }
-
-        res.json(unprocessedPhotos);
+        // Silver photos that haven't been promoted to gold
+        listing = tierListing('silver', false, collapseStacks);
       } else if (tier === 'all_versions') {
         // Show all versions of all photos (admin view)
-        const allVersions = await storage.getAllFileVersions();
-        const photosWithAssets = await Promise.all(
-          allVersions.map(async (photo) => {
-            const asset = await storage.getMediaAsset(photo.mediaAssetId);
-            const enhancedAsset = {
-              ...asset,
-              displayFilename: path.basename(photo.filePath)
-            };
-            return { ...photo, mediaAsset: enhancedAsset };
-          })
-        );
-        res.json(photosWithAssets);
+        listing = { order: [byCreatedAt()], collapseStacks };
       } else if (tier) {
         // Show specific tier, but filter out superseded versions unless explicitly requested
-        const photos = await storage.getFileVersionsByTier(tier);
-        let filteredPhotos = photos;
-
-        if (!showAllVersions) {
-          // Filter out photos that have been superseded by higher tiers
-          const photosWithSuperseded = await Promise.all(
-            photos.map(async (photo) => {
-              const versions = await storage.getFileVersionsByAsset(photo.mediaAssetId);
-              const hasSilver = versions.some(v => v.tier === 'silver');
-              const hasGold = versions.some(v => v.tier === 'gold');
-
-              let isSuperseded = false;
-              if (photo.tier === 'silver' && hasGold) {
-                isSuperseded = true;
-              }
-
-              return { ...photo, isSuperseded };
-            })
-          );
-
-          filteredPhotos = photosWithSuperseded.filter(photo => !photo.isSuperseded);
-        }
-
-        const photosWithAssets = await Promise.all(
-          filteredPhotos.map(async (photo) => {
-            const asset = await storage.getMediaAsset(photo.mediaAssetId);
-            const enhancedAsset = {
-              ...asset,
-              displayFilename: path.basename(photo.filePath)
-            };
-            return { ...photo, mediaAsset: enhancedAsset };
-          })
-        );
-        res.json(photosWithAssets);
+        listing = tierListing(tier, showAllVersions, collapseStacks);
       } else {
-        // Default view: show highest tier version of each asset
-        const allAssets = await storage.getAllMediaAssets();
-        const highestTierPhotos = [];
-
-        for (const asset of allAssets) {
-          const versions = await storage.getFileVersionsByAsset(asset.id);
-
-          // Find highest tier version (Gold > Silver)
-          const goldVersion = versions.find(v => v.tier === 'gold');
-          const silverVersion = versions.find(v => v.tier === 'silver');
-
-          const highestVersion = goldVersion || silverVersion;
-          if (highestVersion) {
-            const enhancedAsset = {
-              ...asset,
-              displayFilename: path.basename(highestVersion.filePath)
-            };
-            highestTierPhotos.push({ ...highestVersion, mediaAsset: enhancedAsset });
-          }
-        }
-
-        // Sort by creation date, most recent first
-        highestTierPhotos.sort((a, b) => new Date(b.createdAt).getTime() - new Date(a.createdAt).getTime());
+        // Default view: show highest tier version of each asset, most recently taken
+        // first. Photos in system albums flagged excludeFromTimeline are hidden
+        // unless explicitly requested.
+        listing = timelineListing(collapseStacks, req.query.includeSystemAlbums === 'true');
+      }
 
-        res.json(highestTierPhotos.slice(0, 100)); // Limit to 100 for performance
+      const withDisplayFilename = (photo: ListedPhoto) => ({
+        ...photo,
+        mediaAsset: { ...photo.mediaAsset, displayFilename: path.basename(photo.filePath) },
+      });
+      if (wantsPage(page.data)) {
+        const result = await photoListingService.page(listing, page.data);
+        res.json({ ...result, photos: result.photos.map(withDisplayFilename) });
+      } else {
+        // Unpaged callers get the newest 100 of the default view
+        const photos = await photoListingService.rows(listing, tier ? undefined : 100);
+        res.json(photos.map(withDisplayFilename));
       }
     } catch (error) {
       console.error("Error fetching photos:", error);
@@ -692,10 +673,25 @@ export async function registerRoutes(app: Express): Promise<Server> {
 
       const results = [];
       const conflicts = [];
+      const sourceDevice = typeof req.body.sourceDevice === 'string' ? req.body.sourceDevice : null;
 
       console.log(`Processing ${files.length} uploaded files...`);
+      const operation = operationRegistry.start('import', `Importing ${files.length} files`, requestedOperationId(req));
+      const faceRecognitionIds: string[] = [];
 
       for (const file of files) {
+        if (operation.token.isCancelled) {
+          // Files are committed one at a time; drop the staged uploads we never started
+          await fs.rm(file.path, { force: true });
+          results.push({
+            filename: file.originalname,
+            status: 'cancelled',
+            message: 'Import was cancelled before this file was processed'
+          });
+          continue;
+        }
+        operation.progress(results.length, files.length);
+        operation.checkpoint({ pendingFiles: files.slice(results.length).map(pending => pending.originalname) });
         console.log(`Starting processing for file: ${file.originalname}, size: ${file.size} bytes`);
 
         try {
@@ -717,6 +713,14 @@ export async function registerRoutes(app: Express): Promise<Server> {
             const exactDuplicate = await storage.getFileByHash(fileHash);
             if (exactDuplicate) {
               console.log(`Auto-skipping MD5 identical file: ${file.originalname}`);
+              // Remember this source against the existing photo
+              await storage.createPhotoSource({
+                mediaAssetId: exactDuplicate.mediaAssetId,
+                sourcePath: file.originalname,
+                sourceDevice,
+                fileHash,
+                isDuplicate: true,
+              });
               results.push({
                 filename: file.originalname,
                 status: 'skipped',
@@ -750,10 +754,17 @@ export async function registerRoutes(app: Express): Promise<Server> {
           });
 
           // Process file directly to Silver tier with basic processing only
-          const silverPath = await fileManager.processToSilver(file.path, file.originalname);
+          const silverPath = await fileManager.processToSilver(file.path, file.originalname, fileHash);
 
           // Extract basic EXIF metadata (no AI processing)
           const metadata = await fileManager.extractMetadata(silverPath);
+          // Browsers send generic mime types for formats they don't know (e.g. RAW)
+          const mimeType = file.mimetype === 'application/octet-stream'
+            ? formatRegistry.getMimeType(file.originalname) ?? file.mimetype
+            : file.mimetype;
+          const dimensions = formatRegistry.supports(file.originalname, 'thumbnail')
+            ? await fileManager.getImageDimensions(silverPath)
+            : null;
 
           // Create Silver file version with basic metadata only
           const fileVersion = await storage.createFileVersion({
@@ -762,48 +773,36 @@ export async function registerRoutes(app: Express): Promise<Server> {
             filePath: silverPath,
             fileHash,
             fileSize: file.size,
-            mimeType: file.mimetype,
+            mimeType,
+            width: dimensions?.width ?? null,
+            height: dimensions?.height ?? null,
             metadata: metadata as any,
             aiShortDescription: null, // No AI processing at upload
             isReviewed: false,
           });
+          await systemAlbumService.processPhoto(fileVersion);
 
-          // Detect faces (non-LLM processing) if it's an image
-          if (file.mimetype.startsWith('image/')) {
-            // Run face detection on the processed Silver file
-            console.log('Running face detection on uploaded photo...');
-            const faceDetectionResult = await faceDetectionService.detectFaces(silverPath);
-            const detectedFaces = faceDetectionResult.faces;
-
-            // Update photo metadata with face detection status
-            const currentMetadata = fileVersion.metadata || {};
-            const updatedMetadata = {
-              ...currentMetadata,
-              ...faceDetectionResult.metadata
-            };
-
-            await storage.updateFileVersion(fileVersion.id, { metadata: updatedMetadata });
-
-            // Save detected faces to database
-            for (const face of detectedFaces) {
-              await storage.createFace({
-                photoId: fileVersion.id,
-                boundingBox: face.boundingBox,
-                confidence: face.confidence,
-                embedding: face.embedding,
-                personId: null, // Faces start unassigned
-              });
-            }
+          // Faces are detected by a background job once the batch is stored
+          if (mimeType.startsWith('image/')) {
+            faceRecognitionIds.push(fileVersion.id);
           }
 
-          // Log ingestion
-          await storage.createAssetHistory({
+          await storage.createPhotoSource({
             mediaAssetId: mediaAsset.id,
-            action: 'INGESTED',
-            details: `File uploaded to Silver tier with basic processing: ${file.originalname}`,
+            sourcePath: file.originalname,
+            sourceDevice,
+            fileHash,
+            isDuplicate: false,
+          });
+
+          // Log ingestion with where the file came from
+          await provenanceService.record(fileVersion, 'INGESTED', `File uploaded to Silver tier with basic processing: ${file.originalname}`, {
+            source: file.originalname,
+            sourceDevice,
+            fileHash,
           });
 
-          console.log(`Successfully uploaded ${file.originalname} to Silver tier with basic processing and face detection. Asset ID: ${mediaAsset.id}`);
+          console.log(`Successfully uploaded ${file.originalname} to Silver tier with basic processing. Asset ID: ${mediaAsset.id}`);
 
           results.push({
             filename: file.originalname,
@@ -822,11 +821,25 @@ export async function registerRoutes(app: Express): Promise<Server> {
         }
       }
 
+      if (operation.token.isCancelled) {
+        operation.checkpoint({
+          pendingFiles: results.filter(result => result.status === 'cancelled').map(result => result.filename)
+        });
+      }
+      operation.finish();
+
+      const faceJob = faceRecognitionIds.length > 0
+        ? await jobQueue.enqueueFaceRecognition(faceRecognitionIds)
+        : null;
+
       console.log(`Upload complete. Results:`, JSON.stringify(results, null, 2));
       res.json({ 
         results,
+        faceJobId: faceJob?.id ?? null,
         hasConflicts: conflicts.length > 0,
-        totalConflicts: conflicts.length
+        totalConflicts: conflicts.length,
+        operationId: operation.id,
+        cancelled: operation.token.isCancelled
       });
     } catch (error) {
       console.error("Error in upload endpoint:", error);
@@ -874,11 +887,185 @@ export async function registerRoutes(app: Express): Promise<Server> {
       const updatedPhoto = await storage.getFileVersion(photoId);
       res.json(updatedPhoto);
     } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
       console.error("Error updating photo metadata:", error);
       res.status(500).json({ message: "Failed to update metadata" });
     }
   });
 
+  // Typed single-field metadata edits, optionally written into the file too
+  app.put("/api/photos/:id/capture-date", async (req, res) => {
+    try {
+      const parsed = setCaptureDateSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid capture date", errors: parsed.error.errors });
+      }
+      const result = await photoMetadataEditor.setCaptureDate(req.params.id, parsed.data);
+      if (!result) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      console.error("Error setting capture date:", error);
+      res.status(500).json({ message: "Failed to set capture date" });
+    }
+  });
+
+  app.put("/api/photos/:id/gps", async (req, res) => {
+    try {
+      const parsed = setGpsSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid GPS position", errors: parsed.error.errors });
+      }
+      const result = await photoMetadataEditor.setGps(req.params.id, parsed.data);
+      if (!result) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      console.error("Error setting GPS position:", error);
+      res.status(500).json({ message: "Failed to set GPS position" });
+    }
+  });
+
+  app.put("/api/photos/:id/description", async (req, res) => {
+    try {
+      const parsed = setDescriptionSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid description", errors: parsed.error.errors });
+      }
+      const result = await photoMetadataEditor.setDescription(req.params.id, parsed.data);
+      if (!result) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      console.error("Error setting description:", error);
+      res.status(500).json({ message: "Failed to set description" });
+    }
+  });
+
+  app.put("/api/photos/:id/keywords", async (req, res) => {
+    try {
+      const parsed = setKeywordsSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid keywords", errors: parsed.error.errors });
+      }
+      const before = await operationJournal.captureTags([req.params.id], parsed.data.propagate);
+      const result = await photoMetadataEditor.setKeywords(req.params.id, parsed.data);
+      if (!result) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      await operationJournal.record('tags', `Set keywords on ${1 + result.propagatedTo.length} photo(s)`, await operationJournal.tagChanges(before));
+      res.json(result);
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      console.error("Error setting keywords:", error);
+      res.status(500).json({ message: "Failed to set keywords" });
+    }
+  });
+
+  // Canonical camera and lens names in the library, with the EXIF strings folded into each
+  app.get("/api/cameras", async (req, res) => {
+    try {
+      res.json(await cameraCanonicalizationService.listNames());
+    } catch (error) {
+      console.error("Error listing cameras:", error);
+      res.status(500).json({ message: "Failed to list cameras" });
+    }
+  });
+
+  app.get("/api/cameras/aliases", async (req, res) => {
+    try {
+      res.json(await cameraCanonicalizationService.getAliases());
+    } catch (error) {
+      console.error("Error getting camera aliases:", error);
+      res.status(500).json({ message: "Failed to get camera aliases" });
+    }
+  });
+
+  app.put("/api/cameras/aliases", async (req, res) => {
+    try {
+      const parsed = cameraAliasesSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid camera aliases", errors: parsed.error.errors });
+      }
+      res.json(await cameraCanonicalizationService.updateAliases(parsed.data));
+    } catch (error) {
+      console.error("Error updating camera aliases:", error);
+      res.status(500).json({ message: "Failed to update camera aliases" });
+    }
+  });
+
+  // Work out daylight, and optionally look up weather, for when and where photos were taken
+  app.post("/api/photos/environment/enrich", async (req, res) => {
+    try {
+      const parsed = enrichEnvironmentSchema.safeParse(req.body ?? {});
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid enrichment request", errors: parsed.error.errors });
+      }
+      const operation = operationRegistry.start('metadata_refresh', parsed.data.weather ? 'Adding daylight and weather to photos' : 'Adding daylight to photos', requestedOperationId(req));
+      try {
+        const result = await photoEnvironmentService.enrich(parsed.data, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      console.error("Error enriching photo environment:", error);
+      res.status(500).json({ message: "Failed to add daylight and weather" });
+    }
+  });
+
+  // Score photos as candidates for a higher tier: sharpness, exposure, faces and an AI aesthetic rating
+  app.post("/api/photos/promotion-scores", async (req, res) => {
+    try {
+      const parsed = scorePhotosSchema.safeParse(req.body ?? {});
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid scoring request", errors: parsed.error.errors });
+      }
+      const operation = operationRegistry.start('promotion_scoring', 'Scoring photos for promotion', requestedOperationId(req));
+      try {
+        const result = await promotionScoringService.scorePhotos(parsed.data, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      console.error("Error scoring photos for promotion:", error);
+      res.status(500).json({ message: "Failed to score photos" });
+    }
+  });
+
+  // Re-apply built-in rules and aliases to photos already in the library
+  app.post("/api/cameras/canonicalize", async (req, res) => {
+    try {
+      const operation = operationRegistry.start('metadata_refresh', 'Canonicalizing camera and lens names', requestedOperationId(req));
+      try {
+        const result = await cameraCanonicalizationService.applyToLibrary(operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      console.error("Error canonicalizing cameras:", error);
+      res.status(500).json({ message: "Failed to canonicalize camera names" });
+    }
+  });
+
 
 
   // AI Configuration Routes
@@ -897,9 +1084,12 @@ export async function registerRoutes(app: Express): Promise<Server> {
             textModel: config.ollama.textModel
           },
           openai: {
+            baseUrl: config.openai.baseUrl,
             model: config.openai.model,
             hasApiKey: !!config.openai.apiKey
-          }
+          },
+          requestsPerMinute: config.requestsPerMinute,
+          maxRetries: config.maxRetries
         }
       });
     } catch (error) {
@@ -910,14 +1100,12 @@ export async function registerRoutes(app: Express): Promise<Server> {
 
   app.post("/api/ai/config", async (req, res) => {
     try {
-      const { provider, ollama, openai } = req.body;
-
-      const newConfig: any = {};
-      if (provider) newConfig.provider = provider;
-      if (ollama) newConfig.ollama = ollama;
-      if (openai) newConfig.openai = openai;
+      const updates = aiProviderConfigSchema.safeParse(req.body);
+      if (!updates.success) {
+        return res.status(400).json({ message: "Invalid AI configuration", errors: updates.error.errors });
+      }
 
-      aiService.setConfig(newConfig);
+      await aiService.setConfig(updates.data);
 
       res.json({ success: true, message: "AI configuration updated" });
     } catch (error) {
@@ -948,10 +1136,15 @@ export async function registerRoutes(app: Express): Promise<Server> {
         return res.status(404).json({ message: "Photo not found" });
       }
 
+      const { minConfidence } = req.body ?? {};
+      if (minConfidence !== undefined && (typeof minConfidence !== 'number' || minConfidence < 0 || minConfidence > 100)) {
+        return res.status(400).json({ message: "minConfidence must be a number between 0 and 100" });
+      }
+
       console.log('Testing face detection on photo:', photo.filePath);
 
       // Run face detection
-      const faceDetectionResult = await faceDetectionService.detectFaces(photo.filePath);
+      const faceDetectionResult = await faceDetectionService.detectFaces(photo.filePath, { minConfidence });
       const detectedFaces = faceDetectionResult.faces;
 
       // Save faces to database if any detected
@@ -962,10 +1155,18 @@ export async function registerRoutes(app: Express): Promise<Server> {
           boundingBox: face.boundingBox,
           confidence: face.confidence,
           embedding: face.embedding,
+          embeddingModel: face.embeddingModel ?? null,
+          detector: face.detector ?? null,
           personId: face.personId || null,
         });
-        savedFaces.push(savedFace);
+        savedFaces.push({ ...savedFace, normalizedBox: face.normalizedBox ?? null });
       }
+      await provenanceService.recordFaceDetection(
+        photo,
+        detectedFaces.length,
+        faceDetectionResult.metadata.faceDetection,
+        detectedFaces.some(face => face.embeddingModel) ? FACE_EMBEDDING_MODEL : null
+      );
 
       // Get the media asset separately
       const mediaAsset = await storage.getMediaAsset(photo.mediaAssetId);
@@ -973,6 +1174,7 @@ export async function registerRoutes(app: Express): Promise<Server> {
       res.json({
         photo: mediaAsset?.originalFilename || 'Unknown',
         facesDetected: detectedFaces.length,
+        minConfidence: faceDetectionResult.metadata.faceDetection.minConfidence,
         faces: savedFaces
       });
     } catch (error) {
@@ -1070,9 +1272,32 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
+  // Privacy flags keep photos of this person out of exports, shares and AI uploads
+  app.put("/api/people/:id/privacy", async (req, res) => {
+    try {
+      const parsed = personPrivacySchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid privacy settings", errors: parsed.error.errors });
+      }
+      const person = await storage.updatePerson(req.params.id, parsed.data);
+      if (!person) {
+        return res.status(404).json({ message: "Person not found" });
+      }
+      res.json(person);
+    } catch (error) {
+      console.error("Error updating person privacy:", error);
+      res.status(500).json({ message: "Failed to update person privacy" });
+    }
+  });
+
+  // Soft-delete: restorable from /api/deleted until purged
   app.delete("/api/people/:id", async (req, res) => {
     try {
-      await storage.deletePerson(req.params.id);
+      const person = await storage.getPerson(req.params.id);
+      if (!(await softDeleteService.delete('person', req.params.id))) {
+        return res.status(404).json({ message: "Person not found" });
+      }
+      await operationJournal.record('deletion', `Deleted person ${person!.name}`, [{ type: 'deletion', kind: 'person', id: req.params.id }]);
       res.json({ success: true, message: "Person deleted successfully" });
     } catch (error) {
       console.error("Error deleting person:", error);
@@ -1080,6 +1305,89 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
+  // Keywords that name people (from Picasa, Lightroom, ...) and who they may be
+  app.get("/api/people/keyword-mapping", async (req, res) => {
+    try {
+      res.json(await keywordPeopleService.preview());
+    } catch (error) {
+      console.error("Error listing people keywords:", error);
+      res.status(500).json({ message: "Failed to list people keywords" });
+    }
+  });
+
+  // Link photos carrying a keyword to a person through their unnamed faces
+  app.post("/api/people/keyword-mapping/apply", async (req, res) => {
+    try {
+      const parsed = applyKeywordPeopleSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid keyword mapping", errors: parsed.error.errors });
+      }
+      const result = await keywordPeopleService.apply(parsed.data);
+      if (result.assignedFaceIds.length > 0) {
+        const before = new Map<string, string | null>(result.assignedFaceIds.map(faceId => [faceId, null]));
+        await operationJournal.record('people', `Assigned ${result.linked} face(s) from keywords`, await operationJournal.faceChanges(before));
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof KeywordPeopleError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error("Error applying people keywords:", error);
+      res.status(500).json({ message: "Failed to apply keyword mapping" });
+    }
+  });
+
+  // Likely duplicate people, each pair ready for the merge preview
+  app.get("/api/people/merge/suggestions", async (req, res) => {
+    try {
+      const query = suggestMergesQuerySchema.safeParse(req.query);
+      if (!query.success) {
+        return res.status(400).json({ message: "Invalid suggestion options", errors: query.error.errors });
+      }
+      res.json(await peopleMergeService.suggestMerges(query.data));
+    } catch (error) {
+      console.error("Error suggesting people merges:", error);
+      res.status(500).json({ message: "Failed to suggest merges" });
+    }
+  });
+
+  // Show what merging these people would change before doing it
+  app.post("/api/people/merge/preview", async (req, res) => {
+    try {
+      const parsed = previewMergePeopleSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "personIds must list at least two people", errors: parsed.error.errors });
+      }
+      const preview = await peopleMergeService.previewMerge(parsed.data.personIds);
+      res.json(preview);
+    } catch (error) {
+      if (error instanceof PeopleMergeError) {
+        return res.status(404).json({ message: error.message });
+      }
+      console.error("Error previewing people merge:", error);
+      res.status(500).json({ message: "Failed to preview merge" });
+    }
+  });
+
+  // Merge people into a target, with per-field choices for conflicting values
+  app.post("/api/people/merge", async (req, res) => {
+    try {
+      const parsed = mergePeopleSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid merge request", errors: parsed.error.errors });
+      }
+      const person = await peopleMergeService.mergePeople(parsed.data);
+      counterConsistencyService.scheduleCheck();
+      res.json(person);
+    } catch (error) {
+      if (error instanceof PeopleMergeError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error("Error merging people:", error);
+      res.status(500).json({ message: "Failed to merge people" });
+    }
+  });
+
   app.put("/api/people/:id/thumbnail", async (req, res) => {
     try {
       const { faceId } = req.body;
@@ -1132,19 +1440,98 @@ export async function registerRoutes(app: Express): Promise<Server> {
 
   app.get("/api/people/:id/photos", async (req, res) => {
     try {
-      const photos = await storage.getPersonPhotos ? await storage.getPersonPhotos(req.params.id) : [];
-      res.json(photos);
+      const page = pageQuerySchema.safeParse(req.query);
+      if (!page.success) {
+        return res.status(400).json({ message: "Invalid paging parameters", errors: page.error.errors });
+      }
+      const listing = personListing(req.params.id, !photoStackService.shouldExpand(req.query.expandStacks));
+      res.json(wantsPage(page.data) ? await photoListingService.page(listing, page.data) : await photoListingService.rows(listing));
     } catch (error) {
       console.error("Error fetching person photos:", error);
       res.status(500).json({ message: "Failed to fetch person photos" });
     }
   });
 
-  // Relationship routes
-  app.get("/api/people/:id/relationships", async (req, res) => {
+  // Create or refresh a "best of" album for a person
+  app.post("/api/people/:id/album", async (req, res) => {
     try {
-      const relationships = await storage.getRelationshipsByPerson(req.params.id);
-      res.json(relationships);
+      const person = await storage.getPerson(req.params.id);
+      if (!person) {
+        return res.status(404).json({ message: "Person not found" });
+      }
+
+      const result = await personAlbumService.generatePersonAlbum(person.id, req.body.options ?? {});
+      res.json(result);
+    } catch (error) {
+      console.error("Error generating person album:", error);
+      res.status(500).json({ message: "Failed to generate person album" });
+    }
+  });
+
+  // Export a person's face across the years as aligned, same-sized crops
+  app.post("/api/people/:id/export-faces", async (req, res) => {
+    try {
+      const parsed = personFaceExportSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid face export", errors: parsed.error.errors });
+      }
+      const person = await personFaceExportService.getPerson(req.params.id);
+
+      const operation = operationRegistry.start('export', `Exporting faces of ${person.name}`, requestedOperationId(req));
+      try {
+        const result = await personFaceExportService.exportPersonFaces(person.id, parsed.data, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof PersonFaceExportError) {
+        return res.status(404).json({ message: error.message });
+      }
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Export was cancelled", cancelled: true });
+      }
+      console.error("Error exporting person faces:", error);
+      res.status(500).json({ message: "Failed to export faces" });
+    }
+  });
+
+  // Render a person's faces, one per month or year, as an MP4; follow progress through the operation
+  app.post("/api/people/:id/timelapse", async (req, res) => {
+    try {
+      const parsed = personTimelapseSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid timelapse request", errors: parsed.error.errors });
+      }
+      const person = await personFaceExportService.getPerson(req.params.id);
+
+      const operation = operationRegistry.start('timelapse', `Making timelapse of ${person.name}`, requestedOperationId(req));
+      try {
+        const result = await personTimelapseService.generatePersonTimelapse(person.id, parsed.data, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof PersonFaceExportError) {
+        return res.status(404).json({ message: error.message });
+      }
+      if (error instanceof PersonTimelapseError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Timelapse was cancelled", cancelled: true });
+      }
+      console.error("Error generating person timelapse:", error);
+      res.status(500).json({ message: "Failed to generate timelapse" });
+    }
+  });
+
+  // Relationship routes
+  app.get("/api/people/:id/relationships", async (req, res) => {
+    try {
+      const relationships = await storage.getRelationshipsByPerson(req.params.id);
+      res.json(relationships);
     } catch (error) {
       console.error("Error fetching relationships:", error);
       res.status(500).json({ message: "Failed to fetch relationships" });
@@ -1279,14 +1666,19 @@ export async function registerRoutes(app: Express): Promise<Server> {
   app.post("/api/faces/assign", async (req, res) => {
     try {
       const { faceIds, personId } = req.body;
+      const propagate = propagationScopeSchema.optional().safeParse(req.body.propagate);
+      if (!propagate.success) {
+        return res.status(400).json({ message: "Invalid propagation options", errors: propagate.error.errors });
+      }
 
+      const before = await operationJournal.captureFaces(faceIds);
+      const propagatedFaceIds: string[] = [];
       for (const faceId of faceIds) {
-        if (storage.assignFaceToPerson) {
-          await storage.assignFaceToPerson(faceId, personId);
-        }
+        propagatedFaceIds.push(...(await propagationService.assignFace(faceId, personId, propagate.data)));
       }
+      await operationJournal.record('people', `Assigned ${faceIds.length} face(s) to a person`, await operationJournal.faceChanges(before, propagatedFaceIds));
 
-      res.json({ success: true, assigned: faceIds.length });
+      res.json({ success: true, assigned: faceIds.length, propagatedFaceIds });
     } catch (error) {
       console.error("Error assigning faces:", error);
       res.status(500).json({ message: "Failed to assign faces" });
@@ -1403,6 +1795,85 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
+  // Group unassigned faces by likeness; faces clustered earlier keep their cluster
+  app.post("/api/faces/clusters", async (req, res) => {
+    try {
+      const operation = operationRegistry.start('face_detection', 'Clustering unassigned faces', requestedOperationId(req));
+      try {
+        const result = await faceClusteringService.clusterUnassignedFaces(operation.token, operation.progress);
+        counterConsistencyService.scheduleCheck();
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      console.error("Error clustering faces:", error);
+      res.status(500).json({ message: "Failed to cluster faces" });
+    }
+  });
+
+  app.get("/api/faces/clusters", async (req, res) => {
+    try {
+      res.json(await faceClusteringService.getClusters());
+    } catch (error) {
+      console.error("Error fetching face clusters:", error);
+      res.status(500).json({ message: "Failed to fetch face clusters" });
+    }
+  });
+
+  // Name a whole cluster at once, as an existing person or a new one
+  app.post("/api/faces/clusters/:id/assign", async (req, res) => {
+    try {
+      const parsed = assignClusterSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid cluster assignment", errors: parsed.error.errors });
+      }
+      if (parsed.data.personId && !(await storage.getPerson(parsed.data.personId))) {
+        return res.status(404).json({ message: "Person not found" });
+      }
+      const result = await faceClusteringService.assignCluster(req.params.id, parsed.data);
+      if (!result) {
+        return res.status(404).json({ message: "Face cluster not found" });
+      }
+      counterConsistencyService.scheduleCheck();
+      res.json({ success: true, ...result });
+    } catch (error) {
+      console.error("Error assigning face cluster:", error);
+      res.status(500).json({ message: "Failed to assign face cluster" });
+    }
+  });
+
+  // "Is this Alice?": people whose assigned faces look most like this one
+  app.get("/api/faces/:id/person-suggestions", async (req, res) => {
+    try {
+      const limit = req.query.limit ? Math.min(10, Math.max(1, parseInt(req.query.limit as string) || 3)) : 3;
+      const suggestions = await faceDetectionService.suggestPersonForFace(req.params.id, limit);
+      if (!suggestions) {
+        return res.status(404).json({ message: "Face not found" });
+      }
+      res.json(suggestions);
+    } catch (error) {
+      console.error("Error suggesting person for face:", error);
+      res.status(500).json({ message: "Failed to suggest person for face" });
+    }
+  });
+
+  // Compute embeddings for faces that have none or only a legacy vector
+  app.post("/api/faces/embeddings/backfill", async (req, res) => {
+    try {
+      const operation = operationRegistry.start('face_detection', 'Generating face embeddings', requestedOperationId(req));
+      try {
+        const result = await faceDetectionService.backfillEmbeddings(operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      console.error("Error backfilling face embeddings:", error);
+      res.status(500).json({ message: "Failed to generate face embeddings" });
+    }
+  });
+
   // Reprocess unassigned faces after manual assignments
   app.post("/api/faces/reprocess", async (req, res) => {
     try {
@@ -1421,8 +1892,15 @@ export async function registerRoutes(app: Express): Promise<Server> {
   // Batch assign faces using suggestions
   app.post("/api/faces/batch-assign", async (req, res) => {
     try {
-      const { assignments } = req.body; // Array of {faceId, personId}
-      const result = await faceDetectionService.batchAssignFaces(assignments);
+      const { assignments, propagate } = req.body; // Array of {faceId, personId}
+      const scope = propagationScopeSchema.optional().safeParse(propagate);
+      if (!scope.success) {
+        return res.status(400).json({ message: "Invalid propagation options", errors: scope.error.errors });
+      }
+      const before = await operationJournal.captureFaces(assignments.map((assignment: { faceId: string }) => assignment.faceId));
+      const { propagatedFaceIds, ...result } = await faceDetectionService.batchAssignFaces(assignments, scope.data);
+      await operationJournal.record('people', `Assigned ${result.success} face(s)`, await operationJournal.faceChanges(before, propagatedFaceIds));
+      counterConsistencyService.scheduleCheck();
       res.json(result);
     } catch (error) {
       console.error("Error batch assigning faces:", error);
@@ -1480,6 +1958,49 @@ export async function registerRoutes(app: Express): Promise<Server> {
     }
   });
 
+  // Review queue of borderline detections, lowest confidence first
+  app.get("/api/faces/review", async (req, res) => {
+    try {
+      const query = faceReviewQuerySchema.safeParse(req.query);
+      if (!query.success) {
+        return res.status(400).json({ message: "Invalid review query", errors: query.error.errors });
+      }
+      const { threshold, limit, offset } = query.data;
+      res.json(await faceReviewService.listLowConfidenceFaces(threshold, limit, offset));
+    } catch (error) {
+      console.error("Error fetching low-confidence faces:", error);
+      res.status(500).json({ message: "Failed to fetch low-confidence faces" });
+    }
+  });
+
+  // Confirm detections as real faces, taking them out of the review queue
+  app.post("/api/faces/review/confirm", async (req, res) => {
+    try {
+      const parsed = faceReviewDecisionSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid face ids", errors: parsed.error.errors });
+      }
+      res.json({ confirmed: await faceReviewService.confirmFaces(parsed.data.faceIds) });
+    } catch (error) {
+      console.error("Error confirming faces:", error);
+      res.status(500).json({ message: "Failed to confirm faces" });
+    }
+  });
+
+  // Delete detections that are not faces at all
+  app.post("/api/faces/review/discard", async (req, res) => {
+    try {
+      const parsed = faceReviewDecisionSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid face ids", errors: parsed.error.errors });
+      }
+      res.json({ discarded: await faceReviewService.discardFaces(parsed.data.faceIds) });
+    } catch (error) {
+      console.error("Error discarding faces:", error);
+      res.status(500).json({ message: "Failed to discard faces" });
+    }
+  });
+
   // Get grouped faces for better organization
   app.get("/api/faces/grouped", async (req, res) => {
     try {
@@ -1742,17 +2263,22 @@ app.get("/api/smart-collections", async (req, res) => {
 
 app.post("/api/smart-collections", async (req, res) => {
   try {
-    const { name, description, rules } = req.body;
+    const { name, description } = req.body;
+    const rules = smartCollectionRulesSchema.safeParse(req.body.rules);
+    if (!rules.success) {
+      return res.status(400).json({ message: "Invalid smart collection rules", errors: rules.error.errors });
+    }
 
     const collection = await storage.createCollection({
       name,
       description,
       isSmartCollection: true,
-      smartRules: rules || {},
+      smartRules: rules.data,
       isPublic: false
     });
+    const evaluation = await smartCollectionService.evaluateSmartCollection(collection.id);
 
-    res.json(collection);
+    res.json({ ...collection, photoCount: evaluation.photoCount });
   } catch (error) {
     console.error("Failed to create smart collection:", error);
     res.status(500).json({ message: "Failed to create smart collection" });
@@ -1774,9 +2300,8 @@ app.patch("/api/smart-collections/:id/toggle", async (req, res) => {
 
 app.post("/api/smart-collections/organize", async (req, res) => {
   try {
-    // For now, just return a success message
-    // TODO: Implement smart collection organization
-    res.json({ message: "Smart collection organization completed", organized: 0 });
+    const results = await smartCollectionService.evaluateAll();
+    res.json({ message: "Smart collection organization completed", organized: results.length, results });
   } catch (error) {
     console.error("Failed to organize photos:", error);
     res.status(500).json({ message: "Failed to organize photos" });
@@ -1789,7 +2314,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
     const photos = await storage.getCollectionPhotos(id);
 
-    res.json(photos);
+    res.json(await photoStackService.applyStackMode(photos, photoStackService.shouldExpand(req.query.expandStacks)));
   } catch (error) {
     console.error("Failed to get collection photos:", error);
     res.status(500).json({ message: "Failed to retrieve collection photos" });
@@ -1817,10 +2342,12 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       const collectionsWithCounts = await Promise.all(
         collections.map(async (collection) => {
           const photos = await storage.getCollectionPhotos(collection.id);
+          // The chosen cover, else the first photo in album order
+          const cover = photos.find(photo => photo.id === collection.coverPhotoId) ?? photos[0];
           return {
             ...collection,
             photoCount: photos.length,
-            coverPhoto: photos[0]?.filePath || null
+            coverPhoto: cover?.filePath || null
           };
         })
       );
@@ -1833,7 +2360,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
   app.post("/api/collections", async (req, res) => {
     try {
-      const collection = await storage.createCollection(req.body);
+      const { systemKey, ...collectionData } = req.body;
+      const collection = await storage.createCollection(collectionData);
       res.json(collection);
     } catch (error) {
       console.error("Error creating collection:", error);
@@ -1841,19 +2369,119 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Built-in system albums (Screenshots, Documents, Receipts, Whiteboards)
+  app.get("/api/collections/system", async (req, res) => {
+    try {
+      const albums = await systemAlbumService.getSystemAlbums();
+      res.json(albums);
+    } catch (error) {
+      console.error("Error fetching system albums:", error);
+      res.status(500).json({ message: "Failed to fetch system albums" });
+    }
+  });
+
+  // Reclassify the whole library into the system albums
+  app.post("/api/collections/system/refresh", async (req, res) => {
+    try {
+      const counts = await systemAlbumService.refreshAll();
+      res.json({ success: true, counts });
+    } catch (error) {
+      console.error("Error refreshing system albums:", error);
+      res.status(500).json({ message: "Failed to refresh system albums" });
+    }
+  });
+
+  // Albums suggested for photos in no album yet; pending unless ?status= says otherwise
+  app.get("/api/collections/suggestions", async (req, res) => {
+    try {
+      const query = albumSuggestionsQuerySchema.safeParse(req.query);
+      if (!query.success) {
+        return res.status(400).json({ message: "Invalid suggestions query", errors: query.error.errors });
+      }
+      res.json(await albumSuggestionService.getSuggestions(query.data.status));
+    } catch (error) {
+      console.error("Error fetching album suggestions:", error);
+      res.status(500).json({ message: "Failed to fetch album suggestions" });
+    }
+  });
+
+  // Group unorganized photos by time, place and people into new suggestions
+  app.post("/api/collections/suggestions", async (req, res) => {
+    try {
+      const parsed = suggestAlbumsSchema.safeParse(req.body ?? {});
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid suggestion request", errors: parsed.error.errors });
+      }
+      const operation = operationRegistry.start('album_suggestions', 'Suggesting albums', requestedOperationId(req));
+      try {
+        const result = await albumSuggestionService.suggestAlbums(parsed.data, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Album suggestion was cancelled", cancelled: true });
+      }
+      console.error("Error suggesting albums:", error);
+      res.status(500).json({ message: "Failed to suggest albums" });
+    }
+  });
+
+  app.post("/api/collections/suggestions/:id/accept", async (req, res) => {
+    try {
+      const parsed = acceptAlbumSuggestionSchema.safeParse(req.body ?? {});
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid album name", errors: parsed.error.errors });
+      }
+      res.status(201).json(await albumSuggestionService.acceptSuggestion(req.params.id, parsed.data.name));
+    } catch (error) {
+      if (error instanceof AlbumSuggestionError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error accepting album suggestion:", error);
+      res.status(500).json({ message: "Failed to create album from suggestion" });
+    }
+  });
+
+  app.post("/api/collections/suggestions/:id/dismiss", async (req, res) => {
+    try {
+      res.json(await albumSuggestionService.dismissSuggestion(req.params.id));
+    } catch (error) {
+      if (error instanceof AlbumSuggestionError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error dismissing album suggestion:", error);
+      res.status(500).json({ message: "Failed to dismiss album suggestion" });
+    }
+  });
+
   app.get("/api/collections/:id/photos", async (req, res) => {
     try {
-      const photos = await storage.getCollectionPhotos(req.params.id);
-      res.json(photos);
+      const page = pageQuerySchema.safeParse(req.query);
+      if (!page.success) {
+        return res.status(400).json({ message: "Invalid paging parameters", errors: page.error.errors });
+      }
+      const listing = collectionListing(req.params.id, !photoStackService.shouldExpand(req.query.expandStacks));
+      res.json(wantsPage(page.data) ? await photoListingService.page(listing, page.data) : await photoListingService.rows(listing));
     } catch (error) {
       console.error("Error fetching collection photos:", error);
       res.status(500).json({ message: "Failed to fetch collection photos" });
     }
   });
 
+  // Soft-delete: restorable from /api/deleted until purged
   app.delete("/api/collections/:id", async (req, res) => {
     try {
-      await storage.deleteCollection(req.params.id);
+      const collection = await storage.getCollection(req.params.id);
+      if (collection?.systemKey) {
+        return res.status(400).json({ message: "System albums cannot be deleted" });
+      }
+
+      if (!(await softDeleteService.delete('album', req.params.id))) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+      await operationJournal.record('deletion', `Deleted album ${collection!.name}`, [{ type: 'deletion', kind: 'album', id: req.params.id }]);
       res.json({ success: true, message: "Collection deleted successfully" });
     } catch (error) {
       console.error("Error deleting collection:", error);
@@ -1867,9 +2495,11 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       const { photoIds } = req.body;
       const collectionId = req.params.id;
 
+      const before = await operationJournal.captureAlbums([collectionId]);
       for (const photoId of photoIds) {
         await storage.addPhotoToCollection(collectionId, photoId);
       }
+      await operationJournal.record('album', `Added ${photoIds.length} photo(s) to an album`, await operationJournal.albumChanges(before));
 
       res.json({ success: true, added: photoIds.length });
     } catch (error) {
@@ -1884,9 +2514,11 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       const { photoIds } = req.body;
       const collectionId = req.params.id;
 
+      const before = await operationJournal.captureAlbums([collectionId]);
       for (const photoId of photoIds) {
         await storage.removePhotoFromCollection(collectionId, photoId);
       }
+      await operationJournal.record('album', `Removed ${photoIds.length} photo(s) from an album`, await operationJournal.albumChanges(before));
 
       res.json({ success: true, removed: photoIds.length });
     } catch (error) {
@@ -1898,8 +2530,21 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
   // Update collection
   app.patch("/api/collections/:id", async (req, res) => {
     try {
-      const updates = req.body;
+      // System album identity is fixed; only settings like excludeFromTimeline may change
+      const { systemKey, ...updates } = req.body;
+      if (updates.smartRules !== undefined && updates.smartRules !== null) {
+        const rules = smartCollectionRulesSchema.safeParse(updates.smartRules);
+        if (!rules.success) {
+          return res.status(400).json({ message: "Invalid smart collection rules", errors: rules.error.errors });
+        }
+        updates.smartRules = rules.data;
+      }
       const collection = await storage.updateCollection(req.params.id, updates);
+      // New rules, or a collection just made smart, get their membership worked out again
+      if (collection?.isSmartCollection && (updates.smartRules || updates.isSmartCollection)) {
+        const evaluation = await smartCollectionService.evaluateSmartCollection(collection.id);
+        return res.json({ ...collection, photoCount: evaluation.photoCount });
+      }
       res.json(collection);
     } catch (error) {
       console.error("Error updating collection:", error);
@@ -1907,29 +2552,213 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Set or clear an album's cover photo
+  app.put("/api/collections/:id/cover", async (req, res) => {
+    try {
+      const { photoId } = req.body;
+      if (photoId !== null && typeof photoId !== 'string') {
+        return res.status(400).json({ message: "photoId must be a photo id or null" });
+      }
+      const collection = await storage.getCollection(req.params.id);
+      if (!collection || collection.deletedAt) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      if (photoId === null) {
+        return res.json(await storage.setCollectionCover(collection.id, null));
+      }
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo || photo.deletedAt) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      if (!(await storage.getCollectionPhotoIds(collection.id)).includes(photo.id)) {
+        return res.status(400).json({ message: "The cover photo must be in the album" });
+      }
+      res.json(await storage.setCollectionCover(collection.id, photo));
+    } catch (error) {
+      console.error("Error setting album cover:", error);
+      res.status(500).json({ message: "Failed to set album cover" });
+    }
+  });
+
+  // Save a manual photo order; photos left out keep their place after the ordered ones
+  app.put("/api/collections/:id/order", async (req, res) => {
+    try {
+      const { photoIds } = req.body;
+      if (!Array.isArray(photoIds) || !photoIds.every(id => typeof id === 'string')) {
+        return res.status(400).json({ message: "photoIds must be an array of photo ids" });
+      }
+      const collection = await storage.getCollection(req.params.id);
+      if (!collection || collection.deletedAt) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      await storage.reorderCollectionPhotos(collection.id, photoIds);
+      res.json({ success: true, photoIds: await storage.getCollectionPhotoIds(collection.id) });
+    } catch (error) {
+      console.error("Error reordering album photos:", error);
+      res.status(500).json({ message: "Failed to reorder album photos" });
+    }
+  });
+
+  // Duplicate collection
+  app.post("/api/collections/:id/duplicate", async (req, res) => {
+    try {
+      const source = await storage.getCollection(req.params.id);
+      if (!source) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      const name = req.body.name || `${source.name} (Copy)`;
+      const collection = await storage.duplicateCollection(req.params.id, name);
+      res.json(collection);
+    } catch (error) {
+      console.error("Error duplicating collection:", error);
+      res.status(500).json({ message: "Failed to duplicate collection" });
+    }
+  });
+
+  // Merge source collections into a target collection
+  app.post("/api/collections/merge", async (req, res) => {
+    try {
+      const { targetId, sourceIds, deleteSources = false } = req.body;
+      if (!targetId || !Array.isArray(sourceIds) || sourceIds.length === 0) {
+        return res.status(400).json({ message: "targetId and sourceIds are required" });
+      }
+
+      const target = await storage.getCollection(targetId);
+      if (!target) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      const added = await storage.mergeCollections(targetId, sourceIds, deleteSources);
+      res.json({ success: true, added, collection: target });
+    } catch (error) {
+      console.error("Error merging collections:", error);
+      res.status(500).json({ message: "Failed to merge collections" });
+    }
+  });
+
+  // Create a collection from the photos shared by two collections
+  app.post("/api/collections/intersect", async (req, res) => {
+    try {
+      const { name, firstId, secondId } = req.body;
+      if (!name || !firstId || !secondId) {
+        return res.status(400).json({ message: "name, firstId and secondId are required" });
+      }
+
+      const [first, second] = await Promise.all([
+        storage.getCollection(firstId),
+        storage.getCollection(secondId),
+      ]);
+      if (!first || !second) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      const collection = await storage.intersectCollections(name, firstId, secondId);
+      res.json(collection);
+    } catch (error) {
+      console.error("Error intersecting collections:", error);
+      res.status(500).json({ message: "Failed to intersect collections" });
+    }
+  });
+
+  // Add every photo matching a search to a collection
+  app.post("/api/collections/:id/search-results", async (req, res) => {
+    try {
+      const collection = await storage.getCollection(req.params.id);
+      if (!collection) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      const photoIds = await advancedSearch.findMatchingPhotoIds(req.body.filters || {});
+      const added = await storage.addPhotosToCollection(req.params.id, photoIds);
+      res.json({ success: true, matched: photoIds.length, added });
+    } catch (error) {
+      console.error("Error adding search results to collection:", error);
+      res.status(500).json({ message: "Failed to add search results to collection" });
+    }
+  });
+
+  // Snapshot the current search results into a regular collection
+  app.post("/api/collections/from-search", async (req, res) => {
+    try {
+      const { name, description, filters = {} } = req.body;
+      if (!name) {
+        return res.status(400).json({ message: "name is required" });
+      }
+
+      const photoIds = await advancedSearch.findMatchingPhotoIds(filters);
+      const collection = await storage.createCollectionWithPhotos(
+        { name, description, isSmartCollection: false },
+        photoIds
+      );
+      res.json({ ...collection, photoCount: new Set(photoIds).size });
+    } catch (error) {
+      console.error("Error creating collection from search:", error);
+      res.status(500).json({ message: "Failed to create collection from search" });
+    }
+  });
+
+  // Turn a regular collection into a smart collection driven by rules
+  app.post("/api/collections/:id/convert-to-smart", async (req, res) => {
+    try {
+      const rules = smartCollectionRulesSchema.safeParse(req.body.rules);
+      if (!rules.success) {
+        return res.status(400).json({ message: "Valid smart collection rules are required", errors: rules.error.errors });
+      }
+
+      const existing = await storage.getCollection(req.params.id);
+      if (!existing) {
+        return res.status(404).json({ message: "Collection not found" });
+      }
+
+      const collection = await storage.updateCollection(req.params.id, {
+        isSmartCollection: true,
+        smartRules: rules.data,
+      });
+      const evaluation = await smartCollectionService.evaluateSmartCollection(req.params.id);
+      res.json({ ...collection, photoCount: evaluation.photoCount });
+    } catch (error) {
+      console.error("Error converting collection to smart collection:", error);
+      res.status(500).json({ message: "Failed to convert collection to smart collection" });
+    }
+  });
+
+  // Re-run a smart collection's rules over the whole library
+  app.post("/api/collections/:id/evaluate", async (req, res) => {
+    try {
+      res.json(await smartCollectionService.evaluateSmartCollection(req.params.id));
+    } catch (error) {
+      if (error instanceof SmartCollectionError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error("Error evaluating smart collection:", error);
+      res.status(500).json({ message: "Failed to evaluate smart collection" });
+    }
+  });
+
   // Batch operations
   app.post("/api/photos/batch", async (req, res) => {
     try {
       const { operation, photoIds, params } = req.body;
+      const propagate = propagationScopeSchema.optional().safeParse(params?.propagate);
+      if (!propagate.success) {
+        return res.status(400).json({ message: "Invalid propagation options", errors: propagate.error.errors });
+      }
 
       switch (operation) {
-        case 'addTags':
+        case 'addTags': {
+          const before = await operationJournal.captureTags(photoIds, propagate.data);
           for (const photoId of photoIds) {
             const photo = await storage.getFileVersion(photoId);
             if (photo) {
-              const metadata = (photo.metadata as any) || {};
-              const existingTags = metadata.ai?.aiTags || [];
-              const newTags = Array.from(new Set([...existingTags, ...params.tags]));
-
-              await storage.updateFileVersion(photoId, {
-                metadata: {
-                  ...metadata,
-                  ai: { ...(metadata as any).ai, aiTags: newTags }
-                } as any
-              });
+              await propagationService.changeTags(photo.id, { action: 'add', tags: params.tags }, propagate.data);
             }
           }
+          await operationJournal.record('tags', `Added tags to ${photoIds.length} photo(s)`, await operationJournal.tagChanges(before));
           break;
+        }
         default:
           return res.status(400).json({ message: "Unknown operation" });
       }
@@ -1941,80 +2770,175 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
-  // Analytics data
+  // Analytics data: uploads over the last `weeks`, tag usage over the last `months`, top `limit` cameras, people and tags
   app.get("/api/analytics", async (req, res) => {
     try {
-      const stats = await storage.getCollectionStats();
-
-      const analytics = {
-        uploadTrends: [
-          { date: '2025-01-20', count: 5 },
-          { date: '2025-01-21', count: 8 },
-          { date: '2025-01-22', count: 3 },
-          { date: '2025-01-23', count: 12 },
-          { date: '2025-01-24', count: 7 },
-          { date: '2025-01-25', count: stats.totalPhotos },
-        ],
-        tierDistribution: [
-          { tier: 'Silver', count: stats.silverCount, percentage: Math.round((stats.silverCount / stats.totalPhotos) * 100) || 0 },
-          { tier: 'Gold', count: stats.goldCount, percentage: Math.round((stats.goldCount / stats.totalPhotos) * 100) || 0 },
-        ],
-
-        topTags: [
-          { tag: 'landscape', count: 15 },
-          { tag: 'portrait', count: 12 },
-          { tag: 'nature', count: 8 }
-        ]
+      const clamp = (value: unknown, fallback: number, max: number) => {
+        const parsed = parseInt(value as string);
+        return Number.isFinite(parsed) ? Math.min(max, Math.max(1, parsed)) : fallback;
       };
+      const analytics = await storage.getAnalytics({
+        weeks: clamp(req.query.weeks, 12, 520),
+        months: clamp(req.query.months, 12, 240),
+        limit: clamp(req.query.limit, 10, 100),
+      });
+      const captureDates = await getLibraryCaptureDates();
 
-      res.json(analytics);
+      res.json({ ...analytics, yearlyCoverage: calculateYearlyCoverage(captureDates) });
     } catch (error) {
       console.error("Error fetching analytics:", error);
       res.status(500).json({ message: "Failed to fetch analytics" });
     }
   });
 
+  // Report significant gaps in capture dates (likely un-imported archives)
+  app.get("/api/analytics/coverage-gaps", async (req, res) => {
+    try {
+      const minGapDays = req.query.minGapDays ? Number(req.query.minGapDays) : 90;
+      const captureDates = await getLibraryCaptureDates();
 
+      res.json({
+        gaps: findCoverageGaps(captureDates, minGapDays),
+        yearlyCoverage: calculateYearlyCoverage(captureDates),
+        datedPhotos: captureDates.length,
+      });
+    } catch (error) {
+      console.error("Error analyzing coverage gaps:", error);
+      res.status(500).json({ message: "Failed to analyze coverage gaps" });
+    }
+  });
 
 
 
-  // Burst Photo Detection routes
-  app.get("/api/burst/analyze", async (req, res) => {
-    try {
-      // Get all photos from all tiers for cross-tier burst analysis
-      const allPhotos = await storage.getAllFileVersions();
-      const photosWithAssets = [];
 
-      for (const photo of allPhotos) {
-        const asset = await storage.getMediaAsset(photo.mediaAssetId);
-        if (asset) {
-          photosWithAssets.push({
-            ...photo,
-            mediaAsset: asset
-          });
-        }
-      }
 
-      const analysis = await burstPhotoService.analyzeBurstPhotos(photosWithAssets);
-      res.json(analysis);
+  // Photo stack routes
+  app.get("/api/stacks/:id", async (req, res) => {
+    try {
+      const stack = await photoStackService.getStack(req.params.id);
+      if (!stack) {
+        return res.status(404).json({ message: "Stack not found" });
+      }
+      res.json(stack);
     } catch (error) {
-      console.error("Burst analysis failed:", error);
-      res.status(500).json({ error: "Failed to analyze burst photos" });
+      console.error("Error fetching stack:", error);
+      res.status(500).json({ message: "Failed to fetch stack" });
     }
   });
 
-  app.post("/api/burst/process", async (req, res) => {
+  app.get("/api/photos/:id/stack", async (req, res) => {
     try {
-      const { selections, ungroupedPhotos } = req.body;
-
-      if (!Array.isArray(selections)) {
-        return res.status(400).json({ error: "Selections must be an array" });
+      const stack = await photoStackService.getStackForPhoto(req.params.id);
+      if (!stack) {
+        return res.status(404).json({ message: "Photo is not stacked" });
       }
+      res.json(stack);
+    } catch (error) {
+      console.error("Error fetching photo stack:", error);
+      res.status(500).json({ message: "Failed to fetch stack" });
+    }
+  });
+
+  app.post("/api/stacks", async (req, res) => {
+    try {
+      const parsed = createStackSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid stack", errors: parsed.error.errors });
+      }
+      res.status(201).json(await photoStackService.createStack(parsed.data));
+    } catch (error) {
+      if (error instanceof PhotoStackError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error creating stack:", error);
+      res.status(500).json({ message: "Failed to create stack" });
+    }
+  });
+
+  app.put("/api/stacks/:id/primary", async (req, res) => {
+    try {
+      const parsed = setStackPrimarySchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid stack primary", errors: parsed.error.errors });
+      }
+      res.json(await photoStackService.setStackPrimary(req.params.id, parsed.data.primaryId));
+    } catch (error) {
+      if (error instanceof PhotoStackError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error setting stack primary:", error);
+      res.status(500).json({ message: "Failed to set stack primary" });
+    }
+  });
+
+  // Unstack: the stack goes, its photos stay
+  app.delete("/api/stacks/:id", async (req, res) => {
+    try {
+      await photoStackService.unstack(req.params.id);
+      res.status(204).send();
+    } catch (error) {
+      if (error instanceof PhotoStackError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error removing stack:", error);
+      res.status(500).json({ message: "Failed to unstack" });
+    }
+  });
+
+  // Burst Photo Detection routes
+  app.get("/api/burst/analyze", async (req, res) => {
+    try {
+      // Detect again so new imports are grouped; unchanged bursts keep their IDs
+      const analysis = await burstPhotoService.detectBursts();
+      res.json(analysis);
+    } catch (error) {
+      console.error("Burst analysis failed:", error);
+      res.status(500).json({ error: "Failed to analyze burst photos" });
+    }
+  });
+
+  app.get("/api/bursts/:id", async (req, res) => {
+    try {
+      const burst = await burstPhotoService.getBurst(req.params.id);
+      if (!burst) {
+        return res.status(404).json({ message: "Burst not found" });
+      }
+      res.json(burst);
+    } catch (error) {
+      console.error("Error fetching burst:", error);
+      res.status(500).json({ message: "Failed to fetch burst" });
+    }
+  });
+
+  // Keep the sharpest frame of a burst, optionally stacking the rest under it
+  app.post("/api/bursts/:id/select-best", async (req, res) => {
+    try {
+      const parsed = selectBurstBestSchema.safeParse(req.body ?? {});
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid burst selection", errors: parsed.error.errors });
+      }
+      res.json(await burstPhotoService.selectBest(req.params.id, parsed.data));
+    } catch (error) {
+      if (error instanceof BurstError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error selecting best burst frame:", error);
+      res.status(500).json({ message: "Failed to select best frame" });
+    }
+  });
+
+  app.post("/api/burst/process", async (req, res) => {
+    try {
+      const { selections, ungroupedPhotos } = req.body;
+
+      if (!Array.isArray(selections)) {
+        return res.status(400).json({ error: "Selections must be an array" });
+      }
+
+      let processed = 0;
+      let promoted = 0;
+      const errors: string[] = [];
 
-      let processed = 0;
-      let promoted = 0;
-      const errors: string[] = [];
-
       // Process burst group selections
       for (const selection of selections) {
         const { groupId, selectedPhotoIds } = selection;
@@ -2036,7 +2960,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
                 continue;              }
 
               // Run AI analysis for metadata update only
-              const aiMetadata = await aiService.analyzeImage(photo.filePath, "openai");
+              const aiMetadata = await aiService.analyzeImage(photo.filePath);
               const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
 
               // Detect faces
@@ -2053,7 +2977,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
               let eventName: string | undefined;
               if (photoDate) {
                 try {
-                  const detectedEvents = await eventDetectionService.detectEvents(photoDate);
+                  const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
                   if (detectedEvents.length > 0) {
                     const bestEvent = detectedEvents.reduce((max, event) => 
                       event.confidence > max.confidence ? event : max
@@ -2074,13 +2998,14 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
                 ai: enhancedMetadata,
               };
 
-              await storage.updateFileVersion(photo.id, {
+              const updatedPhoto = await storage.updateFileVersion(photo.id, {
                 metadata: combinedMetadata as any,
                 aiShortDescription: enhancedMetadata.shortDescription,
                 eventType: eventType || undefined,
                 eventName: eventName || undefined,
                 isReviewed: false, // Reset review status
               });
+              await systemAlbumService.processPhoto(updatedPhoto);
 
               // Update faces
               await storage.deleteFacesByPhoto(photo.id);
@@ -2090,6 +3015,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
                   boundingBox: face.boundingBox,
                   confidence: face.confidence,
                   embedding: face.embedding,
+                  embeddingModel: face.embeddingModel ?? null,
+                  detector: face.detector ?? null,
                   personId: face.personId || null,
                 });
               }
@@ -2108,7 +3035,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             }
 
             // Run AI analysis
-            const aiMetadata = await aiService.analyzeImage(photo.filePath, "openai");
+            const aiMetadata = await aiService.analyzeImage(photo.filePath);
             const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
 
             // Get naming pattern from settings
@@ -2142,7 +3069,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             // Copy to silver tier
             const photoWithAsset = { ...photo, mediaAsset: mediaAsset };
             const photoDate = extractPhotoDate(photoWithAsset);
-            const silverPath = await fileManager.copyToSilver(photo.filePath, newFilename, photoDate);
+            const silverPath = await fileManager.copyToSilver(photo.filePath, newFilename, photoDate, photo.fileHash);
 
             // Detect faces
            const faceDetectionResult = await faceDetectionService.detectFaces(photo.filePath);
@@ -2153,7 +3080,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             let eventName: string | undefined;
             if (photoDate) {
               try {
-                const detectedEvents = await eventDetectionService.detectEvents(photoDate);
+                const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
                 if (detectedEvents.length > 0) {
                   // Use the highest confidence event
                   const bestEvent = detectedEvents.reduce((max, event) => 
@@ -2182,12 +3109,15 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
               fileHash: photo.fileHash,
               fileSize: photo.fileSize,
               mimeType: photo.mimeType,
+              width: photo.width,
+              height: photo.height,
               metadata: combinedMetadata as any,
               aiShortDescription: enhancedMetadata.shortDescription,
               eventType: eventType || undefined,
               eventName: eventName || undefined,
               isReviewed: false,
             });
+            await systemAlbumService.processPhoto(silverVersion);
 
             // Save detected faces
             for (const face of detectedFaces) {
@@ -2196,6 +3126,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
                 boundingBox: face.boundingBox,
                 confidence: face.confidence,
                 embedding: face.embedding,
+                embeddingModel: face.embeddingModel ?? null,
+                detector: face.detector ?? null,
                 personId: face.personId || null,
               });
             }
@@ -2206,11 +3138,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             });
 
             // Log promotion
-            await storage.createAssetHistory({
-              mediaAssetId: photo.mediaAssetId,
-              action: 'PROMOTED',
-              details: 'Promoted from Bronze to Silver tier via burst selection',
-            });
+            await provenanceService.recordAiAnalysis(silverVersion, 'Promoted from Bronze to Silver tier via burst selection', 'PROMOTED');
 
             promoted++;
           } catch (error: any) {
@@ -2219,30 +3147,20 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         }
 
         // Mark remaining photos in group as processed (but keep in bronze)
-        // Handle smart demotion logic here for mixed-tier groups
         try {
-          // Find all photos in this burst group from the analysis to mark non-selected ones as processed
-          const allPhotos = await storage.getAllFileVersions();
-          const photosWithAssets = [];
-          for (const photo of allPhotos) {
-            const asset = await storage.getMediaAsset(photo.mediaAssetId);
-            if (asset) {
-              photosWithAssets.push({ ...photo, mediaAsset: asset });
-            }
-          }
-          const analysis = await burstPhotoService.analyzeBurstPhotos(photosWithAssets);
-          const group = analysis.groups.find(g => g.id === groupId);
+          const group = await burstPhotoService.getBurst(groupId);
 
           if (group) {
             for (const groupPhoto of group.photos) {
-              // Get the actual file version to check tier
-              const fileVersion = await storage.getFileVersion(groupPhoto.id);
-              if (!selectedPhotoIds.includes(groupPhoto.id) && fileVersion?.tier === 'bronze') {
+              if (!selectedPhotoIds.includes(groupPhoto.id) && groupPhoto.tier === 'bronze') {
                 await storage.updateFileVersion(groupPhoto.id, {
                   processingState: 'processed'
                 });
               }
             }
+            if (selectedPhotoIds.length > 0) {
+              await burstPhotoService.resolve(group.id, selectedPhotoIds[0]);
+            }
           }
         } catch (error) {
           console.error('Failed to update group processing states:', error);
@@ -2260,7 +3178,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             }
 
             // Process same as grouped photos
-            const aiMetadata = await aiService.analyzeImage(photo.filePath, "openai");
+            const aiMetadata = await aiService.analyzeImage(photo.filePath);
             const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
 
             const namingPatternSetting = await storage.getSettingByKey('silver_naming_pattern');
@@ -2291,7 +3209,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
             const photoWithAsset = { ...photo, mediaAsset: mediaAsset };
             const photoDate = extractPhotoDate(photoWithAsset);
-            const silverPath = await fileManager.copyToSilver(photo.filePath, newFilename, photoDate);
+            const silverPath = await fileManager.copyToSilver(photo.filePath, newFilename, photoDate, photo.fileHash);
 
             // Face detection
             const faceDetectionResult = await faceDetectionService.detectFaces(photo.filePath);
@@ -2302,7 +3220,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             let eventName: string | undefined;
             if (photoDate) {
               try {
-                const detectedEvents = await eventDetectionService.detectEvents(photoDate);
+                const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
                 if (detectedEvents.length > 0) {
                   // Use the highest confidence event
                   const bestEvent = detectedEvents.reduce((max, event) => 
@@ -2334,12 +3252,15 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
               fileHash: photo.fileHash,
               fileSize: photo.fileSize,
               mimeType: photo.mimeType,
+              width: photo.width,
+              height: photo.height,
               metadata: combinedMetadata as any,
               aiShortDescription: enhancedMetadata.shortDescription,
               eventType: eventType || undefined,
               eventName: eventName || undefined,
               isReviewed: false,
             });
+            await systemAlbumService.processPhoto(silverVersion);
 
             for (const face of detectedFaces) {
               await storage.createFace({
@@ -2347,6 +3268,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
                 boundingBox: face.boundingBox,
                 confidence: face.confidence,
                 embedding: face.embedding,
+                embeddingModel: face.embeddingModel ?? null,
+                detector: face.detector ?? null,
                 personId: face.personId || null,
               });
             }
@@ -2356,11 +3279,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
               processingState: 'promoted'
             });
 
-            await storage.createAssetHistory({
-              mediaAssetId: photo.mediaAssetId,
-              action: 'PROMOTED',
-              details: 'Promoted from Bronze to Silver tier via burst processing',
-            });
+            await provenanceService.recordAiAnalysis(silverVersion, 'Promoted from Bronze to Silver tier via burst processing', 'PROMOTED');
 
             promoted++;
           } catch (error: any) {
@@ -2381,68 +3300,131 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
   // Advanced search endpoint
   app.post("/api/photos/search", async (req, res) => {
     try {
-      const { filters = {}, sort = { field: 'createdAt', direction: 'desc' }, limit = 50, offset = 0 } = req.body;
+      const { filters = {}, sort = { field: 'takenAt', direction: 'desc' }, limit = 50, offset = 0, cursor, expandStacks } = req.body;
+
+      const results = await advancedSearch.searchPhotos(
+        filters,
+        sort,
+        Number(limit),
+        Number(offset),
+        photoStackService.shouldExpand(expandStacks),
+        typeof cursor === 'string' ? cursor : undefined
+      );
+      res.json(results);
+    } catch (error) {
+      console.error("Error in advanced search:", error);
+      res.status(500).json({ message: "Search failed" });
+    }
+  });
 
-      // For now, use the existing silver photos endpoint as a fallback
-      // TODO: Implement full advanced search functionality
-      const allPhotos = await storage.getFileVersionsByTier('silver');
-      const photosWithAssets = [];
+  // Count the photos a search would list, for sizing virtual scrolling
+  app.post("/api/photos/count", async (req, res) => {
+    try {
+      const { filters = {}, expandStacks } = req.body;
+      const count = await advancedSearch.countPhotos(filters, photoStackService.shouldExpand(expandStacks));
+      res.json({ count });
+    } catch (error) {
+      console.error("Error counting photos:", error);
+      res.status(500).json({ message: "Failed to count photos" });
+    }
+  });
 
-      for (const photo of allPhotos) {
-        const asset = await storage.getMediaAsset(photo.mediaAssetId);
-        if (asset) {
-          photosWithAssets.push({
-            ...photo,
-            mediaAsset: asset
-          });
-        }
+  // Full-text search: all words must match, "quoted phrases" match in order, word* matches prefixes
+  app.get("/api/search/text", async (req, res) => {
+    try {
+      const query = textSearchQuerySchema.safeParse(req.query);
+      if (!query.success) {
+        return res.status(400).json({ message: "Invalid search query", errors: query.error.errors });
       }
-
-      // Simple filtering by query if provided
-      let filteredPhotos = photosWithAssets;
-      if (filters.query) {
-        const query = filters.query.toLowerCase();
-        filteredPhotos = photosWithAssets.filter(photo => {
-          const filename = photo.mediaAsset.originalFilename.toLowerCase();
-          const location = (photo.location || '').toLowerCase();
-          const keywords = (photo.keywords || []).join(' ').toLowerCase();
-          return filename.includes(query) || location.includes(query) || keywords.includes(query);
-        });
+      res.json(await photoTextSearchService.search(query.data.q, query.data.limit, query.data.offset));
+    } catch (error) {
+      if (error instanceof TextSearchError) {
+        return res.status(400).json({ message: error.message });
       }
+      console.error("Error in text search:", error);
+      res.status(500).json({ message: "Search failed" });
+    }
+  });
+
+  // Rebuild every photo's full-text document
+  app.post("/api/search/text/rebuild", async (req, res) => {
+    try {
+      res.json({ indexed: await photoTextSearchService.rebuild() });
+    } catch (error) {
+      console.error("Error rebuilding search index:", error);
+      res.status(500).json({ message: "Failed to rebuild search index" });
+    }
+  });
+
+  // Built-in technical searches (high ISO, long exposures, ...)
+  app.get("/api/search/presets", async (req, res) => {
+    res.json(searchPresetService.list());
+  });
 
-      // Apply tier filter
-      if (filters.tier) {
-        filteredPhotos = filteredPhotos.filter(photo => photo.tier === filters.tier);
+  // Run a preset; the response includes its filters for use with /api/photos/search
+  app.get("/api/search/presets/:id", async (req, res) => {
+    try {
+      const limit = parseInt(req.query.limit as string) || 50;
+      const offset = parseInt(req.query.offset as string) || 0;
+      const results = await searchPresetService.run(
+        req.params.id,
+        limit,
+        offset,
+        photoStackService.shouldExpand(req.query.expandStacks)
+      );
+      if (!results) {
+        return res.status(404).json({ message: "Search preset not found" });
       }
+      res.json(results);
+    } catch (error) {
+      console.error("Error running search preset:", error);
+      res.status(500).json({ message: "Search failed" });
+    }
+  });
 
-      // Apply rating filter
-      if (filters.rating?.min !== undefined || filters.rating?.max !== undefined) {
-        filteredPhotos = filteredPhotos.filter(photo => {
-          const rating = photo.rating || 0;
-          if (filters.rating?.min !== undefined && rating < filters.rating.min) return false;
-          if (filters.rating?.max !== undefined && rating > filters.rating.max) return false;
-          return true;
-        });
+  // Slideshow: next photo in a continuous, filter-aware shuffle
+  app.post("/api/slideshow/:sessionId/next", async (req, res) => {
+    try {
+      const parsed = slideshowNextSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid slideshow request", errors: parsed.error.errors });
       }
+      const { filters, repeatWindow, expandStacks } = parsed.data;
+      const photo = await slideshowService.nextPhoto(
+        req.params.sessionId,
+        filters,
+        repeatWindow,
+        photoStackService.shouldExpand(expandStacks)
+      );
+      if (!photo) {
+        return res.status(404).json({ message: "No photos match the slideshow filters" });
+      }
+      res.json(photo);
+    } catch (error) {
+      console.error("Error getting next slideshow photo:", error);
+      res.status(500).json({ message: "Failed to get next slideshow photo" });
+    }
+  });
 
-      // Simple facets
-      const facets = {
-        tiers: { silver: filteredPhotos.length },
-        ratings: {},
-        mimeTypes: {},
-        eventTypes: {}
-      };
+  app.delete("/api/slideshow/:sessionId", async (req, res) => {
+    slideshowService.endSession(req.params.sessionId);
+    res.json({ success: true });
+  });
 
-      const results = {
-        photos: filteredPhotos.slice(offset, offset + limit),
-        totalCount: filteredPhotos.length,
-        facets
-      };
+  // Related photos strip: blended visual, semantic, people and time similarity
+  app.get("/api/photos/:id/related", async (req, res) => {
+    try {
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
 
-      res.json(results);
+      const limit = req.query.limit ? Number(req.query.limit) : 12;
+      const related = await advancedSearch.findRelatedPhotos(req.params.id, limit);
+      res.json(related);
     } catch (error) {
-      console.error("Error in advanced search:", error);
-      res.status(500).json({ message: "Search failed" });
+      console.error("Error finding related photos:", error);
+      res.status(500).json({ message: "Failed to find related photos" });
     }
   });
 
@@ -2469,19 +3451,93 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       if (rating < 0 || rating > 5) {
         return res.status(400).json({ message: "Rating must be between 0 and 5" });
       }
+      const propagate = propagationScopeSchema.optional().safeParse(req.body.propagate);
+      if (!propagate.success) {
+        return res.status(400).json({ message: "Invalid propagation options", errors: propagate.error.errors });
+      }
 
-      await storage.updateFileVersion(req.params.id, { rating });
-      res.json({ success: true });
+      const propagatedTo = await propagationService.setRating(req.params.id, rating, propagate.data);
+      res.json({ success: true, propagatedTo });
     } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
       console.error("Error updating rating:", error);
       res.status(500).json({ message: "Failed to update rating" });
     }
   });
 
+  // Mark a photo as a pick or a reject, or clear the flag with null
+  app.patch("/api/photos/:id/flag", async (req, res) => {
+    try {
+      const parsed = pickFlagSchema.safeParse(req.body.flag);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "flag must be pick, reject or null", errors: parsed.error.errors });
+      }
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      assertUnlocked(photo, 'edit');
+      const updated = await storage.updateFileVersion(photo.id, { pickFlag: parsed.data });
+      smartCollectionService.photosChanged([photo.id]);
+      res.json(updated);
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      console.error("Error updating pick flag:", error);
+      res.status(500).json({ message: "Failed to update pick flag" });
+    }
+  });
+
+  // Rate many photos at once
+  app.post("/api/photos/batch-rating", async (req, res) => {
+    try {
+      const parsed = batchRatingSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid batch rating", errors: parsed.error.errors });
+      }
+      const { photoIds, rating, propagate } = parsed.data;
+      const results = await batchOperationService.applyOperations(photoIds, [{ type: 'rate', rating }], propagate);
+      res.json({
+        updated: results.filter(result => result.success).length,
+        failed: results.filter(result => !result.success).length,
+        results,
+      });
+    } catch (error) {
+      console.error("Error batch rating photos:", error);
+      res.status(500).json({ message: "Failed to rate photos" });
+    }
+  });
+
+  // Flag many photos as picks or rejects at once, or clear their flags with null
+  app.post("/api/photos/batch-flag", async (req, res) => {
+    try {
+      const parsed = batchPickFlagSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid batch flag", errors: parsed.error.errors });
+      }
+      const results = await batchOperationService.applyOperations(parsed.data.photoIds, [{ type: 'pick', flag: parsed.data.flag }]);
+      res.json({
+        updated: results.filter(result => result.success).length,
+        failed: results.filter(result => !result.success).length,
+        results,
+      });
+    } catch (error) {
+      console.error("Error batch flagging photos:", error);
+      res.status(500).json({ message: "Failed to flag photos" });
+    }
+  });
+
   // Update photo metadata
   app.patch("/api/photos/:id/metadata", async (req, res) => {
     try {
       const { keywords, eventType, eventName, location } = req.body;
+      const propagate = propagationScopeSchema.optional().safeParse(req.body.propagate);
+      if (!propagate.success) {
+        return res.status(400).json({ message: "Invalid propagation options", errors: propagate.error.errors });
+      }
 
       const updates: any = {};
       if (keywords !== undefined) updates.keywords = keywords;
@@ -2489,9 +3545,15 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       if (eventName !== undefined) updates.eventName = eventName;
       if (location !== undefined) updates.location = location;
 
-      await storage.updateFileVersion(req.params.id, updates);
-      res.json({ success: true });
+      const photo = await storage.updateFileVersion(req.params.id, updates);
+      const propagatedTo = keywords !== undefined && photo
+        ? await propagationService.propagateKeywords(photo, keywords, propagate.data)
+        : [];
+      res.json({ success: true, propagatedTo });
     } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
       console.error("Error updating metadata:", error);
       res.status(500).json({ message: "Failed to update metadata" });
     }
@@ -2524,6 +3586,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         fileHash: photo.fileHash,
         fileSize: photo.fileSize,
         mimeType: photo.mimeType,
+        width: photo.width,
+        height: photo.height,
         metadata: photo.metadata as any,
         isReviewed: photo.isReviewed,
         rating: photo.rating,
@@ -2567,8 +3631,9 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
   // Update smart collections
   app.post("/api/collections/smart/update", async (req, res) => {
     try {
-      await advancedSearch.updateSmartCollections();
-      res.json({ success: true });
+      const results = await smartCollectionService.evaluateAll();
+      await personAlbumService.refreshAll();
+      res.json({ success: true, results });
     } catch (error) {
       console.error("Error updating smart collections:", error);
       res.status(500).json({ message: "Failed to update smart collections" });
@@ -2591,7 +3656,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       const asset = await storage.getMediaAsset(photo.mediaAssetId);
       const photoWithAsset = { ...photo, mediaAsset: asset };
       const photoDate = extractPhotoDate(photoWithAsset);
-      const goldPath = await fileManager.copyToGold(photo.filePath, photoDate);
+      const goldPath = await fileManager.copyToGold(photo.filePath, photoDate, photo.fileHash);
 
       // Create Gold file version with embedded metadata
       const goldVersion = await storage.createFileVersion({
@@ -2601,6 +3666,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         fileHash: photo.fileHash,
         fileSize: photo.fileSize,
         mimeType: photo.mimeType,
+        width: photo.width,
+        height: photo.height,
         metadata: photo.metadata as any,
         isReviewed: true,
       });
@@ -2611,6 +3678,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         action: 'PROMOTED',
         details: 'Promoted from Silver to Gold tier',
       });
+      await operationJournal.record('promotion', 'Promoted 1 photo to Gold', [{ type: 'promotion', photoId: goldVersion.id }]);
 
       res.json(goldVersion);
     } catch (error) {
@@ -2619,6 +3687,40 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Apply an ordered list of operations (tag, rate, album, tier, flag) to many photos,
+  // atomically per photo
+  app.post("/api/photos/apply-operations", async (req, res) => {
+    try {
+      const parsed = applyOperationsSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid operations", errors: parsed.error.errors });
+      }
+
+      const { photoIds, operations, propagate } = parsed.data;
+      const tagsBefore = await operationJournal.captureTags(photoIds, propagate);
+      const albumsBefore = await operationJournal.captureAlbums(
+        operations.flatMap(operation => operation.type === 'album' ? [operation.collectionId] : [])
+      );
+      const results = await batchOperationService.applyOperations(photoIds, operations, propagate);
+
+      const promoted = results.filter(result => result.success && result.resultPhotoId && result.resultPhotoId !== result.photoId);
+      const kind: JournalKind = promoted.length > 0 ? 'promotion' : operations.some(operation => operation.type === 'tag') ? 'tags' : 'album';
+      await operationJournal.record(kind, `Applied ${operations.length} operation(s) to ${photoIds.length} photo(s)`, [
+        ...promoted.map(result => ({ type: 'promotion' as const, photoId: result.resultPhotoId! })),
+        ...(await operationJournal.tagChanges(tagsBefore)),
+        ...(await operationJournal.albumChanges(albumsBefore)),
+      ]);
+      res.json({
+        applied: results.filter(result => result.success).length,
+        failed: results.filter(result => !result.success).length,
+        results,
+      });
+    } catch (error) {
+      console.error("Error applying photo operations:", error);
+      res.status(500).json({ message: error instanceof Error ? error.message : "Failed to apply photo operations" });
+    }
+  });
+
   // Manual AI processing for Silver tier photos
   app.post("/api/photos/:id/process-ai", async (req, res) => {
     try {
@@ -2631,6 +3733,10 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         return res.status(400).json({ message: "Only Silver tier photos can be AI processed" });
       }
 
+      if (photo.isLocked) {
+        return res.status(423).json({ message: "Photo is locked; unlock it before AI processing", photoId: photo.id, action: 'edit' });
+      }
+
       if (!photo.mimeType.startsWith('image/')) {
         return res.status(400).json({ message: "AI processing only supports images" });
       }
@@ -2682,7 +3788,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       // Run AI analysis with people context
       const aiMetadata = await aiService.analyzeImageWithPeopleContext(
         photo.filePath, 
-        "openai", 
+        undefined, 
         peopleContext
       );
       const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
@@ -2695,7 +3801,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       const photoDate = extractPhotoDate(photoWithAsset);
       if (photoDate) {
         try {
-          const detectedEvents = await eventDetectionService.detectEvents(photoDate);
+          const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
           if (detectedEvents.length > 0) {
             const bestEvent = detectedEvents.reduce((max, event) => 
               event.confidence > max.confidence ? event : max
@@ -2716,20 +3822,17 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         ai: enhancedMetadata,
       };
 
-      await storage.updateFileVersion(photo.id, {
+      const updatedPhoto = await storage.updateFileVersion(photo.id, {
         metadata: combinedMetadata as any,
         aiShortDescription: enhancedMetadata.shortDescription,
         eventType: eventType || undefined,
         eventName: eventName || undefined,
         isReviewed: false, // Reset review status since we have new AI data
       });
+      await systemAlbumService.processPhoto(updatedPhoto);
 
       // Log AI processing
-      await storage.createAssetHistory({
-        mediaAssetId: photo.mediaAssetId,
-        action: 'AI_PROCESSED',
-        details: 'AI analysis completed with enhanced metadata and descriptions',
-      });
+      await provenanceService.recordAiAnalysis(photo, 'AI analysis completed with enhanced metadata and descriptions');
 
       res.json({ 
         success: true, 
@@ -2742,8 +3845,68 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
-  // Batch AI processing for multiple Silver tier photos
+  // Batch AI processing for multiple Silver tier photos; queued and run by the AI batch scheduler
   app.post("/api/photos/batch-ai-process", async (req, res) => {
+    try {
+      const { photoIds } = req.body;
+
+      if (!Array.isArray(photoIds) || photoIds.length === 0 || !photoIds.every(id => typeof id === 'string')) {
+        return res.status(400).json({ message: "photoIds array is required" });
+      }
+
+      const { queued, total } = await aiBatchScheduler.enqueue(photoIds);
+      res.status(202).json({ queued, total, status: await aiBatchScheduler.getStatus() });
+    } catch (error) {
+      console.error("Error in batch AI processing:", error);
+      res.status(500).json({ message: "Failed to batch process photos with AI" });
+    }
+  });
+
+  app.get("/api/ai/batch", async (req, res) => {
+    try {
+      res.json(await aiBatchScheduler.getStatus());
+    } catch (error) {
+      console.error("Error getting AI batch status:", error);
+      res.status(500).json({ message: "Failed to get AI batch status" });
+    }
+  });
+
+  app.put("/api/ai/batch/settings", async (req, res) => {
+    try {
+      const parsed = aiScheduleSettingsSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid AI batch settings", errors: parsed.error.errors });
+      }
+      res.json(await aiBatchScheduler.updateSettings(parsed.data));
+    } catch (error) {
+      console.error("Error updating AI batch settings:", error);
+      res.status(500).json({ message: "Failed to update AI batch settings" });
+    }
+  });
+
+  // Pause stops after the photos in progress; the rest stay queued
+  app.post("/api/ai/batch/pause", async (req, res) => {
+    aiBatchScheduler.pause();
+    res.json(await aiBatchScheduler.getStatus());
+  });
+
+  app.post("/api/ai/batch/resume", async (req, res) => {
+    aiBatchScheduler.resume();
+    res.json(await aiBatchScheduler.getStatus());
+  });
+
+  app.delete("/api/ai/batch/queue", async (req, res) => {
+    try {
+      const removed = await aiBatchScheduler.clearQueue();
+      res.json({ success: true, removed });
+    } catch (error) {
+      console.error("Error clearing AI batch queue:", error);
+      res.status(500).json({ message: "Failed to clear AI batch queue" });
+    }
+  });
+
+  // Batch process photos from Bronze to Silver
+  app.post("/api/photos/batch-process", async (req, res) => {
     try {
       const { photoIds } = req.body;
 
@@ -2753,191 +3916,69 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
       let processed = 0;
       const errors = [];
+      const operation = operationRegistry.start('face_detection', `Processing ${photoIds.length} photos with face detection`, requestedOperationId(req));
+
+      for (let index = 0; index < photoIds.length; index++) {
+        // Stop between photos so each one is either fully processed or untouched
+        operation.checkpoint({ remainingPhotoIds: photoIds.slice(index) });
+        if (operation.token.isCancelled) break;
+        operation.progress(index, photoIds.length);
+        const photoId = photoIds[index];
 
-      for (const photoId of photoIds) {
         try {
           const photo = await storage.getFileVersion(photoId);
           if (!photo || photo.tier !== 'silver') {
             continue;
           }
 
-          if (!photo.mimeType.startsWith('image/')) {
+          // Check if this asset already has a Silver version
+          const existingSilver = await storage.getFileVersionsByAsset(photo.mediaAssetId);
+          const hasSilver = existingSilver.some(version => version.tier === 'silver');
+          if (hasSilver) {
             continue;
           }
 
-          // Check if already has AI processing
-          const existingAI = (photo.metadata as any)?.ai;
-          if (existingAI?.shortDescription) {
-            continue; // Skip already processed photos
+          // Check if file is an image
+          if (!photo.mimeType.startsWith('image/')) {
+            continue;
           }
 
-          // Get people context (same as single photo processing)
-          const existingFaces = await storage.getFacesByPhoto(photo.id);
-          const peopleContext = [];
-
-          for (const face of existingFaces) {
-            if (face.personId) {
-              const person = await storage.getPerson(face.personId);
-              if (person) {
-                let ageInPhoto = null;
-                const asset = await storage.getMediaAsset(photo.mediaAssetId);
-                const photoWithAsset = { ...photo, mediaAsset: asset };
-                const photoDate = extractPhotoDate(photoWithAsset);
+          // Run AI analysis with OpenAI as preferred provider
+          const aiMetadata = await aiService.analyzeImage(photo.filePath);
+          const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
 
-                if (person.birthdate && photoDate) {
-                  ageInPhoto = eventDetectionService.calculateAgeInPhoto(new Date(person.birthdate), photoDate);
-                }
+          // Get naming pattern from settings
+          const namingPatternSetting = await storage.getSettingByKey('silver_naming_pattern');
+          const customPatternSetting = await storage.getSettingByKey('custom_naming_pattern');
+          const namingPattern = namingPatternSetting?.value || 'datetime';
+          const customPattern = customPatternSetting?.value || '';
 
-                const relationships = await storage.getRelationshipsByPerson(person.id);
-                const relationshipInfo = relationships.map(rel => {
-                  const otherPersonId = rel.person1Id === person.id ? rel.person2Id : rel.person1Id;
-                  return {
-                    type: rel.relationshipType,
-                    otherPersonId: otherPersonId
-                  };
-                });
+          // Get asset for both filename generation and photo date extraction
+          const asset = await storage.getMediaAsset(photo.mediaAssetId);
 
-                peopleContext.push({
-                  name: person.name,
-                  ageInPhoto: ageInPhoto,
-                  relationships: relationshipInfo,
-                  boundingBox: face.boundingBox
-                });
-              }
-            }
-          }
-
-          // Run AI analysis
-          const aiMetadata = await aiService.analyzeImageWithPeopleContext(
-            photo.filePath, 
-            "openai", 
-            peopleContext
-          );
-          const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
-
-          // Event detection
-          let eventType: string | undefined;
-          let eventName: string | undefined;
-          const asset = await storage.getMediaAsset(photo.mediaAssetId);
-          const photoWithAsset = { ...photo, mediaAsset: asset };
-          const photoDate = extractPhotoDate(photoWithAsset);
-          if (photoDate) {
-            try {
-              const detectedEvents = await eventDetectionService.detectEvents(photoDate);
-              if (detectedEvents.length > 0) {
-                const bestEvent = detectedEvents.reduce((max, event) => 
-                  event.confidence > max.confidence ? event : max
-                );
-                if (bestEvent.confidence >= 80) {
-                  eventType = bestEvent.eventType;
-                  eventName = bestEvent.eventName;
-                }
-              }
-            } catch (error) {
-              console.error('Event detection failed for photo:', photoId, error);
-            }
-          }
-
-          // Update metadata
-          const combinedMetadata = {
-            ...(photo.metadata || {}),
-            ai: enhancedMetadata,
-          };
-
-          await storage.updateFileVersion(photo.id, {
-            metadata: combinedMetadata as any,
-            aiShortDescription: enhancedMetadata.shortDescription,
-            eventType: eventType || undefined,
-            eventName: eventName || undefined,
-            isReviewed: false,
-          });
-
-          await storage.createAssetHistory({
-            mediaAssetId: photo.mediaAssetId,
-            action: 'AI_PROCESSED',
-            details: 'Batch AI processing completed',
-          });
-
-          processed++;
-        } catch (error: any) {
-          errors.push({ photoId, error: error.message });
-        }
-      }
-
-      res.json({ processed, errors });
-    } catch (error) {
-      console.error("Error in batch AI processing:", error);
-      res.status(500).json({ message: "Failed to batch process photos with AI" });
-    }
-  });
-
-  // Batch process photos from Bronze to Silver
-  app.post("/api/photos/batch-process", async (req, res) => {
-    try {
-      const { photoIds } = req.body;
-
-      if (!Array.isArray(photoIds) || photoIds.length === 0) {
-        return res.status(400).json({ message: "photoIds array is required" });
-      }
-
-      let processed = 0;
-      const errors = [];
-
-      for (const photoId of photoIds) {
-        try {
-          const photo = await storage.getFileVersion(photoId);
-          if (!photo || photo.tier !== 'silver') {
-            continue;
-          }
-
-          // Check if this asset already has a Silver version
-          const existingSilver = await storage.getFileVersionsByAsset(photo.mediaAssetId);
-          const hasSilver = existingSilver.some(version => version.tier === 'silver');
-          if (hasSilver) {
-            continue;
-          }
-
-          // Check if file is an image
-          if (!photo.mimeType.startsWith('image/')) {
-            continue;
-          }
-
-          // Run AI analysis with OpenAI as preferred provider
-          const aiMetadata = await aiService.analyzeImage(photo.filePath, "openai");
-          const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
-
-          // Get naming pattern from settings
-          const namingPatternSetting = await storage.getSettingByKey('silver_naming_pattern');
-          const customPatternSetting = await storage.getSettingByKey('custom_naming_pattern');
-          const namingPattern = namingPatternSetting?.value || 'datetime';
-          const customPattern = customPatternSetting?.value || '';
-
-          // Get asset for both filename generation and photo date extraction
-          const asset = await storage.getMediaAsset(photo.mediaAssetId);
-
-          // Generate new filename for Silver tier
-          let newFilename: string | undefined;
-          if (namingPattern !== 'original') {
-            const namingContext = {
-              aiMetadata: {
-                shortDescription: enhancedMetadata.shortDescription,
-                detectedObjects: enhancedMetadata.detectedObjects,
-                aiTags: enhancedMetadata.aiTags,
-              },
-              exifMetadata: (photo.metadata && typeof photo.metadata === 'object' && 'exif' in photo.metadata && typeof photo.metadata.exif === 'object')
-                ? (photo.metadata.exif as { dateTime?: string; dateTimeOriginal?: string; createDate?: string; camera?: string; lens?: string })
-                : undefined,
-              originalFilename: asset?.originalFilename || 'unknown.jpg',
-              tier: 'silver' as const
-            };
-            const finalPattern = namingPattern === 'custom' ? customPattern : namingPattern;
-            newFilename = await generateSilverFilename(namingContext, finalPattern);
+          // Generate new filename for Silver tier
+          let newFilename: string | undefined;
+          if (namingPattern !== 'original') {
+            const namingContext = {
+              aiMetadata: {
+                shortDescription: enhancedMetadata.shortDescription,
+                detectedObjects: enhancedMetadata.detectedObjects,
+                aiTags: enhancedMetadata.aiTags,
+              },
+              exifMetadata: (photo.metadata && typeof photo.metadata === 'object' && 'exif' in photo.metadata && typeof photo.metadata.exif === 'object')
+                ? (photo.metadata.exif as { dateTime?: string; dateTimeOriginal?: string; createDate?: string; camera?: string; lens?: string })
+                : undefined,
+              originalFilename: asset?.originalFilename || 'unknown.jpg',
+              tier: 'silver' as const
+            };
+            const finalPattern = namingPattern === 'custom' ? customPattern : namingPattern;
+            newFilename = await generateSilverFilename(namingContext, finalPattern);
           }
 
           // Copy file to Silver tier with new filename
           const photoWithAsset = { ...photo, mediaAsset: asset };
           const photoDate = extractPhotoDate(photoWithAsset);
-          const silverPath = await fileManager.copyToSilver(photo.filePath, newFilename, photoDate);
+          const silverPath = await fileManager.copyToSilver(photo.filePath, newFilename, photoDate, photo.fileHash);
 
           // Detect faces in the image
           const faceDetectionResult = await faceDetectionService.detectFaces(photo.filePath);
@@ -2948,7 +3989,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
           let eventName: string | undefined;
           if (photoDate) {
             try {
-              const detectedEvents = await eventDetectionService.detectEvents(photoDate);
+              const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
               if (detectedEvents.length > 0) {
                 const bestEvent = detectedEvents.reduce((max, event) => 
                   event.confidence > max.confidence ? event : max
@@ -2980,12 +4021,15 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             fileHash: photo.fileHash,
             fileSize: photo.fileSize,
             mimeType: photo.mimeType,
+            width: photo.width,
+            height: photo.height,
             metadata: combinedMetadata as any,
             aiShortDescription: enhancedMetadata.shortDescription,
             eventType: eventType || undefined,
             eventName: eventName || undefined,
             isReviewed: false,
           });
+          await systemAlbumService.processPhoto(silverVersion);
 
           // Save detected faces to database
           for (const face of detectedFaces) {
@@ -2994,16 +4038,14 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
               boundingBox: face.boundingBox,
               confidence: face.confidence,
               embedding: face.embedding,
+              embeddingModel: face.embeddingModel ?? null,
+              detector: face.detector ?? null,
               personId: face.personId || null,
             });
           }
 
           // Log promotion
-          await storage.createAssetHistory({
-            mediaAssetId: photo.mediaAssetId,
-            action: 'PROMOTED',
-            details: 'Batch promoted from Bronze to Silver tier with AI processing',
-          });
+          await provenanceService.recordAiAnalysis(silverVersion, 'Batch promoted from Bronze to Silver tier with AI processing', 'PROMOTED');
 
           processed++;
         } catch (error: any) {
@@ -3011,7 +4053,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         }
       }
 
-      res.json({ processed, errors });
+      operation.finish();
+      res.json({ processed, errors, operationId: operation.id, cancelled: operation.token.isCancelled });
     } catch (error) {
       console.error("Error in batch processing:", error);
       res.status(500).json({ message: "Failed to batch process photos" });
@@ -3029,6 +4072,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
       let promoted = 0;
       const errors = [];
+      const goldVersionIds: string[] = [];
 
       for (const photoId of photoIds) {
         try {
@@ -3041,16 +4085,18 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
           const asset = await storage.getMediaAsset(photo.mediaAssetId);
           const photoWithAsset = { ...photo, mediaAsset: asset };
           const photoDate = extractPhotoDate(photoWithAsset);
-          const goldPath = await fileManager.copyToGold(photo.filePath, photoDate);
+          const goldPath = await fileManager.copyToGold(photo.filePath, photoDate, photo.fileHash);
 
           // Create Gold file version
-          await storage.createFileVersion({
+          const goldVersion = await storage.createFileVersion({
             mediaAssetId: photo.mediaAssetId,
             tier: 'gold',
             filePath: goldPath,
             fileHash: photo.fileHash,
             fileSize: photo.fileSize,
             mimeType: photo.mimeType,
+            width: photo.width,
+            height: photo.height,
             metadata: photo.metadata as any,
             isReviewed: true,
           });
@@ -3062,12 +4108,15 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             details: 'Batch promoted from Silver to Gold tier',
           });
 
+          goldVersionIds.push(goldVersion.id);
           promoted++;
         } catch (error: any) {
           errors.push({ photoId, error: error.message });
         }
       }
 
+      await operationJournal.record('promotion', `Promoted ${promoted} photo(s) to Gold`,
+        goldVersionIds.map(photoId => ({ type: 'promotion' as const, photoId })));
       res.json({ promoted, errors });
     } catch (error) {
       console.error("Error in batch promotion:", error);
@@ -3075,6 +4124,23 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Soft-delete: the file goes to the trash, restorable from /api/deleted until purged
+  app.delete("/api/photos/:id", async (req, res) => {
+    try {
+      if (!(await softDeleteService.delete('photo', req.params.id))) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      await operationJournal.record('deletion', 'Moved 1 photo to the trash', [{ type: 'deletion', kind: 'photo', id: req.params.id }]);
+      res.json({ success: true, message: "Photo moved to trash" });
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      console.error("Error deleting photo:", error);
+      res.status(500).json({ message: "Failed to delete photo" });
+    }
+  });
+
   // Demote photo to lower tier
   app.post("/api/photos/:id/demote", async (req, res) => {
     try {
@@ -3083,6 +4149,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         return res.status(404).json({ message: "Photo not found" });
       }
 
+      assertUnlocked(photo, 'demote');
+
       // Can only demote Gold to Silver or Silver to Bronze
       if (photo.tier === 'bronze') {
         return res.status(400).json({ message: "Cannot demote Bronze tier photos" });
@@ -3106,6 +4174,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
       // Delete the current higher tier version
       await storage.deleteFileVersion(photo.id);
+      await thumbnailService.removePhotoThumbnails(photo.id);
 
       // Log demotion
       await storage.createAssetHistory({
@@ -3127,6 +4196,9 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         activeVersion: { ...targetVersion, mediaAsset: enhancedAsset }
       });
     } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
       console.error("Error demoting photo:", error);
       res.status(500).json({ message: "Failed to demote photo" });
     }
@@ -3144,6 +4216,10 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         return res.status(400).json({ message: "Use /process endpoint for Bronze photos" });
       }
 
+      if (photo.isLocked) {
+        return res.status(423).json({ message: "Photo is locked; unlock it before reprocessing", photoId: photo.id, action: 'edit' });
+      }
+
       if (!photo.mimeType.startsWith('image/')) {
         return res.status(400).json({ message: "AI reprocessing only supports images" });
       }
@@ -3190,7 +4266,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       // Re-run AI analysis with enhanced context about people
       const aiMetadata = await aiService.analyzeImageWithPeopleContext(
         photo.filePath, 
-        "openai", 
+        undefined, 
         peopleContext
       );
       const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
@@ -3206,7 +4282,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       const photoDate = extractPhotoDate(photoWithAsset);
       if (photoDate) {
         try {
-          const detectedEvents = await eventDetectionService.detectEvents(photoDate);
+          const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
           if (detectedEvents.length > 0) {
             const bestEvent = detectedEvents.reduce((max, event) => 
               event.confidence > max.confidence ? event : max
@@ -3234,6 +4310,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
         eventName: eventName || undefined,
         isReviewed: false, // Reset review status since metadata changed
       });
+      await systemAlbumService.processPhoto(updatedPhoto);
 
       // Preserve existing face assignments during reprocessing
       // Match new faces to existing ones based on position similarity
@@ -3268,6 +4345,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             boundingBox: newFace.boundingBox,
             confidence: newFace.confidence,
             embedding: newFace.embedding,
+            embeddingModel: newFace.embeddingModel ?? null,
+            detector: newFace.detector ?? null,
             // Keep existing personId
           });
 
@@ -3282,6 +4361,8 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
             boundingBox: newFace.boundingBox,
             confidence: newFace.confidence,
             embedding: newFace.embedding,
+            embeddingModel: newFace.embeddingModel ?? null,
+            detector: newFace.detector ?? null,
             personId: null, // New face starts unassigned
           });
         }
@@ -3293,11 +4374,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       }
 
       // Log reprocessing
-      await storage.createAssetHistory({
-        mediaAssetId: photo.mediaAssetId,
-        action: 'REPROCESSED',
-        details: `${photo.tier} tier photo reprocessed with updated AI analysis`,
-      });
+      await provenanceService.recordAiAnalysis(photo, `${photo.tier} tier photo reprocessed with updated AI analysis`, 'REPROCESSED');
 
       res.json({ 
         success: true, 
@@ -3344,6 +4421,376 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // All import sources recorded against a photo
+  app.get("/api/photos/:id/sources", async (req, res) => {
+    try {
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+
+      const sources = await storage.getPhotoSources(photo.mediaAssetId);
+      res.json(sources);
+    } catch (error) {
+      console.error("Error fetching photo sources:", error);
+      res.status(500).json({ message: "Failed to fetch photo sources" });
+    }
+  });
+
+  // How many imports were deduplicated, and how many of those came from another device
+  app.get("/api/imports/dedupe-report", async (req, res) => {
+    try {
+      const sources = await storage.getAllPhotoSources();
+      const devicesByAsset = new Map<string, Set<string>>();
+      const byDevice: Record<string, { imports: number; duplicates: number }> = {};
+      let duplicates = 0;
+      let crossDeviceDuplicates = 0;
+
+      // Sources are ordered by import time, so earlier devices are known when a duplicate arrives
+      for (const source of sources) {
+        const device = source.sourceDevice || 'Unknown';
+        const seenDevices = devicesByAsset.get(source.mediaAssetId) ?? new Set<string>();
+        byDevice[device] = byDevice[device] ?? { imports: 0, duplicates: 0 };
+        byDevice[device].imports++;
+
+        if (source.isDuplicate) {
+          duplicates++;
+          byDevice[device].duplicates++;
+          if (seenDevices.size > 0 && !seenDevices.has(device)) crossDeviceDuplicates++;
+        }
+
+        seenDevices.add(device);
+        devicesByAsset.set(source.mediaAssetId, seenDevices);
+      }
+
+      res.json({
+        totalImports: sources.length,
+        duplicates,
+        crossDeviceDuplicates,
+        sameDeviceDuplicates: duplicates - crossDeviceDuplicates,
+        photosFromMultipleDevices: Array.from(devicesByAsset.values()).filter(devices => devices.size > 1).length,
+        byDevice,
+      });
+    } catch (error) {
+      console.error("Error building dedupe report:", error);
+      res.status(500).json({ message: "Failed to build dedupe report" });
+    }
+  });
+
+  // Print preparation info: megapixels, max print sizes and crop warnings
+  app.get("/api/photos/:id/print-info", async (req, res) => {
+    try {
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+
+      let { width, height } = photo;
+      if (!width || !height) {
+        // Backfill dimensions for photos ingested before they were stored
+        const dimensions = await fileManager.getImageDimensions(photo.filePath);
+        if (!dimensions) {
+          return res.status(422).json({ message: "Photo dimensions are unavailable" });
+        }
+        ({ width, height } = dimensions);
+        await storage.updateFileVersion(photo.id, { width, height });
+      }
+
+      const printInfo = calculatePrintInfo(width, height);
+      if (photo.isPanorama) {
+        // Aspect-ratio crop warnings are meaningless for a projected panorama
+        return res.json({ ...printInfo, cropWarnings: [], isPanorama: true });
+      }
+      res.json(printInfo);
+    } catch (error) {
+      console.error("Error calculating print info:", error);
+      res.status(500).json({ message: "Failed to calculate print info" });
+    }
+  });
+
+  // Thumbnail keyed by photo id, stable across tier moves and renames
+  app.get("/api/photos/:id/thumbnail", async (req, res) => {
+    try {
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      const fullPath = libraryPath(photo.filePath);
+      if (!formatRegistry.supports(fullPath, 'thumbnail')) {
+        return res.status(415).json({ message: "Thumbnails are not supported for this format" });
+      }
+
+      const { quality, size } = req.query as { quality?: string; size?: string };
+      const original = await volumeStatusService.locateOriginal(photo);
+      let thumbnailPath: string;
+      try {
+        thumbnailPath = await thumbnailService.generateThumbnail(original.path, {
+          size: parseInt(size || '300'),
+          quality: quality === 'low' ? 60 : quality === 'high' ? 90 : 80,
+          format: 'jpeg',
+        }, photo.id);
+      } catch (error) {
+        // Only thumbnails cached before the volume went away can be served
+        if (!original.available) {
+          return res.status(503).json({
+            message: `Photo is on volume ${original.volumeLabel}, which is not connected`,
+            offline: true,
+            volumeLabel: original.volumeLabel,
+          });
+        }
+        throw error;
+      }
+
+      res.setHeader('Cache-Control', 'public, max-age=604800'); // 1 week
+      res.setHeader('Content-Type', 'image/jpeg');
+      res.sendFile(path.resolve(thumbnailPath));
+    } catch (error) {
+      console.error("Error serving photo thumbnail:", error);
+      res.status(500).json({ message: "Failed to serve thumbnail" });
+    }
+  });
+
+  // Projection parameters for the pano viewer
+  app.get("/api/photos/:id/panorama", async (req, res) => {
+    try {
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+
+      const panorama = (photo.metadata as CombinedMetadata | null)?.panorama;
+      if (!photo.isPanorama || !panorama) {
+        return res.status(404).json({ message: "Photo is not a panorama" });
+      }
+
+      res.json({ ...panorama, width: photo.width, height: photo.height });
+    } catch (error) {
+      console.error("Error getting panorama info:", error);
+      res.status(500).json({ message: "Failed to get panorama info" });
+    }
+  });
+
+  // Scan photos imported before panorama detection for GPano metadata
+  app.post("/api/photos/detect-panoramas", async (req, res) => {
+    try {
+      const photos = await storage.getAllFileVersions();
+      let detected = 0;
+      let offline = 0;
+
+      for (const photo of photos) {
+        if (photo.isPanorama || formatRegistry.getFormat(photo.filePath)?.kind !== 'image') continue;
+        // Offline originals are left for a later scan
+        const original = await volumeStatusService.locateOriginal(photo);
+        if (!original.available) {
+          offline++;
+          continue;
+        }
+        try {
+          const panorama = await formatMetadataExtractor.extractPanorama(original.path);
+          if (!panorama) continue;
+          await storage.updateFileVersion(photo.id, {
+            isPanorama: true,
+            metadata: { ...((photo.metadata as CombinedMetadata | null) ?? {}), panorama } as any,
+          });
+          detected++;
+        } catch (error) {
+          console.error(`Panorama detection failed for ${photo.filePath}:`, error);
+        }
+      }
+
+      res.json({ scanned: photos.length, detected, offline });
+    } catch (error) {
+      console.error("Error detecting panoramas:", error);
+      res.status(500).json({ message: "Failed to detect panoramas" });
+    }
+  });
+
+  // Pre-generate cached thumbnails for the library (cancellable)
+  app.post("/api/thumbnails/backfill", async (req, res) => {
+    try {
+      const sizes: number[] = Array.isArray(req.body.sizes) && req.body.sizes.length > 0 ? req.body.sizes : [300];
+      const photos = (await storage.getAllFileVersions())
+        .filter(photo => formatRegistry.supports(photo.filePath, 'thumbnail'));
+
+      // Photos whose original is offline keep whatever thumbnails were cached before
+      const originals: Array<{ path: string; photoId: string }> = [];
+      let offline = 0;
+      for (const photo of photos) {
+        const original = await volumeStatusService.locateOriginal(photo);
+        if (original.available) {
+          originals.push({ path: original.path, photoId: photo.id });
+        } else {
+          offline++;
+        }
+      }
+
+      const operation = operationRegistry.start('thumbnail_backfill', `Generating thumbnails for ${originals.length} photos`, requestedOperationId(req));
+      try {
+        const result = await thumbnailService.backfillThumbnails(
+          originals,
+          sizes.map(size => ({ size, quality: 80, format: 'jpeg' as const })),
+          operation.token,
+          operation.progress
+        );
+        res.json({ ...result, offline, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      console.error("Error backfilling thumbnails:", error);
+      res.status(500).json({ message: "Failed to backfill thumbnails" });
+    }
+  });
+
+  // Export document/receipt photos as a perspective-corrected grayscale PDF
+  app.post("/api/exports/documents-pdf", async (req, res) => {
+    try {
+      const { photoIds, destination } = req.body;
+      if (!Array.isArray(photoIds) || photoIds.length === 0) {
+        return res.status(400).json({ message: "photoIds must be a non-empty array" });
+      }
+      if (!destination || typeof destination !== 'string') {
+        return res.status(400).json({ message: "destination is required" });
+      }
+
+      const operation = operationRegistry.start('export', `Exporting ${photoIds.length} documents to PDF`, requestedOperationId(req));
+      try {
+        const result = await documentExportService.exportDocumentsPdf(photoIds, destination, operation.token);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Export was cancelled", cancelled: true });
+      }
+      console.error("Error exporting documents PDF:", error);
+      res.status(500).json({ message: error instanceof Error ? error.message : "Failed to export documents PDF" });
+    }
+  });
+
+  // Copy photos to a folder, optionally with their tags, rating, GPS and people written into the copies
+  app.post("/api/exports/photos", async (req, res) => {
+    try {
+      const parsed = photoExportSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid photo export", errors: parsed.error.errors });
+      }
+      const { photoIds, destination, ...options } = parsed.data;
+
+      const operation = operationRegistry.start('export', `Exporting ${photoIds.length} photos`, requestedOperationId(req));
+      try {
+        const result = await photoExportService.exportPhotos(photoIds, destination, options, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Export was cancelled", cancelled: true });
+      }
+      console.error("Error exporting photos:", error);
+      res.status(500).json({ message: error instanceof Error ? error.message : "Failed to export photos" });
+    }
+  });
+
+  // Export people's faces as aligned crops with a provenance manifest, for training recognition models elsewhere
+  app.post("/api/exports/face-dataset", async (req, res) => {
+    try {
+      const parsed = faceDatasetExportSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid face dataset export", errors: parsed.error.errors });
+      }
+      const { personIds, destination, ...options } = parsed.data;
+
+      const operation = operationRegistry.start('export', `Exporting faces of ${personIds.length} people`, requestedOperationId(req));
+      try {
+        const result = await faceDatasetExportService.exportFaceDataset(personIds, destination, options, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Export was cancelled", cancelled: true });
+      }
+      console.error("Error exporting face dataset:", error);
+      res.status(500).json({ message: error instanceof Error ? error.message : "Failed to export face dataset" });
+    }
+  });
+
+  // Write photos, or an album, with their people, faces, tags and albums to a portable archive for another library
+  app.post("/api/exports/archive", async (req, res) => {
+    try {
+      const parsed = archiveExportSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid archive export", errors: parsed.error.errors });
+      }
+      const { destination, ...selection } = parsed.data;
+
+      const label = selection.albumId ? 'Exporting album to archive' : `Exporting ${selection.photoIds!.length} photos to archive`;
+      const operation = operationRegistry.start('export', label, requestedOperationId(req));
+      try {
+        const result = await libraryArchiveService.exportArchive(selection, destination, operation.token, operation.progress);
+        res.json({ ...result, operationId: operation.id });
+      } finally {
+        operation.finish();
+      }
+    } catch (error) {
+      if (error instanceof LibraryArchiveError) {
+        return res.status(400).json({ message: error.message });
+      }
+      if (error instanceof OperationCancelledError) {
+        return res.status(409).json({ message: "Export was cancelled", cancelled: true });
+      }
+      console.error("Error exporting archive:", error);
+      res.status(500).json({ message: error instanceof Error ? error.message : "Failed to export archive" });
+    }
+  });
+
+  // Open a copy of the photo in an external editor and import the result as a new version
+  app.post("/api/photos/:id/edit-externally", async (req, res) => {
+    try {
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+
+      const session = await externalEditorService.startEdit(photo);
+      res.json(session);
+    } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
+      if (error instanceof OriginalOfflineError) {
+        return res.status(error.status).json({ message: error.message });
+      }
+      console.error("Error starting external edit:", error);
+      res.status(500).json({ message: error instanceof Error ? error.message : "Failed to start external edit" });
+    }
+  });
+
+  app.get("/api/external-edits/:sessionId", async (req, res) => {
+    const session = externalEditorService.getSession(req.params.sessionId);
+    if (!session) {
+      return res.status(404).json({ message: "Edit session not found" });
+    }
+    res.json(session);
+  });
+
+  app.delete("/api/external-edits/:sessionId", async (req, res) => {
+    try {
+      const cancelled = await externalEditorService.cancel(req.params.sessionId);
+      if (!cancelled) {
+        return res.status(404).json({ message: "Active edit session not found" });
+      }
+      res.json({ success: true });
+    } catch (error) {
+      console.error("Error cancelling external edit:", error);
+      res.status(500).json({ message: "Failed to cancel external edit" });
+    }
+  });
+
   // Get similar photos for review
   app.get("/api/photos/similarity", async (req, res) => {
     try {
@@ -3360,24 +4807,66 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
     });
 
+  // Supported formats, with file-dialog filters for the import UI
+  app.get("/api/formats", async (req, res) => {
+    try {
+      const formats = formatRegistry.getFormats();
+      res.json({
+        formats,
+        dialogFilters: formatRegistry.getDialogFilters(),
+        accept: formats.filter(format => format.enabled).map(format => `.${format.extension}`).join(','),
+      });
+    } catch (error) {
+      console.error("Error fetching supported formats:", error);
+      res.status(500).json({ message: "Failed to fetch supported formats" });
+    }
+  });
+
   // Settings routes
   app.get("/api/settings", async (req, res) => {
     try {
       const settings = await storage.getAllSettings();
-      res.json(settings);
+      res.json(settings.map(redactSetting));
     } catch (error) {
       console.error("Error fetching settings:", error);
       res.status(500).json({ message: "Failed to fetch settings" });
     }
   });
 
+  // Library location and every library setting in one object
+  app.get("/api/settings/app", async (req, res) => {
+    try {
+      res.json(await appSettingsService.getSettings());
+    } catch (error) {
+      console.error("Error fetching app settings:", error);
+      res.status(500).json({ message: "Failed to fetch settings" });
+    }
+  });
+
+  // Update several settings at once, optionally switching to another existing library folder
+  app.patch("/api/settings", async (req, res) => {
+    try {
+      const parsed = updateSettingsSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid settings", errors: parsed.error.errors });
+      }
+      res.json(await appSettingsService.updateSettings(parsed.data));
+    } catch (error) {
+      if (error instanceof SettingsError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error("Error updating settings:", error);
+      res.status(500).json({ message: "Failed to update settings" });
+    }
+  });
+
   app.get("/api/settings/:key", async (req, res) => {
     try {
       const setting = await storage.getSettingByKey(req.params.key);
       if (!setting) {
         return res.status(404).json({ message: "Setting not found" });
       }
-      res.json(setting);
+      res.json(redactSetting(setting));
     } catch (error) {
       console.error("Error fetching setting:", error);
       res.status(500).json({ message: "Failed to fetch setting" });
@@ -3387,7 +4876,16 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
   app.post("/api/settings", async (req, res) => {
     try {
       const setting = await storage.createSetting(req.body);
-      res.status(201).json(setting);
+      if (setting.key === SUPPORTED_FORMATS_SETTING) {
+        await formatRegistry.refresh();
+      }
+      if (THUMBNAIL_ENCODER_SETTINGS.includes(setting.key)) {
+        thumbnailService.refreshEncoderSettings();
+      }
+      if (setting.key === AI_PROVIDER_SETTING) {
+        await aiProviderRegistry.refresh();
+      }
+      res.status(201).json(redactSetting(setting));
     } catch (error) {
       console.error("Error creating setting:", error);
       res.status(500).json({ message: "Failed to create setting" });
@@ -3396,9 +4894,19 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
   app.put("/api/settings/:key", async (req, res) => {
     try {
-      const { value } = req.body;
+      const saved = await storage.getSettingByKey(req.params.key);
+      const value = keepSavedSecrets(req.params.key, req.body.value, saved?.value);
       const setting = await storage.updateSetting(req.params.key, value);
-      res.json(setting);
+      if (req.params.key === SUPPORTED_FORMATS_SETTING) {
+        await formatRegistry.refresh();
+      }
+      if (THUMBNAIL_ENCODER_SETTINGS.includes(req.params.key)) {
+        thumbnailService.refreshEncoderSettings();
+      }
+      if (req.params.key === AI_PROVIDER_SETTING) {
+        await aiProviderRegistry.refresh();
+      }
+      res.json(redactSetting(setting));
     } catch (error) {
       console.error("Error updating setting:", error);
       res.status(500).json({ message: "Failed to update setting" });
@@ -3408,6 +4916,15 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
   app.delete("/api/settings/:key", async (req, res) => {
     try {
       await storage.deleteSetting(req.params.key);
+      if (req.params.key === SUPPORTED_FORMATS_SETTING) {
+        await formatRegistry.refresh();
+      }
+      if (THUMBNAIL_ENCODER_SETTINGS.includes(req.params.key)) {
+        thumbnailService.refreshEncoderSettings();
+      }
+      if (req.params.key === AI_PROVIDER_SETTING) {
+        await aiProviderRegistry.refresh();
+      }
       res.json({ message: "Setting deleted successfully" });
     } catch (error) {
       console.error("Error deleting setting:", error);
@@ -3426,6 +4943,29 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Whether rating, tag and person changes reach stack members and other versions
+  app.get("/api/settings/propagation/rules", async (req, res) => {
+    try {
+      res.json(await propagationService.getRules());
+    } catch (error) {
+      console.error("Error fetching propagation rules:", error);
+      res.status(500).json({ message: "Failed to fetch propagation rules" });
+    }
+  });
+
+  app.put("/api/settings/propagation/rules", async (req, res) => {
+    try {
+      const parsed = propagationRulesSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid propagation rules", errors: parsed.error.errors });
+      }
+      res.json(await propagationService.updateRules(parsed.data));
+    } catch (error) {
+      console.error("Error updating propagation rules:", error);
+      res.status(500).json({ message: "Failed to update propagation rules" });
+    }
+  });
+
   // Event Detection Routes
   app.get("/api/events", async (req, res) => {
     try {
@@ -3439,7 +4979,11 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
   app.post("/api/events", async (req, res) => {
     try {
-      const event = await storage.createEvent(req.body);
+      const parsed = insertEventSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid event data", errors: parsed.error.errors });
+      }
+      const event = await storage.createEvent(parsed.data);
       res.status(201).json(event);
     } catch (error) {
       console.error("Error creating event:", error);
@@ -3449,8 +4993,7 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 
   app.get("/api/events/holiday-sets", async (req, res) => {
     try {
-      const { eventDetectionService } = await import("./services/eventDetection");
-      const holidaySets = eventDetectionService.getAvailableHolidaySets();
+      const holidaySets = await holidayCalendarService.listCalendars();
       res.json(holidaySets);
     } catch (error) {
       console.error("Error fetching holiday sets:", error);
@@ -3458,6 +5001,40 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Import a custom holiday set from an ICS file
+  app.post("/api/events/holiday-sets/import", calendarUpload.single('file'), async (req, res) => {
+    try {
+      if (!req.file) {
+        return res.status(400).json({ message: "No calendar file uploaded" });
+      }
+      const calendar = await holidayCalendarService.importCalendar(
+        req.file.buffer.toString('utf8'),
+        req.body.name || undefined,
+        req.body.enable !== 'false'
+      );
+      res.status(201).json(calendar);
+    } catch (error) {
+      if (error instanceof HolidayCalendarError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error("Error importing holiday set:", error);
+      res.status(500).json({ message: "Failed to import holiday set" });
+    }
+  });
+
+  app.delete("/api/events/holiday-sets/:code", async (req, res) => {
+    try {
+      await holidayCalendarService.deleteCalendar(req.params.code);
+      res.json({ success: true, message: "Holiday set deleted successfully" });
+    } catch (error) {
+      if (error instanceof HolidayCalendarError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error("Error deleting holiday set:", error);
+      res.status(500).json({ message: "Failed to delete holiday set" });
+    }
+  });
+
   app.post("/api/events/detect", async (req, res) => {
     try {
       const { photoDate } = req.body;
@@ -3474,6 +5051,99 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  app.get("/api/events/:id", async (req, res) => {
+    try {
+      const event = await storage.getEvent(req.params.id);
+      if (!event) {
+        return res.status(404).json({ message: "Event not found" });
+      }
+      res.json(event);
+    } catch (error) {
+      console.error("Error fetching event:", error);
+      res.status(500).json({ message: "Failed to fetch event" });
+    }
+  });
+
+  app.put("/api/events/:id", async (req, res) => {
+    try {
+      const parsed = insertEventSchema.partial().safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid event data", errors: parsed.error.errors });
+      }
+      const existing = await storage.getEvent(req.params.id);
+      if (!existing) {
+        return res.status(404).json({ message: "Event not found" });
+      }
+      const event = await storage.updateEvent(existing.id, parsed.data);
+      res.json(event);
+    } catch (error) {
+      console.error("Error updating event:", error);
+      res.status(500).json({ message: "Failed to update event" });
+    }
+  });
+
+  app.delete("/api/events/:id", async (req, res) => {
+    try {
+      const existing = await storage.getEvent(req.params.id);
+      if (!existing) {
+        return res.status(404).json({ message: "Event not found" });
+      }
+      await storage.deleteEvent(existing.id);
+      res.json({ success: true, message: "Event deleted successfully" });
+    } catch (error) {
+      console.error("Error deleting event:", error);
+      res.status(500).json({ message: "Failed to delete event" });
+    }
+  });
+
+  // Occurrences of an event in a date range (defaults to the current year)
+  app.get("/api/events/:id/occurrences", async (req, res) => {
+    try {
+      const event = await storage.getEvent(req.params.id);
+      if (!event) {
+        return res.status(404).json({ message: "Event not found" });
+      }
+      const year = new Date().getFullYear();
+      const from = req.query.from ? new Date(req.query.from as string) : new Date(year, 0, 1);
+      const to = req.query.to ? new Date(req.query.to as string) : new Date(year, 11, 31);
+      if (isNaN(from.getTime()) || isNaN(to.getTime())) {
+        return res.status(400).json({ message: "from and to must be valid dates" });
+      }
+      const durationDays = event.durationDays ?? 1;
+      const occurrences = getOccurrencesBetween(event, from, to).map(start => ({
+        start,
+        end: new Date(start.getFullYear(), start.getMonth(), start.getDate() + durationDays - 1),
+      }));
+      res.json(occurrences);
+    } catch (error) {
+      console.error("Error listing event occurrences:", error);
+      res.status(500).json({ message: "Failed to list event occurrences" });
+    }
+  });
+
+  // Photos taken during any occurrence of an event, for memories and slideshows
+  app.get("/api/events/:id/photos", async (req, res) => {
+    try {
+      const event = await storage.getEvent(req.params.id);
+      if (!event) {
+        return res.status(404).json({ message: "Event not found" });
+      }
+      const limit = parseInt(req.query.limit as string) || 100;
+      const offset = parseInt(req.query.offset as string) || 0;
+      const result = await advancedSearch.searchPhotos(
+        { eventId: event.id },
+        { field: 'takenAt', direction: 'desc' },
+        limit,
+        offset,
+        photoStackService.shouldExpand(req.query.expandStacks)
+      );
+      res.json({ photos: result.photos, totalCount: result.totalCount });
+    } catch (error) {
+      console.error("Error fetching event photos:", error);
+      res.status(500).json({ message: "Failed to fetch event photos" });
+    }
+  });
+
   app.post("/api/people/:personId/age-in-photo", async (req, res) => {
     try {
       const { photoDate } = req.body;
@@ -3596,6 +5266,67 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
   // Location routes
   app.use("/api/locations", locationRoutes);
 
+  // Library-wide duplicate scanning
+  app.use("/api/duplicates", duplicateRoutes);
+
+  // Digital photo frame publish targets
+  app.use("/api/frame-targets", frameTargetRoutes);
+  frameSyncService.startScheduler();
+  // Rotating photo for screens on the network; configured at /api/frame-targets/display
+  app.use("/frame", frameRoutes);
+
+  // Share links with per-link permissions, and what their recipients can open
+  app.use("/api/shares", shareRoutes);
+  app.use("/s", publicShareRoutes);
+  // Photos sent by email through an SMTP server
+  app.use("/api/email", emailRoutes);
+  startupMetrics.defer('ai_batch_scheduler', async () => aiBatchScheduler.startScheduler());
+
+  // Persistent selection sets
+  app.use("/api/selections", selectionRoutes);
+  // Unsaved quick collection shared across windows
+  app.use("/api/working-set", workingSetRoutes);
+  // Gallery and viewer windows, and the events they send each other
+  app.use("/api/windows", windowRoutes);
+
+  // Cancellable long-running operations
+  app.use("/api/operations", operationRoutes);
+
+  // Persistent background jobs (face recognition after import)
+  app.use("/api/jobs", jobRoutes);
+  startupMetrics.defer('job_queue', () => jobQueue.start());
+
+  // Resumable imports of files already on disk
+  app.use("/api/imports", importRoutes);
+  startupMetrics.defer('import_queue', () => importSessionService.start());
+
+  // Restore or purge soft-deleted people, albums, tags and trashed photos
+  app.use("/api/deleted", deletedRoutes);
+  startupMetrics.defer('purge_expired_deleted', () => softDeleteService.purgeExpired());
+  startupMetrics.defer('tag_hierarchy', async () => { await tagHierarchyService.syncHierarchy(); });
+
+  // Folders whose new files are imported automatically
+  app.use("/api/watch-folders", watchFolderRoutes);
+  startupMetrics.defer('folder_watcher', () => folderWatcherService.start());
+
+  // Originals on external volumes, and which volumes are connected
+  app.use("/api/cold-storage", coldStorageRoutes);
+  app.use("/api/volumes", volumeRoutes);
+
+  // Shutdown events and interrupted-operation checkpoints
+  app.use("/api/system", systemRoutes);
+
+  // Undo and redo of promotions, deletions, tag, album and person changes
+  app.use("/api/journal", journalRoutes);
+
+  // Tray commands for work that continues with the main window closed
+  app.use("/api/background", backgroundRoutes);
+  backgroundService.startMaintenance();
+
+  // Library location, initialization status, integrity and backups
+  app.use("/api/library", libraryRoutes);
+  libraryBackupService.startScheduler();
+
   // Update photo endpoint
   app.put('/api/photos/:id', async (req, res) => {
     try {
@@ -3623,6 +5354,30 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Lock a photo against deletion, demotion, edits and metadata write-back, or unlock it
+  app.put("/api/photos/:id/lock", async (req, res) => {
+    try {
+      const parsed = setPhotoLockSchema.safeParse(req.body);
+      if (!parsed.success) {
+        return res.status(400).json({ message: "Invalid lock request", errors: parsed.error.errors });
+      }
+      const photo = await storage.getFileVersion(req.params.id);
+      if (!photo) {
+        return res.status(404).json({ message: "Photo not found" });
+      }
+      if (photo.isLocked === parsed.data.locked) {
+        return res.json(photo);
+      }
+
+      const updated = await storage.updateFileVersion(photo.id, { isLocked: parsed.data.locked });
+      await provenanceService.record(updated, parsed.data.locked ? 'LOCKED' : 'UNLOCKED', parsed.data.locked ? 'Photo locked' : 'Photo unlocked');
+      res.json(updated);
+    } catch (error) {
+      console.error("Error updating photo lock:", error);
+      res.status(500).json({ message: "Failed to update photo lock" });
+    }
+  });
+
   // Toggle photo favorite status
   app.patch("/api/photos/:id/favorite", async (req, res) => {
     try {
@@ -3635,6 +5390,9 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       await storage.updateFileVersion(req.params.id, { isFavorite });
       res.json({ success: true, isFavorite });
     } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
       console.error("Error updating favorite status:", error);
       res.status(500).json({ message: "Failed to update favorite status" });
     }
@@ -3648,30 +5406,24 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
       if (!photo) {
         return res.status(404).json({ message: "Photo not found" });
       }
-
-      const metadata = (photo.metadata as any) || {};
-      const currentTags = metadata.ai?.aiTags || [];
-      
-      let updatedTags;
-      if (action === 'add') {
-        updatedTags = Array.from(new Set([...currentTags, ...tags]));
-      } else if (action === 'remove') {
-        updatedTags = currentTags.filter((tag: string) => !tags.includes(tag));
-      } else {
-        updatedTags = tags; // Replace all tags
+      const propagate = propagationScopeSchema.optional().safeParse(req.body.propagate);
+      if (!propagate.success) {
+        return res.status(400).json({ message: "Invalid propagation options", errors: propagate.error.errors });
       }
 
-      const updatedMetadata = {
-        ...metadata,
-        ai: { ...(metadata.ai || {}), aiTags: updatedTags }
-      };
-
-      await storage.updateFileVersion(req.params.id, { 
-        metadata: updatedMetadata as any 
-      });
+      const before = await operationJournal.captureTags([photo.id], propagate.data);
+      const result = await propagationService.changeTags(
+        photo.id,
+        { action: action === 'add' || action === 'remove' ? action : 'set', tags }, // anything else replaces all tags
+        propagate.data
+      );
 
-      res.json({ success: true, tags: updatedTags });
+      await operationJournal.record('tags', `Changed tags on ${1 + result.propagatedTo.length} photo(s)`, await operationJournal.tagChanges(before));
+      res.json({ success: true, tags: result.tags, propagatedTo: result.propagatedTo });
     } catch (error) {
+      if (error instanceof PhotoLockedError) {
+        return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+      }
       console.error("Error updating photo tags:", error);
       res.status(500).json({ message: "Failed to update photo tags" });
     }
@@ -3680,12 +5432,17 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
 // Get available tags endpoint
   app.get('/api/tags/library', async (req, res) => {
     try {
-      const result = await db.execute(sql`
-        SELECT tag, usage_count, created_at 
-        FROM global_tag_library 
-        ORDER BY usage_count DESC, tag ASC
-      `);
-      res.json(result.rows);
+      // Most used first; photo_count is how many photos carry the tag now
+      const usage = (await storage.getTagUsage())
+        .sort((a, b) => b.photoCount - a.photoCount || b.usageCount - a.usageCount || a.tag.localeCompare(b.tag));
+      res.json(usage.map(entry => ({
+        id: entry.id,
+        tag: entry.tag,
+        parent_tag_id: entry.parentTagId,
+        usage_count: entry.usageCount,
+        photo_count: entry.photoCount,
+        created_at: entry.createdAt,
+      })));
     } catch (error) {
       console.error('Error fetching tags library:', error);
       res.status(500).json({ error: 'Failed to fetch tags library' });
@@ -3714,11 +5471,14 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
-  // Delete tag from library
+  // Delete tag from library; photos keep the tag and it can be restored from /api/deleted
   app.delete('/api/tags/library/:tag', async (req, res) => {
     try {
       const { tag } = req.params;
-      await db.execute(sql`DELETE FROM global_tag_library WHERE tag = ${tag}`);
+      if (!(await softDeleteService.delete('tag', tag))) {
+        return res.status(404).json({ error: 'Tag not found in library' });
+      }
+      await operationJournal.record('deletion', `Deleted tag ${tag}`, [{ type: 'deletion', kind: 'tag', id: tag }]);
       res.json({ success: true });
     } catch (error) {
       console.error('Error deleting tag from library:', error);
@@ -3726,6 +5486,82 @@ app.get("/api/smart-collections/:id/photos", async (req, res) => {
     }
   });
 
+  // Rename a library tag on every photo, with the tags under it
+  app.patch('/api/tags/library/:id', async (req, res) => {
+    try {
+      const { name } = req.body;
+      if (typeof name !== 'string' || !name.trim()) {
+        return res.status(400).json({ message: 'name is required' });
+      }
+      const before = await operationJournal.captureTags(await tagHierarchyService.findPhotosWithTagIds([req.params.id]), { stack: false, versions: false });
+      const result = await tagHierarchyService.renameTag(req.params.id, name);
+      if (result.photosUpdated.length > 0) {
+        await operationJournal.record('tags', `Renamed tag ${result.from} to ${result.to}`, await operationJournal.tagChanges(before));
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof TagHierarchyError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error('Error renaming tag:', error);
+      res.status(500).json({ message: 'Failed to rename tag' });
+    }
+  });
+
+  // Merge near-duplicate tags into one
+  app.post('/api/tags/merge', async (req, res) => {
+    try {
+      const { sourceIds, targetId } = req.body;
+      if (!Array.isArray(sourceIds) || sourceIds.length === 0 || !sourceIds.every(id => typeof id === 'string') || typeof targetId !== 'string') {
+        return res.status(400).json({ message: 'sourceIds and targetId are required' });
+      }
+      const before = await operationJournal.captureTags(await tagHierarchyService.findPhotosWithTagIds(sourceIds), { stack: false, versions: false });
+      const result = await tagHierarchyService.mergeTags(sourceIds, targetId);
+      if (result.photosUpdated.length > 0) {
+        await operationJournal.record('tags', `Merged ${result.mergedTags.join(', ')} into ${result.target}`, await operationJournal.tagChanges(before));
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof TagHierarchyError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error('Error merging tags:', error);
+      res.status(500).json({ message: 'Failed to merge tags' });
+    }
+  });
+
+  // Tag library as a tree of nested tags ("animals/dogs/corgi")
+  app.get('/api/tags/tree', async (req, res) => {
+    try {
+      res.json(await tagHierarchyService.getTagTree());
+    } catch (error) {
+      console.error('Error fetching tag tree:', error);
+      res.status(500).json({ message: 'Failed to fetch tag tree' });
+    }
+  });
+
+  // Move a tag and everything under it beneath another tag, or to the top level with parent null
+  app.post('/api/tags/move', async (req, res) => {
+    try {
+      const { tag, parent } = req.body;
+      if (typeof tag !== 'string' || !tag || (parent !== null && parent !== undefined && typeof parent !== 'string')) {
+        return res.status(400).json({ message: 'tag and an optional parent tag are required' });
+      }
+      const before = await operationJournal.captureTags(await tagHierarchyService.findPhotosWithTags([tag]), { stack: false, versions: false });
+      const result = await tagHierarchyService.moveTag(tag, parent ?? null);
+      if (result.photosUpdated.length > 0) {
+        await operationJournal.record('tags', `Moved tag ${result.from} to ${result.to}`, await operationJournal.tagChanges(before));
+      }
+      res.json(result);
+    } catch (error) {
+      if (error instanceof TagHierarchyError) {
+        return res.status(400).json({ message: error.message });
+      }
+      console.error('Error moving tag:', error);
+      res.status(500).json({ message: 'Failed to move tag' });
+    }
+  });
+
   // System health check
   app.get("/api/health", async (req, res) => {
     try {
@@ -0,0 +1,35 @@
+import express from "express";
+import { backgroundService } from "../services/background";
+
+const router = express.Router();
+
+// What the tray shows: paused or not, running operations and recent imports
+router.get("/status", async (req, res) => {
+  try {
+    res.json(await backgroundService.getStatus());
+  } catch (error) {
+    console.error("Error getting background status:", error);
+    res.status(500).json({ message: "Failed to get background status" });
+  }
+});
+
+// Stop imports, jobs and schedulers after the item in progress
+router.post("/pause", async (req, res) => {
+  try {
+    res.json(await backgroundService.pause());
+  } catch (error) {
+    console.error("Error pausing background work:", error);
+    res.status(500).json({ message: "Failed to pause background work" });
+  }
+});
+
+router.post("/resume", async (req, res) => {
+  try {
+    res.json(await backgroundService.resume());
+  } catch (error) {
+    console.error("Error resuming background work:", error);
+    res.status(500).json({ message: "Failed to resume background work" });
+  }
+});
+
+export default router;
@@ -0,0 +1,93 @@
+import express from "express";
+import {
+  coldStorageService,
+  archiveToColdStorageSchema,
+  evictSchema,
+  reconnectVolumeSchema,
+  ColdStorageError,
+} from "../services/coldStorage";
+import { operationRegistry, requestedOperationId } from "../services/operations";
+
+const router = express.Router();
+
+// Copy originals to a folder on an external volume, optionally evicting the local files
+router.post("/archive", async (req, res) => {
+  try {
+    const parsed = archiveToColdStorageSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid archive request", errors: parsed.error.errors });
+    }
+    const { photoIds, destination, volumeLabel, evict } = parsed.data;
+
+    const operation = operationRegistry.start('cold_storage', `Archiving ${photoIds.length} photos to ${destination}`, requestedOperationId(req));
+    try {
+      const result = await coldStorageService.archive(photoIds, destination, { volumeLabel, evict }, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof ColdStorageError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error archiving photos:", error);
+    res.status(500).json({ message: "Failed to archive photos" });
+  }
+});
+
+// Remove the local originals of archived photos
+router.post("/evict", async (req, res) => {
+  try {
+    const parsed = evictSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "photoIds must be a non-empty array", errors: parsed.error.errors });
+    }
+    const operation = operationRegistry.start('cold_storage', `Evicting ${parsed.data.photoIds.length} originals`, requestedOperationId(req));
+    try {
+      const result = await coldStorageService.evict(parsed.data.photoIds, operation.token);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    console.error("Error evicting originals:", error);
+    res.status(500).json({ message: "Failed to evict originals" });
+  }
+});
+
+// Photos whose original is only on an external volume, and whether that volume is connected
+router.get("/offline", async (req, res) => {
+  try {
+    res.json(await coldStorageService.listOfflinePhotos());
+  } catch (error) {
+    console.error("Error listing offline photos:", error);
+    res.status(500).json({ message: "Failed to list offline photos" });
+  }
+});
+
+// Verify every copy on a volume that has been connected again, optionally restoring originals
+router.post("/reconnect", async (req, res) => {
+  try {
+    const parsed = reconnectVolumeSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid reconnect request", errors: parsed.error.errors });
+    }
+    const { volumeLabel, archiveRoot, restore } = parsed.data;
+
+    const operation = operationRegistry.start('cold_storage', `Verifying volume ${volumeLabel}`, requestedOperationId(req));
+    try {
+      const result = await coldStorageService.reconnect(volumeLabel, { archiveRoot, restore }, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof ColdStorageError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error reconnecting volume:", error);
+    res.status(500).json({ message: "Failed to reconnect volume" });
+  }
+});
+
+export default router;
@@ -0,0 +1,78 @@
+import express from "express";
+import { z } from "zod";
+import { softDeleteService } from "../services/softDelete";
+import { counterConsistencyService } from "../services/counterConsistency";
+import { PhotoLockedError } from "../services/photoLock";
+
+const router = express.Router();
+
+const kindSchema = z.enum(['person', 'album', 'tag', 'photo']);
+
+// Soft-deleted people, albums, tags and photos with the date each will be
+// purged; ?kind=photo lists only the photo trash
+router.get("/", async (req, res) => {
+  try {
+    const kind = kindSchema.optional().safeParse(req.query.kind);
+    if (!kind.success) {
+      return res.status(400).json({ message: "kind must be person, album, tag or photo", errors: kind.error.errors });
+    }
+    res.json(await softDeleteService.list(kind.data));
+  } catch (error) {
+    console.error("Error listing deleted items:", error);
+    res.status(500).json({ message: "Failed to list deleted items" });
+  }
+});
+
+router.post("/:kind/:id/restore", async (req, res) => {
+  try {
+    const kind = kindSchema.safeParse(req.params.kind);
+    if (!kind.success) {
+      return res.status(400).json({ message: "kind must be person, album, tag or photo", errors: kind.error.errors });
+    }
+    if (!(await softDeleteService.restore(kind.data, req.params.id))) {
+      return res.status(404).json({ message: "Deleted item not found" });
+    }
+    res.json({ success: true });
+  } catch (error) {
+    if (error instanceof PhotoLockedError) {
+      return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+    }
+    console.error("Error restoring deleted item:", error);
+    res.status(500).json({ message: "Failed to restore item" });
+  }
+});
+
+// Purge one item for good
+router.delete("/:kind/:id", async (req, res) => {
+  try {
+    const kind = kindSchema.safeParse(req.params.kind);
+    if (!kind.success) {
+      return res.status(400).json({ message: "kind must be person, album, tag or photo", errors: kind.error.errors });
+    }
+    if (!(await softDeleteService.purge(kind.data, req.params.id))) {
+      return res.status(404).json({ message: "Deleted item not found" });
+    }
+    res.json({ success: true });
+  } catch (error) {
+    console.error("Error purging deleted item:", error);
+    res.status(500).json({ message: "Failed to purge item" });
+  }
+});
+
+// Purge everything that is soft-deleted; ?kind=photo empties the photo trash
+router.delete("/", async (req, res) => {
+  try {
+    const kind = kindSchema.optional().safeParse(req.query.kind);
+    if (!kind.success) {
+      return res.status(400).json({ message: "kind must be person, album, tag or photo", errors: kind.error.errors });
+    }
+    const purged = await softDeleteService.purgeAll(kind.data);
+    counterConsistencyService.scheduleCheck();
+    res.json({ purged });
+  } catch (error) {
+    console.error("Error purging deleted items:", error);
+    res.status(500).json({ message: "Failed to purge deleted items" });
+  }
+});
+
+export default router;
@@ -0,0 +1,67 @@
+import express from "express";
+import { storage } from "../storage";
+import { duplicateScanService } from "../services/duplicateScan";
+
+const router = express.Router();
+
+// Scan for exact and near-duplicate groups, optionally scoped to a selection, collection or import batch
+router.post("/scan", async (req, res) => {
+  try {
+    const { scope = {}, threshold, includeResolved = false } = req.body;
+    const groups = await duplicateScanService.findDuplicateGroups(
+      scope,
+      threshold !== undefined ? Number(threshold) : undefined,
+      includeResolved
+    );
+    res.json({ groups, totalGroups: groups.length });
+  } catch (error) {
+    console.error("Error scanning for duplicates:", error);
+    res.status(500).json({ message: "Failed to scan for duplicates" });
+  }
+});
+
+// Get recorded duplicate decisions
+router.get("/decisions", async (req, res) => {
+  try {
+    const decisions = await storage.getDuplicateDecisions();
+    res.json(decisions);
+  } catch (error) {
+    console.error("Error fetching duplicate decisions:", error);
+    res.status(500).json({ message: "Failed to fetch duplicate decisions" });
+  }
+});
+
+// Record a decision for a reviewed group
+router.post("/decisions", async (req, res) => {
+  try {
+    const { photoIds, decision, keptPhotoId } = req.body;
+    if (!Array.isArray(photoIds) || photoIds.length < 2) {
+      return res.status(400).json({ message: "At least two photoIds are required" });
+    }
+    if (!['not_duplicate', 'resolved'].includes(decision)) {
+      return res.status(400).json({ message: "decision must be 'not_duplicate' or 'resolved'" });
+    }
+
+    const record = await duplicateScanService.recordDecision(photoIds, decision, keptPhotoId);
+    res.status(201).json(record);
+  } catch (error) {
+    console.error("Error recording duplicate decision:", error);
+    res.status(500).json({ message: "Failed to record duplicate decision" });
+  }
+});
+
+// Forget a decision so its group can surface again
+router.delete("/decisions/:id", async (req, res) => {
+  try {
+    const deleted = await storage.deleteDuplicateDecision(req.params.id);
+    if (!deleted) {
+      return res.status(404).json({ message: "Duplicate decision not found" });
+    }
+    res.json({ message: "Duplicate decision deleted successfully" });
+  } catch (error) {
+    console.error("Error deleting duplicate decision:", error);
+    res.status(500).json({ message: "Failed to delete duplicate decision" });
+  }
+});
+
+export default router;
@@ -0,0 +1,66 @@
+import express from "express";
+import { emailExportService, emailPhotosSchema, smtpConfigSchema, EmailExportError } from "../services/emailExport";
+import { SmtpError } from "../utils/smtp";
+
+const router = express.Router();
+
+// SMTP settings; the password is never sent back
+router.get("/smtp", async (req, res) => {
+  try {
+    const config = await emailExportService.getConfig();
+    if (!config) {
+      return res.json({ configured: false });
+    }
+    const { password, ...rest } = config;
+    res.json({ configured: true, ...rest, hasPassword: !!password });
+  } catch (error) {
+    console.error("Error fetching SMTP settings:", error);
+    res.status(500).json({ message: "Failed to fetch SMTP settings" });
+  }
+});
+
+router.put("/smtp", async (req, res) => {
+  try {
+    const parsed = smtpConfigSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid SMTP settings", errors: parsed.error.errors });
+    }
+    const { password, ...rest } = await emailExportService.updateConfig(parsed.data);
+    res.json({ configured: true, ...rest, hasPassword: !!password });
+  } catch (error) {
+    console.error("Error saving SMTP settings:", error);
+    res.status(500).json({ message: "Failed to save SMTP settings" });
+  }
+});
+
+router.delete("/smtp", async (req, res) => {
+  try {
+    await emailExportService.clearConfig();
+    res.json({ configured: false });
+  } catch (error) {
+    console.error("Error removing SMTP settings:", error);
+    res.status(500).json({ message: "Failed to remove SMTP settings" });
+  }
+});
+
+// Email photos rendered with a preset (small, medium, large or original)
+router.post("/photos", async (req, res) => {
+  try {
+    const parsed = emailPhotosSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid email request", errors: parsed.error.errors });
+    }
+    res.json(await emailExportService.emailPhotos(parsed.data));
+  } catch (error) {
+    if (error instanceof EmailExportError) {
+      return res.status(400).json({ message: error.message });
+    }
+    if (error instanceof SmtpError) {
+      return res.status(502).json({ message: `The mail server refused the message: ${error.message}` });
+    }
+    console.error("Error emailing photos:", error);
+    res.status(500).json({ message: "Failed to email photos" });
+  }
+});
+
+export default router;
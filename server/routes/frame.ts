@@ -0,0 +1,67 @@
+import express from "express";
+import { frameDisplayService, FrameDisplayError } from "../services/frameDisplay";
+
+// Served outside /api so a browser on any screen on the network can open it as is
+const router = express.Router();
+
+// The current photo. It is cacheable until the slot ends, so screens only fetch each photo once.
+router.get("/", async (req, res) => {
+  try {
+    const frame = await frameDisplayService.currentImage();
+    const secondsLeft = Math.max(1, Math.ceil((frame.expiresAt.getTime() - Date.now()) / 1000));
+    const etag = `"${frame.photoId}-${frame.slotStart.getTime()}"`;
+
+    res.set({
+      'Cache-Control': `public, max-age=${secondsLeft}`,
+      'Expires': frame.expiresAt.toUTCString(),
+      'Last-Modified': frame.slotStart.toUTCString(),
+      'ETag': etag,
+      'Refresh': String(secondsLeft),
+    });
+    if (req.headers['if-none-match'] === etag) {
+      return res.status(304).end();
+    }
+    res.type('image/jpeg').send(frame.image);
+  } catch (error) {
+    if (error instanceof FrameDisplayError) {
+      return res.status(404).json({ message: error.message });
+    }
+    console.error("Error serving photo frame:", error);
+    res.status(500).json({ message: "Failed to serve photo frame" });
+  }
+});
+
+// Full-screen page around the photo that reloads when the next one is due. Plain HTML with a meta
+// refresh, so it also works on old tablet browsers without modern JavaScript.
+router.get("/view", async (req, res) => {
+  try {
+    const settings = await frameDisplayService.getSettings();
+    if (!settings.enabled) {
+      return res.status(404).json({ message: "The photo frame is turned off" });
+    }
+    const intervalMs = settings.intervalSeconds * 1000;
+    const secondsLeft = Math.max(1, Math.ceil((intervalMs - (Date.now() % intervalMs)) / 1000));
+    const slot = Math.floor(Date.now() / intervalMs);
+
+    res.set('Cache-Control', 'no-store');
+    res.type('html').send(`<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta http-equiv="refresh" content="${secondsLeft}">
+<title>Pictallion</title>
+<style>
+html, body { margin: 0; height: 100%; background: #000; overflow: hidden; }
+img { width: 100%; height: 100%; object-fit: contain; }
+</style>
+</head>
+<body><img src="${req.baseUrl}?slot=${slot}" alt=""></body>
+</html>`);
+  } catch (error) {
+    console.error("Error serving photo frame page:", error);
+    res.status(500).json({ message: "Failed to serve photo frame page" });
+  }
+});
+
+export default router;
@@ -0,0 +1,118 @@
+import express from "express";
+import { storage } from "../storage";
+import { frameSyncService } from "../services/frameSync";
+import { frameDisplayService, frameDisplaySettingsSchema } from "../services/frameDisplay";
+import { operationRegistry, requestedOperationId } from "../services/operations";
+import { insertFrameTargetSchema } from "@shared/schema";
+import { z } from "zod";
+
+const router = express.Router();
+
+// Get all frame targets
+router.get("/", async (req, res) => {
+  try {
+    const targets = await storage.getFrameTargets();
+    res.json(targets);
+  } catch (error) {
+    console.error("Error fetching frame targets:", error);
+    res.status(500).json({ message: "Failed to fetch frame targets" });
+  }
+});
+
+// Settings of the photo frame served at /frame
+router.get("/display", async (req, res) => {
+  try {
+    res.json(await frameDisplayService.getSettings());
+  } catch (error) {
+    console.error("Error fetching photo frame settings:", error);
+    res.status(500).json({ message: "Failed to fetch photo frame settings" });
+  }
+});
+
+router.put("/display", async (req, res) => {
+  try {
+    const parsed = frameDisplaySettingsSchema.partial().safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid photo frame settings", errors: parsed.error.errors });
+    }
+    res.json(await frameDisplayService.updateSettings(parsed.data));
+  } catch (error) {
+    console.error("Error updating photo frame settings:", error);
+    res.status(500).json({ message: "Failed to update photo frame settings" });
+  }
+});
+
+// Create new frame target
+router.post("/", async (req, res) => {
+  try {
+    const validatedData = insertFrameTargetSchema.parse(req.body);
+    const target = await storage.createFrameTarget(validatedData);
+    res.status(201).json(target);
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({
+        message: "Invalid frame target data",
+        errors: error.errors
+      });
+    }
+    console.error("Error creating frame target:", error);
+    res.status(500).json({ message: "Failed to create frame target" });
+  }
+});
+
+// Update frame target
+router.patch("/:id", async (req, res) => {
+  try {
+    const updateData = insertFrameTargetSchema.partial().parse(req.body);
+    const target = await storage.updateFrameTarget(req.params.id, updateData);
+    if (!target) {
+      return res.status(404).json({ message: "Frame target not found" });
+    }
+    res.json(target);
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({
+        message: "Invalid frame target data",
+        errors: error.errors
+      });
+    }
+    console.error("Error updating frame target:", error);
+    res.status(500).json({ message: "Failed to update frame target" });
+  }
+});
+
+// Delete frame target (published files are left in place)
+router.delete("/:id", async (req, res) => {
+  try {
+    const deleted = await storage.deleteFrameTarget(req.params.id);
+    if (!deleted) {
+      return res.status(404).json({ message: "Frame target not found" });
+    }
+    res.json({ message: "Frame target deleted successfully" });
+  } catch (error) {
+    console.error("Error deleting frame target:", error);
+    res.status(500).json({ message: "Failed to delete frame target" });
+  }
+});
+
+// Refresh a frame target now
+router.post("/:id/sync", async (req, res) => {
+  try {
+    const target = await storage.getFrameTarget(req.params.id);
+    if (!target) {
+      return res.status(404).json({ message: "Frame target not found" });
+    }
+    const operation = operationRegistry.start('frame_sync', `Syncing frame target ${target.name}`, requestedOperationId(req));
+    try {
+      const result = await frameSyncService.syncTarget(target, operation.token);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    console.error("Error syncing frame target:", error);
+    res.status(500).json({ message: "Failed to sync frame target" });
+  }
+});
+
+export default router;
@@ -0,0 +1,201 @@
+import express from "express";
+import { importSessionService, startImportSchema, osShareImportSchema, ImportSessionError } from "../services/importSessions";
+import { digikamImportService, digikamImportSchema, DigikamImportError } from "../services/digikamImport";
+import { immichImportService, immichImportSchema, ImmichImportError } from "../services/immichImport";
+import { picasaImportService, picasaImportSchema, PicasaImportError } from "../services/picasaImport";
+import { libraryArchiveService, archiveImportSchema, LibraryArchiveError } from "../services/libraryArchive";
+import { operationRegistry, requestedOperationId, OperationCancelledError } from "../services/operations";
+
+const router = express.Router();
+
+// Recent import sessions, newest first
+router.get("/", async (req, res) => {
+  try {
+    const limit = req.query.limit ? Math.min(200, Math.max(1, parseInt(req.query.limit as string) || 50)) : 50;
+    res.json(await importSessionService.listImports(limit));
+  } catch (error) {
+    console.error("Error listing imports:", error);
+    res.status(500).json({ message: "Failed to list imports" });
+  }
+});
+
+// Queue an import of files and folders on this machine; returns the session right away
+router.post("/", async (req, res) => {
+  try {
+    const parsed = startImportSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid import request", errors: parsed.error.errors });
+    }
+    const { paths, ...options } = parsed.data;
+    const session = await importSessionService.startImport(paths, options);
+    res.status(202).json(session);
+  } catch (error) {
+    if (error instanceof ImportSessionError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error starting import:", error);
+    res.status(500).json({ message: "Failed to start import" });
+  }
+});
+
+// Files handed over by the OS share menu, Send To or a file association (sent by the desktop shell)
+router.post("/os-share", async (req, res) => {
+  try {
+    const parsed = osShareImportSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid shared files", errors: parsed.error.errors });
+    }
+    res.status(202).json(await importSessionService.importFromOsShare(parsed.data.paths));
+  } catch (error) {
+    if (error instanceof ImportSessionError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error importing shared files:", error);
+    res.status(500).json({ message: "Failed to import shared files" });
+  }
+});
+
+// Bring in a digiKam collection: files, tag tree, ratings, people with face regions and albums
+router.post("/digikam", async (req, res) => {
+  try {
+    const parsed = digikamImportSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid digiKam import", errors: parsed.error.errors });
+    }
+    const { databasePath, ...options } = parsed.data;
+
+    const operation = operationRegistry.start('import', 'Importing digiKam collection', requestedOperationId(req));
+    try {
+      const result = await digikamImportService.importDatabase(databasePath, options, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof DigikamImportError) {
+      return res.status(400).json({ message: error.message });
+    }
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Import was cancelled", cancelled: true });
+    }
+    console.error("Error importing digiKam collection:", error);
+    res.status(500).json({ message: "Failed to import digiKam collection" });
+  }
+});
+
+// Bring in the stars and named faces Picasa kept in .picasa.ini files under a folder
+router.post("/picasa", async (req, res) => {
+  try {
+    const parsed = picasaImportSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid Picasa import", errors: parsed.error.errors });
+    }
+    const { folderPath, ...options } = parsed.data;
+
+    const operation = operationRegistry.start('import', `Importing Picasa folders under ${folderPath}`, requestedOperationId(req));
+    try {
+      const result = await picasaImportService.importFolder(folderPath, options, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof PicasaImportError) {
+      return res.status(400).json({ message: error.message });
+    }
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Import was cancelled", cancelled: true });
+    }
+    console.error("Error importing Picasa folders:", error);
+    res.status(500).json({ message: "Failed to import Picasa folders" });
+  }
+});
+
+// Merge a portable archive from another Pictallion library: photos, people, faces, tags and albums
+router.post("/archive", async (req, res) => {
+  try {
+    const parsed = archiveImportSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "path is required", errors: parsed.error.errors });
+    }
+
+    const operation = operationRegistry.start('import', `Importing archive ${parsed.data.path}`, requestedOperationId(req));
+    try {
+      const result = await libraryArchiveService.importArchive(parsed.data.path, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof LibraryArchiveError) {
+      return res.status(400).json({ message: error.message });
+    }
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Import was cancelled", cancelled: true });
+    }
+    console.error("Error importing archive:", error);
+    res.status(500).json({ message: "Failed to import archive" });
+  }
+});
+
+// Bring in a library from an Immich server; running it again resumes an interrupted import
+router.post("/immich", async (req, res) => {
+  try {
+    const parsed = immichImportSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid Immich import", errors: parsed.error.errors });
+    }
+    const { serverUrl, apiKey, ...options } = parsed.data;
+
+    const operation = operationRegistry.start('import', `Importing from Immich at ${serverUrl}`, requestedOperationId(req));
+    try {
+      const result = await immichImportService.importServer(serverUrl, apiKey, options, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof ImmichImportError) {
+      return res.status(400).json({ message: error.message });
+    }
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Import was cancelled; run it again to resume", cancelled: true });
+    }
+    console.error("Error importing from Immich:", error);
+    res.status(500).json({ message: "Failed to import from Immich" });
+  }
+});
+
+// Server-sent events stream of "started", "file" (hash and dedupe decision), "finished" and "shared"
+router.get("/events", (req, res) => {
+  importSessionService.addEventClient(res);
+});
+
+router.get("/:id", async (req, res) => {
+  try {
+    const session = await importSessionService.getImport(req.params.id);
+    if (!session) {
+      return res.status(404).json({ message: "Import not found" });
+    }
+    res.json(session);
+  } catch (error) {
+    console.error("Error getting import:", error);
+    res.status(500).json({ message: "Failed to get import" });
+  }
+});
+
+// Cancel a queued import, or stop a running one after the file it is on
+router.post("/:id/cancel", async (req, res) => {
+  try {
+    const session = await importSessionService.cancelImport(req.params.id);
+    if (!session) {
+      return res.status(404).json({ message: "Import not found" });
+    }
+    res.json(session);
+  } catch (error) {
+    console.error("Error cancelling import:", error);
+    res.status(500).json({ message: "Failed to cancel import" });
+  }
+});
+
+export default router;
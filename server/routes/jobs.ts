@@ -0,0 +1,69 @@
+import express from "express";
+import { z } from "zod";
+import { jobQueue } from "../services/jobQueue";
+
+const router = express.Router();
+
+const faceRecognitionSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+});
+
+// Recent background jobs, newest first
+router.get("/", async (req, res) => {
+  try {
+    const limit = req.query.limit ? Math.min(200, Math.max(1, parseInt(req.query.limit as string) || 50)) : 50;
+    res.json(await jobQueue.listJobs(limit));
+  } catch (error) {
+    console.error("Error listing jobs:", error);
+    res.status(500).json({ message: "Failed to list jobs" });
+  }
+});
+
+// Queue face detection and embeddings for photos; returns as soon as the job is stored
+router.post("/face-recognition", async (req, res) => {
+  try {
+    const parsed = faceRecognitionSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "photoIds must be a non-empty array", errors: parsed.error.errors });
+    }
+    const job = await jobQueue.enqueueFaceRecognition(parsed.data.photoIds);
+    res.status(202).json(job);
+  } catch (error) {
+    console.error("Error queueing face recognition:", error);
+    res.status(500).json({ message: "Failed to queue face recognition" });
+  }
+});
+
+// Server-sent events stream of "queued", "progress" and "finished" job updates
+router.get("/events", (req, res) => {
+  jobQueue.addEventClient(res);
+});
+
+router.get("/:id", async (req, res) => {
+  try {
+    const job = await jobQueue.getJob(req.params.id);
+    if (!job) {
+      return res.status(404).json({ message: "Job not found" });
+    }
+    res.json(job);
+  } catch (error) {
+    console.error("Error getting job:", error);
+    res.status(500).json({ message: "Failed to get job" });
+  }
+});
+
+// Cancel a queued job, or stop a running one after the photo it is working on
+router.post("/:id/cancel", async (req, res) => {
+  try {
+    const job = await jobQueue.cancel(req.params.id);
+    if (!job) {
+      return res.status(404).json({ message: "Job not found" });
+    }
+    res.json(job);
+  } catch (error) {
+    console.error("Error cancelling job:", error);
+    res.status(500).json({ message: "Failed to cancel job" });
+  }
+});
+
+export default router;
@@ -0,0 +1,55 @@
+import express from "express";
+import { operationJournal } from "../services/operationJournal";
+import { PhotoLockedError } from "../services/photoLock";
+import { counterConsistencyService } from "../services/counterConsistency";
+
+const router = express.Router();
+
+// Recent undoable actions, newest first; undone ones have undoneAt set
+router.get("/", async (req, res) => {
+  try {
+    const limit = req.query.limit ? Math.min(200, Math.max(1, parseInt(req.query.limit as string) || 20)) : 20;
+    res.json(await operationJournal.listRecent(limit));
+  } catch (error) {
+    console.error("Error listing recent operations:", error);
+    res.status(500).json({ message: "Failed to list recent operations" });
+  }
+});
+
+// Undo the most recent action that is not undone yet
+router.post("/undo", async (req, res) => {
+  try {
+    const result = await operationJournal.undoLast();
+    if (!result) {
+      return res.status(404).json({ message: "Nothing to undo" });
+    }
+    counterConsistencyService.scheduleCheck();
+    res.json(result);
+  } catch (error) {
+    if (error instanceof PhotoLockedError) {
+      return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+    }
+    console.error("Error undoing operation:", error);
+    res.status(500).json({ message: "Failed to undo operation" });
+  }
+});
+
+// Redo the most recently undone action
+router.post("/redo", async (req, res) => {
+  try {
+    const result = await operationJournal.redoLast();
+    if (!result) {
+      return res.status(404).json({ message: "Nothing to redo" });
+    }
+    counterConsistencyService.scheduleCheck();
+    res.json(result);
+  } catch (error) {
+    if (error instanceof PhotoLockedError) {
+      return res.status(423).json({ message: error.message, photoId: error.photoId, action: error.action });
+    }
+    console.error("Error redoing operation:", error);
+    res.status(500).json({ message: "Failed to redo operation" });
+  }
+});
+
+export default router;
@@ -0,0 +1,210 @@
+import express from "express";
+import { z } from "zod";
+import { libraryInitService } from "../services/libraryInit";
+import { libraryMigrationService, LibraryMigrationError } from "../services/libraryMigration";
+import { libraryIntegrityService, integrityScanSchema, resolveIntegritySchema } from "../services/libraryIntegrity";
+import { libraryBackupService, LibraryBackupError, createBackupSchema, restoreBackupSchema } from "../services/libraryBackup";
+import { operationRegistry, requestedOperationId, OperationCancelledError } from "../services/operations";
+import { counterConsistencyService } from "../services/counterConsistency";
+import { getLibraryRoot } from "../utils/libraryPaths";
+
+const router = express.Router();
+
+const migrateSchema = z.object({
+  oldRoot: z.string().min(1).optional(), // defaults to the current library
+  newRoot: z.string().min(1),
+  removeOld: z.boolean().optional(),
+});
+
+const initializeOptionsSchema = z.object({
+  createMissing: z.boolean().optional(),
+  applyMigrations: z.boolean().optional(),
+  seedDefaults: z.boolean().optional(),
+});
+
+// Current library root and the report from its last initialization
+router.get("/", async (req, res) => {
+  res.json({ root: getLibraryRoot(), lastInitialization: libraryInitService.getLastReport() });
+});
+
+// Re-run initialization for the current library and report each step
+router.post("/initialize", async (req, res) => {
+  try {
+    const options = initializeOptionsSchema.parse(req.body ?? {});
+    const report = await libraryInitService.initializeLibrary(getLibraryRoot(), options);
+    res.status(report.success ? 200 : 500).json(report);
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({ message: "Invalid initialization options", errors: error.errors });
+    }
+    console.error("Error initializing library:", error);
+    res.status(500).json({ message: "Failed to initialize library" });
+  }
+});
+
+// Libraries found in the locations earlier versions may have used
+router.get("/candidates", async (req, res) => {
+  try {
+    const candidates = await libraryMigrationService.locateExistingLibraries();
+    res.json(candidates);
+  } catch (error) {
+    console.error("Error locating libraries:", error);
+    res.status(500).json({ message: "Failed to locate libraries" });
+  }
+});
+
+// Copy a library to a new location, verify it and switch to it
+router.post("/migrate", async (req, res) => {
+  try {
+    const { oldRoot, newRoot, removeOld } = migrateSchema.parse(req.body);
+    if (operationRegistry.list().length > 0) {
+      return res.status(409).json({ message: "Wait for running operations to finish before moving the library" });
+    }
+
+    const operation = operationRegistry.start('library_migration', `Moving library to ${newRoot}`, requestedOperationId(req));
+    try {
+      const result = await libraryMigrationService.migrateLibrary(
+        oldRoot ?? getLibraryRoot(),
+        newRoot,
+        { removeOld },
+        operation.token,
+        (completed, total) => operation.progress(completed, total)
+      );
+      res.status(result.success || result.cancelled ? 200 : 500).json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({ message: "Invalid migration request", errors: error.errors });
+    }
+    if (error instanceof LibraryMigrationError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error migrating library:", error);
+    res.status(500).json({ message: "Failed to migrate library" });
+  }
+});
+
+// Compare cached counters (face counts, location photo counts) with the rows they count
+router.get("/consistency", async (req, res) => {
+  try {
+    res.json(await counterConsistencyService.check(false));
+  } catch (error) {
+    console.error("Error checking counter consistency:", error);
+    res.status(500).json({ message: "Failed to check counter consistency" });
+  }
+});
+
+// Write the true values back to any counters that have drifted
+router.post("/consistency/repair", async (req, res) => {
+  try {
+    res.json(await counterConsistencyService.check(true));
+  } catch (error) {
+    console.error("Error repairing counters:", error);
+    res.status(500).json({ message: "Failed to repair counters" });
+  }
+});
+
+// Reconcile media files with photo rows: orphaned files, missing files and changed content, with disk usage per tier
+router.post("/integrity/scan", async (req, res) => {
+  try {
+    const parsed = integrityScanSchema.safeParse(req.body ?? {});
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid scan options", errors: parsed.error.errors });
+    }
+
+    const operation = operationRegistry.start('integrity_scan', 'Checking library integrity', requestedOperationId(req));
+    try {
+      const report = await libraryIntegrityService.scan(parsed.data.verifyHashes, operation.token, operation.progress);
+      res.json({ ...report, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Integrity scan was cancelled", cancelled: true });
+    }
+    console.error("Error scanning library integrity:", error);
+    res.status(500).json({ message: "Failed to scan library integrity" });
+  }
+});
+
+// Relink, re-import or quarantine discrepancies found by a scan; each item reports its own outcome
+router.post("/integrity/resolve", async (req, res) => {
+  try {
+    const parsed = resolveIntegritySchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "items must be a non-empty array of actions", errors: parsed.error.errors });
+    }
+    res.json({ results: await libraryIntegrityService.resolve(parsed.data.items) });
+  } catch (error) {
+    console.error("Error resolving integrity issues:", error);
+    res.status(500).json({ message: "Failed to resolve integrity issues" });
+  }
+});
+
+// Backups in the configured backup folder, or the one given as ?destination=
+router.get("/backups", async (req, res) => {
+  try {
+    const destination = typeof req.query.destination === 'string' && req.query.destination ? req.query.destination : undefined;
+    res.json(await libraryBackupService.listBackups(destination));
+  } catch (error) {
+    console.error("Error listing backups:", error);
+    res.status(500).json({ message: "Failed to list backups" });
+  }
+});
+
+// Snapshot the database with a manifest of media hashes
+router.post("/backups", async (req, res) => {
+  try {
+    const parsed = createBackupSchema.safeParse(req.body ?? {});
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid backup request", errors: parsed.error.errors });
+    }
+
+    const operation = operationRegistry.start('backup', 'Backing up library', requestedOperationId(req));
+    try {
+      const backup = await libraryBackupService.createBackup(parsed.data.destination, 'manual', operation.token, operation.progress);
+      res.json({ ...backup, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Backup was cancelled", cancelled: true });
+    }
+    console.error("Error backing up library:", error);
+    res.status(500).json({ message: "Failed to back up library" });
+  }
+});
+
+// Validate a backup against this library and, unless dryRun, replace the database with it
+router.post("/backups/restore", async (req, res) => {
+  try {
+    const parsed = restoreBackupSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "path is required", errors: parsed.error.errors });
+    }
+    const { path: backupPath, dryRun } = parsed.data;
+
+    const operation = operationRegistry.start('backup', `Restoring backup ${backupPath}`, requestedOperationId(req));
+    try {
+      const result = await libraryBackupService.restoreBackup(backupPath, dryRun, operation.token, operation.progress);
+      res.json({ ...result, operationId: operation.id });
+    } finally {
+      operation.finish();
+    }
+  } catch (error) {
+    if (error instanceof LibraryBackupError) {
+      return res.status(400).json({ message: error.message });
+    }
+    if (error instanceof OperationCancelledError) {
+      return res.status(409).json({ message: "Restore was cancelled; the database is unchanged", cancelled: true });
+    }
+    console.error("Error restoring backup:", error);
+    res.status(500).json({ message: "Failed to restore backup" });
+  }
+});
+
+export default router;
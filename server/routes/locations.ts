@@ -2,6 +2,13 @@ import express from "express";
 import { storage } from "../storage";
 import { insertLocationSchema } from "@shared/schema";
 import { z } from "zod";
+import {
+  geoCorrectionService,
+  setPhotoLocationSchema,
+  locationOutlierQuerySchema,
+  fixLocationOutliersSchema,
+  GeoCorrectionError,
+} from "../services/geoCorrection";
 
 const router = express.Router();
 
@@ -38,6 +45,52 @@ router.get("/hotspots", async (req, res) => {
   }
 });
 
+// Set the position of photos, from coordinates or a saved place
+router.post("/assign", async (req, res) => {
+  try {
+    const parsed = setPhotoLocationSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid photo location", errors: parsed.error.errors });
+    }
+    res.json(await geoCorrectionService.setPhotoLocation(parsed.data));
+  } catch (error) {
+    if (error instanceof GeoCorrectionError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error setting photo location:", error);
+    res.status(500).json({ message: "Failed to set photo location" });
+  }
+});
+
+// Photos whose GPS disagrees with the photos taken just before and after them
+router.get("/outliers", async (req, res) => {
+  try {
+    const query = locationOutlierQuerySchema.safeParse(req.query);
+    if (!query.success) {
+      return res.status(400).json({ message: "Invalid outlier query", errors: query.error.errors });
+    }
+    res.json(await geoCorrectionService.findLocationOutliers(query.data));
+  } catch (error) {
+    console.error("Error finding location outliers:", error);
+    res.status(500).json({ message: "Failed to find location outliers" });
+  }
+});
+
+// Move outliers to their neighbours' average position
+router.post("/outliers/fix", async (req, res) => {
+  try {
+    const parsed = fixLocationOutliersSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid outlier fix", errors: parsed.error.errors });
+    }
+    const { photoIds, writeToFile, ...query } = parsed.data;
+    res.json(await geoCorrectionService.fixLocationOutliers(query, photoIds, writeToFile));
+  } catch (error) {
+    console.error("Error fixing location outliers:", error);
+    res.status(500).json({ message: "Failed to fix location outliers" });
+  }
+});
+
 // Get specific location
 router.get("/:id", async (req, res) => {
   try {
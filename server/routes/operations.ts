@@ -0,0 +1,45 @@
+import express from "express";
+import { operationRegistry } from "../services/operations";
+import { indexingStatusService } from "../services/indexingStatus";
+
+const router = express.Router();
+
+// List running cancellable operations
+router.get("/", async (req, res) => {
+  res.json(operationRegistry.list());
+});
+
+// Library-wide progress of thumbnails, metadata, hashes, faces and AI processing
+router.get("/indexing", async (req, res) => {
+  try {
+    res.json(await indexingStatusService.getStatus());
+  } catch (error) {
+    console.error("Error getting indexing status:", error);
+    res.status(500).json({ message: "Failed to get indexing status" });
+  }
+});
+
+// Server-sent events stream of indexing "progress" and "status" updates
+router.get("/indexing/events", (req, res) => {
+  indexingStatusService.addEventClient(res);
+});
+
+// Get a running operation's progress
+router.get("/:id", async (req, res) => {
+  const operation = operationRegistry.get(req.params.id);
+  if (!operation) {
+    return res.status(404).json({ message: "Operation not found" });
+  }
+  res.json(operation);
+});
+
+// Cancel an operation; it stops after the item it is currently working on
+router.post("/:id/cancel", async (req, res) => {
+  const cancelled = operationRegistry.cancel(req.params.id);
+  if (!cancelled) {
+    return res.status(404).json({ message: "Operation not found" });
+  }
+  res.json({ success: true, message: "Cancellation requested" });
+});
+
+export default router;
@@ -0,0 +1,67 @@
+import express from "express";
+import { shareLinkService, ShareAccessError } from "../services/shareLinks";
+
+// What someone with a share link can reach, outside /api. The password, when the link has one,
+// comes in the X-Share-Password header or a password query parameter (for image tags).
+const router = express.Router();
+
+function sharePassword(req: express.Request): string | undefined {
+  const header = req.get('x-share-password');
+  if (header) return header;
+  return typeof req.query.password === 'string' ? req.query.password : undefined;
+}
+
+function sendError(res: express.Response, error: unknown, activity: string, message: string) {
+  if (error instanceof ShareAccessError) {
+    return res.status(error.status).json({ message: error.message });
+  }
+  console.error(`Error ${activity}:`, error);
+  res.status(500).json({ message });
+}
+
+// Opening the link counts as a view
+router.get("/:token", async (req, res) => {
+  try {
+    const share = await shareLinkService.open(req.params.token, sharePassword(req), true);
+    const photos = await shareLinkService.getSharedPhotos(share);
+    const base = `${req.baseUrl}/${share.token}/photos`;
+    res.set('Cache-Control', 'no-store');
+    res.json({
+      title: share.title,
+      expiresAt: share.expiresAt,
+      allowOriginals: share.allowOriginals,
+      photos: photos.map(photo => ({
+        id: photo.id,
+        width: photo.width,
+        height: photo.height,
+        url: `${base}/${photo.id}`,
+        originalUrl: share.allowOriginals ? `${base}/${photo.id}/original` : null,
+      })),
+    });
+  } catch (error) {
+    sendError(res, error, "opening share", "Failed to open share");
+  }
+});
+
+router.get("/:token/photos/:photoId", async (req, res) => {
+  try {
+    const share = await shareLinkService.open(req.params.token, sharePassword(req), false);
+    const file = await shareLinkService.getPhotoFile(share, req.params.photoId, false);
+    res.set('Cache-Control', 'private, max-age=3600');
+    res.type('image/jpeg').send(file.image);
+  } catch (error) {
+    sendError(res, error, "loading shared photo", "Failed to load shared photo");
+  }
+});
+
+router.get("/:token/photos/:photoId/original", async (req, res) => {
+  try {
+    const share = await shareLinkService.open(req.params.token, sharePassword(req), false);
+    const file = await shareLinkService.getPhotoFile(share, req.params.photoId, true);
+    res.download(file.path!, file.filename);
+  } catch (error) {
+    sendError(res, error, "downloading shared photo", "Failed to download shared photo");
+  }
+});
+
+export default router;
@@ -0,0 +1,160 @@
+import express from "express";
+import { storage } from "../storage";
+import { photoStackService } from "../services/photoStacks";
+import { insertSelectionSchema } from "@shared/schema";
+import { z } from "zod";
+
+const router = express.Router();
+
+const photoIdsSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+});
+
+// Get all selections with photo counts
+router.get("/", async (req, res) => {
+  try {
+    const selections = await storage.getSelections();
+    res.json(selections);
+  } catch (error) {
+    console.error("Error fetching selections:", error);
+    res.status(500).json({ message: "Failed to fetch selections" });
+  }
+});
+
+// Create a selection, optionally seeded with photos
+router.post("/", async (req, res) => {
+  try {
+    const validatedData = insertSelectionSchema.parse(req.body);
+    const { photoIds } = z.object({ photoIds: z.array(z.string()).optional() }).parse(req.body);
+    const selection = await storage.createSelection(validatedData, photoIds);
+    res.status(201).json(selection);
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({
+        message: "Invalid selection data",
+        errors: error.errors
+      });
+    }
+    console.error("Error creating selection:", error);
+    res.status(500).json({ message: "Failed to create selection" });
+  }
+});
+
+// Get a single selection
+router.get("/:id", async (req, res) => {
+  try {
+    const selection = await storage.getSelection(req.params.id);
+    if (!selection) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    res.json(selection);
+  } catch (error) {
+    console.error("Error fetching selection:", error);
+    res.status(500).json({ message: "Failed to fetch selection" });
+  }
+});
+
+// Rename or describe a selection
+router.patch("/:id", async (req, res) => {
+  try {
+    const updateData = insertSelectionSchema.partial().parse(req.body);
+    const selection = await storage.updateSelection(req.params.id, updateData);
+    if (!selection) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    res.json(selection);
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({
+        message: "Invalid selection data",
+        errors: error.errors
+      });
+    }
+    console.error("Error updating selection:", error);
+    res.status(500).json({ message: "Failed to update selection" });
+  }
+});
+
+// Delete a selection (the photos themselves are untouched)
+router.delete("/:id", async (req, res) => {
+  try {
+    const deleted = await storage.deleteSelection(req.params.id);
+    if (!deleted) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    res.json({ message: "Selection deleted successfully" });
+  } catch (error) {
+    console.error("Error deleting selection:", error);
+    res.status(500).json({ message: "Failed to delete selection" });
+  }
+});
+
+// List the photos in a selection
+router.get("/:id/photos", async (req, res) => {
+  try {
+    const selection = await storage.getSelection(req.params.id);
+    if (!selection) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    const photos = await storage.getSelectionPhotos(selection.id);
+    res.json(await photoStackService.applyStackMode(photos, photoStackService.shouldExpand(req.query.expandStacks)));
+  } catch (error) {
+    console.error("Error fetching selection photos:", error);
+    res.status(500).json({ message: "Failed to fetch selection photos" });
+  }
+});
+
+// Add photos to a selection
+router.post("/:id/photos", async (req, res) => {
+  try {
+    const { photoIds } = photoIdsSchema.parse(req.body);
+    const selection = await storage.getSelection(req.params.id);
+    if (!selection) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    const added = await storage.addToSelection(selection.id, photoIds);
+    res.json({ added });
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({ message: "photoIds must be a non-empty array", errors: error.errors });
+    }
+    console.error("Error adding photos to selection:", error);
+    res.status(500).json({ message: "Failed to add photos to selection" });
+  }
+});
+
+// Remove photos from a selection
+router.delete("/:id/photos", async (req, res) => {
+  try {
+    const { photoIds } = photoIdsSchema.parse(req.body);
+    const selection = await storage.getSelection(req.params.id);
+    if (!selection) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    const removed = await storage.removeFromSelection(selection.id, photoIds);
+    res.json({ removed });
+  } catch (error) {
+    if (error instanceof z.ZodError) {
+      return res.status(400).json({ message: "photoIds must be a non-empty array", errors: error.errors });
+    }
+    console.error("Error removing photos from selection:", error);
+    res.status(500).json({ message: "Failed to remove photos from selection" });
+  }
+});
+
+// Empty a selection
+router.post("/:id/clear", async (req, res) => {
+  try {
+    const selection = await storage.getSelection(req.params.id);
+    if (!selection) {
+      return res.status(404).json({ message: "Selection not found" });
+    }
+    const removed = await storage.clearSelection(selection.id);
+    res.json({ removed });
+  } catch (error) {
+    console.error("Error clearing selection:", error);
+    res.status(500).json({ message: "Failed to clear selection" });
+  }
+});
+
+export default router;
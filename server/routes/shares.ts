@@ -0,0 +1,46 @@
+import express from "express";
+import { shareLinkService, createShareSchema, ShareAccessError } from "../services/shareLinks";
+
+const router = express.Router();
+
+// All share links, with their status; passwords are never returned
+router.get("/", async (req, res) => {
+  try {
+    res.json(await shareLinkService.listShares());
+  } catch (error) {
+    console.error("Error fetching shares:", error);
+    res.status(500).json({ message: "Failed to fetch shares" });
+  }
+});
+
+router.post("/", async (req, res) => {
+  try {
+    const parsed = createShareSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid share", errors: parsed.error.errors });
+    }
+    res.status(201).json(await shareLinkService.createShare(parsed.data));
+  } catch (error) {
+    if (error instanceof ShareAccessError) {
+      return res.status(error.status).json({ message: error.message });
+    }
+    console.error("Error creating share:", error);
+    res.status(500).json({ message: "Failed to create share" });
+  }
+});
+
+// Revoke a link; it stays listed so its history is kept
+router.delete("/:id", async (req, res) => {
+  try {
+    const share = await shareLinkService.revokeShare(req.params.id);
+    if (!share) {
+      return res.status(404).json({ message: "Share not found" });
+    }
+    res.json(share);
+  } catch (error) {
+    console.error("Error revoking share:", error);
+    res.status(500).json({ message: "Failed to revoke share" });
+  }
+});
+
+export default router;
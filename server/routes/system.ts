@@ -0,0 +1,55 @@
+import express from "express";
+import { shutdownService } from "../services/shutdown";
+import { startupMetrics } from "../services/startupMetrics";
+
+const router = express.Router();
+
+const LOOPBACK_ADDRESSES = new Set(['127.0.0.1', '::1', '::ffff:127.0.0.1']);
+
+// Server-sent events stream; emits a "shutdown" event when the server is stopping
+router.get("/events", (req, res) => {
+  shutdownService.addEventClient(res);
+});
+
+// Run the shutdown sequence without exiting (used by the desktop shell before it stops the server)
+router.post("/shutdown", async (req, res) => {
+  if (!LOOPBACK_ADDRESSES.has(req.socket.remoteAddress ?? '')) {
+    return res.status(403).json({ message: "Shutdown can only be requested locally" });
+  }
+  try {
+    const checkpoint = await shutdownService.shutdown(typeof req.body?.reason === 'string' ? req.body.reason : 'requested');
+    res.json(checkpoint);
+  } catch (error) {
+    console.error("Error shutting down:", error);
+    res.status(500).json({ message: "Failed to shut down cleanly" });
+  }
+});
+
+// Startup phase timings, deferred initialization and time to the first gallery grid
+router.get("/startup-metrics", (req, res) => {
+  res.json(startupMetrics.getMetrics());
+});
+
+// Operations that were interrupted by the previous shutdown, with what was left to do
+router.get("/interrupted-operations", async (req, res) => {
+  try {
+    const checkpoint = await shutdownService.getInterruptedOperations();
+    res.json(checkpoint ?? { interruptedOperations: [] });
+  } catch (error) {
+    console.error("Error reading shutdown checkpoint:", error);
+    res.status(500).json({ message: "Failed to read interrupted operations" });
+  }
+});
+
+// Dismiss the interrupted operations once they have been resumed or abandoned
+router.delete("/interrupted-operations", async (req, res) => {
+  try {
+    await shutdownService.clearInterruptedOperations();
+    res.json({ success: true });
+  } catch (error) {
+    console.error("Error clearing shutdown checkpoint:", error);
+    res.status(500).json({ message: "Failed to clear interrupted operations" });
+  }
+});
+
+export default router;
@@ -0,0 +1,16 @@
+import express from "express";
+import { volumeStatusService } from "../services/volumeStatus";
+
+const router = express.Router();
+
+// Volumes the library and its external folders live on, and the photos on disconnected ones
+router.get("/status", async (req, res) => {
+  try {
+    res.json(await volumeStatusService.getVolumeStatus());
+  } catch (error) {
+    console.error("Error getting volume status:", error);
+    res.status(500).json({ message: "Failed to get volume status" });
+  }
+});
+
+export default router;
@@ -0,0 +1,53 @@
+import express from "express";
+import { folderWatcherService, addWatchFolderSchema, WatchFolderError } from "../services/folderWatcher";
+
+const router = express.Router();
+
+// Watched folders, with whether each is being watched right now
+router.get("/", async (req, res) => {
+  try {
+    res.json(await folderWatcherService.listWatchFolders());
+  } catch (error) {
+    console.error("Error listing watch folders:", error);
+    res.status(500).json({ message: "Failed to list watch folders" });
+  }
+});
+
+// Start watching a folder; new files in it are imported into the bronze tier
+router.post("/", async (req, res) => {
+  try {
+    const parsed = addWatchFolderSchema.safeParse(req.body);
+    if (!parsed.success) {
+      return res.status(400).json({ message: "Invalid watch folder", errors: parsed.error.errors });
+    }
+    const { path, ...options } = parsed.data;
+    const folder = await folderWatcherService.addWatchFolder(path, options);
+    res.status(201).json(folder);
+  } catch (error) {
+    if (error instanceof WatchFolderError) {
+      return res.status(400).json({ message: error.message });
+    }
+    console.error("Error adding watch folder:", error);
+    res.status(500).json({ message: "Failed to add watch folder" });
+  }
+});
+
+// Server-sent events stream of "detected", "imported", "duplicate" and "failed" files
+router.get("/events", (req, res) => {
+  folderWatcherService.addEventClient(res);
+});
+
+// Stop watching a folder (imported photos are kept)
+router.delete("/:id", async (req, res) => {
+  try {
+    if (!(await folderWatcherService.removeWatchFolder(req.params.id))) {
+      return res.status(404).json({ message: "Watch folder not found" });
+    }
+    res.json({ success: true });
+  } catch (error) {
+    console.error("Error removing watch folder:", error);
+    res.status(500).json({ message: "Failed to remove watch folder" });
+  }
+});
+
+export default router;
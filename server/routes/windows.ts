@@ -0,0 +1,40 @@
+import express from "express";
+import { windowService, openPhotoWindowSchema, publishWindowEventSchema, WINDOW_KINDS, type WindowKind } from "../services/windows";
+
+const router = express.Router();
+
+// Windows currently connected to the event stream
+router.get("/", (req, res) => {
+  res.json(windowService.listWindows());
+});
+
+// Event stream for one window: ?windowId=...&kind=gallery|viewer
+router.get("/events", (req, res) => {
+  const windowId = typeof req.query.windowId === 'string' ? req.query.windowId : '';
+  const kind = (req.query.kind ?? 'gallery') as WindowKind;
+  if (!windowId || !WINDOW_KINDS.includes(kind)) {
+    return res.status(400).json({ message: "windowId and a valid kind are required" });
+  }
+  windowService.connect(res, windowId, kind);
+});
+
+// Show a photo in a viewer window, reusing an open one when there is one
+router.post("/open-photo", (req, res) => {
+  const parsed = openPhotoWindowSchema.safeParse(req.body);
+  if (!parsed.success) {
+    return res.status(400).json({ message: "Invalid open photo request", errors: parsed.error.errors });
+  }
+  res.json(windowService.openPhotoWindow(parsed.data.photoId, parsed.data.sourceWindowId));
+});
+
+// Send an event to one other window, or to all of them
+router.post("/publish", (req, res) => {
+  const parsed = publishWindowEventSchema.safeParse(req.body);
+  if (!parsed.success) {
+    return res.status(400).json({ message: "Invalid window event", errors: parsed.error.errors });
+  }
+  const { event, data, sourceWindowId, targetWindowId } = parsed.data;
+  res.json({ delivered: windowService.publish(event, data, sourceWindowId, targetWindowId) });
+});
+
+export default router;
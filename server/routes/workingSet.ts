@@ -0,0 +1,37 @@
+import express from "express";
+import { workingSetService, workingSetPushSchema, workingSetPopSchema } from "../services/workingSet";
+
+const router = express.Router();
+
+// Photos in the working set, in the order they were added
+router.get("/", (req, res) => {
+  res.json(workingSetService.list());
+});
+
+router.post("/push", (req, res) => {
+  const parsed = workingSetPushSchema.safeParse(req.body);
+  if (!parsed.success) {
+    return res.status(400).json({ message: "photoIds must be a non-empty array", errors: parsed.error.errors });
+  }
+  res.json(workingSetService.push(parsed.data.photoIds));
+});
+
+// Remove the given photos, or the last one added when none are given
+router.post("/pop", (req, res) => {
+  const parsed = workingSetPopSchema.safeParse(req.body ?? {});
+  if (!parsed.success) {
+    return res.status(400).json({ message: "Invalid working set request", errors: parsed.error.errors });
+  }
+  res.json(workingSetService.pop(parsed.data.photoIds));
+});
+
+router.delete("/", (req, res) => {
+  res.json(workingSetService.clear());
+});
+
+// Server-sent "changed" events with the whole set, shared by every open window
+router.get("/events", (req, res) => {
+  workingSetService.addEventClient(res);
+});
+
+export default router;
@@ -1,15 +1,38 @@
-import { eq, and, or, gte, lte, like, isNotNull, inArray, sql } from "drizzle-orm";
+import { eq, and, isNotNull, isNull, sql, type SQL } from "drizzle-orm";
 import { storage } from "../storage";
 import { db } from "../db";
-import { fileVersions, mediaAssets, people, faces, collections, collectionPhotos } from "@shared/schema";
-import type { SmartCollectionRules } from "@shared/schema";
+import { fileVersions, mediaAssets, faces, collectionPhotos, photoSources } from "@shared/schema";
+import type { FileVersion, MediaAsset, DaylightPhase, WeatherCondition } from "@shared/schema";
+import { getCaptureDate } from "../utils/photoDates";
+import { getOccurrencesBetween } from "../utils/eventRecurrence";
+import type { StackAnnotation } from "./photoStacks";
+import { TAG_SEPARATOR } from "./tagHierarchy";
+import {
+  photoListingService,
+  byCreatedAt,
+  byTakenAt,
+  notSuperseded,
+  takenAt,
+  type PhotoColumns,
+  type PhotoCondition,
+  type PhotoListing,
+  type SortKey,
+} from "./photoListing";
+import { resolveDateBounds, type DateField, type RelativeDateRange } from "../utils/dateRanges";
 
 export interface SearchFilters {
   query?: string;
   tier?: 'bronze' | 'silver' | 'gold';
   rating?: { min?: number; max?: number };
-  dateRange?: { start?: Date; end?: Date };
+  pickFlag?: Array<'pick' | 'reject' | 'none'>; // any of these; 'none' matches unflagged photos
+  dateRange?: {
+    start?: Date;
+    end?: Date;
+    relative?: RelativeDateRange; // resolved when the search runs, in place of start and end
+    field?: DateField; // capturedAt when left out
+  };
   keywords?: string[];
+  tags?: string[]; // any of these tags, or a tag nested under one ("animals" matches "animals/dogs/corgi")
   eventType?: string[];
   eventName?: string;
   location?: string;
@@ -22,174 +45,447 @@ export interface SearchFilters {
   collections?: string[];
   isReviewed?: boolean;
   perceptualHashSimilarity?: { hash: string; threshold: number };
+  orientation?: 'portrait' | 'landscape' | 'square';
+  aspectRatio?: { min?: number; max?: number }; // width / height
+  fileSize?: { min?: number; max?: number }; // bytes
+  megapixels?: { min?: number; max?: number };
+  iso?: { min?: number; max?: number };
+  exposureTime?: { min?: number; max?: number }; // seconds
+  aperture?: { min?: number; max?: number }; // f-number
+  focalLength?: { min?: number; max?: number }; // mm
+  isPanorama?: boolean;
+  promotionScore?: { min?: number; max?: number }; // 0-100; unscored photos never match
+  eventId?: string; // taken during an occurrence of this event
+  daylight?: DaylightPhase[]; // any of these; photos without a capture date and position never match
+  weather?: WeatherCondition[];
+  context?: SearchContext; // search only within these; every scope given must hold
+  // Exclusions: a photo matching any of these is left out
+  notTagIds?: string[]; // tag library ids; tags nested under them are excluded too
+  notPersonIds?: string[];
+  notTiers?: Array<'bronze' | 'silver' | 'gold'>;
+  noGps?: boolean; // only photos without GPS coordinates
+  noFaces?: boolean; // only photos without detected faces; ignored faces don't count
+}
+
+/**
+ * Narrows a search to the photos of an album, a person, a saved place or an
+ * import, so other filters combine with it in one query
+ */
+export interface SearchContext {
+  albumId?: string;
+  personId?: string;
+  placeId?: string; // photos whose GPS position is within the place's radius
+  importSessionId?: string;
 }
 
 export interface SortOptions {
-  field: 'createdAt' | 'rating' | 'fileSize' | 'confidence' | 'eventName';
+  field: 'takenAt' | 'createdAt' | 'rating' | 'fileSize' | 'confidence' | 'eventName';
   direction: 'asc' | 'desc';
 }
 
 export interface SearchResult {
-  photos: Array<{
-    id: string;
-    filePath: string;
-    tier: string;
-    metadata?: any;
-    mediaAsset: { originalFilename: string };
-    createdAt: string;
-  }>;
-  totalCount: number;
+  photos: Array<FileVersion & { mediaAsset: MediaAsset } & StackAnnotation>;
+  totalCount: number; // stacks count once unless expanded
+  nextCursor: string | null;
   facets: {
     tiers: Record<string, number>;
     ratings: Record<string, number>;
+    pickFlags: Record<string, number>;
     eventTypes: Record<string, number>;
     cameras: Record<string, number>;
     mimeTypes: Record<string, number>;
     keywords: Record<string, number>;
+    daylight: Record<string, number>;
+    years: Record<string, number>; // by year of the date the date filter applies to
   };
 }
 
+const RELATED_WEIGHTS: Record<string, number> = { visual: 0.35, semantic: 0.25, people: 0.25, time: 0.15 };
+const RELATED_TIME_HALF_LIFE_DAYS = 3;
+const SQUARE_TOLERANCE = 0.02;
+
+function oneOf(value: SQL, values: readonly unknown[]): SQL {
+  return sql`${value} IN (${sql.join(values.map(item => sql`${item}`), sql`, `)})`;
+}
+
+// Case-insensitive substring match; the needle is already lower case
+function contains(value: SQL, needle: string): SQL {
+  return sql`strpos(lower(COALESCE(${value}, '')), ${needle}) > 0`;
+}
+
+// Values that are missing never fall in a range
+function inRange(value: SQL, range: { min?: number; max?: number }): SQL {
+  return and(
+    sql`${value} IS NOT NULL`,
+    range.min !== undefined ? sql`${value} >= ${range.min}` : undefined,
+    range.max !== undefined ? sql`${value} <= ${range.max}` : undefined
+  )!;
+}
+
 class AdvancedSearchService {
   
   /**
-   * Perform comprehensive search across all photos with filters and facets
+   * Perform comprehensive search across all photos with filters and facets.
+   * Like the default photo view, each asset is listed once as its highest
+   * tier version, and stacks are collapsed to their cover unless expandStacks
+   * is set; counts and facets follow the same mode. A cursor from a previous
+   * page takes precedence over offset.
    */
   async searchPhotos(
     filters: SearchFilters = {},
-    sort: SortOptions = { field: 'createdAt', direction: 'desc' },
+    sort: SortOptions = { field: 'takenAt', direction: 'desc' },
     limit: number = 50,
-    offset: number = 0
+    offset: number = 0,
+    expandStacks: boolean = false,
+    cursor?: string
   ): Promise<SearchResult> {
-    
-    // For now, use a simplified approach with the existing storage interface
-    // TODO: Implement full advanced search when direct database access is available
-    let allPhotos = await storage.getAllFileVersions();
+    const listing: PhotoListing = {
+      where: await this.buildSearch(filters),
+      order: [this.getSortKey(sort)],
+      collapseStacks: !expandStacks,
+    };
+    const page = await photoListingService.page(listing, {
+      limit: Number.isInteger(limit) && limit > 0 ? limit : 50,
+      offset: Number.isInteger(offset) && offset > 0 ? offset : 0,
+      cursor,
+    });
 
-    // Apply simple filters using array operations
-    let filteredPhotos = allPhotos;
+    return {
+      photos: page.photos,
+      totalCount: page.totalCount,
+      nextCursor: page.nextCursor,
+      facets: await this.generateFacets(expandStacks, filters.dateRange?.field),
+    };
+  }
+
+  /**
+   * Number of photos matching the filters, counted the way searchPhotos
+   * lists them, so a client can size a virtual scroller before paging
+   */
+  async countPhotos(filters: SearchFilters = {}, expandStacks: boolean = false): Promise<number> {
+    return photoListingService.count({ where: await this.buildSearch(filters), order: [], collapseStacks: !expandStacks });
+  }
+
+  /**
+   * Return the ids of every photo matching the filters, without pagination
+   */
+  async findMatchingPhotoIds(filters: SearchFilters = {}): Promise<string[]> {
+    return photoListingService.ids({ where: await this.buildFilter(filters), order: [], collapseStacks: false });
+  }
+
+  /**
+   * What searchPhotos lists: the photos matching the filters, one version per
+   * asset
+   */
+  private async buildSearch(filters: SearchFilters): Promise<PhotoCondition> {
+    const matches = await this.buildFilter(filters);
+    return photo => and(matches(photo), this.listedVersion(photo, !!filters.tier));
+  }
+
+  /**
+   * The version of an asset search results show, as the default photo view
+   * does: gold over silver, and bronze only when a tier filter asks for it
+   */
+  private listedVersion(photo: PhotoColumns, includeBronze = false): SQL | undefined {
+    return and(includeBronze ? undefined : sql`${photo.tier} <> 'bronze'`, notSuperseded(photo));
+  }
+
+  /**
+   * The filters as one SQL condition on a photo. What needs more than the
+   * photo row (the event, the context, the excluded tags) is looked up first.
+   */
+  private async buildFilter(filters: SearchFilters): Promise<PhotoCondition> {
+    const lookups = [await this.loadEvent(filters), await this.loadContext(filters), await this.loadExclusions(filters)]
+      .filter((test): test is PhotoCondition => !!test);
+    return photo => and(this.applyFilters(photo, filters), ...lookups.map(test => test(photo)));
+  }
+
+  /**
+   * Photos taken during an occurrence of the event. An unknown event matches
+   * nothing rather than everything.
+   */
+  private async loadEvent(filters: SearchFilters): Promise<PhotoCondition | undefined> {
+    if (!filters.eventId) return undefined;
+    const event = await storage.getEvent(filters.eventId);
+    if (!event) return () => sql`false`;
+
+    // Occurrences over the span the library's photos were taken in
+    const [span] = await db
+      .select({
+        first: sql<Date | null>`MIN(${takenAt(fileVersions)})`.mapWith(fileVersions.createdAt),
+        last: sql<Date | null>`MAX(${takenAt(fileVersions)})`.mapWith(fileVersions.createdAt),
+      })
+      .from(fileVersions)
+      .where(isNull(fileVersions.deletedAt));
+    const starts = span?.first && span.last ? getOccurrencesBetween(event, span.first, span.last) : [];
+    if (starts.length === 0) return () => sql`false`;
+
+    const durationDays = Math.max(1, event.durationDays ?? 1);
+    const ends = starts.map(start => new Date(start.getFullYear(), start.getMonth(), start.getDate() + durationDays));
+    return photo => sql`EXISTS (
+      SELECT 1 FROM unnest(
+        ${sql.param(starts.map(date => date.toISOString()))}::timestamp[],
+        ${sql.param(ends.map(date => date.toISOString()))}::timestamp[]
+      ) AS occurrence(starts_at, ends_at)
+      WHERE ${takenAt(photo)} >= occurrence.starts_at AND ${takenAt(photo)} < occurrence.ends_at
+    )`;
+  }
+
+  private async loadExclusions(filters: SearchFilters): Promise<PhotoCondition | undefined> {
+    const tests: PhotoCondition[] = [];
+
+    if (filters.notTagIds && filters.notTagIds.length > 0) {
+      const excludedTags = (await storage.getTagLibrary())
+        .filter(entry => filters.notTagIds!.includes(entry.id))
+        .map(entry => entry.tag);
+      if (excludedTags.length > 0) tests.push(photo => sql`NOT ${this.hasTag(photo, excludedTags)}`);
+    }
+
+    if (filters.notPersonIds && filters.notPersonIds.length > 0) {
+      const personIds = filters.notPersonIds;
+      tests.push(photo => sql`NOT EXISTS (
+        SELECT 1 FROM ${faces} face
+        WHERE face.photo_id = ${photo.id} AND NOT face.ignored AND ${oneOf(sql`face.person_id`, personIds)}
+      )`);
+    }
+
+    // Ignored faces don't count
+    if (filters.noFaces) {
+      tests.push(photo => sql`NOT EXISTS (SELECT 1 FROM ${faces} face WHERE face.photo_id = ${photo.id} AND NOT face.ignored)`);
+    }
+
+    if (tests.length === 0) return undefined;
+    return photo => and(...tests.map(test => test(photo)));
+  }
+
+  /**
+   * Resolve the search context to a condition on each photo. An unknown
+   * album, person, place or import matches nothing rather than everything.
+   */
+  private async loadContext(filters: SearchFilters): Promise<PhotoCondition | undefined> {
+    const context = filters.context;
+    if (!context) return undefined;
+    const tests: PhotoCondition[] = [];
+
+    if (context.albumId) {
+      const albumId = context.albumId;
+      tests.push(photo => sql`EXISTS (
+        SELECT 1 FROM ${collectionPhotos} album_photo
+        WHERE album_photo.photo_id = ${photo.id} AND album_photo.collection_id = ${albumId}
+      )`);
+    }
+
+    if (context.personId) {
+      const personId = context.personId;
+      tests.push(photo => sql`EXISTS (
+        SELECT 1 FROM ${faces} face WHERE face.photo_id = ${photo.id} AND face.person_id = ${personId} AND NOT face.ignored
+      )`);
+    }
+
+    if (context.placeId) {
+      const place = await storage.getLocation(context.placeId);
+      if (!place) return () => sql`false`;
+      const latitude = parseFloat(place.latitude);
+      const longitude = parseFloat(place.longitude);
+      const radius = place.radius ?? 100;
+      // Haversine distance in meters, as locationClusteringService.calculateDistance works it out
+      tests.push(photo => sql`${photo.gpsLatitude} IS NOT NULL AND ${photo.gpsLongitude} IS NOT NULL AND 12742000 * asin(LEAST(1, sqrt(
+        power(sin(radians(${photo.gpsLatitude} - ${latitude}) / 2), 2) +
+        cos(radians(${latitude})) * cos(radians(${photo.gpsLatitude})) * power(sin(radians(${photo.gpsLongitude} - ${longitude}) / 2), 2)
+      ))) <= ${radius}`);
+    }
+
+    // Imports are linked to photos through the source paths recorded for each file
+    if (context.importSessionId) {
+      const session = await storage.getImportSession(context.importSessionId);
+      if (!session) return () => sql`false`;
+      const files = session.files;
+      tests.push(photo => sql`${photo.mediaAssetId} IN (
+        SELECT source.media_asset_id FROM ${photoSources} source WHERE source.source_path = ANY(${sql.param(files)}::text[])
+      )`);
+    }
+
+    return photo => and(...tests.map(test => test(photo)));
+  }
+
+  /**
+   * Apply the search filters that only need the photo row, as a SQL condition
+   * on it
+   */
+  private applyFilters(photo: PhotoColumns, filters: SearchFilters): SQL | undefined {
+    const conditions: Array<SQL | undefined> = [];
 
     if (filters.tier) {
-      filteredPhotos = filteredPhotos.filter(photo => photo.tier === filters.tier);
+      conditions.push(sql`${photo.tier} = ${filters.tier}`);
+    }
+
+    if (filters.notTiers && filters.notTiers.length > 0) {
+      conditions.push(sql`NOT ${oneOf(sql`${photo.tier}`, filters.notTiers)}`);
     }
 
     if (filters.rating?.min !== undefined || filters.rating?.max !== undefined) {
-      filteredPhotos = filteredPhotos.filter(photo => {
-        const rating = photo.rating || 0;
-        if (filters.rating?.min !== undefined && rating < filters.rating.min) return false;
-        if (filters.rating?.max !== undefined && rating > filters.rating.max) return false;
-        return true;
-      });
+      const rating = sql`COALESCE(${photo.rating}, 0)`;
+      if (filters.rating.min !== undefined) conditions.push(sql`${rating} >= ${filters.rating.min}`);
+      if (filters.rating.max !== undefined) conditions.push(sql`${rating} <= ${filters.rating.max}`);
+    }
+
+    if (filters.pickFlag && filters.pickFlag.length > 0) {
+      conditions.push(oneOf(sql`COALESCE(${photo.pickFlag}, 'none')`, filters.pickFlag));
     }
 
     if (filters.query) {
       const query = filters.query.toLowerCase();
-      filteredPhotos = filteredPhotos.filter(photo => {
-        const filename = photo.filePath.toLowerCase();
-        const location = (photo.location || '').toLowerCase();
-        const eventName = (photo.eventName || '').toLowerCase();
-        const keywords = (photo.keywords || []).join(' ').toLowerCase();
-        const shortDesc = ((photo.metadata as any)?.ai?.shortDescription || '').toLowerCase();
-        const longDesc = ((photo.metadata as any)?.ai?.longDescription || '').toLowerCase();
-        
-        return filename.includes(query) || 
-               location.includes(query) || 
-               eventName.includes(query) ||
-               keywords.includes(query) ||
-               shortDesc.includes(query) ||
-               longDesc.includes(query);
-      });
+      const fields = [
+        sql`${photo.filePath}`,
+        sql`${photo.location}`,
+        sql`${photo.eventName}`,
+        sql`array_to_string(${photo.keywords}, ' ')`,
+        sql`${photo.metadata}->'ai'->>'shortDescription'`,
+        sql`${photo.metadata}->'ai'->>'longDescription'`,
+      ];
+      conditions.push(sql`(${sql.join(fields.map(field => contains(field, query)), sql` OR `)})`);
     }
 
     if (filters.mimeType && filters.mimeType.length > 0) {
-      filteredPhotos = filteredPhotos.filter(photo => 
-        filters.mimeType!.includes(photo.mimeType)
-      );
+      conditions.push(oneOf(sql`${photo.mimeType}`, filters.mimeType));
     }
 
     if (filters.location) {
-      filteredPhotos = filteredPhotos.filter(photo => 
-        (photo.location || '').toLowerCase().includes(filters.location!.toLowerCase())
-      );
+      conditions.push(contains(sql`${photo.location}`, filters.location.toLowerCase()));
     }
 
     if (filters.eventName) {
-      filteredPhotos = filteredPhotos.filter(photo => 
-        (photo.eventName || '').toLowerCase().includes(filters.eventName!.toLowerCase())
-      );
+      conditions.push(contains(sql`${photo.eventName}`, filters.eventName.toLowerCase()));
     }
 
     if (filters.eventType && filters.eventType.length > 0) {
-      filteredPhotos = filteredPhotos.filter(photo => 
-        photo.eventType && filters.eventType!.includes(photo.eventType)
-      );
+      conditions.push(oneOf(sql`${photo.eventType}`, filters.eventType));
+    }
+
+    // Camera and lens names are canonicalized at import, so facet values match exactly
+    if (filters.camera) {
+      conditions.push(sql`lower(COALESCE(${photo.camera}, '')) = ${filters.camera.toLowerCase()}`);
+    }
+
+    if (filters.lens) {
+      conditions.push(sql`lower(COALESCE(${photo.lens}, '')) = ${filters.lens.toLowerCase()}`);
+    }
+
+    // Dates are when the photo was taken unless the filter names another date
+    if (filters.dateRange?.start || filters.dateRange?.end || filters.dateRange?.relative) {
+      const { start, end } = resolveDateBounds(filters.dateRange);
+      const date = this.dateOf(photo, filters.dateRange.field);
+      conditions.push(sql`${date} IS NOT NULL`);
+      if (Number.isFinite(start)) conditions.push(sql`${date} >= ${new Date(start).toISOString()}`);
+      if (Number.isFinite(end)) conditions.push(sql`${date} <= ${new Date(end).toISOString()}`);
     }
 
     if (filters.keywords && filters.keywords.length > 0) {
-      filteredPhotos = filteredPhotos.filter(photo => {
-        const photoKeywords = photo.keywords || [];
-        return filters.keywords!.some(keyword => 
-          photoKeywords.some(pk => pk.toLowerCase().includes(keyword.toLowerCase()))
-        );
-      });
+      const keywords = filters.keywords.map(keyword => contains(sql`keyword`, keyword.toLowerCase()));
+      conditions.push(sql`EXISTS (SELECT 1 FROM unnest(${photo.keywords}) AS keyword WHERE ${sql.join(keywords, sql` OR `)})`);
+    }
+
+    if (filters.tags && filters.tags.length > 0) {
+      conditions.push(this.hasTag(photo, filters.tags));
     }
 
     if (filters.isReviewed !== undefined) {
-      filteredPhotos = filteredPhotos.filter(photo => photo.isReviewed === filters.isReviewed);
+      conditions.push(sql`${photo.isReviewed} = ${filters.isReviewed}`);
     }
 
     if (filters.hasGPS) {
-      filteredPhotos = filteredPhotos.filter(photo => photo.location && photo.location.length > 0);
-    }
-
-    // Apply sorting
-    filteredPhotos.sort((a, b) => {
-      let aValue, bValue;
-      
-      switch (sort.field) {
-        case 'rating':
-          aValue = a.rating || 0;
-          bValue = b.rating || 0;
-          break;
-        case 'fileSize':
-          aValue = a.fileSize || 0;
-          bValue = b.fileSize || 0;
-          break;
-        case 'eventName':
-          aValue = a.eventName || '';
-          bValue = b.eventName || '';
-          break;
-        case 'createdAt':
-        default:
-          aValue = new Date(a.createdAt).getTime();
-          bValue = new Date(b.createdAt).getTime();
-          break;
-      }
-      
-      if (sort.direction === 'desc') {
-        return bValue > aValue ? 1 : -1;
-      } else {
-        return aValue > bValue ? 1 : -1;
-      }
-    });
+      conditions.push(sql`COALESCE(${photo.location}, '') <> ''`);
+    }
 
-    const totalCount = filteredPhotos.length;
-    const paginatedPhotos = filteredPhotos.slice(offset, offset + limit);
+    if (filters.noGps) {
+      conditions.push(sql`(${photo.gpsLatitude} IS NULL OR ${photo.gpsLongitude} IS NULL)`);
+    }
 
-    // Generate simple facets
-    const facets = this.generateSimpleFacets(allPhotos);
+    if (filters.daylight && filters.daylight.length > 0) {
+      conditions.push(oneOf(sql`${photo.daylightPhase}`, filters.daylight));
+    }
 
-    return {
-      photos: paginatedPhotos.map((photo: any) => ({
-        id: photo.id,
-        filePath: photo.filePath,
-        tier: photo.tier,
-        metadata: photo.metadata,
-        mediaAsset: { originalFilename: photo.mediaAsset?.originalFilename || 'Unknown' },
-        createdAt: photo.createdAt.toISOString()
-      })),
-      totalCount,
-      facets
-    };
+    if (filters.weather && filters.weather.length > 0) {
+      conditions.push(oneOf(sql`${photo.environment}->'weather'->>'condition'`, filters.weather));
+    }
+
+    if (filters.isPanorama !== undefined) {
+      conditions.push(sql`COALESCE(${photo.isPanorama}, false) = ${filters.isPanorama}`);
+    }
+
+    // Dimension-based filters only match photos with stored width/height. Panoramas
+    // are excluded: their 2:1 projected frame says nothing about how they were shot.
+    const measured = sql`COALESCE(${photo.width}, 0) > 0 AND COALESCE(${photo.height}, 0) > 0`;
+    const flat = sql`NOT COALESCE(${photo.isPanorama}, false)`;
+    const ratio = sql`(${photo.width}::float8 / ${photo.height})`;
+
+    if (filters.orientation) {
+      // The same square tolerance as getOrientation
+      const square = sql`abs(${ratio} - 1) <= ${SQUARE_TOLERANCE}`;
+      const shape = filters.orientation === 'square' ? square
+        : filters.orientation === 'landscape' ? sql`NOT (${square}) AND ${photo.width} > ${photo.height}`
+        : sql`NOT (${square}) AND ${photo.width} <= ${photo.height}`;
+      conditions.push(sql`${flat} AND ${measured} AND ${shape}`);
+    }
+
+    if (filters.aspectRatio?.min !== undefined || filters.aspectRatio?.max !== undefined) {
+      conditions.push(sql`${flat} AND ${measured}`);
+      if (filters.aspectRatio.min !== undefined) conditions.push(sql`${ratio} >= ${filters.aspectRatio.min}`);
+      if (filters.aspectRatio.max !== undefined) conditions.push(sql`${ratio} <= ${filters.aspectRatio.max}`);
+    }
+
+    if (filters.fileSize?.min !== undefined || filters.fileSize?.max !== undefined) {
+      if (filters.fileSize.min !== undefined) conditions.push(sql`${photo.fileSize} >= ${filters.fileSize.min}`);
+      if (filters.fileSize.max !== undefined) conditions.push(sql`${photo.fileSize} <= ${filters.fileSize.max}`);
+    }
+
+    if (filters.megapixels?.min !== undefined || filters.megapixels?.max !== undefined) {
+      conditions.push(measured, inRange(sql`(${photo.width}::float8 * ${photo.height} / 1000000)`, filters.megapixels));
+    }
+
+    if (filters.promotionScore) {
+      conditions.push(inRange(sql`${photo.promotionScore}`, filters.promotionScore));
+    }
+
+    // Photos without the exposure value in their EXIF never match a range on it
+    if (filters.iso) conditions.push(inRange(sql`${photo.iso}`, filters.iso));
+    if (filters.exposureTime) conditions.push(inRange(sql`${photo.exposureTime}`, filters.exposureTime));
+    if (filters.aperture) conditions.push(inRange(sql`${photo.aperture}`, filters.aperture));
+    if (filters.focalLength) conditions.push(inRange(sql`${photo.focalLength}`, filters.focalLength));
+
+    return and(...conditions);
+  }
+
+  /**
+   * The photo's date for a date filter field; null for files without an
+   * EXIF modification date
+   */
+  private dateOf(photo: PhotoColumns, field: DateField = 'capturedAt'): SQL {
+    switch (field) {
+      case 'capturedAt':
+        return takenAt(photo);
+      case 'importedAt':
+        return sql`${photo.createdAt}`;
+      case 'modifiedAt':
+        return sql`photo_metadata_date(${photo.metadata}->'exif'->>'modifyDate')`;
+    }
+  }
+
+  /**
+   * Whether any of the photo's tags is one of these or nested under one
+   * ("animals" matches "animals/dogs/corgi")
+   */
+  private hasTag(photo: PhotoColumns, tags: string[]): SQL {
+    const matches = tags.map(tag => {
+      const ancestor = tag.toLowerCase().replace(/\/+$/, '');
+      return sql`(lower(tag) = ${ancestor} OR starts_with(lower(tag), ${ancestor + TAG_SEPARATOR}))`;
+    });
+    return sql`EXISTS (
+      SELECT 1 FROM jsonb_array_elements_text(
+        CASE WHEN jsonb_typeof(${photo.metadata}->'ai'->'aiTags') = 'array' THEN ${photo.metadata}->'ai'->'aiTags' ELSE '[]'::jsonb END
+      ) AS tag
+      WHERE ${sql.join(matches, sql` OR `)}
+    )`;
   }
 
   /**
@@ -243,163 +539,136 @@ class AdvancedSearchService {
   }
 
   /**
-   * Auto-update smart collections based on their rules
+   * Rank related photos by a blended score of visual (phash), semantic
+   * (embedding or AI tags), shared people and capture-time proximity.
+   * Signals missing on either photo are left out and the weights renormalized.
    */
-  async updateSmartCollections(): Promise<void> {
-    const smartCollections = await db
-      .select()
-      .from(collections)
-      .where(eq(collections.isSmartCollection, true));
-
-    for (const collection of smartCollections) {
-      if (!collection.smartRules) continue;
-
-      try {
-        const rules = collection.smartRules as SmartCollectionRules;
-        const matchingPhotos = await this.findPhotosMatchingRules(rules);
-
-        // Clear existing photos in smart collection
-        await db
-          .delete(collectionPhotos)
-          .where(eq(collectionPhotos.collectionId, collection.id));
-
-        // Add matching photos
-        if (matchingPhotos.length > 0) {
-          await db.insert(collectionPhotos).values(
-            matchingPhotos.map(photoId => ({
-              collectionId: collection.id,
-              photoId: photoId
-            }))
-          );
-        }
-
-        console.log(`Updated smart collection "${collection.name}" with ${matchingPhotos.length} photos`);
-      } catch (error) {
-        console.error(`Failed to update smart collection ${collection.name}:`, error);
-      }
+  async findRelatedPhotos(
+    photoId: string,
+    limit: number = 12
+  ): Promise<Array<FileVersion & { mediaAsset: MediaAsset; score: number; signals: Record<string, number> }>> {
+    const allPhotos = await storage.getAllFileVersionsWithAssets();
+    const source = allPhotos.find(photo => photo.id === photoId);
+    if (!source) return [];
+
+    const peopleByPhoto = new Map<string, Set<string>>();
+    for (const face of await storage.getAllFaces()) {
+      if (!face.personId || face.ignored) continue;
+      const set = peopleByPhoto.get(face.photoId) ?? new Set<string>();
+      set.add(face.personId);
+      peopleByPhoto.set(face.photoId, set);
     }
-  }
 
-  /**
-   * Find photos matching smart collection rules
-   */
-  private async findPhotosMatchingRules(rules: SmartCollectionRules): Promise<string[]> {
-    let query;
-    const conditions = rules.rules.map(rule => this.buildRuleCondition(rule));
-    if (conditions.length > 0) {
-      if (rules.operator === 'AND') {
-        query = db.select({ id: fileVersions.id }).from(fileVersions).where(and(...conditions));
-      } else {
-        query = db.select({ id: fileVersions.id }).from(fileVersions).where(or(...conditions));
-      }
-    } else {
-      query = db.select({ id: fileVersions.id }).from(fileVersions);
-    }
-    const results = await query;
-    return results.map((r: any) => r.id);
-  }
+    const sourcePeople = peopleByPhoto.get(source.id);
+    const sourceDate = getCaptureDate(source);
+    const sourceAi = (source.metadata as any)?.ai;
 
-  /**
-   * Build SQL condition from smart collection rule
-   */
-  private buildRuleCondition(rule: any): any {
-    const { field, operator, value } = rule;
+    const scored = allPhotos
+      .filter(photo => photo.mediaAssetId !== source.mediaAssetId && photo.mimeType.startsWith('image/'))
+      .map(photo => {
+        const signals: Record<string, number> = {};
 
-    switch (field) {
-      case 'rating':
-        switch (operator) {
-          case 'equals': return eq(fileVersions.rating, value);
-          case 'greater_than': return gte(fileVersions.rating, value);
-          case 'less_than': return lte(fileVersions.rating, value);
-          case 'between': return and(gte(fileVersions.rating, value[0]), lte(fileVersions.rating, value[1]));
+        if (source.perceptualHash && photo.perceptualHash) {
+          signals.visual = this.calculateHashSimilarity(source.perceptualHash, photo.perceptualHash) / 100;
         }
-        break;
 
-      case 'keywords':
-        switch (operator) {
-          case 'contains': return sql`${fileVersions.keywords} && ${[value]}`;
-          case 'in': return sql`${fileVersions.keywords} && ${value}`;
+        const ai = (photo.metadata as any)?.ai;
+        if (Array.isArray(sourceAi?.embedding) && Array.isArray(ai?.embedding)) {
+          signals.semantic = Math.max(0, this.cosineSimilarity(sourceAi.embedding, ai.embedding));
+        } else if (sourceAi?.aiTags?.length && ai?.aiTags?.length) {
+          signals.semantic = this.jaccard(sourceAi.aiTags, ai.aiTags);
         }
-        break;
 
-      case 'eventType':
-        switch (operator) {
-          case 'equals': return eq(fileVersions.eventType, value);
-          case 'in': return inArray(fileVersions.eventType, value);
+        const photoPeople = peopleByPhoto.get(photo.id);
+        if (sourcePeople?.size && photoPeople?.size) {
+          signals.people = this.jaccard(Array.from(sourcePeople), Array.from(photoPeople));
         }
-        break;
 
-      case 'tier':
-        return eq(fileVersions.tier, value);
+        const photoDate = getCaptureDate(photo);
+        if (sourceDate && photoDate) {
+          const days = Math.abs(sourceDate.getTime() - photoDate.getTime()) / (24 * 60 * 60 * 1000);
+          signals.time = Math.pow(0.5, days / RELATED_TIME_HALF_LIFE_DAYS);
+        }
 
-      case 'isReviewed':
-        return eq(fileVersions.isReviewed, value);
+        let weighted = 0;
+        let totalWeight = 0;
+        for (const [signal, value] of Object.entries(signals)) {
+          weighted += RELATED_WEIGHTS[signal] * value;
+          totalWeight += RELATED_WEIGHTS[signal];
+        }
+        // Penalise matches backed by only a few signals
+        const coverage = totalWeight / Object.values(RELATED_WEIGHTS).reduce((a, b) => a + b, 0);
+        const score = totalWeight > 0 ? (weighted / totalWeight) * (0.5 + 0.5 * coverage) : 0;
 
-      default:
-        return sql`true`; // fallback
-    }
+        return { ...photo, score: Math.round(score * 1000) / 1000, signals };
+      })
+      .filter(photo => photo.score > 0)
+      .sort((a, b) => b.score - a.score);
+
+    return scored.slice(0, limit);
   }
 
   /**
-   * Generate simple facets for filtering UI
+   * Facet counts for the filtering UI over the whole library, with versions
+   * and stacks counted the way results list them. Tiers count every tier,
+   * bronze included, as a tier filter would list it.
    */
-  private generateSimpleFacets(allPhotos: any[]): SearchResult['facets'] {
-    const tiers: Record<string, number> = {};
-    const ratings: Record<string, number> = {};
-    const eventTypes: Record<string, number> = {};
-    const cameras: Record<string, number> = {};
-    const mimeTypes: Record<string, number> = {};
-    const keywords: Record<string, number> = {};
-
-    allPhotos.forEach(photo => {
-      // Count tiers
-      tiers[photo.tier] = (tiers[photo.tier] || 0) + 1;
-
-      // Count ratings
-      if (photo.rating) {
-        ratings[String(photo.rating)] = (ratings[String(photo.rating)] || 0) + 1;
-      }
-
-      // Count event types
-      if (photo.eventType) {
-        eventTypes[photo.eventType] = (eventTypes[photo.eventType] || 0) + 1;
-      }
-
-      // Count mime types
-      mimeTypes[photo.mimeType] = (mimeTypes[photo.mimeType] || 0) + 1;
-
-      // Count cameras from metadata
-      const camera = (photo.metadata as any)?.exif?.camera;
-      if (camera) {
-        cameras[camera] = (cameras[camera] || 0) + 1;
-      }
-
-      // Count keywords
-      if (photo.keywords) {
-        photo.keywords.forEach((keyword: string) => {
-          keywords[keyword] = (keywords[keyword] || 0) + 1;
-        });
-      }
-    });
+  private async generateFacets(expandStacks: boolean, dateField?: DateField): Promise<SearchResult['facets']> {
+    const library: PhotoListing = { where: photo => this.listedVersion(photo), order: [], collapseStacks: !expandStacks };
+    const everyTier: PhotoListing = { where: photo => this.listedVersion(photo, true), order: [], collapseStacks: !expandStacks };
+    const count = (value: SQL, perElement = false) => photoListingService.countByValue(library, value, perElement);
+
+    const [tiers, ratings, pickFlags, eventTypes, cameras, mimeTypes, keywords, daylight, years] = await Promise.all([
+      photoListingService.countByValue(everyTier, sql`${fileVersions.tier}`),
+      count(sql`NULLIF(${fileVersions.rating}, 0)`),
+      count(sql`${fileVersions.pickFlag}`),
+      count(sql`NULLIF(${fileVersions.eventType}, '')`),
+      count(sql`NULLIF(${fileVersions.camera}, '')`),
+      count(sql`${fileVersions.mimeType}`),
+      count(sql`${fileVersions.keywords}`, true),
+      // Light at capture (golden hour, midday, ...)
+      count(sql`${fileVersions.daylightPhase}`),
+      count(sql`EXTRACT(YEAR FROM ${this.dateOf(fileVersions, dateField)})::int`),
+    ]);
+
+    return { tiers, ratings, pickFlags, eventTypes, cameras, mimeTypes, keywords, daylight, years };
+  }
 
-    return {
-      tiers,
-      ratings,
-      eventTypes,
-      cameras,
-      mimeTypes,
-      keywords
-    };
+  private getSortKey(sort: SortOptions): SortKey {
+    const direction = sort.direction === 'asc' ? 'asc' : 'desc';
+    switch (sort.field) {
+      case 'rating':
+        return { value: sql`COALESCE(${fileVersions.rating}, 0)`, type: 'integer', direction };
+      case 'fileSize':
+        return { value: sql`${fileVersions.fileSize}`, type: 'integer', direction };
+      case 'eventName':
+        return { value: sql`COALESCE(${fileVersions.eventName}, '')`, type: 'text', direction };
+      case 'takenAt':
+        return byTakenAt(direction);
+      case 'createdAt':
+      default:
+        return byCreatedAt(direction);
+    }
   }
 
-  private getSortColumn(field: string) {
-    switch (field) {
-      case 'createdAt': return fileVersions.createdAt;
-      case 'rating': return fileVersions.rating;
-      case 'fileSize': return fileVersions.fileSize;
-      case 'eventName': return fileVersions.eventName;
-      default: return fileVersions.createdAt;
+  private cosineSimilarity(a: number[], b: number[]): number {
+    if (a.length !== b.length || a.length === 0) return 0;
+    let dot = 0, normA = 0, normB = 0;
+    for (let i = 0; i < a.length; i++) {
+      dot += a[i] * b[i];
+      normA += a[i] * a[i];
+      normB += b[i] * b[i];
     }
+    return normA && normB ? dot / (Math.sqrt(normA) * Math.sqrt(normB)) : 0;
+  }
+
+  private jaccard(a: string[], b: string[]): number {
+    const setA = new Set(a.map(value => value.toLowerCase()));
+    const setB = new Set(b.map(value => value.toLowerCase()));
+    let intersection = 0;
+    setA.forEach(value => { if (setB.has(value)) intersection++; });
+    const union = setA.size + setB.size - intersection;
+    return union > 0 ? intersection / union : 0;
   }
 
   private calculateHashSimilarity(hash1: string, hash2: string): number {
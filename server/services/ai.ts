@@ -1,138 +1,35 @@
-import fs from "fs/promises";
 import path from "path";
 import sharp from "sharp";
 import type { AIMetadata } from "@shared/schema";
 import { logger } from "../utils/logger.js";
 import { promptManager } from "./promptManager";
-
-// AI Provider configuration
-export type AIProvider = "ollama" | "openai" | "both";
-
-interface AIConfig {
-  provider: AIProvider;
-  ollama: {
-    baseUrl: string;
-    visionModel: string;
-    textModel: string;
-  };
-  openai: {
-    apiKey: string;
-    model: string;
-  };
-}
-
-const DEFAULT_CONFIG: AIConfig = {
-  provider: (process.env.AI_PROVIDER as AIProvider) || (process.env.OPENAI_API_KEY ? "openai" : "ollama"),
-  ollama: {
-    baseUrl: process.env.OLLAMA_BASE_URL || "http://localhost:11434",
-    visionModel: process.env.OLLAMA_MODEL || "llava:latest",
-    textModel: process.env.OLLAMA_TEXT_MODEL || "llama3.2:latest"
-  },
-  openai: {
-    apiKey: process.env.OPENAI_API_KEY || "",
-    model: process.env.OPENAI_MODEL || "gpt-4o"
-  }
-};
-
-class AIService {
-  private config: AIConfig = DEFAULT_CONFIG;
-
-  setConfig(config: Partial<AIConfig>): void {
-    this.config = { ...this.config, ...config };
-  }
-
-  getConfig(): AIConfig {
-    return this.config;
-  }
-
-  async analyzeImage(imagePath: string, preferredProvider?: AIProvider): Promise<AIMetadata> {
-    return this.analyzeImageWithPeopleContext(imagePath, preferredProvider, []);
-  }
-
-  async analyzeImageWithPeopleContext(
-    imagePath: string, 
-    preferredProvider?: AIProvider, 
-    peopleContext?: Array<{
-      name: string;
-      ageInPhoto?: number | null;
-      relationships: Array<{ type: string; otherPersonId: string }>;
-      boundingBox: any;
-    }>
-  ): Promise<AIMetadata> {
-    const provider = preferredProvider || this.config.provider;
-    
-    try {
-      // Try OpenAI first if it's the preferred provider or available
-      if ((provider === "openai" || provider === "both") && this.config.openai.apiKey) {
-        logger.debug("Using OpenAI for image analysis", { hasApiKey: !!this.config.openai.apiKey });
-        try {
-          return await this.analyzeWithOpenAI(imagePath, peopleContext);
-        } catch (openaiError: any) {
-          logger.error("OpenAI analysis failed", openaiError);
-          // Check if it's a quota/rate limit error
-          if (openaiError?.status === 429 || openaiError?.code === 'insufficient_quota') {
-            logger.warn("OpenAI quota exceeded, falling back to alternative processing");
-          }
-          // Continue to try Ollama if OpenAI fails
-        }
-      } else {
-        logger.debug("OpenAI not available", { provider, hasApiKey: !!this.config.openai.apiKey });
-      }
-
-      // Try Ollama if OpenAI failed or if it's the preferred provider
-      if (provider === "ollama" || provider === "both") {
-        const isOllamaAvailable = await this.checkOllamaAvailability();
-        if (isOllamaAvailable) {
-          logger.debug("Using Ollama for image analysis");
-          return await this.analyzeWithOllama(imagePath, peopleContext);
-        } else if (provider === "ollama") {
-          logger.debug("Ollama not available, falling back to basic metadata");
-          return this.generateBasicMetadata(imagePath, peopleContext);
-        }
-      }
-
-      // Fallback to basic metadata
-      logger.debug("No AI providers available, returning basic metadata");
-      return this.generateBasicMetadata(imagePath, peopleContext);
-    } catch (error) {
-      logger.error("AI analysis failed", error);
-      return this.generateBasicMetadata(imagePath, peopleContext);
-    }
-  }
-
-  private async analyzeWithOllama(imagePath: string, peopleContext?: Array<{
-    name: string;
-    ageInPhoto?: number | null;
-    relationships: Array<{ type: string; otherPersonId: string }>;
-    boundingBox: any;
-  }>): Promise<AIMetadata> {
-
-      // Read and encode image to base64
-      const imageBuffer = await fs.readFile(path.join(process.cwd(), 'data', imagePath));
-      const base64Image = imageBuffer.toString('base64');
-
-      // Get prompt from prompt manager
-      const aiPrompt = await promptManager.getPrompt('analysis', 'ollama');
-      
-      // Create context string for known people
-      const peopleContextStr = peopleContext && peopleContext.length > 0 
-        ? `\n\nKNOWN PEOPLE IN THIS IMAGE:\n${peopleContext.map(person => 
-            `- ${person.name}${person.ageInPhoto ? ` (age ${person.ageInPhoto} in this photo)` : ''}${
-              person.relationships.length > 0 
-                ? ` - relationships: ${person.relationships.map(r => r.type).join(', ')}` 
-                : ''
-            }`
-          ).join('\n')}\n\nUse this information to enhance your analysis and descriptions. Consider the people's ages and relationships when describing the image context and generating tags.`
-        : '';
-
-      // Use dynamic prompt from prompt manager
-      const systemPrompt = aiPrompt?.systemPrompt || `Analyze this image and provide detailed metadata in JSON format:
+import { libraryPath } from "../utils/libraryPaths";
+import { privacyService } from "./privacy";
+import { displayImage } from "../utils/colorProfile";
+import { aiProviderRegistry, type AIProvider, type AIConfig, type AiProvider, type AiProviderConfigUpdate } from "./aiProviders";
+
+export type { AIProvider } from "./aiProviders";
+
+// Longest side of the image sent to a vision model; larger only costs time and tokens
+const MODEL_IMAGE_SIZE = 1536;
+
+type PeopleContext = Array<{
+  name: string;
+  ageInPhoto?: number | null;
+  relationships: Array<{ type: string; otherPersonId: string }>;
+  boundingBox: any;
+}>;
+
+// Fallback prompts for when none is saved for the provider
+const DEFAULT_SYSTEM_PROMPTS: Record<AiProvider['name'], string> = {
+  ollama: `Analyze this image and provide detailed metadata in JSON format:
 {
   "aiTags": ["tag1", "tag2", "tag3", "tag4", "tag5"],
   "shortDescription": "Brief description under 100 characters",
   "longDescription": "Warm family-friendly description of the image",
   "detectedObjects": [{"name": "object_name", "confidence": 0.95}],
   "placeName": "Location or place name if identifiable",
+  "detectedText": "Legible text in the image (documents, receipts, screens, signs), empty if none",
   "aiConfidenceScores": {"tags": 0.9, "description": 0.85, "objects": 0.8, "place": 0.7}
 }
 
@@ -145,79 +42,8 @@ Rules:
 - Focus on emotions, relationships, and special moments
 - Only include highly confident object detections (>0.7 confidence)
 - Only specify placeName if you can clearly identify a specific location
-- Return ONLY valid JSON, no additional text`;
-
-      const userPrompt = aiPrompt?.userPrompt || "Please analyze this family photo and provide warm, natural metadata in the specified JSON format.";
-      const fullPrompt = systemPrompt + peopleContextStr + "\n\n" + userPrompt;
-
-      const response = await fetch(`${this.config.ollama.baseUrl}/api/generate`, {
-        method: 'POST',
-        headers: {
-          'Content-Type': 'application/json',
-        },
-        body: JSON.stringify({
-          model: this.config.ollama.visionModel,
-          prompt: fullPrompt,
-          images: [base64Image],
-          stream: false,
-          format: "json"
-        }),
-      });
-
-      if (!response.ok) {
-        throw new Error(`Ollama API error: ${response.statusText}`);
-      }
-
-      const data = await response.json();
-      const aiResult = JSON.parse(data.response || '{}');
-
-      // Validate and structure the response
-      const metadata: AIMetadata = {
-        aiTags: Array.isArray(aiResult.aiTags) ? aiResult.aiTags.slice(0, 8) : [],
-        shortDescription: aiResult.shortDescription || "AI analysis unavailable",
-        longDescription: aiResult.longDescription || "Detailed AI analysis unavailable for this image.",
-        detectedObjects: Array.isArray(aiResult.detectedObjects) ? aiResult.detectedObjects : [],
-        placeName: aiResult.placeName || undefined,
-        aiConfidenceScores: aiResult.aiConfidenceScores || {
-          tags: 0.5,
-          description: 0.5,
-          objects: 0.5,
-          place: 0.0
-        }
-      };
-
-      return metadata;
-  }
-
-  private async analyzeWithOpenAI(imagePath: string, peopleContext?: Array<{
-    name: string;
-    ageInPhoto?: number | null;
-    relationships: Array<{ type: string; otherPersonId: string }>;
-    boundingBox: any;
-  }>): Promise<AIMetadata> {
-    const OpenAI = (await import("openai")).default;
-    const openai = new OpenAI({ apiKey: this.config.openai.apiKey });
-
-    // Read and encode image to base64
-    const imageBuffer = await fs.readFile(path.join(process.cwd(), 'data', imagePath));
-    const base64Image = imageBuffer.toString('base64');
-
-    // Get prompt from prompt manager
-    const aiPrompt = await promptManager.getPrompt('analysis', 'openai');
-
-    // Create context string for known people
-    const peopleContextStr = peopleContext && peopleContext.length > 0 
-      ? `\n\nKNOWN PEOPLE IN THIS IMAGE:\n${peopleContext.map(person => 
-          `- ${person.name}${person.ageInPhoto ? ` (age ${person.ageInPhoto} in this photo)` : ''}${
-            person.relationships.length > 0 
-              ? ` - relationships: ${person.relationships.map(r => r.type).join(', ')}` 
-              : ''
-          }`
-        ).join('\n')}\n\nUse this information to enhance your analysis and descriptions. Consider the people's ages and relationships when describing the image context and generating tags.`
-      : '';
-
-    // Use dynamic prompt from prompt manager with fallback
-    const systemPrompt = aiPrompt?.systemPrompt || `You are an expert family photo analyst. Create warm, natural descriptions perfect for a family album. When people are identified, write descriptions as if this is a cherished family memory.
+- Return ONLY valid JSON, no additional text`,
+  openai: `You are an expert family photo analyst. Create warm, natural descriptions perfect for a family album. When people are identified, write descriptions as if this is a cherished family memory.
 
 Analyze the image and return a JSON object with this structure:
 {
@@ -228,6 +54,7 @@ Analyze the image and return a JSON object with this structure:
   "detectedFaces": [{"faceId": "uuid", "personName": null, "confidence": 0.95, "boundingBox": [x,y,w,h]}],
   "detectedEvents": [{"eventType": "holiday", "eventName": "Christmas", "confidence": 0.9}],
   "placeName": "Location or place name if identifiable",
+  "detectedText": "Legible text in the image (documents, receipts, screens, signs), empty if none",
   "gpsCoordinates": {"latitude": 40.7128, "longitude": -74.0060},
   "aiConfidenceScores": {"tags": 0.9, "description": 0.85, "objects": 0.8, "faces": 0.7, "events": 0.6, "place": 0.7}
 }
@@ -247,80 +74,124 @@ FACE DETECTION INSTRUCTIONS:
 - Double-check coordinates point to actual faces before including them
 - If uncertain about face locations, omit detectedFaces entirely
 
-For events, detect holidays, celebrations, activities, and life events.`;
-
-    const userPrompt = aiPrompt?.userPrompt || "Analyze this family photo and provide comprehensive metadata in the specified JSON format. Focus on creating warm, natural descriptions that would be perfect for a family album.";
-
-    const response = await openai.chat.completions.create({
-      // the newest OpenAI model is "gpt-4o" which was released May 13, 2024. do not change this unless explicitly requested by the user
-      model: "gpt-4o",
-      messages: [
-        {
-          role: "system",
-          content: systemPrompt + peopleContextStr
-        },
-        {
-          role: "user",
-          content: [
-            {
-              type: "text",
-              text: userPrompt
-            },
-            {
-              type: "image_url",
-              image_url: { url: `data:image/jpeg;base64,${base64Image}` }
-            }
-          ],
-        },
-      ],
-      response_format: { type: "json_object" },
-      max_tokens: 1500,
-    });
+For events, detect holidays, celebrations, activities, and life events.`,
+};
 
-    const aiResult = JSON.parse(response.choices[0].message.content || '{}');
+const DEFAULT_USER_PROMPTS: Record<AiProvider['name'], string> = {
+  ollama: "Please analyze this family photo and provide warm, natural metadata in the specified JSON format.",
+  openai: "Analyze this family photo and provide comprehensive metadata in the specified JSON format. Focus on creating warm, natural descriptions that would be perfect for a family album.",
+};
 
-    // Generate perceptual hash for similarity detection
-    const perceptualHash = await this.generatePerceptualHash(imagePath);
+// Added to every analysis prompt, saved ones included, so photos are flagged whichever prompt is in use
+const NSFW_INSTRUCTION = `
+
+Also include "nsfw": {"flagged": true or false, "confidence": 0.0-1.0} in the JSON, flagged when the image shows nudity, sexual content or graphic violence.`;
+
+class AIService {
+  getConfig(): AIConfig {
+    return aiProviderRegistry.getConfig();
+  }
+
+  async setConfig(updates: AiProviderConfigUpdate): Promise<AIConfig> {
+    return aiProviderRegistry.updateConfig(updates);
+  }
+
+  async analyzeImage(imagePath: string, preferredProvider?: AIProvider): Promise<AIMetadata> {
+    return this.analyzeImageWithPeopleContext(imagePath, preferredProvider, []);
+  }
+
+  /**
+   * Describe, tag and classify a photo with the configured provider, or the
+   * one asked for. Providers are tried in order; when none is available or
+   * all fail, metadata is made up from the file name and the people in it.
+   */
+  async analyzeImageWithPeopleContext(
+    imagePath: string, 
+    preferredProvider?: AIProvider, 
+    peopleContext?: PeopleContext
+  ): Promise<AIMetadata> {
+    try {
+      // Photos of people excluded from AI uploads are never sent to a provider
+      if (await privacyService.isPathRestricted(imagePath, 'ai')) {
+        logger.debug("Photo shows a person excluded from AI uploads, returning basic metadata", { imagePath });
+        return this.generateBasicMetadata(imagePath, peopleContext);
+      }
+
+      for (const provider of aiProviderRegistry.getProviders(preferredProvider)) {
+        if (!(await provider.isAvailable())) {
+          logger.debug(`AI provider ${provider.name} not available`);
+          continue;
+        }
+        try {
+          logger.debug(`Using ${provider.name} for image analysis`, { model: provider.visionModel });
+          return await this.analyzeWith(provider, imagePath, peopleContext);
+        } catch (error) {
+          logger.error(`${provider.name} analysis failed`, error);
+        }
+      }
+
+      // Fallback to basic metadata
+      logger.debug("No AI providers available, returning basic metadata");
+      return this.generateBasicMetadata(imagePath, peopleContext);
+    } catch (error) {
+      logger.error("AI analysis failed", error);
+      return this.generateBasicMetadata(imagePath, peopleContext);
+    }
+  }
+
+  private async analyzeWith(provider: AiProvider, imagePath: string, peopleContext?: PeopleContext): Promise<AIMetadata> {
+    const aiPrompt = await promptManager.getPrompt('analysis', provider.name);
+
+    // Create context string for known people
+    const peopleContextStr = peopleContext && peopleContext.length > 0 
+      ? `\n\nKNOWN PEOPLE IN THIS IMAGE:\n${peopleContext.map(person => 
+          `- ${person.name}${person.ageInPhoto ? ` (age ${person.ageInPhoto} in this photo)` : ''}${
+            person.relationships.length > 0 
+              ? ` - relationships: ${person.relationships.map(r => r.type).join(', ')}` 
+              : ''
+          }`
+        ).join('\n')}\n\nUse this information to enhance your analysis and descriptions. Consider the people's ages and relationships when describing the image context and generating tags.`
+      : '';
+
+    const reply = await provider.describeImage({
+      image: await this.renderForModel(imagePath),
+      systemPrompt: (aiPrompt?.systemPrompt || DEFAULT_SYSTEM_PROMPTS[provider.name]) + NSFW_INSTRUCTION + peopleContextStr,
+      userPrompt: aiPrompt?.userPrompt || DEFAULT_USER_PROMPTS[provider.name],
+    });
+    const aiResult = JSON.parse(reply);
 
     return {
       aiTags: Array.isArray(aiResult.aiTags) ? aiResult.aiTags.slice(0, 8) : [],
-      shortDescription: aiResult.shortDescription || "OpenAI analysis unavailable",
-      longDescription: aiResult.longDescription || "Detailed OpenAI analysis unavailable for this image.",
+      shortDescription: aiResult.shortDescription || "AI analysis unavailable",
+      longDescription: aiResult.longDescription || "Detailed AI analysis unavailable for this image.",
       detectedObjects: Array.isArray(aiResult.detectedObjects) ? aiResult.detectedObjects : [],
       detectedFaces: Array.isArray(aiResult.detectedFaces) ? aiResult.detectedFaces : [],
       detectedEvents: Array.isArray(aiResult.detectedEvents) ? aiResult.detectedEvents : [],
       placeName: aiResult.placeName || undefined,
+      detectedText: typeof aiResult.detectedText === 'string' && aiResult.detectedText.trim() ? aiResult.detectedText.trim() : undefined,
       gpsCoordinates: aiResult.gpsCoordinates || undefined,
-      perceptualHash: perceptualHash,
+      nsfw: typeof aiResult.nsfw?.flagged === 'boolean'
+        ? { flagged: aiResult.nsfw.flagged, confidence: Number(aiResult.nsfw.confidence) || 0 }
+        : undefined,
+      perceptualHash: await this.generatePerceptualHash(imagePath),
       aiConfidenceScores: aiResult.aiConfidenceScores || {
-        tags: 0.8,
-        description: 0.8,
-        objects: 0.7,
-        faces: 0.7,
-        events: 0.6,
-        place: 0.6
+        tags: 0.5,
+        description: 0.5,
+        objects: 0.5,
+        place: 0.0
       }
     };
   }
 
-  private async checkOllamaAvailability(): Promise<boolean> {
-    try {
-      const response = await fetch(`${this.config.ollama.baseUrl}/api/tags`, {
-        method: 'GET',
-        signal: AbortSignal.timeout(5000), // 5 second timeout
-      });
-      return response.ok;
-    } catch (error: any) {
-      return false;
-    }
+  // Upright, sRGB and downsized JPEG, whatever the original's format
+  private async renderForModel(imagePath: string): Promise<Buffer> {
+    return displayImage(libraryPath(imagePath))
+      .resize(MODEL_IMAGE_SIZE, MODEL_IMAGE_SIZE, { fit: 'inside', withoutEnlargement: true })
+      .jpeg({ quality: 85 })
+      .toBuffer();
   }
 
-  private async generateBasicMetadata(imagePath: string, peopleContext?: Array<{
-    name: string;
-    ageInPhoto?: number | null;
-    relationships: Array<{ type: string; otherPersonId: string }>;
-    boundingBox: any;
-  }>): Promise<AIMetadata> {
+  private async generateBasicMetadata(imagePath: string, peopleContext?: PeopleContext): Promise<AIMetadata> {
     // Extract basic info from filename and path
     const filename = path.basename(imagePath);
     const extension = path.extname(filename).toLowerCase();
@@ -429,16 +300,15 @@ For events, detect holidays, celebrations, activities, and life events.`;
    */
   async enhanceMetadataWithShortDescription(metadata: AIMetadata, imagePath: string): Promise<AIMetadata> {
     try {
-      // Only generate AI short description if using OpenAI
-      if (this.config.openai.apiKey && (this.config.provider === "openai" || this.config.provider === "both")) {
+      if (await privacyService.isPathRestricted(imagePath, 'ai')) {
+        return metadata;
+      }
+      // Only with the OpenAI-compatible endpoint: a second pass is too slow on a local Ollama
+      const config = this.getConfig();
+      const provider = aiProviderRegistry.getProvider('openai');
+      if ((config.provider === "openai" || config.provider === "both") && await provider.isAvailable()) {
         const { generateAIShortDescription } = await import("./aiNaming");
-        
-        // Convert image to base64 for OpenAI
-        const fullPath = path.join(process.cwd(), 'data', imagePath);
-        const imageBuffer = await fs.readFile(fullPath);
-        const base64Image = imageBuffer.toString('base64');
-        
-        const shortDescription = await generateAIShortDescription(base64Image);
+        const shortDescription = await generateAIShortDescription(provider, await this.renderForModel(imagePath));
         
         return {
           ...metadata,
@@ -458,7 +328,7 @@ For events, detect holidays, celebrations, activities, and life events.`;
    */
   async generatePerceptualHash(imagePath: string): Promise<string> {
     try {
-      const fullPath = path.join(process.cwd(), 'data', imagePath);
+      const fullPath = libraryPath(imagePath);
       
       // Resize image to 8x8 grayscale and get average pixel value
       const { data, info } = await sharp(fullPath)
@@ -511,51 +381,18 @@ For events, detect holidays, celebrations, activities, and life events.`;
   }
 
   async generateTags(description: string, preferredProvider?: AIProvider): Promise<string[]> {
-    const provider = preferredProvider || this.config.provider;
-    
     try {
-      // Try Ollama first if available
-      if ((provider === "ollama" || provider === "both") && await this.checkOllamaAvailability()) {
-        const response = await fetch(`${this.config.ollama.baseUrl}/api/generate`, {
-          method: 'POST',
-          headers: { 'Content-Type': 'application/json' },
-          body: JSON.stringify({
-            model: this.config.ollama.textModel,
-            prompt: `Generate 5-8 relevant tags for this image description. Return as JSON array of strings.
-
-Description: ${description}
-
-Return ONLY a JSON array like: ["tag1", "tag2", "tag3"]`,
-            stream: false,
-            format: "json"
-          }),
-        });
-
-        if (response.ok) {
-          const data = await response.json();
-          const result = JSON.parse(data.response || '[]');
-          return Array.isArray(result) ? result.slice(0, 8) : this.extractTagsFromDescription(description);
-        }
-      }
-
-      // Try OpenAI if available
-      if ((provider === "openai" || provider === "both") && this.config.openai.apiKey) {
-        const OpenAI = (await import("openai")).default;
-        const openai = new OpenAI({ apiKey: this.config.openai.apiKey });
-        
-        const response = await openai.chat.completions.create({
-          // the newest OpenAI model is "gpt-4o" which was released May 13, 2024. do not change this unless explicitly requested by the user
-          model: "gpt-4o",
-          messages: [
-            { role: "system", content: "Generate 5-8 relevant tags for the given image description. Return as JSON array of strings." },
-            { role: "user", content: `Generate tags for: ${description}` }
-          ],
-          response_format: { type: "json_object" },
-          max_tokens: 200,
+      for (const provider of aiProviderRegistry.getProviders(preferredProvider)) {
+        if (!(await provider.isAvailable())) continue;
+        const reply = await provider.complete({
+          systemPrompt: 'Generate 5-8 relevant tags for the given image description. Return ONLY a JSON object like: {"tags": ["tag1", "tag2", "tag3"]}',
+          userPrompt: `Generate tags for: ${description}`,
+          maxTokens: 200,
         });
-
-        const result = JSON.parse(response.choices[0].message.content || '{"tags": []}');
-        return Array.isArray(result.tags) ? result.tags.slice(0, 8) : this.extractTagsFromDescription(description);
+        // Some models answer with the bare array
+        const result = JSON.parse(reply);
+        const tags = Array.isArray(result) ? result : result.tags;
+        return Array.isArray(tags) ? tags.slice(0, 8) : this.extractTagsFromDescription(description);
       }
 
       return this.extractTagsFromDescription(description);
@@ -566,14 +403,11 @@ Return ONLY a JSON array like: ["tag1", "tag2", "tag3"]`,
   }
 
   async getAvailableProviders(): Promise<{ ollama: boolean; openai: boolean }> {
-    const [ollamaAvailable] = await Promise.all([
-      this.checkOllamaAvailability(),
+    const [ollama, openai] = await Promise.all([
+      aiProviderRegistry.getProvider('ollama').isAvailable(),
+      aiProviderRegistry.getProvider('openai').isAvailable(),
     ]);
-    
-    return {
-      ollama: ollamaAvailable,
-      openai: !!this.config.openai.apiKey
-    };
+    return { ollama, openai };
   }
 
   private extractTagsFromDescription(description: string): string[] {
@@ -0,0 +1,352 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { aiService } from "./ai";
+import { eventDetectionService } from "./eventDetection";
+import { systemAlbumService } from "./systemAlbums";
+import { operationRegistry, type OperationHandle } from "./operations";
+import { extractPhotoDate } from "../utils/photoDates";
+import { isNetworkMetered, isOnBatteryPower } from "../utils/powerState";
+import { libraryPath } from "../utils/libraryPaths";
+import { provenanceService } from "./provenance";
+
+export const AI_SCHEDULE_SETTING = 'ai_batch_schedule';
+
+const TIME_OF_DAY = /^([01]\d|2[0-3]):[0-5]\d$/;
+const SCHEDULER_INTERVAL_MS = 60 * 1000;
+
+export const aiScheduleSettingsSchema = z.object({
+  concurrency: z.number().int().min(1).max(8).optional(),
+  pauseOnBattery: z.boolean().optional(),
+  pauseOnMeteredNetwork: z.boolean().optional(), // only applies to cloud providers
+  nightly: z.object({
+    enabled: z.boolean().optional(),
+    start: z.string().regex(TIME_OF_DAY).optional(), // HH:MM, local time
+    end: z.string().regex(TIME_OF_DAY).optional(),
+  }).optional(),
+});
+
+export interface AiScheduleSettings {
+  concurrency: number;
+  pauseOnBattery: boolean;
+  pauseOnMeteredNetwork: boolean;
+  nightly: { enabled: boolean; start: string; end: string };
+}
+
+const DEFAULT_SETTINGS: AiScheduleSettings = {
+  concurrency: 1,
+  pauseOnBattery: true,
+  pauseOnMeteredNetwork: true,
+  nightly: { enabled: false, start: '01:00', end: '06:00' },
+};
+
+export type AiSchedulePauseReason = 'battery' | 'metered_network' | 'outside_nightly_window' | 'paused_by_user';
+
+export interface AiScheduleStatus {
+  queued: number;
+  running: boolean;
+  operationId: string | null;
+  pausedReason: AiSchedulePauseReason | null;
+  processed: number; // since the queue last ran empty
+  errors: Array<{ photoId: string; error: string }>;
+  settings: AiScheduleSettings;
+}
+
+export type AiProcessOutcome = 'processed' | 'skipped';
+
+/**
+ * Queue for AI captioning and classification of many photos. A pass over a
+ * big library takes hours, so the queue survives restarts (it is kept in the
+ * library), runs a configurable number of photos at once, and holds off on
+ * battery, on metered connections or outside a nightly window.
+ */
+class AiBatchScheduler {
+  private queue: string[] | null = null;
+  private operation: OperationHandle | null = null;
+  private pausedReason: AiSchedulePauseReason | null = null;
+  private userPaused = false;
+  private processed = 0;
+  private errors: Array<{ photoId: string; error: string }> = [];
+  private schedulerHandle: NodeJS.Timeout | null = null;
+  private saving: Promise<void> = Promise.resolve();
+
+  private get queuePath(): string {
+    return libraryPath('ai-queue.json');
+  }
+
+  async getSettings(): Promise<AiScheduleSettings> {
+    const setting = await storage.getSettingByKey(AI_SCHEDULE_SETTING);
+    if (!setting?.value) return DEFAULT_SETTINGS;
+    try {
+      const parsed = aiScheduleSettingsSchema.safeParse(JSON.parse(setting.value));
+      return parsed.success ? this.mergeSettings(DEFAULT_SETTINGS, parsed.data) : DEFAULT_SETTINGS;
+    } catch {
+      return DEFAULT_SETTINGS;
+    }
+  }
+
+  async updateSettings(updates: z.infer<typeof aiScheduleSettingsSchema>): Promise<AiScheduleSettings> {
+    const settings = this.mergeSettings(await this.getSettings(), updates);
+    const value = JSON.stringify(settings);
+    if (await storage.getSettingByKey(AI_SCHEDULE_SETTING)) {
+      await storage.updateSetting(AI_SCHEDULE_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: AI_SCHEDULE_SETTING,
+        value,
+        category: 'ai',
+        description: 'Concurrency, power and nightly window settings for batch AI processing',
+      });
+    }
+    this.kick();
+    return settings;
+  }
+
+  async getStatus(): Promise<AiScheduleStatus> {
+    const queue = await this.loadQueue();
+    return {
+      queued: queue.length,
+      running: this.operation !== null,
+      operationId: this.operation?.id ?? null,
+      pausedReason: this.userPaused ? 'paused_by_user' : this.pausedReason,
+      processed: this.processed,
+      errors: this.errors.slice(-50),
+      settings: await this.getSettings(),
+    };
+  }
+
+  /**
+   * Add photos to the end of the queue; photos already queued keep their place
+   */
+  async enqueue(photoIds: string[]): Promise<{ queued: number; total: number }> {
+    const queue = await this.loadQueue();
+    const existing = new Set(queue);
+    const added = photoIds.filter(photoId => !existing.has(photoId) && existing.add(photoId));
+    queue.push(...added);
+    await this.saveQueue();
+    this.kick();
+    return { queued: added.length, total: queue.length };
+  }
+
+  async clearQueue(): Promise<number> {
+    const queue = await this.loadQueue();
+    const removed = queue.length;
+    queue.length = 0;
+    await this.saveQueue();
+    return removed;
+  }
+
+  pause(): void {
+    this.userPaused = true;
+    this.operation?.token.cancel();
+  }
+
+  resume(): void {
+    this.userPaused = false;
+    this.kick();
+  }
+
+  startScheduler(): void {
+    if (this.schedulerHandle) return;
+    this.schedulerHandle = setInterval(() => this.kick(), SCHEDULER_INTERVAL_MS);
+    this.schedulerHandle.unref();
+    this.kick();
+  }
+
+  stopScheduler(): void {
+    if (this.schedulerHandle) {
+      clearInterval(this.schedulerHandle);
+      this.schedulerHandle = null;
+    }
+  }
+
+  /**
+   * Caption, tag and detect events for one photo. Photos that are not
+   * Silver images, are locked or already have AI metadata are skipped.
+   */
+  async processPhoto(photoId: string): Promise<AiProcessOutcome> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo || photo.tier !== 'silver' || photo.isLocked || !photo.mimeType.startsWith('image/')) {
+      return 'skipped';
+    }
+    if ((photo.metadata as any)?.ai?.shortDescription) {
+      return 'skipped';
+    }
+
+    const asset = await storage.getMediaAsset(photo.mediaAssetId);
+    const photoDate = extractPhotoDate({ ...photo, mediaAsset: asset });
+
+    // People context, as for single photo processing
+    const peopleContext = [];
+    for (const face of await storage.getFacesByPhoto(photo.id)) {
+      if (!face.personId) continue;
+      const person = await storage.getPerson(face.personId);
+      if (!person) continue;
+
+      const ageInPhoto = person.birthdate && photoDate
+        ? eventDetectionService.calculateAgeInPhoto(new Date(person.birthdate), photoDate)
+        : null;
+      const relationships = (await storage.getRelationshipsByPerson(person.id)).map(rel => ({
+        type: rel.relationshipType,
+        otherPersonId: rel.person1Id === person.id ? rel.person2Id : rel.person1Id,
+      }));
+      peopleContext.push({ name: person.name, ageInPhoto, relationships, boundingBox: face.boundingBox });
+    }
+
+    const aiMetadata = await aiService.analyzeImageWithPeopleContext(photo.filePath, undefined, peopleContext);
+    const enhancedMetadata = await aiService.enhanceMetadataWithShortDescription(aiMetadata, photo.filePath);
+
+    let eventType: string | undefined;
+    let eventName: string | undefined;
+    if (photoDate) {
+      try {
+        const detectedEvents = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
+        if (detectedEvents.length > 0) {
+          const bestEvent = detectedEvents.reduce((max, event) => event.confidence > max.confidence ? event : max);
+          if (bestEvent.confidence >= 80) {
+            eventType = bestEvent.eventType;
+            eventName = bestEvent.eventName;
+          }
+        }
+      } catch (error) {
+        console.error('Event detection failed for photo:', photoId, error);
+      }
+    }
+
+    const updatedPhoto = await storage.updateFileVersion(photo.id, {
+      metadata: { ...(photo.metadata || {}), ai: enhancedMetadata } as any,
+      aiShortDescription: enhancedMetadata.shortDescription,
+      eventType: eventType || undefined,
+      eventName: eventName || undefined,
+      isReviewed: false,
+    });
+    await systemAlbumService.processPhoto(updatedPhoto);
+
+    await provenanceService.recordAiAnalysis(photo, 'Batch AI processing completed');
+    return 'processed';
+  }
+
+  /**
+   * Start a run if there is work and nothing holds it back
+   */
+  private kick(): void {
+    if (this.operation) return;
+    this.run().catch(error => console.error('AI batch scheduler failed:', error));
+  }
+
+  private async run(): Promise<void> {
+    const queue = await this.loadQueue();
+    if (queue.length === 0 || this.operation || this.userPaused) return;
+    const settings = await this.getSettings();
+    if (await this.updatePausedReason(settings)) return;
+
+    const operation = operationRegistry.start('ai_processing', `AI processing ${queue.length} queued photos`);
+    this.operation = operation;
+    let completed = 0;
+    let sinceCheck = 0;
+
+    const worker = async () => {
+      while (queue.length > 0 && !operation.token.isCancelled) {
+        // Conditions can change mid-run (unplugged, left the nightly window)
+        if (++sinceCheck >= settings.concurrency * 5) {
+          sinceCheck = 0;
+          if (await this.updatePausedReason(settings)) {
+            operation.token.cancel();
+            break;
+          }
+        }
+
+        const photoId = queue.shift()!;
+        try {
+          if (await this.processPhoto(photoId) === 'processed') this.processed++;
+        } catch (error: any) {
+          this.errors.push({ photoId, error: error.message });
+        }
+        completed++;
+        operation.progress(completed, completed + queue.length);
+        operation.checkpoint({ remainingPhotoIds: queue.slice() });
+        await this.saveQueue();
+      }
+    };
+
+    try {
+      await Promise.all(Array.from({ length: settings.concurrency }, () => worker()));
+    } finally {
+      operation.finish();
+      this.operation = null;
+      if (queue.length === 0) {
+        console.log(`AI batch queue finished: ${this.processed} processed, ${this.errors.length} errors`);
+        this.processed = 0;
+        this.errors = [];
+      }
+    }
+  }
+
+  /**
+   * Record why the queue cannot run right now; returns true when it must wait
+   */
+  private async updatePausedReason(settings: AiScheduleSettings): Promise<boolean> {
+    this.pausedReason = null;
+    if (settings.nightly.enabled && !this.inWindow(new Date(), settings.nightly.start, settings.nightly.end)) {
+      this.pausedReason = 'outside_nightly_window';
+    } else if (settings.pauseOnBattery && await isOnBatteryPower() === true) {
+      this.pausedReason = 'battery';
+    } else if (settings.pauseOnMeteredNetwork && aiService.getConfig().provider !== 'ollama' && await isNetworkMetered() === true) {
+      this.pausedReason = 'metered_network';
+    }
+    return this.pausedReason !== null;
+  }
+
+  private inWindow(now: Date, start: string, end: string): boolean {
+    const minutes = (value: string) => Number(value.slice(0, 2)) * 60 + Number(value.slice(3, 5));
+    const current = now.getHours() * 60 + now.getMinutes();
+    const from = minutes(start);
+    const to = minutes(end);
+    // Windows may cross midnight (01:00-06:00 does not, 22:00-06:00 does)
+    return from <= to ? current >= from && current < to : current >= from || current < to;
+  }
+
+  private mergeSettings(base: AiScheduleSettings, updates: z.infer<typeof aiScheduleSettingsSchema>): AiScheduleSettings {
+    return {
+      concurrency: updates.concurrency ?? base.concurrency,
+      pauseOnBattery: updates.pauseOnBattery ?? base.pauseOnBattery,
+      pauseOnMeteredNetwork: updates.pauseOnMeteredNetwork ?? base.pauseOnMeteredNetwork,
+      nightly: { ...base.nightly, ...updates.nightly },
+    };
+  }
+
+  private async loadQueue(): Promise<string[]> {
+    if (!this.queue) {
+      try {
+        const saved = JSON.parse(await fs.readFile(this.queuePath, 'utf8'));
+        this.queue = Array.isArray(saved) ? saved.filter((id): id is string => typeof id === 'string') : [];
+      } catch {
+        this.queue = [];
+      }
+    }
+    return this.queue;
+  }
+
+  /**
+   * Workers finish photos concurrently; writes are chained so they cannot interleave
+   */
+  private saveQueue(): Promise<void> {
+    this.saving = this.saving.then(() => this.writeQueue());
+    return this.saving;
+  }
+
+  private async writeQueue(): Promise<void> {
+    const queue = await this.loadQueue();
+    try {
+      await fs.mkdir(path.dirname(this.queuePath), { recursive: true });
+      const partialPath = `${this.queuePath}.partial`;
+      await fs.writeFile(partialPath, JSON.stringify(queue));
+      await fs.rename(partialPath, this.queuePath);
+    } catch (error) {
+      console.error('Failed to save AI batch queue:', error);
+    }
+  }
+}
+
+export const aiBatchScheduler = new AiBatchScheduler();
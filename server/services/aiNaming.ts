@@ -1,7 +1,4 @@
-import OpenAI from "openai";
-
-// the newest OpenAI model is "gpt-4o" which was released May 13, 2024. do not change this unless explicitly requested by the user
-const openai = new OpenAI({ apiKey: process.env.OPENAI_API_KEY });
+import type { AiProvider } from "./aiProviders";
 
 export interface NamingContext {
   aiMetadata?: {
@@ -69,37 +66,17 @@ export const BUILTIN_NAMING_PATTERNS: NamingPattern[] = [
 /**
  * Generate a short AI description (2-3 words in PascalCase) for an image
  */
-export async function generateAIShortDescription(base64Image: string): Promise<string> {
+export async function generateAIShortDescription(provider: AiProvider, image: Buffer): Promise<string> {
   try {
-    const response = await openai.chat.completions.create({
-      model: "gpt-4o",
-      messages: [
-        {
-          role: "system",
-          content: `Generate a very short 2-3 word description for this image in PascalCase format (e.g., SunsetBeach, FamilyDinner, MountainHike). 
-          Focus on the main subject or scene. Be concise and descriptive. Respond with JSON format: {"description": "YourDescription"}`
-        },
-        {
-          role: "user",
-          content: [
-            {
-              type: "text",
-              text: "Generate a short PascalCase description for this image."
-            },
-            {
-              type: "image_url",
-              image_url: {
-                url: `data:image/jpeg;base64,${base64Image}`
-              }
-            }
-          ]
-        }
-      ],
-      response_format: { type: "json_object" },
-      max_tokens: 50
+    const reply = await provider.describeImage({
+      systemPrompt: `Generate a very short 2-3 word description for this image in PascalCase format (e.g., SunsetBeach, FamilyDinner, MountainHike). 
+          Focus on the main subject or scene. Be concise and descriptive. Respond with JSON format: {"description": "YourDescription"}`,
+      userPrompt: "Generate a short PascalCase description for this image.",
+      image,
+      maxTokens: 50,
     });
 
-    const result = JSON.parse(response.choices[0].message.content || '{}');
+    const result = JSON.parse(reply);
     return result.description || 'UnknownImage';
   } catch (error) {
     console.error('Failed to generate AI description:', error);
@@ -0,0 +1,357 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { logger } from "../utils/logger.js";
+
+export const AI_PROVIDER_SETTING = 'ai_provider_config';
+
+const OPENAI_BASE_URL = 'https://api.openai.com/v1';
+const AVAILABILITY_TIMEOUT_MS = 5000;
+const REQUEST_TIMEOUT_MS = 120 * 1000; // local vision models can take a while on a CPU
+const MAX_RETRY_DELAY_MS = 60 * 1000;
+
+// "both" tries the OpenAI-compatible endpoint first and falls back to Ollama
+export type AIProvider = "ollama" | "openai" | "both";
+
+export const aiProviderConfigSchema = z.object({
+  provider: z.enum(['ollama', 'openai', 'both']).optional(),
+  ollama: z.object({
+    baseUrl: z.string().url().optional(),
+    visionModel: z.string().min(1).optional(),
+    textModel: z.string().min(1).optional(),
+  }).optional(),
+  openai: z.object({
+    baseUrl: z.string().url().optional(), // any OpenAI-compatible server: LM Studio, vLLM, LocalAI, a proxy
+    apiKey: z.string().optional(), // write-only; not needed by most local servers
+    model: z.string().min(1).optional(),
+  }).optional(),
+  requestsPerMinute: z.number().int().min(1).max(1000).optional(),
+  maxRetries: z.number().int().min(0).max(10).optional(),
+});
+
+export type AiProviderConfigUpdate = z.infer<typeof aiProviderConfigSchema>;
+
+export interface AIConfig {
+  provider: AIProvider;
+  ollama: {
+    baseUrl: string;
+    visionModel: string;
+    textModel: string;
+  };
+  openai: {
+    baseUrl: string;
+    apiKey: string;
+    model: string;
+  };
+  requestsPerMinute: number; // per provider, shared by every caller
+  maxRetries: number; // for rate limits, server errors and dropped connections
+}
+
+const DEFAULT_CONFIG: AIConfig = {
+  provider: (process.env.AI_PROVIDER as AIProvider) || (process.env.OPENAI_API_KEY ? "openai" : "ollama"),
+  ollama: {
+    baseUrl: process.env.OLLAMA_BASE_URL || "http://localhost:11434",
+    visionModel: process.env.OLLAMA_MODEL || "llava:latest",
+    textModel: process.env.OLLAMA_TEXT_MODEL || "llama3.2:latest"
+  },
+  openai: {
+    baseUrl: process.env.OPENAI_BASE_URL || OPENAI_BASE_URL,
+    apiKey: process.env.OPENAI_API_KEY || "",
+    model: process.env.OPENAI_MODEL || "gpt-4o"
+  },
+  requestsPerMinute: 60,
+  maxRetries: 3,
+};
+
+export class AiProviderError extends Error {
+  constructor(message: string, readonly status?: number, readonly retryAfterMs?: number) {
+    super(message);
+  }
+
+  get retryable(): boolean {
+    return this.status === undefined || this.status === 408 || this.status === 429 || this.status >= 500;
+  }
+}
+
+export interface VisionRequest {
+  image: Buffer; // JPEG
+  systemPrompt: string;
+  userPrompt: string;
+  maxTokens?: number;
+}
+
+export interface TextRequest {
+  systemPrompt: string;
+  userPrompt: string;
+  maxTokens?: number;
+}
+
+/**
+ * A backend that runs the vision and text models. Both calls ask for JSON
+ * and return the model's reply as text, for the caller to parse.
+ */
+export interface AiProvider {
+  readonly name: 'ollama' | 'openai';
+  readonly visionModel: string;
+  isAvailable(): Promise<boolean>;
+  describeImage(request: VisionRequest): Promise<string>;
+  complete(request: TextRequest): Promise<string>;
+}
+
+/**
+ * Spaces requests evenly to stay under a requests-per-minute budget. A rate
+ * limit reply pushes every waiting request back, not just the one refused.
+ */
+class RateLimiter {
+  private nextSlot = 0;
+
+  constructor(private readonly requestsPerMinute: number) {}
+
+  async acquire(): Promise<void> {
+    const now = Date.now();
+    const slot = Math.max(now, this.nextSlot);
+    this.nextSlot = slot + 60_000 / this.requestsPerMinute;
+    if (slot > now) await new Promise(resolve => setTimeout(resolve, slot - now));
+  }
+
+  backOff(delayMs: number): void {
+    this.nextSlot = Math.max(this.nextSlot, Date.now() + delayMs);
+  }
+}
+
+// Seconds or an HTTP date, as servers send it
+function parseRetryAfter(value: string | null): number | undefined {
+  if (!value) return undefined;
+  const seconds = Number(value);
+  if (Number.isFinite(seconds)) return Math.max(0, seconds * 1000);
+  const date = Date.parse(value);
+  return Number.isNaN(date) ? undefined : Math.max(0, date - Date.now());
+}
+
+abstract class HttpProvider {
+  constructor(private readonly limiter: RateLimiter, private readonly maxRetries: number) {}
+
+  protected async postJson(url: string, body: unknown, headers: Record<string, string> = {}): Promise<any> {
+    for (let attempt = 0; ; attempt++) {
+      await this.limiter.acquire();
+      try {
+        const response = await fetch(url, {
+          method: 'POST',
+          headers: { 'Content-Type': 'application/json', ...headers },
+          body: JSON.stringify(body),
+          signal: AbortSignal.timeout(REQUEST_TIMEOUT_MS),
+        });
+        if (!response.ok) {
+          const detail = (await response.text().catch(() => '')).slice(0, 300);
+          throw new AiProviderError(
+            `${url} returned ${response.status} ${response.statusText}${detail ? `: ${detail}` : ''}`,
+            response.status,
+            parseRetryAfter(response.headers.get('retry-after'))
+          );
+        }
+        return await response.json();
+      } catch (caught) {
+        const error = caught instanceof AiProviderError
+          ? caught
+          : new AiProviderError(caught instanceof Error ? caught.message : String(caught));
+        if (!error.retryable || attempt >= this.maxRetries) throw error;
+
+        const delay = Math.min(error.retryAfterMs ?? 1000 * 2 ** attempt + Math.random() * 500, MAX_RETRY_DELAY_MS);
+        if (error.status === 429) this.limiter.backOff(delay);
+        logger.warn(`AI request failed, retrying in ${Math.round(delay / 1000)}s`, { url, attempt: attempt + 1, error: error.message });
+        await new Promise(resolve => setTimeout(resolve, delay));
+      }
+    }
+  }
+
+  protected async probe(url: string, headers: Record<string, string> = {}): Promise<boolean> {
+    try {
+      const response = await fetch(url, { headers, signal: AbortSignal.timeout(AVAILABILITY_TIMEOUT_MS) });
+      return response.ok;
+    } catch {
+      return false;
+    }
+  }
+}
+
+class OllamaProvider extends HttpProvider implements AiProvider {
+  readonly name = 'ollama' as const;
+  readonly visionModel: string;
+
+  constructor(private readonly config: AIConfig['ollama'], limiter: RateLimiter, maxRetries: number) {
+    super(limiter, maxRetries);
+    this.visionModel = config.visionModel;
+  }
+
+  isAvailable(): Promise<boolean> {
+    return this.probe(`${this.config.baseUrl}/api/tags`);
+  }
+
+  async describeImage(request: VisionRequest): Promise<string> {
+    return this.generate(this.config.visionModel, request, [request.image.toString('base64')]);
+  }
+
+  async complete(request: TextRequest): Promise<string> {
+    return this.generate(this.config.textModel, request);
+  }
+
+  private async generate(model: string, request: TextRequest, images?: string[]): Promise<string> {
+    const data = await this.postJson(`${this.config.baseUrl}/api/generate`, {
+      model,
+      system: request.systemPrompt,
+      prompt: request.userPrompt,
+      images,
+      stream: false,
+      format: "json",
+      options: request.maxTokens ? { num_predict: request.maxTokens } : undefined,
+    });
+    return data.response || '{}';
+  }
+}
+
+/**
+ * OpenAI's chat completions API, as spoken by OpenAI and by local servers
+ * that copy it. The same model is used for text as for images.
+ */
+class OpenAICompatibleProvider extends HttpProvider implements AiProvider {
+  readonly name = 'openai' as const;
+  readonly visionModel: string;
+
+  constructor(private readonly config: AIConfig['openai'], limiter: RateLimiter, maxRetries: number) {
+    super(limiter, maxRetries);
+    this.visionModel = config.model;
+  }
+
+  private get baseUrl(): string {
+    return this.config.baseUrl.replace(/\/+$/, '');
+  }
+
+  private get headers(): Record<string, string> {
+    return this.config.apiKey ? { Authorization: `Bearer ${this.config.apiKey}` } : {};
+  }
+
+  // OpenAI itself needs a key; a custom endpoint is asked whether it is up
+  async isAvailable(): Promise<boolean> {
+    if (this.baseUrl === OPENAI_BASE_URL) return !!this.config.apiKey;
+    return this.probe(`${this.baseUrl}/models`, this.headers);
+  }
+
+  async describeImage(request: VisionRequest): Promise<string> {
+    return this.chat(request, [
+      { type: "text", text: request.userPrompt },
+      { type: "image_url", image_url: { url: `data:image/jpeg;base64,${request.image.toString('base64')}` } },
+    ]);
+  }
+
+  async complete(request: TextRequest): Promise<string> {
+    return this.chat(request, request.userPrompt);
+  }
+
+  private async chat(request: TextRequest, userContent: unknown): Promise<string> {
+    const data = await this.postJson(`${this.baseUrl}/chat/completions`, {
+      model: this.config.model,
+      messages: [
+        { role: "system", content: request.systemPrompt },
+        { role: "user", content: userContent },
+      ],
+      response_format: { type: "json_object" },
+      max_tokens: request.maxTokens ?? 1500,
+    }, this.headers);
+    return data.choices?.[0]?.message?.content || '{}';
+  }
+}
+
+/**
+ * The AI backend configuration, kept in the library's settings, and the
+ * providers built from it. Providers are rebuilt when the configuration
+ * changes, so rate limits apply across every caller of the same backend.
+ */
+class AiProviderRegistry {
+  private config: AIConfig = DEFAULT_CONFIG;
+  private providers: { ollama: OllamaProvider; openai: OpenAICompatibleProvider } | null = null;
+
+  getConfig(): AIConfig {
+    return this.config;
+  }
+
+  /**
+   * Reload the configuration from settings, over the environment defaults
+   */
+  async refresh(): Promise<void> {
+    const setting = await storage.getSettingByKey(AI_PROVIDER_SETTING);
+    let saved: AiProviderConfigUpdate = {};
+    if (setting?.value) {
+      try {
+        const parsed = aiProviderConfigSchema.safeParse(JSON.parse(setting.value));
+        if (parsed.success) saved = parsed.data;
+      } catch {
+        // Unreadable setting: fall back to the defaults
+      }
+    }
+    this.config = this.merge(DEFAULT_CONFIG, saved);
+    this.providers = null;
+  }
+
+  async updateConfig(updates: AiProviderConfigUpdate): Promise<AIConfig> {
+    // The API key is write-only; leaving it out keeps the one already saved
+    const config = this.merge(this.config, updates);
+    const value = JSON.stringify(this.toSaved(config));
+    if (await storage.getSettingByKey(AI_PROVIDER_SETTING)) {
+      await storage.updateSetting(AI_PROVIDER_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: AI_PROVIDER_SETTING,
+        value,
+        category: 'ai',
+        description: 'AI backend: Ollama or an OpenAI-compatible endpoint, models and request limits',
+      });
+    }
+    this.config = config;
+    this.providers = null;
+    return config;
+  }
+
+  getProvider(name: 'ollama' | 'openai'): AiProvider {
+    if (!this.providers) {
+      this.providers = {
+        ollama: new OllamaProvider(this.config.ollama, new RateLimiter(this.config.requestsPerMinute), this.config.maxRetries),
+        openai: new OpenAICompatibleProvider(this.config.openai, new RateLimiter(this.config.requestsPerMinute), this.config.maxRetries),
+      };
+    }
+    return this.providers[name];
+  }
+
+  /**
+   * Providers to try, in order, for the configured or requested selection
+   */
+  getProviders(preferred?: AIProvider): AiProvider[] {
+    const selection = preferred || this.config.provider;
+    if (selection === 'both') return [this.getProvider('openai'), this.getProvider('ollama')];
+    return [this.getProvider(selection)];
+  }
+
+  /**
+   * What goes into the setting: a key from OPENAI_API_KEY stays in the
+   * environment, and only a key entered by the user is saved
+   */
+  private toSaved(config: AIConfig): AiProviderConfigUpdate {
+    const { apiKey, ...openai } = config.openai;
+    const entered = apiKey && apiKey !== (process.env.OPENAI_API_KEY || '');
+    return { ...config, openai: entered ? { ...openai, apiKey } : openai };
+  }
+
+  private merge(base: AIConfig, updates: AiProviderConfigUpdate): AIConfig {
+    return {
+      provider: updates.provider ?? base.provider,
+      ollama: { ...base.ollama, ...updates.ollama },
+      openai: {
+        ...base.openai,
+        ...updates.openai,
+        apiKey: updates.openai?.apiKey || base.openai.apiKey,
+      },
+      requestsPerMinute: updates.requestsPerMinute ?? base.requestsPerMinute,
+      maxRetries: updates.maxRetries ?? base.maxRetries,
+    };
+  }
+}
+
+export const aiProviderRegistry = new AiProviderRegistry();
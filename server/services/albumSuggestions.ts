@@ -0,0 +1,449 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { locationClusteringService } from "./location-clustering";
+import { reverseGeocodingService } from "./reverse-geocoding";
+import { photoStackService } from "./photoStacks";
+import type { CancellationToken } from "./operations";
+import { getEffectiveDate } from "../utils/photoDates";
+import type { AlbumSuggestion, Collection, FileVersion, InsertAlbumSuggestion, Location } from "@shared/schema";
+
+export const suggestAlbumsSchema = z.object({
+  gapHours: z.number().min(1).max(168).default(18), // a longer break in shooting starts a new event
+  minPhotos: z.number().int().min(3).max(1000).default(12),
+  geocode: z.boolean().default(true), // name places that are not saved locations through OpenStreetMap
+});
+
+export const albumSuggestionsQuerySchema = z.object({
+  status: z.enum(['pending', 'accepted', 'dismissed']).default('pending'),
+});
+
+export const acceptAlbumSuggestionSchema = z.object({
+  name: z.string().trim().min(1).max(200).optional(), // rename the album as it is created
+});
+
+export type SuggestAlbumsInput = z.infer<typeof suggestAlbumsSchema>;
+
+export interface AlbumSuggestionView extends AlbumSuggestion {
+  photoCount: number;
+  summary: string; // "Weekend in Portland with Sam, 42 photos"
+}
+
+export interface AlbumSuggestionRun {
+  suggestions: AlbumSuggestionView[];
+  unorganizedPhotos: number;
+}
+
+export class AlbumSuggestionError extends Error {
+  constructor(message: string, readonly status: number) {
+    super(message);
+  }
+}
+
+const TIER_ORDER: Record<FileVersion['tier'], number> = { bronze: 0, silver: 1, gold: 2 };
+// A stretch of shooting longer than this is everyday life rather than an event
+const MAX_EVENT_DAYS = 14;
+// Photos this close together were taken in the same town, for naming events
+const EVENT_PLACE_RADIUS_M = 25_000;
+// Photos this close together were taken at the same place, for place albums
+const PLACE_RADIUS_M = 1_000;
+// A place album needs visits on this many different days, or it would be an event
+const MIN_PLACE_DAYS = 3;
+// Share of a suggestion's photos a person must be in to be named
+const MIN_PERSON_SHARE = 0.3;
+// A new suggestion mostly made of a dismissed one's photos is not made again
+const DISMISSED_OVERLAP = 0.8;
+
+interface CandidatePhoto {
+  photo: FileVersion;
+  takenAt: Date;
+  coordinates: { latitude: number; longitude: number } | null;
+  personIds: string[];
+}
+
+interface Place {
+  name: string;
+  preposition: 'in' | 'at'; // "in Portland", "at Grandma's"
+}
+
+/**
+ * Albums proposed for photos that are in no album yet. Photos are split into
+ * events wherever shooting pauses for longer than gapHours; what is left is
+ * grouped by places returned to on several days, then by pairs of people
+ * often photographed together. Suggestions are named after when, where and
+ * with whom ("Weekend in Portland with Sam") and become real albums when
+ * accepted. Dismissed suggestions are remembered and not made again.
+ */
+class AlbumSuggestionService {
+  async suggestAlbums(
+    input: SuggestAlbumsInput,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<AlbumSuggestionRun> {
+    const candidates = await this.loadUnorganizedPhotos();
+    token?.throwIfCancelled();
+
+    const events = this.groupByTime(candidates, input.gapHours * 3_600_000, input.minPhotos);
+    const inEvent = new Set(events.flat().map(candidate => candidate.photo.id));
+    const places = this.groupByPlace(candidates.filter(candidate => !inEvent.has(candidate.photo.id)), input.minPhotos);
+    const inPlace = new Set(places.flat().map(candidate => candidate.photo.id));
+    const together = this.groupByPeople(
+      candidates.filter(candidate => !inEvent.has(candidate.photo.id) && !inPlace.has(candidate.photo.id)),
+      input.minPhotos
+    );
+    const groups: Array<{ kind: AlbumSuggestion['kind']; photos: CandidatePhoto[] }> = [
+      ...events.map(photos => ({ kind: 'event' as const, photos })),
+      ...places.map(photos => ({ kind: 'place' as const, photos })),
+      ...together.map(photos => ({ kind: 'people' as const, photos })),
+    ];
+
+    const existing = await storage.getAlbumSuggestions();
+    const dismissed = existing.filter(suggestion => suggestion.status === 'dismissed').map(suggestion => new Set(suggestion.photoIds));
+    const pendingBySignature = new Map(existing
+      .filter(suggestion => suggestion.status === 'pending')
+      .map(suggestion => [this.signature(suggestion.photoIds), suggestion]));
+    const people = new Map((await storage.getPeople()).map(person => [person.id, person.name]));
+    const locations = await storage.getLocations();
+    const placeNames = new Map<string, Place | null>();
+
+    const keepIds: string[] = [];
+    const created: InsertAlbumSuggestion[] = [];
+    for (let index = 0; index < groups.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(index, groups.length);
+      const { kind, photos } = groups[index];
+      const photoIds = photos.map(candidate => candidate.photo.id);
+      if (dismissed.some(ids => photoIds.filter(id => ids.has(id)).length >= photoIds.length * DISMISSED_OVERLAP)) continue;
+
+      // The same photos as a pending suggestion keep it, so its ID stays valid for clients
+      const pending = pendingBySignature.get(this.signature(photoIds));
+      if (pending) {
+        keepIds.push(pending.id);
+        continue;
+      }
+
+      const personIds = this.namedPeople(photos, kind === 'people' ? 0.5 : MIN_PERSON_SHARE).filter(id => people.has(id));
+      const place = await this.placeOf(photos, kind === 'place' ? PLACE_RADIUS_M : EVENT_PLACE_RADIUS_M, locations, input.geocode, placeNames);
+      created.push({
+        kind,
+        name: this.nameFor(kind, photos, place, personIds.map(id => people.get(id)!)),
+        photoIds,
+        coverPhotoId: this.chooseCover(photos).id,
+        personIds,
+        placeName: place?.name ?? null,
+        startedAt: photos[0].takenAt,
+        endedAt: photos[photos.length - 1].takenAt,
+      });
+    }
+    onProgress?.(groups.length, groups.length);
+
+    await storage.replacePendingAlbumSuggestions(created, keepIds);
+    return { suggestions: await this.getSuggestions('pending'), unorganizedPhotos: candidates.length };
+  }
+
+  async getSuggestions(status: AlbumSuggestion['status'] = 'pending'): Promise<AlbumSuggestionView[]> {
+    return (await storage.getAlbumSuggestions(status)).map(suggestion => this.toView(suggestion));
+  }
+
+  /**
+   * Create the suggested album from whichever of its photos are still in the
+   * library
+   */
+  async acceptSuggestion(id: string, name?: string): Promise<{ suggestion: AlbumSuggestionView; collection: Collection; photoCount: number }> {
+    const suggestion = await this.getPending(id);
+    const photos: FileVersion[] = [];
+    for (const photoId of suggestion.photoIds) {
+      const photo = await storage.getFileVersion(photoId);
+      if (photo && !photo.deletedAt) photos.push(photo);
+    }
+    if (photos.length === 0) {
+      throw new AlbumSuggestionError('None of the suggested photos are in the library any more', 409);
+    }
+
+    const collection = await storage.createCollectionWithPhotos(
+      { name: name ?? suggestion.name, description: 'Created from an album suggestion' },
+      photos.map(photo => photo.id)
+    );
+    const cover = photos.find(photo => photo.id === suggestion.coverPhotoId);
+    const album = cover ? await storage.setCollectionCover(collection.id, cover) : collection;
+    const updated = await storage.updateAlbumSuggestion(suggestion.id, {
+      status: 'accepted',
+      collectionId: album.id,
+      resolvedAt: new Date(),
+    });
+    return { suggestion: this.toView(updated!), collection: album, photoCount: photos.length };
+  }
+
+  async dismissSuggestion(id: string): Promise<AlbumSuggestionView> {
+    const suggestion = await this.getPending(id);
+    const updated = await storage.updateAlbumSuggestion(suggestion.id, { status: 'dismissed', resolvedAt: new Date() });
+    return this.toView(updated!);
+  }
+
+  /**
+   * The displayed (highest tier) version of every asset that is in no album,
+   * with stacks collapsed to their cover. Smart collections and person albums
+   * fill themselves, so being in one does not count as organized.
+   */
+  private async loadUnorganizedPhotos(): Promise<CandidatePhoto[]> {
+    const versions = await storage.getAllFileVersions();
+    const versionsById = new Map(versions.map(version => [version.id, version]));
+
+    const organized = new Set<string>();
+    for (const collection of await storage.getCollections()) {
+      if (collection.isSmartCollection || collection.personAlbum) continue;
+      for (const photoId of await storage.getCollectionPhotoIds(collection.id)) {
+        const version = versionsById.get(photoId);
+        if (version) organized.add(version.mediaAssetId);
+      }
+    }
+
+    const displayed = new Map<string, FileVersion>();
+    for (const version of versions) {
+      if (organized.has(version.mediaAssetId)) continue;
+      const current = displayed.get(version.mediaAssetId);
+      if (!current || TIER_ORDER[version.tier] > TIER_ORDER[current.tier]) displayed.set(version.mediaAssetId, version);
+    }
+
+    // Faces point at the version that was analysed, so people are gathered per asset
+    const peopleByAsset = new Map<string, Set<string>>();
+    for (const face of await storage.getAllFaces()) {
+      const version = versionsById.get(face.photoId);
+      if (!face.personId || face.ignored || !version) continue;
+      const assetPeople = peopleByAsset.get(version.mediaAssetId) ?? new Set<string>();
+      assetPeople.add(face.personId);
+      peopleByAsset.set(version.mediaAssetId, assetPeople);
+    }
+
+    const photos = await photoStackService.applyStackMode(Array.from(displayed.values()), false);
+    return photos
+      .map(photo => ({
+        photo,
+        takenAt: getEffectiveDate(photo),
+        coordinates: photo.gpsLatitude && photo.gpsLongitude
+          ? { latitude: photo.gpsLatitude, longitude: photo.gpsLongitude }
+          : null,
+        personIds: Array.from(peopleByAsset.get(photo.mediaAssetId) ?? []),
+      }))
+      .sort((a, b) => a.takenAt.getTime() - b.takenAt.getTime());
+  }
+
+  /**
+   * Runs of photos without a pause longer than maxGapMs. Runs too small, or
+   * too long to be one event, are left for the other groupings.
+   */
+  private groupByTime(photos: CandidatePhoto[], maxGapMs: number, minPhotos: number): CandidatePhoto[][] {
+    const runs: CandidatePhoto[][] = [];
+    let run: CandidatePhoto[] = [];
+    for (const candidate of photos) {
+      const previous = run[run.length - 1];
+      if (previous && candidate.takenAt.getTime() - previous.takenAt.getTime() > maxGapMs) {
+        runs.push(run);
+        run = [];
+      }
+      run.push(candidate);
+    }
+    if (run.length > 0) runs.push(run);
+
+    return runs.filter(photos => photos.length >= minPhotos
+      && photos[photos.length - 1].takenAt.getTime() - photos[0].takenAt.getTime() <= MAX_EVENT_DAYS * 86_400_000);
+  }
+
+  /**
+   * Places photographed on several different days, such as a holiday home
+   * or a favourite park
+   */
+  private groupByPlace(photos: CandidatePhoto[], minPhotos: number): CandidatePhoto[][] {
+    return this.clusterByDistance(photos.filter(candidate => candidate.coordinates), PLACE_RADIUS_M)
+      .filter(cluster => cluster.length >= minPhotos
+        && new Set(cluster.map(candidate => this.dayKey(candidate.takenAt))).size >= MIN_PLACE_DAYS);
+  }
+
+  /**
+   * Pairs of people often in the same photos, the pair with the most photos
+   * first; each photo goes to one pair only
+   */
+  private groupByPeople(photos: CandidatePhoto[], minPhotos: number): CandidatePhoto[][] {
+    const pairs = new Map<string, CandidatePhoto[]>();
+    for (const candidate of photos) {
+      const ids = [...candidate.personIds].sort();
+      for (let i = 0; i < ids.length; i++) {
+        for (let j = i + 1; j < ids.length; j++) {
+          const key = `${ids[i]}|${ids[j]}`;
+          pairs.set(key, [...(pairs.get(key) ?? []), candidate]);
+        }
+      }
+    }
+
+    const used = new Set<string>();
+    const groups: CandidatePhoto[][] = [];
+    for (const members of Array.from(pairs.values()).sort((a, b) => b.length - a.length)) {
+      const remaining = members.filter(candidate => !used.has(candidate.photo.id));
+      if (remaining.length < minPhotos) continue;
+      remaining.forEach(candidate => used.add(candidate.photo.id));
+      groups.push(remaining);
+    }
+    return groups;
+  }
+
+  /**
+   * Greedy clustering around the first unclustered photo, as the location
+   * hotspots do, keeping the photos in date order within each cluster
+   */
+  private clusterByDistance(photos: CandidatePhoto[], radiusMeters: number): CandidatePhoto[][] {
+    const clusters: CandidatePhoto[][] = [];
+    const clustered = new Set<string>();
+    for (const seed of photos) {
+      if (clustered.has(seed.photo.id)) continue;
+      const cluster = photos.filter(candidate => !clustered.has(candidate.photo.id) && locationClusteringService.calculateDistance(
+        seed.coordinates!.latitude, seed.coordinates!.longitude,
+        candidate.coordinates!.latitude, candidate.coordinates!.longitude
+      ) <= radiusMeters);
+      cluster.forEach(candidate => clustered.add(candidate.photo.id));
+      clusters.push(cluster);
+    }
+    return clusters;
+  }
+
+  /**
+   * People in at least the given share of the photos, most photographed first
+   */
+  private namedPeople(photos: CandidatePhoto[], minShare: number): string[] {
+    const counts = new Map<string, number>();
+    for (const candidate of photos) {
+      candidate.personIds.forEach(id => counts.set(id, (counts.get(id) ?? 0) + 1));
+    }
+    return Array.from(counts.entries())
+      .filter(([, count]) => count >= 2 && count >= photos.length * minShare)
+      .sort((a, b) => b[1] - a[1])
+      .map(([id]) => id);
+  }
+
+  /**
+   * Name of the place most of the geotagged photos were taken: a saved
+   * location covering it, else the town OpenStreetMap puts it in
+   */
+  private async placeOf(
+    photos: CandidatePhoto[],
+    radiusMeters: number,
+    locations: Location[],
+    geocode: boolean,
+    cache: Map<string, Place | null>
+  ): Promise<Place | null> {
+    const [largest] = this.clusterByDistance(photos.filter(candidate => candidate.coordinates), radiusMeters)
+      .sort((a, b) => b.length - a.length);
+    if (!largest) return null;
+    const latitude = largest.reduce((sum, candidate) => sum + candidate.coordinates!.latitude, 0) / largest.length;
+    const longitude = largest.reduce((sum, candidate) => sum + candidate.coordinates!.longitude, 0) / largest.length;
+
+    const saved = locations
+      .map(location => ({
+        location,
+        distance: locationClusteringService.calculateDistance(latitude, longitude, parseFloat(location.latitude), parseFloat(location.longitude)),
+      }))
+      .filter(({ location, distance }) => distance <= Math.max(location.radius ?? 100, PLACE_RADIUS_M))
+      .sort((a, b) => a.distance - b.distance)[0];
+    if (saved) return { name: saved.location.name, preposition: 'at' };
+    if (!geocode) return null;
+
+    // Neighbouring suggestions share a lookup; Nominatim allows one request a second
+    const key = `${latitude.toFixed(2)},${longitude.toFixed(2)}`;
+    if (!cache.has(key)) {
+      const result = await reverseGeocodingService.reverseGeocode(latitude, longitude);
+      const address = result?.address;
+      const town = address?.city ?? address?.town ?? address?.village ?? address?.county ?? address?.state;
+      cache.set(key, town ? { name: town, preposition: 'in' } : null);
+    }
+    return cache.get(key)!;
+  }
+
+  private nameFor(kind: AlbumSuggestion['kind'], photos: CandidatePhoto[], place: Place | null, personNames: string[]): string {
+    if (kind === 'people') {
+      return this.joinNames(personNames.slice(0, 2)) + (place ? ` ${place.preposition} ${place.name}` : '');
+    }
+    const withPeople = personNames.length > 0 ? ` with ${this.joinNames(personNames)}` : '';
+    if (kind === 'place') {
+      const { latitude, longitude } = photos[0].coordinates!;
+      return `${place?.name ?? `Place near ${latitude.toFixed(3)}, ${longitude.toFixed(3)}`}${withPeople}`;
+    }
+
+    // An event name the pipeline gave most of the photos beats a date
+    const eventNames = new Map<string, number>();
+    for (const { photo } of photos) {
+      if (photo.eventName) eventNames.set(photo.eventName, (eventNames.get(photo.eventName) ?? 0) + 1);
+    }
+    const [event] = Array.from(eventNames.entries()).sort((a, b) => b[1] - a[1]);
+    const where = place ? ` ${place.preposition} ${place.name}` : '';
+    if (event && event[1] >= photos.length / 2) {
+      return `${event[0]}${where}${withPeople}`;
+    }
+
+    const start = photos[0].takenAt;
+    const end = photos[photos.length - 1].takenAt;
+    const days = Math.round((this.midnight(end) - this.midnight(start)) / 86_400_000) + 1;
+    if (this.isWeekend(start, days)) {
+      return place ? `Weekend${where}${withPeople}` : `Weekend of ${this.formatDate(start, { month: 'long', day: 'numeric', year: 'numeric' })}${withPeople}`;
+    }
+    if (days === 1) {
+      return place
+        ? `${this.formatDate(start, { weekday: 'long' })}${where}${withPeople}`
+        : `${this.formatDate(start, { weekday: 'long', month: 'long', day: 'numeric', year: 'numeric' })}${withPeople}`;
+    }
+    return place
+      ? `${days} days${where}${withPeople}`
+      : `${this.formatDate(start, { month: 'long', day: 'numeric' })} – ${this.formatDate(end, { month: 'long', day: 'numeric', year: 'numeric' })}${withPeople}`;
+  }
+
+  // Two or three days that fall on Friday to Monday and take in Saturday or Sunday
+  private isWeekend(start: Date, days: number): boolean {
+    if (days < 2 || days > 3) return false;
+    const weekdays = Array.from({ length: days }, (_, offset) => (start.getDay() + offset) % 7);
+    return weekdays.every(day => day === 5 || day === 6 || day === 0 || day === 1)
+      && weekdays.some(day => day === 6 || day === 0);
+  }
+
+  // "Sam", "Sam and Alex", "Sam, Alex and 2 others"
+  private joinNames(names: string[]): string {
+    if (names.length <= 2) return names.join(' and ');
+    if (names.length === 3) return `${names[0]}, ${names[1]} and ${names[2]}`;
+    return `${names[0]}, ${names[1]} and ${names.length - 2} others`;
+  }
+
+  // Best rated, then best scored, then highest tier
+  private chooseCover(photos: CandidatePhoto[]): FileVersion {
+    return photos
+      .map(candidate => candidate.photo)
+      .sort((a, b) => (b.rating ?? 0) - (a.rating ?? 0)
+        || (b.promotionScore ?? 0) - (a.promotionScore ?? 0)
+        || TIER_ORDER[b.tier] - TIER_ORDER[a.tier])[0];
+  }
+
+  private async getPending(id: string): Promise<AlbumSuggestion> {
+    const suggestion = await storage.getAlbumSuggestion(id);
+    if (!suggestion) throw new AlbumSuggestionError('Album suggestion not found', 404);
+    if (suggestion.status !== 'pending') throw new AlbumSuggestionError(`Album suggestion was already ${suggestion.status}`, 409);
+    return suggestion;
+  }
+
+  private toView(suggestion: AlbumSuggestion): AlbumSuggestionView {
+    const photoCount = suggestion.photoIds.length;
+    return { ...suggestion, photoCount, summary: `${suggestion.name}, ${photoCount} ${photoCount === 1 ? 'photo' : 'photos'}` };
+  }
+
+  private signature(photoIds: string[]): string {
+    return [...photoIds].sort().join(',');
+  }
+
+  private dayKey(date: Date): string {
+    return `${date.getFullYear()}-${date.getMonth()}-${date.getDate()}`;
+  }
+
+  private midnight(date: Date): number {
+    return new Date(date.getFullYear(), date.getMonth(), date.getDate()).getTime();
+  }
+
+  private formatDate(date: Date, options: Intl.DateTimeFormatOptions): string {
+    return date.toLocaleDateString('en-US', options);
+  }
+}
+
+export const albumSuggestionService = new AlbumSuggestionService();
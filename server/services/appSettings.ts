@@ -0,0 +1,104 @@
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { formatRegistry, SUPPORTED_FORMATS_SETTING } from "./formatRegistry";
+import { thumbnailService, THUMBNAIL_ENCODER_SETTINGS } from "./thumbnailService";
+import { aiProviderRegistry, AI_PROVIDER_SETTING } from "./aiProviders";
+import { libraryInitService, LIBRARY_ROOT_SETTING } from "./libraryInit";
+import { libraryMigrationService } from "./libraryMigration";
+import { operationRegistry } from "./operations";
+import { getLibraryRoot } from "../utils/libraryPaths";
+import { getAppConfigPath, readAppConfig, writeAppConfig } from "../utils/appConfig";
+import { redactSettingValue, keepSavedSecrets } from "../utils/settingSecrets";
+
+export const updateSettingsSchema = z.object({
+  libraryRoot: z.string().min(1).optional(), // switch to an existing library folder
+  settings: z.record(z.string()).optional(),
+});
+
+export type UpdateSettings = z.infer<typeof updateSettingsSchema>;
+
+// The requested change is not possible; nothing was changed
+export class SettingsError extends Error {}
+
+export interface AppSettings {
+  libraryRoot: string;
+  libraryRootSource: 'environment' | 'app_config' | 'database' | 'default';
+  configPath: string;
+  settings: Record<string, string>;
+}
+
+/**
+ * One view over the library location (kept in the app config file) and the
+ * library's own settings (kept in its database), updated together.
+ */
+class AppSettingsService {
+  async getSettings(): Promise<AppSettings> {
+    const config = await readAppConfig();
+    const settings: Record<string, string> = {};
+    for (const setting of await storage.getAllSettings()) {
+      settings[setting.key] = redactSettingValue(setting.key, setting.value);
+    }
+    return {
+      libraryRoot: getLibraryRoot(),
+      libraryRootSource: process.env.PICTALLION_LIBRARY_ROOT
+        ? 'environment'
+        : config.libraryRoot ? 'app_config' : settings[LIBRARY_ROOT_SETTING] ? 'database' : 'default',
+      configPath: getAppConfigPath(),
+      settings,
+    };
+  }
+
+  /**
+   * Apply a set of changes. A new library root must already hold a library
+   * (e.g. one on an external drive); moving the current library to a new
+   * place, which rewrites stored paths, goes through the library migration.
+   */
+  async updateSettings(update: UpdateSettings): Promise<AppSettings> {
+    if (update.libraryRoot && path.resolve(update.libraryRoot) !== getLibraryRoot()) {
+      await this.switchLibrary(update.libraryRoot);
+    }
+
+    const entries = Object.entries(update.settings ?? {});
+    for (const [key, value] of entries) {
+      const saved = await storage.getSettingByKey(key);
+      if (saved) {
+        await storage.updateSetting(key, keepSavedSecrets(key, value, saved.value));
+      } else {
+        await storage.createSetting({ key, value, category: 'general' });
+      }
+    }
+    if (entries.some(([key]) => key === SUPPORTED_FORMATS_SETTING)) {
+      await formatRegistry.refresh();
+    }
+    if (entries.some(([key]) => THUMBNAIL_ENCODER_SETTINGS.includes(key))) {
+      thumbnailService.refreshEncoderSettings();
+    }
+    if (entries.some(([key]) => key === AI_PROVIDER_SETTING)) {
+      await aiProviderRegistry.refresh();
+    }
+
+    return this.getSettings();
+  }
+
+  private async switchLibrary(root: string): Promise<void> {
+    const resolved = path.resolve(root);
+    if (process.env.PICTALLION_LIBRARY_ROOT) {
+      throw new SettingsError('The library location is fixed by PICTALLION_LIBRARY_ROOT');
+    }
+    if (operationRegistry.list().length > 0) {
+      throw new SettingsError('Wait for running operations to finish before switching libraries');
+    }
+    if (!(await libraryMigrationService.isLibrary(resolved))) {
+      throw new SettingsError(`${resolved} does not hold a library; use /api/library/migrate to move the current one there`);
+    }
+
+    const report = await libraryInitService.initializeLibrary(resolved, { createMissing: false });
+    if (report.steps[0].status !== 'completed') {
+      throw new SettingsError(report.steps[0].message ?? `Could not open the library at ${resolved}`);
+    }
+    await writeAppConfig({ libraryRoot: resolved });
+  }
+}
+
+export const appSettingsService = new AppSettingsService();
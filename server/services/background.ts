@@ -0,0 +1,113 @@
+import { storage } from "../storage";
+import { operationRegistry, type OperationInfo } from "./operations";
+import { frameSyncService } from "./frameSync";
+import { aiBatchScheduler } from "./aiBatchScheduler";
+import { jobQueue } from "./jobQueue";
+import { folderWatcherService } from "./folderWatcher";
+import { importSessionService } from "./importSessions";
+import { softDeleteService } from "./softDelete";
+import { counterConsistencyService } from "./counterConsistency";
+import { smartCollectionService } from "./smartCollectionService";
+import { libraryBackupService } from "./libraryBackup";
+
+export const RUN_IN_BACKGROUND_SETTING = 'run_in_background';
+
+const MAINTENANCE_INTERVAL_MS = 24 * 60 * 60 * 1000;
+const RECENT_IMPORTS_LIMIT = 10;
+
+export interface RecentImport {
+  photoId: string;
+  filename: string;
+  tier: string;
+  importedAt: Date;
+}
+
+export interface BackgroundStatus {
+  paused: boolean;
+  pausedAt: Date | null;
+  runInBackground: boolean; // keep working in the tray once the main window is closed
+  operations: OperationInfo[];
+  recentImports: RecentImport[];
+}
+
+/**
+ * Work that keeps going while the desktop app sits in the tray with its
+ * window closed: watch-folder imports, queued imports and jobs, AI batches,
+ * frame syncs and daily maintenance. Pausing stops all of it after the item
+ * in progress, leaving queued work to pick up again on resume.
+ */
+class BackgroundService {
+  private pausedAt: Date | null = null;
+  private maintenanceHandle: NodeJS.Timeout | null = null;
+
+  async getStatus(): Promise<BackgroundStatus> {
+    const setting = await storage.getSettingByKey(RUN_IN_BACKGROUND_SETTING);
+    const recent = await storage.getRecentPhotos(RECENT_IMPORTS_LIMIT);
+    return {
+      paused: this.pausedAt !== null,
+      pausedAt: this.pausedAt,
+      runInBackground: setting?.value !== 'false',
+      operations: operationRegistry.list(),
+      recentImports: recent.map(photo => ({
+        photoId: photo.id,
+        filename: photo.mediaAsset.originalFilename,
+        tier: photo.tier,
+        importedAt: photo.createdAt,
+      })),
+    };
+  }
+
+  async pause(): Promise<BackgroundStatus> {
+    if (!this.pausedAt) {
+      this.pausedAt = new Date();
+      frameSyncService.stopScheduler();
+      libraryBackupService.stopScheduler();
+      aiBatchScheduler.pause();
+      folderWatcherService.stop();
+      await Promise.all([jobQueue.pause(), importSessionService.pause()]);
+      console.log('Background work paused');
+    }
+    return this.getStatus();
+  }
+
+  /**
+   * Start everything again; watch folders catch up on files that arrived
+   * while paused
+   */
+  async resume(): Promise<BackgroundStatus> {
+    if (this.pausedAt) {
+      this.pausedAt = null;
+      frameSyncService.startScheduler();
+      libraryBackupService.startScheduler();
+      aiBatchScheduler.resume();
+      await Promise.all([jobQueue.start(), importSessionService.start(), folderWatcherService.start()]);
+      console.log('Background work resumed');
+    }
+    return this.getStatus();
+  }
+
+  /**
+   * Purge expired deleted items, check counters and refresh albums with
+   * relative date rules once a day for as long as the app stays open
+   */
+  startMaintenance(): void {
+    if (this.maintenanceHandle) return;
+    this.maintenanceHandle = setInterval(() => {
+      if (this.pausedAt) return;
+      softDeleteService.purgeExpired()
+        .then(() => counterConsistencyService.check(true))
+        .then(() => smartCollectionService.evaluateRelativeDateCollections())
+        .catch(error => console.error("Scheduled maintenance failed:", error));
+    }, MAINTENANCE_INTERVAL_MS);
+    this.maintenanceHandle.unref();
+  }
+
+  stopMaintenance(): void {
+    if (this.maintenanceHandle) {
+      clearInterval(this.maintenanceHandle);
+      this.maintenanceHandle = null;
+    }
+  }
+}
+
+export const backgroundService = new BackgroundService();
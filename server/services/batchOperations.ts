@@ -0,0 +1,272 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { and, eq, inArray } from "drizzle-orm";
+import { db } from "../db";
+import { storage } from "../storage";
+import { fileManager } from "./fileManager";
+import { smartCollectionService } from "./smartCollectionService";
+import { assertUnlocked } from "./photoLock";
+import { getCaptureDate } from "../utils/photoDates";
+import { getExifColumns } from "../utils/exifColumns";
+import { assetHistory, collectionPhotos, fileVersions, type FileVersion } from "@shared/schema";
+import { getLibraryRoot } from "../utils/libraryPaths";
+import { applyTagChange, propagationScopeSchema, propagationService, type PropagationScope, type TagChange } from "./propagation";
+
+export const pickFlagSchema = z.enum(["pick", "reject"]).nullable();
+
+export const photoOperationSchema = z.discriminatedUnion("type", [
+  z.object({ type: z.literal("tag"), action: z.enum(["add", "remove", "set"]), tags: z.array(z.string()) }),
+  z.object({ type: z.literal("rate"), rating: z.number().int().min(0).max(5) }),
+  z.object({ type: z.literal("album"), action: z.enum(["add", "remove"]), collectionId: z.string() }),
+  z.object({ type: z.literal("tier"), tier: z.literal("gold") }),
+  z.object({ type: z.literal("flag"), flag: z.enum(["reviewed", "rejected"]), value: z.boolean() }),
+  z.object({ type: z.literal("pick"), flag: pickFlagSchema }), // null clears the flag
+]);
+
+export const applyOperationsSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  operations: z.array(photoOperationSchema).min(1),
+  propagate: propagationScopeSchema.optional(), // overrides the configured rating/tag propagation
+});
+
+export const batchRatingSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  rating: z.number().int().min(0).max(5),
+  propagate: propagationScopeSchema.optional(),
+});
+
+export const batchPickFlagSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  flag: pickFlagSchema,
+});
+
+export type PhotoOperation = z.infer<typeof photoOperationSchema>;
+
+export interface OperationResult {
+  photoId: string;
+  success: boolean;
+  resultPhotoId?: string; // differs from photoId when a tier operation created a new version
+  propagatedTo?: string[]; // stack members and versions that received the rating/tag changes
+  error?: string;
+}
+
+interface PlannedChanges {
+  updates: Partial<FileVersion>;
+  albumsToAdd: Set<string>;
+  albumsToRemove: Set<string>;
+  promote: boolean;
+  history: string[];
+}
+
+/**
+ * Apply an ordered list of culling operations to many photos. Each photo's
+ * operations are folded into one set of changes and committed in a single
+ * transaction, so a photo is either fully updated or left untouched.
+ */
+class BatchOperationService {
+  private get dataDir(): string {
+    return getLibraryRoot();
+  }
+
+  async applyOperations(photoIds: string[], operations: PhotoOperation[], propagate?: PropagationScope): Promise<OperationResult[]> {
+    const knownCollections = new Set<string>();
+    for (const operation of operations) {
+      if (operation.type !== 'album' || knownCollections.has(operation.collectionId)) continue;
+      if (!(await storage.getCollection(operation.collectionId))) {
+        throw new Error(`Collection ${operation.collectionId} not found`);
+      }
+      knownCollections.add(operation.collectionId);
+    }
+
+    const ids = Array.from(new Set(photoIds));
+    const results: OperationResult[] = [];
+    for (const photoId of ids) {
+      try {
+        const resultPhotoId = await this.applyToPhoto(photoId, operations);
+        // Photos in the batch get the operations themselves, not a propagated copy
+        const propagatedTo = await this.propagate(photoId, operations, propagate, new Set([...ids, resultPhotoId]));
+        results.push({ photoId, success: true, resultPhotoId, propagatedTo });
+        smartCollectionService.photosChanged([photoId, resultPhotoId]);
+      } catch (error) {
+        results.push({ photoId, success: false, error: error instanceof Error ? error.message : String(error) });
+      }
+    }
+    return results;
+  }
+
+  private async applyToPhoto(photoId: string, operations: PhotoOperation[]): Promise<string> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) {
+      throw new Error('Photo not found');
+    }
+
+    const plan = this.plan(photo, operations);
+    // Promotion leaves the photo itself alone; anything else changes it
+    if (!plan.promote && Object.keys(plan.updates).length > 0) {
+      assertUnlocked(photo, 'edit');
+    }
+
+    // The gold copy is written before the transaction and removed again if it rolls back
+    let goldPath: string | null = null;
+    if (plan.promote) {
+      goldPath = await fileManager.copyToGold(photo.filePath, getCaptureDate(photo) ?? undefined, photo.fileHash);
+    }
+
+    try {
+      return await db.transaction(async (tx) => {
+        let targetId = photo.id;
+
+        if (goldPath) {
+          const [goldVersion] = await tx
+            .insert(fileVersions)
+            .values({
+              mediaAssetId: photo.mediaAssetId,
+              tier: 'gold',
+              filePath: goldPath,
+              fileHash: photo.fileHash,
+              fileSize: photo.fileSize,
+              mimeType: photo.mimeType,
+              width: photo.width,
+              height: photo.height,
+              isPanorama: photo.isPanorama,
+              metadata: photo.metadata,
+              ...getExifColumns(photo.metadata),
+              rating: photo.rating,
+              pickFlag: photo.pickFlag,
+              keywords: photo.keywords,
+              location: photo.location,
+              eventType: photo.eventType,
+              eventName: photo.eventName,
+              perceptualHash: photo.perceptualHash,
+              aiShortDescription: photo.aiShortDescription,
+              isReviewed: true,
+              ...plan.updates,
+            })
+            .returning();
+          targetId = goldVersion.id;
+        } else if (Object.keys(plan.updates).length > 0) {
+          await tx.update(fileVersions).set(plan.updates).where(eq(fileVersions.id, photo.id));
+        }
+
+        for (const collectionId of Array.from(plan.albumsToRemove)) {
+          await tx
+            .delete(collectionPhotos)
+            .where(and(eq(collectionPhotos.collectionId, collectionId), inArray(collectionPhotos.photoId, [photo.id, targetId])));
+        }
+        for (const collectionId of Array.from(plan.albumsToAdd)) {
+          const [existing] = await tx
+            .select({ id: collectionPhotos.id })
+            .from(collectionPhotos)
+            .where(and(eq(collectionPhotos.collectionId, collectionId), eq(collectionPhotos.photoId, targetId)));
+          if (!existing) {
+            await tx.insert(collectionPhotos).values({ collectionId, photoId: targetId });
+          }
+        }
+
+        if (plan.history.length > 0) {
+          await tx.insert(assetHistory).values({
+            mediaAssetId: photo.mediaAssetId,
+            action: 'BATCH_OPERATIONS',
+            details: plan.history.join('; '),
+          });
+        }
+
+        return targetId;
+      });
+    } catch (error) {
+      if (goldPath) {
+        await fs.rm(path.join(this.dataDir, goldPath), { force: true });
+      }
+      throw error;
+    }
+  }
+
+  /**
+   * Pass the rating and tag operations on to the photo's stack members and
+   * versions, as configured
+   */
+  private async propagate(photoId: string, operations: PhotoOperation[], override: PropagationScope | undefined, exclude: Set<string>): Promise<string[]> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return [];
+
+    const propagatedTo = new Set<string>();
+    const rate = operations.filter(operation => operation.type === 'rate').pop();
+    if (rate?.type === 'rate') {
+      (await propagationService.propagateRating(photo, rate.rating, override, exclude)).forEach(id => propagatedTo.add(id));
+    }
+    const tagChanges: TagChange[] = operations.flatMap(operation =>
+      operation.type === 'tag' ? [{ action: operation.action, tags: operation.tags }] : []
+    );
+    if (tagChanges.length > 0) {
+      (await propagationService.propagateTags(photo, tagChanges, override, exclude)).forEach(id => propagatedTo.add(id));
+    }
+    return Array.from(propagatedTo);
+  }
+
+  /**
+   * Fold the operations, in order, into the final field values for the photo
+   */
+  private plan(photo: FileVersion, operations: PhotoOperation[]): PlannedChanges {
+    const plan: PlannedChanges = {
+      updates: {},
+      albumsToAdd: new Set(),
+      albumsToRemove: new Set(),
+      promote: false,
+      history: [],
+    };
+    const metadata = (photo.metadata as any) || {};
+    let tags: string[] | null = null;
+
+    for (const operation of operations) {
+      switch (operation.type) {
+        case 'tag': {
+          tags = applyTagChange(tags ?? metadata.ai?.aiTags ?? [], operation);
+          plan.history.push(`Tags ${operation.action}: ${operation.tags.join(', ')}`);
+          break;
+        }
+        case 'rate':
+          plan.updates.rating = operation.rating;
+          plan.history.push(`Rated ${operation.rating}`);
+          break;
+        case 'album':
+          if (operation.action === 'add') {
+            plan.albumsToAdd.add(operation.collectionId);
+            plan.albumsToRemove.delete(operation.collectionId);
+          } else {
+            plan.albumsToRemove.add(operation.collectionId);
+            plan.albumsToAdd.delete(operation.collectionId);
+          }
+          plan.history.push(`Album ${operation.action}: ${operation.collectionId}`);
+          break;
+        case 'tier':
+          if (photo.tier === 'gold') break;
+          if (photo.tier !== 'silver') {
+            throw new Error('Only Silver tier photos can be promoted to Gold');
+          }
+          plan.promote = true;
+          plan.history.push('Promoted from Silver to Gold tier');
+          break;
+        case 'flag':
+          if (operation.flag === 'reviewed') {
+            plan.updates.isReviewed = operation.value;
+          } else {
+            plan.updates.processingState = operation.value ? 'rejected' : 'processed';
+          }
+          plan.history.push(`${operation.value ? 'Flagged' : 'Unflagged'} ${operation.flag}`);
+          break;
+        case 'pick':
+          plan.updates.pickFlag = operation.flag;
+          plan.history.push(operation.flag ? `Marked as ${operation.flag}` : 'Cleared pick/reject flag');
+          break;
+      }
+    }
+
+    if (tags) {
+      plan.updates.metadata = { ...metadata, ai: { ...(metadata.ai || {}), aiTags: tags } };
+    }
+    return plan;
+  }
+}
+
+export const batchOperationService = new BatchOperationService();
@@ -1,25 +1,41 @@
+import { z } from "zod";
 import { storage } from "../storage";
-import crypto from "crypto";
-import { promises as fs } from "fs";
-import path from "path";
+import { loadLuma, measureSharpness } from "../utils/imageQuality";
+import { getShutterTime } from "../utils/photoDates";
+import { volumeStatusService } from "./volumeStatus";
+import type { BurstWithFrames, FileVersion, MediaAsset } from "@shared/schema";
+
+export const selectBurstBestSchema = z.object({
+  stack: z.boolean().default(false), // stack the other frames under the best one
+});
+
+export type SelectBurstBestInput = z.infer<typeof selectBurstBestSchema>;
+
+export interface BurstPhoto {
+  id: string;
+  filePath: string;
+  metadata?: any;
+  mediaAsset: {
+    originalFilename: string;
+  };
+  createdAt: string;
+  fileSize?: number;
+  fileHash: string;
+  tier: FileVersion['tier'];
+  capturedAt: string; // shutter time to the millisecond, ISO
+  sharpness: number | null; // 0-1, once the best frame has been chosen
+}
 
 export interface BurstGroup {
   id: string;
-  photos: Array<{
-    id: string;
-    filePath: string;
-    metadata?: any;
-    mediaAsset: {
-      originalFilename: string;
-    };
-    createdAt: string;
-    fileSize?: number;
-    fileHash: string;
-  }>;
+  photos: BurstPhoto[]; // in capture order
   suggestedBest: string; // ID of the photo to promote
-  averageSimilarity: number;
+  camera: string | null;
   timeSpan: number; // in milliseconds
   groupReason: string;
+  bestPhotoId: string | null; // set once resolved
+  stackId: string | null;
+  resolvedAt: string | null;
 }
 
 export interface BurstAnalysis {
@@ -36,384 +52,295 @@ export interface BurstAnalysis {
   }>;
 }
 
-export class BurstPhotoDetectionService {
-  
-  /**
-   * Analyze photos from all tiers for burst sequences
-   * Groups photos with 95%+ similarity taken within ±10 seconds
-   * Handles cross-tier detection and mixed-tier groups
-   */
-  async analyzeBurstPhotos(allPhotos: any[]): Promise<BurstAnalysis> {
-    try {
-      if (allPhotos.length === 0) {
-        return this.generateEmptyAnalysis();
-      }
-
-      // Group photos by mediaAssetId first to handle same original photo across tiers
-      const photosByAsset = new Map<string, any[]>();
-      for (const photo of allPhotos) {
-        if (!photosByAsset.has(photo.mediaAssetId)) {
-          photosByAsset.set(photo.mediaAssetId, []);
-        }
-        photosByAsset.get(photo.mediaAssetId)!.push(photo);
-      }
+export interface BurstBestSelection {
+  burst: BurstGroup;
+  bestPhotoId: string;
+  stackId: string | null;
+  skipped: Array<{ photoId: string; reason: string }>; // frames that could not be measured
+}
 
-      // For burst detection, use one representative photo per media asset
-      // Priority: unprocessed bronze > processed bronze > silver > gold
-      const representativePhotos = Array.from(photosByAsset.values()).map(versions => {
-        return versions.sort((a, b) => {
-          // Priority order for burst detection
-          const tierPriority: { [key: string]: number } = { bronze: 0, silver: 1, gold: 2 };
-          const statePriority: { [key: string]: number } = { unprocessed: 0, processed: 1, promoted: 2, rejected: 3 };
-          
-          if (tierPriority[a.tier] !== tierPriority[b.tier]) {
-            return tierPriority[a.tier] - tierPriority[b.tier];
-          }
-          
-          if (a.tier === 'bronze' && b.tier === 'bronze') {
-            return (statePriority[a.processingState || 'unprocessed'] || 0) - 
-                   (statePriority[b.processingState || 'unprocessed'] || 0);
-          }
-          
-          return 0;
-        })[0];
-      });
+export class BurstError extends Error {
+  constructor(message: string, readonly status: number) {
+    super(message);
+  }
+}
 
-      // Sort photos by creation time for efficient processing
-      const sortedPhotos = [...representativePhotos].sort((a, b) => 
-        new Date(a.createdAt).getTime() - new Date(b.createdAt).getTime()
-      );
+// Longest gap between consecutive frames of one burst. Cameras without
+// sub-second timestamps put every frame on a whole second, so this must
+// reach the next second.
+const MAX_FRAME_GAP_MS = 1000;
+const MIN_FRAMES = 2;
 
-      const burstGroups: BurstGroup[] = [];
-      const processedPhotos = new Set<string>();
-      const ungroupedPhotos: any[] = [];
+type PhotoWithAsset = FileVersion & { mediaAsset: MediaAsset };
 
-      for (let i = 0; i < sortedPhotos.length; i++) {
-        const currentPhoto = sortedPhotos[i];
-        
-        if (processedPhotos.has(currentPhoto.id)) {
-          continue;
-        }
+interface TimedPhoto {
+  photo: PhotoWithAsset;
+  shutterTime: number;
+  camera: string | null;
+}
 
-        // Find photos within 10 seconds window using extracted timestamps
-        const timeWindow = 10 * 1000; // 10 seconds in milliseconds
-        const getPhotoTime = (photo: any) => {
-          // First try EXIF datetime fields
-          if (photo.metadata?.exif?.dateTime) {
-            return new Date(photo.metadata.exif.dateTime).getTime();
-          }
-          if (photo.metadata?.exif?.dateTimeOriginal) {
-            return new Date(photo.metadata.exif.dateTimeOriginal).getTime();
-          }
-          
-          // Try to extract from filename if it has timestamp format (YYYYMMDD_HHMMSS)
-          const filename = photo.mediaAsset.originalFilename;
-          const timestampMatch = filename.match(/^(\d{8})_(\d{6})/);
-          if (timestampMatch) {
-            const dateStr = timestampMatch[1]; // YYYYMMDD
-            const timeStr = timestampMatch[2]; // HHMMSS
-            const year = parseInt(dateStr.substring(0, 4));
-            const month = parseInt(dateStr.substring(4, 6)) - 1; // Month is 0-indexed
-            const day = parseInt(dateStr.substring(6, 8));
-            const hour = parseInt(timeStr.substring(0, 2));
-            const minute = parseInt(timeStr.substring(2, 4));
-            const second = parseInt(timeStr.substring(4, 6));
-            
-            const extractedDate = new Date(year, month, day, hour, minute, second);
-            if (!isNaN(extractedDate.getTime())) {
-              return extractedDate.getTime();
-            }
-          }
-          
-          // Fall back to upload time as last resort
-          return new Date(photo.createdAt).getTime();
-        };
-        
-        const currentTime = getPhotoTime(currentPhoto);
-        const candidatePhotos = [currentPhoto];
-        
-        // Look for photos within time window
-        for (let j = i + 1; j < sortedPhotos.length; j++) {
-          const comparePhoto = sortedPhotos[j];
-          if (processedPhotos.has(comparePhoto.id)) {
-            continue;
-          }
+/**
+ * Burst sequences: frames from one camera whose EXIF shutter times
+ * (DateTimeOriginal with SubSecTimeOriginal) follow each other within a
+ * second. Photos without an EXIF capture time are never grouped; file and
+ * import times say when a file was copied, not when the shutter fired.
+ * Bursts are kept in the database until a best frame is chosen for them.
+ */
+export class BurstPhotoDetectionService {
 
-          const compareTime = getPhotoTime(comparePhoto);
-          const timeDiff = Math.abs(compareTime - currentTime);
-          
-          if (timeDiff > timeWindow) {
-            break; // Photos are sorted by time, so no more matches possible
-          }
+  /**
+   * Detect bursts across the library and store them. Unresolved bursts whose
+   * frames have not changed keep their IDs; resolved ones are left alone and
+   * their frames are not grouped again.
+   */
+  async detectBursts(): Promise<BurstAnalysis> {
+    const photos = await this.loadRepresentativePhotos();
+    const existing = await storage.getBursts();
+    const resolvedPhotoIds = new Set(
+      existing.filter(burst => burst.resolvedAt).flatMap(burst => burst.frames.map(frame => frame.photoId))
+    );
 
-          // Check similarity
-          const similarity = await this.calculatePhotoSimilarity(currentPhoto, comparePhoto);
-          if (similarity >= 0.95) {
-            candidatePhotos.push(comparePhoto);
-          }
-        }
+    const runs = this.groupByShutterTime(photos.filter(photo => !resolvedPhotoIds.has(photo.id)));
 
-        // If we found similar photos, create a burst group
-        if (candidatePhotos.length > 1) {
-          const group = await this.createBurstGroup(candidatePhotos);
-          burstGroups.push(group);
-          candidatePhotos.forEach(photo => processedPhotos.add(photo.id));
-        } else {
-          ungroupedPhotos.push(currentPhoto);
-          processedPhotos.add(currentPhoto.id);
-        }
+    const unresolvedByFrames = new Map(
+      existing.filter(burst => !burst.resolvedAt).map(burst => [this.framesKey(burst.frames.map(frame => frame.photoId)), burst])
+    );
+    const kept: string[] = [];
+    const detected = [];
+    for (const run of runs) {
+      const unchanged = unresolvedByFrames.get(this.framesKey(run.map(frame => frame.photo.id)));
+      if (unchanged) {
+        kept.push(unchanged.id);
+        continue;
       }
+      detected.push({
+        burst: {
+          camera: run[0].camera,
+          startedAt: new Date(run[0].shutterTime),
+          endedAt: new Date(run[run.length - 1].shutterTime),
+        },
+        frames: run.map(frame => ({ photoId: frame.photo.id, capturedAt: new Date(frame.shutterTime) })),
+      });
+    }
+    await storage.replaceUnresolvedBursts(detected, kept);
 
-      return {
-        groups: burstGroups,
-        totalPhotos: representativePhotos.length,
-        ungroupedPhotos: ungroupedPhotos
-      };
+    return this.buildAnalysis(photos, { includeResolved: false });
+  }
 
-    } catch (error) {
-      console.error('Error analyzing burst photos:', error);
-      return this.generateEmptyAnalysis();
-    }
+  /**
+   * Stored bursts, without detecting again
+   */
+  async getAnalysis(options: { includeResolved: boolean }): Promise<BurstAnalysis> {
+    return this.buildAnalysis(await this.loadRepresentativePhotos(), options);
+  }
+
+  async getBurst(id: string): Promise<BurstGroup | undefined> {
+    const burst = await storage.getBurst(id);
+    if (!burst) return undefined;
+    return this.toGroup(burst, await this.loadPhotos(burst.frames.map(frame => frame.photoId)));
   }
 
   /**
-   * Calculate similarity between two photos
-   * Uses multiple factors: filename similarity, file size, EXIF data, time proximity
+   * Choose the sharpest frame of a burst (highest variance of the Laplacian,
+   * so the least motion blur and missed focus) and mark the burst resolved.
+   * With `stack`, the other frames are stacked under it so listings show the
+   * burst as that one photo.
    */
-  private async calculatePhotoSimilarity(photo1: any, photo2: any): Promise<number> {
-    let similarityScore = 0;
-    let factorsChecked = 0;
-
-    // File hash comparison (if available)
-    if (photo1.fileHash && photo2.fileHash) {
-      if (photo1.fileHash === photo2.fileHash) {
-        return 1.0; // Identical files
-      }
-    }
+  async selectBest(burstId: string, input: SelectBurstBestInput): Promise<BurstBestSelection> {
+    const burst = await storage.getBurst(burstId);
+    if (!burst) throw new BurstError('Burst not found', 404);
 
-    // Time proximity is important but not enough alone for burst photos
-    // Use actual photo capture time from multiple sources in order of preference
-    const getPhotoTime = (photo: any) => {
-      // First try EXIF datetime fields
-      if (photo.metadata?.exif?.dateTime) {
-        return new Date(photo.metadata.exif.dateTime).getTime();
-      }
-      if (photo.metadata?.exif?.dateTimeOriginal) {
-        return new Date(photo.metadata.exif.dateTimeOriginal).getTime();
-      }
-      
-      // Try to extract from filename if it has timestamp format (YYYYMMDD_HHMMSS)
-      const filename = photo.mediaAsset.originalFilename;
-      const timestampMatch = filename.match(/^(\d{8})_(\d{6})/);
-      if (timestampMatch) {
-        const dateStr = timestampMatch[1]; // YYYYMMDD
-        const timeStr = timestampMatch[2]; // HHMMSS
-        const year = parseInt(dateStr.substring(0, 4));
-        const month = parseInt(dateStr.substring(4, 6)) - 1; // Month is 0-indexed
-        const day = parseInt(dateStr.substring(6, 8));
-        const hour = parseInt(timeStr.substring(0, 2));
-        const minute = parseInt(timeStr.substring(2, 4));
-        const second = parseInt(timeStr.substring(4, 6));
-        
-        const extractedDate = new Date(year, month, day, hour, minute, second);
-        if (!isNaN(extractedDate.getTime())) {
-          return extractedDate.getTime();
+    const photos = await this.loadPhotos(burst.frames.map(frame => frame.photoId));
+    const skipped: BurstBestSelection['skipped'] = [];
+    let best: { photo: PhotoWithAsset; sharpness: number } | null = null;
+
+    for (const frame of burst.frames) {
+      const photo = photos.get(frame.photoId);
+      if (!photo) continue;
+      let sharpness = frame.sharpness;
+      if (sharpness === null) {
+        try {
+          const original = await volumeStatusService.locateOriginal(photo);
+          if (!original.available) {
+            skipped.push({ photoId: photo.id, reason: `Original is offline${original.volumeLabel ? ` on ${original.volumeLabel}` : ''}` });
+            continue;
+          }
+          sharpness = measureSharpness(await loadLuma(original.path));
+          await storage.updateBurstFrame(frame.id, { sharpness });
+        } catch (error) {
+          console.error(`Sharpness measurement failed for photo ${photo.id}:`, error);
+          skipped.push({ photoId: photo.id, reason: error instanceof Error ? error.message : 'Measurement failed' });
+          continue;
         }
       }
-      
-      // Fall back to upload time as last resort
-      return new Date(photo.createdAt).getTime();
-    };
-    
-    const time1 = getPhotoTime(photo1);
-    const time2 = getPhotoTime(photo2);
-    const timeDiff = Math.abs(time1 - time2);
-    
-    // More strict time-based scoring
-    if (timeDiff <= 5000) { // Within 5 seconds - very strong indicator
-      similarityScore += 0.4;
-      factorsChecked++;
-    } else if (timeDiff <= 30000) { // Within 30 seconds - moderate indicator  
-      similarityScore += 0.2;
-      factorsChecked++;
-    } else if (timeDiff <= 60000) { // Within 1 minute - weak indicator
-      similarityScore += 0.1;
-      factorsChecked++;
+      // Frames are in capture order, so ties go to the earlier frame
+      if (!best || sharpness > best.sharpness) best = { photo, sharpness };
     }
+    if (!best) throw new BurstError('None of the frames in this burst could be measured', 409);
 
-    // Filename similarity (burst photos often have sequential names)
-    const name1 = photo1.mediaAsset.originalFilename.toLowerCase();
-    const name2 = photo2.mediaAsset.originalFilename.toLowerCase();
-    
-    // Extract base names and numbers for burst detection
-    const extractBaseAndNumber = (filename: string) => {
-      const withoutExt = filename.replace(/\.(jpg|jpeg|png|tiff)$/i, '');
-      const match = withoutExt.match(/^(.+?)[-_]?(\d+)$/);
-      if (match) {
-        return { base: match[1], number: parseInt(match[2]) };
-      }
-      return { base: withoutExt, number: null };
-    };
-    
-    const file1 = extractBaseAndNumber(name1);
-    const file2 = extractBaseAndNumber(name2);
-    
-    // Strong similarity: exact base name with sequential numbers
-    if (file1.base === file2.base && file1.number !== null && file2.number !== null) {
-      const numberDiff = Math.abs(file1.number - file2.number);
-      if (numberDiff <= 3) { // Sequential or very close numbers
-        similarityScore += 0.4;
-        factorsChecked++;
-      }
-    } else if (file1.base.length > 8 && file2.base.length > 8) {
-      // Weaker similarity: common prefix for longer names
-      const commonPrefix = this.getCommonPrefix(file1.base, file2.base);
-      if (commonPrefix.length >= Math.min(file1.base.length, file2.base.length) * 0.8) {
-        similarityScore += 0.2;
-        factorsChecked++;
-      }
+    let stackId = burst.stackId;
+    if (input.stack) {
+      if (stackId) await storage.deletePhotoStack(stackId);
+      const assetIds = burst.frames
+        .map(frame => photos.get(frame.photoId)?.mediaAssetId)
+        .filter((assetId): assetId is string => !!assetId);
+      const stack = await storage.createPhotoStack({ primaryAssetId: best.photo.mediaAssetId, kind: 'burst' }, assetIds);
+      stackId = stack.id;
     }
 
-    // File size similarity (burst photos should be very similar in size)
-    if (photo1.fileSize && photo2.fileSize) {
-      const sizeDiff = Math.abs(photo1.fileSize - photo2.fileSize);
-      const avgSize = (photo1.fileSize + photo2.fileSize) / 2;
-      const sizeRatio = 1 - (sizeDiff / avgSize);
-      if (sizeRatio > 0.95) { // Very strict size similarity for bursts
-        similarityScore += 0.3;
-        factorsChecked++;
-      } else if (sizeRatio > 0.85) { // Moderate similarity
-        similarityScore += 0.1;
-        factorsChecked++;
-      }
+    await storage.updateBurst(burst.id, { bestPhotoId: best.photo.id, stackId, resolvedAt: new Date() });
+    const updated = await this.getBurst(burst.id);
+    return { burst: updated!, bestPhotoId: best.photo.id, stackId, skipped };
+  }
+
+  /**
+   * Mark a burst resolved with a frame the user picked
+   */
+  async resolve(burstId: string, bestPhotoId: string): Promise<void> {
+    await storage.updateBurst(burstId, { bestPhotoId, resolvedAt: new Date() });
+  }
+
+  /**
+   * Split photos into runs of frames from the same camera that follow each
+   * other within MAX_FRAME_GAP_MS
+   */
+  private groupByShutterTime(photos: PhotoWithAsset[]): TimedPhoto[][] {
+    const byCamera = new Map<string, TimedPhoto[]>();
+    for (const photo of photos) {
+      const shutterTime = getShutterTime(photo);
+      if (!shutterTime) continue;
+      const camera = photo.camera ?? (photo.metadata as any)?.exif?.camera ?? null;
+      const key = camera ?? '';
+      const timed = byCamera.get(key) ?? [];
+      timed.push({ photo, shutterTime: shutterTime.getTime(), camera });
+      byCamera.set(key, timed);
     }
 
-    // EXIF data similarity (same camera settings indicate burst)
-    try {
-      const metadata1 = photo1.metadata;
-      const metadata2 = photo2.metadata;
-      
-      if (metadata1?.exif && metadata2?.exif) {
-        const exif1 = metadata1.exif;
-        const exif2 = metadata2.exif;
-        let exifScore = 0;
-        
-        // Check camera settings
-        if (exif1.make === exif2.make && exif1.model === exif2.model) {
-          exifScore += 0.05;
-        }
-        
-        if (exif1.iso === exif2.iso) {
-          exifScore += 0.05;
-        }
-        
-        if (exif1.focalLength === exif2.focalLength) {
-          exifScore += 0.05;
-        }
+    const runs: TimedPhoto[][] = [];
+    for (const timed of Array.from(byCamera.values())) {
+      // Frames within the same second are ordered by their file numbers when the camera records no sub-seconds
+      timed.sort((a, b) => a.shutterTime - b.shutterTime
+        || a.photo.mediaAsset.originalFilename.localeCompare(b.photo.mediaAsset.originalFilename, undefined, { numeric: true }));
 
-        if (exif1.aperture === exif2.aperture) {
-          exifScore += 0.05;
+      let run: TimedPhoto[] = [];
+      for (const frame of timed) {
+        if (run.length > 0 && frame.shutterTime - run[run.length - 1].shutterTime > MAX_FRAME_GAP_MS) {
+          if (run.length >= MIN_FRAMES) runs.push(run);
+          run = [];
         }
-
-        similarityScore += exifScore;
-        if (exifScore > 0) factorsChecked++;
+        run.push(frame);
       }
-    } catch (error) {
-      // EXIF comparison failed, continue without it
-    }
-
-    // Balanced criteria for burst grouping
-    if (timeDiff <= 10000 && similarityScore >= 0.5) {
-      // Photos within 10 seconds with moderate similarity - likely burst sequence
-      return Math.min(similarityScore + 0.45, 1.0);
-    } else if (timeDiff <= 30000 && similarityScore >= 0.8) {
-      // Photos within 30 seconds with high similarity
-      return Math.min(similarityScore + 0.15, 1.0);
-    } else if (timeDiff <= 60000 && similarityScore >= 0.9) {
-      // Photos within 1 minute with very high similarity
-      return Math.min(similarityScore, 1.0);
+      if (run.length >= MIN_FRAMES) runs.push(run);
     }
-
-    return Math.min(similarityScore, 1.0);
+    return runs.sort((a, b) => a[0].shutterTime - b[0].shutterTime);
   }
 
   /**
-   * Get common prefix between two strings
+   * One photo per media asset, the least processed version: a frame
+   * promoted to Silver is still the same frame as its Bronze original
    */
-  private getCommonPrefix(str1: string, str2: string): string {
-    let i = 0;
-    while (i < str1.length && i < str2.length && str1[i] === str2[i]) {
-      i++;
+  private async loadRepresentativePhotos(): Promise<PhotoWithAsset[]> {
+    const tierPriority: Record<string, number> = { bronze: 0, silver: 1, gold: 2 };
+    const statePriority: Record<string, number> = { unprocessed: 0, processed: 1, promoted: 2, rejected: 3 };
+    const rank = (version: FileVersion) =>
+      tierPriority[version.tier] * 10 + (statePriority[version.processingState || 'unprocessed'] || 0);
+    const byAsset = new Map<string, PhotoWithAsset>();
+    for (const photo of await storage.getAllFileVersionsWithAssets()) {
+      if (!photo.mediaAsset || !photo.mimeType.startsWith('image/')) continue;
+      const current = byAsset.get(photo.mediaAssetId);
+      if (!current || rank(photo) < rank(current)) byAsset.set(photo.mediaAssetId, photo);
     }
-    return str1.substring(0, i);
+    return Array.from(byAsset.values());
   }
 
-  /**
-   * Create a burst group from similar photos
-   */
-  private async createBurstGroup(photos: any[]): Promise<BurstGroup> {
-    const groupId = crypto.randomUUID();
-    
-    // Calculate time span
-    const times = photos.map(p => new Date(p.createdAt).getTime());
-    const timeSpan = Math.max(...times) - Math.min(...times);
-    
-    // Calculate actual average similarity between all pairs
-    let totalSimilarity = 0;
-    let pairCount = 0;
-    
-    for (let i = 0; i < photos.length; i++) {
-      for (let j = i + 1; j < photos.length; j++) {
-        const similarity = await this.calculatePhotoSimilarity(photos[i], photos[j]);
-        totalSimilarity += similarity;
-        pairCount++;
-      }
+  private async loadPhotos(photoIds: string[]): Promise<Map<string, PhotoWithAsset>> {
+    const photos = new Map<string, PhotoWithAsset>();
+    for (const photoId of photoIds) {
+      const photo = await storage.getFileVersion(photoId);
+      const asset = photo && !photo.deletedAt ? await storage.getMediaAsset(photo.mediaAssetId) : undefined;
+      if (photo && asset) photos.set(photoId, { ...photo, mediaAsset: asset });
     }
-    
-    const averageSimilarity = pairCount > 0 ? totalSimilarity / pairCount : 0;
-    
-    // Find the best photo (largest file size usually indicates best quality)
-    const suggestedBest = photos.reduce((best, current) => {
-      if (!best.fileSize && current.fileSize) return current;
-      if (best.fileSize && !current.fileSize) return best;
-      if (current.fileSize > best.fileSize) return current;
-      // If same size, prefer most recent
-      return new Date(current.createdAt) > new Date(best.createdAt) ? current : best;
-    });
-
-    // Determine group reason
-    let groupReason = 'Similar photos taken within 10 seconds';
-    if (timeSpan < 5000) {
-      groupReason = 'Rapid burst sequence (under 5 seconds)';
-    } else if (timeSpan < 10000) {
-      groupReason = 'Quick burst sequence (under 10 seconds)';
+    return photos;
+  }
+
+  private async buildAnalysis(photos: PhotoWithAsset[], options: { includeResolved: boolean }): Promise<BurstAnalysis> {
+    const bursts = (await storage.getBursts()).filter(burst => options.includeResolved || !burst.resolvedAt);
+    const photosById = new Map(photos.map(photo => [photo.id, photo]));
+    const grouped = new Set<string>();
+    const groups: BurstGroup[] = [];
+
+    for (const burst of bursts) {
+      burst.frames.forEach(frame => grouped.add(frame.photoId));
+      const group = this.toGroup(burst, photosById);
+      if (group.photos.length >= MIN_FRAMES) groups.push(group);
     }
+    groups.sort((a, b) => a.photos[0].capturedAt.localeCompare(b.photos[0].capturedAt));
 
     return {
-      id: groupId,
-      photos: photos.map(p => ({
-        id: p.id,
-        filePath: p.filePath,
-        metadata: p.metadata,
-        mediaAsset: p.mediaAsset,
-        createdAt: p.createdAt,
-        fileSize: p.fileSize,
-        fileHash: p.fileHash
+      groups,
+      totalPhotos: photos.length,
+      ungroupedPhotos: photos.filter(photo => !grouped.has(photo.id)).map(photo => ({
+        id: photo.id,
+        filePath: photo.filePath,
+        metadata: photo.metadata,
+        mediaAsset: photo.mediaAsset,
+        createdAt: photo.createdAt.toISOString(),
+        fileSize: photo.fileSize,
+        fileHash: photo.fileHash,
       })),
-      suggestedBest: suggestedBest.id,
-      averageSimilarity,
-      timeSpan,
-      groupReason
     };
   }
 
-  private generateEmptyAnalysis(): BurstAnalysis {
+  private toGroup(burst: BurstWithFrames, photos: Map<string, PhotoWithAsset>): BurstGroup {
+    const members = burst.frames
+      .filter(frame => photos.has(frame.photoId))
+      .map(frame => {
+        const photo = photos.get(frame.photoId)!;
+        return {
+          id: photo.id,
+          filePath: photo.filePath,
+          metadata: photo.metadata,
+          mediaAsset: photo.mediaAsset,
+          createdAt: photo.createdAt.toISOString(),
+          fileSize: photo.fileSize,
+          fileHash: photo.fileHash,
+          tier: photo.tier,
+          capturedAt: frame.capturedAt.toISOString(),
+          sharpness: frame.sharpness,
+        };
+      });
+
+    const timeSpan = burst.endedAt.getTime() - burst.startedAt.getTime();
+    const measured = members.filter(member => member.sharpness !== null);
+    // Until frames are measured, the middle frame is as good a guess as any: the
+    // first often has camera shake from pressing the shutter
+    const suggestedBest = burst.bestPhotoId && members.some(member => member.id === burst.bestPhotoId)
+      ? burst.bestPhotoId
+      : measured.length > 0
+        ? measured.reduce((best, member) => member.sharpness! > best.sharpness! ? member : best).id
+        : members[Math.floor(members.length / 2)]?.id ?? '';
+
+    const perSecond = timeSpan > 0 ? (members.length - 1) / (timeSpan / 1000) : null;
+    const groupReason = perSecond
+      ? `${members.length} frames in ${(timeSpan / 1000).toFixed(1)}s (${perSecond.toFixed(1)} fps)`
+      : `${members.length} frames in the same second`;
+
     return {
-      groups: [],
-      totalPhotos: 0,
-      ungroupedPhotos: []
+      id: burst.id,
+      photos: members,
+      suggestedBest,
+      camera: burst.camera,
+      timeSpan,
+      groupReason,
+      bestPhotoId: burst.bestPhotoId,
+      stackId: burst.stackId,
+      resolvedAt: burst.resolvedAt?.toISOString() ?? null,
     };
   }
+
+  private framesKey(photoIds: string[]): string {
+    return [...photoIds].sort().join(',');
+  }
 }
 
-export const burstPhotoService = new BurstPhotoDetectionService();
\ No newline at end of file
+export const burstPhotoService = new BurstPhotoDetectionService();
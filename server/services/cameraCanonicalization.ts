@@ -0,0 +1,222 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import type { CancellationToken } from "./operations";
+import type { ExifMetadata } from "@shared/schema";
+
+export const CAMERA_ALIASES_SETTING = 'camera_aliases';
+
+// User overrides: exact camera or lens string as read (case-insensitive) -> name to show
+export const cameraAliasesSchema = z.object({
+  cameras: z.record(z.string().min(1)).optional(),
+  lenses: z.record(z.string().min(1)).optional(),
+});
+
+export type CameraAliases = { cameras: Record<string, string>; lenses: Record<string, string> };
+
+// Manufacturer names as cameras write them in the EXIF Make tag
+const MAKE_ALIASES: Array<[RegExp, string]> = [
+  [/^canon(\s+inc\.?)?$/i, 'Canon'],
+  [/^nikon(\s+corporation)?$/i, 'Nikon'],
+  [/^sony(\s+corporation)?$/i, 'Sony'],
+  [/^fuji(film)?(\s+corporation)?$/i, 'Fujifilm'],
+  [/^olympus(\s+(imaging\s+corp\.?|corporation|optical\s+co\.?,?\s*ltd\.?))?$/i, 'Olympus'],
+  [/^om\s+digital\s+solutions$/i, 'OM System'],
+  [/^panasonic$/i, 'Panasonic'],
+  [/^(pentax|ricoh)(\s+imaging(\s+company)?,?\s*ltd\.?|\s+corporation)?$/i, 'Ricoh'],
+  [/^leica(\s+camera(\s+ag)?)?$/i, 'Leica'],
+  [/^samsung(\s+techwin)?$/i, 'Samsung'],
+  [/^apple$/i, 'Apple'],
+  [/^google$/i, 'Google'],
+  [/^huawei$/i, 'Huawei'],
+  [/^xiaomi$/i, 'Xiaomi'],
+  [/^oneplus$/i, 'OnePlus'],
+  [/^hasselblad$/i, 'Hasselblad'],
+  [/^sigma$/i, 'Sigma'],
+  [/^gopro$/i, 'GoPro'],
+  [/^dji$/i, 'DJI'],
+  [/^eastman\s+kodak(\s+company)?$|^kodak$/i, 'Kodak'],
+  [/^minolta(\s+co\.?,?\s*ltd\.?)?$|^konica\s+minolta(\s+camera,?\s*inc\.?)?$/i, 'Konica Minolta'],
+];
+
+const ROMAN_NUMERALS = ['', 'I', 'II', 'III', 'IV', 'V', 'VI', 'VII', 'VIII', 'IX', 'X'];
+
+/**
+ * Canonical camera name from EXIF "Make Model" strings: the maker's common
+ * name, no repeated maker in the model, "Mark 4" spelled "Mark IV".
+ */
+export function canonicalizeCameraName(raw: string): string {
+  let name = raw.replace(/\s+/g, ' ').trim();
+  if (!name) return name;
+
+  // Try the longest leading run of words that names a known maker
+  const words = name.split(' ');
+  for (let count = Math.min(words.length - 1, 5); count >= 1; count--) {
+    const alias = MAKE_ALIASES.find(([pattern]) => pattern.test(words.slice(0, count).join(' ')));
+    if (!alias) continue;
+    let model = words.slice(count).join(' ');
+    // Many makers repeat themselves in the model ("NIKON CORPORATION NIKON D850")
+    const firstWord = model.split(' ')[0];
+    if (firstWord && MAKE_ALIASES.some(([pattern, make]) => make === alias[1] && pattern.test(firstWord))) {
+      model = model.slice(firstWord.length).trim();
+    }
+    name = model ? `${alias[1]} ${model}` : alias[1];
+    break;
+  }
+
+  return name
+    .replace(/\bmark\s*([1-9]|10)\b/gi, (_, digits: string) => `Mark ${ROMAN_NUMERALS[Number(digits)]}`)
+    .replace(/\bmark\s+([ivx]+)\b/gi, (_, numeral: string) => `Mark ${numeral.toUpperCase()}`)
+    .replace(/\bmk\.?\s*([ivx]+|[1-9]|10)\b/gi, (_, value: string) =>
+      `Mark ${/^\d+$/.test(value) ? ROMAN_NUMERALS[Number(value)] : value.toUpperCase()}`);
+}
+
+/**
+ * Canonical lens name: single spaces, "f/2.8" apertures and "mm" joined to
+ * the focal length
+ */
+export function canonicalizeLensName(raw: string): string {
+  return raw
+    .replace(/\s+/g, ' ')
+    .trim()
+    .replace(/(\d)\s+mm\b/gi, '$1mm')
+    .replace(/\bF\s*\/?\s*(\d+(\.\d+)?)/g, 'f/$1')
+    .replace(/\bf\s+\/\s*(\d)/g, 'f/$1');
+}
+
+/**
+ * Keeps camera and lens facets from fragmenting on spelling differences
+ * between firmware versions and EXIF readers. Built-in rules tidy maker
+ * names and model spellings; user aliases in settings win over them. The
+ * string as read is kept beside the canonical one, so changed aliases can be
+ * re-applied to the whole library.
+ */
+class CameraCanonicalizationService {
+  async getAliases(): Promise<CameraAliases> {
+    const setting = await storage.getSettingByKey(CAMERA_ALIASES_SETTING);
+    const empty: CameraAliases = { cameras: {}, lenses: {} };
+    if (!setting?.value) return empty;
+    try {
+      const parsed = cameraAliasesSchema.safeParse(JSON.parse(setting.value));
+      return parsed.success ? { cameras: parsed.data.cameras ?? {}, lenses: parsed.data.lenses ?? {} } : empty;
+    } catch {
+      return empty;
+    }
+  }
+
+  /**
+   * Replace the user aliases. Run applyToLibrary afterwards to update existing photos.
+   */
+  async updateAliases(aliases: z.infer<typeof cameraAliasesSchema>): Promise<CameraAliases> {
+    const current = await this.getAliases();
+    const next: CameraAliases = {
+      cameras: aliases.cameras ?? current.cameras,
+      lenses: aliases.lenses ?? current.lenses,
+    };
+    const value = JSON.stringify(next);
+    if (await storage.getSettingByKey(CAMERA_ALIASES_SETTING)) {
+      await storage.updateSetting(CAMERA_ALIASES_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: CAMERA_ALIASES_SETTING,
+        value,
+        category: 'metadata',
+        description: 'Camera and lens names to show in place of the strings read from EXIF',
+      });
+    }
+    return next;
+  }
+
+  canonicalizeCamera(raw: string, aliases: CameraAliases): string {
+    return this.lookup(aliases.cameras, raw) ?? this.lookup(aliases.cameras, canonicalizeCameraName(raw)) ?? canonicalizeCameraName(raw);
+  }
+
+  canonicalizeLens(raw: string, aliases: CameraAliases): string {
+    return this.lookup(aliases.lenses, raw) ?? this.lookup(aliases.lenses, canonicalizeLensName(raw)) ?? canonicalizeLensName(raw);
+  }
+
+  /**
+   * EXIF with canonical camera and lens names; the originals are kept when they differ
+   */
+  async apply(exif: ExifMetadata, aliases?: CameraAliases): Promise<ExifMetadata> {
+    const rules = aliases ?? await this.getAliases();
+    const result: ExifMetadata = { ...exif };
+
+    const rawCamera = exif.cameraOriginal ?? exif.camera;
+    if (rawCamera) {
+      result.camera = this.canonicalizeCamera(rawCamera, rules);
+      if (result.camera !== rawCamera) result.cameraOriginal = rawCamera;
+      else delete result.cameraOriginal;
+    }
+    const rawLens = exif.lensOriginal ?? exif.lens;
+    if (rawLens) {
+      result.lens = this.canonicalizeLens(rawLens, rules);
+      if (result.lens !== rawLens) result.lensOriginal = rawLens;
+      else delete result.lensOriginal;
+    }
+    return result;
+  }
+
+  /**
+   * Re-canonicalize every photo, e.g. after the aliases changed
+   */
+  async applyToLibrary(token?: CancellationToken, onProgress?: (completed: number, total: number) => void): Promise<{ updated: number; cancelled: boolean }> {
+    const aliases = await this.getAliases();
+    const photos = await storage.getAllFileVersions();
+    let updated = 0;
+
+    for (let index = 0; index < photos.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, photos.length);
+      const photo = photos[index];
+      const metadata = (photo.metadata as { exif?: ExifMetadata } | null) ?? {};
+      if (!metadata.exif?.camera && !metadata.exif?.lens) continue;
+
+      const exif = await this.apply(metadata.exif, aliases);
+      if (exif.camera === metadata.exif.camera && exif.lens === metadata.exif.lens &&
+          exif.cameraOriginal === metadata.exif.cameraOriginal && exif.lensOriginal === metadata.exif.lensOriginal) {
+        continue;
+      }
+      await storage.updateFileVersion(photo.id, { metadata: { ...metadata, exif } });
+      updated++;
+    }
+
+    return { updated, cancelled: token?.isCancelled ?? false };
+  }
+
+  /**
+   * Canonical cameras and lenses in the library with the strings that map to them
+   */
+  async listNames(): Promise<{
+    cameras: Array<{ name: string; count: number; variants: string[] }>;
+    lenses: Array<{ name: string; count: number; variants: string[] }>;
+  }> {
+    const cameras = new Map<string, { count: number; variants: Set<string> }>();
+    const lenses = new Map<string, { count: number; variants: Set<string> }>();
+    const add = (map: typeof cameras, name: string | undefined, original: string | undefined) => {
+      if (!name) return;
+      const entry = map.get(name) ?? { count: 0, variants: new Set<string>() };
+      entry.count++;
+      entry.variants.add(original ?? name);
+      map.set(name, entry);
+    };
+
+    for (const photo of await storage.getAllFileVersions()) {
+      const exif = (photo.metadata as { exif?: ExifMetadata } | null)?.exif;
+      add(cameras, exif?.camera, exif?.cameraOriginal);
+      add(lenses, exif?.lens, exif?.lensOriginal);
+    }
+
+    const toList = (map: typeof cameras) => Array.from(map.entries())
+      .map(([name, entry]) => ({ name, count: entry.count, variants: Array.from(entry.variants).sort() }))
+      .sort((a, b) => b.count - a.count);
+    return { cameras: toList(cameras), lenses: toList(lenses) };
+  }
+
+  private lookup(map: Record<string, string>, value: string): string | undefined {
+    const key = value.toLowerCase();
+    const match = Object.keys(map).find(candidate => candidate.toLowerCase() === key);
+    return match ? map[match] : undefined;
+  }
+}
+
+export const cameraCanonicalizationService = new CameraCanonicalizationService();
@@ -0,0 +1,313 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { thumbnailService } from "./thumbnailService";
+import { formatRegistry } from "./formatRegistry";
+import { provenanceService } from "./provenance";
+import type { CancellationToken } from "./operations";
+import { getLibraryRoot, libraryPath } from "../utils/libraryPaths";
+import { guessVolumeLabel } from "../utils/volumes";
+import { hashFile, copyFileAtomic } from "../utils/files";
+import type { ColdStorageCopy, FileVersion } from "@shared/schema";
+
+// Thumbnail sizes made before an original is evicted, so the gallery keeps working offline
+const EVICTION_THUMBNAIL_SIZES = [150, 300, 600];
+
+export const archiveToColdStorageSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  destination: z.string().min(1), // folder on the external volume
+  volumeLabel: z.string().min(1).optional(), // defaults to the name of the volume holding destination
+  evict: z.boolean().optional(), // remove the local originals once the copies are verified
+});
+
+export const evictSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+});
+
+export const reconnectVolumeSchema = z.object({
+  volumeLabel: z.string().min(1),
+  archiveRoot: z.string().min(1).optional(), // where the archive folder is now, if the volume mounted elsewhere
+  restore: z.boolean().optional(), // copy verified originals back into the library
+});
+
+// The request cannot be carried out; nothing was changed
+export class ColdStorageError extends Error {}
+
+export type ColdStorageStatus = 'archived' | 'evicted' | 'verified' | 'restored' | 'missing' | 'mismatched' | 'skipped' | 'failed';
+
+export interface ColdStorageItemResult {
+  photoId: string;
+  status: ColdStorageStatus;
+  message?: string;
+}
+
+export interface ColdStorageRunResult {
+  results: ColdStorageItemResult[];
+  cancelled: boolean;
+}
+
+export interface OfflinePhoto {
+  photo: FileVersion;
+  volumeLabel: string;
+  archivePath: string;
+  evictedAt: Date;
+  verifiedAt: Date | null;
+  volumeAvailable: boolean; // the copy can be read right now
+}
+
+/**
+ * Keeps originals on external or cold storage volumes. Archiving copies an
+ * original to a folder on the volume and verifies it by hash; evicting then
+ * removes the local original, leaving thumbnails, metadata and a stub
+ * record naming the volume. When the volume is back, reconnecting verifies
+ * every copy and can restore the originals.
+ */
+class ColdStorageService {
+  async archive(
+    photoIds: string[],
+    destination: string,
+    options: { volumeLabel?: string; evict?: boolean } = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ColdStorageRunResult> {
+    const archiveRoot = path.resolve(destination);
+    const stats = await fs.stat(archiveRoot).catch(() => null);
+    if (!stats?.isDirectory()) {
+      throw new ColdStorageError(`${archiveRoot} is not a folder`);
+    }
+    if (this.isWithin(archiveRoot, getLibraryRoot())) {
+      throw new ColdStorageError('Cold storage must be outside the library folder');
+    }
+    const volumeLabel = options.volumeLabel ?? guessVolumeLabel(archiveRoot);
+
+    const results: ColdStorageItemResult[] = [];
+    for (let index = 0; index < photoIds.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, photoIds.length);
+      const photoId = photoIds[index];
+
+      try {
+        const photo = await storage.getFileVersion(photoId);
+        if (!photo) {
+          results.push({ photoId, status: 'skipped', message: 'Photo not found' });
+          continue;
+        }
+
+        let copy = await storage.getColdStorageCopy(photo.id);
+        if (!copy) {
+          copy = await this.writeCopy(photo, archiveRoot, volumeLabel);
+          await provenanceService.record(photo, 'ARCHIVED', `Original copied to volume ${volumeLabel}`, {
+            volumeLabel,
+            archivePath: this.archivePath(copy),
+          });
+        }
+
+        if (options.evict && !copy.evictedAt) {
+          results.push(await this.evictOne(photo, copy));
+        } else {
+          results.push({ photoId, status: copy.evictedAt ? 'evicted' : 'archived' });
+        }
+      } catch (error) {
+        console.error(`Failed to archive photo ${photoId}:`, error);
+        results.push({ photoId, status: 'failed', message: error instanceof Error ? error.message : String(error) });
+      }
+    }
+
+    return { results, cancelled: token?.isCancelled ?? false };
+  }
+
+  /**
+   * Remove the local originals of archived photos. Each copy is verified
+   * again first; photos without a readable, matching copy are left alone.
+   */
+  async evict(photoIds: string[], token?: CancellationToken): Promise<ColdStorageRunResult> {
+    const results: ColdStorageItemResult[] = [];
+    for (const photoId of photoIds) {
+      if (token?.isCancelled) break;
+      const photo = await storage.getFileVersion(photoId);
+      const copy = photo ? await storage.getColdStorageCopy(photo.id) : undefined;
+      if (!photo || !copy) {
+        results.push({ photoId, status: 'skipped', message: photo ? 'Photo has not been archived' : 'Photo not found' });
+      } else if (copy.evictedAt) {
+        results.push({ photoId, status: 'evicted' });
+      } else {
+        try {
+          results.push(await this.evictOne(photo, copy));
+        } catch (error) {
+          console.error(`Failed to evict photo ${photoId}:`, error);
+          results.push({ photoId, status: 'failed', message: error instanceof Error ? error.message : String(error) });
+        }
+      }
+    }
+    return { results, cancelled: token?.isCancelled ?? false };
+  }
+
+  /**
+   * Photos whose original only exists on an external volume
+   */
+  async listOfflinePhotos(): Promise<OfflinePhoto[]> {
+    const offline: OfflinePhoto[] = [];
+    for (const copy of await storage.getColdStorageCopies({ evicted: true })) {
+      const photo = await storage.getFileVersion(copy.photoId);
+      if (!photo) continue;
+      const archivePath = this.archivePath(copy);
+      offline.push({
+        photo,
+        volumeLabel: copy.volumeLabel,
+        archivePath,
+        evictedAt: copy.evictedAt!,
+        verifiedAt: copy.verifiedAt,
+        volumeAvailable: await this.exists(archivePath),
+      });
+    }
+    return offline;
+  }
+
+  /**
+   * A volume is back: check every copy on it against its recorded hash,
+   * remember where the archive folder is now, and restore the originals of
+   * evicted photos when asked
+   */
+  async reconnect(
+    volumeLabel: string,
+    options: { archiveRoot?: string; restore?: boolean } = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ColdStorageRunResult> {
+    const copies = await storage.getColdStorageCopies({ volumeLabel });
+    if (copies.length === 0) {
+      throw new ColdStorageError(`Nothing is archived on volume ${volumeLabel}`);
+    }
+    const newRoot = options.archiveRoot ? path.resolve(options.archiveRoot) : null;
+
+    const results: ColdStorageItemResult[] = [];
+    for (let index = 0; index < copies.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, copies.length);
+      const copy = copies[index];
+
+      try {
+        const archivePath = path.join(newRoot ?? copy.archiveRoot, copy.relativePath);
+        if (!(await this.exists(archivePath))) {
+          results.push({ photoId: copy.photoId, status: 'missing', message: `${archivePath} not found` });
+          continue;
+        }
+        if ((await hashFile(archivePath)) !== copy.fileHash) {
+          results.push({ photoId: copy.photoId, status: 'mismatched', message: `${archivePath} does not match the archived hash` });
+          continue;
+        }
+
+        const verified = await storage.updateColdStorageCopy(copy.id, {
+          archiveRoot: newRoot ?? copy.archiveRoot,
+          verifiedAt: new Date(),
+        });
+        if (options.restore && copy.evictedAt && verified) {
+          results.push(await this.restoreOne(verified));
+        } else {
+          results.push({ photoId: copy.photoId, status: 'verified' });
+        }
+      } catch (error) {
+        console.error(`Failed to verify archived copy of ${copy.photoId}:`, error);
+        results.push({ photoId: copy.photoId, status: 'failed', message: error instanceof Error ? error.message : String(error) });
+      }
+    }
+
+    return { results, cancelled: token?.isCancelled ?? false };
+  }
+
+  private async writeCopy(photo: FileVersion, archiveRoot: string, volumeLabel: string): Promise<ColdStorageCopy> {
+    const source = libraryPath(photo.filePath);
+    const relativePath = photo.filePath;
+    const target = path.join(archiveRoot, relativePath);
+    if (await this.exists(target)) {
+      throw new ColdStorageError(`${target} already exists`);
+    }
+
+    await fs.mkdir(path.dirname(target), { recursive: true });
+    await copyFileAtomic(source, target);
+
+    const [sourceHash, copyHash] = await Promise.all([hashFile(source), hashFile(target)]);
+    if (sourceHash !== copyHash) {
+      await fs.rm(target, { force: true });
+      throw new ColdStorageError('Copy did not match the original after writing');
+    }
+
+    return storage.createColdStorageCopy({
+      photoId: photo.id,
+      volumeLabel,
+      archiveRoot,
+      relativePath,
+      fileHash: copyHash,
+      fileSize: (await fs.stat(target)).size,
+      evictedAt: null,
+      verifiedAt: new Date(),
+    });
+  }
+
+  private async evictOne(photo: FileVersion, copy: ColdStorageCopy): Promise<ColdStorageItemResult> {
+    const archivePath = this.archivePath(copy);
+    if (!(await this.exists(archivePath))) {
+      return { photoId: photo.id, status: 'missing', message: `Volume ${copy.volumeLabel} is not connected` };
+    }
+    if ((await hashFile(archivePath)) !== copy.fileHash) {
+      return { photoId: photo.id, status: 'mismatched', message: `${archivePath} does not match the archived hash` };
+    }
+
+    const localPath = libraryPath(photo.filePath);
+    if (formatRegistry.supports(localPath, 'thumbnail')) {
+      for (const size of EVICTION_THUMBNAIL_SIZES) {
+        await thumbnailService.generateThumbnail(localPath, { size, quality: 80, format: 'jpeg' }, photo.id);
+      }
+    }
+
+    await fs.rm(localPath, { force: true });
+    await storage.updateColdStorageCopy(copy.id, { evictedAt: new Date(), verifiedAt: new Date() });
+    await provenanceService.record(photo, 'EVICTED', `Local original removed; kept on volume ${copy.volumeLabel}`, {
+      volumeLabel: copy.volumeLabel,
+      archivePath,
+    });
+    return { photoId: photo.id, status: 'evicted' };
+  }
+
+  private async restoreOne(copy: ColdStorageCopy): Promise<ColdStorageItemResult> {
+    const photo = await storage.getFileVersion(copy.photoId);
+    if (!photo) {
+      return { photoId: copy.photoId, status: 'skipped', message: 'Photo not found' };
+    }
+
+    const localPath = libraryPath(photo.filePath);
+    await fs.mkdir(path.dirname(localPath), { recursive: true });
+    await copyFileAtomic(this.archivePath(copy), localPath);
+    if ((await hashFile(localPath)) !== copy.fileHash) {
+      await fs.rm(localPath, { force: true });
+      return { photoId: photo.id, status: 'failed', message: 'Restored file did not match the archived hash' };
+    }
+
+    await storage.updateColdStorageCopy(copy.id, { evictedAt: null });
+    await provenanceService.record(photo, 'RESTORED', `Original restored from volume ${copy.volumeLabel}`, {
+      volumeLabel: copy.volumeLabel,
+    });
+    return { photoId: photo.id, status: 'restored' };
+  }
+
+  private archivePath(copy: ColdStorageCopy): string {
+    return path.join(copy.archiveRoot, copy.relativePath);
+  }
+
+  private async exists(filePath: string): Promise<boolean> {
+    try {
+      await fs.access(filePath);
+      return true;
+    } catch {
+      return false;
+    }
+  }
+
+  private isWithin(child: string, parent: string): boolean {
+    const relative = path.relative(parent, child);
+    return relative === '' || (!relative.startsWith('..') && !path.isAbsolute(relative));
+  }
+}
+
+export const coldStorageService = new ColdStorageService();
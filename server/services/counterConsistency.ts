@@ -0,0 +1,123 @@
+import { sql, eq } from "drizzle-orm";
+import { db } from "../db";
+import { faceClusters, locations, people } from "@shared/schema";
+
+export type CounterName = 'person.face_count' | 'face_cluster.face_count' | 'location.photo_count';
+
+export interface CounterDrift {
+  id: string;
+  label: string | null;
+  stored: number;
+  actual: number;
+}
+
+export interface CounterCheck {
+  counter: CounterName;
+  checked: number;
+  drifted: CounterDrift[];
+  repaired: number;
+}
+
+export interface ConsistencyReport {
+  checkedAt: Date;
+  repair: boolean;
+  counters: CounterCheck[];
+}
+
+// Bulk operations often come in bursts; check once they settle
+const CHECK_AFTER_BULK_DELAY_MS = 5000;
+
+// Great-circle distance in metres between a photo's GPS position and a location's centre
+const DISTANCE_METRES = sql`6371000 * 2 * asin(sqrt(
+  power(sin(radians(fv.gps_latitude - CAST(l.latitude AS double precision)) / 2), 2) +
+  cos(radians(CAST(l.latitude AS double precision))) * cos(radians(fv.gps_latitude)) *
+  power(sin(radians(fv.gps_longitude - CAST(l.longitude AS double precision)) / 2), 2)
+))`;
+
+/**
+ * Checks the counters cached on rows (a person's face count, a face
+ * cluster's size, the photos at a location) against the rows they count,
+ * and optionally writes the true values back. Albums, selections and tags
+ * count their photos on read, so they cannot drift. Runs with the daily
+ * maintenance and shortly after bulk face and people operations.
+ */
+class CounterConsistencyService {
+  private pendingCheck: NodeJS.Timeout | null = null;
+
+  async check(repair = false): Promise<ConsistencyReport> {
+    const counters: CounterCheck[] = [];
+
+    const personCounts = await this.rows(sql`
+      SELECT p.id, p.name AS label, COALESCE(p.face_count, 0) AS stored, COUNT(f.id)::int AS actual
+      FROM people p LEFT JOIN faces f ON f.person_id = p.id
+      GROUP BY p.id`);
+    counters.push(await this.settle('person.face_count', personCounts, repair, (id, actual) =>
+      db.update(people).set({ faceCount: actual }).where(eq(people.id, id))));
+
+    const clusterCounts = await this.rows(sql`
+      SELECT c.id, NULL AS label, c.face_count AS stored, COUNT(f.id)::int AS actual
+      FROM face_clusters c LEFT JOIN faces f ON f.cluster_id = c.id
+      GROUP BY c.id`);
+    counters.push(await this.settle('face_cluster.face_count', clusterCounts, repair, (id, actual) =>
+      db.update(faceClusters).set({ faceCount: actual }).where(eq(faceClusters.id, id))));
+
+    // A photo is at a location when its GPS position is within the location's radius
+    const locationCounts = await this.rows(sql`
+      SELECT l.id, l.name AS label, COALESCE(l.photo_count, 0) AS stored,
+        (SELECT COUNT(DISTINCT fv.media_asset_id)::int FROM file_versions fv
+         WHERE fv.deleted_at IS NULL AND fv.gps_latitude IS NOT NULL AND fv.gps_longitude IS NOT NULL
+           AND ${DISTANCE_METRES} <= COALESCE(l.radius, 100)) AS actual
+      FROM locations l`);
+    counters.push(await this.settle('location.photo_count', locationCounts, repair, (id, actual) =>
+      db.update(locations).set({ photoCount: actual }).where(eq(locations.id, id))));
+
+    const report: ConsistencyReport = { checkedAt: new Date(), repair, counters };
+    const drifted = counters.reduce((total, counter) => total + counter.drifted.length, 0);
+    if (drifted > 0) {
+      console.log(`Counter check found ${drifted} drifted value(s)${repair ? ', repaired' : ''}`);
+    }
+    return report;
+  }
+
+  /**
+   * Repair counters a few seconds after a bulk operation; repeated calls
+   * within that window share one check
+   */
+  scheduleCheck(): void {
+    if (this.pendingCheck) clearTimeout(this.pendingCheck);
+    this.pendingCheck = setTimeout(() => {
+      this.pendingCheck = null;
+      this.check(true).catch(error => console.error("Counter consistency check failed:", error));
+    }, CHECK_AFTER_BULK_DELAY_MS);
+    this.pendingCheck.unref();
+  }
+
+  private async rows(query: ReturnType<typeof sql>): Promise<CounterDrift[]> {
+    const result = await db.execute(query);
+    return (result.rows as Array<Record<string, unknown>>).map(row => ({
+      id: String(row.id),
+      label: row.label == null ? null : String(row.label),
+      stored: Number(row.stored),
+      actual: Number(row.actual),
+    }));
+  }
+
+  private async settle(
+    counter: CounterName,
+    rows: CounterDrift[],
+    repair: boolean,
+    write: (id: string, actual: number) => Promise<unknown>
+  ): Promise<CounterCheck> {
+    const drifted = rows.filter(row => row.stored !== row.actual);
+    let repaired = 0;
+    if (repair) {
+      for (const row of drifted) {
+        await write(row.id, row.actual);
+        repaired++;
+      }
+    }
+    return { counter, checked: rows.length, drifted, repaired };
+  }
+}
+
+export const counterConsistencyService = new CounterConsistencyService();
@@ -0,0 +1,285 @@
+import fs from "fs/promises";
+import path from "path";
+import Database from "better-sqlite3";
+import { z } from "zod";
+import { storage } from "../storage";
+import { localImportService } from "./localImport";
+import { propagationService, type PropagationScope } from "./propagation";
+import { counterConsistencyService } from "./counterConsistency";
+import type { CancellationToken } from "./operations";
+import { boxOverlap, SAME_FACE_OVERLAP, type FaceBox } from "../utils/faceBoxes";
+import type { Person } from "@shared/schema";
+
+export const digikamImportSchema = z.object({
+  databasePath: z.string().min(1), // digikam4.db
+  // Where each album root is mounted on this machine, by digiKam album root id; needed for roots
+  // digiKam identifies by volume UUID rather than by path
+  rootPaths: z.record(z.string(), z.string().min(1)).optional(),
+});
+
+export type DigikamImportOptions = Omit<z.infer<typeof digikamImportSchema>, 'databasePath'>;
+
+export interface DigikamImportResult {
+  images: number;
+  imported: number;
+  duplicates: number; // already in the library; their digiKam organization is still applied
+  missing: string[]; // files the database lists that are not on disk
+  failed: Array<{ path: string; reason: string }>;
+  tagged: number;
+  rated: number;
+  faces: number;
+  peopleCreated: number;
+  albums: number;
+}
+
+export class DigikamImportError extends Error {}
+
+// digiKam keeps its own bookkeeping tags (colour labels, pick labels, face states) under this root
+const INTERNAL_TAG_ROOT = '_Digikam_Internal_Tags_';
+// Images.status for files that are in the collection, as opposed to hidden, trashed or obsolete
+const VISIBLE_STATUS = 1;
+// Region properties on ImageTagProperties: a confirmed face, and one digiKam detected but nobody named
+const CONFIRMED_REGION = 'tagRegion';
+const DETECTED_REGION = 'autodetectedFace';
+// Tags from digiKam belong to the file they were on, not to its stack or other versions
+const NO_PROPAGATION: PropagationScope = { stack: false, versions: false };
+
+interface DigikamTag { id: number; pid: number; name: string }
+interface DigikamAlbumRoot { id: number; identifier: string | null; specificPath: string | null; label: string | null }
+interface DigikamAlbum { id: number; albumRoot: number; relativePath: string }
+interface DigikamImage { id: number; album: number; name: string; rating: number | null }
+interface DigikamRegion { imageid: number; tagid: number; property: string; value: string }
+
+/**
+ * Import a digiKam collection from its SQLite database: the files of every
+ * album, with their star ratings, the tag tree (as "Parent/Child" tags),
+ * people with their face regions, and each album as an album of the same
+ * name. Files are copied in through the normal import, so a collection that
+ * is partly in the library already only gets its organization applied.
+ * Running it again fills in what is missing without duplicating anything.
+ */
+class DigikamImportService {
+  async importDatabase(
+    databasePath: string,
+    options: DigikamImportOptions = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<DigikamImportResult> {
+    let database: Database.Database;
+    try {
+      database = new Database(databasePath, { readonly: true, fileMustExist: true });
+    } catch {
+      throw new DigikamImportError(`Cannot open digiKam database at ${databasePath}`);
+    }
+
+    try {
+      const roots = database.prepare('SELECT id, identifier, specificPath, label FROM AlbumRoots').all() as DigikamAlbumRoot[];
+      const albums = database.prepare('SELECT id, albumRoot, relativePath FROM Albums').all() as DigikamAlbum[];
+      const images = database.prepare(`
+        SELECT i.id, i.album, i.name, ii.rating
+        FROM Images i LEFT JOIN ImageInformation ii ON ii.imageid = i.id
+        WHERE i.status = ? AND i.album IS NOT NULL`).all(VISIBLE_STATUS) as DigikamImage[];
+      const tags = database.prepare('SELECT id, pid, name FROM Tags').all() as DigikamTag[];
+      const personTagIds = new Set(
+        (database.prepare(`SELECT tagid FROM TagProperties WHERE property = 'person'`).all() as Array<{ tagid: number }>)
+          .map(row => row.tagid)
+      );
+      const imageTags = database.prepare('SELECT imageid, tagid FROM ImageTags').all() as Array<{ imageid: number; tagid: number }>;
+      const regions = database.prepare(`SELECT imageid, tagid, property, value FROM ImageTagProperties WHERE property IN (?, ?)`)
+        .all(CONFIRMED_REGION, DETECTED_REGION) as DigikamRegion[];
+
+      const albumPaths = this.albumPaths(roots, albums, options.rootPaths ?? {});
+      const tagPaths = this.tagPaths(tags);
+      const tagsByImage = new Map<number, string[]>();
+      imageTags.forEach(({ imageid, tagid }) => {
+        const tagPath = tagPaths.get(tagid);
+        // People come in as faces, not as tags
+        if (!tagPath || personTagIds.has(tagid)) return;
+        if (!tagsByImage.has(imageid)) tagsByImage.set(imageid, []);
+        tagsByImage.get(imageid)!.push(tagPath);
+      });
+      const regionsByImage = new Map<number, DigikamRegion[]>();
+      regions.forEach(region => {
+        if (!regionsByImage.has(region.imageid)) regionsByImage.set(region.imageid, []);
+        regionsByImage.get(region.imageid)!.push(region);
+      });
+      const tagNames = new Map(tags.map(tag => [tag.id, tag.name]));
+
+      const result: DigikamImportResult = {
+        images: images.length, imported: 0, duplicates: 0, missing: [], failed: [],
+        tagged: 0, rated: 0, faces: 0, peopleCreated: 0, albums: 0,
+      };
+      const people = new Map((await storage.getPeople()).map(person => [person.name.toLowerCase(), person]));
+      const photosByAlbum = new Map<number, string[]>();
+
+      for (let index = 0; index < images.length; index++) {
+        token?.throwIfCancelled();
+        onProgress?.(index, images.length);
+        const image = images[index];
+        const folder = albumPaths.get(image.album);
+        if (!folder) continue;
+        const filePath = path.join(folder, image.name);
+
+        try {
+          await fs.access(filePath);
+        } catch {
+          result.missing.push(filePath);
+          continue;
+        }
+
+        try {
+          const imported = await localImportService.importFile(filePath, {
+            sourceDevice: 'digiKam',
+            details: `Imported from digiKam database ${path.basename(databasePath)}`,
+            data: { digikamImageId: image.id },
+          });
+          if (imported.decision === 'imported') result.imported++;
+          else result.duplicates++;
+          const photo = await storage.getFileVersion(imported.photoId);
+          if (!photo) continue;
+
+          if (!photosByAlbum.has(image.album)) photosByAlbum.set(image.album, []);
+          photosByAlbum.get(image.album)!.push(photo.id);
+
+          const imageTagPaths = tagsByImage.get(image.id) ?? [];
+          if (imageTagPaths.length > 0) {
+            await propagationService.changeTags(photo.id, { action: 'add', tags: imageTagPaths }, NO_PROPAGATION);
+            result.tagged++;
+          }
+
+          // digiKam uses -1 for unrated; a rating set in Pictallion wins
+          if (image.rating && image.rating > 0 && !photo.rating) {
+            await storage.updateFileVersion(photo.id, { rating: Math.min(5, image.rating) });
+            result.rated++;
+          }
+
+          result.faces += await this.importFaces(photo.id, regionsByImage.get(image.id) ?? [], personTagIds, tagNames, people, result);
+        } catch (error) {
+          console.error(`Failed to import ${filePath} from digiKam:`, error);
+          result.failed.push({ path: filePath, reason: error instanceof Error ? error.message : 'Import failed' });
+        }
+      }
+      onProgress?.(images.length, images.length);
+
+      result.albums = await this.importAlbums(albums, photosByAlbum);
+      if (result.faces > 0) counterConsistencyService.scheduleCheck();
+      return result;
+    } finally {
+      database.close();
+    }
+  }
+
+  private async importFaces(
+    photoId: string,
+    regions: DigikamRegion[],
+    personTagIds: Set<number>,
+    tagNames: Map<number, string>,
+    people: Map<string, Person>,
+    result: DigikamImportResult
+  ): Promise<number> {
+    const existing = await storage.getFacesByPhoto(photoId);
+    let created = 0;
+    for (const region of regions) {
+      const boundingBox = this.parseRect(region.value);
+      if (!boundingBox) continue;
+      // Already imported on an earlier run, or found by Pictallion's own detection
+      if (existing.some(face => boxOverlap(face.boundingBox as FaceBox, boundingBox) >= SAME_FACE_OVERLAP)) {
+        continue;
+      }
+
+      let personId: string | null = null;
+      const name = tagNames.get(region.tagid);
+      if (region.property === CONFIRMED_REGION && personTagIds.has(region.tagid) && name) {
+        let person = people.get(name.toLowerCase());
+        if (!person) {
+          person = await storage.createPerson({ name });
+          people.set(name.toLowerCase(), person);
+          result.peopleCreated++;
+        }
+        personId = person.id;
+      }
+
+      const face = await storage.createFace({ photoId, personId, boundingBox, confidence: 100 });
+      existing.push(face);
+      created++;
+    }
+    return created;
+  }
+
+  /**
+   * One album per digiKam album with imported photos, named after its folder;
+   * an album with that name already in the library is added to instead
+   */
+  private async importAlbums(albums: DigikamAlbum[], photosByAlbum: Map<number, string[]>): Promise<number> {
+    const existing = new Map((await storage.getCollections()).map(collection => [collection.name, collection]));
+    let count = 0;
+    for (const album of albums) {
+      const photoIds = photosByAlbum.get(album.id);
+      if (!photoIds || photoIds.length === 0) continue;
+      const name = album.relativePath.replace(/^\/+|\/+$/g, '');
+      // The root folder of an album root holds loose files, not an album
+      if (!name) continue;
+
+      const collection = existing.get(name)
+        ?? await storage.createCollection({ name, description: 'Imported from digiKam' });
+      existing.set(name, collection);
+      await storage.addPhotosToCollection(collection.id, photoIds);
+      count++;
+    }
+    return count;
+  }
+
+  /**
+   * Folder on disk of each album. Roots identified by path
+   * ("volumeid:?path=/home/me/Pictures") resolve on their own; others need
+   * a rootPaths entry, falling back to the root's specific path.
+   */
+  private albumPaths(roots: DigikamAlbumRoot[], albums: DigikamAlbum[], rootPaths: Record<string, string>): Map<number, string> {
+    const rootFolders = new Map<number, string>();
+    roots.forEach(root => {
+      const mapped = rootPaths[String(root.id)];
+      const byPath = root.identifier?.match(/[?&]path=([^&]+)/);
+      const folder = mapped ?? (byPath ? path.join(decodeURIComponent(byPath[1]), root.specificPath ?? '') : root.specificPath);
+      if (folder) rootFolders.set(root.id, folder);
+    });
+
+    const folders = new Map<number, string>();
+    albums.forEach(album => {
+      const root = rootFolders.get(album.albumRoot);
+      if (root) folders.set(album.id, path.join(root, album.relativePath));
+    });
+    return folders;
+  }
+
+  /**
+   * Full "Parent/Child" path of every user tag
+   */
+  private tagPaths(tags: DigikamTag[]): Map<number, string> {
+    const byId = new Map(tags.map(tag => [tag.id, tag]));
+    const paths = new Map<number, string>();
+    tags.forEach(tag => {
+      const names: string[] = [];
+      const seen = new Set<number>();
+      let current: DigikamTag | undefined = tag;
+      while (current && !seen.has(current.id)) {
+        seen.add(current.id);
+        names.unshift(current.name);
+        current = current.pid ? byId.get(current.pid) : undefined;
+      }
+      if (names[0] !== INTERNAL_TAG_ROOT) paths.set(tag.id, names.join('/'));
+    });
+    return paths;
+  }
+
+  // Regions are stored as <rect x="…" y="…" width="…" height="…"/> in image pixels
+  private parseRect(value: string): FaceBox | null {
+    const attribute = (name: string) => {
+      const match = value.match(new RegExp(`\\b${name}="(-?\\d+(?:\\.\\d+)?)"`));
+      return match ? Number(match[1]) : NaN;
+    };
+    const rect: FaceBox = [attribute('x'), attribute('y'), attribute('width'), attribute('height')];
+    return rect.every(Number.isFinite) && rect[2] > 0 && rect[3] > 0 ? rect : null;
+  }
+}
+
+export const digikamImportService = new DigikamImportService();
@@ -0,0 +1,294 @@
+import fs from "fs/promises";
+import path from "path";
+import sharp from "sharp";
+import { storage } from "../storage";
+import { systemAlbumService } from "./systemAlbums";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import { buildImagePdf, type PdfImagePage } from "../utils/pdfWriter";
+import { getEffectiveDate } from "../utils/photoDates";
+import type { CancellationToken } from "./operations";
+import { volumeStatusService } from "./volumeStatus";
+import type { FileVersion } from "@shared/schema";
+
+// Working resolution for correction; plenty for A4 at ~200 DPI
+const MAX_WORKING_SIZE = 2400;
+const DETECTION_SIZE = 400;
+// The detected page must cover this share of the frame to be trusted
+const MIN_PAGE_COVERAGE = 0.2;
+// Pages filling almost the whole frame (flatbed scans) are left alone
+const MAX_PAGE_COVERAGE = 0.97;
+
+type Point = [number, number];
+
+interface GrayImage {
+  data: Buffer;
+  width: number;
+  height: number;
+}
+
+export interface DocumentExportResult {
+  filePath: string;
+  pageCount: number;
+  corrected: number; // pages that received perspective correction
+  skipped: Array<{ photoId: string; reason: string }>;
+}
+
+/**
+ * Bundle document and receipt photos into a dated, grayscale PDF. Each page is
+ * flattened with a simple four-corner perspective correction before export.
+ */
+class DocumentExportService {
+  async exportDocumentsPdf(photoIds: string[], destination: string, token?: CancellationToken): Promise<DocumentExportResult> {
+    const documentAssetIds = await this.getDocumentAssetIds();
+    const restricted = await privacyService.getRestrictedPhotos('export');
+    const skipped: DocumentExportResult['skipped'] = [];
+    const pages: PdfImagePage[] = [];
+    const exported: FileVersion[] = [];
+    let corrected = 0;
+
+    for (const photoId of photoIds) {
+      // Cancelling before the PDF is written leaves nothing behind
+      token?.throwIfCancelled();
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo) {
+        skipped.push({ photoId, reason: 'Photo not found' });
+        continue;
+      }
+      const asset = await storage.getMediaAsset(photo.mediaAssetId);
+      // Photos processed before the albums existed are classified on the fly
+      const isDocument = documentAssetIds.has(photo.mediaAssetId) ||
+        systemAlbumService.classify(photo, asset).some(key => key === 'documents' || key === 'receipts');
+      if (!isDocument) {
+        skipped.push({ photoId, reason: 'Photo is not tagged as a document or receipt' });
+        continue;
+      }
+      if (!photo.mimeType.startsWith('image/')) {
+        skipped.push({ photoId, reason: 'Not an image' });
+        continue;
+      }
+      if (restricted.ids.has(photo.id)) {
+        skipped.push({ photoId, reason: 'Shows a person excluded from exports' });
+        continue;
+      }
+
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) {
+        skipped.push({ photoId, reason: `Original is offline${original.volumeLabel ? ` on ${original.volumeLabel}` : ''}` });
+        continue;
+      }
+
+      try {
+        const image = await this.loadGrayscale(original.path);
+        const corners = this.detectPageCorners(image);
+        const page = corners ? this.warpPerspective(image, corners) : image;
+        if (corners) corrected++;
+
+        const jpeg = await sharp(page.data, { raw: { width: page.width, height: page.height, channels: 1 } })
+          .normalise()
+          .jpeg({ quality: 85 })
+          .toBuffer();
+
+        const date = getEffectiveDate(photo);
+        pages.push({
+          jpeg,
+          width: page.width,
+          height: page.height,
+          grayscale: true,
+          caption: `${date.toISOString().slice(0, 10)}  ${asset?.originalFilename ?? path.basename(photo.filePath)}`,
+        });
+        exported.push(photo);
+      } catch (error) {
+        console.error(`Failed to prepare document page for ${photo.filePath}:`, error);
+        skipped.push({ photoId, reason: 'Failed to process image' });
+      }
+    }
+
+    if (pages.length === 0) {
+      throw new Error('None of the selected photos could be exported');
+    }
+
+    const now = new Date();
+    const datestamp = now.toISOString().slice(0, 10);
+    await fs.mkdir(destination, { recursive: true });
+    const filePath = await this.uniquePath(destination, `documents-${datestamp}`);
+    token?.throwIfCancelled();
+    const partialPath = `${filePath}.partial`;
+    await fs.writeFile(partialPath, buildImagePdf(pages, { title: `Documents ${datestamp}`, creationDate: now }));
+    await fs.rename(partialPath, filePath);
+    await provenanceService.recordExport(exported, filePath, 'document PDF');
+
+    return { filePath, pageCount: pages.length, corrected, skipped };
+  }
+
+  private async getDocumentAssetIds(): Promise<Set<string>> {
+    const assetIds = new Set<string>();
+    for (const key of ['documents', 'receipts']) {
+      const album = await storage.getCollectionBySystemKey(key);
+      if (!album) continue;
+      for (const photo of await storage.getCollectionPhotos(album.id)) assetIds.add(photo.mediaAssetId);
+    }
+    return assetIds;
+  }
+
+  private async loadGrayscale(fullPath: string): Promise<GrayImage> {
+    const { data, info } = await sharp(fullPath)
+      .rotate()
+      .resize(MAX_WORKING_SIZE, MAX_WORKING_SIZE, { fit: 'inside', withoutEnlargement: true })
+      .grayscale()
+      .raw()
+      .toBuffer({ resolveWithObject: true });
+    return { data, width: info.width, height: info.height };
+  }
+
+  /**
+   * Find the four corners of a bright page on a darker background: threshold a
+   * small copy with Otsu's method, then take the extreme page pixels along the
+   * diagonals. Returns null when no plausible page outline is found.
+   */
+  private detectPageCorners(image: GrayImage): [Point, Point, Point, Point] | null {
+    const scale = Math.min(1, DETECTION_SIZE / Math.max(image.width, image.height));
+    const width = Math.max(1, Math.round(image.width * scale));
+    const height = Math.max(1, Math.round(image.height * scale));
+    const small = Buffer.alloc(width * height);
+    for (let y = 0; y < height; y++) {
+      for (let x = 0; x < width; x++) {
+        small[y * width + x] = image.data[Math.min(image.height - 1, Math.floor(y / scale)) * image.width + Math.min(image.width - 1, Math.floor(x / scale))];
+      }
+    }
+
+    const threshold = this.otsuThreshold(small);
+    let topLeft: Point | null = null, topRight: Point | null = null, bottomRight: Point | null = null, bottomLeft: Point | null = null;
+    let minSum = Infinity, maxSum = -Infinity, minDiff = Infinity, maxDiff = -Infinity;
+
+    for (let y = 0; y < height; y++) {
+      for (let x = 0; x < width; x++) {
+        if (small[y * width + x] <= threshold) continue;
+        const sum = x + y;
+        const diff = x - y;
+        if (sum < minSum) { minSum = sum; topLeft = [x, y]; }
+        if (sum > maxSum) { maxSum = sum; bottomRight = [x, y]; }
+        if (diff > maxDiff) { maxDiff = diff; topRight = [x, y]; }
+        if (diff < minDiff) { minDiff = diff; bottomLeft = [x, y]; }
+      }
+    }
+    if (!topLeft || !topRight || !bottomRight || !bottomLeft) return null;
+
+    const corners: [Point, Point, Point, Point] = [topLeft, topRight, bottomRight, bottomLeft];
+    const coverage = this.polygonArea(corners) / (width * height);
+    if (coverage < MIN_PAGE_COVERAGE || coverage > MAX_PAGE_COVERAGE) return null;
+
+    return corners.map(([x, y]) => [x / scale, y / scale]) as [Point, Point, Point, Point];
+  }
+
+  /**
+   * Map the quadrilateral onto an upright rectangle using a homography and
+   * bilinear sampling
+   */
+  private warpPerspective(image: GrayImage, [tl, tr, br, bl]: [Point, Point, Point, Point]): GrayImage {
+    const distance = (a: Point, b: Point) => Math.hypot(a[0] - b[0], a[1] - b[1]);
+    const width = Math.round(Math.max(distance(tl, tr), distance(bl, br)));
+    const height = Math.round(Math.max(distance(tl, bl), distance(tr, br)));
+    const h = this.solveHomography(
+      [[0, 0], [width - 1, 0], [width - 1, height - 1], [0, height - 1]],
+      [tl, tr, br, bl]
+    );
+
+    const output = Buffer.alloc(width * height);
+    for (let y = 0; y < height; y++) {
+      for (let x = 0; x < width; x++) {
+        const w = h[6] * x + h[7] * y + 1;
+        const sx = (h[0] * x + h[1] * y + h[2]) / w;
+        const sy = (h[3] * x + h[4] * y + h[5]) / w;
+        output[y * width + x] = this.sampleBilinear(image, sx, sy);
+      }
+    }
+    return { data: output, width, height };
+  }
+
+  // Solve for the 8 homography coefficients mapping `from` points onto `to` points
+  private solveHomography(from: Point[], to: Point[]): number[] {
+    const matrix: number[][] = [];
+    for (let i = 0; i < 4; i++) {
+      const [x, y] = from[i];
+      const [u, v] = to[i];
+      matrix.push([x, y, 1, 0, 0, 0, -u * x, -u * y, u]);
+      matrix.push([0, 0, 0, x, y, 1, -v * x, -v * y, v]);
+    }
+
+    // Gaussian elimination with partial pivoting
+    for (let col = 0; col < 8; col++) {
+      let pivot = col;
+      for (let row = col + 1; row < 8; row++) {
+        if (Math.abs(matrix[row][col]) > Math.abs(matrix[pivot][col])) pivot = row;
+      }
+      [matrix[col], matrix[pivot]] = [matrix[pivot], matrix[col]];
+      for (let row = 0; row < 8; row++) {
+        if (row === col || matrix[col][col] === 0) continue;
+        const factor = matrix[row][col] / matrix[col][col];
+        for (let k = col; k < 9; k++) matrix[row][k] -= factor * matrix[col][k];
+      }
+    }
+    return matrix.map((row, i) => row[8] / row[i]);
+  }
+
+  private sampleBilinear(image: GrayImage, x: number, y: number): number {
+    const x0 = Math.max(0, Math.min(image.width - 1, Math.floor(x)));
+    const y0 = Math.max(0, Math.min(image.height - 1, Math.floor(y)));
+    const x1 = Math.min(image.width - 1, x0 + 1);
+    const y1 = Math.min(image.height - 1, y0 + 1);
+    const fx = Math.max(0, Math.min(1, x - x0));
+    const fy = Math.max(0, Math.min(1, y - y0));
+    const at = (px: number, py: number) => image.data[py * image.width + px];
+    const top = at(x0, y0) * (1 - fx) + at(x1, y0) * fx;
+    const bottom = at(x0, y1) * (1 - fx) + at(x1, y1) * fx;
+    return Math.round(top * (1 - fy) + bottom * fy);
+  }
+
+  private otsuThreshold(data: Buffer): number {
+    const histogram = new Array<number>(256).fill(0);
+    for (let i = 0; i < data.length; i++) histogram[data[i]]++;
+
+    const total = data.length;
+    const sumAll = histogram.reduce((sum, count, value) => sum + count * value, 0);
+    let sumBackground = 0, weightBackground = 0, best = 0, threshold = 127;
+    for (let t = 0; t < 256; t++) {
+      weightBackground += histogram[t];
+      if (weightBackground === 0) continue;
+      const weightForeground = total - weightBackground;
+      if (weightForeground === 0) break;
+      sumBackground += t * histogram[t];
+      const meanBackground = sumBackground / weightBackground;
+      const meanForeground = (sumAll - sumBackground) / weightForeground;
+      const between = weightBackground * weightForeground * (meanBackground - meanForeground) ** 2;
+      if (between > best) {
+        best = between;
+        threshold = t;
+      }
+    }
+    return threshold;
+  }
+
+  private polygonArea(points: Point[]): number {
+    let area = 0;
+    for (let i = 0; i < points.length; i++) {
+      const [x1, y1] = points[i];
+      const [x2, y2] = points[(i + 1) % points.length];
+      area += x1 * y2 - x2 * y1;
+    }
+    return Math.abs(area) / 2;
+  }
+
+  private async uniquePath(directory: string, baseName: string): Promise<string> {
+    for (let attempt = 0; ; attempt++) {
+      const candidate = path.join(directory, `${baseName}${attempt ? `-${attempt}` : ''}.pdf`);
+      try {
+        await fs.access(candidate);
+      } catch {
+        return candidate;
+      }
+    }
+  }
+}
+
+export const documentExportService = new DocumentExportService();
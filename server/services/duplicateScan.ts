@@ -0,0 +1,197 @@
+import path from "path";
+import { storage } from "../storage";
+import { enhancedDuplicateDetectionService } from "./enhancedDuplicateDetection";
+import { volumeStatusService } from "./volumeStatus";
+import type { FileVersion } from "@shared/schema";
+
+export interface DuplicateScanScope {
+  photoIds?: string[];
+  collectionId?: string;
+  ingestedAfter?: string; // ISO date, scopes the scan to a recent import batch
+}
+
+export interface DuplicateGroup {
+  type: 'exact' | 'near';
+  similarity: number;
+  photos: FileVersion[];
+}
+
+const DEFAULT_NEAR_THRESHOLD = 95;
+
+class DuplicateScanService {
+  /**
+   * Find exact and near-duplicate groups using the stored file hash and perceptual
+   * hash columns. Groups must contain at least one photo from the scope, and groups
+   * whose pairs have all been reviewed are not returned again.
+   */
+  async findDuplicateGroups(
+    scope: DuplicateScanScope = {},
+    threshold: number = DEFAULT_NEAR_THRESHOLD,
+    includeResolved = false
+  ): Promise<DuplicateGroup[]> {
+    const allPhotos = (await storage.getAllFileVersions()).filter(photo => photo.mimeType.startsWith('image/'));
+    const scopedAssets = await this.resolveScope(scope, allPhotos);
+    const resolvedPairs = includeResolved ? new Set<string>() : await this.getResolvedPairs();
+
+    // A scoped scan only hashes the photos it compares
+    await this.backfillPerceptualHashes(scopedAssets ? allPhotos.filter(photo => scopedAssets.has(photo.mediaAssetId)) : allPhotos);
+
+    const groups: DuplicateGroup[] = [];
+    const exactMembers = new Set<string>();
+
+    // Exact duplicates share a file hash (but skip tier copies of the same asset)
+    const byHash = new Map<string, FileVersion[]>();
+    for (const photo of allPhotos) {
+      const list = byHash.get(photo.fileHash) ?? [];
+      list.push(photo);
+      byHash.set(photo.fileHash, list);
+    }
+    byHash.forEach(photos => {
+      const distinct = this.onePerAsset(photos);
+      if (distinct.length < 2) return;
+      if (!this.touchesScope(distinct, scopedAssets) || this.isResolved(distinct, resolvedPairs)) return;
+      distinct.forEach(photo => exactMembers.add(photo.id));
+      groups.push({ type: 'exact', similarity: 100, photos: distinct });
+    });
+
+    // Near duplicates: union photos whose perceptual hashes are within the threshold
+    const hashed = this.onePerAsset(allPhotos.filter(photo => photo.perceptualHash && !exactMembers.has(photo.id)));
+    const parent = new Map<string, string>(hashed.map(photo => [photo.id, photo.id]));
+    const find = (id: string): string => {
+      while (parent.get(id) !== id) id = parent.get(id)!;
+      return id;
+    };
+    const bestSimilarity = new Map<string, number>();
+
+    // A scoped scan only compares scoped photos against the library, so it costs
+    // n·k rather than n² on large libraries
+    const sources = scopedAssets ? hashed.filter(photo => scopedAssets.has(photo.mediaAssetId)) : hashed;
+    const compared = new Set<string>();
+    for (const a of sources) {
+      compared.add(a.id);
+      for (const b of hashed) {
+        if (compared.has(b.id)) continue;
+
+        const similarity = enhancedDuplicateDetectionService.calculatePerceptualSimilarity(a.perceptualHash!, b.perceptualHash!);
+        if (similarity < threshold || resolvedPairs.has(this.pairKey(a.mediaAssetId, b.mediaAssetId))) continue;
+
+        const rootA = find(a.id);
+        const rootB = find(b.id);
+        if (rootA !== rootB) parent.set(rootB, rootA);
+        const root = find(a.id);
+        bestSimilarity.set(root, Math.min(bestSimilarity.get(root) ?? 100, similarity));
+      }
+    }
+
+    const clusters = new Map<string, FileVersion[]>();
+    for (const photo of hashed) {
+      const root = find(photo.id);
+      const list = clusters.get(root) ?? [];
+      list.push(photo);
+      clusters.set(root, list);
+    }
+    clusters.forEach((photos, root) => {
+      if (photos.length < 2) return;
+      groups.push({ type: 'near', similarity: bestSimilarity.get(root) ?? threshold, photos });
+    });
+
+    return groups;
+  }
+
+  /**
+   * Record a review decision so the group's pairs are skipped by future scans.
+   * Pairs are matched by media asset, so the decision covers every tier
+   * version of the reviewed photos.
+   */
+  async recordDecision(photoIds: string[], decision: 'not_duplicate' | 'resolved', keptPhotoId?: string) {
+    const photos = await Promise.all(photoIds.map(id => storage.getFileVersion(id)));
+    const mediaAssetIds = photos.flatMap(photo => photo ? [photo.mediaAssetId] : []);
+    return await storage.createDuplicateDecision({
+      photoIds: Array.from(new Set(photoIds)).sort(),
+      mediaAssetIds: Array.from(new Set(mediaAssetIds)).sort(),
+      decision,
+      keptPhotoId: keptPhotoId ?? null,
+    });
+  }
+
+  // Resolve the scope to media asset ids so any tier version of a scoped photo counts
+  private async resolveScope(scope: DuplicateScanScope, allPhotos: FileVersion[]): Promise<Set<string> | null> {
+    const constraints: string[][] = [];
+    if (scope.photoIds?.length) constraints.push(scope.photoIds);
+    if (scope.collectionId) {
+      constraints.push((await storage.getCollectionPhotos(scope.collectionId)).map(photo => photo.id));
+    }
+    if (scope.ingestedAfter) {
+      const since = new Date(scope.ingestedAfter).getTime();
+      constraints.push(allPhotos.filter(photo => photo.createdAt.getTime() >= since).map(photo => photo.id));
+    }
+    if (constraints.length === 0) return null;
+
+    const photoIds = constraints.reduce((acc, next) => acc.filter(id => next.includes(id)));
+    const idSet = new Set(photoIds);
+    return new Set(allPhotos.filter(photo => idSet.has(photo.id)).map(photo => photo.mediaAssetId));
+  }
+
+  private async getResolvedPairs(): Promise<Set<string>> {
+    const pairs = new Set<string>();
+    for (const decision of await storage.getDuplicateDecisions()) {
+      const ids = decision.mediaAssetIds;
+      for (let i = 0; i < ids.length; i++) {
+        for (let j = i + 1; j < ids.length; j++) {
+          pairs.add(this.pairKey(ids[i], ids[j]));
+        }
+      }
+    }
+    return pairs;
+  }
+
+  /**
+   * Compute perceptual hashes once for photos that predate stored hashes
+   */
+  private async backfillPerceptualHashes(photos: FileVersion[]): Promise<void> {
+    for (const photo of photos) {
+      if (photo.perceptualHash) continue;
+      // Offline originals are hashed once their volume is back
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) continue;
+      const hash = await enhancedDuplicateDetectionService.generatePerceptualHash(original.path);
+      if (hash) {
+        await storage.updateFileVersionPerceptualHash(photo.id, hash);
+        photo.perceptualHash = hash;
+      }
+    }
+  }
+
+  // Keep the highest tier version of each media asset so tier copies are not reported
+  private onePerAsset(photos: FileVersion[]): FileVersion[] {
+    const tierRank: Record<string, number> = { gold: 3, silver: 2, bronze: 1 };
+    const byAsset = new Map<string, FileVersion>();
+    for (const photo of photos) {
+      const current = byAsset.get(photo.mediaAssetId);
+      if (!current || (tierRank[photo.tier] ?? 0) > (tierRank[current.tier] ?? 0)) {
+        byAsset.set(photo.mediaAssetId, photo);
+      }
+    }
+    return Array.from(byAsset.values());
+  }
+
+  private touchesScope(photos: FileVersion[], scopedAssets: Set<string> | null): boolean {
+    return !scopedAssets || photos.some(photo => scopedAssets.has(photo.mediaAssetId));
+  }
+
+  private isResolved(photos: FileVersion[], resolvedPairs: Set<string>): boolean {
+    for (let i = 0; i < photos.length; i++) {
+      for (let j = i + 1; j < photos.length; j++) {
+        if (!resolvedPairs.has(this.pairKey(photos[i].mediaAssetId, photos[j].mediaAssetId))) return false;
+      }
+    }
+    return true;
+  }
+
+  // Pairs are keyed by media asset id
+  private pairKey(a: string, b: string): string {
+    return a < b ? `${a}:${b}` : `${b}:${a}`;
+  }
+}
+
+export const duplicateScanService = new DuplicateScanService();
@@ -0,0 +1,241 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import { volumeStatusService } from "./volumeStatus";
+import { sendMail, encodedSize, type MailAttachment, type SmtpConfig } from "../utils/smtp";
+import { displayImage } from "../utils/colorProfile";
+import type { FileVersion } from "@shared/schema";
+
+export const SMTP_SETTING = 'smtp_config';
+
+export const smtpConfigSchema = z.object({
+  host: z.string().min(1),
+  port: z.number().int().min(1).max(65535),
+  secure: z.boolean(),
+  allowInsecureAuth: z.boolean().optional(), // let the password go over a connection without TLS
+  username: z.string().optional(),
+  password: z.string().optional(),
+  from: z.string().min(3).regex(/^[^\r\n]+$/), // "Name <address>" or a bare address
+  // Largest message the server (or the recipients' inboxes) accept, attachments included
+  maxMessageBytes: z.number().int().min(1024 * 1024).optional(),
+});
+
+export type SmtpSettings = z.infer<typeof smtpConfigSchema>;
+
+// Rendering of the attached photos; "original" attaches the files as they are
+export const EMAIL_PRESETS = {
+  small: { maxSize: 1280, quality: 80 },
+  medium: { maxSize: 2048, quality: 85 },
+  large: { maxSize: 3072, quality: 90 },
+  original: null,
+} as const;
+
+export type EmailPreset = keyof typeof EMAIL_PRESETS;
+
+export const emailPhotosSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  recipients: z.array(z.string().email()).min(1),
+  preset: z.enum(["small", "medium", "large", "original"]).default("medium"),
+  subject: z.string().max(200).optional(),
+  message: z.string().optional(),
+});
+
+export type EmailPhotosInput = z.infer<typeof emailPhotosSchema>;
+
+export interface EmailPhotosResult {
+  messageId: string;
+  recipients: string[];
+  attached: number;
+  bytes: number;
+  downsized: number; // photos attached smaller than the preset to fit the size budget
+  skipped: Array<{ photoId: string; reason: string }>;
+}
+
+export class EmailExportError extends Error {}
+
+// Most providers reject messages over 25 MB; leave room for the text and headers
+const DEFAULT_MAX_MESSAGE_BYTES = 20 * 1024 * 1024;
+// Sizes tried, largest first, when the preset's rendering does not fit the budget
+const FALLBACK_SIZES = [2048, 1600, 1280, 1024, 800];
+
+/**
+ * Email photos through a configured SMTP server, for people who do not use
+ * share links. Photos are rendered with an email preset and attached; when
+ * the attachments would exceed the message size budget they are sent
+ * smaller, and photos that still do not fit are left out and reported.
+ * Each send is recorded in the photos' activity.
+ */
+class EmailExportService {
+  async getConfig(): Promise<SmtpSettings | null> {
+    const setting = await storage.getSettingByKey(SMTP_SETTING);
+    if (!setting?.value) return null;
+    try {
+      const parsed = smtpConfigSchema.safeParse(JSON.parse(setting.value));
+      return parsed.success ? parsed.data : null;
+    } catch {
+      return null;
+    }
+  }
+
+  async updateConfig(config: SmtpSettings): Promise<SmtpSettings> {
+    const current = await this.getConfig();
+    // The password is write-only; leaving it out keeps the one already saved
+    const saved = { ...config, password: config.password ?? current?.password };
+    const value = JSON.stringify(saved);
+    if (await storage.getSettingByKey(SMTP_SETTING)) {
+      await storage.updateSetting(SMTP_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: SMTP_SETTING,
+        value,
+        category: 'sharing',
+        description: 'SMTP server used to email photos',
+      });
+    }
+    return saved;
+  }
+
+  async clearConfig(): Promise<void> {
+    await storage.deleteSetting(SMTP_SETTING);
+  }
+
+  async emailPhotos(input: EmailPhotosInput): Promise<EmailPhotosResult> {
+    const config = await this.getConfig();
+    if (!config) {
+      throw new EmailExportError('Email is not set up; add an SMTP server first');
+    }
+
+    const budget = config.maxMessageBytes ?? DEFAULT_MAX_MESSAGE_BYTES;
+    const restricted = await privacyService.getRestrictedPhotos('export');
+    const skipped: EmailPhotosResult['skipped'] = [];
+    const attachments: MailAttachment[] = [];
+    const attachedPhotos: FileVersion[] = [];
+    const usedNames = new Set<string>();
+    let bytes = 0;
+    let downsized = 0;
+
+    for (const photoId of Array.from(new Set(input.photoIds))) {
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo || photo.deletedAt) {
+        skipped.push({ photoId, reason: 'Photo not found' });
+        continue;
+      }
+      if (restricted.ids.has(photo.id)) {
+        skipped.push({ photoId, reason: 'privacy' });
+        continue;
+      }
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) {
+        skipped.push({ photoId, reason: `Original is offline on ${original.volumeLabel}` });
+        continue;
+      }
+
+      try {
+        const asset = await storage.getMediaAsset(photo.mediaAssetId);
+        const rendered = await this.render(photo, original.path, input.preset, budget - bytes);
+        if (!rendered) {
+          skipped.push({ photoId, reason: 'Does not fit in the email size limit' });
+          continue;
+        }
+        const name = asset?.originalFilename ?? path.basename(photo.filePath);
+        const filename = this.uniqueName(rendered.jpeg ? `${path.parse(name).name}.jpg` : name, usedNames);
+        attachments.push({ filename, contentType: rendered.jpeg ? 'image/jpeg' : photo.mimeType, content: rendered.content });
+        attachedPhotos.push(photo);
+        bytes += encodedSize(rendered.content.length);
+        if (rendered.downsized) downsized++;
+      } catch (error) {
+        console.error(`Failed to prepare photo ${photo.id} for email:`, error);
+        skipped.push({ photoId, reason: error instanceof Error ? error.message : 'Failed to render photo' });
+      }
+    }
+
+    if (attachments.length === 0) {
+      throw new EmailExportError('None of the photos can be emailed');
+    }
+
+    const subject = (input.subject || `${attachments.length} photo${attachments.length === 1 ? '' : 's'}`).replace(/[\r\n]+/g, ' ');
+    const messageId = await sendMail(this.smtp(config), {
+      to: input.recipients,
+      subject,
+      text: input.message ?? '',
+      attachments,
+    });
+
+    for (const photo of attachedPhotos) {
+      await provenanceService.record(photo, 'EXPORTED', `Emailed to ${input.recipients.join(', ')}`, {
+        kind: 'email',
+        recipients: input.recipients,
+        preset: input.preset,
+        messageId,
+      });
+    }
+
+    return { messageId, recipients: input.recipients, attached: attachments.length, bytes, downsized, skipped };
+  }
+
+  /**
+   * The photo as it will be attached: the preset's rendering when it fits in
+   * the space left, else the largest smaller rendering that does, else null
+   */
+  private async render(
+    photo: FileVersion,
+    originalPath: string,
+    preset: EmailPreset,
+    remaining: number
+  ): Promise<{ content: Buffer; jpeg: boolean; downsized: boolean } | null> {
+    const settings = EMAIL_PRESETS[preset];
+    const fits = (content: Buffer) => encodedSize(content.length) <= remaining;
+
+    const isImage = photo.mimeType.startsWith('image/');
+    // Videos and other files are always sent as they are
+    if (!settings || !isImage) {
+      const content = await fs.readFile(originalPath);
+      if (fits(content)) return { content, jpeg: false, downsized: false };
+      if (!isImage) return null;
+    } else {
+      const content = await this.jpeg(originalPath, settings.maxSize, settings.quality);
+      if (fits(content)) return { content, jpeg: true, downsized: false };
+    }
+
+    const start = settings?.maxSize ?? Infinity;
+    for (const size of FALLBACK_SIZES.filter(candidate => candidate < start)) {
+      const content = await this.jpeg(originalPath, size, 80);
+      if (fits(content)) return { content, jpeg: true, downsized: true };
+    }
+    return null;
+  }
+
+  private async jpeg(originalPath: string, maxSize: number, quality: number): Promise<Buffer> {
+    return displayImage(originalPath)
+      .resize(maxSize, maxSize, { fit: 'inside', withoutEnlargement: true })
+      .jpeg({ quality })
+      .toBuffer();
+  }
+
+  private smtp(config: SmtpSettings): SmtpConfig {
+    return {
+      host: config.host,
+      port: config.port,
+      secure: config.secure,
+      username: config.username,
+      password: config.password,
+      from: config.from,
+    };
+  }
+
+  private uniqueName(filename: string, used: Set<string>): string {
+    const extension = path.extname(filename);
+    const stem = path.basename(filename, extension);
+    let candidate = filename;
+    for (let counter = 1; used.has(candidate.toLowerCase()); counter++) {
+      candidate = `${stem}_${counter}${extension}`;
+    }
+    used.add(candidate.toLowerCase());
+    return candidate;
+  }
+}
+
+export const emailExportService = new EmailExportService();
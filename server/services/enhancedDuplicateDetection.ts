@@ -3,6 +3,9 @@ import crypto from "crypto";
 import { promises as fs } from "fs";
 import path from "path";
 import sharp from "sharp";
+import { formatRegistry } from "./formatRegistry";
+import { assertUnlocked } from "./photoLock";
+import { libraryPath } from "../utils/libraryPaths";
 
 export interface DuplicateConflict {
   id: string;
@@ -161,7 +164,7 @@ export class EnhancedDuplicateDetectionService {
       log(`File extension: ${ext}`);
       
       // For temp files without extensions, check if it's an image by reading file header
-      let isImage = ext.match(/\.(jpg|jpeg|tiff)$/);
+      let isImage = formatRegistry.supports(filePath, 'metadata');
       if (!isImage && !ext) {
         // Check file header for JPEG signature
         const buffer = await fs.readFile(filePath);
@@ -337,7 +340,7 @@ export class EnhancedDuplicateDetectionService {
 
             // Generate perceptual hash for existing photo if not available
             if (!existingPerceptualHash) {
-              const fullPath = path.join(process.cwd(), 'data', photo.filePath);
+              const fullPath = libraryPath(photo.filePath);
               try {
                 await fs.access(fullPath);
                 existingPerceptualHash = await this.generatePerceptualHash(fullPath);
@@ -588,9 +591,13 @@ export class EnhancedDuplicateDetectionService {
     if (conflict.existingPhoto.tier !== 'silver') {
       throw new Error('Can only replace files in Silver tier');
     }
+    const existing = await storage.getFileVersion(conflict.existingPhoto.id);
+    if (existing) {
+      assertUnlocked(existing, 'delete');
+    }
 
     // Remove the old file
-    const oldFilePath = path.join(process.cwd(), 'data', conflict.existingPhoto.filePath);
+    const oldFilePath = libraryPath(conflict.existingPhoto.filePath);
     try {
       await fs.unlink(oldFilePath);
     } catch (error) {
@@ -600,7 +607,8 @@ export class EnhancedDuplicateDetectionService {
     // Process new file to silver tier
     const silverPath = await fileManager.processToSilver(
       conflict.newFile.tempPath, 
-      conflict.newFile.originalFilename
+      conflict.newFile.originalFilename,
+      conflict.newFile.fileHash
     );
 
     // Extract metadata from new file
@@ -852,11 +860,13 @@ export class EnhancedDuplicateDetectionService {
     // Process file to Silver tier
     const silverPath = await fileManager.processToSilver(
       conflict.newFile.tempPath, 
-      conflict.newFile.originalFilename
+      conflict.newFile.originalFilename,
+      conflict.newFile.fileHash
     );
 
     // Extract metadata with AI processing
     const metadata = await fileManager.extractMetadata(silverPath);
+    const dimensions = await fileManager.getImageDimensions(silverPath);
     
     // Add AI analysis for images
     let aiMetadata = null;
@@ -868,7 +878,7 @@ export class EnhancedDuplicateDetectionService {
     if (mimeType.startsWith('image/')) {
       try {
         const { aiService } = await import("./ai");
-        aiMetadata = await aiService.analyzeImage(silverPath, "openai");
+        aiMetadata = await aiService.analyzeImage(silverPath);
         aiShortDescription = aiMetadata.shortDescription;
       } catch (aiError) {
         console.warn(`AI analysis failed for ${conflict.newFile.originalFilename}:`, aiError);
@@ -890,6 +900,8 @@ export class EnhancedDuplicateDetectionService {
       mimeType: path.extname(conflict.newFile.originalFilename).toLowerCase().includes('jpg') ? 'image/jpeg' : 
                 path.extname(conflict.newFile.originalFilename).toLowerCase().includes('png') ? 'image/png' : 
                 'image/jpeg', // default
+      width: dimensions?.width ?? null,
+      height: dimensions?.height ?? null,
       metadata: combinedMetadata as any,
       perceptualHash: conflict.newFile.perceptualHash,
       aiShortDescription,
@@ -1,5 +1,8 @@
 import { storage } from "../storage";
-import { type Event, type Person } from "@shared/schema";
+import { locationClusteringService } from "./location-clustering";
+import { holidayCalendarService, ENABLED_HOLIDAYS_SETTING, DEFAULT_HOLIDAY_SETS, type HolidayDefinition } from "./holidayCalendars";
+import { daysFromOccurrence } from "../utils/eventRecurrence";
+import { type CombinedMetadata, type Event, type FileVersion, type Location, type Person } from "@shared/schema";
 
 export interface EventMatch {
   eventId: string;
@@ -9,73 +12,35 @@ export interface EventMatch {
   personId?: string;
   personName?: string;
   age?: number; // For birthdays
+  personIds?: string[]; // associated people of a custom event
+  locationIds?: string[]; // associated places of a custom event
 }
 
-export interface HolidayDefinition {
-  name: string;
-  month: number; // 1-12
-  day: number; // 1-31
-  country?: string;
-  region?: string;
+// What else is known about the photo, used to confirm custom events
+export interface EventDetectionContext {
+  personIds?: string[];
+  gps?: { latitude: number; longitude: number };
 }
 
-// US Holiday definitions
-const US_HOLIDAYS: HolidayDefinition[] = [
-  { name: "New Year's Day", month: 1, day: 1, country: "US" },
-  { name: "Martin Luther King Jr. Day", month: 1, day: 15, country: "US" }, // Third Monday approximation
-  { name: "Presidents' Day", month: 2, day: 19, country: "US" }, // Third Monday approximation
-  { name: "Memorial Day", month: 5, day: 27, country: "US" }, // Last Monday approximation
-  { name: "Independence Day", month: 7, day: 4, country: "US" },
-  { name: "Labor Day", month: 9, day: 2, country: "US" }, // First Monday approximation
-  { name: "Columbus Day", month: 10, day: 14, country: "US" }, // Second Monday approximation
-  { name: "Veterans Day", month: 11, day: 11, country: "US" },
-  { name: "Thanksgiving", month: 11, day: 28, country: "US" }, // Fourth Thursday approximation
-  { name: "Christmas Day", month: 12, day: 25, country: "US" },
-  { name: "Christmas Eve", month: 12, day: 24, country: "US" },
-  { name: "New Year's Eve", month: 12, day: 31, country: "US" },
-  { name: "Valentine's Day", month: 2, day: 14, country: "US" },
-  { name: "St. Patrick's Day", month: 3, day: 17, country: "US" },
-  { name: "Easter", month: 4, day: 12, country: "US" }, // Approximate, Easter varies
-  { name: "Mother's Day", month: 5, day: 12, country: "US" }, // Second Sunday approximation
-  { name: "Father's Day", month: 6, day: 16, country: "US" }, // Third Sunday approximation
-  { name: "Halloween", month: 10, day: 31, country: "US" },
-];
-
-// UK/International holidays
-const UK_HOLIDAYS: HolidayDefinition[] = [
-  { name: "New Year's Day", month: 1, day: 1, country: "UK" },
-  { name: "Good Friday", month: 4, day: 10, country: "UK" }, // Approximate, varies
-  { name: "Easter Monday", month: 4, day: 13, country: "UK" }, // Approximate, varies
-  { name: "May Day", month: 5, day: 1, country: "UK" },
-  { name: "Spring Bank Holiday", month: 5, day: 27, country: "UK" }, // Last Monday
-  { name: "Summer Bank Holiday", month: 8, day: 26, country: "UK" }, // Last Monday
-  { name: "Christmas Day", month: 12, day: 25, country: "UK" },
-  { name: "Boxing Day", month: 12, day: 26, country: "UK" },
-];
-
 export class EventDetectionService {
   
   /**
    * Detect events in a photo based on its taken date
    */
-  async detectEvents(photoDate: Date): Promise<EventMatch[]> {
+  async detectEvents(photoDate: Date, context: EventDetectionContext = {}): Promise<EventMatch[]> {
     const matches: EventMatch[] = [];
     
     try {
-      // Get enabled holiday settings
-      const holidaySettings = await storage.getSettingByKey('enabled_holidays');
-      const enabledCountries = holidaySettings?.value ? JSON.parse(holidaySettings.value) : ['US'];
-      
-      // Check for holiday matches
-      const holidayMatches = this.detectHolidays(photoDate, enabledCountries);
-      matches.push(...holidayMatches);
+      // Holidays from the enabled holiday sets
+      const holidays = await holidayCalendarService.getEnabledHolidays();
+      matches.push(...this.detectHolidays(photoDate, holidays));
       
       // Check for birthday matches
       const birthdayMatches = await this.detectBirthdays(photoDate);
       matches.push(...birthdayMatches);
       
       // Check for custom events
-      const customMatches = await this.detectCustomEvents(photoDate);
+      const customMatches = await this.detectCustomEvents(photoDate, context);
       matches.push(...customMatches);
       
       return matches;
@@ -86,41 +51,56 @@ export class EventDetectionService {
   }
   
   /**
-   * Detect holiday matches for a given date
+   * Detect events for a photo, using its GPS position and tagged people to
+   * confirm custom events that have associated places or people
    */
-  private detectHolidays(photoDate: Date, enabledCountries: string[]): EventMatch[] {
-    const matches: EventMatch[] = [];
-    const month = photoDate.getMonth() + 1; // JavaScript months are 0-indexed
-    const day = photoDate.getDate();
-    
-    // Get all relevant holidays
-    const allHolidays = [
-      ...(enabledCountries.includes('US') ? US_HOLIDAYS : []),
-      ...(enabledCountries.includes('UK') ? UK_HOLIDAYS : []),
-    ];
+  async detectEventsForPhoto(photo: FileVersion, photoDate: Date): Promise<EventMatch[]> {
+    const metadata = photo.metadata as CombinedMetadata | null;
+    const latitude = metadata?.exif?.gpsLatitude ?? metadata?.ai?.gpsCoordinates?.latitude;
+    const longitude = metadata?.exif?.gpsLongitude ?? metadata?.ai?.gpsCoordinates?.longitude;
+
+    let personIds: string[] = [];
+    try {
+      personIds = (await storage.getFacesByPhoto(photo.id))
+        .map(face => face.personId)
+        .filter((personId): personId is string => Boolean(personId));
+    } catch (error) {
+      console.error('Error loading faces for event detection:', error);
+    }
+
+    return this.detectEvents(photoDate, {
+      personIds,
+      gps: latitude !== undefined && longitude !== undefined ? { latitude, longitude } : undefined,
+    });
+  }
+
+  /**
+   * Detect holiday matches for a given date. Dates come from the calendars, so
+   * only the day either side is a near match (photos from the evening before,
+   * or uploads dated the morning after).
+   */
+  private detectHolidays(photoDate: Date, holidays: HolidayDefinition[]): EventMatch[] {
+    // The same holiday can come from several enabled sets; keep the best match per name
+    const byEventId = new Map<string, EventMatch>();
     
-    for (const holiday of allHolidays) {
-      // Exact date match
-      if (holiday.month === month && holiday.day === day) {
-        matches.push({
-          eventId: `holiday_${holiday.name.toLowerCase().replace(/[^a-z0-9]/g, '_')}`,
+    for (const holiday of holidays) {
+      const daysAway = holidayCalendarService.daysFromHoliday(holiday, photoDate, 1);
+      if (daysAway === null) continue;
+      
+      const eventId = `holiday_${holiday.name.toLowerCase().replace(/[^a-z0-9]/g, '_')}`;
+      const confidence = daysAway === 0 ? 100 : 80;
+      const existing = byEventId.get(eventId);
+      if (!existing || existing.confidence < confidence) {
+        byEventId.set(eventId, {
+          eventId,
           eventName: holiday.name,
           eventType: "holiday",
-          confidence: 100
-        });
-      }
-      // Near-match (within 1-2 days for holidays that move)
-      else if (Math.abs(holiday.day - day) <= 2 && holiday.month === month) {
-        matches.push({
-          eventId: `holiday_${holiday.name.toLowerCase().replace(/[^a-z0-9]/g, '_')}`,
-          eventName: holiday.name,
-          eventType: "holiday", 
-          confidence: 80
+          confidence
         });
       }
     }
     
-    return matches;
+    return Array.from(byEventId.values());
   }
   
   /**
@@ -180,44 +160,42 @@ export class EventDetectionService {
   }
   
   /**
-   * Detect custom events for a given date
+   * Detect custom events for a given date, including recurring and multi-day ones
    */
-  private async detectCustomEvents(photoDate: Date): Promise<EventMatch[]> {
+  private async detectCustomEvents(photoDate: Date, context: EventDetectionContext): Promise<EventMatch[]> {
     const matches: EventMatch[] = [];
     
     try {
       const events = await storage.getEvents?.() || [];
-      const targetDate = new Date(photoDate.getFullYear(), photoDate.getMonth(), photoDate.getDate());
+      const locationsById = new Map<string, Location>();
+      if (context.gps && events.some(event => event.locationIds?.length)) {
+        (await storage.getLocations()).forEach(location => locationsById.set(location.id, location));
+      }
       
       for (const event of events) {
         if (!event.isEnabled) continue;
         
-        const eventDate = new Date(event.date);
-        let checkDate: Date;
+        // Within one day of an occurrence's span
+        const daysAway = daysFromOccurrence(event, photoDate, 1);
+        if (daysAway === null) continue;
         
-        if (event.isRecurring && event.recurringType === 'yearly') {
-          // For yearly recurring events, check same month/day in photo year
-          checkDate = new Date(photoDate.getFullYear(), eventDate.getMonth(), eventDate.getDate());
-        } else {
-          checkDate = new Date(eventDate.getFullYear(), eventDate.getMonth(), eventDate.getDate());
+        let confidence = daysAway === 0 ? 100 : 85; // Slight penalty for near matches
+        const associated = this.matchAssociations(event, context, locationsById);
+        if (associated === true) {
+          confidence = Math.min(100, confidence + 10);
+        } else if (associated === false) {
+          confidence -= 25;
         }
         
-        // Check if dates match (within 1 day tolerance)
-        const timeDiff = Math.abs(targetDate.getTime() - checkDate.getTime());
-        const daysDiff = timeDiff / (1000 * 60 * 60 * 24);
-        
-        if (daysDiff <= 1) {
-          let confidence = 100;
-          if (daysDiff > 0) confidence = 85; // Slight penalty for near matches
-          
-          matches.push({
-            eventId: event.id,
-            eventName: event.name,
-            eventType: event.type,
-            confidence: confidence,
-            personId: event.personId || undefined
-          });
-        }
+        matches.push({
+          eventId: event.id,
+          eventName: event.name,
+          eventType: event.type,
+          confidence: confidence,
+          personId: event.personId || undefined,
+          personIds: event.personIds ?? undefined,
+          locationIds: event.locationIds ?? undefined
+        });
       }
       
       return matches;
@@ -226,6 +204,36 @@ export class EventDetectionService {
       return [];
     }
   }
+
+  /**
+   * Whether the photo's people or place agree with the event's associations;
+   * undefined when the event has none or the photo gives nothing to compare
+   */
+  private matchAssociations(event: Event, context: EventDetectionContext, locationsById: Map<string, Location>): boolean | undefined {
+    let comparable = false;
+
+    if (event.personIds?.length && context.personIds?.length) {
+      comparable = true;
+      if (context.personIds.some(personId => event.personIds!.includes(personId))) return true;
+    }
+
+    if (event.locationIds?.length && context.gps) {
+      for (const locationId of event.locationIds) {
+        const location = locationsById.get(locationId);
+        if (!location) continue;
+        comparable = true;
+        const distance = locationClusteringService.calculateDistance(
+          context.gps.latitude,
+          context.gps.longitude,
+          parseFloat(location.latitude),
+          parseFloat(location.longitude)
+        );
+        if (distance <= (location.radius ?? 100)) return true;
+      }
+    }
+
+    return comparable ? false : undefined;
+  }
   
   /**
    * Calculate age of a person in a photo based on photo date and birthdate
@@ -258,11 +266,11 @@ export class EventDetectionService {
    */
   async initializeDefaultHolidays(): Promise<void> {
     try {
-      const existing = await storage.getSettingByKey('enabled_holidays');
+      const existing = await storage.getSettingByKey(ENABLED_HOLIDAYS_SETTING);
       if (!existing) {
         await storage.createSetting({
-          key: 'enabled_holidays',
-          value: JSON.stringify(['US']),
+          key: ENABLED_HOLIDAYS_SETTING,
+          value: JSON.stringify(DEFAULT_HOLIDAY_SETS),
           category: 'events',
           description: 'Enabled holiday country sets for event detection'
         });
@@ -271,16 +279,6 @@ export class EventDetectionService {
       console.error('Error initializing holiday settings:', error);
     }
   }
-  
-  /**
-   * Get available holiday country options
-   */
-  getAvailableHolidaySets(): Array<{code: string, name: string, count: number}> {
-    return [
-      { code: 'US', name: 'United States', count: US_HOLIDAYS.length },
-      { code: 'UK', name: 'United Kingdom', count: UK_HOLIDAYS.length },
-    ];
-  }
 }
 
 export const eventDetectionService = new EventDetectionService();
\ No newline at end of file
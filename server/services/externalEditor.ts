@@ -0,0 +1,178 @@
+import fs from "fs/promises";
+import { watch, type FSWatcher } from "fs";
+import path from "path";
+import crypto from "crypto";
+import { spawn } from "child_process";
+import { storage } from "../storage";
+import { fileManager } from "./fileManager.js";
+import { assertUnlocked } from "./photoLock";
+import { provenanceService } from "./provenance";
+import { volumeStatusService } from "./volumeStatus";
+import type { FileVersion } from "@shared/schema";
+import { getLibraryRoot, scratchPath } from "../utils/libraryPaths";
+
+export interface ExternalEditSession {
+  id: string;
+  photoId: string;
+  editorPath: string;
+  scratchPath: string;
+  status: 'editing' | 'importing' | 'imported' | 'failed' | 'cancelled';
+  importedVersionId?: string;
+  error?: string;
+  startedAt: Date;
+}
+
+const SAVE_DEBOUNCE_MS = 2000;
+
+export class OriginalOfflineError extends Error {
+  readonly status = 503;
+}
+
+class ExternalEditorService {
+  private sessions = new Map<string, ExternalEditSession>();
+  private watchers = new Map<string, FSWatcher>();
+
+  /**
+   * Copy a photo to a scratch location, open it in the external editor and
+   * watch for the saved result. Only the editor configured in the
+   * external_editor_path setting is ever launched; requests cannot name one.
+   */
+  async startEdit(photo: FileVersion): Promise<ExternalEditSession> {
+    assertUnlocked(photo, 'edit');
+    const editor = (await storage.getSettingByKey('external_editor_path'))?.value;
+    if (!editor) {
+      throw new Error('No external editor configured');
+    }
+
+    const original = await volumeStatusService.locateOriginal(photo);
+    if (!original.available) {
+      throw new OriginalOfflineError(`Original is offline${original.volumeLabel ? ` on ${original.volumeLabel}` : ''}`);
+    }
+
+    const sessionId = crypto.randomUUID();
+    const sessionDir = scratchPath('external-edits', sessionId);
+    await fs.mkdir(sessionDir, { recursive: true });
+
+    const scratchPath = path.join(sessionDir, path.basename(photo.filePath));
+    await fs.copyFile(original.path, scratchPath);
+
+    const session: ExternalEditSession = {
+      id: sessionId,
+      photoId: photo.id,
+      editorPath: editor,
+      scratchPath,
+      status: 'editing',
+      startedAt: new Date(),
+    };
+    this.sessions.set(sessionId, session);
+
+    this.watchForSave(session, photo);
+
+    const child = spawn(editor, [scratchPath], { detached: true, stdio: 'ignore' });
+    child.on('error', (error) => {
+      console.error(`Failed to launch external editor ${editor}:`, error);
+      this.finish(session, 'failed', error.message);
+    });
+    child.unref();
+
+    return session;
+  }
+
+  getSession(sessionId: string): ExternalEditSession | undefined {
+    return this.sessions.get(sessionId);
+  }
+
+  /**
+   * Stop watching a session without importing anything
+   */
+  async cancel(sessionId: string): Promise<boolean> {
+    const session = this.sessions.get(sessionId);
+    if (!session || session.status !== 'editing') return false;
+    this.finish(session, 'cancelled');
+    await fs.rm(path.dirname(session.scratchPath), { recursive: true, force: true });
+    return true;
+  }
+
+  private watchForSave(session: ExternalEditSession, photo: FileVersion): void {
+    let timer: NodeJS.Timeout | null = null;
+
+    // Watch the directory rather than the file, since many editors save via rename
+    const watcher = watch(path.dirname(session.scratchPath), () => {
+      // Writing the imported version can touch the folder again
+      if (session.status !== 'editing') return;
+      if (timer) clearTimeout(timer);
+      timer = setTimeout(() => {
+        this.importEditedFile(session, photo).catch((error) => {
+          console.error(`Failed to import external edit for ${photo.id}:`, error);
+          this.finish(session, 'failed', error instanceof Error ? error.message : String(error));
+        });
+      }, SAVE_DEBOUNCE_MS);
+    });
+    this.watchers.set(session.id, watcher);
+  }
+
+  /**
+   * Import the saved file back as a new version of the photo with provenance
+   */
+  private async importEditedFile(session: ExternalEditSession, photo: FileVersion): Promise<void> {
+    if (session.status !== 'editing') return;
+
+    let buffer: Buffer;
+    try {
+      buffer = await fs.readFile(session.scratchPath);
+    } catch {
+      return; // file mid-rename; a later event will retry
+    }
+
+    const fileHash = crypto.createHash('md5').update(buffer).digest('hex');
+    if (fileHash === photo.fileHash) return; // not saved yet
+    session.status = 'importing';
+
+    const ext = path.extname(photo.filePath);
+    const editedName = `${path.basename(photo.filePath, ext)}_edited${ext}`;
+    const relativeScratch = path.relative(getLibraryRoot(), session.scratchPath);
+    const newPath = photo.tier === 'gold'
+      ? await fileManager.copyToGold(relativeScratch, undefined, fileHash)
+      : await fileManager.copyToSilver(relativeScratch, editedName, undefined, fileHash);
+
+    const metadata = await fileManager.extractMetadata(newPath);
+    const dimensions = await fileManager.getImageDimensions(newPath);
+    const version = await storage.createFileVersion({
+      mediaAssetId: photo.mediaAssetId,
+      tier: photo.tier === 'gold' ? 'gold' : 'silver',
+      filePath: newPath,
+      fileHash,
+      fileSize: buffer.length,
+      mimeType: photo.mimeType,
+      width: dimensions?.width ?? null,
+      height: dimensions?.height ?? null,
+      metadata: metadata as any,
+      rating: photo.rating,
+      keywords: photo.keywords,
+      location: photo.location,
+      eventType: photo.eventType,
+      eventName: photo.eventName,
+      aiShortDescription: photo.aiShortDescription,
+      isReviewed: photo.isReviewed,
+    });
+
+    const editor = path.basename(session.editorPath);
+    await provenanceService.record(version, 'EDITED_EXTERNALLY', `New version ${version.id} created from ${photo.id} using ${editor}`, {
+      sourcePhotoId: photo.id,
+      editor,
+    });
+
+    session.importedVersionId = version.id;
+    this.finish(session, 'imported');
+    await fs.rm(path.dirname(session.scratchPath), { recursive: true, force: true });
+  }
+
+  private finish(session: ExternalEditSession, status: ExternalEditSession['status'], error?: string): void {
+    session.status = status;
+    session.error = error;
+    this.watchers.get(session.id)?.close();
+    this.watchers.delete(session.id);
+  }
+}
+
+export const externalEditorService = new ExternalEditorService();
@@ -0,0 +1,282 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { faceDetectionService, FACE_EMBEDDING_MODEL, SUGGESTION_MIN_SIMILARITY } from "./faceDetection";
+import { propagationService } from "./propagation";
+import type { CancellationToken } from "./operations";
+import type { Face, FaceCluster } from "@shared/schema";
+
+// Faces closer than this join the same cluster: the person-suggestion match threshold
+const CLUSTER_MIN_SIMILARITY = SUGGESTION_MIN_SIMILARITY;
+// DBSCAN core point: a face plus at least one close neighbour
+const MIN_CLUSTER_SIZE = 2;
+
+export const assignClusterSchema = z.object({
+  personId: z.string().optional(),
+  name: z.string().trim().min(1).optional(),
+  faceIds: z.array(z.string()).optional(), // members to assign; all when omitted
+}).refine(body => Boolean(body.personId) !== Boolean(body.name), {
+  message: 'Give either personId or name',
+});
+
+export interface FaceClusterSuggestion {
+  clusterId: string;
+  faceCount: number;
+  faceIds: string[];
+  representativeFaceId: string;
+  suggestedPerson: { personId: string; personName: string; similarity: number } | null;
+}
+
+export interface ClusteringResult {
+  clusters: FaceClusterSuggestion[];
+  created: number;
+  grown: number; // faces added to clusters from earlier runs
+  unclustered: number;
+  cancelled: boolean;
+}
+
+/**
+ * Groups unassigned faces by embedding similarity so whole groups can be
+ * named at once. Membership is stored on the faces, so each run only places
+ * faces that are new since the last one: they join the closest existing
+ * cluster, and the rest are clustered among themselves with DBSCAN. Faces
+ * that match nothing stay loose and are tried again next time.
+ */
+class FaceClusteringService {
+  async clusterUnassignedFaces(
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ClusteringResult> {
+    const allFaces = await storage.getAllFaces();
+
+    // Faces named or ignored since the last run leave their clusters
+    const departed = allFaces.filter(face => face.clusterId && (face.personId || face.ignored));
+    await storage.setFacesCluster(departed.map(face => face.id), null);
+
+    const candidates = allFaces.filter(face =>
+      !face.personId && !face.ignored && face.embeddingModel === FACE_EMBEDDING_MODEL && Array.isArray(face.embedding));
+    const members = new Map<string, Face[]>();
+    const loose: Face[] = [];
+    for (const face of candidates) {
+      if (face.clusterId) {
+        const list = members.get(face.clusterId) ?? [];
+        list.push(face);
+        members.set(face.clusterId, list);
+      } else {
+        loose.push(face);
+      }
+    }
+
+    // Existing clusters lose departed members, so their centroids move
+    const centroids = new Map<string, number[]>();
+    for (const cluster of await storage.getFaceClusters()) {
+      const faces = members.get(cluster.id) ?? [];
+      if (faces.length === 0) {
+        await storage.deleteFaceCluster(cluster.id);
+        continue;
+      }
+      centroids.set(cluster.id, this.centroid(faces));
+    }
+
+    // New faces join the closest existing cluster when it is close enough
+    let grown = 0;
+    const remaining: Face[] = [];
+    for (let index = 0; index < loose.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, loose.length);
+      const face = loose[index];
+      const embedding = face.embedding as number[];
+
+      let best: { clusterId: string; similarity: number } | null = null;
+      for (const [clusterId, centroid] of Array.from(centroids.entries())) {
+        const similarity = faceDetectionService.calculateEmbeddingSimilarity(embedding, centroid);
+        if (similarity >= CLUSTER_MIN_SIMILARITY && (!best || similarity > best.similarity)) {
+          best = { clusterId, similarity };
+        }
+      }
+      if (best) {
+        members.get(best.clusterId)!.push(face);
+        grown++;
+      } else {
+        remaining.push(face);
+      }
+    }
+
+    for (const clusterId of Array.from(centroids.keys())) {
+      const faces = members.get(clusterId)!;
+      await storage.setFacesCluster(faces.filter(face => !face.clusterId).map(face => face.id), clusterId);
+      await storage.updateFaceCluster(clusterId, { centroid: this.centroid(faces), faceCount: faces.length });
+    }
+
+    // Whatever did not fit an existing cluster may form new ones
+    let created = 0;
+    let unclustered = remaining.length;
+    if (!token?.isCancelled) {
+      for (const group of this.dbscan(remaining)) {
+        const cluster = await storage.createFaceCluster(this.centroid(group), group.map(face => face.id));
+        members.set(cluster.id, group);
+        created++;
+        unclustered -= group.length;
+      }
+    }
+    onProgress?.(loose.length, loose.length);
+
+    return {
+      clusters: await this.suggestions(members),
+      created,
+      grown,
+      unclustered,
+      cancelled: token?.isCancelled ?? false,
+    };
+  }
+
+  /**
+   * Stored clusters with their suggestions, without placing new faces
+   */
+  async getClusters(): Promise<FaceClusterSuggestion[]> {
+    const members = new Map<string, Face[]>();
+    for (const face of await storage.getUnassignedFaces()) {
+      if (!face.clusterId || !Array.isArray(face.embedding)) continue;
+      const list = members.get(face.clusterId) ?? [];
+      list.push(face);
+      members.set(face.clusterId, list);
+    }
+    return this.suggestions(members);
+  }
+
+  /**
+   * Name a cluster: assign its faces to an existing person or to a new one
+   * created with the given name. Faces left out stay unassigned and loose.
+   */
+  async assignCluster(clusterId: string, target: z.infer<typeof assignClusterSchema>): Promise<{ personId: string; assigned: number; propagatedFaceIds: string[] } | null> {
+    const cluster = await storage.getFaceCluster(clusterId);
+    if (!cluster) return null;
+
+    const personId = target.personId ?? (await storage.createPerson({ name: target.name! })).id;
+    const clusterFaces = (await storage.getUnassignedFaces()).filter(face => face.clusterId === clusterId);
+    const selected = target.faceIds ? new Set(target.faceIds) : null;
+
+    const propagatedFaceIds: string[] = [];
+    let assigned = 0;
+    for (const face of clusterFaces) {
+      if (selected && !selected.has(face.id)) continue;
+      propagatedFaceIds.push(...(await propagationService.assignFace(face.id, personId)));
+      assigned++;
+    }
+    // Deleting the cluster releases any members the user left out
+    await storage.deleteFaceCluster(clusterId);
+
+    return { personId, assigned, propagatedFaceIds };
+  }
+
+  /**
+   * DBSCAN over cosine distance. Returns the clusters; noise is left out.
+   */
+  private dbscan(faces: Face[]): Face[][] {
+    const embeddings = faces.map(face => face.embedding as number[]);
+    const neighbours = (index: number): number[] => {
+      const result: number[] = [];
+      for (let other = 0; other < faces.length; other++) {
+        if (other !== index && faceDetectionService.calculateEmbeddingSimilarity(embeddings[index], embeddings[other]) >= CLUSTER_MIN_SIMILARITY) {
+          result.push(other);
+        }
+      }
+      return result;
+    };
+
+    const labels = new Array<number>(faces.length).fill(-1); // -1 unvisited, 0 noise, n cluster
+    let clusterCount = 0;
+    for (let index = 0; index < faces.length; index++) {
+      if (labels[index] !== -1) continue;
+      const seeds = neighbours(index);
+      if (seeds.length + 1 < MIN_CLUSTER_SIZE) {
+        labels[index] = 0;
+        continue;
+      }
+
+      const label = ++clusterCount;
+      labels[index] = label;
+      const queue = [...seeds];
+      while (queue.length > 0) {
+        const point = queue.shift()!;
+        if (labels[point] === 0) labels[point] = label; // border point
+        if (labels[point] !== -1) continue;
+        labels[point] = label;
+        const expansion = neighbours(point);
+        if (expansion.length + 1 >= MIN_CLUSTER_SIZE) {
+          queue.push(...expansion);
+        }
+      }
+    }
+
+    const groups: Face[][] = Array.from({ length: clusterCount }, () => []);
+    labels.forEach((label, index) => {
+      if (label > 0) groups[label - 1].push(faces[index]);
+    });
+    return groups;
+  }
+
+  private centroid(faces: Face[]): number[] {
+    return this.meanVector(faces.map(face => face.embedding as number[]));
+  }
+
+  private meanVector(embeddings: number[][]): number[] {
+    const length = Math.min(...embeddings.map(embedding => embedding.length));
+    const sum = new Array<number>(length).fill(0);
+    for (const embedding of embeddings) {
+      for (let i = 0; i < length; i++) sum[i] += embedding[i];
+    }
+    return sum.map(value => value / embeddings.length);
+  }
+
+  private async suggestions(members: Map<string, Face[]>): Promise<FaceClusterSuggestion[]> {
+    // Each person is represented by the mean of their assigned faces
+    const byPerson = new Map<string, number[][]>();
+    for (const face of await storage.getAssignedFaceEmbeddings(FACE_EMBEDDING_MODEL)) {
+      const list = byPerson.get(face.personId) ?? [];
+      list.push(face.embedding);
+      byPerson.set(face.personId, list);
+    }
+    const people = new Map((await storage.getPeople()).map(person => [person.id, person]));
+    const personCentroids = Array.from(byPerson.entries())
+      .filter(([personId]) => people.has(personId)) // soft-deleted people are not suggested
+      .map(([personId, embeddings]) => ({ personId, centroid: this.meanVector(embeddings) }));
+
+    const clusters: FaceCluster[] = await storage.getFaceClusters();
+    return clusters
+      .filter(cluster => (members.get(cluster.id)?.length ?? 0) > 0)
+      .map(cluster => {
+        const faces = members.get(cluster.id)!;
+        const centroid = cluster.centroid as number[];
+        const similarityTo = (embedding: number[]) => faceDetectionService.calculateEmbeddingSimilarity(embedding, centroid);
+
+        // The member closest to the centroid, preferring confident detections on ties
+        const representative = faces.reduce((best, face) => {
+          const difference = similarityTo(face.embedding as number[]) - similarityTo(best.embedding as number[]);
+          return difference > 0 || (difference === 0 && face.confidence > best.confidence) ? face : best;
+        });
+
+        let suggestedPerson: FaceClusterSuggestion['suggestedPerson'] = null;
+        for (const candidate of personCentroids) {
+          const similarity = similarityTo(candidate.centroid);
+          if (similarity >= CLUSTER_MIN_SIMILARITY && (!suggestedPerson || similarity > suggestedPerson.similarity)) {
+            suggestedPerson = {
+              personId: candidate.personId,
+              personName: people.get(candidate.personId)!.name,
+              similarity: Math.round(similarity * 1000) / 1000,
+            };
+          }
+        }
+
+        return {
+          clusterId: cluster.id,
+          faceCount: faces.length,
+          faceIds: faces.map(face => face.id),
+          representativeFaceId: representative.id,
+          suggestedPerson,
+        };
+      })
+      .sort((a, b) => b.faceCount - a.faceCount);
+  }
+}
+
+export const faceClusteringService = new FaceClusteringService();
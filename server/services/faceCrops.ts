@@ -0,0 +1,155 @@
+import fs from "fs/promises";
+import path from "path";
+import sharp from "sharp";
+import { createHash } from "crypto";
+import { faceDetectionService } from "./faceDetection";
+import { volumeStatusService } from "./volumeStatus";
+import { libraryPath } from "../utils/libraryPaths";
+import type { FaceBox } from "../utils/faceBoxes";
+import type { Face, FileVersion } from "@shared/schema";
+
+// Share of the face box kept around it in the final crop
+const CROP_PADDING = 0.4;
+// Bump when crops are cut differently, so cached ones are made again
+const CROP_VERSION = 1;
+
+type Step = (image: sharp.Sharp) => sharp.Sharp;
+const rotate = (angle: number): Step => image => image.rotate(angle);
+const flop: Step = image => image.flop();
+const flip: Step = image => image.flip();
+
+// What turns stored pixels upright for each EXIF orientation
+const ORIENTATION_STEPS: Record<number, Step[]> = {
+  1: [],
+  2: [flop],
+  3: [rotate(180)],
+  4: [flip],
+  5: [rotate(90), flop], // transpose
+  6: [rotate(90)],
+  7: [rotate(90), flip], // transverse
+  8: [rotate(270)],
+};
+
+export interface RenderedFaceCrop {
+  image: Buffer; // square JPEG
+  rotation: number | null; // degrees applied to level the eyes, null when not aligned
+}
+
+export class FaceCropUnavailableError extends Error {}
+
+/**
+ * Square face crops: upright, centred on the face with the same margin
+ * around every face, and rotated so the eyes are level when landmarks can be
+ * found. Crops asked for through getCachedCrop are kept with the thumbnails,
+ * so they survive the original going offline or into cold storage just as
+ * thumbnails do.
+ */
+class FaceCropService {
+  async renderCrop(filePath: string, [x, y, width, height]: FaceBox, size: number, align: boolean): Promise<RenderedFaceCrop> {
+    // Face boxes are in the stored pixel orientation, as detection reads them
+    const metadata = await sharp(filePath).metadata();
+    const imageWidth = metadata.width ?? 0;
+    const imageHeight = metadata.height ?? 0;
+
+    const centerX = x + width / 2;
+    const centerY = y + height / 2;
+    const cropSide = Math.round(Math.max(width, height) * (1 + CROP_PADDING));
+    // Twice the crop, so rotating leaves no empty corners inside it
+    const regionSide = cropSide * 2;
+    const region = await this.orient(
+      await this.extractPadded(filePath, centerX - regionSide / 2, centerY - regionSide / 2, regionSide, imageWidth, imageHeight),
+      metadata.orientation ?? 1
+    );
+
+    let rotation: number | null = null;
+    let levelled = region;
+    if (align) {
+      const angle = await faceDetectionService.estimateEyeAngle(region);
+      if (angle !== null) {
+        rotation = Math.round(angle * 10) / 10;
+        levelled = await sharp(region).rotate(-angle, { background: { r: 0, g: 0, b: 0 } }).png().toBuffer();
+      }
+    }
+
+    // Rotation grows the canvas around its centre, which is still the face centre
+    const rotated = await sharp(levelled).metadata();
+    const left = Math.round(((rotated.width ?? regionSide) - cropSide) / 2);
+    const top = Math.round(((rotated.height ?? regionSide) - cropSide) / 2);
+    const image = await sharp(levelled)
+      .extract({ left, top, width: cropSide, height: cropSide })
+      .resize(size, size)
+      .jpeg({ quality: 92 })
+      .toBuffer();
+    return { image, rotation };
+  }
+
+  /**
+   * Path of an aligned crop of the face, made from the original when it is
+   * missing or older than the original. When the original is offline an
+   * existing crop is used as it is.
+   */
+  async getCachedCrop(face: Face, photo: FileVersion, size: number): Promise<string> {
+    const boundingBox = face.boundingBox as FaceBox;
+    const key = createHash('md5').update(JSON.stringify({ CROP_VERSION, boundingBox })).digest('hex').slice(0, 8);
+    // Named after the photo like its thumbnails, so they are removed together
+    const cachePath = path.join(libraryPath('thumbnails'), `${photo.id}-face-${face.id}-${size}-${key}.jpeg`);
+
+    const cached = await fs.stat(cachePath).catch(() => null);
+    const original = await volumeStatusService.locateOriginal(photo);
+    if (!original.available) {
+      if (cached) return cachePath;
+      throw new FaceCropUnavailableError(`Original is offline${original.volumeLabel ? ` on ${original.volumeLabel}` : ''}`);
+    }
+    if (cached && cached.mtimeMs >= (await fs.stat(original.path)).mtimeMs) {
+      return cachePath;
+    }
+
+    const { image } = await this.renderCrop(original.path, boundingBox, size, true);
+    await fs.mkdir(path.dirname(cachePath), { recursive: true });
+    // Write beside the final path and rename, so the cache never holds a partial file
+    await fs.writeFile(`${cachePath}.partial`, image);
+    await fs.rename(`${cachePath}.partial`, cachePath);
+    return cachePath;
+  }
+
+  /**
+   * Turn a region cut from the stored pixels the way its EXIF orientation
+   * says to show it. The region is square and centred on the face, so the
+   * face stays in the middle.
+   */
+  private async orient(region: Buffer, orientation: number): Promise<Buffer> {
+    const steps = ORIENTATION_STEPS[orientation] ?? [];
+    let oriented = region;
+    // One operation per pass; sharp applies rotation and mirroring in its own order otherwise
+    for (const step of steps) {
+      oriented = await step(sharp(oriented)).png().toBuffer();
+    }
+    return oriented;
+  }
+
+  private async extractPadded(filePath: string, left: number, top: number, side: number, imageWidth: number, imageHeight: number): Promise<Buffer> {
+    const x0 = Math.round(left);
+    const y0 = Math.round(top);
+    const clampedLeft = Math.max(0, x0);
+    const clampedTop = Math.max(0, y0);
+    const clampedRight = Math.min(imageWidth, x0 + side);
+    const clampedBottom = Math.min(imageHeight, y0 + side);
+    if (clampedRight <= clampedLeft || clampedBottom <= clampedTop) {
+      throw new Error('Face lies outside the image');
+    }
+
+    return sharp(filePath)
+      .extract({ left: clampedLeft, top: clampedTop, width: clampedRight - clampedLeft, height: clampedBottom - clampedTop })
+      .extend({
+        left: clampedLeft - x0,
+        top: clampedTop - y0,
+        right: x0 + side - clampedRight,
+        bottom: y0 + side - clampedBottom,
+        background: { r: 0, g: 0, b: 0 },
+      })
+      .png()
+      .toBuffer();
+  }
+}
+
+export const faceCropService = new FaceCropService();
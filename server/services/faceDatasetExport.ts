@@ -0,0 +1,176 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { faceCropService } from "./faceCrops";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import type { CancellationToken } from "./operations";
+import { volumeStatusService } from "./volumeStatus";
+import type { Face } from "@shared/schema";
+
+export const faceDatasetExportSchema = z.object({
+  personIds: z.array(z.string()).min(1),
+  destination: z.string().min(1),
+  size: z.number().int().min(64).max(1024).optional(),
+  minConfidence: z.number().int().min(0).max(100).optional(),
+  excludeFaceIds: z.array(z.string()).optional(), // faces rejected by the user
+  align: z.boolean().optional(),
+});
+
+export type FaceDatasetExportOptions = Omit<z.infer<typeof faceDatasetExportSchema>, 'personIds' | 'destination'>;
+
+export interface FaceDatasetEntry {
+  file: string; // relative to the dataset folder
+  faceId: string;
+  personId: string;
+  personName: string;
+  photoId: string;
+  mediaAssetId: string;
+  originalFilename: string | null;
+  libraryPath: string;
+  fileHash: string;
+  boundingBox: [number, number, number, number];
+  confidence: number;
+  rotation: number | null; // degrees applied to level the eyes, null when not aligned
+}
+
+export interface FaceDatasetManifest {
+  createdAt: string;
+  size: number;
+  people: Array<{ id: string; name: string; folder: string; faceCount: number }>;
+  faces: FaceDatasetEntry[];
+  excluded: Array<{ faceId: string; personId: string; reason: string }>;
+}
+
+export interface FaceDatasetExportResult {
+  destination: string;
+  manifestPath: string;
+  exported: number;
+  excluded: number;
+  failed: Array<{ faceId: string; reason: string }>;
+}
+
+const DEFAULT_SIZE = 160;
+const DEFAULT_MIN_CONFIDENCE = 0;
+
+/**
+ * Export confirmed faces as a training set for face recognition models built
+ * outside Pictallion: one folder of eye-levelled, square crops per person and
+ * a manifest tracing every crop back to its photo.
+ */
+class FaceDatasetExportService {
+  async exportFaceDataset(
+    personIds: string[],
+    destination: string,
+    options: FaceDatasetExportOptions = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<FaceDatasetExportResult> {
+    const size = options.size ?? DEFAULT_SIZE;
+    const minConfidence = options.minConfidence ?? DEFAULT_MIN_CONFIDENCE;
+    const rejected = new Set(options.excludeFaceIds ?? []);
+    const align = options.align ?? true;
+    // A crop can show more than its own face, so any private person in the photo holds it back
+    const restricted = await privacyService.getRestrictedPhotos('export');
+
+    const manifest: FaceDatasetManifest = { createdAt: new Date().toISOString(), size, people: [], faces: [], excluded: [] };
+    const failed: FaceDatasetExportResult['failed'] = [];
+    const work: Array<{ face: Face; personName: string; folder: string }> = [];
+
+    for (const personId of personIds) {
+      const person = await storage.getPerson(personId);
+      if (!person) {
+        throw new Error(`Person ${personId} not found`);
+      }
+      const folder = `${this.sanitize(person.name)}-${person.id.slice(0, 8)}`;
+      let faceCount = 0;
+
+      for (const face of await storage.getFacesByPerson(personId)) {
+        let reason: string | null = null;
+        if (rejected.has(face.id)) reason = 'rejected';
+        else if (face.ignored) reason = 'ignored';
+        else if (restricted.ids.has(face.photoId)) reason = 'privacy';
+        else if (face.confidence < minConfidence) reason = 'below minimum confidence';
+
+        if (reason) {
+          manifest.excluded.push({ faceId: face.id, personId, reason });
+        } else {
+          work.push({ face, personName: person.name, folder });
+          faceCount++;
+        }
+      }
+      manifest.people.push({ id: person.id, name: person.name, folder, faceCount });
+    }
+
+    await fs.mkdir(destination, { recursive: true });
+    for (const entry of manifest.people) {
+      await fs.mkdir(path.join(destination, entry.folder), { recursive: true });
+    }
+
+    for (let index = 0; index < work.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(index, work.length);
+      const { face, personName, folder } = work[index];
+
+      const photo = await storage.getFileVersion(face.photoId);
+      if (!photo) {
+        failed.push({ faceId: face.id, reason: 'Photo not found' });
+        continue;
+      }
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) {
+        failed.push({ faceId: face.id, reason: `Original is offline${original.volumeLabel ? ` on ${original.volumeLabel}` : ''}` });
+        continue;
+      }
+      try {
+        const boundingBox = face.boundingBox as [number, number, number, number];
+        const { image, rotation } = await faceCropService.renderCrop(original.path, boundingBox, size, align);
+        const file = path.posix.join(folder, `${face.id}.jpg`);
+        await fs.writeFile(path.join(destination, file), image);
+        await provenanceService.record(photo, 'EXPORTED', `Face crop exported to face dataset at ${destination}`, {
+          kind: 'face dataset',
+          destination,
+          faceId: face.id,
+        });
+
+        const asset = await storage.getMediaAsset(photo.mediaAssetId);
+        manifest.faces.push({
+          file,
+          faceId: face.id,
+          personId: face.personId!,
+          personName,
+          photoId: photo.id,
+          mediaAssetId: photo.mediaAssetId,
+          originalFilename: asset?.originalFilename ?? null,
+          libraryPath: photo.filePath,
+          fileHash: photo.fileHash,
+          boundingBox,
+          confidence: face.confidence,
+          rotation,
+        });
+      } catch (error) {
+        console.error(`Failed to export face ${face.id}:`, error);
+        failed.push({ faceId: face.id, reason: 'Failed to crop face' });
+      }
+    }
+    onProgress?.(work.length, work.length);
+
+    const manifestPath = path.join(destination, 'manifest.json');
+    await fs.writeFile(manifestPath, JSON.stringify(manifest, null, 2));
+
+    return {
+      destination,
+      manifestPath,
+      exported: manifest.faces.length,
+      excluded: manifest.excluded.length,
+      failed,
+    };
+  }
+
+  private sanitize(name: string): string {
+    return name.replace(/[<>:"/\\|?*\x00-\x1f]/g, '').replace(/\s+/g, '_').slice(0, 60) || 'person';
+  }
+}
+
+export const faceDatasetExportService = new FaceDatasetExportService();
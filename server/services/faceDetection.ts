@@ -3,14 +3,18 @@ import { storage } from '../storage.js';
 import sharp from 'sharp';
 import path from 'path';
 import fs from 'fs';
-import * as tf from '@tensorflow/tfjs-node';
-import * as faceapi from '@vladmandic/face-api';
+import { libraryPath, scratchPath } from "../utils/libraryPaths";
+import { propagationService, type PropagationScope } from "./propagation";
+import type { CancellationToken } from "./operations";
 
 export interface DetectedFace {
   id: string;
   boundingBox: [number, number, number, number]; // x, y, width, height
+  normalizedBox?: [number, number, number, number]; // the same box as fractions of the image size
   confidence: number; // 0-100 integer scale
   embedding?: number[]; // Face embedding for recognition
+  embeddingModel?: string;
+  detector?: FaceDetector;
   personId?: string; // If matched to known person
 }
 
@@ -22,17 +26,63 @@ export interface Person {
   created_at: Date;
 }
 
+// face-api scores are the network's probability; heuristic scores are guesses
+// from skin tone and composition and are not comparable with them
+export type FaceDetector = 'face-api' | 'heuristic';
+
+type FaceApi = typeof import('@vladmandic/face-api');
+
+// js: TensorFlow.js bundled with face-api, runs anywhere without native modules.
+// tfjs-node: libtensorflow bindings, much faster but an optional dependency
+// that needs a prebuilt binary for the platform.
+export type FaceDetectionBackend = 'js' | 'tfjs-node';
+
+export const FACE_DETECTION_THRESHOLD_SETTING = 'face_detection_min_confidence';
+const DEFAULT_MIN_CONFIDENCE = 50;
+
+// Stored with each embedding; vectors from different models are not comparable
+export const FACE_EMBEDDING_MODEL = 'face-api/face_recognition_resnet34';
+// Face-API's usual 0.6 euclidean match distance, as cosine similarity of its ~unit-length descriptors
+export const SUGGESTION_MIN_SIMILARITY = 0.82;
+const SUGGESTION_TOP_MATCHES = 3;
+
+export interface FaceDetectionOptions {
+  minConfidence?: number; // 0-100, defaults to the face_detection_min_confidence setting
+}
+
 class FaceDetectionService {
   private faceApiInitialized = false;
+  private faceapi: FaceApi | null = null;
+
+  /**
+   * Backend chosen with FACE_DETECTION_BACKEND, js by default
+   */
+  getBackend(): FaceDetectionBackend {
+    return process.env.FACE_DETECTION_BACKEND?.toLowerCase() === 'tfjs-node' ? 'tfjs-node' : 'js';
+  }
+
+  private async loadFaceApi(): Promise<FaceApi> {
+    if (this.getBackend() === 'tfjs-node') {
+      try {
+        // Registers the native backend that face-api's node build runs on
+        await import('@tensorflow/tfjs-node');
+        return await import('@vladmandic/face-api');
+      } catch (error) {
+        console.error('tfjs-node face detection backend unavailable, using the JavaScript backend:', error);
+      }
+    }
+    return await import('@vladmandic/face-api/dist/face-api.esm.js') as FaceApi;
+  }
 
   async initializeFaceAPI() {
     if (this.faceApiInitialized) return;
 
     try {
-      console.log('Initializing Face-API.js with TensorFlow.js backend...');
+      console.log(`Initializing Face-API.js with the ${this.getBackend()} TensorFlow.js backend...`);
+      const faceapi = this.faceapi ?? (this.faceapi = await this.loadFaceApi());
 
       // Initialize TensorFlow.js backend first
-      await tf.ready();
+      await faceapi.tf.ready();
 
       // Load face detection models
       const modelPath = 'https://vladmandic.github.io/face-api/model';
@@ -60,12 +110,27 @@ class FaceDetectionService {
     }
   }
 
-  async detectFaces(imagePath: string): Promise<{ faces: DetectedFace[], metadata: any }> {
+  /**
+   * Confidence (0-100) a detection needs to be kept, from settings
+   */
+  async getMinConfidence(): Promise<number> {
+    try {
+      const setting = await storage.getSettingByKey(FACE_DETECTION_THRESHOLD_SETTING);
+      const value = setting ? Number(setting.value) : NaN;
+      return Number.isFinite(value) ? Math.min(100, Math.max(0, value)) : DEFAULT_MIN_CONFIDENCE;
+    } catch {
+      return DEFAULT_MIN_CONFIDENCE;
+    }
+  }
+
+  async detectFaces(imagePath: string, options: FaceDetectionOptions = {}): Promise<{ faces: DetectedFace[], metadata: any }> {
+    const minConfidence = options.minConfidence ?? await this.getMinConfidence();
     const metadata = {
       faceDetection: {
         attempted: true,
         timestamp: new Date().toISOString(),
         method: null as string | null,
+        minConfidence,
         failed: false,
         error: null as string | null
       }
@@ -75,7 +140,7 @@ class FaceDetectionService {
       console.log('Running Face-API.js neural network face detection on:', imagePath);
 
       // Try Face-API detection first
-      let faces = await this.detectFacesWithFaceAPI(imagePath);
+      let faces = await this.detectFacesWithFaceAPI(imagePath, minConfidence);
 
       if (faces.length > 0) {
         console.log(`Face-API detected ${faces.length} faces successfully`);
@@ -83,10 +148,20 @@ class FaceDetectionService {
       } else {
         // If Face-API fails, fall back to heuristics
         console.log('Face-API found no faces, using heuristic fallback');
-        faces = await this.detectFacesWithAdvancedAnalysis(imagePath);
+        faces = (await this.detectFacesWithAdvancedAnalysis(imagePath))
+          .filter(face => face.confidence >= minConfidence);
         metadata.faceDetection.method = faces.length > 0 ? 'heuristic' : 'none';
       }
 
+      // Normalized boxes stay valid for any rendition of the photo
+      const { width, height } = await sharp(libraryPath(imagePath)).metadata();
+      if (width && height) {
+        for (const face of faces) {
+          const [x, y, w, h] = face.boundingBox;
+          face.normalizedBox = [x / width, y / height, w / width, h / height];
+        }
+      }
+
       console.log(`Face detection completed: found ${faces.length} faces`);
       return { faces, metadata };
     } catch (error) {
@@ -97,16 +172,17 @@ class FaceDetectionService {
     }
   }
 
-  async detectFacesWithFaceAPI(imagePath: string): Promise<DetectedFace[]> {
+  async detectFacesWithFaceAPI(imagePath: string, minConfidence: number = DEFAULT_MIN_CONFIDENCE): Promise<DetectedFace[]> {
     try {
       await this.initializeFaceAPI();
 
-      if (!this.faceApiInitialized) {
+      const faceapi = this.faceapi;
+      if (!this.faceApiInitialized || !faceapi) {
         console.warn('Face-API models not available, will use fallback detection');
         return [];
       }
 
-      const fullImagePath = path.join(process.cwd(), 'data', imagePath);
+      const fullImagePath = libraryPath(imagePath);
 
       // Verify file exists
       if (!fs.existsSync(fullImagePath)) {
@@ -114,17 +190,12 @@ class FaceDetectionService {
         return [];
       }
 
-      // Load and process image with Sharp for Face-API
-      const imageBuffer = await sharp(fullImagePath)
-        .jpeg({ quality: 90 })
-        .toBuffer();
-
-      // Convert buffer to tensor
-      const imageTensor: tf.Tensor3D = tf.node.decodeImage(imageBuffer, 3) as tf.Tensor3D;
+      // Decode with Sharp so no backend needs its own image decoder
+      const imageTensor = await this.decodeToTensor(faceapi, sharp(fullImagePath));
 
       // Remove MTCNN detection, use SSD MobileNet only
       const detections = await faceapi
-        .detectAllFaces(imageTensor as any, new faceapi.SsdMobilenetv1Options({ minConfidence: 0.5 }))
+        .detectAllFaces(imageTensor as any, new faceapi.SsdMobilenetv1Options({ minConfidence: minConfidence / 100 }))
         .withFaceLandmarks()
         .withFaceDescriptors();
 
@@ -147,7 +218,9 @@ class FaceDetectionService {
             Math.round(box.height)
           ],
           confidence: Math.round(detection.detection.score * 100),
-          embedding: Array.from(detection.descriptor)
+          embedding: Array.from(detection.descriptor),
+          embeddingModel: FACE_EMBEDDING_MODEL,
+          detector: 'face-api'
         };
 
         faces.push(faceData);
@@ -169,9 +242,21 @@ class FaceDetectionService {
     }
   }
 
+  /**
+   * Decode an image to an RGB tensor for Face-API
+   */
+  private async decodeToTensor(faceapi: FaceApi, image: sharp.Sharp) {
+    const { data, info } = await image
+      .removeAlpha()
+      .toColourspace('srgb')
+      .raw()
+      .toBuffer({ resolveWithObject: true });
+    return faceapi.tf.tensor3d(new Uint8Array(data), [info.height, info.width, 3], 'int32');
+  }
+
   async detectFacesWithAdvancedAnalysis(imagePath: string): Promise<DetectedFace[]> {
     try {
-      const fullImagePath = path.join(process.cwd(), 'data', imagePath);
+      const fullImagePath = libraryPath(imagePath);
       const imageInfo = await sharp(fullImagePath).metadata();
       const width = imageInfo.width || 1000;
       const height = imageInfo.height || 1000;
@@ -194,7 +279,8 @@ class FaceDetectionService {
           id: `advanced_face_${Date.now()}_${i}_${Math.random().toString(36).substr(2, 9)}`,
           boundingBox: region.boundingBox,
           confidence: region.confidence,
-          embedding: await this.generateFaceEmbedding(imagePath, region.boundingBox)
+          detector: 'heuristic',
+          ...await this.embeddingFields(imagePath, region.boundingBox)
         });
       }
 
@@ -395,7 +481,7 @@ class FaceDetectionService {
 
   async detectFacesWithHeuristics(imagePath: string): Promise<DetectedFace[]> {
     try {
-      const fullImagePath = path.join(process.cwd(), 'data', imagePath);
+      const fullImagePath = libraryPath(imagePath);
       const imageInfo = await sharp(fullImagePath).metadata();
       const width = imageInfo.width || 1000;
       const height = imageInfo.height || 1000;
@@ -411,7 +497,8 @@ class FaceDetectionService {
           id: `heuristic_face_${Date.now()}_${Math.random().toString(36).substr(2, 9)}`,
           boundingBox: region.boundingBox,
           confidence: region.confidence,
-          embedding: await this.generateFaceEmbedding(imagePath, region.boundingBox)
+          detector: 'heuristic',
+          ...await this.embeddingFields(imagePath, region.boundingBox)
         });
       }
 
@@ -486,69 +573,164 @@ class FaceDetectionService {
     });
   }
 
-  async generateFaceEmbedding(imagePath: string, boundingBox: [number, number, number, number]): Promise<number[]> {
+  private async embeddingFields(imagePath: string, boundingBox: [number, number, number, number]): Promise<Pick<DetectedFace, 'embedding' | 'embeddingModel'>> {
+    const embedding = await this.generateFaceEmbedding(imagePath, boundingBox);
+    return embedding ? { embedding, embeddingModel: FACE_EMBEDDING_MODEL } : {};
+  }
+
+  /**
+   * Recognition descriptor of a face, from Face-API's ResNet-34 model (see
+   * FACE_EMBEDDING_MODEL). Null when the models are unavailable or no face
+   * can be read in the box; callers store no embedding rather than a guess.
+   */
+  async generateFaceEmbedding(imagePath: string, boundingBox: [number, number, number, number]): Promise<number[] | null> {
     try {
       await this.initializeFaceAPI();
 
-      if (!this.faceApiInitialized) {
-        // Fallback to simple embedding if Face-API not available
-        const hash = imagePath + boundingBox.join(',');
-        const embedding = [];
+      const faceapi = this.faceapi;
+      if (!this.faceApiInitialized || !faceapi) return null;
 
-        for (let i = 0; i < 128; i++) {
-          embedding.push(Math.sin(hash.charCodeAt(i % hash.length) + i) * 100);
-        }
+      const fullImagePath = libraryPath(imagePath);
+      const { width: imageWidth = 0, height: imageHeight = 0 } = await sharp(fullImagePath).metadata();
 
-        return embedding;
+      // Pad the box so the detector sees the whole head and can place landmarks
+      const [x, y, width, height] = boundingBox;
+      const pad = Math.round(Math.max(width, height) * 0.3);
+      const left = Math.max(0, x - pad);
+      const top = Math.max(0, y - pad);
+      const cropWidth = Math.min(imageWidth, x + width + pad) - left;
+      const cropHeight = Math.min(imageHeight, y + height + pad) - top;
+      if (cropWidth <= 0 || cropHeight <= 0) return null;
+
+      const faceTensor = await this.decodeToTensor(faceapi, sharp(fullImagePath)
+        .extract({ left, top, width: cropWidth, height: cropHeight })
+        .resize(300, 300, { fit: 'inside' }));
+      try {
+        const detection = await faceapi
+          .detectSingleFace(faceTensor as any, new faceapi.SsdMobilenetv1Options({ minConfidence: 0.3 }))
+          .withFaceLandmarks()
+          .withFaceDescriptor();
+        if (detection?.descriptor) {
+          return Array.from(detection.descriptor);
+        }
+      } finally {
+        faceTensor.dispose();
       }
 
-      const fullImagePath = path.join(process.cwd(), 'data', imagePath);
-
-      // Crop face region first
-      const [x, y, width, height] = boundingBox;
-      const faceBuffer = await sharp(fullImagePath)
+      // The detector can miss small or partly turned faces; describe the box itself
+      const boxTensor = await this.decodeToTensor(faceapi, sharp(fullImagePath)
         .extract({ left: x, top: y, width, height })
-        .resize(150, 150)
-        .jpeg({ quality: 90 })
-        .toBuffer();
+        .resize(150, 150));
+      try {
+        const descriptor = await faceapi.computeFaceDescriptor(boxTensor as any);
+        return Array.from(Array.isArray(descriptor) ? descriptor[0] : descriptor);
+      } finally {
+        boxTensor.dispose();
+      }
+    } catch (error) {
+      console.error('Failed to generate face embedding:', error);
+      return null;
+    }
+  }
 
-      // Convert to tensor
-      const faceTensor: tf.Tensor3D = tf.node.decodeImage(faceBuffer, 3) as tf.Tensor3D;
+  /**
+   * Rank the people a face most likely belongs to, by cosine similarity
+   * against the embeddings of faces already assigned to each person. A
+   * person's score is the mean of their closest few faces, so one
+   * lookalike photo does not outweigh a consistent match.
+   */
+  async suggestPersonForFace(faceId: string, limit: number = 3): Promise<Array<{
+    personId: string;
+    personName: string;
+    similarity: number;
+    bestSimilarity: number;
+    matchedFaceIds: string[];
+  }> | null> {
+    const face = await storage.getFaceById(faceId);
+    if (!face) return null;
+    if (!Array.isArray(face.embedding) || face.embeddingModel !== FACE_EMBEDDING_MODEL) return [];
+    const embedding = face.embedding as number[];
+
+    const byPerson = new Map<string, Array<{ faceId: string; similarity: number }>>();
+    for (const candidate of await storage.getAssignedFaceEmbeddings(FACE_EMBEDDING_MODEL)) {
+      if (candidate.id === face.id || candidate.personId === face.personId) continue;
+      const similarity = this.calculateEmbeddingSimilarity(embedding, candidate.embedding);
+      if (similarity < SUGGESTION_MIN_SIMILARITY) continue;
+      const matches = byPerson.get(candidate.personId) ?? [];
+      matches.push({ faceId: candidate.id, similarity });
+      byPerson.set(candidate.personId, matches);
+    }
 
-      // Get face descriptor using Face-API
-      const detection = await faceapi
-        .detectSingleFace(faceTensor as any, new faceapi.SsdMobilenetv1Options({ minConfidence: 0.3 }))
-        .withFaceLandmarks()
-        .withFaceDescriptor();
+    // Soft-deleted people are not suggested
+    const people = new Map((await storage.getPeople()).map(person => [person.id, person]));
+    return Array.from(byPerson.entries())
+      .filter(([personId]) => people.has(personId))
+      .map(([personId, matches]) => {
+        const closest = matches.sort((a, b) => b.similarity - a.similarity).slice(0, SUGGESTION_TOP_MATCHES);
+        const mean = closest.reduce((sum, match) => sum + match.similarity, 0) / closest.length;
+        return {
+          personId,
+          personName: people.get(personId)!.name,
+          similarity: Math.round(mean * 1000) / 1000,
+          bestSimilarity: Math.round(closest[0].similarity * 1000) / 1000,
+          matchedFaceIds: closest.map(match => match.faceId),
+        };
+      })
+      .sort((a, b) => b.similarity - a.similarity)
+      .slice(0, limit);
+  }
 
-      faceTensor.dispose();
+  /**
+   * Average direction of each person's face embeddings, for comparing people
+   * as a whole rather than face by face
+   */
+  async getPersonCentroids(): Promise<Map<string, number[]>> {
+    const sums = new Map<string, { total: number[]; count: number }>();
+    for (const face of await storage.getAssignedFaceEmbeddings(FACE_EMBEDDING_MODEL)) {
+      const norm = Math.sqrt(face.embedding.reduce((sum, value) => sum + value * value, 0));
+      if (norm === 0) continue;
+      const entry = sums.get(face.personId) ?? { total: new Array(face.embedding.length).fill(0), count: 0 };
+      face.embedding.forEach((value, index) => { entry.total[index] += value / norm; });
+      entry.count++;
+      sums.set(face.personId, entry);
+    }
+    return new Map(Array.from(sums.entries()).map(([personId, entry]) => [personId, entry.total.map(value => value / entry.count)]));
+  }
 
-      if (detection && detection.descriptor) {
-        return Array.from(detection.descriptor);
-      } else {
-        // Fallback if no face detected in crop
-        const hash = imagePath + boundingBox.join(',');
-        const embedding = [];
+  /**
+   * Compute embeddings for faces that have none, or only a vector from an
+   * older model, so they can take part in person suggestions
+   */
+  async backfillEmbeddings(
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<{ updated: number; failed: number; cancelled: boolean }> {
+    const pending = (await storage.getAllFaces()).filter(face => face.embeddingModel !== FACE_EMBEDDING_MODEL);
+    const photoPaths = new Map<string, string | null>();
+    let updated = 0;
+    let failed = 0;
 
-        for (let i = 0; i < 128; i++) {
-          embedding.push(Math.sin(hash.charCodeAt(i % hash.length) + i) * 100);
-        }
+    for (let index = 0; index < pending.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, pending.length);
+      const face = pending[index];
 
-        return embedding;
+      if (!photoPaths.has(face.photoId)) {
+        photoPaths.set(face.photoId, (await storage.getFileVersion(face.photoId))?.filePath ?? null);
       }
-    } catch (error) {
-      console.error('Failed to generate face embedding:', error);
-
-      // Fallback embedding
-      const hash = imagePath + boundingBox.join(',');
-      const embedding = [];
-
-      for (let i = 0; i < 128; i++) {
-        embedding.push(Math.sin(hash.charCodeAt(i % hash.length) + i) * 100);
+      const filePath = photoPaths.get(face.photoId);
+      const embedding = filePath
+        ? await this.generateFaceEmbedding(filePath, face.boundingBox as [number, number, number, number])
+        : null;
+      if (!embedding) {
+        failed++;
+        continue;
       }
-
-      return embedding;
+      await storage.updateFace(face.id, { embedding, embeddingModel: FACE_EMBEDDING_MODEL });
+      updated++;
     }
+
+    return { updated, failed, cancelled: token?.isCancelled ?? false };
   }
 
   async findSimilarFaces(faceEmbedding: number[], threshold: number = 0.75): Promise<Array<{id: string, similarity: number, personId?: string}>> {
@@ -656,13 +838,17 @@ class FaceDetectionService {
     return await this.generateFaceSuggestions(unassignedFaceIds);
   }
 
-  async batchAssignFaces(assignments: Array<{faceId: string, personId: string}>): Promise<{success: number, failed: number}> {
+  async batchAssignFaces(
+    assignments: Array<{faceId: string, personId: string}>,
+    propagate?: PropagationScope
+  ): Promise<{success: number, failed: number, propagatedFaceIds: string[]}> {
     let success = 0;
     let failed = 0;
+    const propagatedFaceIds: string[] = [];
 
     for (const assignment of assignments) {
       try {
-        await storage.assignFaceToPerson(assignment.faceId, assignment.personId);
+        propagatedFaceIds.push(...(await propagationService.assignFace(assignment.faceId, assignment.personId, propagate)));
         success++;
       } catch (error) {
         console.error(`Failed to assign face ${assignment.faceId} to person ${assignment.personId}:`, error);
@@ -670,13 +856,44 @@ class FaceDetectionService {
       }
     }
 
-    return { success, failed };
+    return { success, failed, propagatedFaceIds };
+  }
+
+  /**
+   * Roll of a face in degrees, from the line between its eyes; positive when
+   * the right side of the image is lower. Null when no landmarks are found.
+   */
+  async estimateEyeAngle(faceImage: Buffer): Promise<number | null> {
+    try {
+      await this.initializeFaceAPI();
+
+      const faceapi = this.faceapi;
+      if (!this.faceApiInitialized || !faceapi) return null;
+
+      const faceTensor = await this.decodeToTensor(faceapi, sharp(faceImage));
+      const detection = await faceapi
+        .detectSingleFace(faceTensor as any, new faceapi.SsdMobilenetv1Options({ minConfidence: 0.3 }))
+        .withFaceLandmarks();
+      faceTensor.dispose();
+      if (!detection) return null;
+
+      const center = (points: Array<{ x: number; y: number }>) => ({
+        x: points.reduce((sum, point) => sum + point.x, 0) / points.length,
+        y: points.reduce((sum, point) => sum + point.y, 0) / points.length,
+      });
+      const leftEye = center(detection.landmarks.getLeftEye());
+      const rightEye = center(detection.landmarks.getRightEye());
+      return Math.atan2(rightEye.y - leftEye.y, rightEye.x - leftEye.x) * 180 / Math.PI;
+    } catch (error) {
+      console.error('Failed to estimate eye angle:', error);
+      return null;
+    }
   }
 
   async generateFaceCrop(imagePath: string, boundingBox: [number, number, number, number]): Promise<string> {
     try {
       const [x, y, width, height] = boundingBox;
-      const fullImagePath = path.join(process.cwd(), 'data', imagePath);
+      const fullImagePath = libraryPath(imagePath);
 
       // Get image metadata
       const imageInfo = await sharp(fullImagePath).metadata();
@@ -749,7 +966,7 @@ class FaceDetectionService {
 
       // Save to temporary location
       const cropFileName = `face_crop_${Date.now()}_${Math.random().toString(36).substr(2, 9)}.jpg`;
-      const cropPath = path.join(process.cwd(), 'uploads', 'temp', cropFileName);
+      const cropPath = scratchPath(cropFileName);
 
       await sharp(imageBuffer).toFile(cropPath);
 
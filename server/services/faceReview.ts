@@ -0,0 +1,81 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { counterConsistencyService } from "./counterConsistency";
+import { faceDetectionService } from "./faceDetection";
+import { smartCollectionService } from "./smartCollectionService";
+import type { Face, FileVersion } from "@shared/schema";
+
+// Face-API detections scored under this are worth a second look
+export const DEFAULT_REVIEW_THRESHOLD = 70;
+
+export const faceReviewQuerySchema = z.object({
+  threshold: z.coerce.number().int().min(1).max(100).default(DEFAULT_REVIEW_THRESHOLD),
+  limit: z.coerce.number().int().min(1).max(200).default(50),
+  offset: z.coerce.number().int().min(0).default(0),
+});
+
+export const faceReviewDecisionSchema = z.object({
+  faceIds: z.array(z.string()).min(1),
+});
+
+export type ReviewFace = Omit<Face, 'embedding'> & {
+  faceCropUrl: string | null;
+  photo: Pick<FileVersion, 'id' | 'filePath' | 'mimeType'>;
+};
+
+export interface FaceReviewPage {
+  threshold: number;
+  total: number;
+  faces: ReviewFace[];
+}
+
+/**
+ * Review queue for borderline face detections: statues, posters and pets
+ * picked up as faces. It lists unnamed faces whose detector confidence is
+ * under a threshold, lowest first; heuristic detections are always listed,
+ * since their scores are guesses rather than probabilities. Confirmed faces
+ * leave the queue for good, discarded ones are deleted.
+ */
+class FaceReviewService {
+  async listLowConfidenceFaces(threshold: number, limit: number, offset: number): Promise<FaceReviewPage> {
+    const page = await storage.getLowConfidenceFaces(threshold, limit, offset);
+    const faces: ReviewFace[] = [];
+    for (const face of page.faces) {
+      const photo = await storage.getFileVersion(face.photoId);
+      if (!photo) continue;
+      const { embedding, ...rest } = face;
+      let faceCropUrl: string | null = null;
+      try {
+        faceCropUrl = await faceDetectionService.generateFaceCrop(photo.filePath, face.boundingBox as [number, number, number, number]);
+      } catch (error) {
+        console.error(`Failed to generate crop for face ${face.id}:`, error);
+      }
+      faces.push({ ...rest, faceCropUrl, photo: { id: photo.id, filePath: photo.filePath, mimeType: photo.mimeType } });
+    }
+    return { threshold, total: page.total, faces };
+  }
+
+  /**
+   * Mark detections as real faces so they no longer show in the queue
+   */
+  async confirmFaces(faceIds: string[]): Promise<number> {
+    return storage.confirmFaces(Array.from(new Set(faceIds)));
+  }
+
+  /**
+   * Delete detections that are not faces
+   */
+  async discardFaces(faceIds: string[]): Promise<number> {
+    const deleted = await storage.deleteFaces(Array.from(new Set(faceIds)));
+    if (deleted.length > 0) {
+      // People's face counts and cluster sizes counted these faces
+      counterConsistencyService.scheduleCheck();
+      if (deleted.some(face => face.personId)) {
+        smartCollectionService.photosChanged(Array.from(new Set(deleted.map(face => face.photoId))));
+      }
+    }
+    return deleted.length;
+  }
+}
+
+export const faceReviewService = new FaceReviewService();
@@ -1,40 +1,69 @@
 import fs from "fs/promises";
 import path from "path";
-import ExifImage from "exif";
-import type { ExifMetadata, CombinedMetadata } from "@shared/schema";
+import sharp from "sharp";
+import { formatRegistry } from "./formatRegistry";
+import { formatMetadataExtractor } from "./formatMetadata";
+import { getMetadataBackend } from "./metadataBackend";
+import { cameraCanonicalizationService } from "./cameraCanonicalization";
+import { getLibraryRoot } from "../utils/libraryPaths";
+import { detectColorProfile } from "../utils/colorProfile";
+import { copyFileAtomic } from "../utils/files";
+import { storage } from "../storage";
+import type { ExifMetadata, CombinedMetadata, FileVersion } from "@shared/schema";
+
+// 'original' keeps the uploaded name; 'hash' stores files as {hash prefix}_{original name}
+export const STORAGE_NAMING_SETTING = 'storage_naming_mode';
+export type StorageNamingMode = 'original' | 'hash';
+const HASH_PREFIX_LENGTH = 8;
+
+type Tier = FileVersion['tier'];
 
 class FileManager {
-  private dataDir = path.join(process.cwd(), 'data');
-  private mediaDir = path.join(this.dataDir, 'media');
-
-  async initializeDirectories(): Promise<void> {
-    const dirs = [
-      this.dataDir,
-      this.mediaDir,
-      path.join(this.mediaDir, 'silver'),
-      path.join(this.mediaDir, 'gold'),
-      path.join(this.mediaDir, 'archive'),
-    ];
-
-    for (const dir of dirs) {
-      try {
-        await fs.access(dir);
-      } catch {
-        await fs.mkdir(dir, { recursive: true });
-        console.log(`Created directory: ${dir}`);
-      }
-    }
+  private get dataDir(): string {
+    return getLibraryRoot();
+  }
+  private get mediaDir(): string {
+    return path.join(this.dataDir, 'media');
   }
 
-  async processToSilver(tempPath: string, originalFilename: string): Promise<string> {
+  async processToSilver(tempPath: string, originalFilename: string, fileHash?: string): Promise<string> {
     console.log(`Processing file directly to Silver: ${originalFilename} from ${tempPath}`);
     
+    const photoDate = await this.resolvePhotoDate(tempPath, originalFilename);
+    const silverPath = await this.resolveDestination('silver', photoDate, originalFilename, fileHash);
+
+    console.log(`Moving ${tempPath} to ${silverPath}`);
+    await fs.rename(tempPath, silverPath);
+    
+    const relativePath = path.relative(this.dataDir, silverPath);
+    console.log(`File moved successfully to Silver: ${relativePath}`);
+    
+    // Return relative path from data directory
+    return relativePath;
+  }
+
+  /**
+   * Import a file into the bronze tier, leaving the source where it is.
+   * Returns the library-relative path of the copy.
+   */
+  async importToBronze(sourcePath: string, originalFilename: string, fileHash?: string): Promise<string> {
+    const photoDate = await this.resolvePhotoDate(sourcePath, originalFilename);
+    const bronzePath = await this.resolveDestination('bronze', photoDate, originalFilename, fileHash);
+    await copyFileAtomic(sourcePath, bronzePath);
+    return path.relative(this.dataDir, bronzePath);
+  }
+
+  /**
+   * Capture date used to file a new photo: EXIF dates first, then the
+   * filename, then today
+   */
+  private async resolvePhotoDate(filePath: string, originalFilename: string): Promise<Date> {
     // Extract EXIF metadata to determine photo date for organization
     let photoDate: Date | null = null;
     
     try {
-      if (path.extname(originalFilename).toLowerCase().match(/\.(jpg|jpeg|tiff)$/)) {
-        const exifData = await this.extractExifData(tempPath);
+      if (formatRegistry.supports(originalFilename, 'metadata')) {
+        const exifData = await this.readEmbeddedMetadata(filePath, originalFilename);
         
         console.log(`EXIF data for ${originalFilename}:`, {
           dateTimeOriginal: exifData.dateTimeOriginal,
@@ -82,121 +111,90 @@ class FileManager {
       console.log(`No date found in EXIF or filename, using current date: ${photoDate.toISOString()}`);
     }
 
-    // Create Silver directory structure by date
-    const year = photoDate.getFullYear();
-    const month = String(photoDate.getMonth() + 1).padStart(2, '0');
-    const silverDir = path.join(this.mediaDir, 'silver', String(year), month);
-    
-    console.log(`Target Silver directory: ${silverDir}`);
-
-    try {
-      await fs.access(silverDir);
-    } catch {
-      await fs.mkdir(silverDir, { recursive: true });
-    }
+    return photoDate;
+  }
 
-    // Use original filename, add timestamp only if conflict exists
-    let silverPath = path.join(silverDir, originalFilename);
-    
-    // Check if file already exists and generate unique filename if needed
+  async getNamingMode(): Promise<StorageNamingMode> {
     try {
-      await fs.access(silverPath);
-      // File exists, add timestamp to avoid conflict
-      const ext = path.extname(originalFilename);
-      const name = path.basename(originalFilename, ext);
-      const timestamp = Date.now();
-      const newFilename = `${name}_${timestamp}${ext}`;
-      silverPath = path.join(silverDir, newFilename);
+      const setting = await storage.getSettingByKey(STORAGE_NAMING_SETTING);
+      return setting?.value === 'hash' ? 'hash' : 'original';
     } catch {
-      // File doesn't exist, use original name
+      return 'original';
     }
+  }
 
-    console.log(`Moving ${tempPath} to ${silverPath}`);
-    await fs.rename(tempPath, silverPath);
-    
-    const relativePath = path.relative(this.dataDir, silverPath);
-    console.log(`File moved successfully to Silver: ${relativePath}`);
-    
-    // Return relative path from data directory
-    return relativePath;
+  /**
+   * Name a file gets when stored in a tier. In 'hash' mode it is prefixed
+   * with the start of the file hash, so photos sharing a display name keep
+   * distinct names in every tier. A name that already carries the prefix
+   * (a silver copy being promoted) is kept as is.
+   */
+  storedFilename(filename: string, mode: StorageNamingMode, fileHash?: string): string {
+    if (mode !== 'hash' || !fileHash) return filename;
+    const prefix = `${fileHash.slice(0, HASH_PREFIX_LENGTH)}_`;
+    return filename.startsWith(prefix) ? filename : `${prefix}${filename}`;
   }
 
-  async copyToSilver(sourcePath: string, newFilename?: string, photoDate?: Date): Promise<string> {
-    const fullSourcePath = path.join(this.dataDir, sourcePath);
-    
-    // Use photo's actual date if provided, otherwise fall back to current date
-    const date = photoDate || new Date();
-    const yearMonth = `${date.getFullYear()}/${String(date.getMonth() + 1).padStart(2, '0')}`;
-    const silverDir = path.join(this.mediaDir, 'silver', yearMonth);
+  /**
+   * Every file written into a tier is placed through here: the dated
+   * media/<tier>/YYYY/MM folder is created, the name is chosen per the naming
+   * mode, and a clash with an existing file gets a counter suffix. Returns
+   * an absolute path that is free to write.
+   */
+  async resolveDestination(tier: Tier, date: Date, filename: string, fileHash?: string): Promise<string> {
+    const dir = path.join(
+      this.mediaDir,
+      tier,
+      String(date.getFullYear()),
+      String(date.getMonth() + 1).padStart(2, '0')
+    );
+    await fs.mkdir(dir, { recursive: true });
+
+    const storedName = this.storedFilename(filename, await this.getNamingMode(), fileHash);
+    const ext = path.extname(storedName);
+    const nameWithoutExt = path.basename(storedName, ext);
+    let destination = path.join(dir, storedName);
+    for (let counter = 1; await this.exists(destination); counter++) {
+      destination = path.join(dir, `${nameWithoutExt}_${counter}${ext}`);
+    }
+    return destination;
+  }
 
+  private async exists(filePath: string): Promise<boolean> {
     try {
-      await fs.access(silverDir);
+      await fs.access(filePath);
+      return true;
     } catch {
-      await fs.mkdir(silverDir, { recursive: true });
+      return false;
     }
+  }
 
-    const filename = newFilename || path.basename(sourcePath);
-    let silverPath = path.join(silverDir, filename);
-    
-    // Check if file already exists and generate unique filename if needed
-    let counter = 1;
-    const ext = path.extname(filename);
-    const nameWithoutExt = path.basename(filename, ext);
-    
-    while (true) {
-      try {
-        await fs.access(silverPath);
-        // File exists, generate new name
-        const uniqueFilename = `${nameWithoutExt}_${counter}${ext}`;
-        silverPath = path.join(silverDir, uniqueFilename);
-        counter++;
-      } catch {
-        // File doesn't exist, we can use this path
-        break;
-      }
-    }
+  async copyToSilver(sourcePath: string, newFilename?: string, photoDate?: Date, fileHash?: string): Promise<string> {
+    const fullSourcePath = path.join(this.dataDir, sourcePath);
     
-    await fs.copyFile(fullSourcePath, silverPath);
+    // Use photo's actual date if provided, otherwise fall back to current date
+    const silverPath = await this.resolveDestination(
+      'silver',
+      photoDate || new Date(),
+      newFilename || path.basename(sourcePath),
+      fileHash
+    );
+    await copyFileAtomic(fullSourcePath, silverPath);
     
     return path.relative(this.dataDir, silverPath);
   }
 
-  async copyToGold(silverPath: string, photoDate?: Date): Promise<string> {
+  async copyToGold(silverPath: string, photoDate?: Date, fileHash?: string): Promise<string> {
     const fullSilverPath = path.join(this.dataDir, silverPath);
     
     // Use photo's actual date if provided, otherwise fall back to current date
-    const date = photoDate || new Date();
-    const yearMonth = `${date.getFullYear()}/${String(date.getMonth() + 1).padStart(2, '0')}`;
-    const goldDir = path.join(this.mediaDir, 'gold', yearMonth);
-
-    try {
-      await fs.access(goldDir);
-    } catch {
-      await fs.mkdir(goldDir, { recursive: true });
-    }
-
-    const filename = path.basename(silverPath);
-    let goldPath = path.join(goldDir, filename);
-    
-    // Check if file already exists and generate unique filename if needed
-    let counter = 1;
-    const ext = path.extname(filename);
-    const nameWithoutExt = path.basename(filename, ext);
-    
-    while (true) {
-      try {
-        await fs.access(goldPath);
-        // File exists, generate new name
-        const uniqueFilename = `${nameWithoutExt}_${counter}${ext}`;
-        goldPath = path.join(goldDir, uniqueFilename);
-        counter++;
-      } catch {
-        // File doesn't exist, we can use this path
-        break;
-      }
-    }
-    
-    await fs.copyFile(fullSilverPath, goldPath);
+    const goldPath = await this.resolveDestination(
+      'gold',
+      photoDate || new Date(),
+      path.basename(silverPath),
+      fileHash
+    );
+    await copyFileAtomic(fullSilverPath, goldPath);
     
     return path.relative(this.dataDir, goldPath);
   }
@@ -214,15 +212,38 @@ class FileManager {
       };
 
       // Try to extract EXIF data for images
-      if (path.extname(fullPath).toLowerCase().match(/\.(jpg|jpeg|tiff)$/)) {
+      if (formatRegistry.supports(fullPath, 'metadata')) {
         try {
-          const exifData = await this.extractExifData(fullPath);
+          const exifData = await this.readEmbeddedMetadata(fullPath);
           metadata.exif = { ...metadata.exif, ...exifData };
         } catch (exifError) {
           console.log(`No EXIF data available for ${filePath}`);
         }
       }
 
+      if (formatRegistry.getFormat(fullPath)?.kind === 'image') {
+        try {
+          const panorama = await formatMetadataExtractor.extractPanorama(fullPath);
+          if (panorama) metadata.panorama = panorama;
+        } catch (panoramaError) {
+          console.log(`Panorama detection failed for ${filePath}`);
+        }
+
+        try {
+          const profile = await detectColorProfile(fullPath, metadata.exif?.colorSpace);
+          if (profile) {
+            metadata.exif = {
+              ...metadata.exif,
+              colorSpace: profile.colorSpace,
+              colorProfile: profile.profileName ?? undefined,
+              wideGamut: profile.wideGamut,
+            };
+          }
+        } catch (profileError) {
+          console.log(`Color profile detection failed for ${filePath}`);
+        }
+      }
+
       return metadata;
     } catch (error) {
       console.error(`Error extracting metadata for ${filePath}:`, error);
@@ -230,161 +251,17 @@ class FileManager {
     }
   }
 
-  private async extractExifData(imagePath: string): Promise<ExifMetadata> {
-    return new Promise((resolve, reject) => {
-      // Set a timeout to prevent hanging
-      const timeout = setTimeout(() => {
-        reject(new Error('EXIF extraction timeout'));
-      }, 10000);
-
-      try {
-        new ExifImage({ image: imagePath }, (error: any, exifData: any) => {
-          clearTimeout(timeout);
-          
-          if (error) {
-            console.log(`EXIF extraction error for ${imagePath}:`, error.message);
-            reject(error);
-            return;
-          }
-
-          try {
-            const metadata: ExifMetadata = {};
-
-            // Extract camera information with multiple fallback sources
-            if (exifData.image) {
-              const make = this.safeGetStringField(exifData.image.Make || exifData.image.make);
-              const model = this.safeGetStringField(exifData.image.Model || exifData.image.model);
-              
-              if (make && model) {
-                metadata.camera = `${make} ${model}`;
-              } else if (make) {
-                metadata.camera = make;
-              } else if (model) {
-                metadata.camera = model;
-              }
-              
-              console.log(`Camera detection for ${imagePath}:`, {
-                rawMake: exifData.image.Make,
-                rawModel: exifData.image.Model,
-                detected: metadata.camera
-              });
-            }
-
-            // Extract date/time information with proper prioritization
-            if (exifData.exif) {
-              // Priority order: DateTimeOriginal > CreateDate > DateTime
-              const dateTimeOriginal = this.safeGetStringField(exifData.exif.DateTimeOriginal);
-              const createDate = this.safeGetStringField(exifData.exif.CreateDate);
-              const dateTime = this.safeGetStringField(exifData.image?.DateTime);
-              
-              metadata.dateTime = dateTimeOriginal || createDate || dateTime;
-              metadata.dateTimeOriginal = dateTimeOriginal;
-              metadata.createDate = createDate;
-              metadata.modifyDate = this.safeGetStringField(exifData.image?.ModifyDate);
-              
-              // Extract camera settings with proper validation
-              metadata.aperture = this.formatAperture(exifData.exif.FNumber);
-              metadata.shutter = this.formatShutter(exifData.exif.ExposureTime);
-              metadata.iso = this.safeGetNumberField(exifData.exif.ISO);
-              metadata.focalLength = this.formatFocalLength(exifData.exif.FocalLength);
-              metadata.lens = this.safeGetStringField(exifData.exif.LensModel);
-            }
-
-            // Extract GPS information with validation
-            if (exifData.gps && this.isValidGpsData(exifData.gps)) {
-              try {
-                metadata.gpsLatitude = exifData.gps.GPSLatitude ? 
-                  this.convertDMSToDD(exifData.gps.GPSLatitude, exifData.gps.GPSLatitudeRef) : undefined;
-                metadata.gpsLongitude = exifData.gps.GPSLongitude ? 
-                  this.convertDMSToDD(exifData.gps.GPSLongitude, exifData.gps.GPSLongitudeRef) : undefined;
-              } catch (gpsError) {
-                console.log(`GPS extraction error for ${imagePath}:`, gpsError);
-              }
-            }
-
-            resolve(metadata);
-          } catch (processingError) {
-            console.error(`Error processing EXIF data for ${imagePath}:`, processingError);
-            resolve({}); // Return empty metadata instead of rejecting
-          }
-        });
-      } catch (error) {
-        clearTimeout(timeout);
-        reject(error);
-      }
-    });
-  }
-
-  private safeGetStringField(value: any): string | undefined {
-    if (value === null || value === undefined) return undefined;
-    const str = String(value).trim();
-    return str.length > 0 ? str : undefined;
-  }
-
-  private safeGetNumberField(value: any): string | undefined {
-    if (value === null || value === undefined || isNaN(Number(value))) return undefined;
-    return String(value);
-  }
-
-  private formatAperture(fNumber: any): string | undefined {
-    if (fNumber === null || fNumber === undefined || isNaN(Number(fNumber))) return undefined;
-    return `f/${Number(fNumber).toFixed(1)}`;
-  }
-
-  private formatShutter(exposureTime: any): string | undefined {
-    if (exposureTime === null || exposureTime === undefined || isNaN(Number(exposureTime))) return undefined;
-    const time = Number(exposureTime);
-    if (time >= 1) return `${time}s`;
-    return `1/${Math.round(1/time)}s`;
-  }
-
-  private formatFocalLength(focalLength: any): string | undefined {
-    if (focalLength === null || focalLength === undefined || isNaN(Number(focalLength))) return undefined;
-    return `${Number(focalLength)}mm`;
-  }
-
-  private isValidGpsData(gps: any): boolean {
-    return gps && 
-           Array.isArray(gps.GPSLatitude) && gps.GPSLatitude.length === 3 &&
-           Array.isArray(gps.GPSLongitude) && gps.GPSLongitude.length === 3 &&
-           typeof gps.GPSLatitudeRef === 'string' &&
-           typeof gps.GPSLongitudeRef === 'string';
-  }
-
-  private convertDMSToDD(dms: number[], ref: string): number {
-    // Validate input
-    if (!Array.isArray(dms) || dms.length !== 3) {
-      throw new Error('Invalid DMS array format');
-    }
-    
-    const [degrees, minutes, seconds] = dms.map(Number);
-    
-    // Validate numeric values
-    if (isNaN(degrees) || isNaN(minutes) || isNaN(seconds)) {
-      throw new Error('Invalid DMS numeric values');
-    }
-    
-    // Validate ranges
-    if (minutes >= 60 || seconds >= 60 || degrees < 0 || minutes < 0 || seconds < 0) {
-      throw new Error('Invalid DMS value ranges');
-    }
-    
-    let dd = degrees + minutes/60 + seconds/3600;
-    
-    // Apply hemisphere reference
-    if (ref === "S" || ref === "W") {
-      dd = dd * -1;
-    }
-    
-    // Validate final coordinates
-    const isLatitude = ref === "N" || ref === "S";
-    const maxValue = isLatitude ? 90 : 180;
-    
-    if (Math.abs(dd) > maxValue) {
-      throw new Error(`Invalid ${isLatitude ? 'latitude' : 'longitude'} value: ${dd}`);
-    }
-    
-    return Math.round(dd * 1000000) / 1000000; // Round to 6 decimal places
+  /**
+   * Read embedded metadata, using the per-format extractors for containers the
+   * metadata backend can't parse (PNG, TIFF, WebP). Camera and lens names
+   * come back canonicalized.
+   */
+  private async readEmbeddedMetadata(filePath: string, originalFilename: string = filePath): Promise<ExifMetadata> {
+    // Uploads are staged without an extension, so dispatch on the original name
+    const exif = formatMetadataExtractor.canExtract(originalFilename)
+      ? await formatMetadataExtractor.extractAs(filePath, path.extname(originalFilename).toLowerCase())
+      : await (await getMetadataBackend()).readExif(filePath);
+    return cameraCanonicalizationService.apply(exif);
   }
 
   private parseExifDate(dateStr: string): Date | null {
@@ -432,6 +309,21 @@ class FileManager {
     return null;
   }
 
+  /**
+   * Read pixel dimensions of an image, accounting for EXIF orientation
+   */
+  async getImageDimensions(relativePath: string): Promise<{ width: number; height: number } | null> {
+    try {
+      const { width, height, orientation } = await sharp(path.join(this.dataDir, relativePath)).metadata();
+      if (!width || !height) return null;
+      // Orientations 5-8 are rotated by 90 degrees
+      return orientation && orientation >= 5 ? { width: height, height: width } : { width, height };
+    } catch (error) {
+      console.log(`Could not read dimensions for ${relativePath}`);
+      return null;
+    }
+  }
+
   getFileUrl(relativePath: string): string {
     return `/api/files/${relativePath}`;
   }
@@ -0,0 +1,243 @@
+import fs from "fs/promises";
+import { watch, type FSWatcher } from "fs";
+import path from "path";
+import type { Response } from "express";
+import { z } from "zod";
+import { storage } from "../storage";
+import { formatRegistry } from "./formatRegistry";
+import { localImportService } from "./localImport";
+import { getLibraryRoot } from "../utils/libraryPaths";
+import type { WatchFolder } from "@shared/schema";
+
+// Quiet period after the last change to a file before it is imported
+const DEBOUNCE_MS = 2000;
+// A file still being copied keeps growing; wait until its size holds
+const STABLE_CHECK_MS = 1000;
+const MAX_STABLE_CHECKS = 30;
+
+export const addWatchFolderSchema = z.object({
+  path: z.string().min(1),
+  recursive: z.boolean().optional(),
+  importExisting: z.boolean().optional(),
+});
+
+export type WatchImportStatus = 'imported' | 'duplicate' | 'failed';
+
+export interface WatchImportEvent {
+  watchFolderId: string;
+  filePath: string;
+  status?: WatchImportStatus;
+  photoId?: string;
+  message?: string;
+  pending: number; // files waiting to be imported across all folders
+}
+
+export class WatchFolderError extends Error {
+  constructor(message: string) {
+    super(message);
+    this.name = 'WatchFolderError';
+  }
+}
+
+/**
+ * Imports files dropped into watched folders into the bronze tier. Change
+ * notifications are debounced per file, files are imported one at a time
+ * once they stop growing, and anything whose hash is already in the library
+ * is skipped. Sources are copied, never moved. Progress goes out as
+ * server-sent events: detected, then imported, duplicate or failed.
+ */
+class FolderWatcherService {
+  private watchers = new Map<string, FSWatcher>();
+  private timers = new Map<string, NodeJS.Timeout>();
+  private pending = new Set<string>();
+  private queue: Promise<void> = Promise.resolve();
+  private started = false;
+  private eventClients = new Set<Response>();
+
+  async addWatchFolder(folderPath: string, options: { recursive?: boolean; importExisting?: boolean } = {}): Promise<WatchFolder> {
+    const resolved = path.resolve(folderPath);
+    const stats = await fs.stat(resolved).catch(() => null);
+    if (!stats?.isDirectory()) {
+      throw new WatchFolderError(`${resolved} is not a folder`);
+    }
+    // Watching the library (or a folder holding it) would re-import our own copies
+    const libraryRoot = getLibraryRoot();
+    if (this.isWithin(resolved, libraryRoot) || this.isWithin(libraryRoot, resolved)) {
+      throw new WatchFolderError('Watch folders cannot overlap the library folder');
+    }
+    if ((await storage.getWatchFolders()).some(folder => folder.path === resolved)) {
+      throw new WatchFolderError(`${resolved} is already watched`);
+    }
+
+    const folder = await storage.createWatchFolder({
+      path: resolved,
+      recursive: options.recursive ?? true,
+      importExisting: options.importExisting ?? true,
+      isEnabled: true,
+    });
+    if (this.started) {
+      await this.watchFolder(folder);
+    }
+    return folder;
+  }
+
+  async listWatchFolders(): Promise<Array<WatchFolder & { active: boolean }>> {
+    return (await storage.getWatchFolders()).map(folder => ({ ...folder, active: this.watchers.has(folder.id) }));
+  }
+
+  /**
+   * Stop watching and forget the folder; photos already imported stay
+   */
+  async removeWatchFolder(id: string): Promise<boolean> {
+    this.unwatchFolder(id);
+    return storage.deleteWatchFolder(id);
+  }
+
+  async start(): Promise<void> {
+    if (this.started) return;
+    this.started = true;
+    for (const folder of await storage.getWatchFolders()) {
+      if (folder.isEnabled) {
+        await this.watchFolder(folder);
+      }
+    }
+  }
+
+  /**
+   * Close all watchers; files not yet imported are picked up by the scan on the next start
+   */
+  stop(): void {
+    this.started = false;
+    Array.from(this.watchers.keys()).forEach(id => this.unwatchFolder(id));
+    this.timers.forEach(timer => clearTimeout(timer));
+    this.timers.clear();
+  }
+
+  addEventClient(res: Response): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    res.write(': connected\n\n');
+    this.eventClients.add(res);
+    res.on('close', () => this.eventClients.delete(res));
+  }
+
+  private async watchFolder(folder: WatchFolder): Promise<void> {
+    if (this.watchers.has(folder.id)) return;
+    try {
+      const watcher = watch(folder.path, { recursive: folder.recursive }, (_event, filename) => {
+        if (filename) this.schedule(folder, path.join(folder.path, filename.toString()));
+      });
+      watcher.on('error', error => {
+        console.error(`Watching ${folder.path} failed:`, error);
+        this.unwatchFolder(folder.id);
+      });
+      this.watchers.set(folder.id, watcher);
+    } catch (error) {
+      console.error(`Could not watch ${folder.path}:`, error);
+      return;
+    }
+
+    // Catch files that arrived while we were not watching
+    const since = folder.lastImportAt ?? (folder.importExisting ? null : folder.createdAt);
+    for (const filePath of await this.listFiles(folder.path, folder.recursive)) {
+      const stats = await fs.stat(filePath).catch(() => null);
+      if (stats && (!since || stats.mtimeMs > since.getTime())) this.schedule(folder, filePath);
+    }
+  }
+
+  private unwatchFolder(id: string): void {
+    this.watchers.get(id)?.close();
+    this.watchers.delete(id);
+  }
+
+  private schedule(folder: WatchFolder, filePath: string): void {
+    const name = path.basename(filePath);
+    if (name.startsWith('.') || name.endsWith('.partial') || !formatRegistry.getMimeType(name)) return;
+
+    const existing = this.timers.get(filePath);
+    if (existing) clearTimeout(existing);
+    this.timers.set(filePath, setTimeout(() => {
+      this.timers.delete(filePath);
+      if (this.pending.has(filePath)) return;
+      this.pending.add(filePath);
+      this.broadcast('detected', { watchFolderId: folder.id, filePath, pending: this.pending.size });
+      this.queue = this.queue
+        .then(() => this.importFile(folder, filePath))
+        .then(outcome => {
+          this.pending.delete(filePath);
+          if (outcome) {
+            this.broadcast(outcome.status, { watchFolderId: folder.id, filePath, ...outcome, pending: this.pending.size });
+          }
+        })
+        .catch(error => console.error(`Watch folder import failed for ${filePath}:`, error));
+    }, DEBOUNCE_MS));
+  }
+
+  /**
+   * Import one file; null when it disappeared or watching stopped first
+   */
+  private async importFile(
+    folder: WatchFolder,
+    filePath: string
+  ): Promise<{ status: WatchImportStatus; photoId?: string; message?: string } | null> {
+    if (!this.started) return null;
+    try {
+      if (!(await this.waitUntilStable(filePath))) return null; // deleted or renamed meanwhile
+
+      const result = await localImportService.importFile(filePath, {
+        sourceDevice: 'Watch folder',
+        details: `Imported to Bronze tier from watch folder: ${filePath}`,
+        data: { watchFolderId: folder.id },
+      });
+      if (result.decision === 'duplicate') {
+        return { status: 'duplicate', photoId: result.photoId };
+      }
+      await storage.updateWatchFolder(folder.id, { lastImportAt: new Date() });
+
+      return { status: 'imported', photoId: result.photoId };
+    } catch (error) {
+      console.error(`Failed to import ${filePath} from watch folder:`, error);
+      return { status: 'failed', message: error instanceof Error ? error.message : 'Unknown error' };
+    }
+  }
+
+  private async waitUntilStable(filePath: string): Promise<boolean> {
+    let previous = -1;
+    for (let check = 0; check < MAX_STABLE_CHECKS; check++) {
+      const stats = await fs.stat(filePath).catch(() => null);
+      if (!stats?.isFile()) return false;
+      if (stats.size === previous) return true;
+      previous = stats.size;
+      await new Promise(resolve => setTimeout(resolve, STABLE_CHECK_MS));
+    }
+    return true;
+  }
+
+  private async listFiles(dir: string, recursive: boolean): Promise<string[]> {
+    const entries = await fs.readdir(dir, { withFileTypes: true }).catch(() => []);
+    const files: string[] = [];
+    for (const entry of entries) {
+      const entryPath = path.join(dir, entry.name);
+      if (entry.isFile()) files.push(entryPath);
+      else if (recursive && entry.isDirectory() && !entry.name.startsWith('.')) {
+        files.push(...(await this.listFiles(entryPath, true)));
+      }
+    }
+    return files;
+  }
+
+  private isWithin(child: string, parent: string): boolean {
+    const relative = path.relative(parent, child);
+    return relative === '' || (!relative.startsWith('..') && !path.isAbsolute(relative));
+  }
+
+  private broadcast(event: 'detected' | 'imported' | 'duplicate' | 'failed', payload: WatchImportEvent): void {
+    const message = `event: ${event}\ndata: ${JSON.stringify(payload)}\n\n`;
+    this.eventClients.forEach(client => client.write(message));
+  }
+}
+
+export const folderWatcherService = new FolderWatcherService();
@@ -0,0 +1,357 @@
+import fs from "fs/promises";
+import path from "path";
+import zlib from "zlib";
+import type { ExifMetadata, PanoramaMetadata } from "@shared/schema";
+
+// Byte sizes of TIFF field types
+const TIFF_TYPE_SIZES: Record<number, number> = { 1: 1, 2: 1, 3: 2, 4: 4, 5: 8, 7: 1, 9: 4, 10: 8 };
+
+const TIFF_TAGS = {
+  imageDescription: 0x010e,
+  make: 0x010f,
+  model: 0x0110,
+  software: 0x0131,
+  dateTime: 0x0132,
+  artist: 0x013b,
+  exifIfd: 0x8769,
+  gpsIfd: 0x8825,
+  exposureTime: 0x829a,
+  fNumber: 0x829d,
+  iso: 0x8827,
+  dateTimeOriginal: 0x9003,
+  createDate: 0x9004,
+  subSecTimeOriginal: 0x9291,
+  focalLength: 0x920a,
+  lensModel: 0xa434,
+  gpsLatitudeRef: 0x0001,
+  gpsLatitude: 0x0002,
+  gpsLongitudeRef: 0x0003,
+  gpsLongitude: 0x0004,
+};
+
+/**
+ * Metadata extractors for containers the `exif` package can't read:
+ * PNG text/eXIf chunks, TIFF tags and WebP EXIF/XMP chunks.
+ */
+class FormatMetadataExtractor {
+  canExtract(filePath: string): boolean {
+    return ['.png', '.tif', '.tiff', '.dng', '.webp'].includes(path.extname(filePath).toLowerCase());
+  }
+
+  async extract(filePath: string): Promise<ExifMetadata> {
+    return this.extractAs(filePath, path.extname(filePath).toLowerCase());
+  }
+
+  /**
+   * Extract using an explicit extension, for staged uploads that have none
+   */
+  async extractAs(filePath: string, ext: string): Promise<ExifMetadata> {
+    const buffer = await fs.readFile(filePath);
+    switch (ext) {
+      case '.png':
+        return this.parsePng(buffer);
+      case '.tif':
+      case '.tiff':
+      case '.dng':
+        return this.parseTiff(buffer);
+      case '.webp':
+        return this.parseWebp(buffer);
+      default:
+        return {};
+    }
+  }
+
+  /**
+   * Detect a spherical panorama from the GPano namespace of the file's XMP packet.
+   * The packet is stored uncompressed in JPEG APP1, WebP and PNG iTXt, so it is
+   * located by scanning for its envelope rather than parsing each container.
+   */
+  async extractPanorama(filePath: string): Promise<PanoramaMetadata | null> {
+    const buffer = await fs.readFile(filePath);
+    const start = buffer.indexOf('<x:xmpmeta');
+    if (start < 0) return null;
+    const end = buffer.indexOf('</x:xmpmeta>', start);
+    if (end < 0) return null;
+    return this.parseGPano(buffer.toString('utf8', start, end));
+  }
+
+  /**
+   * PNG: tEXt/zTXt/iTXt keyword chunks, an optional eXIf chunk, and XMP in iTXt
+   */
+  parsePng(buffer: Buffer): ExifMetadata {
+    const signature = Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]);
+    if (buffer.length < 8 || !buffer.subarray(0, 8).equals(signature)) return {};
+
+    let metadata: ExifMetadata = {};
+    const text: Record<string, string> = {};
+    let offset = 8;
+
+    while (offset + 8 <= buffer.length) {
+      const length = buffer.readUInt32BE(offset);
+      const type = buffer.toString('latin1', offset + 4, offset + 8);
+      const data = buffer.subarray(offset + 8, offset + 8 + length);
+      offset += 12 + length;
+
+      try {
+        if (type === 'tEXt') {
+          const separator = data.indexOf(0);
+          text[data.toString('latin1', 0, separator)] = data.toString('latin1', separator + 1);
+        } else if (type === 'zTXt') {
+          const separator = data.indexOf(0);
+          text[data.toString('latin1', 0, separator)] = zlib.inflateSync(data.subarray(separator + 2)).toString('latin1');
+        } else if (type === 'iTXt') {
+          const entry = this.parseITxt(data);
+          if (entry) text[entry.keyword] = entry.text;
+        } else if (type === 'eXIf') {
+          metadata = { ...metadata, ...this.parseTiff(data) };
+        } else if (type === 'IEND') {
+          break;
+        }
+      } catch (error) {
+        console.log(`Skipping unreadable PNG ${type} chunk:`, error);
+      }
+    }
+
+    if (text['XML:com.adobe.xmp']) {
+      metadata = { ...this.parseXmp(text['XML:com.adobe.xmp']), ...metadata };
+    }
+
+    const creationTime = this.normalizeDate(text['Creation Time'] || text['creation-time'] || text['date:create']);
+    return this.compact({
+      ...metadata,
+      dateTimeOriginal: metadata.dateTimeOriginal || creationTime,
+      dateTime: metadata.dateTime || creationTime,
+      description: metadata.description || text['Description'] || text['Comment'],
+      title: metadata.title || text['Title'],
+      artist: metadata.artist || text['Author'],
+      software: metadata.software || text['Software'],
+    });
+  }
+
+  /**
+   * WebP: RIFF container with optional EXIF and XMP chunks
+   */
+  parseWebp(buffer: Buffer): ExifMetadata {
+    if (buffer.length < 12 || buffer.toString('latin1', 0, 4) !== 'RIFF' || buffer.toString('latin1', 8, 12) !== 'WEBP') {
+      return {};
+    }
+
+    let metadata: ExifMetadata = {};
+    let offset = 12;
+    while (offset + 8 <= buffer.length) {
+      const type = buffer.toString('latin1', offset, offset + 4);
+      const size = buffer.readUInt32LE(offset + 4);
+      const data = buffer.subarray(offset + 8, offset + 8 + size);
+      offset += 8 + size + (size % 2); // chunks are padded to even sizes
+
+      if (type === 'EXIF') {
+        // Some encoders keep the JPEG-style "Exif\0\0" prefix
+        const tiff = data.toString('latin1', 0, 6) === 'Exif\0\0' ? data.subarray(6) : data;
+        metadata = { ...metadata, ...this.parseTiff(tiff) };
+      } else if (type === 'XMP ') {
+        metadata = { ...this.parseXmp(data.toString('utf8')), ...metadata };
+      }
+    }
+    return this.compact(metadata);
+  }
+
+  /**
+   * TIFF structure (also the payload of EXIF blocks): IFD0 plus EXIF and GPS sub-IFDs
+   */
+  parseTiff(buffer: Buffer): ExifMetadata {
+    if (buffer.length < 8) return {};
+    const byteOrder = buffer.toString('latin1', 0, 2);
+    if (byteOrder !== 'II' && byteOrder !== 'MM') return {};
+    const le = byteOrder === 'II';
+
+    const ifd0 = this.readIfd(buffer, le ? buffer.readUInt32LE(4) : buffer.readUInt32BE(4), le);
+    const exifIfd = typeof ifd0.get(TIFF_TAGS.exifIfd) === 'number'
+      ? this.readIfd(buffer, ifd0.get(TIFF_TAGS.exifIfd), le)
+      : new Map<number, any>();
+    const gpsIfd = typeof ifd0.get(TIFF_TAGS.gpsIfd) === 'number'
+      ? this.readIfd(buffer, ifd0.get(TIFF_TAGS.gpsIfd), le)
+      : new Map<number, any>();
+
+    const make = ifd0.get(TIFF_TAGS.make);
+    const model = ifd0.get(TIFF_TAGS.model);
+    const exposure = exifIfd.get(TIFF_TAGS.exposureTime);
+    const fNumber = exifIfd.get(TIFF_TAGS.fNumber);
+    const focalLength = exifIfd.get(TIFF_TAGS.focalLength);
+    const iso = exifIfd.get(TIFF_TAGS.iso);
+
+    const metadata: ExifMetadata = {
+      camera: [make, model].filter(Boolean).join(' ') || undefined,
+      cameraMake: make || undefined,
+      cameraModel: model || undefined,
+      description: ifd0.get(TIFF_TAGS.imageDescription),
+      software: ifd0.get(TIFF_TAGS.software),
+      artist: ifd0.get(TIFF_TAGS.artist),
+      modifyDate: ifd0.get(TIFF_TAGS.dateTime),
+      dateTimeOriginal: exifIfd.get(TIFF_TAGS.dateTimeOriginal),
+      createDate: exifIfd.get(TIFF_TAGS.createDate),
+      subSecTimeOriginal: String(exifIfd.get(TIFF_TAGS.subSecTimeOriginal) ?? '').match(/^\d+/)?.[0],
+      lens: exifIfd.get(TIFF_TAGS.lensModel),
+      aperture: typeof fNumber === 'number' ? `f/${fNumber.toFixed(1)}` : undefined,
+      shutter: typeof exposure === 'number' && exposure > 0
+        ? (exposure >= 1 ? `${exposure}s` : `1/${Math.round(1 / exposure)}s`)
+        : undefined,
+      focalLength: typeof focalLength === 'number' ? `${focalLength}mm` : undefined,
+      iso: iso !== undefined ? String(Array.isArray(iso) ? iso[0] : iso) : undefined,
+    };
+    metadata.dateTime = metadata.dateTimeOriginal || metadata.createDate || metadata.modifyDate;
+
+    const latitude = gpsIfd.get(TIFF_TAGS.gpsLatitude);
+    const longitude = gpsIfd.get(TIFF_TAGS.gpsLongitude);
+    if (Array.isArray(latitude) && Array.isArray(longitude) && latitude.length === 3 && longitude.length === 3) {
+      const toDecimal = ([d, m, s]: number[], ref: string) =>
+        Math.round((d + m / 60 + s / 3600) * (ref === 'S' || ref === 'W' ? -1 : 1) * 1000000) / 1000000;
+      metadata.gpsLatitude = toDecimal(latitude, gpsIfd.get(TIFF_TAGS.gpsLatitudeRef));
+      metadata.gpsLongitude = toDecimal(longitude, gpsIfd.get(TIFF_TAGS.gpsLongitudeRef));
+    }
+
+    return this.compact(metadata);
+  }
+
+  /**
+   * Pull the commonly used fields out of an XMP packet
+   */
+  parseXmp(xml: string): ExifMetadata {
+    const field = (names: string[]): string | undefined => {
+      for (const name of names) {
+        // Attribute form: exif:DateTimeOriginal="..."
+        const attribute = xml.match(new RegExp(`${name}="([^"]*)"`));
+        if (attribute) return attribute[1];
+        // Element form, possibly wrapping an rdf:Alt/rdf:Seq list
+        const element = xml.match(new RegExp(`<${name}[^>]*>([\\s\\S]*?)</${name}>`));
+        if (element) {
+          const item = element[1].match(/<rdf:li[^>]*>([\s\S]*?)<\/rdf:li>/);
+          return (item ? item[1] : element[1]).trim() || undefined;
+        }
+      }
+      return undefined;
+    };
+
+    const dateTimeOriginal = this.normalizeDate(field(['exif:DateTimeOriginal', 'photoshop:DateCreated', 'xmp:CreateDate']));
+    return this.compact({
+      dateTimeOriginal,
+      createDate: this.normalizeDate(field(['xmp:CreateDate'])),
+      modifyDate: this.normalizeDate(field(['xmp:ModifyDate'])),
+      dateTime: dateTimeOriginal,
+      description: field(['dc:description']),
+      title: field(['dc:title']),
+      artist: field(['dc:creator']),
+      software: field(['xmp:CreatorTool']),
+    });
+  }
+
+  parseGPano(xml: string): PanoramaMetadata | null {
+    const field = (name: string): string | undefined => {
+      const attribute = xml.match(new RegExp(`GPano:${name}="([^"]*)"`));
+      if (attribute) return attribute[1];
+      const element = xml.match(new RegExp(`<GPano:${name}>([^<]*)</GPano:${name}>`));
+      return element ? element[1].trim() : undefined;
+    };
+    const number = (name: string): number | undefined => {
+      const value = parseFloat(field(name) ?? '');
+      return isNaN(value) ? undefined : value;
+    };
+
+    const projectionType = field('ProjectionType');
+    if (!projectionType) return null;
+
+    return Object.fromEntries(Object.entries({
+      projectionType,
+      fullPanoWidth: number('FullPanoWidthPixels'),
+      fullPanoHeight: number('FullPanoHeightPixels'),
+      croppedAreaWidth: number('CroppedAreaImageWidthPixels'),
+      croppedAreaHeight: number('CroppedAreaImageHeightPixels'),
+      croppedAreaLeft: number('CroppedAreaLeftPixels'),
+      croppedAreaTop: number('CroppedAreaTopPixels'),
+      poseHeadingDegrees: number('PoseHeadingDegrees'),
+      initialViewHeadingDegrees: number('InitialViewHeadingDegrees'),
+      initialViewPitchDegrees: number('InitialViewPitchDegrees'),
+      initialHorizontalFovDegrees: number('InitialHorizontalFOVDegrees'),
+    }).filter(([, value]) => value !== undefined)) as unknown as PanoramaMetadata;
+  }
+
+  private parseITxt(data: Buffer): { keyword: string; text: string } | null {
+    const keywordEnd = data.indexOf(0);
+    if (keywordEnd < 0) return null;
+    const keyword = data.toString('latin1', 0, keywordEnd);
+    const compressed = data[keywordEnd + 1] === 1;
+    const languageEnd = data.indexOf(0, keywordEnd + 3);
+    const translatedEnd = data.indexOf(0, languageEnd + 1);
+    const payload = data.subarray(translatedEnd + 1);
+    return { keyword, text: (compressed ? zlib.inflateSync(payload) : payload).toString('utf8') };
+  }
+
+  private readIfd(buffer: Buffer, offset: number, le: boolean): Map<number, any> {
+    const tags = new Map<number, any>();
+    const r16 = (o: number) => (le ? buffer.readUInt16LE(o) : buffer.readUInt16BE(o));
+    const r32 = (o: number) => (le ? buffer.readUInt32LE(o) : buffer.readUInt32BE(o));
+    const s32 = (o: number) => (le ? buffer.readInt32LE(o) : buffer.readInt32BE(o));
+    if (offset + 2 > buffer.length) return tags;
+
+    const count = r16(offset);
+    for (let i = 0; i < count; i++) {
+      const entry = offset + 2 + i * 12;
+      if (entry + 12 > buffer.length) break;
+
+      const tag = r16(entry);
+      const type = r16(entry + 2);
+      const n = r32(entry + 4);
+      const size = (TIFF_TYPE_SIZES[type] ?? 0) * n;
+      if (size === 0) continue;
+      const valueOffset = size <= 4 ? entry + 8 : r32(entry + 8);
+      if (valueOffset + size > buffer.length) continue;
+
+      let values: any[] = [];
+      switch (type) {
+        case 2:
+          tags.set(tag, buffer.toString('latin1', valueOffset, valueOffset + n).replace(/\0+$/, '').trim() || undefined);
+          continue;
+        case 1:
+        case 7:
+          values = Array.from(buffer.subarray(valueOffset, valueOffset + n));
+          break;
+        case 3:
+          for (let j = 0; j < n; j++) values.push(r16(valueOffset + j * 2));
+          break;
+        case 4:
+          for (let j = 0; j < n; j++) values.push(r32(valueOffset + j * 4));
+          break;
+        case 9:
+          for (let j = 0; j < n; j++) values.push(s32(valueOffset + j * 4));
+          break;
+        case 5:
+        case 10:
+          for (let j = 0; j < n; j++) {
+            const read = type === 5 ? r32 : s32;
+            const denominator = read(valueOffset + j * 8 + 4);
+            values.push(denominator ? read(valueOffset + j * 8) / denominator : 0);
+          }
+          break;
+      }
+      tags.set(tag, values.length === 1 ? values[0] : values);
+    }
+    return tags;
+  }
+
+  // Store dates in the EXIF "YYYY:MM:DD HH:MM:SS" form used by the rest of the metadata
+  private normalizeDate(value?: string): string | undefined {
+    if (!value) return undefined;
+    if (/^\d{4}:\d{2}:\d{2}/.test(value)) return value;
+    const date = new Date(value);
+    if (isNaN(date.getTime())) return undefined;
+    const pad = (n: number) => String(n).padStart(2, '0');
+    return `${date.getFullYear()}:${pad(date.getMonth() + 1)}:${pad(date.getDate())} ${pad(date.getHours())}:${pad(date.getMinutes())}:${pad(date.getSeconds())}`;
+  }
+
+  private compact(metadata: ExifMetadata): ExifMetadata {
+    return Object.fromEntries(
+      Object.entries(metadata).filter(([, value]) => value !== undefined && value !== '')
+    ) as ExifMetadata;
+  }
+}
+
+export const formatMetadataExtractor = new FormatMetadataExtractor();
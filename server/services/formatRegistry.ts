@@ -0,0 +1,131 @@
+import path from "path";
+import { storage } from "../storage";
+
+export type FormatKind = 'image' | 'video' | 'raw';
+export type FormatCapability = 'thumbnail' | 'metadata' | 'editing';
+
+export interface FormatDefinition {
+  extension: string;
+  mimeTypes: string[];
+  kind: FormatKind;
+  enabled: boolean; // importable
+  capabilities: Record<FormatCapability, boolean>;
+}
+
+// Overrides stored in the `supported_formats` setting as JSON
+type FormatOverride = Partial<Omit<FormatDefinition, 'capabilities'>> & {
+  extension: string;
+  capabilities?: Partial<Record<FormatCapability, boolean>>;
+};
+
+export const SUPPORTED_FORMATS_SETTING = 'supported_formats';
+
+const DEFAULT_FORMATS: FormatDefinition[] = [
+  { extension: 'jpg', mimeTypes: ['image/jpeg'], kind: 'image', enabled: true, capabilities: { thumbnail: true, metadata: true, editing: true } },
+  { extension: 'jpeg', mimeTypes: ['image/jpeg'], kind: 'image', enabled: true, capabilities: { thumbnail: true, metadata: true, editing: true } },
+  { extension: 'png', mimeTypes: ['image/png'], kind: 'image', enabled: true, capabilities: { thumbnail: true, metadata: true, editing: true } },
+  { extension: 'tiff', mimeTypes: ['image/tiff'], kind: 'image', enabled: true, capabilities: { thumbnail: true, metadata: true, editing: true } },
+  { extension: 'tif', mimeTypes: ['image/tiff'], kind: 'image', enabled: true, capabilities: { thumbnail: true, metadata: true, editing: true } },
+  { extension: 'webp', mimeTypes: ['image/webp'], kind: 'image', enabled: false, capabilities: { thumbnail: true, metadata: true, editing: true } },
+  { extension: 'gif', mimeTypes: ['image/gif'], kind: 'image', enabled: false, capabilities: { thumbnail: true, metadata: false, editing: false } },
+  { extension: 'heic', mimeTypes: ['image/heic', 'image/heif'], kind: 'image', enabled: false, capabilities: { thumbnail: false, metadata: false, editing: false } },
+  { extension: 'mp4', mimeTypes: ['video/mp4'], kind: 'video', enabled: true, capabilities: { thumbnail: false, metadata: false, editing: false } },
+  { extension: 'mov', mimeTypes: ['video/quicktime', 'video/mov'], kind: 'video', enabled: true, capabilities: { thumbnail: false, metadata: false, editing: false } },
+  { extension: 'avi', mimeTypes: ['video/x-msvideo', 'video/avi'], kind: 'video', enabled: true, capabilities: { thumbnail: false, metadata: false, editing: false } },
+  { extension: 'dng', mimeTypes: ['image/x-adobe-dng'], kind: 'raw', enabled: false, capabilities: { thumbnail: false, metadata: true, editing: false } },
+  { extension: 'cr2', mimeTypes: ['image/x-canon-cr2'], kind: 'raw', enabled: false, capabilities: { thumbnail: false, metadata: false, editing: false } },
+  { extension: 'nef', mimeTypes: ['image/x-nikon-nef'], kind: 'raw', enabled: false, capabilities: { thumbnail: false, metadata: false, editing: false } },
+  { extension: 'arw', mimeTypes: ['image/x-sony-arw'], kind: 'raw', enabled: false, capabilities: { thumbnail: false, metadata: false, editing: false } },
+];
+
+class FormatRegistry {
+  private formats = new Map<string, FormatDefinition>(
+    DEFAULT_FORMATS.map(format => [format.extension, format])
+  );
+
+  /**
+   * Reload the registry from defaults plus the overrides stored in settings
+   */
+  async refresh(): Promise<void> {
+    const formats = new Map<string, FormatDefinition>(
+      DEFAULT_FORMATS.map(format => [format.extension, { ...format, capabilities: { ...format.capabilities } }])
+    );
+
+    try {
+      const setting = await storage.getSettingByKey(SUPPORTED_FORMATS_SETTING);
+      if (setting) {
+        const overrides = JSON.parse(setting.value) as FormatOverride[];
+        for (const override of overrides) {
+          const extension = override.extension.toLowerCase().replace(/^\./, '');
+          const base = formats.get(extension);
+          if (!base && (!override.mimeTypes || !override.kind)) {
+            console.warn(`Ignoring format override for unknown extension without mimeTypes/kind: ${extension}`);
+            continue;
+          }
+          formats.set(extension, {
+            extension,
+            mimeTypes: override.mimeTypes ?? base!.mimeTypes,
+            kind: override.kind ?? base!.kind,
+            enabled: override.enabled ?? base?.enabled ?? true,
+            capabilities: {
+              thumbnail: false,
+              metadata: false,
+              editing: false,
+              ...base?.capabilities,
+              ...override.capabilities,
+            },
+          });
+        }
+      }
+    } catch (error) {
+      console.error("Failed to load format overrides, using defaults:", error);
+    }
+
+    this.formats = formats;
+  }
+
+  getFormats(): FormatDefinition[] {
+    return Array.from(this.formats.values());
+  }
+
+  getFormat(filename: string): FormatDefinition | undefined {
+    return this.formats.get(path.extname(filename).toLowerCase().replace(/^\./, ''));
+  }
+
+  /**
+   * Whether a file may be imported. The extension decides; browsers often send
+   * generic mime types for raw files.
+   */
+  isImportable(filename: string): boolean {
+    return this.getFormat(filename)?.enabled ?? false;
+  }
+
+  supports(filename: string, capability: FormatCapability): boolean {
+    return this.getFormat(filename)?.capabilities[capability] ?? false;
+  }
+
+  getMimeType(filename: string): string | undefined {
+    return this.getFormat(filename)?.mimeTypes[0];
+  }
+
+  /**
+   * File-dialog filters for the importable formats, grouped by kind
+   */
+  getDialogFilters(): Array<{ name: string; extensions: string[] }> {
+    const names: Record<FormatKind, string> = { image: 'Images', video: 'Videos', raw: 'RAW Photos' };
+    const enabled = this.getFormats().filter(format => format.enabled);
+    const filters = (Object.keys(names) as FormatKind[])
+      .map(kind => ({
+        name: names[kind],
+        extensions: enabled.filter(format => format.kind === kind).map(format => format.extension),
+      }))
+      .filter(filter => filter.extensions.length > 0);
+
+    return [
+      { name: 'All Supported', extensions: enabled.map(format => format.extension) },
+      ...filters,
+    ];
+  }
+}
+
+export const formatRegistry = new FormatRegistry();
@@ -0,0 +1,143 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { slideshowService } from "./slideshow";
+import { privacyService } from "./privacy";
+import { volumeStatusService } from "./volumeStatus";
+import { displayImage } from "../utils/colorProfile";
+import type { SearchFilters } from "./advancedSearch";
+
+export const FRAME_DISPLAY_SETTING = 'frame_display';
+// The frame shares one slideshow session, so every screen shows the same photo
+const FRAME_SESSION = 'frame-display';
+// Photos skipped because their original is offline or unreadable before giving up on a slot
+const MAX_ATTEMPTS = 5;
+
+export const frameDisplaySettingsSchema = z.object({
+  enabled: z.boolean(),
+  intervalSeconds: z.number().int().min(10).max(24 * 60 * 60),
+  filters: z.record(z.string(), z.any()).nullable(), // SearchFilters, as the slideshow takes them
+  maxWidth: z.number().int().min(320).max(7680),
+  maxHeight: z.number().int().min(240).max(4320),
+  expandStacks: z.boolean(),
+});
+
+export type FrameDisplaySettings = z.infer<typeof frameDisplaySettingsSchema>;
+
+const DEFAULT_SETTINGS: FrameDisplaySettings = {
+  enabled: false,
+  intervalSeconds: 60,
+  filters: { tier: 'gold' },
+  maxWidth: 1920,
+  maxHeight: 1080,
+  expandStacks: false,
+};
+
+export interface FrameImage {
+  photoId: string;
+  image: Buffer;
+  slotStart: Date;
+  expiresAt: Date;
+}
+
+export class FrameDisplayError extends Error {}
+
+/**
+ * The photo frame served over the local network at /frame: one photo at a
+ * time, picked by the slideshow's weighted shuffle and changed on a fixed
+ * schedule. The schedule is cut into slots of the configured interval; each
+ * slot's photo is picked and resized once and then served to every screen
+ * until the slot ends, so any browser can be pointed at it and cache it.
+ */
+class FrameDisplayService {
+  private current: FrameImage | null = null;
+  private pending: { slot: number; image: Promise<FrameImage> } | null = null;
+
+  async getSettings(): Promise<FrameDisplaySettings> {
+    const setting = await storage.getSettingByKey(FRAME_DISPLAY_SETTING);
+    if (!setting?.value) return DEFAULT_SETTINGS;
+    try {
+      const parsed = frameDisplaySettingsSchema.partial().safeParse(JSON.parse(setting.value));
+      return parsed.success ? { ...DEFAULT_SETTINGS, ...parsed.data } : DEFAULT_SETTINGS;
+    } catch {
+      return DEFAULT_SETTINGS;
+    }
+  }
+
+  async updateSettings(updates: Partial<FrameDisplaySettings>): Promise<FrameDisplaySettings> {
+    const settings = { ...(await this.getSettings()), ...updates };
+    const value = JSON.stringify(settings);
+    if (await storage.getSettingByKey(FRAME_DISPLAY_SETTING)) {
+      await storage.updateSetting(FRAME_DISPLAY_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: FRAME_DISPLAY_SETTING,
+        value,
+        category: 'sharing',
+        description: 'Photo frame served at /frame: schedule, photo filters and image size',
+      });
+    }
+    // New filters or size take effect straight away rather than at the end of the slot
+    this.current = null;
+    this.pending = null;
+    return settings;
+  }
+
+  /**
+   * The photo for the current slot, picking and rendering it if the slot has just begun
+   */
+  async currentImage(): Promise<FrameImage> {
+    const settings = await this.getSettings();
+    if (!settings.enabled) {
+      throw new FrameDisplayError('The photo frame is turned off');
+    }
+
+    const intervalMs = settings.intervalSeconds * 1000;
+    const slot = Math.floor(Date.now() / intervalMs);
+    if (this.current && this.current.slotStart.getTime() === slot * intervalMs) {
+      return this.current;
+    }
+    // Screens asking at the same moment share one pick
+    if (this.pending?.slot === slot) {
+      return this.pending.image;
+    }
+    const image = this.render(settings, slot, intervalMs);
+    this.pending = { slot, image };
+    image.then(result => {
+      if (this.pending?.slot === slot) this.current = result;
+    }, () => {
+      if (this.pending?.slot === slot) this.pending = null;
+    });
+    return image;
+  }
+
+  private async render(settings: FrameDisplaySettings, slot: number, intervalMs: number): Promise<FrameImage> {
+    // A frame is a shared screen; photos of people excluded from shares never appear
+    const restricted = await privacyService.getRestrictedPhotos('share');
+    const filters = (settings.filters as SearchFilters | null) ?? {};
+
+    for (let attempt = 0; attempt < MAX_ATTEMPTS; attempt++) {
+      const photo = await slideshowService.nextPhoto(FRAME_SESSION, filters, undefined, settings.expandStacks, restricted.ids);
+      if (!photo) break;
+
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) continue;
+      try {
+        const image = await displayImage(original.path)
+          .resize(settings.maxWidth, settings.maxHeight, { fit: 'inside', withoutEnlargement: true })
+          .jpeg({ quality: 85 })
+          .toBuffer();
+        return {
+          photoId: photo.id,
+          image,
+          slotStart: new Date(slot * intervalMs),
+          expiresAt: new Date((slot + 1) * intervalMs),
+        };
+      } catch (error) {
+        console.error(`Failed to render ${photo.filePath} for the photo frame:`, error);
+      }
+    }
+    throw new FrameDisplayError('No photos match the photo frame filters');
+  }
+}
+
+export const frameDisplayService = new FrameDisplayService();
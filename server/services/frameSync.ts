@@ -0,0 +1,197 @@
+import fs from "fs/promises";
+import path from "path";
+import { storage } from "../storage";
+import { advancedSearch, type SearchFilters } from "./advancedSearch";
+import { formatRegistry } from "./formatRegistry";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import type { CancellationToken } from "./operations";
+import type { FrameTarget } from "@shared/schema";
+import { volumeStatusService } from "./volumeStatus";
+import { displayImage } from "../utils/colorProfile";
+
+// Manifest written into each destination so we only ever touch files we published
+const MANIFEST_FILENAME = '.pictallion-frame.json';
+const SCHEDULER_INTERVAL_MS = 60 * 60 * 1000;
+// The only file names sync writes: "<photoId>.jpg"
+const PUBLISHED_FILENAME = /^([A-Za-z0-9-]+)\.jpg$/;
+
+interface FrameManifest {
+  targetId: string;
+  files: Record<string, string>; // photoId -> filename
+}
+
+export interface FrameSyncResult {
+  added: number;
+  removed: number;
+  kept: number;
+  failed: number;
+  offline: number; // originals on a disconnected volume, tried again on the next sync
+  cancelled: boolean;
+}
+
+class FrameSyncService {
+  private schedulerHandle: NodeJS.Timeout | null = null;
+  private syncing = new Set<string>();
+
+  /**
+   * Refresh a frame target with a new random selection. Photos that stay in the
+   * selection are left untouched; only new photos are resized and written.
+   */
+  async syncTarget(target: FrameTarget, token?: CancellationToken): Promise<FrameSyncResult> {
+    if (this.syncing.has(target.id)) {
+      throw new Error(`Frame target ${target.name} is already syncing`);
+    }
+    this.syncing.add(target.id);
+
+    try {
+      await fs.mkdir(target.destinationPath, { recursive: true });
+      const manifest = await this.readManifest(target);
+
+      const filters = (target.filters as SearchFilters | null) ?? { tier: 'gold' };
+      const matchingIds = new Set(await advancedSearch.findMatchingPhotoIds(filters));
+      // Frames are shared displays; photos of people excluded from shares never go out
+      const restricted = await privacyService.getRestrictedPhotos('share');
+      const candidates = (await storage.getAllFileVersions())
+        .filter(photo => matchingIds.has(photo.id) && !restricted.ids.has(photo.id) && formatRegistry.supports(photo.filePath, 'thumbnail'));
+      const selection = this.shuffle(candidates).slice(0, target.photoCount);
+      const selectedIds = new Set(selection.map(photo => photo.id));
+
+      const result: FrameSyncResult = { added: 0, removed: 0, kept: 0, failed: 0, offline: 0, cancelled: false };
+
+      const destination = path.resolve(target.destinationPath);
+      for (const [photoId, filename] of Object.entries(manifest.files)) {
+        if (selectedIds.has(photoId)) continue;
+        const filePath = path.resolve(destination, filename);
+        // readManifest only keeps our own names, but never delete outside the frame folder
+        if (path.dirname(filePath) === destination) {
+          await fs.rm(filePath, { force: true });
+        }
+        delete manifest.files[photoId];
+        result.removed++;
+      }
+
+      for (const photo of selection) {
+        // On cancel, keep what was published so far; the manifest only lists complete files
+        if (token?.isCancelled) {
+          result.cancelled = true;
+          break;
+        }
+        if (manifest.files[photo.id]) {
+          result.kept++;
+          continue;
+        }
+
+        const original = await volumeStatusService.locateOriginal(photo);
+        if (!original.available) {
+          result.offline++;
+          continue;
+        }
+
+        const filename = `${photo.id}.jpg`;
+        const outputPath = path.join(target.destinationPath, filename);
+        try {
+          await displayImage(original.path)
+            .resize(target.maxWidth, target.maxHeight, { fit: 'inside', withoutEnlargement: true })
+            .jpeg({ quality: 90 })
+            .toFile(`${outputPath}.partial`);
+          await fs.rename(`${outputPath}.partial`, outputPath);
+          manifest.files[photo.id] = filename;
+          result.added++;
+          await provenanceService.record(photo, 'SHARED', `Published to frame ${target.name}`, {
+            frameTargetId: target.id,
+            destination: target.destinationPath,
+          });
+        } catch (error) {
+          await fs.rm(`${outputPath}.partial`, { force: true });
+          console.error(`Failed to publish ${photo.filePath} to frame ${target.name}:`, error);
+          result.failed++;
+        }
+      }
+
+      await this.writeManifest(target, manifest);
+      if (!result.cancelled) {
+        await storage.updateFrameTarget(target.id, { lastSyncedAt: new Date() });
+      }
+
+      console.log(`Synced frame target "${target.name}": ${result.added} added, ${result.removed} removed, ${result.kept} kept, ${result.offline} offline`);
+      return result;
+    } finally {
+      this.syncing.delete(target.id);
+    }
+  }
+
+  /**
+   * Periodically refresh enabled targets whose refresh interval has elapsed
+   */
+  startScheduler(): void {
+    if (this.schedulerHandle) return;
+    this.schedulerHandle = setInterval(() => {
+      this.syncDueTargets().catch(error => console.error("Frame target scheduler failed:", error));
+    }, SCHEDULER_INTERVAL_MS);
+    this.schedulerHandle.unref();
+  }
+
+  stopScheduler(): void {
+    if (this.schedulerHandle) {
+      clearInterval(this.schedulerHandle);
+      this.schedulerHandle = null;
+    }
+  }
+
+  private async syncDueTargets(): Promise<void> {
+    const now = Date.now();
+    for (const target of await storage.getFrameTargets()) {
+      if (!target.isEnabled) continue;
+      const intervalMs = target.refreshIntervalDays * 24 * 60 * 60 * 1000;
+      if (target.lastSyncedAt && now - target.lastSyncedAt.getTime() < intervalMs) continue;
+
+      try {
+        await this.syncTarget(target);
+      } catch (error) {
+        console.error(`Failed to sync frame target ${target.name}:`, error);
+      }
+    }
+  }
+
+  /**
+   * The destination's manifest, keeping only entries that name a file sync
+   * could have written for that photo; the manifest sits on a shared drive
+   * and anything else in it is not ours to delete
+   */
+  private async readManifest(target: FrameTarget): Promise<FrameManifest> {
+    try {
+      const content = await fs.readFile(path.join(target.destinationPath, MANIFEST_FILENAME), 'utf-8');
+      const manifest = JSON.parse(content) as FrameManifest;
+      if (manifest.targetId === target.id && manifest.files && typeof manifest.files === 'object') {
+        const files: Record<string, string> = {};
+        for (const [photoId, filename] of Object.entries(manifest.files)) {
+          if (typeof filename === 'string' && PUBLISHED_FILENAME.exec(filename)?.[1] === photoId) {
+            files[photoId] = filename;
+          }
+        }
+        return { targetId: target.id, files };
+      }
+    } catch {
+      // No manifest yet
+    }
+    return { targetId: target.id, files: {} };
+  }
+
+  private async writeManifest(target: FrameTarget, manifest: FrameManifest): Promise<void> {
+    const manifestPath = path.join(target.destinationPath, MANIFEST_FILENAME);
+    await fs.writeFile(`${manifestPath}.partial`, JSON.stringify(manifest, null, 2));
+    await fs.rename(`${manifestPath}.partial`, manifestPath);
+  }
+
+  private shuffle<T>(items: T[]): T[] {
+    const result = [...items];
+    for (let i = result.length - 1; i > 0; i--) {
+      const j = Math.floor(Math.random() * (i + 1));
+      [result[i], result[j]] = [result[j], result[i]];
+    }
+    return result;
+  }
+}
+
+export const frameSyncService = new FrameSyncService();
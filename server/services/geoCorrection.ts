@@ -0,0 +1,199 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { photoMetadataEditor } from "./photoMetadataEditor";
+import { PhotoLockedError } from "./photoLock";
+import { locationClusteringService } from "./location-clustering";
+import { counterConsistencyService } from "./counterConsistency";
+import { getEffectiveDate } from "../utils/photoDates";
+import type { FileVersion } from "@shared/schema";
+
+export const setPhotoLocationSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  latitude: z.number().min(-90).max(90).optional(),
+  longitude: z.number().min(-180).max(180).optional(),
+  locationId: z.string().optional(), // a saved place instead of coordinates
+  writeToFile: z.boolean().optional(),
+}).refine(input => (input.latitude !== undefined && input.longitude !== undefined) !== (input.locationId !== undefined), {
+  message: 'Give either latitude and longitude or a locationId',
+});
+
+export const locationOutlierQuerySchema = z.object({
+  windowMinutes: z.coerce.number().int().min(1).max(7 * 24 * 60).default(120),
+  minDistanceKm: z.coerce.number().min(1).default(50),
+});
+
+export const fixLocationOutliersSchema = locationOutlierQuerySchema.extend({
+  photoIds: z.array(z.string()).min(1).optional(), // all outliers when left out
+  writeToFile: z.boolean().optional(),
+});
+
+export type SetPhotoLocationInput = z.infer<typeof setPhotoLocationSchema>;
+export type LocationOutlierQuery = z.infer<typeof locationOutlierQuerySchema>;
+
+export interface LocationOutlier {
+  photoId: string;
+  filePath: string;
+  camera: string | null;
+  takenAt: Date;
+  latitude: number;
+  longitude: number;
+  distanceKm: number; // from where its neighbours were
+  suggested: { latitude: number; longitude: number };
+  neighborIds: string[];
+}
+
+export interface SetPhotoLocationResult {
+  updated: string[];
+  skipped: Array<{ photoId: string; reason: string }>;
+}
+
+export class GeoCorrectionError extends Error {}
+
+// Neighbours looked at on each side of a photo, and how many it needs to be judged
+const NEIGHBORS_PER_SIDE = 3;
+const MIN_NEIGHBORS = 2;
+// A photo is off when it is this many times further from its neighbours than they are from each other
+const SPREAD_FACTOR = 5;
+
+interface Positioned {
+  photo: FileVersion;
+  time: number;
+  latitude: number;
+  longitude: number;
+}
+
+/**
+ * Fixes for wrong photo positions: setting the position of many photos at
+ * once, from coordinates or a saved place, and finding photos whose GPS is
+ * far from where the photos taken just before and after them were (a
+ * camera that had not found its fix yet, or a glitch), with a one-call fix
+ * that moves them to their neighbours' average position.
+ */
+class GeoCorrectionService {
+  async setPhotoLocation(input: SetPhotoLocationInput): Promise<SetPhotoLocationResult> {
+    let latitude = input.latitude;
+    let longitude = input.longitude;
+    let placeName: string | null = null;
+    if (input.locationId) {
+      const place = await storage.getLocation(input.locationId);
+      if (!place) {
+        throw new GeoCorrectionError('Location not found');
+      }
+      latitude = parseFloat(place.latitude);
+      longitude = parseFloat(place.longitude);
+      placeName = place.name;
+    }
+    if (latitude === undefined || longitude === undefined || !Number.isFinite(latitude) || !Number.isFinite(longitude)) {
+      throw new GeoCorrectionError('The location has no usable coordinates');
+    }
+
+    const result = await this.apply(Array.from(new Set(input.photoIds)).map(photoId => ({ photoId, latitude: latitude!, longitude: longitude! })), input.writeToFile);
+    // Photos placed at a saved place are named after it rather than the geocoder's guess
+    if (placeName) {
+      for (const photoId of result.updated) {
+        await storage.updateFileVersion(photoId, { location: placeName });
+      }
+    }
+    return result;
+  }
+
+  /**
+   * Photos whose position disagrees with the photos taken around the same
+   * time by the same camera, while those agree with each other
+   */
+  async findLocationOutliers(query: LocationOutlierQuery): Promise<LocationOutlier[]> {
+    const windowMs = query.windowMinutes * 60 * 1000;
+    const byCamera = new Map<string, Positioned[]>();
+    (await storage.getAllFileVersions()).forEach(photo => {
+      if (photo.gpsLatitude === null || photo.gpsLongitude === null) return;
+      if (photo.gpsLatitude === 0 && photo.gpsLongitude === 0) return; // no fix, not a position
+      const key = photo.camera ?? '';
+      if (!byCamera.has(key)) byCamera.set(key, []);
+      byCamera.get(key)!.push({ photo, time: getEffectiveDate(photo).getTime(), latitude: photo.gpsLatitude, longitude: photo.gpsLongitude });
+    });
+
+    const outliers: LocationOutlier[] = [];
+    byCamera.forEach(series => {
+      series.sort((a, b) => a.time - b.time);
+      series.forEach((current, index) => {
+        const neighbors = [
+          ...series.slice(Math.max(0, index - NEIGHBORS_PER_SIDE), index),
+          ...series.slice(index + 1, index + 1 + NEIGHBORS_PER_SIDE),
+        ].filter(neighbor => Math.abs(neighbor.time - current.time) <= windowMs);
+        if (neighbors.length < MIN_NEIGHBORS) return;
+
+        const center = {
+          latitude: neighbors.reduce((sum, neighbor) => sum + neighbor.latitude, 0) / neighbors.length,
+          longitude: neighbors.reduce((sum, neighbor) => sum + neighbor.longitude, 0) / neighbors.length,
+        };
+        const spreadKm = Math.max(...neighbors.map(neighbor => this.distanceKm(neighbor, center)));
+        const distanceKm = this.distanceKm(current, center);
+        if (distanceKm < query.minDistanceKm || distanceKm < spreadKm * SPREAD_FACTOR) return;
+
+        outliers.push({
+          photoId: current.photo.id,
+          filePath: current.photo.filePath,
+          camera: current.photo.camera,
+          takenAt: new Date(current.time),
+          latitude: current.latitude,
+          longitude: current.longitude,
+          distanceKm: Math.round(distanceKm * 10) / 10,
+          suggested: { latitude: Number(center.latitude.toFixed(6)), longitude: Number(center.longitude.toFixed(6)) },
+          neighborIds: neighbors.map(neighbor => neighbor.photo.id),
+        });
+      });
+    });
+    return outliers.sort((a, b) => b.distanceKm - a.distanceKm);
+  }
+
+  /**
+   * Move outliers to their neighbours' average position
+   */
+  async fixLocationOutliers(query: LocationOutlierQuery, photoIds?: string[], writeToFile?: boolean): Promise<SetPhotoLocationResult> {
+    const wanted = photoIds ? new Set(photoIds) : null;
+    const outliers = (await this.findLocationOutliers(query)).filter(outlier => !wanted || wanted.has(outlier.photoId));
+    const result = await this.apply(
+      outliers.map(outlier => ({ photoId: outlier.photoId, ...outlier.suggested })),
+      writeToFile
+    );
+    // Photos asked for that are no longer outliers are left as they are
+    if (wanted) {
+      const found = new Set(outliers.map(outlier => outlier.photoId));
+      wanted.forEach(photoId => {
+        if (!found.has(photoId)) result.skipped.push({ photoId, reason: 'Not a location outlier' });
+      });
+    }
+    return result;
+  }
+
+  private async apply(
+    positions: Array<{ photoId: string; latitude: number; longitude: number }>,
+    writeToFile?: boolean
+  ): Promise<SetPhotoLocationResult> {
+    const result: SetPhotoLocationResult = { updated: [], skipped: [] };
+    for (const { photoId, latitude, longitude } of positions) {
+      try {
+        const edited = await photoMetadataEditor.setGps(photoId, { latitude, longitude, writeToFile });
+        if (edited) {
+          result.updated.push(photoId);
+        } else {
+          result.skipped.push({ photoId, reason: 'Photo not found' });
+        }
+      } catch (error) {
+        if (!(error instanceof PhotoLockedError)) {
+          console.error(`Failed to set location of photo ${photoId}:`, error);
+        }
+        result.skipped.push({ photoId, reason: error instanceof Error ? error.message : 'Failed to set location' });
+      }
+    }
+    // Saved places count the photos near them
+    if (result.updated.length > 0) counterConsistencyService.scheduleCheck();
+    return result;
+  }
+
+  private distanceKm(a: { latitude: number; longitude: number }, b: { latitude: number; longitude: number }): number {
+    return locationClusteringService.calculateDistance(a.latitude, a.longitude, b.latitude, b.longitude) / 1000;
+  }
+}
+
+export const geoCorrectionService = new GeoCorrectionService();
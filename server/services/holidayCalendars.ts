@@ -0,0 +1,225 @@
+import fs from "fs/promises";
+import path from "path";
+import { storage } from "../storage";
+import { getLibraryRoot, libraryPath } from "../utils/libraryPaths";
+import { parseIcs, setIcsCalendarName } from "../utils/icsCalendar";
+import { daysFromOccurrence } from "../utils/eventRecurrence";
+import type { EventRecurrenceRule } from "@shared/schema";
+
+export const ENABLED_HOLIDAYS_SETTING = 'enabled_holidays';
+export const DEFAULT_HOLIDAY_SETS = ['US'];
+
+const CUSTOM_PREFIX = 'custom-';
+
+export interface HolidayDefinition {
+  name: string;
+  calendar: string; // code of the calendar it came from
+  start: Date;
+  durationDays: number;
+  recurrenceRule: EventRecurrenceRule | null;
+  extraDates: Date[]; // one-off dates, e.g. Easter for each year
+}
+
+export interface HolidayCalendarSummary {
+  code: string;
+  name: string;
+  source: 'bundled' | 'imported';
+  count: number;
+}
+
+interface LoadedCalendar extends HolidayCalendarSummary {
+  holidays: HolidayDefinition[];
+}
+
+// The import cannot be used; nothing was saved
+export class HolidayCalendarError extends Error {}
+
+/**
+ * Holiday sets for event detection: ICS calendars bundled per locale plus
+ * any the user imported into the library's calendars folder. Which sets are
+ * active is stored in the enabled_holidays setting.
+ */
+class HolidayCalendarService {
+  private bundled: LoadedCalendar[] | null = null;
+  private imported: { root: string; calendars: LoadedCalendar[] } | null = null;
+
+  async listCalendars(): Promise<HolidayCalendarSummary[]> {
+    const calendars = await this.loadAll();
+    return calendars.map(({ code, name, source, count }) => ({ code, name, source, count }));
+  }
+
+  async getEnabledCodes(): Promise<string[]> {
+    const setting = await storage.getSettingByKey(ENABLED_HOLIDAYS_SETTING);
+    if (!setting?.value) return DEFAULT_HOLIDAY_SETS;
+    try {
+      const codes = JSON.parse(setting.value);
+      return Array.isArray(codes) ? codes : DEFAULT_HOLIDAY_SETS;
+    } catch {
+      return DEFAULT_HOLIDAY_SETS;
+    }
+  }
+
+  async getEnabledHolidays(): Promise<HolidayDefinition[]> {
+    const enabled = await this.getEnabledCodes();
+    const calendars = await this.loadAll();
+    return calendars
+      .filter(calendar => enabled.includes(calendar.code))
+      .flatMap(calendar => calendar.holidays);
+  }
+
+  /**
+   * Whole days between the date and the holiday (0 on the day), or null when
+   * it is further away than the tolerance
+   */
+  daysFromHoliday(holiday: HolidayDefinition, date: Date, toleranceDays: number): number | null {
+    const occurrence = (start: Date, recurrenceRule: EventRecurrenceRule | null) => ({
+      date: start,
+      isRecurring: false,
+      recurringType: null,
+      recurrenceRule,
+      durationDays: holiday.durationDays,
+    });
+
+    let best = daysFromOccurrence(occurrence(holiday.start, holiday.recurrenceRule), date, toleranceDays);
+    const windowMs = (toleranceDays + holiday.durationDays + 1) * 24 * 60 * 60 * 1000;
+    for (const extraDate of holiday.extraDates) {
+      if (Math.abs(extraDate.getTime() - date.getTime()) > windowMs) continue;
+      const days = daysFromOccurrence(occurrence(extraDate, null), date, toleranceDays);
+      if (days !== null && (best === null || days < best)) best = days;
+    }
+    return best;
+  }
+
+  /**
+   * Save an ICS file as a holiday set. Importing again under the same name
+   * replaces the earlier copy.
+   */
+  async importCalendar(icsText: string, name?: string, enable = true): Promise<HolidayCalendarSummary & { skipped: string[] }> {
+    const parsed = parseIcs(icsText);
+    const calendarName = (name ?? parsed.name ?? '').trim();
+    if (!calendarName) {
+      throw new HolidayCalendarError('The calendar needs a name');
+    }
+    if (parsed.events.length === 0) {
+      throw new HolidayCalendarError(parsed.skipped.length > 0
+        ? `None of the calendar's events could be used: ${parsed.skipped.join('; ')}`
+        : 'The file does not contain any calendar events');
+    }
+
+    const slug = calendarName.toLowerCase().replace(/[^a-z0-9]+/g, '-').replace(/^-|-$/g, '') || 'calendar';
+    const code = `${CUSTOM_PREFIX}${slug}`;
+    await fs.mkdir(libraryPath('calendars'), { recursive: true });
+    await fs.writeFile(libraryPath('calendars', `${code}.ics`), setIcsCalendarName(icsText, calendarName), 'utf8');
+    this.imported = null;
+
+    if (enable) {
+      const enabled = await this.getEnabledCodes();
+      if (!enabled.includes(code)) await this.saveEnabledCodes([...enabled, code]);
+    }
+
+    return { code, name: calendarName, source: 'imported', count: parsed.events.length, skipped: parsed.skipped };
+  }
+
+  async deleteCalendar(code: string): Promise<void> {
+    const calendar = (await this.loadAll()).find(candidate => candidate.code === code);
+    if (!calendar) {
+      throw new HolidayCalendarError(`Holiday set ${code} not found`);
+    }
+    if (calendar.source === 'bundled') {
+      throw new HolidayCalendarError('Built-in holiday sets cannot be deleted, disable them instead');
+    }
+    await fs.rm(libraryPath('calendars', `${code}.ics`), { force: true });
+    this.imported = null;
+
+    const enabled = await this.getEnabledCodes();
+    if (enabled.includes(code)) {
+      await this.saveEnabledCodes(enabled.filter(enabledCode => enabledCode !== code));
+    }
+  }
+
+  private async saveEnabledCodes(codes: string[]): Promise<void> {
+    const value = JSON.stringify(codes);
+    if (await storage.getSettingByKey(ENABLED_HOLIDAYS_SETTING)) {
+      await storage.updateSetting(ENABLED_HOLIDAYS_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: ENABLED_HOLIDAYS_SETTING,
+        value,
+        category: 'events',
+        description: 'Enabled holiday country sets for event detection',
+      });
+    }
+  }
+
+  private async loadAll(): Promise<LoadedCalendar[]> {
+    if (!this.bundled) {
+      this.bundled = await this.loadFolder(await this.findBundledDir(), 'bundled');
+    }
+    // Imported calendars live in the library, so reload them after the library moves
+    const root = getLibraryRoot();
+    if (!this.imported || this.imported.root !== root) {
+      this.imported = { root, calendars: await this.loadFolder(libraryPath('calendars'), 'imported') };
+    }
+    return [...this.bundled, ...this.imported.calendars];
+  }
+
+  private async loadFolder(dir: string | null, source: 'bundled' | 'imported'): Promise<LoadedCalendar[]> {
+    if (!dir) return [];
+    let files: string[];
+    try {
+      files = (await fs.readdir(dir)).filter(file => file.toLowerCase().endsWith('.ics')).sort();
+    } catch {
+      return [];
+    }
+
+    const calendars: LoadedCalendar[] = [];
+    for (const file of files) {
+      try {
+        const parsed = parseIcs(await fs.readFile(path.join(dir, file), 'utf8'));
+        const baseName = path.basename(file, path.extname(file));
+        // Bundled sets keep the country codes stored by earlier versions (US, UK)
+        const code = source === 'bundled' ? baseName.toUpperCase() : baseName;
+        calendars.push({
+          code,
+          name: parsed.name ?? code,
+          source,
+          count: parsed.events.length,
+          holidays: parsed.events.map(event => ({
+            name: event.summary,
+            calendar: code,
+            start: event.start,
+            durationDays: event.durationDays,
+            recurrenceRule: event.recurrenceRule,
+            extraDates: event.extraDates,
+          })),
+        });
+        if (parsed.skipped.length > 0) {
+          console.warn(`Holiday calendar ${file}: skipped ${parsed.skipped.join('; ')}`);
+        }
+      } catch (error) {
+        console.error(`Failed to load holiday calendar ${file}:`, error);
+      }
+    }
+    return calendars;
+  }
+
+  private async findBundledDir(): Promise<string | null> {
+    // Source layout in development, next to the bundle or the working directory in builds
+    const candidates = [
+      path.resolve(import.meta.dirname, '..', 'holidays'),
+      path.resolve(import.meta.dirname, '..', 'server', 'holidays'),
+      path.resolve(process.cwd(), 'server', 'holidays'),
+    ];
+    for (const candidate of candidates) {
+      try {
+        if ((await fs.stat(candidate)).isDirectory()) return candidate;
+      } catch {
+        // try the next location
+      }
+    }
+    console.error(`Bundled holiday calendars not found. Tried: ${candidates.join(', ')}`);
+    return null;
+  }
+}
+
+export const holidayCalendarService = new HolidayCalendarService();
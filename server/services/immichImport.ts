@@ -0,0 +1,329 @@
+import fs from "fs/promises";
+import { createWriteStream } from "fs";
+import os from "os";
+import path from "path";
+import { Readable } from "stream";
+import { pipeline } from "stream/promises";
+import { z } from "zod";
+import { storage } from "../storage";
+import { localImportService } from "./localImport";
+import { counterConsistencyService } from "./counterConsistency";
+import type { CancellationToken } from "./operations";
+import { boxOverlap, SAME_FACE_OVERLAP, type FaceBox } from "../utils/faceBoxes";
+import type { FileVersion, Person } from "@shared/schema";
+
+export const immichImportSchema = z.object({
+  serverUrl: z.string().url(),
+  apiKey: z.string().min(1), // created under Account Settings > API Keys on the Immich server
+  albums: z.boolean().optional(),
+  people: z.boolean().optional(),
+});
+
+export type ImmichImportOptions = Omit<z.infer<typeof immichImportSchema>, 'serverUrl' | 'apiKey'>;
+
+export interface ImmichImportResult {
+  serverUrl: string;
+  assets: number;
+  imported: number;
+  duplicates: number; // already in the library under another import
+  resumed: number; // brought in by an earlier run of this import
+  failed: Array<{ assetId: string; fileName: string; reason: string }>;
+  favorites: number;
+  rated: number;
+  faces: number;
+  peopleCreated: number;
+  albums: number;
+}
+
+export class ImmichImportError extends Error {}
+
+// Immich caps metadata search pages at 1000
+const PAGE_SIZE = 250;
+const DOWNLOAD_DIR = path.join(os.tmpdir(), 'pictallion-immich');
+
+interface ImmichFace {
+  boundingBoxX1: number;
+  boundingBoxY1: number;
+  boundingBoxX2: number;
+  boundingBoxY2: number;
+  imageWidth: number;
+  imageHeight: number;
+}
+
+interface ImmichAsset {
+  id: string;
+  originalFileName: string;
+  isFavorite: boolean;
+  isTrashed?: boolean;
+  exifInfo?: { rating?: number | null } | null;
+  people?: Array<{ id: string; name: string; faces: ImmichFace[] }>;
+  unassignedFaces?: ImmichFace[];
+}
+
+interface ImmichSearchPage {
+  assets: { items: ImmichAsset[]; nextPage: string | null };
+}
+
+interface ImmichAlbum {
+  id: string;
+  albumName: string;
+  description?: string;
+  assets?: Array<{ id: string }>;
+}
+
+/**
+ * Import a library from an Immich server through its REST API, so a
+ * self-hosted library can move to Pictallion and live offline. Originals
+ * come through the normal import; favorites become picks, star ratings
+ * carry over, named people come in with their face regions and albums
+ * become albums. Each asset is mapped to the photo it became as soon as it
+ * is imported, so an interrupted import picks up where it stopped when it
+ * is run again.
+ */
+class ImmichImportService {
+  async importServer(
+    serverUrl: string,
+    apiKey: string,
+    options: ImmichImportOptions = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ImmichImportResult> {
+    const baseUrl = serverUrl.replace(/\/+$/, '');
+    const client = new ImmichClient(baseUrl, apiKey);
+    await client.checkAuth();
+
+    const importAlbums = options.albums ?? true;
+    const importPeople = options.people ?? true;
+    const mapped = new Map((await storage.getExternalAssets('immich', baseUrl)).map(asset => [asset.externalId, asset.photoId]));
+    const people = new Map((await storage.getPeople()).map(person => [person.name.toLowerCase(), person]));
+    const result: ImmichImportResult = {
+      serverUrl: baseUrl, assets: 0, imported: 0, duplicates: 0, resumed: 0, failed: [],
+      favorites: 0, rated: 0, faces: 0, peopleCreated: 0, albums: 0,
+    };
+
+    const total = await client.countAssets();
+    let page: string | null = '1';
+    while (page) {
+      token?.throwIfCancelled();
+      const { items, nextPage } = await client.searchAssets(Number(page));
+      page = nextPage;
+
+      for (const asset of items) {
+        token?.throwIfCancelled();
+        if (asset.isTrashed) continue;
+        result.assets++;
+        onProgress?.(result.assets - 1, Math.max(total, result.assets));
+
+        if (mapped.has(asset.id)) {
+          result.resumed++;
+          continue;
+        }
+
+        // A folder per asset, so the file keeps its original name for the import
+        const downloadDir = path.join(DOWNLOAD_DIR, asset.id);
+        const download = path.join(downloadDir, path.basename(asset.originalFileName));
+        try {
+          await fs.mkdir(downloadDir, { recursive: true });
+          await client.downloadOriginal(asset.id, download);
+          const imported = await localImportService.importFile(download, {
+            sourceDevice: 'Immich',
+            details: `Imported from Immich at ${baseUrl}`,
+            data: { immichAssetId: asset.id, originalFileName: asset.originalFileName },
+          });
+          if (imported.decision === 'imported') result.imported++;
+          else result.duplicates++;
+
+          const photo = await storage.getFileVersion(imported.photoId);
+          if (photo) {
+            await this.applyOrganization(photo, asset, importPeople, people, result);
+            await storage.createExternalAsset({ source: 'immich', sourceKey: baseUrl, externalId: asset.id, photoId: photo.id });
+            mapped.set(asset.id, photo.id);
+          }
+        } catch (error) {
+          console.error(`Failed to import Immich asset ${asset.id}:`, error);
+          result.failed.push({
+            assetId: asset.id,
+            fileName: asset.originalFileName,
+            reason: error instanceof Error ? error.message : 'Import failed',
+          });
+        } finally {
+          await fs.rm(downloadDir, { recursive: true, force: true });
+        }
+      }
+    }
+    onProgress?.(result.assets, result.assets);
+
+    if (importAlbums) {
+      result.albums = await this.importAlbums(client, mapped, token);
+    }
+    if (result.faces > 0) counterConsistencyService.scheduleCheck();
+    return result;
+  }
+
+  /**
+   * Favorite, rating and faces from Immich. Anything set in Pictallion
+   * already is left alone.
+   */
+  private async applyOrganization(
+    photo: FileVersion,
+    asset: ImmichAsset,
+    importPeople: boolean,
+    people: Map<string, Person>,
+    result: ImmichImportResult
+  ): Promise<void> {
+    const updates: Partial<FileVersion> = {};
+    if (asset.isFavorite && !photo.pickFlag) {
+      updates.pickFlag = 'pick';
+      result.favorites++;
+    }
+    const rating = asset.exifInfo?.rating;
+    if (rating && rating > 0 && !photo.rating) {
+      updates.rating = Math.min(5, rating);
+      result.rated++;
+    }
+    if (Object.keys(updates).length > 0) {
+      await storage.updateFileVersion(photo.id, updates);
+    }
+    if (!importPeople || !photo.width || !photo.height) return;
+
+    const existing = await storage.getFacesByPhoto(photo.id);
+    const regions: Array<{ name: string | null; face: ImmichFace }> = [
+      ...(asset.people ?? []).flatMap(person => person.faces.map(face => ({ name: person.name || null, face }))),
+      ...(asset.unassignedFaces ?? []).map(face => ({ name: null, face })),
+    ];
+    for (const { name, face } of regions) {
+      const boundingBox = this.scaleFace(face, photo.width, photo.height);
+      if (!boundingBox || existing.some(other => boxOverlap(other.boundingBox as FaceBox, boundingBox) >= SAME_FACE_OVERLAP)) {
+        continue;
+      }
+
+      let personId: string | null = null;
+      if (name) {
+        let person = people.get(name.toLowerCase());
+        if (!person) {
+          person = await storage.createPerson({ name });
+          people.set(name.toLowerCase(), person);
+          result.peopleCreated++;
+        }
+        personId = person.id;
+      }
+      existing.push(await storage.createFace({ photoId: photo.id, personId, boundingBox, confidence: 100 }));
+      result.faces++;
+    }
+  }
+
+  /**
+   * Immich measures faces on its preview image, upright; Pictallion stores
+   * them in the original's pixels. A photo whose preview is turned relative
+   * to the stored pixels would need its boxes rotated, so its faces are left
+   * for Pictallion's own detection.
+   */
+  private scaleFace(face: ImmichFace, width: number, height: number): FaceBox | null {
+    if (!face.imageWidth || !face.imageHeight) return null;
+    if ((face.imageWidth > face.imageHeight) !== (width > height) && face.imageWidth !== face.imageHeight) return null;
+    const scaleX = width / face.imageWidth;
+    const scaleY = height / face.imageHeight;
+    const box: FaceBox = [
+      Math.round(face.boundingBoxX1 * scaleX),
+      Math.round(face.boundingBoxY1 * scaleY),
+      Math.round((face.boundingBoxX2 - face.boundingBoxX1) * scaleX),
+      Math.round((face.boundingBoxY2 - face.boundingBoxY1) * scaleY),
+    ];
+    return box[2] > 0 && box[3] > 0 ? box : null;
+  }
+
+  /**
+   * One album per Immich album with imported photos; an album with that name
+   * already in the library is added to instead
+   */
+  private async importAlbums(client: ImmichClient, mapped: Map<string, string>, token?: CancellationToken): Promise<number> {
+    // Only plain albums can take the photos; smart, system and deleted ones are never matched by name
+    const existing = new Map((await storage.getCollections())
+      .filter(collection => !collection.isSmartCollection && !collection.systemKey && !collection.deletedAt)
+      .map(collection => [collection.name, collection]));
+    let count = 0;
+    for (const summary of await client.listAlbums()) {
+      token?.throwIfCancelled();
+      const album = await client.getAlbum(summary.id);
+      const photoIds = (album.assets ?? []).flatMap(asset => mapped.has(asset.id) ? [mapped.get(asset.id)!] : []);
+      if (photoIds.length === 0) continue;
+
+      const collection = existing.get(album.albumName)
+        ?? await storage.createCollection({ name: album.albumName, description: album.description || 'Imported from Immich' });
+      existing.set(album.albumName, collection);
+      await storage.addPhotosToCollection(collection.id, photoIds);
+      count++;
+    }
+    return count;
+  }
+}
+
+/**
+ * The few Immich endpoints the import needs, authenticated with an API key
+ */
+class ImmichClient {
+  constructor(private readonly baseUrl: string, private readonly apiKey: string) {}
+
+  async checkAuth(): Promise<void> {
+    let response: Response;
+    try {
+      response = await fetch(`${this.baseUrl}/api/users/me`, { headers: this.headers() });
+    } catch {
+      throw new ImmichImportError(`Cannot reach Immich server at ${this.baseUrl}`);
+    }
+    if (response.status === 401 || response.status === 403) {
+      throw new ImmichImportError('Immich rejected the API key');
+    }
+    if (!response.ok) {
+      throw new ImmichImportError(`Immich server returned ${response.status} ${response.statusText}`);
+    }
+  }
+
+  async countAssets(): Promise<number> {
+    const statistics = await this.request<{ total: number }>('/api/assets/statistics');
+    return statistics.total;
+  }
+
+  async searchAssets(page: number): Promise<ImmichSearchPage['assets']> {
+    const body = { page, size: PAGE_SIZE, withExif: true, withPeople: true };
+    const result = await this.request<ImmichSearchPage>('/api/search/metadata', { method: 'POST', body: JSON.stringify(body) });
+    return result.assets;
+  }
+
+  async listAlbums(): Promise<ImmichAlbum[]> {
+    // Owned albums and albums shared with the user are listed separately
+    const owned = await this.request<ImmichAlbum[]>('/api/albums');
+    const shared = await this.request<ImmichAlbum[]>('/api/albums?shared=true');
+    const byId = new Map([...owned, ...shared].map(album => [album.id, album]));
+    return Array.from(byId.values());
+  }
+
+  async getAlbum(id: string): Promise<ImmichAlbum> {
+    return this.request<ImmichAlbum>(`/api/albums/${id}`);
+  }
+
+  async downloadOriginal(assetId: string, destination: string): Promise<void> {
+    const response = await fetch(`${this.baseUrl}/api/assets/${assetId}/original`, { headers: this.headers() });
+    if (!response.ok || !response.body) {
+      throw new Error(`Download failed: ${response.status} ${response.statusText}`);
+    }
+    await pipeline(Readable.fromWeb(response.body as any), createWriteStream(destination));
+  }
+
+  private async request<T>(endpoint: string, init: RequestInit = {}): Promise<T> {
+    const response = await fetch(`${this.baseUrl}${endpoint}`, {
+      ...init,
+      headers: { ...this.headers(), Accept: 'application/json', 'Content-Type': 'application/json' },
+    });
+    if (!response.ok) {
+      throw new Error(`Immich ${endpoint} returned ${response.status} ${response.statusText}`);
+    }
+    return (await response.json()) as T;
+  }
+
+  private headers(): Record<string, string> {
+    return { 'x-api-key': this.apiKey };
+  }
+}
+
+export const immichImportService = new ImmichImportService();
@@ -0,0 +1,317 @@
+import fs from "fs/promises";
+import path from "path";
+import type { Response } from "express";
+import { z } from "zod";
+import { storage } from "../storage";
+import { formatRegistry } from "./formatRegistry";
+import { localImportService, type LocalImportDecision } from "./localImport";
+import { jobQueue } from "./jobQueue";
+import { operationRegistry, type CancellationToken } from "./operations";
+import type { ImportSession } from "@shared/schema";
+
+export const startImportSchema = z.object({
+  paths: z.array(z.string().min(1)).min(1), // files and folders on this machine
+  recursive: z.boolean().optional(), // look inside subfolders (default true)
+  sourceDevice: z.string().min(1).optional(),
+});
+
+export const osShareImportSchema = z.object({
+  paths: z.array(z.string().min(1)).min(1), // files shared, dropped or opened from the desktop
+});
+
+// Source device recorded for files handed over by the operating system
+export const OS_SHARE_SOURCE = 'OS share';
+
+// The import cannot be started; nothing was queued
+export class ImportSessionError extends Error {}
+
+export interface ImportFileEvent {
+  importId: string;
+  filePath: string;
+  fileName: string;
+  fileHash?: string;
+  decision: LocalImportDecision | 'failed';
+  photoId?: string; // the new photo, or the existing one a duplicate matched
+  message?: string;
+  completed: number;
+  total: number;
+}
+
+export interface ImportShareEvent {
+  importId: string;
+  total: number;
+  fileNames: string[]; // the first few, for the toast
+}
+
+export interface ImportSessionProgress {
+  importId: string;
+  sourceDevice: string | null;
+  status: ImportSession['status'];
+  completed: number;
+  total: number;
+  imported: number;
+  duplicates: number;
+  failed: number;
+}
+
+/**
+ * Imports of files already on disk into the bronze tier, one session at a
+ * time. A session stores the files it found and the index of the last one
+ * handled, so sessions interrupted by a restart resume where they stopped.
+ * Each file is reported as a "file" server-sent event with its hash and
+ * dedupe decision, between "started" and "finished" for the session. Files
+ * handed over by the OS (share menus, Send To, opening a photo with the app)
+ * also raise a "shared" event so the app can show a toast.
+ */
+class ImportSessionService {
+  private started = false;
+  private draining = false;
+  private current: { importId: string; token: CancellationToken } | null = null;
+  private eventClients = new Set<Response>();
+
+  async startImport(paths: string[], options: { recursive?: boolean; sourceDevice?: string } = {}): Promise<ImportSession> {
+    const resolved = Array.from(new Set(paths.map(entry => path.resolve(entry))));
+    const files: string[] = [];
+    for (const entry of resolved) {
+      const stats = await fs.stat(entry).catch(() => null);
+      if (!stats) {
+        throw new ImportSessionError(`${entry} does not exist`);
+      }
+      if (stats.isDirectory()) {
+        files.push(...(await this.listFiles(entry, options.recursive ?? true)));
+      } else if (this.isImportable(entry)) {
+        files.push(entry);
+      }
+    }
+    if (files.length === 0) {
+      throw new ImportSessionError('No importable files found');
+    }
+
+    const session = await storage.createImportSession({
+      status: 'queued',
+      paths: resolved,
+      files: Array.from(new Set(files)),
+      sourceDevice: options.sourceDevice ?? null,
+    });
+    this.kick();
+    return session;
+  }
+
+  /**
+   * Import files the OS passed to the app from a share or drop target
+   */
+  async importFromOsShare(paths: string[]): Promise<ImportSession> {
+    const session = await this.startImport(paths, { recursive: true, sourceDevice: OS_SHARE_SOURCE });
+    this.broadcast('shared', {
+      importId: session.id,
+      total: session.files.length,
+      fileNames: session.files.slice(0, 5).map(file => path.basename(file)),
+    });
+    return session;
+  }
+
+  async getImport(id: string): Promise<ImportSession | undefined> {
+    return storage.getImportSession(id);
+  }
+
+  async listImports(limit?: number): Promise<ImportSession[]> {
+    return storage.getImportSessions(limit);
+  }
+
+  /**
+   * Cancel a queued import, or stop a running one after the file it is on
+   */
+  async cancelImport(id: string): Promise<ImportSession | null> {
+    const session = await storage.getImportSession(id);
+    if (!session) return null;
+    if (session.status === 'queued') {
+      const cancelled = await storage.updateImportSession(id, { status: 'cancelled', finishedAt: new Date() });
+      if (cancelled) this.broadcast('finished', this.toProgress(cancelled));
+      return cancelled;
+    }
+    if (this.current?.importId === id) {
+      this.current.token.cancel();
+    }
+    return session;
+  }
+
+  /**
+   * Resume imports interrupted by the last shutdown and start working the queue
+   */
+  async start(): Promise<void> {
+    if (this.started) return;
+    this.started = true;
+    const resumed = await storage.requeueRunningImportSessions();
+    if (resumed > 0) {
+      console.log(`Resuming ${resumed} interrupted import(s)`);
+    }
+    this.kick();
+  }
+
+  /**
+   * Stop taking new imports. The running one is cancelled through the
+   * operation registry on shutdown and stays queued, so it resumes on the next start.
+   */
+  stop(): void {
+    this.started = false;
+  }
+
+  /**
+   * Stop after the current file and wait for it; the running import stays
+   * queued and start() picks it up again
+   */
+  async pause(): Promise<void> {
+    this.stop();
+    this.current?.token.cancel();
+    while (this.draining) {
+      await new Promise(resolve => setTimeout(resolve, 100));
+    }
+  }
+
+  addEventClient(res: Response): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    res.write(': connected\n\n');
+    this.eventClients.add(res);
+    res.on('close', () => this.eventClients.delete(res));
+  }
+
+  private kick(): void {
+    if (!this.started || this.draining) return;
+    this.draining = true;
+    this.drain()
+      .catch(error => console.error('Import queue failed:', error))
+      .finally(() => {
+        this.draining = false;
+      });
+  }
+
+  private async drain(): Promise<void> {
+    while (this.started) {
+      const session = await storage.getNextQueuedImportSession();
+      if (!session) return;
+      await this.runImport(session);
+    }
+  }
+
+  private async runImport(session: ImportSession): Promise<void> {
+    const total = session.files.length;
+    const operation = operationRegistry.start('import', `Importing ${total} files`);
+    this.current = { importId: session.id, token: operation.token };
+    let current = (await storage.updateImportSession(session.id, {
+      status: 'running',
+      startedAt: session.startedAt ?? new Date(),
+    })) ?? session;
+    this.broadcast('started', this.toProgress(current));
+    const importedImages: string[] = [];
+
+    try {
+      let index = session.completed;
+      for (; index < total; index++) {
+        if (operation.token.isCancelled) break;
+        operation.progress(index, total);
+
+        const event = await this.importOne(session, session.files[index], index + 1, total);
+        if (event.decision === 'imported' && event.photoId && formatRegistry.getMimeType(event.filePath)?.startsWith('image/')) {
+          importedImages.push(event.photoId);
+        }
+
+        current = (await storage.updateImportSession(session.id, {
+          completed: index + 1,
+          imported: current.imported + (event.decision === 'imported' ? 1 : 0),
+          duplicates: current.duplicates + (event.decision === 'duplicate' ? 1 : 0),
+          failed: current.failed + (event.decision === 'failed' ? 1 : 0),
+        })) ?? current;
+        this.broadcast('file', event);
+      }
+
+      if (index < total) {
+        // Shutdown leaves the session queued to resume; a user cancel ends it
+        const status = this.started ? 'cancelled' : 'queued';
+        current = (await storage.updateImportSession(session.id, {
+          status,
+          finishedAt: status === 'cancelled' ? new Date() : null,
+        })) ?? current;
+      } else {
+        current = (await storage.updateImportSession(session.id, { status: 'completed', finishedAt: new Date() })) ?? current;
+      }
+    } catch (error) {
+      console.error(`Import ${session.id} failed:`, error);
+      current = (await storage.updateImportSession(session.id, {
+        status: 'failed',
+        error: error instanceof Error ? error.message : 'Unknown error',
+        finishedAt: new Date(),
+      })) ?? current;
+    } finally {
+      this.current = null;
+      operation.finish();
+    }
+
+    // Faces are detected by a background job, as for uploads
+    if (importedImages.length > 0) {
+      await jobQueue.enqueueFaceRecognition(importedImages);
+    }
+    if (current.status !== 'queued') {
+      this.broadcast('finished', this.toProgress(current));
+    }
+  }
+
+  private async importOne(session: ImportSession, filePath: string, completed: number, total: number): Promise<ImportFileEvent> {
+    const base = { importId: session.id, filePath, fileName: path.basename(filePath), completed, total };
+    try {
+      const result = await localImportService.importFile(filePath, {
+        sourceDevice: session.sourceDevice,
+        details: `Imported to Bronze tier: ${filePath}`,
+        data: { importId: session.id },
+      });
+      return { ...base, fileHash: result.fileHash, decision: result.decision, photoId: result.photoId };
+    } catch (error) {
+      console.error(`Failed to import ${filePath}:`, error);
+      return { ...base, decision: 'failed', message: error instanceof Error ? error.message : 'Unknown error' };
+    }
+  }
+
+  private isImportable(filePath: string): boolean {
+    const name = path.basename(filePath);
+    return !name.startsWith('.') && !name.endsWith('.partial') && !!formatRegistry.getMimeType(name);
+  }
+
+  private async listFiles(dir: string, recursive: boolean): Promise<string[]> {
+    const entries = await fs.readdir(dir, { withFileTypes: true }).catch(() => []);
+    const files: string[] = [];
+    for (const entry of entries.sort((a, b) => a.name.localeCompare(b.name))) {
+      const entryPath = path.join(dir, entry.name);
+      if (entry.isFile() && this.isImportable(entryPath)) files.push(entryPath);
+      else if (recursive && entry.isDirectory() && !entry.name.startsWith('.')) {
+        files.push(...(await this.listFiles(entryPath, true)));
+      }
+    }
+    return files;
+  }
+
+  private toProgress(session: ImportSession): ImportSessionProgress {
+    return {
+      importId: session.id,
+      sourceDevice: session.sourceDevice,
+      status: session.status,
+      completed: session.completed,
+      total: session.files.length,
+      imported: session.imported,
+      duplicates: session.duplicates,
+      failed: session.failed,
+    };
+  }
+
+  private broadcast(
+    event: 'started' | 'file' | 'finished' | 'shared',
+    payload: ImportSessionProgress | ImportFileEvent | ImportShareEvent
+  ): void {
+    const message = `event: ${event}\ndata: ${JSON.stringify(payload)}\n\n`;
+    this.eventClients.forEach(client => client.write(message));
+  }
+}
+
+export const importSessionService = new ImportSessionService();
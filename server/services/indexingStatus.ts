@@ -0,0 +1,133 @@
+import type { Response } from "express";
+import { storage } from "../storage";
+import { thumbnailService } from "./thumbnailService";
+import { operationRegistry, type OperationInfo, type OperationKind } from "./operations";
+
+export type IndexingStage = 'thumbnails' | 'metadata' | 'perceptualHash' | 'faces' | 'ai';
+
+export interface IndexingStageStatus {
+  stage: IndexingStage;
+  total: number; // photos the step applies to
+  completed: number;
+  pending: number; // queue depth: photos the step has not handled yet
+  percent: number;
+  running: OperationInfo[]; // operations currently working on this step
+}
+
+export interface IndexingStatus {
+  stages: IndexingStageStatus[];
+  running: boolean;
+  updatedAt: string;
+}
+
+// Which operations advance which steps; imports extract metadata and hash as they go, faces follow in a background job
+const STAGE_OPERATIONS: Record<IndexingStage, OperationKind[]> = {
+  thumbnails: ['thumbnail_backfill'],
+  metadata: ['import'],
+  perceptualHash: ['import', 'ai_processing'],
+  faces: ['face_detection'],
+  ai: ['ai_processing'],
+};
+
+const PROGRESS_INTERVAL_MS = 500;
+
+/**
+ * Library-wide progress of the background indexing steps, for the jobs
+ * panel. Polled through getStatus, or followed over server-sent events:
+ * "progress" while an operation runs (throttled per operation) and a fresh
+ * "status" whenever one starts or finishes.
+ */
+class IndexingStatusService {
+  private eventClients = new Set<Response>();
+  private lastProgress = new Map<string, number>();
+
+  constructor() {
+    operationRegistry.on('started', () => this.broadcastStatus());
+    operationRegistry.on('progress', (info: OperationInfo) => this.broadcastProgress(info));
+    operationRegistry.on('finished', (info: OperationInfo) => {
+      this.lastProgress.delete(info.id);
+      this.broadcastStatus();
+    });
+  }
+
+  async getStatus(): Promise<IndexingStatus> {
+    const [counts, imageIds, cachedIds] = await Promise.all([
+      storage.getIndexingCounts(),
+      storage.getImagePhotoIds(),
+      thumbnailService.getCachedPhotoIds(),
+    ]);
+    const thumbnails = imageIds.filter(id => cachedIds.has(id)).length;
+    const operations = operationRegistry.list();
+
+    const stage = (name: IndexingStage, total: number, completed: number): IndexingStageStatus => ({
+      stage: name,
+      total,
+      completed,
+      pending: Math.max(0, total - completed),
+      percent: total > 0 ? Math.round((completed / total) * 1000) / 10 : 100,
+      running: operations.filter(operation => STAGE_OPERATIONS[name].includes(operation.kind)),
+    });
+
+    const stages = [
+      stage('thumbnails', counts.images, thumbnails),
+      stage('metadata', counts.photos, counts.metadata),
+      stage('perceptualHash', counts.images, counts.perceptualHash),
+      stage('faces', counts.images, counts.faces),
+      stage('ai', counts.photos, counts.ai),
+    ];
+    return {
+      stages,
+      running: stages.some(entry => entry.running.length > 0),
+      updatedAt: new Date().toISOString(),
+    };
+  }
+
+  /**
+   * Keep a server-sent events stream open for indexing progress
+   */
+  addEventClient(res: Response): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    res.write(': connected\n\n');
+    this.eventClients.add(res);
+    res.on('close', () => this.eventClients.delete(res));
+    this.getStatus()
+      .then(status => this.send(res, 'status', status))
+      .catch(error => console.error('Failed to send indexing status:', error));
+  }
+
+  private broadcastProgress(info: OperationInfo): void {
+    if (this.eventClients.size === 0) return;
+    const now = Date.now();
+    if (now - (this.lastProgress.get(info.id) ?? 0) < PROGRESS_INTERVAL_MS) return;
+    this.lastProgress.set(info.id, now);
+
+    const stages = (Object.keys(STAGE_OPERATIONS) as IndexingStage[]).filter(name => STAGE_OPERATIONS[name].includes(info.kind));
+    if (stages.length === 0) return;
+    this.eventClients.forEach(client => this.send(client, 'progress', {
+      operationId: info.id,
+      kind: info.kind,
+      label: info.label,
+      stages,
+      completed: info.completed,
+      total: info.total,
+      percent: info.total ? Math.round((info.completed / info.total) * 1000) / 10 : null,
+    }));
+  }
+
+  private broadcastStatus(): void {
+    if (this.eventClients.size === 0) return;
+    this.getStatus()
+      .then(status => this.eventClients.forEach(client => this.send(client, 'status', status)))
+      .catch(error => console.error('Failed to broadcast indexing status:', error));
+  }
+
+  private send(client: Response, event: string, data: unknown): void {
+    client.write(`event: ${event}\ndata: ${JSON.stringify(data)}\n\n`);
+  }
+}
+
+export const indexingStatusService = new IndexingStatusService();
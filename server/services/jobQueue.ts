@@ -0,0 +1,253 @@
+import { EventEmitter } from "events";
+import type { Response } from "express";
+import { storage } from "../storage";
+import { faceDetectionService, FACE_EMBEDDING_MODEL } from "./faceDetection";
+import { operationRegistry, type CancellationToken } from "./operations";
+import { provenanceService } from "./provenance";
+import type { Job } from "@shared/schema";
+
+export type JobKind = Job['kind'];
+
+export interface JobProgress {
+  jobId: string;
+  kind: JobKind;
+  status: Job['status'];
+  completed: number;
+  total: number;
+  failed: number;
+}
+
+type PhotoOutcome = 'processed' | 'skipped' | 'failed';
+
+/**
+ * Persistent queue of background jobs, worked through one at a time so that
+ * imports return as soon as files are stored. Jobs live in the jobs table and
+ * record the index of the last finished photo, so a restart picks them up
+ * where they stopped. Emits queued/progress/finished with a JobProgress and
+ * relays them to server-sent event clients.
+ */
+class JobQueue extends EventEmitter {
+  private started = false;
+  private draining = false;
+  private current: { jobId: string; token: CancellationToken } | null = null;
+  private eventClients = new Set<Response>();
+
+  /**
+   * Detect faces and compute embeddings for photos in the background
+   */
+  async enqueueFaceRecognition(photoIds: string[]): Promise<Job> {
+    const job = await storage.createJob({
+      kind: 'face_recognition',
+      status: 'queued',
+      photoIds: Array.from(new Set(photoIds)),
+    });
+    this.broadcast('queued', job);
+    this.kick();
+    return job;
+  }
+
+  async getJob(id: string): Promise<Job | undefined> {
+    return storage.getJob(id);
+  }
+
+  async listJobs(limit?: number): Promise<Job[]> {
+    return storage.getJobs(limit);
+  }
+
+  /**
+   * Cancel a queued job, or stop a running one after its current photo
+   */
+  async cancel(id: string): Promise<Job | null> {
+    const job = await storage.getJob(id);
+    if (!job) return null;
+    if (job.status === 'queued') {
+      const cancelled = await storage.updateJob(id, { status: 'cancelled', finishedAt: new Date() });
+      if (cancelled) this.broadcast('finished', cancelled);
+      return cancelled;
+    }
+    if (this.current?.jobId === id) {
+      this.current.token.cancel();
+    }
+    return job;
+  }
+
+  /**
+   * Resume jobs interrupted by the last shutdown and start working the queue
+   */
+  async start(): Promise<void> {
+    if (this.started) return;
+    this.started = true;
+    const resumed = await storage.requeueRunningJobs();
+    if (resumed > 0) {
+      console.log(`Resuming ${resumed} interrupted background job(s)`);
+    }
+    this.kick();
+  }
+
+  /**
+   * Stop taking new jobs. The running job is cancelled through the operation
+   * registry on shutdown and stays queued, so it resumes on the next start.
+   */
+  stop(): void {
+    this.started = false;
+  }
+
+  /**
+   * Stop after the current photo and wait for it; the running job stays
+   * queued and start() picks it up again
+   */
+  async pause(): Promise<void> {
+    this.stop();
+    this.current?.token.cancel();
+    while (this.draining) {
+      await new Promise(resolve => setTimeout(resolve, 100));
+    }
+  }
+
+  /**
+   * Keep a server-sent events stream open for job updates
+   */
+  addEventClient(res: Response): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    res.write(': connected\n\n');
+    this.eventClients.add(res);
+    res.on('close', () => this.eventClients.delete(res));
+  }
+
+  private kick(): void {
+    if (!this.started || this.draining) return;
+    this.draining = true;
+    this.drain()
+      .catch(error => console.error('Background job queue failed:', error))
+      .finally(() => {
+        this.draining = false;
+      });
+  }
+
+  private async drain(): Promise<void> {
+    while (this.started) {
+      const job = await storage.getNextQueuedJob();
+      if (!job) return;
+      await this.runJob(job);
+    }
+  }
+
+  private async runJob(job: Job): Promise<void> {
+    const total = job.photoIds.length;
+    const operation = operationRegistry.start('face_detection', `Recognizing faces in ${total} photos`);
+    this.current = { jobId: job.id, token: operation.token };
+    let current = (await storage.updateJob(job.id, { status: 'running', startedAt: job.startedAt ?? new Date() })) ?? job;
+    let failed = job.failed;
+    const counts = { processed: 0, skipped: 0 };
+
+    try {
+      let index = job.completed;
+      for (; index < total; index++) {
+        if (operation.token.isCancelled) break;
+        operation.progress(index, total);
+
+        const outcome = await this.recognizeFaces(job.photoIds[index]);
+        if (outcome === 'failed') failed++;
+        else counts[outcome]++;
+
+        current = (await storage.updateJob(job.id, { completed: index + 1, failed })) ?? current;
+        this.broadcast('progress', current);
+      }
+
+      if (index < total) {
+        // Shutdown leaves the job queued to resume; a user cancel ends it
+        const status = this.started ? 'cancelled' : 'queued';
+        current = (await storage.updateJob(job.id, { status, finishedAt: status === 'cancelled' ? new Date() : null })) ?? current;
+      } else {
+        current = (await storage.updateJob(job.id, {
+          status: 'completed',
+          result: { ...counts, failed },
+          finishedAt: new Date(),
+        })) ?? current;
+      }
+    } catch (error) {
+      console.error(`Background job ${job.id} failed:`, error);
+      current = (await storage.updateJob(job.id, {
+        status: 'failed',
+        error: error instanceof Error ? error.message : 'Unknown error',
+        finishedAt: new Date(),
+      })) ?? current;
+    } finally {
+      this.current = null;
+      operation.finish();
+    }
+    if (current.status !== 'queued') {
+      this.broadcast('finished', current);
+    }
+  }
+
+  /**
+   * Detect and store the faces of one photo. Photos whose faces were already
+   * detected only get embeddings from the current model where they lack one.
+   */
+  private async recognizeFaces(photoId: string): Promise<PhotoOutcome> {
+    try {
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo) return 'failed';
+      if (!photo.mimeType.startsWith('image/')) return 'skipped';
+
+      const existing = await storage.getFacesByPhoto(photo.id);
+      if (existing.length > 0) {
+        const stale = existing.filter(face => face.embeddingModel !== FACE_EMBEDDING_MODEL);
+        for (const face of stale) {
+          const embedding = await faceDetectionService.generateFaceEmbedding(photo.filePath, face.boundingBox as [number, number, number, number]);
+          if (embedding) {
+            await storage.updateFace(face.id, { embedding, embeddingModel: FACE_EMBEDDING_MODEL });
+          }
+        }
+        return stale.length > 0 ? 'processed' : 'skipped';
+      }
+
+      const faceDetectionResult = await faceDetectionService.detectFaces(photo.filePath);
+      await storage.updateFileVersion(photo.id, {
+        metadata: { ...((photo.metadata as object) || {}), ...faceDetectionResult.metadata },
+      });
+      for (const face of faceDetectionResult.faces) {
+        await storage.createFace({
+          photoId: photo.id,
+          boundingBox: face.boundingBox,
+          confidence: face.confidence,
+          embedding: face.embedding,
+          embeddingModel: face.embeddingModel ?? null,
+          detector: face.detector ?? null,
+          personId: null, // Faces start unassigned
+        });
+      }
+      await provenanceService.recordFaceDetection(
+        photo,
+        faceDetectionResult.faces.length,
+        faceDetectionResult.metadata.faceDetection,
+        faceDetectionResult.faces.some(face => face.embeddingModel) ? FACE_EMBEDDING_MODEL : null
+      );
+      return 'processed';
+    } catch (error) {
+      console.error(`Face recognition failed for photo ${photoId}:`, error);
+      return 'failed';
+    }
+  }
+
+  private broadcast(event: 'queued' | 'progress' | 'finished', job: Job): void {
+    const progress: JobProgress = {
+      jobId: job.id,
+      kind: job.kind,
+      status: job.status,
+      completed: job.completed,
+      total: job.photoIds.length,
+      failed: job.failed,
+    };
+    this.emit(event, progress);
+    const payload = `event: ${event}\ndata: ${JSON.stringify(progress)}\n\n`;
+    this.eventClients.forEach(client => client.write(payload));
+  }
+}
+
+export const jobQueue = new JobQueue();
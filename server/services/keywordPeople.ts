@@ -0,0 +1,214 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { faceDetectionService, SUGGESTION_MIN_SIMILARITY } from "./faceDetection";
+import { nameSimilarity, normalizeName } from "./peopleMerge";
+import { propagationService } from "./propagation";
+import { counterConsistencyService } from "./counterConsistency";
+import { smartCollectionService } from "./smartCollectionService";
+import type { Face, FileVersion, Person } from "@shared/schema";
+
+export const applyKeywordPeopleSchema = z.object({
+  mappings: z.array(z.union([
+    z.object({ keyword: z.string().min(1), personId: z.string() }),
+    z.object({ keyword: z.string().min(1), createPerson: z.literal(true), name: z.string().trim().min(1).optional() }),
+  ])).default([]),
+  autoMatched: z.boolean().default(false), // also apply every keyword that names exactly one person
+}).refine(input => input.mappings.length > 0 || input.autoMatched, {
+  message: 'Give mappings or set autoMatched',
+});
+
+export type ApplyKeywordPeopleInput = z.infer<typeof applyKeywordPeopleSchema>;
+
+export interface KeywordPersonCandidate {
+  personId: string;
+  name: string;
+  similarity: number;
+}
+
+export interface KeywordPeopleEntry {
+  keyword: string;
+  name: string; // the keyword without its "People/" parent
+  photoCount: number;
+  // matched: exactly one person has this name; ambiguous: several do, or only similar names; unmatched: nobody
+  status: 'matched' | 'ambiguous' | 'unmatched';
+  candidates: KeywordPersonCandidate[];
+}
+
+export interface KeywordPeopleResult {
+  peopleCreated: Array<{ id: string; name: string }>;
+  linked: number; // faces assigned
+  alreadyLinked: number; // photos where the person already had a face
+  assignedFaceIds: string[];
+  unresolved: Array<{ photoId: string; keyword: string; personId: string; reason: 'no_face' | 'several_faces' }>;
+}
+
+export class KeywordPeopleError extends Error {}
+
+// Parents that hold people in hierarchical keywords: "People/Anna", "Personen|Anna"
+const PEOPLE_ROOTS = new Set(['people', 'person', 'persons', 'personen', 'personnes', 'family', 'friends']);
+// How alike a name must be to offer a person for a keyword that does not match anyone exactly
+const CANDIDATE_MIN_SIMILARITY = 0.85;
+
+function splitKeyword(keyword: string): string[] {
+  return keyword.split(/[/|]/).map(part => part.trim()).filter(Boolean);
+}
+
+/**
+ * Legacy tools (Picasa, Lightroom, digiKam without regions) record who is in
+ * a photo as a keyword. This maps such keywords to people: a preview lists
+ * keywords that look like names with the people they may belong to, and
+ * applying a mapping links each tagged photo's unnamed face to the person.
+ * Photos where the face cannot be told apart are reported for manual work.
+ */
+class KeywordPeopleService {
+  async preview(): Promise<KeywordPeopleEntry[]> {
+    const persons = await storage.getPeople();
+    const byName = this.peopleByName(persons);
+    const entries: KeywordPeopleEntry[] = [];
+
+    this.countKeywords(await storage.getAllFileVersions()).forEach((photoCount, keyword) => {
+      const parts = splitKeyword(keyword);
+      const name = parts[parts.length - 1];
+      const underPeopleRoot = parts.length > 1 && PEOPLE_ROOTS.has(parts[0].toLowerCase());
+
+      const exact = byName.get(normalizeName(name)) ?? [];
+      const candidates: KeywordPersonCandidate[] = exact.length > 0
+        ? exact.map(person => ({ personId: person.id, name: person.name, similarity: 1 }))
+        : persons
+          .map(person => ({ personId: person.id, name: person.name, similarity: Math.round(nameSimilarity(name, person.name) * 1000) / 1000 }))
+          .filter(candidate => candidate.similarity >= CANDIDATE_MIN_SIMILARITY)
+          .sort((a, b) => b.similarity - a.similarity);
+      // Keywords that neither sit under a people parent nor resemble anyone are places, things and moods
+      if (!underPeopleRoot && candidates.length === 0) return;
+
+      entries.push({
+        keyword,
+        name,
+        photoCount,
+        status: exact.length === 1 ? 'matched' : candidates.length > 0 ? 'ambiguous' : 'unmatched',
+        candidates,
+      });
+    });
+    return entries.sort((a, b) => b.photoCount - a.photoCount);
+  }
+
+  async apply(input: ApplyKeywordPeopleInput): Promise<KeywordPeopleResult> {
+    const result: KeywordPeopleResult = { peopleCreated: [], linked: 0, alreadyLinked: 0, assignedFaceIds: [], unresolved: [] };
+    const personByKeyword = new Map<string, string>();
+
+    for (const mapping of input.mappings) {
+      if ('personId' in mapping) {
+        if (!(await storage.getPerson(mapping.personId))) {
+          throw new KeywordPeopleError(`Person ${mapping.personId} not found`);
+        }
+        personByKeyword.set(mapping.keyword, mapping.personId);
+      } else {
+        const parts = splitKeyword(mapping.keyword);
+        const person = await storage.createPerson({ name: mapping.name ?? parts[parts.length - 1] ?? mapping.keyword });
+        result.peopleCreated.push({ id: person.id, name: person.name });
+        personByKeyword.set(mapping.keyword, person.id);
+      }
+    }
+    if (input.autoMatched) {
+      for (const entry of await this.preview()) {
+        if (entry.status === 'matched' && !personByKeyword.has(entry.keyword)) {
+          personByKeyword.set(entry.keyword, entry.candidates[0].personId);
+        }
+      }
+    }
+
+    const centroids = await faceDetectionService.getPersonCentroids();
+    const changedPhotoIds: string[] = [];
+    for (const photo of await storage.getAllFileVersions()) {
+      const wanted = new Map<string, string>(); // personId -> keyword
+      this.photoKeywords(photo).forEach(keyword => {
+        const personId = personByKeyword.get(keyword);
+        if (personId) wanted.set(personId, keyword);
+      });
+      if (wanted.size === 0) continue;
+
+      const photoFaces = await storage.getFacesByPhoto(photo.id);
+      photoFaces.forEach(face => {
+        if (face.personId && wanted.delete(face.personId)) result.alreadyLinked++;
+      });
+      if (wanted.size === 0) continue;
+
+      const assignments = this.matchFaces(Array.from(wanted.keys()), photoFaces.filter(face => !face.personId && !face.ignored), centroids);
+      for (const [personId, face] of Array.from(assignments.entries())) {
+        await storage.linkFaceToPerson(face.id, personId);
+        result.assignedFaceIds.push(face.id);
+        result.linked++;
+        wanted.delete(personId);
+      }
+      if (assignments.size > 0) changedPhotoIds.push(photo.id);
+
+      const facesLeft = photoFaces.filter(face => !face.personId && !face.ignored).length - assignments.size;
+      wanted.forEach((keyword, personId) => {
+        result.unresolved.push({ photoId: photo.id, keyword, personId, reason: facesLeft > 0 ? 'several_faces' : 'no_face' });
+      });
+    }
+
+    if (result.linked > 0 || result.peopleCreated.length > 0) {
+      counterConsistencyService.scheduleCheck();
+    }
+    if (changedPhotoIds.length > 0) {
+      smartCollectionService.photosChanged(changedPhotoIds);
+    }
+    return result;
+  }
+
+  /**
+   * Pair people with a photo's unnamed faces: first by how much each face
+   * looks like the person's known faces, then a last person and a last face
+   * left over belong together
+   */
+  private matchFaces(personIds: string[], candidates: Face[], centroids: Map<string, number[]>): Map<string, Face> {
+    const assignments = new Map<string, Face>();
+    const pairs: Array<{ personId: string; face: Face; similarity: number }> = [];
+    for (const personId of personIds) {
+      const centroid = centroids.get(personId);
+      if (!centroid) continue;
+      for (const face of candidates) {
+        if (!Array.isArray(face.embedding)) continue;
+        const similarity = faceDetectionService.calculateEmbeddingSimilarity(centroid, face.embedding as number[]);
+        if (similarity >= SUGGESTION_MIN_SIMILARITY) pairs.push({ personId, face, similarity });
+      }
+    }
+    const taken = new Set<string>();
+    pairs.sort((a, b) => b.similarity - a.similarity).forEach(pair => {
+      if (assignments.has(pair.personId) || taken.has(pair.face.id)) return;
+      assignments.set(pair.personId, pair.face);
+      taken.add(pair.face.id);
+    });
+
+    const peopleLeft = personIds.filter(personId => !assignments.has(personId));
+    const facesLeft = candidates.filter(face => !taken.has(face.id));
+    if (peopleLeft.length === 1 && facesLeft.length === 1) {
+      assignments.set(peopleLeft[0], facesLeft[0]);
+    }
+    return assignments;
+  }
+
+  private photoKeywords(photo: FileVersion): Set<string> {
+    return new Set([...(photo.keywords ?? []), ...propagationService.getTags(photo)]);
+  }
+
+  private countKeywords(photos: FileVersion[]): Map<string, number> {
+    const counts = new Map<string, number>();
+    photos.forEach(photo => {
+      this.photoKeywords(photo).forEach(keyword => counts.set(keyword, (counts.get(keyword) ?? 0) + 1));
+    });
+    return counts;
+  }
+
+  private peopleByName(persons: Person[]): Map<string, Person[]> {
+    const byName = new Map<string, Person[]>();
+    persons.forEach(person => {
+      const key = normalizeName(person.name);
+      byName.set(key, [...(byName.get(key) ?? []), person]);
+    });
+    return byName;
+  }
+}
+
+export const keywordPeopleService = new KeywordPeopleService();
@@ -0,0 +1,405 @@
+import fs from "fs/promises";
+import path from "path";
+import crypto from "crypto";
+import { z } from "zod";
+import { storage } from "../storage";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import { propagationService } from "./propagation";
+import { volumeStatusService } from "./volumeStatus";
+import { localImportService } from "./localImport";
+import { counterConsistencyService } from "./counterConsistency";
+import { smartCollectionService } from "./smartCollectionService";
+import type { CancellationToken } from "./operations";
+import { TarWriter, listTarEntries, readTarEntry, extractTarEntry } from "../utils/tarArchive";
+import { boxOverlap, SAME_FACE_OVERLAP, type FaceBox } from "../utils/faceBoxes";
+import { scratchPath } from "../utils/libraryPaths";
+import type { Collection, FileVersion, Person } from "@shared/schema";
+
+export const archiveExportSchema = z.object({
+  photoIds: z.array(z.string()).min(1).optional(),
+  albumId: z.string().optional(),
+  destination: z.string().min(1), // a .tar file, or a folder to write one into
+}).refine(input => !!input.photoIds !== !!input.albumId, {
+  message: 'Give either photoIds or albumId',
+});
+
+export const archiveImportSchema = z.object({
+  path: z.string().min(1),
+});
+
+export interface ArchivePhoto {
+  id: string; // in the library it came from
+  file: string; // entry name in the archive
+  originalFilename: string;
+  fileHash: string;
+  mimeType: string;
+  takenAt: string | null;
+  rating: number;
+  pickFlag: 'pick' | 'reject' | null;
+  keywords: string[];
+  tags: string[];
+  location: string | null;
+  eventType: string | null;
+  eventName: string | null;
+  description: string | null;
+  width: number | null; // face boxes are in these pixels
+  height: number | null;
+  faces: Array<{ boundingBox: FaceBox; personId: string | null; confidence: number }>;
+}
+
+export interface ArchiveManifest {
+  format: number;
+  createdAt: string;
+  photos: ArchivePhoto[];
+  people: Array<{ id: string; name: string; notes: string | null; birthdate: string | null }>;
+  albums: Array<{ id: string; name: string; description: string | null; photoIds: string[] }>; // photos in album order
+}
+
+export interface ArchiveExportResult {
+  path: string;
+  photos: number;
+  people: number;
+  albums: number;
+  bytes: number;
+  skipped: Array<{ photoId: string; reason: string }>;
+}
+
+export interface ArchiveImportResult {
+  photos: number;
+  imported: number;
+  duplicates: number; // already in this library; curation from the archive is merged into them
+  locked: number; // duplicates whose lock kept curation from being merged
+  faces: number;
+  peopleCreated: number;
+  albumsCreated: number;
+  addedToAlbums: number;
+  failed: Array<{ file: string; reason: string }>;
+}
+
+export class LibraryArchiveError extends Error {}
+
+const MANIFEST_ENTRY = 'pictallion-archive.json';
+const FORMAT_VERSION = 1;
+
+/**
+ * Portable archives for moving photos between Pictallion libraries: a tar
+ * with the originals and a manifest of their people, face regions, tags,
+ * keywords, ratings and albums. Importing brings the files in through the
+ * normal import (as Bronze), then merges the curation: people and albums
+ * are matched by name, faces by their box, and tags and keywords are added
+ * to what a photo already has, so importing twice changes nothing.
+ */
+class LibraryArchiveService {
+  async exportArchive(
+    selection: { photoIds?: string[]; albumId?: string },
+    destination: string,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ArchiveExportResult> {
+    const result: ArchiveExportResult = { path: '', photos: 0, people: 0, albums: 0, bytes: 0, skipped: [] };
+    const albumsToExport: Collection[] = [];
+    let photoIds = selection.photoIds ?? [];
+    if (selection.albumId) {
+      const album = await storage.getCollection(selection.albumId);
+      if (!album || album.deletedAt) throw new LibraryArchiveError('Album not found');
+      albumsToExport.push(album);
+      photoIds = await storage.getCollectionPhotoIds(album.id);
+    }
+
+    const restricted = await privacyService.getRestrictedPhotos('export');
+    const included: Array<{ photo: FileVersion; path: string; entry: ArchivePhoto }> = [];
+    const personIds = new Set<string>();
+    for (const photoId of Array.from(new Set(photoIds))) {
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo || photo.deletedAt) {
+        result.skipped.push({ photoId, reason: 'Photo not found' });
+        continue;
+      }
+      if (restricted.ids.has(photo.id)) {
+        result.skipped.push({ photoId, reason: 'privacy' });
+        continue;
+      }
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) {
+        result.skipped.push({ photoId, reason: `Original is offline on ${original.volumeLabel}` });
+        continue;
+      }
+      const entry = await this.describe(photo);
+      entry.faces.forEach(face => face.personId && personIds.add(face.personId));
+      included.push({ photo, path: original.path, entry });
+    }
+    if (included.length === 0) throw new LibraryArchiveError('None of the photos can be exported');
+
+    // Albums the photos are in travel along, holding only the exported photos
+    const includedIds = new Set(included.map(item => item.photo.id));
+    if (!selection.albumId) {
+      for (const album of await storage.getCollections()) {
+        if (album.isSmartCollection || album.systemKey || album.personAlbum) continue;
+        if ((await storage.getCollectionPhotoIds(album.id)).some(id => includedIds.has(id))) albumsToExport.push(album);
+      }
+    }
+    const people: ArchiveManifest['people'] = [];
+    for (const personId of Array.from(personIds)) {
+      const person = await storage.getPerson(personId);
+      if (!person || person.deletedAt) continue;
+      people.push({
+        id: person.id, name: person.name, notes: person.notes, birthdate: person.birthdate?.toISOString() ?? null,
+      });
+    }
+    const knownPeople = new Set(people.map(person => person.id));
+    included.forEach(({ entry }) => entry.faces.forEach(face => {
+      if (face.personId && !knownPeople.has(face.personId)) face.personId = null;
+    }));
+
+    const manifest: ArchiveManifest = {
+      format: FORMAT_VERSION,
+      createdAt: new Date().toISOString(),
+      photos: included.map(item => item.entry),
+      people,
+      albums: await Promise.all(albumsToExport.map(async album => ({
+        id: album.id,
+        name: album.name,
+        description: album.description,
+        photoIds: (await storage.getCollectionPhotoIds(album.id)).filter(id => includedIds.has(id)),
+      }))),
+    };
+
+    const archivePath = await this.archivePath(destination);
+    const partialPath = `${archivePath}.partial`;
+    const writer = await TarWriter.create(partialPath);
+    try {
+      // The manifest goes first, so an import knows what is coming before reading any media
+      await writer.addBuffer(MANIFEST_ENTRY, Buffer.from(JSON.stringify(manifest, null, 2)));
+      for (let index = 0; index < included.length; index++) {
+        token?.throwIfCancelled();
+        onProgress?.(index, included.length);
+        await writer.addFile(included[index].entry.file, included[index].path);
+      }
+      await writer.finish();
+      await fs.rename(partialPath, archivePath);
+    } catch (error) {
+      await writer.abort();
+      await fs.rm(partialPath, { force: true });
+      throw error;
+    }
+    onProgress?.(included.length, included.length);
+
+    for (const { photo } of included) {
+      await provenanceService.record(photo, 'EXPORTED', `Exported to archive ${archivePath}`, { kind: 'archive', destination: archivePath });
+    }
+    return {
+      ...result,
+      path: archivePath,
+      photos: included.length,
+      people: people.length,
+      albums: manifest.albums.length,
+      bytes: (await fs.stat(archivePath)).size,
+    };
+  }
+
+  async importArchive(
+    archivePath: string,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ArchiveImportResult> {
+    let entries;
+    try {
+      entries = await listTarEntries(archivePath);
+    } catch (error) {
+      throw new LibraryArchiveError(`${archivePath} is not a readable archive: ${error instanceof Error ? error.message : error}`);
+    }
+    const manifestEntry = entries.find(entry => entry.name === MANIFEST_ENTRY);
+    if (!manifestEntry) throw new LibraryArchiveError(`${archivePath} is not a Pictallion archive`);
+    const manifest = JSON.parse((await readTarEntry(archivePath, manifestEntry)).toString('utf8')) as ArchiveManifest;
+    if (manifest.format !== FORMAT_VERSION || !Array.isArray(manifest.photos)) {
+      throw new LibraryArchiveError('The archive was written by a version of Pictallion this one cannot read');
+    }
+    const files = new Map(entries.map(entry => [entry.name, entry]));
+
+    const result: ArchiveImportResult = {
+      photos: manifest.photos.length, imported: 0, duplicates: 0, locked: 0, faces: 0,
+      peopleCreated: 0, albumsCreated: 0, addedToAlbums: 0, failed: [],
+    };
+    const people = new Map((await storage.getPeople()).map(person => [person.name.toLowerCase(), person]));
+    const archivePeople = new Map((manifest.people ?? []).map(person => [person.id, person]));
+    const photoIds = new Map<string, string>(); // archive photo id -> photo in this library
+    const workDir = scratchPath(`archive-${crypto.randomUUID()}`);
+
+    try {
+      for (let index = 0; index < manifest.photos.length; index++) {
+        token?.throwIfCancelled();
+        onProgress?.(index, manifest.photos.length);
+        const item = manifest.photos[index];
+        const entry = files.get(item.file);
+        if (!entry) {
+          result.failed.push({ file: item.file, reason: 'Missing from the archive' });
+          continue;
+        }
+
+        try {
+          // Extract under the original name, which the import keeps
+          const folder = path.join(workDir, String(index));
+          await fs.mkdir(folder, { recursive: true });
+          const extracted = path.join(folder, path.basename(item.originalFilename) || path.basename(item.file));
+          await extractTarEntry(archivePath, entry, extracted);
+          const imported = await localImportService.importFile(extracted, {
+            sourceDevice: 'Pictallion archive',
+            details: `Imported from archive ${path.basename(archivePath)}`,
+            data: { archive: archivePath, archivePhotoId: item.id },
+          });
+          await fs.rm(folder, { recursive: true, force: true });
+          if (imported.decision === 'imported') result.imported++;
+          else result.duplicates++;
+          photoIds.set(item.id, imported.photoId);
+
+          const photo = await storage.getFileVersion(imported.photoId);
+          if (!photo) continue;
+          if (photo.isLocked) {
+            result.locked++;
+            continue;
+          }
+          await this.mergeCuration(photo, item);
+          result.faces += await this.mergeFaces(photo, item, archivePeople, people, result);
+        } catch (error) {
+          console.error(`Failed to import ${item.file} from archive:`, error);
+          result.failed.push({ file: item.file, reason: error instanceof Error ? error.message : 'Import failed' });
+        }
+      }
+      onProgress?.(manifest.photos.length, manifest.photos.length);
+    } finally {
+      await fs.rm(workDir, { recursive: true, force: true });
+    }
+
+    const albums = new Map((await storage.getCollections())
+      .filter(album => !album.isSmartCollection && !album.systemKey)
+      .map(album => [album.name.toLowerCase(), album]));
+    for (const archived of manifest.albums ?? []) {
+      const ids = archived.photoIds.map(id => photoIds.get(id)).filter((id): id is string => !!id);
+      if (ids.length === 0) continue;
+      let album = albums.get(archived.name.toLowerCase());
+      if (!album) {
+        album = await storage.createCollection({ name: archived.name, description: archived.description ?? 'Imported from a Pictallion archive' });
+        albums.set(archived.name.toLowerCase(), album);
+        result.albumsCreated++;
+      }
+      result.addedToAlbums += await storage.addPhotosToCollection(album.id, ids);
+    }
+
+    if (result.faces > 0 || result.peopleCreated > 0) counterConsistencyService.scheduleCheck();
+    smartCollectionService.photosChanged(Array.from(photoIds.values()));
+    return result;
+  }
+
+  private async describe(photo: FileVersion): Promise<ArchivePhoto> {
+    const asset = await storage.getMediaAsset(photo.mediaAssetId);
+    const originalFilename = asset?.originalFilename ?? path.basename(photo.filePath);
+    const faces = (await storage.getFacesByPhoto(photo.id))
+      .filter(face => !face.ignored)
+      .map(face => ({ boundingBox: face.boundingBox as FaceBox, personId: face.personId, confidence: face.confidence }));
+    const ai = (photo.metadata as any)?.ai;
+    return {
+      id: photo.id,
+      file: `media/${photo.id}${path.extname(originalFilename).toLowerCase()}`,
+      originalFilename,
+      fileHash: photo.fileHash,
+      mimeType: photo.mimeType,
+      takenAt: photo.takenAt?.toISOString() ?? null,
+      rating: photo.rating ?? 0,
+      pickFlag: photo.pickFlag ?? null,
+      keywords: photo.keywords ?? [],
+      tags: propagationService.getTags(photo),
+      location: photo.location,
+      eventType: photo.eventType,
+      eventName: photo.eventName,
+      description: ai?.longDescription ?? ai?.shortDescription ?? null,
+      width: photo.width,
+      height: photo.height,
+      faces,
+    };
+  }
+
+  /**
+   * Add the archive's curation to a photo without overwriting what this
+   * library already decided: a rating, flag, place or event set here wins
+   */
+  private async mergeCuration(photo: FileVersion, item: ArchivePhoto): Promise<void> {
+    const updates: Partial<FileVersion> = {};
+    if (!photo.rating && item.rating) updates.rating = item.rating;
+    if (!photo.pickFlag && item.pickFlag) updates.pickFlag = item.pickFlag;
+    if (!photo.location && item.location) updates.location = item.location;
+    if (!photo.eventName && item.eventName) {
+      updates.eventName = item.eventName;
+      updates.eventType = item.eventType;
+    }
+    const keywords = Array.from(new Set([...(photo.keywords ?? []), ...item.keywords]));
+    if (keywords.length !== (photo.keywords ?? []).length) updates.keywords = keywords;
+
+    const current = propagationService.getTags(photo);
+    const tags = Array.from(new Set([...current, ...item.tags]));
+    if (tags.length !== current.length) {
+      const metadata = (photo.metadata as any) || {};
+      updates.metadata = { ...metadata, ai: { ...(metadata.ai || {}), aiTags: tags } };
+    }
+    if (Object.keys(updates).length > 0) await storage.updateFileVersion(photo.id, updates);
+  }
+
+  private async mergeFaces(
+    photo: FileVersion,
+    item: ArchivePhoto,
+    archivePeople: Map<string, ArchiveManifest['people'][number]>,
+    people: Map<string, Person>,
+    result: ArchiveImportResult
+  ): Promise<number> {
+    // Boxes are in the source photo's pixels; the same file has the same size, but scale in case it was recorded differently
+    const scaleX = item.width && photo.width ? photo.width / item.width : 1;
+    const scaleY = item.height && photo.height ? photo.height / item.height : 1;
+    const existing = await storage.getFacesByPhoto(photo.id);
+    let created = 0;
+
+    for (const face of item.faces) {
+      const [x, y, width, height] = face.boundingBox;
+      const boundingBox: FaceBox = [Math.round(x * scaleX), Math.round(y * scaleY), Math.round(width * scaleX), Math.round(height * scaleY)];
+
+      let personId: string | null = null;
+      const archived = face.personId ? archivePeople.get(face.personId) : undefined;
+      if (archived) {
+        let person = people.get(archived.name.toLowerCase());
+        if (!person) {
+          person = await storage.createPerson({
+            name: archived.name,
+            notes: archived.notes,
+            birthdate: archived.birthdate ? new Date(archived.birthdate) : null,
+          });
+          people.set(archived.name.toLowerCase(), person);
+          result.peopleCreated++;
+        }
+        personId = person.id;
+      }
+
+      const match = existing.find(other => boxOverlap(other.boundingBox as FaceBox, boundingBox) >= SAME_FACE_OVERLAP);
+      if (match) {
+        if (personId && !match.personId && !match.ignored) {
+          await storage.linkFaceToPerson(match.id, personId);
+          match.personId = personId;
+        }
+        continue;
+      }
+      existing.push(await storage.createFace({ photoId: photo.id, personId, boundingBox, confidence: face.confidence }));
+      created++;
+    }
+    return created;
+  }
+
+  private async archivePath(destination: string): Promise<string> {
+    const stats = await fs.stat(destination).catch(() => null);
+    if (stats?.isDirectory() || (!stats && !path.extname(destination))) {
+      await fs.mkdir(destination, { recursive: true });
+      return path.join(destination, `pictallion-archive-${new Date().toISOString().replace(/[:.]/g, '-')}.tar`);
+    }
+    await fs.mkdir(path.dirname(destination), { recursive: true });
+    return destination;
+  }
+}
+
+export const libraryArchiveService = new LibraryArchiveService();
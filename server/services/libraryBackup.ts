@@ -0,0 +1,411 @@
+import fs from "fs/promises";
+import { createReadStream, createWriteStream } from "fs";
+import crypto from "crypto";
+import path from "path";
+import readline from "readline";
+import { pipeline } from "stream/promises";
+import { createGzip, createGunzip } from "zlib";
+import { z } from "zod";
+import type { PoolClient } from "@neondatabase/serverless";
+import { pool } from "../db";
+import { storage } from "../storage";
+import { counterConsistencyService } from "./counterConsistency";
+import { operationRegistry, type CancellationToken } from "./operations";
+import { libraryPath } from "../utils/libraryPaths";
+
+export const BACKUP_INTERVAL_SETTING = 'backup_interval_hours';
+export const BACKUP_DESTINATION_SETTING = 'backup_destination';
+export const BACKUP_KEEP_SETTING = 'backup_keep';
+
+export const createBackupSchema = z.object({
+  destination: z.string().min(1).optional(), // folder the backup is written into; the configured one by default
+});
+
+export const restoreBackupSchema = z.object({
+  path: z.string().min(1), // a backup folder, as listed
+  dryRun: z.boolean().default(false), // validate and report without changing the database
+});
+
+export type BackupTrigger = 'manual' | 'scheduled' | 'pre_restore';
+
+export interface BackupManifest {
+  format: number;
+  createdAt: string;
+  trigger: BackupTrigger;
+  migrations: string[]; // schema version: SQL migrations applied when the backup was taken
+  tables: Record<string, number>; // rows per table
+  databaseFile: string;
+  databaseSha256: string;
+  // Media is not copied; its hashes tell a restore whether the library still holds the same files
+  media: Array<{ photoId: string; path: string; hash: string; size: number }>;
+}
+
+export interface BackupSummary {
+  path: string;
+  createdAt: string;
+  trigger: BackupTrigger;
+  tables: number;
+  rows: number;
+  photos: number;
+  bytes: number;
+}
+
+export interface RestoreResult {
+  path: string;
+  dryRun: boolean;
+  restored: boolean;
+  tables: Record<string, number>;
+  // Photos in the backup whose file is gone from the library, or differs in size
+  missingMedia: string[];
+  changedMedia: string[];
+  safetyBackup: string | null; // the database as it was before the restore
+}
+
+export class LibraryBackupError extends Error {}
+
+const MANIFEST_FILE = 'manifest.json';
+const DATABASE_FILE = 'database.ndjson.gz';
+const FORMAT_VERSION = 1;
+// Bookkeeping of the schema itself, which a restore keeps as it is
+const EXCLUDED_TABLES = new Set(['library_migrations']);
+const FETCH_SIZE = 1000;
+const INSERT_BATCH_SIZE = 500;
+const SCHEDULER_INTERVAL_MS = 60 * 60 * 1000;
+const DEFAULT_KEEP = 7;
+
+/**
+ * Backups of the library database. A backup is a folder with every table's
+ * rows, read in one snapshot so they are consistent with each other, and a
+ * manifest with the schema version and the hash of every photo's file.
+ * Media is not copied; the manifest tells a restore which files the library
+ * must still hold. Restores replace every table in one transaction, after
+ * taking a backup of the database they replace.
+ */
+class LibraryBackupService {
+  private schedulerHandle: NodeJS.Timeout | null = null;
+  private running = false;
+
+  async createBackup(
+    destination?: string,
+    trigger: BackupTrigger = 'manual',
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<BackupSummary> {
+    const root = path.resolve(destination ?? await this.configuredDestination());
+    const folder = path.join(root, `pictallion-backup-${new Date().toISOString().replace(/[:.]/g, '-')}`);
+    const partial = `${folder}.partial`;
+    await fs.mkdir(partial, { recursive: true });
+
+    const client = await pool.connect();
+    try {
+      // One snapshot for every table, so rows written while the backup runs cannot leave it half-updated
+      await client.query('BEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY');
+      const tables = await this.tablesInDependencyOrder(client);
+      const migrations = (await client.query('SELECT name FROM library_migrations ORDER BY name')).rows.map((row: { name: string }) => row.name);
+      const counts: Record<string, number> = {};
+
+      async function* lines() {
+        for (let index = 0; index < tables.length; index++) {
+          token?.throwIfCancelled();
+          onProgress?.(index, tables.length);
+          const table = tables[index];
+          counts[table] = 0;
+          await client.query(`DECLARE backup_rows NO SCROLL CURSOR FOR SELECT row_to_json(t)::text AS row FROM "${table}" t`);
+          for (;;) {
+            const { rows } = await client.query(`FETCH ${FETCH_SIZE} FROM backup_rows`);
+            if (rows.length === 0) break;
+            counts[table] += rows.length;
+            yield rows.map((row: { row: string }) => `{"table":${JSON.stringify(table)},"row":${row.row}}\n`).join('');
+          }
+          await client.query('CLOSE backup_rows');
+        }
+      }
+      await pipeline(lines, createGzip(), createWriteStream(path.join(partial, DATABASE_FILE)));
+
+      const media = (await client.query('SELECT id, file_path, file_hash, file_size FROM file_versions WHERE deleted_at IS NULL')).rows
+        .map((row: { id: string; file_path: string; file_hash: string; file_size: number }) => ({
+          photoId: row.id, path: row.file_path, hash: row.file_hash, size: Number(row.file_size),
+        }));
+      await client.query('COMMIT');
+
+      const manifest: BackupManifest = {
+        format: FORMAT_VERSION,
+        createdAt: new Date().toISOString(),
+        trigger,
+        migrations,
+        tables: counts,
+        databaseFile: DATABASE_FILE,
+        databaseSha256: await this.hashFile(path.join(partial, DATABASE_FILE)),
+        media,
+      };
+      await fs.writeFile(path.join(partial, MANIFEST_FILE), JSON.stringify(manifest, null, 2));
+      await fs.rename(partial, folder);
+      onProgress?.(tables.length, tables.length);
+      return this.summarize(folder, manifest);
+    } catch (error) {
+      await client.query('ROLLBACK').catch(() => undefined);
+      await fs.rm(partial, { recursive: true, force: true });
+      throw error;
+    } finally {
+      client.release();
+    }
+  }
+
+  /**
+   * Backups in a folder, newest first
+   */
+  async listBackups(destination?: string): Promise<BackupSummary[]> {
+    const root = path.resolve(destination ?? await this.configuredDestination());
+    let names: string[];
+    try {
+      names = await fs.readdir(root);
+    } catch {
+      return [];
+    }
+    const backups: BackupSummary[] = [];
+    for (const name of names) {
+      if (!name.startsWith('pictallion-backup-') || name.endsWith('.partial')) continue;
+      try {
+        backups.push(await this.summarize(path.join(root, name), await this.readManifest(path.join(root, name))));
+      } catch {
+        // Not a complete backup
+      }
+    }
+    return backups.sort((a, b) => b.createdAt.localeCompare(a.createdAt));
+  }
+
+  async restoreBackup(
+    backupPath: string,
+    dryRun: boolean,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<RestoreResult> {
+    const folder = path.resolve(backupPath);
+    const manifest = await this.readManifest(folder);
+    const databasePath = path.join(folder, path.basename(manifest.databaseFile));
+    if ((await this.hashFile(databasePath)) !== manifest.databaseSha256) {
+      throw new LibraryBackupError('The backup database file is damaged: its checksum does not match the manifest');
+    }
+
+    const { rows } = await pool.query('SELECT name FROM library_migrations');
+    const applied = new Set(rows.map((row: { name: string }) => row.name));
+    const unknown = manifest.migrations.filter(name => !applied.has(name));
+    if (unknown.length > 0) {
+      throw new LibraryBackupError(`The backup is from a newer version of Pictallion (${unknown.join(', ')}); update before restoring it`);
+    }
+
+    const missingMedia: string[] = [];
+    const changedMedia: string[] = [];
+    for (const file of manifest.media) {
+      const stats = await fs.stat(libraryPath(file.path)).catch(() => null);
+      if (!stats) missingMedia.push(file.path);
+      else if (stats.size !== file.size) changedMedia.push(file.path);
+    }
+
+    const result: RestoreResult = {
+      path: folder, dryRun, restored: false, tables: manifest.tables, missingMedia, changedMedia, safetyBackup: null,
+    };
+    if (dryRun) return result;
+
+    if (operationRegistry.list().some(operation => operation.kind !== 'backup')) {
+      throw new LibraryBackupError('Wait for running operations to finish before restoring a backup');
+    }
+    result.safetyBackup = (await this.createBackup(libraryPath('backups'), 'pre_restore')).path;
+
+    const client = await pool.connect();
+    try {
+      await client.query('BEGIN');
+      const tables = await this.tablesInDependencyOrder(client);
+      const missingTables = Object.keys(manifest.tables).filter(table => !tables.includes(table));
+      if (missingTables.length > 0) {
+        throw new LibraryBackupError(`The library has no tables named ${missingTables.join(', ')}`);
+      }
+      const columns = await this.insertableColumns(client);
+
+      // The search index and other trigger-maintained rows come from the backup as they were
+      for (const table of tables) await client.query(`ALTER TABLE "${table}" DISABLE TRIGGER USER`);
+      await client.query(`TRUNCATE ${tables.map(table => `"${table}"`).join(', ')} CASCADE`);
+
+      const selfReferencing = await this.selfReferencingTables(client);
+      const total = Object.values(manifest.tables).reduce((sum, count) => sum + count, 0);
+      let completed = 0;
+      let batch: { table: string; rows: Record<string, unknown>[] } | null = null;
+      const flush = async () => {
+        if (!batch || batch.rows.length === 0) return;
+        const keys = new Set(Object.keys(batch.rows[0]));
+        const list = columns.get(batch.table)!.filter(column => keys.has(column)).map(column => `"${column}"`).join(', ');
+        await client.query(
+          `INSERT INTO "${batch.table}" (${list}) SELECT ${list} FROM json_populate_recordset(NULL::"${batch.table}", $1::json)`,
+          [JSON.stringify(batch.rows)]
+        );
+        completed += batch.rows.length;
+        onProgress?.(completed, total);
+        batch = null;
+      };
+
+      const input = readline.createInterface({ input: createReadStream(databasePath).pipe(createGunzip()), crlfDelay: Infinity });
+      for await (const line of input) {
+        if (!line) continue;
+        token?.throwIfCancelled();
+        const { table, row } = JSON.parse(line) as { table: string; row: Record<string, unknown> };
+        if (EXCLUDED_TABLES.has(table)) continue;
+        // Rows of a table that references itself go in one statement, so parents need not come first
+        if (batch && (batch.table !== table || (batch.rows.length >= INSERT_BATCH_SIZE && !selfReferencing.has(table)))) {
+          await flush();
+        }
+        batch = batch ?? { table, rows: [] };
+        batch.rows.push(row);
+      }
+      await flush();
+
+      for (const table of tables) await client.query(`ALTER TABLE "${table}" ENABLE TRIGGER USER`);
+      await client.query('COMMIT');
+      result.restored = true;
+    } catch (error) {
+      await client.query('ROLLBACK').catch(() => undefined);
+      throw error;
+    } finally {
+      client.release();
+    }
+
+    counterConsistencyService.scheduleCheck();
+    return result;
+  }
+
+  /**
+   * Check every hour whether a scheduled backup is due
+   */
+  startScheduler(): void {
+    if (this.schedulerHandle) return;
+    this.schedulerHandle = setInterval(() => {
+      this.runScheduledBackup().catch(error => console.error("Scheduled backup failed:", error));
+    }, SCHEDULER_INTERVAL_MS);
+    this.schedulerHandle.unref();
+  }
+
+  stopScheduler(): void {
+    if (this.schedulerHandle) {
+      clearInterval(this.schedulerHandle);
+      this.schedulerHandle = null;
+    }
+  }
+
+  private async runScheduledBackup(): Promise<void> {
+    const intervalHours = Number((await storage.getSettingByKey(BACKUP_INTERVAL_SETTING))?.value ?? 0);
+    if (!(intervalHours > 0) || this.running) return;
+    const destination = await this.configuredDestination();
+    const backups = await this.listBackups(destination);
+    const scheduled = backups.filter(backup => backup.trigger === 'scheduled');
+    if (scheduled[0] && Date.now() - new Date(scheduled[0].createdAt).getTime() < intervalHours * 60 * 60 * 1000) return;
+
+    this.running = true;
+    const operation = operationRegistry.start('backup', 'Scheduled library backup');
+    try {
+      const backup = await this.createBackup(destination, 'scheduled', operation.token, operation.progress);
+      console.log(`Library backed up to ${backup.path}`);
+
+      const keep = Number((await storage.getSettingByKey(BACKUP_KEEP_SETTING))?.value) || DEFAULT_KEEP;
+      for (const old of [backup, ...scheduled].slice(keep)) {
+        await fs.rm(old.path, { recursive: true, force: true });
+      }
+    } finally {
+      operation.finish();
+      this.running = false;
+    }
+  }
+
+  private async configuredDestination(): Promise<string> {
+    const setting = (await storage.getSettingByKey(BACKUP_DESTINATION_SETTING))?.value?.trim();
+    return setting || libraryPath('backups');
+  }
+
+  private async readManifest(folder: string): Promise<BackupManifest> {
+    let manifest: BackupManifest;
+    try {
+      manifest = JSON.parse(await fs.readFile(path.join(folder, MANIFEST_FILE), 'utf8'));
+    } catch {
+      throw new LibraryBackupError(`${folder} is not a Pictallion backup`);
+    }
+    if (manifest.format !== FORMAT_VERSION || !manifest.tables || !Array.isArray(manifest.migrations) || !Array.isArray(manifest.media)) {
+      throw new LibraryBackupError(`${folder} has a backup format this version cannot read`);
+    }
+    return manifest;
+  }
+
+  private async summarize(folder: string, manifest: BackupManifest): Promise<BackupSummary> {
+    const counts = Object.values(manifest.tables);
+    const database = await fs.stat(path.join(folder, manifest.databaseFile)).catch(() => null);
+    return {
+      path: folder,
+      createdAt: manifest.createdAt,
+      trigger: manifest.trigger,
+      tables: counts.length,
+      rows: counts.reduce((sum, count) => sum + count, 0),
+      photos: manifest.media.length,
+      bytes: database?.size ?? 0,
+    };
+  }
+
+  /**
+   * Library tables with the ones others reference first, so restored rows
+   * never point at rows that are not there yet
+   */
+  private async tablesInDependencyOrder(client: PoolClient): Promise<string[]> {
+    const { rows: tableRows } = await client.query(
+      `SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_type = 'BASE TABLE' ORDER BY table_name`
+    );
+    const { rows: keyRows } = await client.query(
+      `SELECT con.conrelid::regclass::text AS child, con.confrelid::regclass::text AS parent
+         FROM pg_constraint con JOIN pg_namespace ns ON ns.oid = con.connamespace
+        WHERE con.contype = 'f' AND ns.nspname = 'public'`
+    );
+    const tables = tableRows.map((row: { table_name: string }) => row.table_name).filter((table: string) => !EXCLUDED_TABLES.has(table));
+    const parents = new Map<string, Set<string>>(tables.map((table: string) => [table, new Set<string>()]));
+    keyRows.forEach((row: { child: string; parent: string }) => {
+      const child = row.child.replace(/"/g, '');
+      const parent = row.parent.replace(/"/g, '');
+      if (child !== parent) parents.get(child)?.add(parent);
+    });
+
+    const ordered: string[] = [];
+    const visiting = new Set<string>();
+    const visit = (table: string) => {
+      if (ordered.includes(table) || visiting.has(table)) return;
+      visiting.add(table);
+      parents.get(table)?.forEach(parent => visit(parent));
+      visiting.delete(table);
+      ordered.push(table);
+    };
+    tables.forEach(visit);
+    return ordered;
+  }
+
+  private async selfReferencingTables(client: PoolClient): Promise<Set<string>> {
+    const { rows } = await client.query(
+      `SELECT DISTINCT con.conrelid::regclass::text AS name FROM pg_constraint con
+        WHERE con.contype = 'f' AND con.conrelid = con.confrelid`
+    );
+    return new Set(rows.map((row: { name: string }) => row.name.replace(/"/g, '')));
+  }
+
+  // Generated columns are computed by the database and cannot be written
+  private async insertableColumns(client: PoolClient): Promise<Map<string, string[]>> {
+    const { rows } = await client.query(
+      `SELECT table_name, column_name FROM information_schema.columns
+        WHERE table_schema = 'public' AND is_generated = 'NEVER' ORDER BY table_name, ordinal_position`
+    );
+    const columns = new Map<string, string[]>();
+    rows.forEach((row: { table_name: string; column_name: string }) => {
+      columns.set(row.table_name, [...(columns.get(row.table_name) ?? []), row.column_name]);
+    });
+    return columns;
+  }
+
+  private async hashFile(filePath: string): Promise<string> {
+    const hash = crypto.createHash('sha256');
+    for await (const chunk of createReadStream(filePath)) hash.update(chunk);
+    return hash.digest('hex');
+  }
+}
+
+export const libraryBackupService = new LibraryBackupService();
@@ -0,0 +1,288 @@
+import fs from "fs/promises";
+import { constants as fsConstants } from "fs";
+import path from "path";
+import { pool } from "../db";
+import { storage } from "../storage";
+import { formatRegistry, SUPPORTED_FORMATS_SETTING } from "./formatRegistry";
+import { ENABLED_HOLIDAYS_SETTING, DEFAULT_HOLIDAY_SETS } from "./holidayCalendars";
+import { FACE_DETECTION_THRESHOLD_SETTING } from "./faceDetection";
+import { DELETED_RETENTION_SETTING } from "./softDelete";
+import { RUN_IN_BACKGROUND_SETTING } from "./background";
+import { STORAGE_NAMING_SETTING } from "./fileManager";
+import { thumbnailService, THUMBNAIL_QUALITY_SETTING, THUMBNAIL_CHROMA_SETTING, THUMBNAIL_SHARPEN_SETTING } from "./thumbnailService";
+import { aiProviderRegistry } from "./aiProviders";
+import { BACKUP_INTERVAL_SETTING, BACKUP_DESTINATION_SETTING, BACKUP_KEEP_SETTING } from "./libraryBackup";
+import { getLibraryRoot, setLibraryRoot } from "../utils/libraryPaths";
+import { readAppConfig } from "../utils/appConfig";
+import type { InsertSetting } from "@shared/schema";
+
+export interface LibraryInitOptions {
+  createMissing?: boolean; // create the root if it does not exist (default true)
+  applyMigrations?: boolean; // default true
+  seedDefaults?: boolean; // default true
+  backfillExif?: boolean; // fill the EXIF columns of photos missing them (default true)
+}
+
+// Where earlier versions recorded a moved library; the app config now holds it
+export const LIBRARY_ROOT_SETTING = 'library_root';
+
+export type LibraryInitStepStatus = 'completed' | 'skipped' | 'failed';
+
+export interface LibraryInitStep {
+  name: string;
+  status: LibraryInitStepStatus;
+  message?: string;
+  durationMs: number;
+}
+
+export interface LibraryInitReport {
+  root: string;
+  success: boolean;
+  startedAt: string;
+  steps: LibraryInitStep[];
+}
+
+// Folders every library needs, relative to the root
+const LIBRARY_FOLDERS = [
+  'media',
+  'media/silver',
+  'media/gold',
+  'media/archive',
+  'trash',
+  'temp',
+  'thumbnails',
+  'calendars',
+  'quarantine',
+];
+
+const DEFAULT_SETTINGS: InsertSetting[] = [
+  { key: 'silver_naming_pattern', value: 'datetime', category: 'tiers', description: 'File naming pattern for Silver tier copies' },
+  { key: 'gold_naming_pattern', value: 'datetime', category: 'tiers', description: 'File naming pattern for Gold tier copies' },
+  { key: SUPPORTED_FORMATS_SETTING, value: '[]', category: 'formats', description: 'Overrides for the built-in supported file formats' },
+  { key: ENABLED_HOLIDAYS_SETTING, value: JSON.stringify(DEFAULT_HOLIDAY_SETS), category: 'events', description: 'Enabled holiday country sets for event detection' },
+  { key: FACE_DETECTION_THRESHOLD_SETTING, value: '50', category: 'faces', description: 'Minimum confidence (0-100) for a detected face to be kept' },
+  { key: STORAGE_NAMING_SETTING, value: 'original', category: 'tiers', description: 'Stored file names: original, or hash to prefix names with the file hash so they never collide' },
+  { key: DELETED_RETENTION_SETTING, value: '30', category: 'general', description: 'Days deleted photos, people, albums and tags can be restored before they are purged' },
+  { key: RUN_IN_BACKGROUND_SETTING, value: 'true', category: 'general', description: 'Keep imports and background jobs running in the tray after the main window is closed' },
+  { key: THUMBNAIL_QUALITY_SETTING, value: '60', category: 'thumbnails', description: 'Highest JPEG quality (1-100) for thumbnails' },
+  { key: THUMBNAIL_CHROMA_SETTING, value: '4:2:0', category: 'thumbnails', description: 'Thumbnail chroma subsampling: 4:2:0, or 4:4:4 for sharper colour edges at a larger size' },
+  { key: THUMBNAIL_SHARPEN_SETTING, value: 'off', category: 'thumbnails', description: 'Sharpening after downscaling thumbnails: off, light or strong' },
+  { key: BACKUP_INTERVAL_SETTING, value: '0', category: 'backup', description: 'Hours between automatic database backups; 0 turns them off' },
+  { key: BACKUP_DESTINATION_SETTING, value: '', category: 'backup', description: 'Folder automatic backups are written to; empty for the backups folder in the library' },
+  { key: BACKUP_KEEP_SETTING, value: '7', category: 'backup', description: 'Automatic backups to keep; older ones are deleted' },
+];
+
+class StepFailedError extends Error {}
+
+/**
+ * Explicit library setup: folder structure, SQL migrations and default
+ * settings, with a status per step instead of silently depending on the
+ * process working directory.
+ */
+class LibraryInitService {
+  private lastReport: LibraryInitReport | null = null;
+
+  getLastReport(): LibraryInitReport | null {
+    return this.lastReport;
+  }
+
+  /**
+   * Library root to open at startup: the environment wins, then the app
+   * config in the app data directory, then the location earlier versions
+   * recorded in the database, then the default
+   */
+  async resolveConfiguredRoot(): Promise<string> {
+    if (process.env.PICTALLION_LIBRARY_ROOT) {
+      return getLibraryRoot();
+    }
+    const config = await readAppConfig();
+    if (config.libraryRoot) {
+      return config.libraryRoot;
+    }
+    try {
+      const setting = await storage.getSettingByKey(LIBRARY_ROOT_SETTING);
+      if (setting?.value) return setting.value;
+    } catch (error) {
+      console.error('Failed to read library root setting:', error);
+    }
+    return getLibraryRoot();
+  }
+
+  async initializeLibrary(root: string, options: LibraryInitOptions = {}): Promise<LibraryInitReport> {
+    const { createMissing = true, applyMigrations = true, seedDefaults = true, backfillExif = true } = options;
+    const resolvedRoot = path.resolve(root);
+    const report: LibraryInitReport = {
+      root: resolvedRoot,
+      success: true,
+      startedAt: new Date().toISOString(),
+      steps: [],
+    };
+
+    // Later steps need a usable root; a failed migration or seed does not block the rest
+    let blocked = false;
+    const runStep = async (name: string, enabled: boolean, action: () => Promise<string | undefined>, critical = false) => {
+      const startedAt = Date.now();
+      if (!enabled || blocked) {
+        report.steps.push({
+          name,
+          status: 'skipped',
+          message: enabled ? 'Skipped after an earlier step failed' : 'Disabled by options',
+          durationMs: 0,
+        });
+        return;
+      }
+      try {
+        const message = await action();
+        report.steps.push({ name, status: 'completed', message, durationMs: Date.now() - startedAt });
+      } catch (error) {
+        report.success = false;
+        blocked = blocked || critical;
+        report.steps.push({
+          name,
+          status: 'failed',
+          message: error instanceof Error ? error.message : String(error),
+          durationMs: Date.now() - startedAt,
+        });
+      }
+    };
+
+    await runStep('root', true, () => this.prepareRoot(resolvedRoot, createMissing), true);
+    await runStep('folders', true, () => this.createFolders(resolvedRoot), true);
+    await runStep('migrations', applyMigrations, () => this.applyMigrations());
+    await runStep('exif', applyMigrations && backfillExif, async () => {
+      const indexed = await storage.backfillExifColumns();
+      return indexed > 0 ? `Indexed EXIF fields of ${indexed} photos` : 'EXIF fields up to date';
+    });
+    await runStep('settings', seedDefaults, () => this.seedSettings());
+    await runStep('formats', true, async () => {
+      await formatRegistry.refresh();
+      thumbnailService.refreshEncoderSettings();
+      await aiProviderRegistry.refresh();
+      return `${formatRegistry.getFormats().filter(format => format.enabled).length} importable formats`;
+    });
+
+    if (report.steps[0].status === 'completed') {
+      setLibraryRoot(resolvedRoot);
+    }
+
+    this.lastReport = report;
+    return report;
+  }
+
+  private async prepareRoot(root: string, createMissing: boolean): Promise<string> {
+    let created = false;
+    try {
+      const stats = await fs.stat(root);
+      if (!stats.isDirectory()) {
+        throw new StepFailedError(`${root} exists but is not a directory`);
+      }
+    } catch (error) {
+      if (error instanceof StepFailedError) throw error;
+      if (!createMissing) {
+        throw new Error(`Library root ${root} does not exist`);
+      }
+      await fs.mkdir(root, { recursive: true });
+      created = true;
+    }
+
+    try {
+      await fs.access(root, fsConstants.R_OK | fsConstants.W_OK);
+    } catch {
+      throw new Error(`Library root ${root} is not readable and writable`);
+    }
+    return created ? `Created ${root}` : `Using ${root}`;
+  }
+
+  private async createFolders(root: string): Promise<string> {
+    const created: string[] = [];
+    for (const folder of LIBRARY_FOLDERS) {
+      const fullPath = path.join(root, folder);
+      try {
+        await fs.access(fullPath);
+      } catch {
+        await fs.mkdir(fullPath, { recursive: true });
+        created.push(folder);
+      }
+    }
+    return created.length > 0 ? `Created ${created.join(', ')}` : 'All folders present';
+  }
+
+  /**
+   * Run each SQL file in server/migrations once, in file name order. Files
+   * carry a numeric prefix ("0042-add-things.sql") so that order is the order
+   * they were written in, and a migration can rely on the tables of earlier ones.
+   */
+  private async applyMigrations(): Promise<string> {
+    const migrationsDir = await this.findMigrationsDir();
+    const files = (await fs.readdir(migrationsDir)).filter(file => file.endsWith('.sql')).sort();
+
+    await pool.query(`CREATE TABLE IF NOT EXISTS library_migrations (
+      name TEXT PRIMARY KEY,
+      applied_at TIMESTAMP NOT NULL DEFAULT NOW()
+    )`);
+    const { rows } = await pool.query('SELECT name FROM library_migrations');
+    const applied = new Set(rows.map((row: { name: string }) => row.name));
+
+    const newlyApplied: string[] = [];
+    for (const file of files) {
+      if (applied.has(file)) continue;
+      // Libraries migrated before the prefixes recorded the bare name
+      const legacyName = file.replace(/^\d+-/, '');
+      if (legacyName !== file && applied.has(legacyName)) {
+        await pool.query('INSERT INTO library_migrations (name) VALUES ($1)', [file]);
+        continue;
+      }
+      const sqlText = await fs.readFile(path.join(migrationsDir, file), 'utf8');
+      const client = await pool.connect();
+      try {
+        await client.query('BEGIN');
+        await client.query(sqlText);
+        await client.query('INSERT INTO library_migrations (name) VALUES ($1)', [file]);
+        await client.query('COMMIT');
+        newlyApplied.push(file);
+      } catch (error) {
+        await client.query('ROLLBACK');
+        throw new Error(`${file}: ${error instanceof Error ? error.message : String(error)}`);
+      } finally {
+        client.release();
+      }
+    }
+
+    return newlyApplied.length > 0
+      ? `Applied ${newlyApplied.join(', ')}`
+      : `Up to date (${files.length} migrations)`;
+  }
+
+  private async findMigrationsDir(): Promise<string> {
+    // Source layout in development, next to the bundle or the working directory in builds
+    const candidates = [
+      path.resolve(import.meta.dirname, '..', 'migrations'),
+      path.resolve(import.meta.dirname, '..', 'server', 'migrations'),
+      path.resolve(process.cwd(), 'server', 'migrations'),
+    ];
+    for (const candidate of candidates) {
+      try {
+        if ((await fs.stat(candidate)).isDirectory()) return candidate;
+      } catch {
+        // try the next location
+      }
+    }
+    throw new Error(`Migrations folder not found. Tried: ${candidates.join(', ')}`);
+  }
+
+  /**
+   * Create default settings that are missing; existing values are never overwritten
+   */
+  private async seedSettings(): Promise<string> {
+    const seeded: string[] = [];
+    for (const setting of DEFAULT_SETTINGS) {
+      if (!(await storage.getSettingByKey(setting.key))) {
+        await storage.createSetting(setting);
+        seeded.push(setting.key);
+      }
+    }
+    return seeded.length > 0 ? `Seeded ${seeded.join(', ')}` : 'All defaults present';
+  }
+}
+
+export const libraryInitService = new LibraryInitService();
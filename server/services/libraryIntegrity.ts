@@ -0,0 +1,320 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { localImportService } from "./localImport";
+import { provenanceService } from "./provenance";
+import type { CancellationToken } from "./operations";
+import { libraryPath } from "../utils/libraryPaths";
+import { hashFile } from "../utils/files";
+import type { FileVersion } from "@shared/schema";
+
+export const integrityScanSchema = z.object({
+  verifyHashes: z.boolean().default(true), // read every file; without it only presence is checked
+});
+
+export const resolveIntegritySchema = z.object({
+  items: z.array(z.object({
+    action: z.enum(['relink', 'reimport', 'quarantine']),
+    photoId: z.string().optional(),
+    path: z.string().min(1).optional(), // relative to the library root, as in the scan report
+  })).min(1),
+});
+
+export type IntegrityAction = z.infer<typeof resolveIntegritySchema>['items'][number];
+
+export interface IntegrityIssue {
+  kind: 'orphaned' | 'missing' | 'hash_mismatch';
+  path: string; // relative to the library root
+  photoId: string | null; // the photo the path belongs to; null for orphaned files
+  expectedHash: string | null;
+  actualHash: string | null; // null for missing files
+  size: number | null;
+  // missing or changed photo: an orphaned file with the expected content, to relink to
+  candidatePath: string | null;
+  // orphaned file: the missing or changed photo it is the content of
+  candidatePhotoId: string | null;
+  actions: Array<IntegrityAction['action']>;
+}
+
+export interface IntegrityReport {
+  scannedFiles: number;
+  checkedPhotos: number;
+  evictedPhotos: number; // originals moved to cold storage on purpose; not reported missing
+  usage: Record<string, { files: number; bytes: number }>; // by tier folder, "media/silver"
+  orphanedBytes: number;
+  issues: IntegrityIssue[];
+}
+
+export interface IntegrityResolution {
+  action: IntegrityAction['action'];
+  photoId: string | null;
+  path: string | null;
+  status: 'done' | 'failed';
+  message: string;
+}
+
+export class LibraryIntegrityError extends Error {}
+
+const MEDIA_FOLDER = 'media';
+const QUARANTINE_FOLDER = 'quarantine';
+// Files operating systems drop into folders on their own
+const SYSTEM_FILES = new Set(['.ds_store', 'thumbs.db', 'desktop.ini']);
+
+/**
+ * Reconcile the media folder with the database: files on disk no photo
+ * points at, photos whose file is gone, and files whose content no longer
+ * matches the hash recorded at import. Each discrepancy can be fixed by
+ * relinking the photo to a file with its content, re-importing (an orphan
+ * as a new photo, or a lost file from where it was first imported from), or
+ * moving the file aside into the quarantine folder.
+ */
+class LibraryIntegrityService {
+  async scan(
+    verifyHashes: boolean,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<IntegrityReport> {
+    const onDisk = await this.listFiles(MEDIA_FOLDER);
+    const photos = await storage.getAllFileVersions();
+    const evicted = new Set((await storage.getColdStorageCopies({ evicted: true })).map(copy => copy.photoId));
+
+    const usage: IntegrityReport['usage'] = {};
+    onDisk.forEach((size, relativePath) => {
+      const folder = relativePath.split('/').slice(0, 2).join('/');
+      usage[folder] = usage[folder] ?? { files: 0, bytes: 0 };
+      usage[folder].files++;
+      usage[folder].bytes += size;
+    });
+
+    const referenced = new Set<string>();
+    const issues: IntegrityIssue[] = [];
+    const wanted: Array<{ issue: IntegrityIssue; hash: string }> = []; // photos whose content is not where it should be
+    const total = photos.length + onDisk.size;
+    let evictedPhotos = 0;
+
+    for (let index = 0; index < photos.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(index, total);
+      const photo = photos[index];
+      const relativePath = this.normalize(photo.filePath);
+      referenced.add(relativePath);
+
+      if (!onDisk.has(relativePath)) {
+        if (evicted.has(photo.id)) {
+          evictedPhotos++;
+          continue;
+        }
+        const issue = this.issue('missing', relativePath, photo, null, null);
+        issues.push(issue);
+        wanted.push({ issue, hash: photo.fileHash });
+        continue;
+      }
+      if (!verifyHashes) continue;
+
+      const actualHash = await hashFile(libraryPath(relativePath));
+      if (actualHash !== photo.fileHash) {
+        const issue = this.issue('hash_mismatch', relativePath, photo, actualHash, onDisk.get(relativePath)!);
+        issues.push(issue);
+        wanted.push({ issue, hash: photo.fileHash });
+      }
+    }
+
+    const orphans = Array.from(onDisk.keys()).filter(relativePath => !referenced.has(relativePath));
+    let orphanedBytes = 0;
+    for (let index = 0; index < orphans.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(photos.length + index, total);
+      const relativePath = orphans[index];
+      const size = onDisk.get(relativePath)!;
+      orphanedBytes += size;
+      const actualHash = await hashFile(libraryPath(relativePath));
+      const issue = this.issue('orphaned', relativePath, null, actualHash, size);
+
+      // A photo that lost its file may find it here, moved or renamed
+      const match = wanted.find(entry => entry.hash === actualHash && !entry.issue.candidatePath);
+      if (match) {
+        match.issue.candidatePath = relativePath;
+        match.issue.actions.unshift('relink');
+        issue.candidatePhotoId = match.issue.photoId;
+      }
+      issues.push(issue);
+    }
+    onProgress?.(total, total);
+
+    // Missing or changed files can come back from where they were imported from, if that still has them
+    for (const { issue, hash } of wanted) {
+      const photo = photos.find(candidate => candidate.id === issue.photoId)!;
+      if (await this.findSource(photo, hash)) issue.actions.push('reimport');
+    }
+
+    return {
+      scannedFiles: onDisk.size,
+      checkedPhotos: photos.length - evictedPhotos,
+      evictedPhotos,
+      usage,
+      orphanedBytes,
+      issues,
+    };
+  }
+
+  async resolve(items: IntegrityAction[]): Promise<IntegrityResolution[]> {
+    const results: IntegrityResolution[] = [];
+    for (const item of items) {
+      const base = { action: item.action, photoId: item.photoId ?? null, path: item.path ?? null };
+      try {
+        const message = await this.apply(item);
+        results.push({ ...base, status: 'done', message });
+      } catch (error) {
+        if (!(error instanceof LibraryIntegrityError)) {
+          console.error(`Failed to ${item.action} ${item.photoId ?? item.path}:`, error);
+        }
+        results.push({ ...base, status: 'failed', message: error instanceof Error ? error.message : 'Failed' });
+      }
+    }
+    return results;
+  }
+
+  private async apply(item: IntegrityAction): Promise<string> {
+    const photo = item.photoId ? await storage.getFileVersion(item.photoId) : undefined;
+    if (item.photoId && !photo) throw new LibraryIntegrityError(`Photo ${item.photoId} not found`);
+    const relativePath = item.path ? this.checkPath(item.path) : null;
+
+    switch (item.action) {
+      case 'relink': {
+        if (!photo || !relativePath) throw new LibraryIntegrityError('Relinking needs a photoId and a path');
+        if ((await hashFile(libraryPath(relativePath))) !== photo.fileHash) {
+          throw new LibraryIntegrityError(`${relativePath} does not have the photo's content`);
+        }
+        const owner = await storage.getFileVersionByPath(relativePath);
+        if (owner && owner.id !== photo.id) throw new LibraryIntegrityError(`${relativePath} belongs to photo ${owner.id}`);
+        await storage.updateFileVersion(photo.id, { filePath: relativePath });
+        await provenanceService.record(photo, 'RELINKED', `File relinked from ${photo.filePath} to ${relativePath}`, { from: photo.filePath, to: relativePath });
+        return `Photo now points at ${relativePath}`;
+      }
+
+      case 'reimport': {
+        if (photo) {
+          const source = await this.findSource(photo, photo.fileHash);
+          if (!source) throw new LibraryIntegrityError('No import source with the original content is available');
+          const target = libraryPath(photo.filePath);
+          if (await this.exists(target)) await this.quarantine(this.normalize(photo.filePath));
+          await fs.mkdir(path.dirname(target), { recursive: true });
+          await fs.copyFile(source, target);
+          await provenanceService.record(photo, 'RESTORED', `File restored from ${source}`, { source, filePath: photo.filePath });
+          return `Restored from ${source}`;
+        }
+        if (!relativePath) throw new LibraryIntegrityError('Re-importing needs a photoId or a path');
+        if (await storage.getFileVersionByPath(relativePath)) throw new LibraryIntegrityError(`${relativePath} already belongs to a photo`);
+        const imported = await localImportService.importFile(libraryPath(relativePath), {
+          sourceDevice: null,
+          details: `Re-imported orphaned library file ${relativePath}`,
+        });
+        // The file now has a copy in the library, or was one already
+        await this.quarantine(relativePath);
+        return imported.decision === 'imported'
+          ? `Imported as photo ${imported.photoId}`
+          : `Already in the library as photo ${imported.photoId}; file quarantined`;
+      }
+
+      case 'quarantine': {
+        const target = relativePath ?? (photo ? this.normalize(photo.filePath) : null);
+        if (!target) throw new LibraryIntegrityError('Quarantining needs a path or a photoId');
+        const owner = await storage.getFileVersionByPath(target);
+        if (owner && owner.fileHash === await hashFile(libraryPath(target))) {
+          throw new LibraryIntegrityError(`${target} is a photo's intact file`);
+        }
+        return `Moved to ${await this.quarantine(target)}`;
+      }
+    }
+  }
+
+  /**
+   * An import source of the photo's asset that still has the given content
+   */
+  private async findSource(photo: FileVersion, hash: string): Promise<string | null> {
+    for (const source of await storage.getPhotoSources(photo.mediaAssetId)) {
+      if (source.fileHash !== hash || !path.isAbsolute(source.sourcePath)) continue;
+      if (await this.exists(source.sourcePath)) return source.sourcePath;
+    }
+    return null;
+  }
+
+  private async quarantine(relativePath: string): Promise<string> {
+    let target = path.posix.join(QUARANTINE_FOLDER, relativePath);
+    // Quarantining the same path twice keeps both files
+    for (let attempt = 1; await this.exists(libraryPath(target)); attempt++) {
+      const parsed = path.posix.parse(path.posix.join(QUARANTINE_FOLDER, relativePath));
+      target = path.posix.join(parsed.dir, `${parsed.name}.${attempt}${parsed.ext}`);
+    }
+    await fs.mkdir(path.dirname(libraryPath(target)), { recursive: true });
+    await fs.rename(libraryPath(relativePath), libraryPath(target));
+    return target;
+  }
+
+  private issue(kind: IntegrityIssue['kind'], relativePath: string, photo: FileVersion | null, actualHash: string | null, size: number | null): IntegrityIssue {
+    return {
+      kind,
+      path: relativePath,
+      photoId: photo?.id ?? null,
+      expectedHash: photo?.fileHash ?? null,
+      actualHash,
+      size,
+      candidatePath: null,
+      candidatePhotoId: null,
+      actions: kind === 'orphaned' ? ['reimport', 'quarantine'] : kind === 'hash_mismatch' ? ['quarantine'] : [],
+    };
+  }
+
+  /**
+   * Every file under a library folder, by path relative to the library root
+   */
+  private async listFiles(folder: string): Promise<Map<string, number>> {
+    const files = new Map<string, number>();
+    const walk = async (relativeFolder: string) => {
+      let entries;
+      try {
+        entries = await fs.readdir(libraryPath(relativeFolder), { withFileTypes: true });
+      } catch {
+        return;
+      }
+      for (const entry of entries) {
+        const relativePath = path.posix.join(relativeFolder, entry.name);
+        if (entry.isDirectory()) {
+          await walk(relativePath);
+        } else if (entry.isFile() && !SYSTEM_FILES.has(entry.name.toLowerCase()) && !entry.name.endsWith('.tmp')) {
+          files.set(relativePath, (await fs.stat(libraryPath(relativePath))).size);
+        }
+      }
+    };
+    await walk(folder);
+    return files;
+  }
+
+  // Stored paths may use Windows separators
+  private normalize(filePath: string): string {
+    return filePath.split(path.sep).join('/').replace(/\\/g, '/');
+  }
+
+  /**
+   * A path from a request, kept inside the media folder
+   */
+  private checkPath(relativePath: string): string {
+    const normalized = path.posix.normalize(this.normalize(relativePath));
+    if (!normalized.startsWith(`${MEDIA_FOLDER}/`) || normalized.split('/').includes('..')) {
+      throw new LibraryIntegrityError(`${relativePath} is not inside the media folder`);
+    }
+    return normalized;
+  }
+
+  private async exists(filePath: string): Promise<boolean> {
+    try {
+      await fs.access(filePath);
+      return true;
+    } catch {
+      return false;
+    }
+  }
+}
+
+export const libraryIntegrityService = new LibraryIntegrityService();
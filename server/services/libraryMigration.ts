@@ -0,0 +1,284 @@
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import { storage } from "../storage";
+import { getAppDataDir, getLibraryRoot, setLibraryRoot } from "../utils/libraryPaths";
+import { writeAppConfig } from "../utils/appConfig";
+import { hashFile } from "../utils/files";
+import { LIBRARY_ROOT_SETTING } from "./libraryInit";
+import type { CancellationToken } from "./operations";
+
+const LEGACY_DATABASE_FILE = 'pictallion.db';
+
+// The requested move is not possible; nothing was changed
+export class LibraryMigrationError extends Error {}
+
+export interface LibraryCandidate {
+  root: string;
+  isCurrent: boolean;
+  tiers: string[]; // tier folders found under media/
+  hasDatabaseFile: boolean;
+  fileCount: number;
+  totalBytes: number;
+}
+
+export interface LibraryMigrationOptions {
+  removeOld?: boolean; // delete the old copy once every file has been verified
+}
+
+export interface LibraryMigrationResult {
+  oldRoot: string;
+  newRoot: string;
+  success: boolean;
+  cancelled: boolean;
+  filesCopied: number;
+  bytesCopied: number;
+  pathsRewritten: number;
+  hashMismatches: string[];
+  missingReferences: string[]; // database paths present in the old library but not the new one
+  switchedLibrary: boolean;
+  removedOld: boolean;
+  error?: string;
+}
+
+/**
+ * Finds libraries left in whatever working directory earlier versions were
+ * started from, and moves one to a new root with hash verification.
+ */
+class LibraryMigrationService {
+  /**
+   * Look for library folders in the places earlier versions could have used
+   */
+  async locateExistingLibraries(): Promise<LibraryCandidate[]> {
+    const home = os.homedir();
+    const bases = [
+      process.cwd(),
+      path.resolve(import.meta.dirname, '..'),
+      path.resolve(import.meta.dirname, '..', '..'),
+      home,
+      path.join(home, 'Pictallion'),
+      path.join(home, 'Documents', 'Pictallion'),
+      path.join(home, 'Pictures', 'Pictallion'),
+      path.join(home, 'Desktop', 'Pictallion'),
+      path.join(home, '.config', 'Pictallion'),
+      path.join(home, 'Library', 'Application Support', 'Pictallion'),
+    ];
+    if (process.env.APPDATA) {
+      bases.push(path.join(process.env.APPDATA, 'Pictallion'));
+    }
+    const appDataDir = getAppDataDir();
+    if (appDataDir) {
+      bases.push(appDataDir);
+    }
+
+    const roots = new Set<string>();
+    for (const base of bases) {
+      roots.add(path.resolve(base, 'data'));
+      roots.add(path.resolve(base));
+    }
+    roots.add(getLibraryRoot());
+
+    const candidates: LibraryCandidate[] = [];
+    for (const root of Array.from(roots)) {
+      const candidate = await this.inspect(root);
+      if (candidate) candidates.push(candidate);
+    }
+    return candidates;
+  }
+
+  /**
+   * Copy a library to a new root, verify every file by hash, make stored
+   * absolute paths relative to the new root and switch to it. The old copy
+   * is only removed when asked and when verification found no problems.
+   */
+  async migrateLibrary(
+    oldRoot: string,
+    newRoot: string,
+    options: LibraryMigrationOptions = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<LibraryMigrationResult> {
+    const source = path.resolve(oldRoot);
+    const destination = path.resolve(newRoot);
+    const result: LibraryMigrationResult = {
+      oldRoot: source,
+      newRoot: destination,
+      success: false,
+      cancelled: false,
+      filesCopied: 0,
+      bytesCopied: 0,
+      pathsRewritten: 0,
+      hashMismatches: [],
+      missingReferences: [],
+      switchedLibrary: false,
+      removedOld: false,
+    };
+
+    if (!(await this.inspect(source))) {
+      throw new LibraryMigrationError(`${source} does not look like a Pictallion library`);
+    }
+    if (this.isWithin(destination, source) || this.isWithin(source, destination)) {
+      throw new LibraryMigrationError('The new library location cannot be inside the old one or contain it');
+    }
+    const destinationExisted = await this.exists(destination);
+    if (destinationExisted && (await fs.readdir(destination)).length > 0) {
+      throw new LibraryMigrationError(`${destination} is not empty`);
+    }
+
+    const files = await this.listFiles(source);
+    try {
+      for (let index = 0; index < files.length; index++) {
+        if (token?.isCancelled) {
+          result.cancelled = true;
+          break;
+        }
+        onProgress?.(index, files.length);
+
+        const relativePath = files[index];
+        const from = path.join(source, relativePath);
+        const to = path.join(destination, relativePath);
+        await fs.mkdir(path.dirname(to), { recursive: true });
+        await fs.copyFile(from, to);
+
+        const [sourceHash, copyHash] = await Promise.all([hashFile(from), hashFile(to)]);
+        if (sourceHash !== copyHash) {
+          result.hashMismatches.push(relativePath);
+        }
+        result.filesCopied++;
+        result.bytesCopied += (await fs.stat(to)).size;
+      }
+    } catch (error) {
+      result.error = error instanceof Error ? error.message : String(error);
+    }
+
+    if (result.cancelled || result.error || result.hashMismatches.length > 0) {
+      // The old library is untouched; drop the incomplete copy
+      await this.removeCopy(destination, destinationExisted);
+      if (!result.error && !result.cancelled) {
+        result.error = `${result.hashMismatches.length} file(s) did not match after copying`;
+      }
+      return result;
+    }
+
+    // Everything the database points at should be in the new library if it was in the old one
+    const photos = await storage.getAllFileVersions();
+    for (const photo of photos) {
+      const relativePath = path.isAbsolute(photo.filePath) && this.isWithin(photo.filePath, source)
+        ? path.relative(source, photo.filePath)
+        : photo.filePath;
+      if (path.isAbsolute(relativePath)) continue; // outside the library, nothing to move
+      if (await this.exists(path.join(source, relativePath)) && !(await this.exists(path.join(destination, relativePath)))) {
+        result.missingReferences.push(relativePath);
+      }
+    }
+    if (result.missingReferences.length > 0) {
+      await this.removeCopy(destination, destinationExisted);
+      result.error = `${result.missingReferences.length} referenced file(s) missing from the new library`;
+      return result;
+    }
+
+    // Stored paths are relative to the root; absolute ones pointing into the old library are rewritten
+    result.pathsRewritten = await storage.rewriteFilePathPrefix(source + path.sep, '');
+
+    if (path.resolve(getLibraryRoot()) === source) {
+      setLibraryRoot(destination);
+      await writeAppConfig({ libraryRoot: destination });
+      // The database setting is still a startup fallback; keep it from pointing at the old copy
+      if (await storage.getSettingByKey(LIBRARY_ROOT_SETTING)) {
+        await storage.updateSetting(LIBRARY_ROOT_SETTING, destination);
+      }
+      result.switchedLibrary = true;
+    }
+
+    if (options.removeOld) {
+      await fs.rm(source, { recursive: true, force: true });
+      result.removedOld = true;
+    }
+
+    result.success = true;
+    return result;
+  }
+
+  /**
+   * Whether a folder holds a library: tier folders under media/ or a legacy database file
+   */
+  async isLibrary(root: string): Promise<boolean> {
+    return (await this.inspect(path.resolve(root))) !== null;
+  }
+
+  private async inspect(root: string): Promise<LibraryCandidate | null> {
+    const tiers: string[] = [];
+    for (const tier of ['bronze', 'silver', 'gold', 'archive']) {
+      if (await this.exists(path.join(root, 'media', tier))) tiers.push(tier);
+    }
+    const hasDatabaseFile = await this.exists(path.join(root, LEGACY_DATABASE_FILE))
+      || await this.exists(path.join(root, '..', LEGACY_DATABASE_FILE));
+    if (tiers.length === 0 && !hasDatabaseFile) return null;
+
+    let fileCount = 0;
+    let totalBytes = 0;
+    if (tiers.length > 0) {
+      const files = await this.listFiles(path.join(root, 'media'));
+      fileCount = files.length;
+      for (const file of files) {
+        totalBytes += (await fs.stat(path.join(root, 'media', file))).size;
+      }
+    }
+
+    return {
+      root,
+      isCurrent: path.resolve(getLibraryRoot()) === root,
+      tiers,
+      hasDatabaseFile,
+      fileCount,
+      totalBytes,
+    };
+  }
+
+  /**
+   * Every file below a directory, relative to it
+   */
+  private async listFiles(dir: string, prefix = ''): Promise<string[]> {
+    const files: string[] = [];
+    let entries;
+    try {
+      entries = await fs.readdir(path.join(dir, prefix), { withFileTypes: true });
+    } catch {
+      return files;
+    }
+    for (const entry of entries) {
+      const relativePath = path.join(prefix, entry.name);
+      if (entry.isDirectory()) {
+        files.push(...(await this.listFiles(dir, relativePath)));
+      } else if (entry.isFile()) {
+        files.push(relativePath);
+      }
+    }
+    return files;
+  }
+
+  private async exists(filePath: string): Promise<boolean> {
+    try {
+      await fs.access(filePath);
+      return true;
+    } catch {
+      return false;
+    }
+  }
+
+  private isWithin(child: string, parent: string): boolean {
+    const relative = path.relative(parent, child);
+    return relative === '' || (!relative.startsWith('..') && !path.isAbsolute(relative));
+  }
+
+  private async removeCopy(destination: string, keepFolder: boolean): Promise<void> {
+    if (keepFolder) {
+      const entries = await fs.readdir(destination);
+      await Promise.all(entries.map(entry => fs.rm(path.join(destination, entry), { recursive: true, force: true })));
+    } else {
+      await fs.rm(destination, { recursive: true, force: true });
+    }
+  }
+}
+
+export const libraryMigrationService = new LibraryMigrationService();
@@ -0,0 +1,85 @@
+import fs from "fs/promises";
+import path from "path";
+import crypto from "crypto";
+import { storage } from "../storage";
+import { fileManager } from "./fileManager";
+import { formatRegistry } from "./formatRegistry";
+import { provenanceService } from "./provenance";
+import { smartCollectionService } from "./smartCollectionService";
+
+export type LocalImportDecision = 'imported' | 'duplicate';
+
+export interface LocalImportResult {
+  decision: LocalImportDecision;
+  photoId: string; // the new photo, or the existing one a duplicate matched
+  fileHash: string;
+}
+
+export interface LocalImportSource {
+  sourceDevice: string | null;
+  details: string; // provenance text for the INGESTED entry
+  data?: Record<string, unknown>; // extra provenance fields
+}
+
+/**
+ * Import one file that is already on disk into the bronze tier. The source is
+ * copied, never moved. A file whose hash is already in the library is not
+ * copied again; it is only recorded as another source of the existing photo.
+ */
+class LocalImportService {
+  async importFile(filePath: string, source: LocalImportSource): Promise<LocalImportResult> {
+    const fileBuffer = await fs.readFile(filePath);
+    const fileHash = crypto.createHash('md5').update(fileBuffer).digest('hex');
+
+    const existing = await storage.getFileByHash(fileHash);
+    if (existing) {
+      await storage.createPhotoSource({
+        mediaAssetId: existing.mediaAssetId,
+        sourcePath: filePath,
+        sourceDevice: source.sourceDevice,
+        fileHash,
+        isDuplicate: true,
+      });
+      return { decision: 'duplicate', photoId: existing.id, fileHash };
+    }
+
+    const originalFilename = path.basename(filePath);
+    const mediaAsset = await storage.createMediaAsset({ originalFilename });
+    const bronzePath = await fileManager.importToBronze(filePath, originalFilename, fileHash);
+    const metadata = await fileManager.extractMetadata(bronzePath);
+    const dimensions = formatRegistry.supports(originalFilename, 'thumbnail')
+      ? await fileManager.getImageDimensions(bronzePath)
+      : null;
+
+    const photo = await storage.createFileVersion({
+      mediaAssetId: mediaAsset.id,
+      tier: 'bronze',
+      filePath: bronzePath,
+      fileHash,
+      fileSize: fileBuffer.length,
+      mimeType: formatRegistry.getMimeType(originalFilename) ?? 'application/octet-stream',
+      width: dimensions?.width ?? null,
+      height: dimensions?.height ?? null,
+      metadata: metadata as any,
+      isReviewed: false,
+    });
+    await storage.createPhotoSource({
+      mediaAssetId: mediaAsset.id,
+      sourcePath: filePath,
+      sourceDevice: source.sourceDevice,
+      fileHash,
+      isDuplicate: false,
+    });
+    await provenanceService.record(photo, 'INGESTED', source.details, {
+      source: filePath,
+      sourceDevice: source.sourceDevice,
+      fileHash,
+      ...source.data,
+    });
+    smartCollectionService.photosChanged([photo.id]);
+
+    return { decision: 'imported', photoId: photo.id, fileHash };
+  }
+}
+
+export const localImportService = new LocalImportService();
@@ -0,0 +1,394 @@
+import fs from "fs/promises";
+import ExifImage from "exif";
+// @ts-ignore - piexifjs doesn't have type definitions
+import piexifjs from "piexifjs";
+import type { ExifMetadata } from "@shared/schema";
+
+// Individual fields to rewrite in a file's existing EXIF; null removes the field
+export interface EmbeddedFieldUpdates {
+  captureDate?: string; // EXIF "YYYY:MM:DD HH:MM:SS"
+  gps?: { latitude: number; longitude: number } | null;
+  description?: string | null;
+  keywords?: string[];
+}
+
+/**
+ * Reads and rewrites embedded EXIF. The default backend is plain JavaScript
+ * so metadata works on every platform the server runs on; the sharp backend
+ * reads through libvips, which understands more containers (HEIF, AVIF,
+ * WebP) but needs the native module.
+ */
+export interface MetadataBackend {
+  readonly name: MetadataBackendName;
+  readExif(filePath: string): Promise<ExifMetadata>;
+  canWrite(mimeType: string): boolean;
+  writeFields(filePath: string, updates: EmbeddedFieldUpdates): Promise<void>;
+}
+
+export type MetadataBackendName = 'js' | 'sharp';
+
+/**
+ * exif for reading, piexifjs for writing; JPEG only
+ */
+class JsMetadataBackend implements MetadataBackend {
+  readonly name = 'js' as const;
+
+  async readExif(imagePath: string): Promise<ExifMetadata> {
+    return new Promise((resolve, reject) => {
+      // Set a timeout to prevent hanging
+      const timeout = setTimeout(() => {
+        reject(new Error('EXIF extraction timeout'));
+      }, 10000);
+
+      try {
+        new ExifImage({ image: imagePath }, (error: any, exifData: any) => {
+          clearTimeout(timeout);
+
+          if (error) {
+            console.log(`EXIF extraction error for ${imagePath}:`, error.message);
+            reject(error);
+            return;
+          }
+
+          try {
+            resolve(toExifMetadata({
+              make: exifData.image?.Make || exifData.image?.make,
+              model: exifData.image?.Model || exifData.image?.model,
+              dateTimeOriginal: exifData.exif?.DateTimeOriginal,
+              subSecTimeOriginal: exifData.exif?.SubSecTimeOriginal,
+              createDate: exifData.exif?.CreateDate,
+              dateTime: exifData.image?.DateTime,
+              modifyDate: exifData.image?.ModifyDate,
+              fNumber: exifData.exif?.FNumber,
+              exposureTime: exifData.exif?.ExposureTime,
+              iso: exifData.exif?.ISO,
+              focalLength: exifData.exif?.FocalLength,
+              lens: exifData.exif?.LensModel,
+              gps: exifData.gps,
+            }, imagePath));
+          } catch (processingError) {
+            console.error(`Error processing EXIF data for ${imagePath}:`, processingError);
+            resolve({}); // Return empty metadata instead of rejecting
+          }
+        });
+      } catch (error) {
+        clearTimeout(timeout);
+        reject(error);
+      }
+    });
+  }
+
+  canWrite(mimeType: string): boolean {
+    return mimeType === 'image/jpeg';
+  }
+
+  /**
+   * Rewrite single fields, keeping the rest of the EXIF (camera, exposure,
+   * orientation) as it is
+   */
+  async writeFields(filePath: string, updates: EmbeddedFieldUpdates): Promise<void> {
+    const imageData = (await fs.readFile(filePath)).toString('binary');
+    let exifObj: any;
+    try {
+      exifObj = piexifjs.load(imageData);
+    } catch {
+      exifObj = { "0th": {}, "Exif": {}, "GPS": {}, "1st": {}, "thumbnail": null };
+    }
+
+    if (updates.captureDate !== undefined) {
+      exifObj["Exif"][piexifjs.ExifIFD.DateTimeOriginal] = updates.captureDate;
+      exifObj["Exif"][piexifjs.ExifIFD.DateTimeDigitized] = updates.captureDate;
+    }
+
+    if (updates.gps !== undefined) {
+      exifObj["GPS"] = updates.gps ? createGPSExif(updates.gps.latitude, updates.gps.longitude) : {};
+    }
+
+    if (updates.description !== undefined) {
+      if (updates.description) {
+        exifObj["0th"][piexifjs.ImageIFD.ImageDescription] = updates.description;
+        exifObj["0th"][piexifjs.ImageIFD.XPComment] = stringToUTF16(updates.description);
+      } else {
+        delete exifObj["0th"][piexifjs.ImageIFD.ImageDescription];
+        delete exifObj["0th"][piexifjs.ImageIFD.XPComment];
+      }
+    }
+
+    if (updates.keywords !== undefined) {
+      if (updates.keywords.length > 0) {
+        exifObj["0th"][piexifjs.ImageIFD.XPKeywords] = stringToUTF16(updates.keywords.join(';'));
+      } else {
+        delete exifObj["0th"][piexifjs.ImageIFD.XPKeywords];
+      }
+    }
+
+    const updated = piexifjs.insert(piexifjs.dump(exifObj), imageData);
+    // Write next to the original and rename so a failed write cannot truncate the photo
+    const tempPath = `${filePath}.partial`;
+    await fs.writeFile(tempPath, Buffer.from(updated, 'binary'));
+    await fs.rename(tempPath, filePath);
+  }
+}
+
+/**
+ * libvips through sharp for reading. Writing would re-encode the image, so
+ * edits still go through the JavaScript writer.
+ */
+class SharpMetadataBackend implements MetadataBackend {
+  readonly name = 'sharp' as const;
+  private readonly fallback = new JsMetadataBackend();
+
+  constructor(
+    private readonly sharp: typeof import("sharp"),
+    private readonly exifReader: (buffer: Buffer) => any
+  ) {}
+
+  async readExif(imagePath: string): Promise<ExifMetadata> {
+    const { exif } = await this.sharp(imagePath).metadata();
+    if (!exif) return {};
+
+    const exifData = this.exifReader(exif);
+    const image = exifData.Image ?? exifData.image ?? {};
+    const photo = exifData.Photo ?? exifData.exif ?? {};
+    return toExifMetadata({
+      make: image.Make,
+      model: image.Model,
+      dateTimeOriginal: photo.DateTimeOriginal,
+      subSecTimeOriginal: photo.SubSecTimeOriginal,
+      createDate: photo.DateTimeDigitized,
+      dateTime: image.DateTime,
+      modifyDate: image.ModifyDate,
+      fNumber: photo.FNumber,
+      exposureTime: photo.ExposureTime,
+      iso: photo.ISOSpeedRatings ?? photo.PhotographicSensitivity,
+      focalLength: photo.FocalLength,
+      lens: photo.LensModel,
+      gps: exifData.GPSInfo ?? exifData.gps,
+    }, imagePath);
+  }
+
+  canWrite(mimeType: string): boolean {
+    return this.fallback.canWrite(mimeType);
+  }
+
+  writeFields(filePath: string, updates: EmbeddedFieldUpdates): Promise<void> {
+    return this.fallback.writeFields(filePath, updates);
+  }
+}
+
+let selected: Promise<MetadataBackend> | null = null;
+
+/**
+ * Backend chosen with METADATA_BACKEND (js by default). The sharp backend is
+ * loaded on demand, so a missing or broken native module falls back to js
+ * instead of stopping the server.
+ */
+export function getMetadataBackend(): Promise<MetadataBackend> {
+  if (!selected) {
+    selected = loadBackend((process.env.METADATA_BACKEND || 'js').toLowerCase());
+  }
+  return selected;
+}
+
+async function loadBackend(name: string): Promise<MetadataBackend> {
+  if (name === 'sharp') {
+    try {
+      const sharp = (await import("sharp")).default;
+      const exifReader = (await import("exif-reader")).default;
+      return new SharpMetadataBackend(sharp, exifReader);
+    } catch (error) {
+      console.error('sharp metadata backend unavailable, using the JavaScript backend:', error);
+    }
+  } else if (name !== 'js') {
+    console.warn(`Unknown METADATA_BACKEND "${name}", using the JavaScript backend`);
+  }
+  return new JsMetadataBackend();
+}
+
+interface RawExifFields {
+  make?: unknown;
+  model?: unknown;
+  dateTimeOriginal?: unknown;
+  subSecTimeOriginal?: unknown;
+  createDate?: unknown;
+  dateTime?: unknown;
+  modifyDate?: unknown;
+  fNumber?: unknown;
+  exposureTime?: unknown;
+  iso?: unknown;
+  focalLength?: unknown;
+  lens?: unknown;
+  gps?: any;
+}
+
+/**
+ * Normalize what either reader returned into the stored metadata shape
+ */
+function toExifMetadata(raw: RawExifFields, imagePath: string): ExifMetadata {
+  const metadata: ExifMetadata = {};
+
+  // Extract camera information with multiple fallback sources
+  const make = safeGetStringField(raw.make);
+  const model = safeGetStringField(raw.model);
+  metadata.cameraMake = make;
+  metadata.cameraModel = model;
+  if (make && model) {
+    metadata.camera = `${make} ${model}`;
+  } else if (make) {
+    metadata.camera = make;
+  } else if (model) {
+    metadata.camera = model;
+  }
+
+  // Priority order: DateTimeOriginal > CreateDate > DateTime
+  const dateTimeOriginal = safeGetDateField(raw.dateTimeOriginal);
+  const createDate = safeGetDateField(raw.createDate);
+  const dateTime = safeGetDateField(raw.dateTime);
+  if (dateTimeOriginal || createDate || dateTime) {
+    metadata.dateTime = dateTimeOriginal || createDate || dateTime;
+  }
+  metadata.dateTimeOriginal = dateTimeOriginal;
+  // Digits only; some cameras pad the field with spaces
+  const subSeconds = safeGetStringField(raw.subSecTimeOriginal)?.match(/^\d+/)?.[0];
+  if (dateTimeOriginal && subSeconds) metadata.subSecTimeOriginal = subSeconds;
+  metadata.createDate = createDate;
+  metadata.modifyDate = safeGetDateField(raw.modifyDate);
+
+  // Extract camera settings with proper validation
+  metadata.aperture = formatAperture(raw.fNumber);
+  metadata.shutter = formatShutter(raw.exposureTime);
+  metadata.iso = safeGetNumberField(Array.isArray(raw.iso) ? raw.iso[0] : raw.iso);
+  metadata.focalLength = formatFocalLength(raw.focalLength);
+  metadata.lens = safeGetStringField(raw.lens);
+
+  // Extract GPS information with validation
+  if (raw.gps && isValidGpsData(raw.gps)) {
+    try {
+      metadata.gpsLatitude = convertDMSToDD(raw.gps.GPSLatitude, raw.gps.GPSLatitudeRef);
+      metadata.gpsLongitude = convertDMSToDD(raw.gps.GPSLongitude, raw.gps.GPSLongitudeRef);
+    } catch (gpsError) {
+      console.log(`GPS extraction error for ${imagePath}:`, gpsError);
+    }
+  }
+
+  return metadata;
+}
+
+function safeGetStringField(value: any): string | undefined {
+  if (value === null || value === undefined) return undefined;
+  const str = String(value).trim();
+  return str.length > 0 ? str : undefined;
+}
+
+/**
+ * EXIF date strings as they are; readers that return Date objects parsed the
+ * local time as UTC, so format them back the same way
+ */
+function safeGetDateField(value: any): string | undefined {
+  if (value instanceof Date) {
+    if (isNaN(value.getTime())) return undefined;
+    const pad = (part: number) => String(part).padStart(2, '0');
+    return `${value.getUTCFullYear()}:${pad(value.getUTCMonth() + 1)}:${pad(value.getUTCDate())} ${pad(value.getUTCHours())}:${pad(value.getUTCMinutes())}:${pad(value.getUTCSeconds())}`;
+  }
+  return safeGetStringField(value);
+}
+
+function safeGetNumberField(value: any): string | undefined {
+  if (value === null || value === undefined || isNaN(Number(value))) return undefined;
+  return String(value);
+}
+
+function formatAperture(fNumber: any): string | undefined {
+  if (fNumber === null || fNumber === undefined || isNaN(Number(fNumber))) return undefined;
+  return `f/${Number(fNumber).toFixed(1)}`;
+}
+
+function formatShutter(exposureTime: any): string | undefined {
+  if (exposureTime === null || exposureTime === undefined || isNaN(Number(exposureTime))) return undefined;
+  const time = Number(exposureTime);
+  if (time >= 1) return `${time}s`;
+  return `1/${Math.round(1/time)}s`;
+}
+
+function formatFocalLength(focalLength: any): string | undefined {
+  if (focalLength === null || focalLength === undefined || isNaN(Number(focalLength))) return undefined;
+  return `${Number(focalLength)}mm`;
+}
+
+function isValidGpsData(gps: any): boolean {
+  return gps &&
+         Array.isArray(gps.GPSLatitude) && gps.GPSLatitude.length === 3 &&
+         Array.isArray(gps.GPSLongitude) && gps.GPSLongitude.length === 3 &&
+         typeof gps.GPSLatitudeRef === 'string' &&
+         typeof gps.GPSLongitudeRef === 'string';
+}
+
+function convertDMSToDD(dms: number[], ref: string): number {
+  // Validate input
+  if (!Array.isArray(dms) || dms.length !== 3) {
+    throw new Error('Invalid DMS array format');
+  }
+
+  const [degrees, minutes, seconds] = dms.map(Number);
+
+  // Validate numeric values
+  if (isNaN(degrees) || isNaN(minutes) || isNaN(seconds)) {
+    throw new Error('Invalid DMS numeric values');
+  }
+
+  // Validate ranges
+  if (minutes >= 60 || seconds >= 60 || degrees < 0 || minutes < 0 || seconds < 0) {
+    throw new Error('Invalid DMS value ranges');
+  }
+
+  let dd = degrees + minutes/60 + seconds/3600;
+
+  // Apply hemisphere reference
+  if (ref === "S" || ref === "W") {
+    dd = dd * -1;
+  }
+
+  // Validate final coordinates
+  const isLatitude = ref === "N" || ref === "S";
+  const maxValue = isLatitude ? 90 : 180;
+
+  if (Math.abs(dd) > maxValue) {
+    throw new Error(`Invalid ${isLatitude ? 'latitude' : 'longitude'} value: ${dd}`);
+  }
+
+  return Math.round(dd * 1000000) / 1000000; // Round to 6 decimal places
+}
+
+export function stringToUTF16(str: string): number[] {
+  const utf16 = [];
+  for (let i = 0; i < str.length; i++) {
+    const code = str.charCodeAt(i);
+    utf16.push(code & 0xFF, (code >> 8) & 0xFF);
+  }
+  utf16.push(0, 0); // null terminator
+  return utf16;
+}
+
+export function createGPSExif(latitude: number, longitude: number): any {
+  const latRef = latitude >= 0 ? 'N' : 'S';
+  const lonRef = longitude >= 0 ? 'E' : 'W';
+
+  return {
+    [piexifjs.GPSIFD.GPSLatitudeRef]: latRef,
+    [piexifjs.GPSIFD.GPSLatitude]: decimalToDMS(Math.abs(latitude)),
+    [piexifjs.GPSIFD.GPSLongitudeRef]: lonRef,
+    [piexifjs.GPSIFD.GPSLongitude]: decimalToDMS(Math.abs(longitude)),
+  };
+}
+
+function decimalToDMS(decimal: number): [[number, number], [number, number], [number, number]] {
+  const degrees = Math.floor(decimal);
+  const minutes = Math.floor((decimal - degrees) * 60);
+  const seconds = ((decimal - degrees) * 60 - minutes) * 60;
+
+  return [
+    [degrees, 1],
+    [minutes, 1],
+    [Math.round(seconds * 1000), 1000]
+  ];
+}
@@ -4,6 +4,9 @@ import sharp from "sharp";
 // @ts-ignore - piexifjs doesn't have type definitions
 import piexifjs from "piexifjs";
 import type { FileVersion, CombinedMetadata, AIMetadata, ExifMetadata } from "@shared/schema";
+import { fileManager } from "./fileManager";
+import { assertUnlocked } from "./photoLock";
+import { getMetadataBackend, stringToUTF16, createGPSExif, type EmbeddedFieldUpdates } from "./metadataBackend";
 
 export interface EmbeddingOptions {
   preserveOriginal?: boolean;
@@ -11,6 +14,22 @@ export interface EmbeddingOptions {
   embedInPlace?: boolean;
 }
 
+// How a photo is organized in Pictallion, written into exported copies
+export interface OrganizationMetadata {
+  keywords: string[]; // tags, keywords and names of the people in the photo
+  rating: number; // 0-5 stars
+  gps: { latitude: number; longitude: number } | null;
+  dimensions: { width: number; height: number } | null; // the pixels face regions are measured in
+  faceRegions: Array<{ name: string; boundingBox: [number, number, number, number] }>;
+}
+
+// Largest payload a JPEG segment can hold, less its length field
+const MAX_SEGMENT_PAYLOAD = 65533;
+const XMP_HEADER = 'http://ns.adobe.com/xap/1.0/\0';
+const PHOTOSHOP_HEADER = 'Photoshop 3.0\0';
+// IPTC IIM limits each keyword to 64 bytes
+const MAX_IPTC_KEYWORD_BYTES = 64;
+
 class MetadataEmbeddingService {
   
   /**
@@ -23,9 +42,13 @@ class MetadataEmbeddingService {
   ): Promise<string> {
     const { preserveOriginal = true, outputPath, embedInPlace = false } = options;
     
+    if (embedInPlace && !outputPath) {
+      assertUnlocked(fileVersion, 'write_metadata');
+    }
+
     try {
       const inputPath = fileVersion.filePath;
-      const outputFilePath = outputPath || (embedInPlace ? inputPath : this.generateGoldPath(fileVersion));
+      const outputFilePath = outputPath || (embedInPlace ? inputPath : await this.generateGoldPath(fileVersion));
       
       // Ensure output directory exists
       await fs.mkdir(path.dirname(outputFilePath), { recursive: true });
@@ -43,6 +66,57 @@ class MetadataEmbeddingService {
     }
   }
 
+  /**
+   * Rewrite single fields of a file's EXIF in place through the configured
+   * metadata backend. Returns false, without touching the file, for formats
+   * the backend cannot edit.
+   */
+  async updateEmbeddedFields(filePath: string, mimeType: string, updates: EmbeddedFieldUpdates): Promise<boolean> {
+    const backend = await getMetadataBackend();
+    if (!backend.canWrite(mimeType)) return false;
+    await backend.writeFields(filePath, updates);
+    return true;
+  }
+
+  /**
+   * Write keywords, rating, GPS and named face regions into an exported
+   * copy. JPEGs get EXIF, XMP (with MWG face regions) and IPTC keywords in
+   * the file itself; other formats get an XMP sidecar next to the copy, which
+   * is where Lightroom looks for raw files. Returns which one was written.
+   */
+  async embedOrganizationMetadata(filePath: string, mimeType: string, organization: OrganizationMetadata): Promise<'embedded' | 'sidecar'> {
+    const xmp = this.createOrganizationXmp(organization);
+    if (mimeType === 'image/jpeg' && Buffer.byteLength(XMP_HEADER + xmp) <= MAX_SEGMENT_PAYLOAD) {
+      let imageData = (await fs.readFile(filePath)).toString('binary');
+      let exifObj: any;
+      try {
+        exifObj = piexifjs.load(imageData);
+      } catch {
+        exifObj = { "0th": {}, "Exif": {}, "GPS": {}, "1st": {}, "thumbnail": null };
+      }
+      if (organization.keywords.length > 0) {
+        exifObj["0th"][piexifjs.ImageIFD.XPKeywords] = stringToUTF16(organization.keywords.join(';'));
+      }
+      if (organization.rating > 0) {
+        exifObj["0th"][piexifjs.ImageIFD.Rating] = organization.rating;
+      }
+      if (organization.gps) {
+        exifObj["GPS"] = createGPSExif(organization.gps.latitude, organization.gps.longitude);
+      }
+      imageData = piexifjs.insert(piexifjs.dump(exifObj), imageData);
+
+      const segments = [
+        this.jpegSegment(0xe1, Buffer.from(XMP_HEADER + xmp, 'utf8')),
+        ...(organization.keywords.length > 0 ? [this.jpegSegment(0xed, this.createIptcKeywords(organization.keywords))] : []),
+      ];
+      await fs.writeFile(filePath, this.replaceJpegSegments(Buffer.from(imageData, 'binary'), segments));
+      return 'embedded';
+    }
+
+    await fs.writeFile(filePath.replace(/\.[^./\\]+$/, '') + '.xmp', xmp);
+    return 'sidecar';
+  }
+
   /**
    * Embed metadata into image files using EXIF/XMP
    */
@@ -146,23 +220,23 @@ class MetadataEmbeddingService {
     // Basic image description
     if (metadata.ai?.longDescription) {
       exifObj["0th"][piexifjs.ImageIFD.ImageDescription] = metadata.ai.longDescription;
-      exifObj["0th"][piexifjs.ImageIFD.XPComment] = this.stringToUTF16(metadata.ai.longDescription);
+      exifObj["0th"][piexifjs.ImageIFD.XPComment] = stringToUTF16(metadata.ai.longDescription);
     }
 
     if (metadata.ai?.shortDescription) {
-      exifObj["0th"][piexifjs.ImageIFD.XPSubject] = this.stringToUTF16(metadata.ai.shortDescription);
+      exifObj["0th"][piexifjs.ImageIFD.XPSubject] = stringToUTF16(metadata.ai.shortDescription);
     }
 
     // Keywords/Tags
     if (metadata.ai?.aiTags && metadata.ai.aiTags.length > 0) {
       const keywords = metadata.ai.aiTags.join(';');
-      exifObj["0th"][piexifjs.ImageIFD.XPKeywords] = this.stringToUTF16(keywords);
+      exifObj["0th"][piexifjs.ImageIFD.XPKeywords] = stringToUTF16(keywords);
     }
 
     // Additional keywords from database
     if (fileVersion.keywords && fileVersion.keywords.length > 0) {
       const allKeywords = [...(metadata.ai?.aiTags || []), ...fileVersion.keywords].join(';');
-      exifObj["0th"][piexifjs.ImageIFD.XPKeywords] = this.stringToUTF16(allKeywords);
+      exifObj["0th"][piexifjs.ImageIFD.XPKeywords] = stringToUTF16(allKeywords);
     }
 
     // Rating
@@ -178,14 +252,14 @@ class MetadataEmbeddingService {
         .join(', ');
       if (peopleNames) {
         exifObj["0th"][piexifjs.ImageIFD.Artist] = peopleNames;
-        exifObj["0th"][piexifjs.ImageIFD.XPAuthor] = this.stringToUTF16(peopleNames);
+        exifObj["0th"][piexifjs.ImageIFD.XPAuthor] = stringToUTF16(peopleNames);
       }
     }
 
     // GPS data
     if (metadata.ai?.gpsCoordinates) {
       const { latitude, longitude } = metadata.ai.gpsCoordinates;
-      exifObj["GPS"] = this.createGPSExif(latitude, longitude);
+      exifObj["GPS"] = createGPSExif(latitude, longitude);
     }
 
     // Software signature
@@ -296,13 +370,8 @@ class MetadataEmbeddingService {
   }
 
   // Utility methods
-  private generateGoldPath(fileVersion: FileVersion): string {
-    const date = new Date();
-    const year = date.getFullYear();
-    const month = String(date.getMonth() + 1).padStart(2, '0');
-    
-    const filename = path.basename(fileVersion.filePath);
-    return path.join(process.cwd(), 'data', 'media', 'gold', String(year), month, filename);
+  private async generateGoldPath(fileVersion: FileVersion): Promise<string> {
+    return fileManager.resolveDestination('gold', new Date(), path.basename(fileVersion.filePath), fileVersion.fileHash);
   }
 
   private isImageFile(mimeType: string): boolean {
@@ -327,48 +396,11 @@ class MetadataEmbeddingService {
     return mimeTypes[ext] || 'application/octet-stream';
   }
 
-  private stringToUTF16(str: string): number[] {
-    const utf16 = [];
-    for (let i = 0; i < str.length; i++) {
-      const code = str.charCodeAt(i);
-      utf16.push(code & 0xFF, (code >> 8) & 0xFF);
-    }
-    utf16.push(0, 0); // null terminator
-    return utf16;
-  }
-
   private encodeUserComment(comment: string): string {
     // ASCII encoding identifier + actual comment
     return "ASCII\0\0\0" + comment;
   }
 
-  private createGPSExif(latitude: number, longitude: number): any {
-    const latRef = latitude >= 0 ? 'N' : 'S';
-    const lonRef = longitude >= 0 ? 'E' : 'W';
-    
-    const latDegrees = this.decimalToDMS(Math.abs(latitude));
-    const lonDegrees = this.decimalToDMS(Math.abs(longitude));
-    
-    return {
-      [piexifjs.GPSIFD.GPSLatitudeRef]: latRef,
-      [piexifjs.GPSIFD.GPSLatitude]: latDegrees,
-      [piexifjs.GPSIFD.GPSLongitudeRef]: lonRef,
-      [piexifjs.GPSIFD.GPSLongitude]: lonDegrees,
-    };
-  }
-
-  private decimalToDMS(decimal: number): [[number, number], [number, number], [number, number]] {
-    const degrees = Math.floor(decimal);
-    const minutes = Math.floor((decimal - degrees) * 60);
-    const seconds = ((decimal - degrees) * 60 - minutes) * 60;
-    
-    return [
-      [degrees, 1],
-      [minutes, 1],
-      [Math.round(seconds * 1000), 1000]
-    ];
-  }
-
   private createXMPSidecar(metadata: CombinedMetadata, fileVersion: FileVersion): string {
     return `<?xml version="1.0" encoding="UTF-8"?>
 <x:xmpmeta xmlns:x="adobe:ns:meta/">
@@ -386,6 +418,142 @@ class MetadataEmbeddingService {
 </x:xmpmeta>`;
   }
 
+  private createOrganizationXmp(organization: OrganizationMetadata): string {
+    const lines: string[] = [];
+    if (organization.rating > 0) {
+      lines.push(`      <xmp:Rating>${organization.rating}</xmp:Rating>`);
+    }
+    if (organization.gps) {
+      lines.push(`      <exif:GPSLatitude>${this.xmpCoordinate(organization.gps.latitude, 'N', 'S')}</exif:GPSLatitude>`);
+      lines.push(`      <exif:GPSLongitude>${this.xmpCoordinate(organization.gps.longitude, 'E', 'W')}</exif:GPSLongitude>`);
+    }
+    if (organization.keywords.length > 0) {
+      lines.push('      <dc:subject>', '        <rdf:Bag>');
+      organization.keywords.forEach(keyword => lines.push(`          <rdf:li>${this.escapeXml(keyword)}</rdf:li>`));
+      lines.push('        </rdf:Bag>', '      </dc:subject>');
+    }
+    // MWG regions give the centre and size of each face as a share of the image
+    const { dimensions } = organization;
+    if (dimensions && organization.faceRegions.length > 0) {
+      lines.push(
+        '      <mwg-rs:Regions rdf:parseType="Resource">',
+        `        <mwg-rs:AppliedToDimensions stDim:w="${dimensions.width}" stDim:h="${dimensions.height}" stDim:unit="pixel"/>`,
+        '        <mwg-rs:RegionList>',
+        '          <rdf:Bag>'
+      );
+      organization.faceRegions.forEach(({ name, boundingBox: [x, y, width, height] }) => {
+        const area = (value: number) => Math.min(1, Math.max(0, value)).toFixed(6);
+        lines.push(
+          `            <rdf:li rdf:parseType="Resource">`,
+          `              <mwg-rs:Name>${this.escapeXml(name)}</mwg-rs:Name>`,
+          '              <mwg-rs:Type>Face</mwg-rs:Type>',
+          `              <mwg-rs:Area stArea:x="${area((x + width / 2) / dimensions.width)}" stArea:y="${area((y + height / 2) / dimensions.height)}" stArea:w="${area(width / dimensions.width)}" stArea:h="${area(height / dimensions.height)}" stArea:unit="normalized"/>`,
+          '            </rdf:li>'
+        );
+      });
+      lines.push('          </rdf:Bag>', '        </mwg-rs:RegionList>', '      </mwg-rs:Regions>');
+    }
+
+    return `<?xpacket begin="\uFEFF" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:dc="http://purl.org/dc/elements/1.1/"
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+        xmlns:exif="http://ns.adobe.com/exif/1.0/"
+        xmlns:mwg-rs="http://www.metadataworkinggroup.com/schemas/regions/"
+        xmlns:stDim="http://ns.adobe.com/xap/1.0/sType/Dimensions#"
+        xmlns:stArea="http://ns.adobe.com/xmp/sType/Area#">
+${lines.join('\n')}
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>`;
+  }
+
+  // XMP writes GPS as degrees and decimal minutes, e.g. "41,24.2028N"
+  private xmpCoordinate(value: number, positive: string, negative: string): string {
+    const absolute = Math.abs(value);
+    const degrees = Math.floor(absolute);
+    const minutes = (absolute - degrees) * 60;
+    return `${degrees},${minutes.toFixed(4)}${value >= 0 ? positive : negative}`;
+  }
+
+  private escapeXml(value: string): string {
+    return value
+      .replace(/&/g, '&amp;')
+      .replace(/</g, '&lt;')
+      .replace(/>/g, '&gt;')
+      .replace(/"/g, '&quot;');
+  }
+
+  /**
+   * Photoshop image resource block holding IPTC keywords, UTF-8 encoded
+   */
+  private createIptcKeywords(keywords: string[]): Buffer {
+    const dataset = (record: number, tag: number, value: Buffer) => {
+      const header = Buffer.from([0x1c, record, tag, 0, 0]);
+      header.writeUInt16BE(value.length, 3);
+      return Buffer.concat([header, value]);
+    };
+    const iim = Buffer.concat([
+      dataset(1, 90, Buffer.from([0x1b, 0x25, 0x47])), // coded character set: UTF-8
+      dataset(2, 0, Buffer.from([0, 4])), // record version
+      ...keywords.map(keyword => dataset(2, 25, this.truncateUtf8(keyword, MAX_IPTC_KEYWORD_BYTES))),
+    ]);
+
+    // 8BIM resource 0x0404 with an empty name, data padded to an even length
+    const resourceHeader = Buffer.alloc(12);
+    resourceHeader.write('8BIM', 0, 'ascii');
+    resourceHeader.writeUInt16BE(0x0404, 4);
+    resourceHeader.writeUInt32BE(iim.length, 8);
+    const padding = Buffer.alloc(iim.length % 2);
+    return Buffer.concat([Buffer.from(PHOTOSHOP_HEADER, 'binary'), resourceHeader, iim, padding]);
+  }
+
+  private truncateUtf8(value: string, maxBytes: number): Buffer {
+    let bytes = Buffer.from(value, 'utf8');
+    let length = value.length;
+    while (bytes.length > maxBytes) {
+      bytes = Buffer.from(value.slice(0, --length), 'utf8');
+    }
+    return bytes;
+  }
+
+  private jpegSegment(marker: number, payload: Buffer): Buffer {
+    const header = Buffer.from([0xff, marker, 0, 0]);
+    header.writeUInt16BE(payload.length + 2, 2);
+    return Buffer.concat([header, payload]);
+  }
+
+  /**
+   * Drop any existing XMP and Photoshop segments and insert the new ones
+   * after the leading JFIF and EXIF segments
+   */
+  private replaceJpegSegments(image: Buffer, segments: Buffer[]): Buffer {
+    const kept: Buffer[] = [image.subarray(0, 2)];
+    let insertAt = 1;
+    let offset = 2;
+    while (offset + 4 <= image.length && image[offset] === 0xff) {
+      const marker = image[offset + 1];
+      // Start of scan: the rest is image data
+      if (marker === 0xda) break;
+      const length = image.readUInt16BE(offset + 2);
+      const segment = image.subarray(offset, offset + 2 + length);
+      const payload = segment.subarray(4).toString('binary');
+      const isXmp = marker === 0xe1 && payload.startsWith(XMP_HEADER);
+      const isPhotoshop = marker === 0xed && payload.startsWith(PHOTOSHOP_HEADER);
+      if (!isXmp && !isPhotoshop) {
+        kept.push(segment);
+        if (marker === 0xe0 || marker === 0xe1) insertAt = kept.length;
+      }
+      offset += 2 + length;
+    }
+    kept.splice(insertAt, 0, ...segments);
+    kept.push(image.subarray(offset));
+    return Buffer.concat(kept);
+  }
+
   private parseExifData(exifObj: any): ExifMetadata {
     return {
       camera: exifObj["0th"]?.[piexifjs.ImageIFD.Make],
@@ -0,0 +1,211 @@
+import { storage } from "../storage";
+import { softDeleteService, type DeletableKind } from "./softDelete";
+import { propagationService, type PropagationScope } from "./propagation";
+import type { FileVersion, JournalEntry } from "@shared/schema";
+
+export type JournalKind = JournalEntry['kind'];
+
+// One item an action touched, with enough state to put it back either way
+export type JournalChange =
+  | { type: 'promotion'; photoId: string } // the gold version the promotion created
+  | { type: 'deletion'; kind: DeletableKind; id: string }
+  | { type: 'tags'; photoId: string; before: string[]; after: string[] }
+  | { type: 'keywords'; photoId: string; before: string[]; after: string[] }
+  | { type: 'album'; collectionId: string; photoId: string; added: boolean }
+  | { type: 'face'; faceId: string; before: string | null; after: string | null };
+
+export interface JournalReplayResult {
+  entry: JournalEntry;
+  applied: number;
+  skipped: number; // items that have since been purged or removed
+}
+
+type PhotoTagState = { tags: string[]; keywords: string[] };
+
+// Propagation already reached every photo it was going to; replaying a change
+// puts back each photo's own state and must not spread it again
+const NO_PROPAGATION: PropagationScope = { stack: false, versions: false };
+
+/**
+ * Journal of user actions that are easy to regret in bulk: tier promotions,
+ * deletions, tag and album changes and person assignments. Entry points
+ * capture the state of what they are about to change, then record an entry
+ * once the action succeeds. Undo puts the recorded items back the way they
+ * were, newest entry first; redo reapplies undone entries until a new
+ * action is recorded. Recording never fails the action it describes.
+ */
+class OperationJournal {
+  async record(kind: JournalKind, label: string, changes: JournalChange[]): Promise<JournalEntry | null> {
+    const effective = changes.filter(change => !this.isNoop(change));
+    if (effective.length === 0) return null;
+    try {
+      await storage.clearUndoneJournalEntries();
+      return await storage.createJournalEntry({ kind, label, changes: effective });
+    } catch (error) {
+      console.error(`Failed to journal ${kind} "${label}":`, error);
+      return null;
+    }
+  }
+
+  async listRecent(limit?: number): Promise<JournalEntry[]> {
+    return storage.getJournalEntries(limit);
+  }
+
+  /**
+   * Undo the newest entry that is not undone yet; null when there is none
+   */
+  async undoLast(): Promise<JournalReplayResult | null> {
+    const entry = await storage.getLastActiveJournalEntry();
+    if (!entry) return null;
+    const counts = await this.replay([...(entry.changes as JournalChange[])].reverse(), 'undo');
+    const updated = (await storage.setJournalEntryUndone(entry.id, new Date())) ?? entry;
+    return { entry: updated, ...counts };
+  }
+
+  /**
+   * Redo the entry undone most recently; null when there is none
+   */
+  async redoLast(): Promise<JournalReplayResult | null> {
+    const entry = await storage.getLastUndoneJournalEntry();
+    if (!entry) return null;
+    const counts = await this.replay(entry.changes as JournalChange[], 'redo');
+    const updated = (await storage.setJournalEntryUndone(entry.id, null)) ?? entry;
+    return { entry: updated, ...counts };
+  }
+
+  /**
+   * Tags and keywords of photos a tag change is about to touch, including
+   * the photos it will propagate to
+   */
+  async captureTags(photoIds: string[], propagate?: PropagationScope): Promise<Map<string, PhotoTagState>> {
+    const state = new Map<string, PhotoTagState>();
+    const add = (photo: FileVersion) => state.set(photo.id, { tags: propagationService.getTags(photo), keywords: photo.keywords ?? [] });
+    for (const photoId of photoIds) {
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo) continue;
+      add(photo);
+      (await propagationService.findTargets(photo, 'tags', propagate)).forEach(add);
+    }
+    return state;
+  }
+
+  /**
+   * Tag and keyword changes since captureTags
+   */
+  async tagChanges(before: Map<string, PhotoTagState>): Promise<JournalChange[]> {
+    const changes: JournalChange[] = [];
+    for (const [photoId, previous] of Array.from(before.entries())) {
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo) continue;
+      changes.push({ type: 'tags', photoId, before: previous.tags, after: propagationService.getTags(photo) });
+      changes.push({ type: 'keywords', photoId, before: previous.keywords, after: photo.keywords ?? [] });
+    }
+    return changes;
+  }
+
+  /**
+   * Current person of each face an assignment is about to touch
+   */
+  async captureFaces(faceIds: string[]): Promise<Map<string, string | null>> {
+    const state = new Map<string, string | null>();
+    for (const faceId of faceIds) {
+      const face = await storage.getFace(faceId);
+      if (face) state.set(face.id, face.personId ?? null);
+    }
+    return state;
+  }
+
+  /**
+   * Person changes since captureFaces. Faces reached by propagation were
+   * unassigned before, since propagation only assigns unassigned faces.
+   */
+  async faceChanges(before: Map<string, string | null>, propagatedFaceIds: string[] = []): Promise<JournalChange[]> {
+    const faceIds = Array.from(new Set([...Array.from(before.keys()), ...propagatedFaceIds]));
+    const changes: JournalChange[] = [];
+    for (const faceId of faceIds) {
+      const face = await storage.getFace(faceId);
+      if (face) changes.push({ type: 'face', faceId, before: before.get(faceId) ?? null, after: face.personId ?? null });
+    }
+    return changes;
+  }
+
+  /**
+   * Photos in each album an album change is about to touch
+   */
+  async captureAlbums(collectionIds: string[]): Promise<Map<string, Set<string>>> {
+    const state = new Map<string, Set<string>>();
+    for (const collectionId of Array.from(new Set(collectionIds))) {
+      state.set(collectionId, new Set(await storage.getCollectionPhotoIds(collectionId)));
+    }
+    return state;
+  }
+
+  /**
+   * Photos added to or removed from each album since captureAlbums
+   */
+  async albumChanges(before: Map<string, Set<string>>): Promise<JournalChange[]> {
+    const changes: JournalChange[] = [];
+    for (const [collectionId, previous] of Array.from(before.entries())) {
+      const current = new Set(await storage.getCollectionPhotoIds(collectionId));
+      current.forEach(photoId => {
+        if (!previous.has(photoId)) changes.push({ type: 'album', collectionId, photoId, added: true });
+      });
+      previous.forEach(photoId => {
+        if (!current.has(photoId)) changes.push({ type: 'album', collectionId, photoId, added: false });
+      });
+    }
+    return changes;
+  }
+
+  private async replay(changes: JournalChange[], direction: 'undo' | 'redo'): Promise<{ applied: number; skipped: number }> {
+    let applied = 0;
+    let skipped = 0;
+    for (const change of changes) {
+      if (await this.apply(change, direction)) applied++;
+      else skipped++;
+    }
+    return { applied, skipped };
+  }
+
+  private async apply(change: JournalChange, direction: 'undo' | 'redo'): Promise<boolean> {
+    const undo = direction === 'undo';
+    switch (change.type) {
+      case 'promotion':
+        // The gold copy goes to the trash rather than away for good, so redo can bring it back
+        return undo ? softDeleteService.delete('photo', change.photoId) : softDeleteService.restore('photo', change.photoId);
+      case 'deletion':
+        return undo ? softDeleteService.restore(change.kind, change.id) : softDeleteService.delete(change.kind, change.id);
+      case 'tags': {
+        if (!(await storage.getFileVersion(change.photoId))) return false;
+        await propagationService.changeTags(change.photoId, { action: 'set', tags: undo ? change.before : change.after }, NO_PROPAGATION);
+        return true;
+      }
+      case 'keywords': {
+        if (!(await storage.getFileVersion(change.photoId))) return false;
+        await storage.updateFileVersion(change.photoId, { keywords: undo ? change.before : change.after });
+        return true;
+      }
+      case 'album': {
+        if (!(await storage.getCollection(change.collectionId)) || !(await storage.getFileVersion(change.photoId))) return false;
+        if (change.added === undo) await storage.removePhotoFromCollection(change.collectionId, change.photoId);
+        else await storage.addPhotoToCollection(change.collectionId, change.photoId);
+        return true;
+      }
+      case 'face': {
+        if (!(await storage.getFace(change.faceId))) return false;
+        await storage.updateFace(change.faceId, { personId: undo ? change.before : change.after });
+        return true;
+      }
+    }
+  }
+
+  private isNoop(change: JournalChange): boolean {
+    if (change.type === 'tags' || change.type === 'keywords') {
+      return change.before.length === change.after.length && change.before.every((value, index) => value === change.after[index]);
+    }
+    if (change.type === 'face') return change.before === change.after;
+    return false;
+  }
+}
+
+export const operationJournal = new OperationJournal();
@@ -0,0 +1,131 @@
+import crypto from "crypto";
+import { EventEmitter } from "events";
+import type { Request } from "express";
+
+export type OperationKind = 'import' | 'thumbnail_backfill' | 'face_detection' | 'ai_processing' | 'export' | 'frame_sync' | 'library_migration' | 'metadata_refresh' | 'cold_storage' | 'integrity_scan' | 'backup' | 'promotion_scoring' | 'timelapse' | 'album_suggestions';
+
+export class OperationCancelledError extends Error {
+  constructor(operationId: string) {
+    super(`Operation ${operationId} was cancelled`);
+    this.name = 'OperationCancelledError';
+  }
+}
+
+/**
+ * Cooperative cancellation flag. Long-running loops check it between units of
+ * work, so every item is either fully committed or not started at all.
+ */
+export class CancellationToken {
+  private cancelled = false;
+
+  constructor(readonly operationId: string) {}
+
+  get isCancelled(): boolean {
+    return this.cancelled;
+  }
+
+  cancel(): void {
+    this.cancelled = true;
+  }
+
+  throwIfCancelled(): void {
+    if (this.cancelled) throw new OperationCancelledError(this.operationId);
+  }
+}
+
+export interface OperationInfo {
+  id: string;
+  kind: OperationKind;
+  label: string;
+  startedAt: string;
+  cancelRequested: boolean;
+  completed: number;
+  total: number | null;
+  resumeState?: Record<string, unknown>; // what is still left to do, kept for resuming after a shutdown
+}
+
+export interface OperationHandle {
+  id: string;
+  token: CancellationToken;
+  progress(completed: number, total?: number): void;
+  checkpoint(resumeState: Record<string, unknown>): void;
+  finish(): void;
+}
+
+export type OperationEvent = 'started' | 'progress' | 'finished';
+
+/**
+ * Registry of running cancellable operations. Clients may supply their own
+ * operation id so they can cancel a request that has not returned yet.
+ * Emits started/progress/finished with the operation's info.
+ */
+class OperationRegistry extends EventEmitter {
+  private operations = new Map<string, { info: OperationInfo; token: CancellationToken }>();
+
+  start(kind: OperationKind, label: string, requestedId?: string): OperationHandle {
+    const id = requestedId && !this.operations.has(requestedId) ? requestedId : crypto.randomUUID();
+    const token = new CancellationToken(id);
+    const info: OperationInfo = {
+      id,
+      kind,
+      label,
+      startedAt: new Date().toISOString(),
+      cancelRequested: false,
+      completed: 0,
+      total: null,
+    };
+    this.operations.set(id, { info, token });
+    this.emit('started', info);
+
+    return {
+      id,
+      token,
+      progress: (completed, total) => {
+        info.completed = completed;
+        if (total !== undefined) info.total = total;
+        this.emit('progress', info);
+      },
+      checkpoint: (resumeState) => {
+        info.resumeState = resumeState;
+      },
+      finish: () => {
+        if (this.operations.delete(id)) this.emit('finished', info);
+      },
+    };
+  }
+
+  /**
+   * Request cancellation; the operation stops at its next checkpoint
+   */
+  cancel(id: string): boolean {
+    const entry = this.operations.get(id);
+    if (!entry) return false;
+    entry.info.cancelRequested = true;
+    entry.token.cancel();
+    return true;
+  }
+
+  cancelAll(): number {
+    this.operations.forEach((_, id) => this.cancel(id));
+    return this.operations.size;
+  }
+
+  get(id: string): OperationInfo | undefined {
+    return this.operations.get(id)?.info;
+  }
+
+  list(): OperationInfo[] {
+    return Array.from(this.operations.values()).map(entry => entry.info);
+  }
+}
+
+export const operationRegistry = new OperationRegistry();
+
+/**
+ * Client-chosen operation id from the X-Operation-Id header or the request body
+ */
+export function requestedOperationId(req: Request): string | undefined {
+  const header = req.get('X-Operation-Id');
+  if (header) return header;
+  return typeof req.body?.operationId === 'string' ? req.body.operationId : undefined;
+}
@@ -0,0 +1,408 @@
+import { z } from "zod";
+import { count, eq, inArray, or } from "drizzle-orm";
+import { db } from "../db";
+import { storage } from "../storage";
+import { faceDetectionService, SUGGESTION_MIN_SIMILARITY } from "./faceDetection";
+import { collectionPhotos, collections, events, faces, people, relationships, type Person, type PersonAlbumConfig, type Relationship } from "@shared/schema";
+
+// From the merged person's point of view; "parent" means they are X's parent
+const INVERSE_RELATIONSHIP: Record<string, string> = { parent: 'child', child: 'parent' };
+
+const fieldChoiceSchema = z.union([
+  z.object({ fromPersonId: z.string() }),
+  z.object({ value: z.string().nullable() }),
+]);
+
+export const previewMergePeopleSchema = z.object({
+  personIds: z.array(z.string()).min(2),
+});
+
+export const mergePeopleSchema = z.object({
+  targetId: z.string(),
+  sourceIds: z.array(z.string()).min(1),
+  resolutions: z.object({
+    name: fieldChoiceSchema.optional(),
+    birthdate: fieldChoiceSchema.optional(),
+    notes: z.union([fieldChoiceSchema, z.literal('combine')]).optional(),
+    thumbnail: z.object({ fromPersonId: z.string() }).optional(),
+  }).default({}),
+  // Which relationship to keep, per other person, where the merged people disagree
+  relationships: z.record(z.string(), z.string()).default({}),
+});
+
+export const suggestMergesQuerySchema = z.object({
+  minNameSimilarity: z.coerce.number().min(0).max(1).default(0.8),
+  minFaceSimilarity: z.coerce.number().min(0).max(1).default(SUGGESTION_MIN_SIMILARITY),
+  limit: z.coerce.number().int().min(1).max(500).default(50),
+});
+
+export type MergePeopleRequest = z.infer<typeof mergePeopleSchema>;
+export type SuggestMergesQuery = z.infer<typeof suggestMergesQuerySchema>;
+
+export interface FieldConflict<T> {
+  field: 'name' | 'birthdate' | 'notes';
+  values: Array<{ personId: string; value: T }>;
+}
+
+export interface RelationshipConflict {
+  otherPersonId: string;
+  otherPersonName: string | null;
+  options: Array<{ relationshipId: string; personId: string; relationshipType: string }>;
+}
+
+export interface MergePeoplePreview {
+  people: Array<{
+    id: string;
+    name: string;
+    faceCount: number;
+    photoCount: number;
+    birthdate: Date | null;
+    notes: string | null;
+  }>;
+  totalFaces: number;
+  totalPhotos: number;
+  overlappingPhotos: Array<{ photoId: string; personIds: string[] }>; // photos where more than one of them appears
+  fieldConflicts: FieldConflict<string | Date | null>[];
+  relationshipConflicts: RelationshipConflict[];
+  relationshipsBetweenMerged: number; // dropped on merge, a person cannot relate to themselves
+  birthdayEvents: number;
+  personAlbums: number;
+}
+
+export interface PeopleMergeSuggestion {
+  personIds: [string, string]; // for /api/people/merge/preview; the one with more faces first
+  people: Array<{ id: string; name: string; faceCount: number }>;
+  nameSimilarity: number; // 0-1, from edit distance
+  faceSimilarity: number | null; // cosine similarity of face centroids; null when either has no embedded faces
+  score: number;
+  reasons: Array<'name' | 'face'>;
+}
+
+export class PeopleMergeError extends Error {}
+
+// Faces this unlike overrule a matching name: two different people called "Sam"
+const NAME_MATCH_MIN_FACE_SIMILARITY = 0.6;
+
+/**
+ * Lowercased, without accents or punctuation, so "José-Luis" and "jose luis" match
+ */
+export function normalizeName(name: string): string {
+  return name.normalize('NFD').replace(/\p{M}/gu, '').toLowerCase().replace(/[^\p{L}\p{N}]+/gu, ' ').trim();
+}
+
+function editDistance(a: string, b: string): number {
+  let previous = Array.from({ length: b.length + 1 }, (_, index) => index);
+  for (let i = 1; i <= a.length; i++) {
+    const current = [i];
+    for (let j = 1; j <= b.length; j++) {
+      current[j] = Math.min(previous[j] + 1, current[j - 1] + 1, previous[j - 1] + (a[i - 1] === b[j - 1] ? 0 : 1));
+    }
+    previous = current;
+  }
+  return previous[b.length];
+}
+
+/**
+ * 1 for the same name, falling towards 0 as more letters differ. Word order
+ * is ignored ("Smith Anna" is "Anna Smith"), and names that differ only in
+ * their numbers ("Person 3", "Person 4") are different people.
+ */
+export function nameSimilarity(a: string, b: string): number {
+  const left = normalizeName(a);
+  const right = normalizeName(b);
+  if (!left || !right) return 0;
+  if (left.replace(/\D/g, '') !== right.replace(/\D/g, '')) return 0;
+  const similarity = (x: string, y: string) => 1 - editDistance(x, y) / Math.max(x.length, y.length);
+  const sorted = (value: string) => value.split(' ').sort().join(' ');
+  return Math.max(similarity(left, right), similarity(sorted(left), sorted(right)));
+}
+
+/**
+ * Merging people: suggestions of likely duplicates, a preview of what would
+ * change, then a single transaction that applies the caller's field-level
+ * choices.
+ */
+class PeopleMergeService {
+  /**
+   * Pairs of people that are probably the same person: similar names, or
+   * faces whose average embeddings are close. People seen together in a
+   * photo are never suggested.
+   */
+  async suggestMerges(query: SuggestMergesQuery): Promise<PeopleMergeSuggestion[]> {
+    const persons = await storage.getPeople();
+
+    const facesByPerson = new Map<string, number>();
+    const photosByPerson = new Map<string, Set<string>>();
+    for (const face of await storage.getAllFaces()) {
+      if (!face.personId) continue;
+      facesByPerson.set(face.personId, (facesByPerson.get(face.personId) ?? 0) + 1);
+      if (!photosByPerson.has(face.personId)) photosByPerson.set(face.personId, new Set());
+      photosByPerson.get(face.personId)!.add(face.photoId);
+    }
+
+    const centroids = await faceDetectionService.getPersonCentroids();
+
+    const seenTogether = (a: string, b: string) => {
+      const left = photosByPerson.get(a);
+      const right = photosByPerson.get(b);
+      if (!left || !right) return false;
+      const [smaller, larger] = left.size < right.size ? [left, right] : [right, left];
+      return Array.from(smaller).some(photoId => larger.has(photoId));
+    };
+
+    const suggestions: PeopleMergeSuggestion[] = [];
+    for (let i = 0; i < persons.length; i++) {
+      for (let j = i + 1; j < persons.length; j++) {
+        const [a, b] = [persons[i], persons[j]];
+        const names = nameSimilarity(a.name, b.name);
+        const centroidA = centroids.get(a.id);
+        const centroidB = centroids.get(b.id);
+        const facesAlike = centroidA && centroidB ? faceDetectionService.calculateEmbeddingSimilarity(centroidA, centroidB) : null;
+
+        const reasons: PeopleMergeSuggestion['reasons'] = [];
+        if (names >= query.minNameSimilarity && (facesAlike === null || facesAlike >= NAME_MATCH_MIN_FACE_SIMILARITY)) reasons.push('name');
+        if (facesAlike !== null && facesAlike >= query.minFaceSimilarity) reasons.push('face');
+        if (reasons.length === 0 || seenTogether(a.id, b.id)) continue;
+
+        const signals = facesAlike === null ? [names] : [names, facesAlike];
+        const ordered = (facesByPerson.get(b.id) ?? 0) > (facesByPerson.get(a.id) ?? 0) ? [b, a] : [a, b];
+        suggestions.push({
+          personIds: [ordered[0].id, ordered[1].id],
+          people: ordered.map(person => ({ id: person.id, name: person.name, faceCount: facesByPerson.get(person.id) ?? 0 })),
+          nameSimilarity: Math.round(names * 1000) / 1000,
+          faceSimilarity: facesAlike === null ? null : Math.round(facesAlike * 1000) / 1000,
+          // Both signals agreeing rank above either alone
+          score: Math.round(Math.max(...signals) * (reasons.length === 2 ? 1 : 0.9) * 1000) / 1000,
+          reasons,
+        });
+      }
+    }
+    return suggestions.sort((a, b) => b.score - a.score).slice(0, query.limit);
+  }
+
+  async previewMerge(personIds: string[]): Promise<MergePeoplePreview> {
+    const ids = Array.from(new Set(personIds));
+    const persons = await this.loadPeople(ids);
+
+    const facesByPerson = new Map<string, string[]>();
+    const peopleByPhoto = new Map<string, Set<string>>();
+    for (const person of persons) {
+      const personFaces = await storage.getFacesByPerson(person.id);
+      facesByPerson.set(person.id, personFaces.map(face => face.photoId));
+      for (const face of personFaces) {
+        const inPhoto = peopleByPhoto.get(face.photoId) ?? new Set<string>();
+        inPhoto.add(person.id);
+        peopleByPhoto.set(face.photoId, inPhoto);
+      }
+    }
+
+    const overlappingPhotos: MergePeoplePreview['overlappingPhotos'] = [];
+    peopleByPhoto.forEach((personSet, photoId) => {
+      if (personSet.size > 1) overlappingPhotos.push({ photoId, personIds: Array.from(personSet) });
+    });
+
+    const fieldConflicts: FieldConflict<string | Date | null>[] = [];
+    const names = persons.map(person => ({ personId: person.id, value: person.name }));
+    if (new Set(names.map(entry => entry.value.trim().toLowerCase())).size > 1) {
+      fieldConflicts.push({ field: 'name', values: names });
+    }
+    const birthdates = persons
+      .filter(person => person.birthdate)
+      .map(person => ({ personId: person.id, value: person.birthdate }));
+    if (new Set(birthdates.map(entry => entry.value!.toISOString().slice(0, 10))).size > 1) {
+      fieldConflicts.push({ field: 'birthdate', values: birthdates });
+    }
+    const notes = persons
+      .filter(person => person.notes && person.notes.trim())
+      .map(person => ({ personId: person.id, value: person.notes }));
+    if (new Set(notes.map(entry => entry.value!.trim())).size > 1) {
+      fieldConflicts.push({ field: 'notes', values: notes });
+    }
+
+    const { conflicts, betweenMerged } = await this.findRelationshipConflicts(ids);
+
+    const birthdayEvents = (await db.select({ id: events.id }).from(events).where(inArray(events.personId, ids))).length;
+    const personAlbums = (await storage.getPersonAlbums())
+      .filter(collection => ids.includes((collection.personAlbum as PersonAlbumConfig).personId)).length;
+
+    return {
+      people: persons.map(person => ({
+        id: person.id,
+        name: person.name,
+        faceCount: facesByPerson.get(person.id)!.length,
+        photoCount: new Set(facesByPerson.get(person.id)).size,
+        birthdate: person.birthdate,
+        notes: person.notes,
+      })),
+      totalFaces: Array.from(facesByPerson.values()).reduce((sum, photoIds) => sum + photoIds.length, 0),
+      totalPhotos: peopleByPhoto.size,
+      overlappingPhotos,
+      fieldConflicts,
+      relationshipConflicts: conflicts,
+      relationshipsBetweenMerged: betweenMerged.length,
+      birthdayEvents,
+      personAlbums,
+    };
+  }
+
+  /**
+   * Fold the source people into the target. Fields without a resolution keep
+   * the target's value, or the first source's when the target has none.
+   */
+  async mergePeople(request: MergePeopleRequest): Promise<Person> {
+    const { targetId, resolutions } = request;
+    const sourceIds = Array.from(new Set(request.sourceIds)).filter(id => id !== targetId);
+    if (sourceIds.length === 0) {
+      throw new PeopleMergeError('Choose at least one person other than the target to merge');
+    }
+    const allIds = [targetId, ...sourceIds];
+    const persons = await this.loadPeople(allIds);
+    const byId = new Map(persons.map(person => [person.id, person]));
+    const target = byId.get(targetId)!;
+
+    const pick = <K extends 'name' | 'birthdate' | 'notes'>(field: K, choice?: z.infer<typeof fieldChoiceSchema>): Person[K] => {
+      if (!choice) {
+        return target[field] ?? persons.map(person => person[field]).find(value => value != null) ?? target[field];
+      }
+      if ('fromPersonId' in choice) {
+        const person = byId.get(choice.fromPersonId);
+        if (!person) throw new PeopleMergeError(`${field} must come from one of the merged people`);
+        return person[field];
+      }
+      if (field === 'birthdate') {
+        return (choice.value ? new Date(choice.value) : null) as Person[K];
+      }
+      if (field === 'name' && !choice.value) {
+        throw new PeopleMergeError('Name cannot be empty');
+      }
+      return choice.value as Person[K];
+    };
+
+    const name = pick('name', resolutions.name);
+    const birthdate = pick('birthdate', resolutions.birthdate);
+    const notes = resolutions.notes === 'combine'
+      ? persons.map(person => person.notes?.trim()).filter(Boolean).filter((note, index, all) => all.indexOf(note) === index).join('\n\n') || null
+      : pick('notes', resolutions.notes);
+
+    let selectedThumbnailFaceId = target.selectedThumbnailFaceId;
+    let representativeFace = target.representativeFace;
+    if (resolutions.thumbnail) {
+      const person = byId.get(resolutions.thumbnail.fromPersonId);
+      if (!person) throw new PeopleMergeError('Thumbnail must come from one of the merged people');
+      selectedThumbnailFaceId = person.selectedThumbnailFaceId;
+      representativeFace = person.representativeFace;
+    }
+
+    const { conflicts, betweenMerged } = await this.findRelationshipConflicts(allIds);
+    const relationshipsToDrop = new Set(betweenMerged.map(relationship => relationship.id));
+    for (const conflict of conflicts) {
+      const keep = request.relationships[conflict.otherPersonId]
+        ?? conflict.options.find(option => option.personId === targetId)?.relationshipId
+        ?? conflict.options[0].relationshipId;
+      if (!conflict.options.some(option => option.relationshipId === keep)) {
+        throw new PeopleMergeError(`Relationship ${keep} does not belong to the conflict with ${conflict.otherPersonName ?? conflict.otherPersonId}`);
+      }
+      conflict.options.forEach(option => {
+        if (option.relationshipId !== keep) relationshipsToDrop.add(option.relationshipId);
+      });
+    }
+
+    const sourceAlbums = (await storage.getPersonAlbums())
+      .filter(collection => sourceIds.includes((collection.personAlbum as PersonAlbumConfig).personId));
+    const targetHasAlbum = (await storage.getPersonAlbums())
+      .some(collection => (collection.personAlbum as PersonAlbumConfig).personId === targetId);
+
+    return db.transaction(async (tx) => {
+      await tx.update(faces).set({ personId: targetId }).where(inArray(faces.personId, sourceIds));
+      await tx.update(events).set({ personId: targetId }).where(inArray(events.personId, sourceIds));
+
+      if (relationshipsToDrop.size > 0) {
+        await tx.delete(relationships).where(inArray(relationships.id, Array.from(relationshipsToDrop)));
+      }
+      await tx.update(relationships).set({ person1Id: targetId }).where(inArray(relationships.person1Id, sourceIds));
+      await tx.update(relationships).set({ person2Id: targetId }).where(inArray(relationships.person2Id, sourceIds));
+
+      // Keep one "best of" album: the target's, else the first source's re-pointed at the target
+      for (let index = 0; index < sourceAlbums.length; index++) {
+        const album = sourceAlbums[index];
+        if (!targetHasAlbum && index === 0) {
+          const config = album.personAlbum as PersonAlbumConfig;
+          await tx.update(collections)
+            .set({ personAlbum: { ...config, personId: targetId }, name: `Best of ${name}`, updatedAt: new Date() })
+            .where(eq(collections.id, album.id));
+        } else {
+          await tx.delete(collectionPhotos).where(eq(collectionPhotos.collectionId, album.id));
+          await tx.delete(collections).where(eq(collections.id, album.id));
+        }
+      }
+
+      const [{ faceCount }] = await tx
+        .select({ faceCount: count() })
+        .from(faces)
+        .where(eq(faces.personId, targetId));
+
+      // Privacy flags never loosen on merge
+      const excludeFromExports = persons.some(person => person.excludeFromExports);
+      const excludeFromShares = persons.some(person => person.excludeFromShares);
+      const excludeFromAi = persons.some(person => person.excludeFromAi);
+
+      const [merged] = await tx
+        .update(people)
+        .set({ name, birthdate, notes, selectedThumbnailFaceId, representativeFace, faceCount, excludeFromExports, excludeFromShares, excludeFromAi })
+        .where(eq(people.id, targetId))
+        .returning();
+
+      await tx.delete(people).where(inArray(people.id, sourceIds));
+      return merged;
+    });
+  }
+
+  private async loadPeople(ids: string[]): Promise<Person[]> {
+    const persons: Person[] = [];
+    for (const id of ids) {
+      const person = await storage.getPerson(id);
+      if (!person) throw new PeopleMergeError(`Person ${id} not found`);
+      persons.push(person);
+    }
+    return persons;
+  }
+
+  /**
+   * Relationships to the same outside person that disagree once the merged
+   * people become one, plus relationships among the merged people themselves
+   */
+  private async findRelationshipConflicts(ids: string[]): Promise<{ conflicts: RelationshipConflict[]; betweenMerged: Relationship[] }> {
+    const rows = await db
+      .select()
+      .from(relationships)
+      .where(or(inArray(relationships.person1Id, ids), inArray(relationships.person2Id, ids)));
+
+    const betweenMerged: Relationship[] = [];
+    const byOther = new Map<string, RelationshipConflict['options']>();
+    for (const row of rows) {
+      const firstIsMerged = ids.includes(row.person1Id);
+      const secondIsMerged = ids.includes(row.person2Id);
+      if (firstIsMerged && secondIsMerged) {
+        betweenMerged.push(row);
+        continue;
+      }
+      const personId = firstIsMerged ? row.person1Id : row.person2Id;
+      const otherPersonId = firstIsMerged ? row.person2Id : row.person1Id;
+      const relationshipType = firstIsMerged ? row.relationshipType : (INVERSE_RELATIONSHIP[row.relationshipType] ?? row.relationshipType);
+      const options = byOther.get(otherPersonId) ?? [];
+      options.push({ relationshipId: row.id, personId, relationshipType });
+      byOther.set(otherPersonId, options);
+    }
+
+    const conflicts: RelationshipConflict[] = [];
+    for (const otherPersonId of Array.from(byOther.keys())) {
+      const options = byOther.get(otherPersonId)!;
+      if (options.length < 2) continue;
+      const other = await storage.getPerson(otherPersonId);
+      conflicts.push({ otherPersonId, otherPersonName: other?.name ?? null, options });
+    }
+    return { conflicts, betweenMerged };
+  }
+}
+
+export const peopleMergeService = new PeopleMergeService();
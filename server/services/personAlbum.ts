@@ -0,0 +1,138 @@
+import { storage } from "../storage";
+import { getEffectiveDate } from "../utils/photoDates";
+import type { Collection, Face, FileVersion, PersonAlbumConfig, PersonAlbumOptions } from "@shared/schema";
+
+const DEFAULT_OPTIONS: Required<PersonAlbumOptions> = {
+  limit: 60,
+  minConfidence: 60,
+  minRating: 0,
+  maxPerYear: 12,
+};
+
+const TIER_SCORES: Record<string, number> = { gold: 1, silver: 0.5, bronze: 0 };
+
+interface ScoredPhoto {
+  photo: FileVersion;
+  score: number;
+  year: number;
+}
+
+/**
+ * Albums of a person's best photos, built from confirmed faces and refreshed
+ * like smart collections as new photos are tagged.
+ */
+class PersonAlbumService {
+  /**
+   * Create the album for a person, or refresh the existing one with new options
+   */
+  async generatePersonAlbum(personId: string, options: PersonAlbumOptions = {}): Promise<{ collection: Collection; photoCount: number }> {
+    const person = await storage.getPerson(personId);
+    if (!person) {
+      throw new Error('Person not found');
+    }
+
+    const existing = (await storage.getPersonAlbums())
+      .find(collection => (collection.personAlbum as PersonAlbumConfig).personId === personId);
+    const config: PersonAlbumConfig = { personId, options };
+
+    const collection = existing
+      ? await storage.updateCollection(existing.id, { personAlbum: config, updatedAt: new Date() })
+      : await storage.createCollection({
+          name: `Best of ${person.name}`,
+          description: `The best photos of ${person.name}, refreshed automatically`,
+          personAlbum: config,
+        });
+
+    const photoCount = await this.refreshPersonAlbum(collection);
+    return { collection, photoCount };
+  }
+
+  async refreshPersonAlbum(collection: Collection): Promise<number> {
+    const config = collection.personAlbum as PersonAlbumConfig;
+    const photoIds = await this.selectPhotos(config.personId, { ...DEFAULT_OPTIONS, ...config.options });
+    return storage.replaceCollectionPhotos(collection.id, photoIds);
+  }
+
+  async refreshAll(): Promise<void> {
+    for (const collection of await storage.getPersonAlbums()) {
+      try {
+        await this.refreshPersonAlbum(collection);
+      } catch (error) {
+        console.error(`Failed to refresh person album ${collection.name}:`, error);
+      }
+    }
+  }
+
+  /**
+   * Rank the person's photos by face confidence, prominence, rating and tier,
+   * then pick round-robin across years so the album spans their whole life in
+   * the library instead of clustering around the best-documented year.
+   */
+  private async selectPhotos(personId: string, options: Required<PersonAlbumOptions>): Promise<string[]> {
+    const faces = (await storage.getFacesByPerson(personId))
+      .filter(face => !face.ignored && face.confidence >= options.minConfidence);
+    if (faces.length === 0) return [];
+
+    // Faces point at the version that was analysed; show the highest tier of each asset
+    const allVersions = await storage.getAllFileVersions();
+    const versionsById = new Map(allVersions.map(version => [version.id, version]));
+    const bestByAsset = new Map<string, FileVersion>();
+    for (const version of allVersions) {
+      const current = bestByAsset.get(version.mediaAssetId);
+      if (!current || (TIER_SCORES[version.tier] ?? 0) > (TIER_SCORES[current.tier] ?? 0)) {
+        bestByAsset.set(version.mediaAssetId, version);
+      }
+    }
+
+    const scoredByAsset = new Map<string, ScoredPhoto>();
+    for (const face of faces) {
+      const facePhoto = versionsById.get(face.photoId);
+      if (!facePhoto) continue;
+      const photo = bestByAsset.get(facePhoto.mediaAssetId) ?? facePhoto;
+      if ((photo.rating ?? 0) < options.minRating) continue;
+
+      const score = this.scorePhoto(photo, face);
+      const existing = scoredByAsset.get(photo.mediaAssetId);
+      if (!existing || score > existing.score) {
+        scoredByAsset.set(photo.mediaAssetId, { photo, score, year: getEffectiveDate(photo).getFullYear() });
+      }
+    }
+
+    const byYear = new Map<number, ScoredPhoto[]>();
+    scoredByAsset.forEach(entry => {
+      const list = byYear.get(entry.year) ?? [];
+      list.push(entry);
+      byYear.set(entry.year, list);
+    });
+    const years = Array.from(byYear.keys()).sort((a, b) => a - b);
+    byYear.forEach(list => list.sort((a, b) => b.score - a.score));
+
+    const selected: string[] = [];
+    for (let round = 0; round < options.maxPerYear && selected.length < options.limit; round++) {
+      for (const year of years) {
+        const entry = byYear.get(year)![round];
+        if (!entry) continue;
+        selected.push(entry.photo.id);
+        if (selected.length >= options.limit) break;
+      }
+    }
+    return selected;
+  }
+
+  private scorePhoto(photo: FileVersion, face: Face): number {
+    const confidence = face.confidence / 100;
+    const rating = (photo.rating ?? 0) / 5;
+    const tier = TIER_SCORES[photo.tier] ?? 0;
+
+    // Larger faces make better portraits; unknown dimensions score neutral
+    let prominence = 0.5;
+    const [, , boxWidth, boxHeight] = face.boundingBox as [number, number, number, number];
+    if (photo.width && photo.height && boxWidth && boxHeight) {
+      prominence = Math.min(1, Math.sqrt((boxWidth * boxHeight) / (photo.width * photo.height)) * 3);
+    }
+
+    return confidence * 0.3 + rating * 0.3 + prominence * 0.2 + tier * 0.2;
+  }
+}
+
+export const personAlbumService = new PersonAlbumService();
@@ -0,0 +1,195 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { faceCropService, FaceCropUnavailableError } from "./faceCrops";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import type { CancellationToken } from "./operations";
+import { getEffectiveDate } from "../utils/photoDates";
+import type { FaceBox } from "../utils/faceBoxes";
+import type { Face, FileVersion, Person } from "@shared/schema";
+
+export const personFaceExportSchema = z.object({
+  destination: z.string().min(1),
+  size: z.number().int().min(64).max(2048).default(512),
+  perYear: z.boolean().default(false), // only the best face of each year
+  minConfidence: z.number().int().min(0).max(100).default(50),
+});
+
+export type PersonFaceExportInput = z.infer<typeof personFaceExportSchema>;
+
+// Periods a person's faces can be thinned to, keeping the best face of each
+export type FacePeriod = 'year' | 'month';
+
+export interface PersonFace {
+  face: Face;
+  photo: FileVersion;
+  takenAt: Date;
+  period: string; // "2019", or "2019-04" by month; the date by default
+}
+
+export interface PersonFaceManifest {
+  createdAt: string;
+  person: { id: string; name: string; birthdate: string | null };
+  size: number;
+  perYear: boolean;
+  faces: Array<{
+    file: string;
+    faceId: string;
+    photoId: string;
+    takenAt: string;
+    age: number | null; // whole years at the photo's date, when the birthdate is known
+  }>;
+}
+
+export interface PersonFaceExportResult {
+  destination: string;
+  manifestPath: string;
+  exported: number;
+  skipped: Array<{ faceId: string; photoId: string; reason: string }>;
+}
+
+export class PersonFaceExportError extends Error {}
+
+/**
+ * A person's face across the years as same-sized, eye-levelled crops, named
+ * by date so they sort into order for birthday montages and timelapses.
+ * Crops come from the face crop cache beside the thumbnails, so faces in
+ * photos whose originals are offline still export once they have been
+ * cropped.
+ */
+class PersonFaceExportService {
+  async exportPersonFaces(
+    personId: string,
+    input: PersonFaceExportInput,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<PersonFaceExportResult> {
+    const person = await this.getPerson(personId);
+    const faces = await this.choosePersonFaces(person.id, {
+      per: input.perYear ? 'year' : undefined,
+      minConfidence: input.minConfidence,
+      size: input.size,
+    });
+
+    await fs.mkdir(input.destination, { recursive: true });
+    const manifest: PersonFaceManifest = {
+      createdAt: new Date().toISOString(),
+      person: { id: person.id, name: person.name, birthdate: person.birthdate?.toISOString() ?? null },
+      size: input.size,
+      perYear: input.perYear,
+      faces: [],
+    };
+    const skipped: PersonFaceExportResult['skipped'] = [];
+
+    for (let index = 0; index < faces.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(index, faces.length);
+      const { face, photo, takenAt } = faces[index];
+      try {
+        const crop = await faceCropService.getCachedCrop(face, photo, input.size);
+        const file = `${this.dateStamp(takenAt)}_${face.id.slice(0, 8)}.jpg`;
+        await fs.copyFile(crop, path.join(input.destination, file));
+        await provenanceService.record(photo, 'EXPORTED', `Face crop of ${person.name} exported to ${input.destination}`, {
+          kind: 'person faces',
+          destination: input.destination,
+          faceId: face.id,
+        });
+        manifest.faces.push({
+          file,
+          faceId: face.id,
+          photoId: photo.id,
+          takenAt: takenAt.toISOString(),
+          age: person.birthdate ? this.ageAt(person.birthdate, takenAt) : null,
+        });
+      } catch (error) {
+        if (!(error instanceof FaceCropUnavailableError)) {
+          console.error(`Failed to export face ${face.id}:`, error);
+        }
+        skipped.push({ faceId: face.id, photoId: photo.id, reason: error instanceof Error ? error.message : 'Failed to crop face' });
+      }
+    }
+    onProgress?.(faces.length, faces.length);
+
+    const manifestPath = path.join(input.destination, 'manifest.json');
+    await fs.writeFile(manifestPath, JSON.stringify(manifest, null, 2));
+    return { destination: input.destination, manifestPath, exported: manifest.faces.length, skipped };
+  }
+
+  /**
+   * A person's usable faces in date order: confirmed, not ignored, and not in
+   * photos held back from exports. With `per`, only the best face of each
+   * year or month is kept: the largest relative to the output size, then the
+   * most confident.
+   */
+  async choosePersonFaces(
+    personId: string,
+    options: { per?: FacePeriod; minConfidence: number; size: number }
+  ): Promise<PersonFace[]> {
+    // A crop can show more than its own face, so any private person in the photo holds it back
+    const restricted = await privacyService.getRestrictedPhotos('export');
+    const candidates: Array<PersonFace & { score: number }> = [];
+
+    for (const face of await storage.getFacesByPerson(personId)) {
+      if (face.ignored || face.confidence < options.minConfidence || restricted.ids.has(face.photoId)) continue;
+      const photo = await storage.getFileVersion(face.photoId);
+      if (!photo || photo.deletedAt) continue;
+
+      const takenAt = getEffectiveDate(photo);
+      const [, , width, height] = face.boundingBox as FaceBox;
+      candidates.push({
+        face,
+        photo,
+        takenAt,
+        period: this.periodOf(takenAt, options.per),
+        // A face smaller than the output is scaled up and looks soft
+        score: Math.min(1, Math.max(width, height) / options.size) + face.confidence / 100,
+      });
+    }
+
+    let chosen: PersonFace[] = candidates;
+    if (options.per) {
+      const best = new Map<string, PersonFace & { score: number }>();
+      for (const candidate of candidates) {
+        const current = best.get(candidate.period);
+        if (!current || candidate.score > current.score) best.set(candidate.period, candidate);
+      }
+      chosen = Array.from(best.values());
+    }
+    return chosen
+      .map(({ face, photo, takenAt, period }) => ({ face, photo, takenAt, period }))
+      .sort((a, b) => a.takenAt.getTime() - b.takenAt.getTime());
+  }
+
+  async getPerson(personId: string): Promise<Person> {
+    const person = await storage.getPerson(personId);
+    if (!person || person.deletedAt) {
+      throw new PersonFaceExportError('Person not found');
+    }
+    return person;
+  }
+
+  ageAt(birthdate: Date, date: Date): number {
+    let age = date.getFullYear() - birthdate.getFullYear();
+    if (date.getMonth() < birthdate.getMonth() || (date.getMonth() === birthdate.getMonth() && date.getDate() < birthdate.getDate())) {
+      age--;
+    }
+    return Math.max(0, age);
+  }
+
+  private periodOf(date: Date, per?: FacePeriod): string {
+    const stamp = this.dateStamp(date);
+    if (per === 'year') return stamp.slice(0, 4);
+    if (per === 'month') return stamp.slice(0, 7);
+    return stamp;
+  }
+
+  // Local date as YYYY-MM-DD, so file names sort by date
+  private dateStamp(date: Date): string {
+    const pad = (part: number) => String(part).padStart(2, '0');
+    return `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())}`;
+  }
+}
+
+export const personFaceExportService = new PersonFaceExportService();
@@ -0,0 +1,200 @@
+import fs from "fs/promises";
+import path from "path";
+import crypto from "crypto";
+import sharp from "sharp";
+import { spawn } from "child_process";
+import { z } from "zod";
+import { faceCropService } from "./faceCrops";
+import { personFaceExportService, type PersonFace } from "./personFaceExport";
+import { provenanceService } from "./provenance";
+import { OperationCancelledError, type CancellationToken } from "./operations";
+import { scratchPath } from "../utils/libraryPaths";
+
+export const personTimelapseSchema = z.object({
+  destination: z.string().min(1), // folder the MP4 is written to
+  per: z.enum(['month', 'year']).default('year'),
+  size: z.number().int().min(128).max(2160).multipleOf(2).default(720), // H.264 in yuv420p needs even sides
+  secondsPerFace: z.number().min(0.1).max(10).default(1),
+  blend: z.boolean().default(true), // cross-fade from one face to the next
+  caption: z.boolean().default(true), // date and age in the corner of each face
+  minConfidence: z.number().int().min(0).max(100).default(50),
+});
+
+export type PersonTimelapseInput = z.infer<typeof personTimelapseSchema>;
+
+export interface PersonTimelapseResult {
+  path: string;
+  durationSeconds: number;
+  frames: Array<{ faceId: string; photoId: string; takenAt: string; period: string; age: number | null }>;
+  skipped: Array<{ faceId: string; photoId: string; reason: string }>;
+}
+
+export class PersonTimelapseError extends Error {
+  constructor(message: string, readonly status: number) {
+    super(message);
+  }
+}
+
+const OUTPUT_FPS = 30;
+
+/**
+ * "Watch them grow up" videos: one eye-levelled face of a person per month
+ * or year, in date order, encoded as an MP4 with ffmpeg (FFMPEG_PATH, or
+ * ffmpeg on the PATH). Faces come from the same crop cache as face exports.
+ * Progress counts faces prepared, then frames encoded.
+ */
+class PersonTimelapseService {
+  async generatePersonTimelapse(
+    personId: string,
+    input: PersonTimelapseInput,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<PersonTimelapseResult> {
+    const person = await personFaceExportService.getPerson(personId);
+    const faces = await personFaceExportService.choosePersonFaces(person.id, {
+      per: input.per,
+      minConfidence: input.minConfidence,
+      size: input.size,
+    });
+    if (faces.length < 2) {
+      throw new PersonTimelapseError(`A timelapse needs faces of ${person.name} from at least two different ${input.per}s`, 400);
+    }
+
+    const workDir = scratchPath('timelapse', crypto.randomUUID());
+    await fs.mkdir(workDir, { recursive: true });
+    try {
+      const frames: PersonTimelapseResult['frames'] = [];
+      const skipped: PersonTimelapseResult['skipped'] = [];
+      const used: PersonFace[] = [];
+      // Encoding is counted in output frames, estimated until it is known how many faces could be cropped
+      const estimatedTotal = faces.length + Math.ceil(faces.length * input.secondsPerFace * OUTPUT_FPS);
+
+      for (let index = 0; index < faces.length; index++) {
+        token?.throwIfCancelled();
+        onProgress?.(index, estimatedTotal);
+        const personFace = faces[index];
+        const { face, photo, takenAt, period } = personFace;
+        try {
+          const crop = await faceCropService.getCachedCrop(face, photo, input.size);
+          const age = person.birthdate ? personFaceExportService.ageAt(person.birthdate, takenAt) : null;
+          await this.writeFrame(crop, path.join(workDir, this.frameName(used.length)), input.caption ? this.captionFor(period, age) : null, input.size);
+          used.push(personFace);
+          frames.push({ faceId: face.id, photoId: photo.id, takenAt: takenAt.toISOString(), period, age });
+        } catch (error) {
+          skipped.push({ faceId: face.id, photoId: photo.id, reason: error instanceof Error ? error.message : 'Failed to crop face' });
+        }
+      }
+      if (used.length < 2) {
+        throw new PersonTimelapseError(`Only ${used.length} of ${person.name}'s faces could be cropped`, 409);
+      }
+      // The image sequence demuxer cuts the last picture short; repeat it so it is shown for its full time
+      await fs.copyFile(path.join(workDir, this.frameName(used.length - 1)), path.join(workDir, this.frameName(used.length)));
+
+      await fs.mkdir(input.destination, { recursive: true });
+      const outputPath = path.join(input.destination, `${this.sanitize(person.name)}-timelapse-${input.per}.mp4`);
+      const totalFrames = Math.ceil(used.length * input.secondsPerFace * OUTPUT_FPS);
+      await this.encode(workDir, outputPath, input, totalFrames, token, encoded => onProgress?.(faces.length + encoded, faces.length + totalFrames));
+
+      for (const { photo, face } of used) {
+        await provenanceService.record(photo, 'EXPORTED', `Face used in timelapse of ${person.name} at ${outputPath}`, {
+          kind: 'person timelapse',
+          destination: outputPath,
+          faceId: face.id,
+        });
+      }
+      return { path: outputPath, durationSeconds: used.length * input.secondsPerFace, frames, skipped };
+    } finally {
+      await fs.rm(workDir, { recursive: true, force: true });
+    }
+  }
+
+  private async writeFrame(cropPath: string, framePath: string, caption: string | null, size: number): Promise<void> {
+    let frame = sharp(cropPath).resize(size, size);
+    if (caption) {
+      const fontSize = Math.round(size / 18);
+      const svg = `<svg xmlns="http://www.w3.org/2000/svg" width="${size}" height="${fontSize * 2}">
+        <rect width="100%" height="100%" fill="black" fill-opacity="0.45"/>
+        <text x="${fontSize * 0.6}" y="${fontSize * 1.35}" font-family="sans-serif" font-size="${fontSize}" fill="white">${caption}</text>
+      </svg>`;
+      frame = frame.composite([{ input: Buffer.from(svg), gravity: 'south' }]);
+    }
+    await frame.jpeg({ quality: 92 }).toFile(framePath);
+  }
+
+  private captionFor(period: string, age: number | null): string {
+    return age === null ? period : `${period} · age ${age}`;
+  }
+
+  /**
+   * Run ffmpeg over the numbered frames. Its machine-readable progress
+   * reports the output frame reached; cancelling kills it.
+   */
+  private encode(
+    workDir: string,
+    outputPath: string,
+    input: PersonTimelapseInput,
+    totalFrames: number,
+    token: CancellationToken | undefined,
+    onFrame: (encoded: number) => void
+  ): Promise<void> {
+    const filter = input.blend
+      // Interpolate between the slow input pictures, which blends each face into the next
+      ? `framerate=fps=${OUTPUT_FPS}:interp_start=0:interp_end=255:scene=100`
+      : `fps=${OUTPUT_FPS}`;
+    const args = [
+      '-y', '-hide_banner', '-loglevel', 'error', '-nostats', '-progress', 'pipe:1',
+      '-framerate', `1/${input.secondsPerFace}`,
+      '-i', path.join(workDir, 'frame-%05d.jpg'),
+      '-vf', `${filter},format=yuv420p`,
+      '-frames:v', String(totalFrames),
+      '-c:v', 'libx264', '-preset', 'medium', '-crf', '20',
+      '-movflags', '+faststart',
+      outputPath,
+    ];
+
+    return new Promise((resolve, reject) => {
+      const child = spawn(process.env.FFMPEG_PATH || 'ffmpeg', args, { windowsHide: true });
+      let errorOutput = '';
+      let cancelled = false;
+
+      child.stdout.on('data', (chunk: Buffer) => {
+        if (token?.isCancelled && !cancelled) {
+          cancelled = true;
+          child.kill('SIGKILL');
+          return;
+        }
+        const frame = chunk.toString().match(/frame=(\d+)/g)?.pop();
+        if (frame) onFrame(Math.min(totalFrames, Number(frame.slice('frame='.length))));
+      });
+      child.stderr.on('data', (chunk: Buffer) => {
+        errorOutput = (errorOutput + chunk.toString()).slice(-2000);
+      });
+      child.on('error', (error: NodeJS.ErrnoException) => {
+        reject(error.code === 'ENOENT'
+          ? new PersonTimelapseError('ffmpeg was not found; install it or set FFMPEG_PATH', 503)
+          : error);
+      });
+      child.on('close', async code => {
+        if (cancelled && token) {
+          await fs.rm(outputPath, { force: true });
+          reject(new OperationCancelledError(token.operationId));
+        } else if (code === 0) {
+          resolve();
+        } else {
+          await fs.rm(outputPath, { force: true });
+          reject(new Error(`ffmpeg exited with code ${code}: ${errorOutput.trim()}`));
+        }
+      });
+    });
+  }
+
+  private frameName(index: number): string {
+    return `frame-${String(index).padStart(5, '0')}.jpg`;
+  }
+
+  private sanitize(name: string): string {
+    return name.replace(/[<>:"/\\|?*\x00-\x1f]/g, '').replace(/\s+/g, '_').slice(0, 60) || 'person';
+  }
+}
+
+export const personTimelapseService = new PersonTimelapseService();
@@ -0,0 +1,165 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { getCaptureDate } from "../utils/photoDates";
+import { computeDaylight, getCaptureContext, type CaptureContext } from "../utils/daylight";
+import type { CancellationToken } from "./operations";
+import type { FileVersion, PhotoEnvironment, WeatherCondition } from "@shared/schema";
+
+export const enrichEnvironmentSchema = z.object({
+  photoIds: z.array(z.string()).min(1).optional(), // the whole library when left out
+  weather: z.boolean().default(false), // also look up historical weather online
+  force: z.boolean().default(false), // redo photos that already have it
+});
+
+export type EnrichEnvironmentInput = z.infer<typeof enrichEnvironmentSchema>;
+
+export interface EnrichEnvironmentResult {
+  enriched: number;
+  skipped: number; // no GPS position or capture date, or already done
+  weatherFailed: number;
+}
+
+// Open-Meteo's archive of ERA5 reanalysis: hourly weather anywhere since 1940, no API key
+const WEATHER_API_URL = process.env.WEATHER_API_URL || 'https://archive-api.open-meteo.com/v1/archive';
+const WEATHER_SOURCE = 'open-meteo';
+const WEATHER_REQUEST_DELAY_MS = 250;
+const HOURLY_FIELDS = ['weather_code', 'temperature_2m', 'precipitation', 'snowfall', 'cloud_cover', 'wind_speed_10m'];
+
+type HourlyWeather = Record<string, Array<number | null>> & { time: string[] };
+
+// WMO weather interpretation codes
+function weatherCondition(code: number): WeatherCondition {
+  if (code === 0) return 'clear';
+  if (code <= 2) return 'partly_cloudy';
+  if (code === 3) return 'cloudy';
+  if (code === 45 || code === 48) return 'fog';
+  if (code >= 51 && code <= 57) return 'drizzle';
+  if ((code >= 71 && code <= 77) || code === 85 || code === 86) return 'snow';
+  if (code >= 95) return 'thunderstorm';
+  return 'rain';
+}
+
+/**
+ * Conditions a photo was taken in, from its GPS position and capture time:
+ * where the sun was (computed locally) and, optionally, the weather at that
+ * hour from a historical weather service. Stored on the photo so searches
+ * can ask for golden hour shots or snowy days. The daylight phase alone is
+ * also kept in its own column, filled in whenever metadata is written.
+ */
+class PhotoEnvironmentService {
+  private weatherCache = new Map<string, HourlyWeather>();
+  private lastWeatherRequest = 0;
+
+  getCaptureContext(photo: FileVersion): CaptureContext | null {
+    return getCaptureContext(getCaptureDate(photo), photo.gpsLatitude, photo.gpsLongitude);
+  }
+
+  computeDaylight(photo: FileVersion): PhotoEnvironment['daylight'] | null {
+    return computeDaylight(getCaptureDate(photo), photo.gpsLatitude, photo.gpsLongitude);
+  }
+
+  async enrich(
+    input: EnrichEnvironmentInput,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<EnrichEnvironmentResult> {
+    const photos = input.photoIds
+      ? (await Promise.all(Array.from(new Set(input.photoIds)).map(id => storage.getFileVersion(id))))
+        .filter((photo): photo is FileVersion => !!photo && !photo.deletedAt)
+      : await storage.getAllFileVersions();
+    const result: EnrichEnvironmentResult = { enriched: 0, skipped: 0, weatherFailed: 0 };
+
+    for (let index = 0; index < photos.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, photos.length);
+      const photo = photos[index];
+      const current = photo.environment;
+      const done = !!current?.daylight && (!input.weather || !!current.weather);
+      const daylight = this.computeDaylight(photo);
+      if (!daylight || (done && !input.force)) {
+        result.skipped++;
+        continue;
+      }
+
+      const environment: PhotoEnvironment = { ...(current ?? {}), daylight, enrichedAt: new Date().toISOString() };
+      if (input.weather && (input.force || !current?.weather)) {
+        try {
+          const weather = await this.lookUpWeather(photo);
+          if (weather) environment.weather = weather;
+        } catch (error) {
+          console.error(`Weather lookup failed for photo ${photo.id}:`, error);
+          result.weatherFailed++;
+        }
+      }
+      await storage.updateFileVersion(photo.id, { environment });
+      result.enriched++;
+    }
+    onProgress?.(photos.length, photos.length);
+    this.weatherCache.clear();
+    return result;
+  }
+
+  /**
+   * Recompute the sun for a photo whose date or position was edited. Weather
+   * looked up for the old values no longer applies and is dropped.
+   */
+  async refresh(photo: FileVersion): Promise<FileVersion> {
+    if (!photo.environment) return photo;
+    const daylight = this.computeDaylight(photo);
+    return storage.updateFileVersion(photo.id, {
+      environment: daylight ? { daylight, enrichedAt: new Date().toISOString() } : null,
+    });
+  }
+
+  private async lookUpWeather(photo: FileVersion): Promise<PhotoEnvironment['weather'] | null> {
+    const context = this.getCaptureContext(photo);
+    if (!context || context.instant.getTime() > Date.now()) return null;
+
+    const day = context.instant.toISOString().slice(0, 10);
+    // A tenth of a degree is finer than the reanalysis grid, so nearby photos share a request
+    const latitude = context.latitude.toFixed(1);
+    const longitude = context.longitude.toFixed(1);
+    const key = `${latitude},${longitude},${day}`;
+
+    let hourly = this.weatherCache.get(key);
+    if (!hourly) {
+      const wait = this.lastWeatherRequest + WEATHER_REQUEST_DELAY_MS - Date.now();
+      if (wait > 0) await new Promise(resolve => setTimeout(resolve, wait));
+      this.lastWeatherRequest = Date.now();
+
+      const params = new URLSearchParams({
+        latitude,
+        longitude,
+        start_date: day,
+        end_date: day,
+        hourly: HOURLY_FIELDS.join(','),
+        timezone: 'GMT',
+      });
+      const response = await fetch(`${WEATHER_API_URL}?${params}`, { signal: AbortSignal.timeout(15000) });
+      if (!response.ok) {
+        throw new Error(`Weather service answered ${response.status}`);
+      }
+      const body = await response.json() as { hourly?: HourlyWeather };
+      if (!body.hourly?.time) return null;
+      hourly = body.hourly;
+      this.weatherCache.set(key, hourly);
+    }
+
+    const index = context.instant.getUTCHours();
+    const code = hourly.weather_code?.[index];
+    if (code === null || code === undefined) return null;
+    const value = (field: string) => hourly![field]?.[index] ?? null;
+    return {
+      condition: weatherCondition(code),
+      weatherCode: code,
+      temperatureC: value('temperature_2m'),
+      precipitationMm: value('precipitation'),
+      snowfallCm: value('snowfall'),
+      cloudCoverPercent: value('cloud_cover'),
+      windSpeedKmh: value('wind_speed_10m'),
+      source: WEATHER_SOURCE,
+    };
+  }
+}
+
+export const photoEnvironmentService = new PhotoEnvironmentService();
@@ -0,0 +1,145 @@
+import fs from "fs/promises";
+import path from "path";
+import sharp from "sharp";
+import { z } from "zod";
+import { storage } from "../storage";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import { propagationService } from "./propagation";
+import { volumeStatusService } from "./volumeStatus";
+import { metadataEmbedding, type OrganizationMetadata } from "./metadataEmbedding";
+import type { CancellationToken } from "./operations";
+import type { FileVersion } from "@shared/schema";
+
+export const photoExportSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+  destination: z.string().min(1),
+  // Write tags, rating, GPS and people into the copies; otherwise they are byte-for-byte copies
+  embedMetadata: z.boolean().optional(),
+});
+
+export type PhotoExportOptions = Omit<z.infer<typeof photoExportSchema>, 'photoIds' | 'destination'>;
+
+export interface PhotoExportResult {
+  destination: string;
+  exported: number;
+  embedded: number; // copies with the metadata inside the file
+  sidecars: number; // copies with the metadata in an XMP sidecar
+  skipped: Array<{ photoId: string; reason: string }>;
+  failed: Array<{ photoId: string; reason: string }>;
+}
+
+/**
+ * Copy photos out of the library into a folder, under their original
+ * filenames. With embedMetadata the copies carry their organization along
+ * (tags, keywords, star rating, GPS and the names and face regions of the
+ * people in them) in the standard fields Lightroom, Photos and digiKam read.
+ * The library files themselves are never modified.
+ */
+class PhotoExportService {
+  async exportPhotos(
+    photoIds: string[],
+    destination: string,
+    options: PhotoExportOptions = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<PhotoExportResult> {
+    const restricted = await privacyService.getRestrictedPhotos('export');
+    const result: PhotoExportResult = { destination, exported: 0, embedded: 0, sidecars: 0, skipped: [], failed: [] };
+    const usedNames = new Set<string>();
+    await fs.mkdir(destination, { recursive: true });
+
+    for (let index = 0; index < photoIds.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(index, photoIds.length);
+      const photoId = photoIds[index];
+
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo || photo.deletedAt) {
+        result.skipped.push({ photoId, reason: 'Photo not found' });
+        continue;
+      }
+      if (restricted.ids.has(photo.id)) {
+        result.skipped.push({ photoId, reason: 'privacy' });
+        continue;
+      }
+      const original = await volumeStatusService.locateOriginal(photo);
+      if (!original.available) {
+        result.skipped.push({ photoId, reason: `Original is offline on ${original.volumeLabel}` });
+        continue;
+      }
+
+      try {
+        const asset = await storage.getMediaAsset(photo.mediaAssetId);
+        const target = path.join(destination, this.uniqueName(asset?.originalFilename ?? path.basename(photo.filePath), usedNames));
+        await fs.copyFile(original.path, target);
+
+        if (options.embedMetadata) {
+          const written = await metadataEmbedding.embedOrganizationMetadata(target, photo.mimeType, await this.organization(photo, original.path));
+          if (written === 'embedded') result.embedded++;
+          else result.sidecars++;
+        }
+
+        await provenanceService.record(photo, 'EXPORTED', `Exported to ${destination}`, {
+          kind: 'photos',
+          destination,
+          embeddedMetadata: options.embedMetadata ?? false,
+        });
+        result.exported++;
+      } catch (error) {
+        console.error(`Failed to export photo ${photo.id}:`, error);
+        result.failed.push({ photoId, reason: error instanceof Error ? error.message : 'Failed to copy photo' });
+      }
+    }
+    onProgress?.(photoIds.length, photoIds.length);
+
+    return result;
+  }
+
+  private async organization(photo: FileVersion, originalPath: string): Promise<OrganizationMetadata> {
+    const faceRegions: OrganizationMetadata['faceRegions'] = [];
+    for (const face of await storage.getFacesByPhoto(photo.id)) {
+      if (!face.personId || face.ignored) continue;
+      const person = await storage.getPerson(face.personId);
+      if (!person || person.deletedAt) continue;
+      faceRegions.push({ name: person.name, boundingBox: face.boundingBox as [number, number, number, number] });
+    }
+
+    const names = faceRegions.map(region => region.name);
+    const keywords = Array.from(new Set([...propagationService.getTags(photo), ...(photo.keywords ?? []), ...names]));
+    const gps = photo.gpsLatitude != null && photo.gpsLongitude != null
+      ? { latitude: photo.gpsLatitude, longitude: photo.gpsLongitude }
+      : null;
+
+    return {
+      keywords,
+      rating: photo.rating ?? 0,
+      gps,
+      dimensions: faceRegions.length > 0 ? await this.dimensions(originalPath) : null,
+      faceRegions,
+    };
+  }
+
+  // Face boxes are in the stored pixel orientation, as detection reads them
+  private async dimensions(originalPath: string): Promise<OrganizationMetadata['dimensions']> {
+    try {
+      const { width, height } = await sharp(originalPath).metadata();
+      return width && height ? { width, height } : null;
+    } catch {
+      return null;
+    }
+  }
+
+  private uniqueName(filename: string, used: Set<string>): string {
+    const extension = path.extname(filename);
+    const stem = path.basename(filename, extension);
+    let candidate = filename;
+    for (let counter = 1; used.has(candidate.toLowerCase()); counter++) {
+      candidate = `${stem}_${counter}${extension}`;
+    }
+    used.add(candidate.toLowerCase());
+    return candidate;
+  }
+}
+
+export const photoExportService = new PhotoExportService();
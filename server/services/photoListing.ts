@@ -0,0 +1,295 @@
+import { and, asc, desc, eq, isNull, sql, type SQL } from "drizzle-orm";
+import { alias } from "drizzle-orm/pg-core";
+import { db } from "../db";
+import {
+  fileVersions,
+  mediaAssets,
+  photoStacks,
+  photoStackMembers,
+  collections,
+  collectionPhotos,
+  faces,
+  type FileVersion,
+  type MediaAsset,
+} from "@shared/schema";
+import { decodeCursor, encodeCursor, type CursorState, type Page, type PageQuery } from "../utils/pagination";
+import type { StackAnnotation } from "./photoStacks";
+
+// Another photo of the same listing, for the stack checks
+const otherPhoto = alias(fileVersions, 'other_photo');
+
+export type PhotoColumns = typeof fileVersions | typeof otherPhoto;
+
+/**
+ * Which photos a listing shows, as a condition on one file_versions row.
+ * Collapsing stacks applies it to the other members of a stack too, so it
+ * must only refer to the photo it is given.
+ */
+export type PhotoCondition = (photo: PhotoColumns) => SQL | undefined;
+
+export interface SortKey {
+  value: SQL; // never null, so a page can resume after it
+  type: 'timestamp' | 'integer' | 'real' | 'text'; // how the value is read back from a cursor
+  direction: 'asc' | 'desc';
+}
+
+export interface PhotoListing {
+  where?: PhotoCondition;
+  order: SortKey[]; // the photo id breaks remaining ties
+  collapseStacks: boolean;
+}
+
+export type ListedPhoto = FileVersion & { mediaAsset: MediaAsset } & StackAnnotation;
+
+/**
+ * When the photo was taken, falling back to its import time like getEffectiveDate
+ */
+export function takenAt(photo: PhotoColumns): SQL {
+  return sql`COALESCE(${photo.takenAt}, ${photo.createdAt})`;
+}
+
+export function byTakenAt(direction: 'asc' | 'desc' = 'desc'): SortKey {
+  return { value: takenAt(fileVersions), type: 'timestamp', direction };
+}
+
+export function byCreatedAt(direction: 'asc' | 'desc' = 'desc'): SortKey {
+  return { value: sql`${fileVersions.createdAt}`, type: 'timestamp', direction };
+}
+
+/**
+ * Hides silver versions of assets that also have a gold version
+ */
+export function notSuperseded(photo: PhotoColumns): SQL {
+  return sql`NOT (${photo.tier} = 'silver' AND EXISTS (
+    SELECT 1 FROM ${fileVersions} newer
+    WHERE newer.media_asset_id = ${photo.mediaAssetId} AND newer.tier = 'gold' AND newer.deleted_at IS NULL
+  ))`;
+}
+
+/**
+ * The default view: the highest tier version of each processed asset, less
+ * the assets of system albums flagged excludeFromTimeline unless asked for
+ */
+export function timelineListing(collapseStacks: boolean, includeSystemAlbums = false): PhotoListing {
+  return {
+    where: photo => and(
+      sql`${photo.tier} IN ('silver', 'gold')`,
+      notSuperseded(photo),
+      includeSystemAlbums ? undefined : sql`NOT EXISTS (
+        SELECT 1 FROM ${collectionPhotos} album_photo
+        JOIN ${collections} album ON album.id = album_photo.collection_id
+        JOIN ${fileVersions} listed ON listed.id = album_photo.photo_id
+        WHERE listed.media_asset_id = ${photo.mediaAssetId} AND listed.deleted_at IS NULL
+          AND album.system_key IS NOT NULL AND album.exclude_from_timeline
+      )`
+    ),
+    order: [byTakenAt()],
+    collapseStacks,
+  };
+}
+
+export function tierListing(tier: FileVersion['tier'], showAllVersions: boolean, collapseStacks: boolean): PhotoListing {
+  return {
+    where: photo => and(sql`${photo.tier} = ${tier}`, showAllVersions ? undefined : notSuperseded(photo)),
+    order: [byCreatedAt()],
+    collapseStacks,
+  };
+}
+
+export function personListing(personId: string, collapseStacks: boolean): PhotoListing {
+  return {
+    where: photo => sql`EXISTS (SELECT 1 FROM ${faces} face WHERE face.photo_id = ${photo.id} AND face.person_id = ${personId})`,
+    order: [byTakenAt()],
+    collapseStacks,
+  };
+}
+
+/**
+ * An album's photos in its manual order, then the most recently added
+ */
+export function collectionListing(collectionId: string, collapseStacks: boolean): PhotoListing {
+  const albumPhoto = (column: string) => sql`(
+    SELECT ${sql.raw(column)} FROM ${collectionPhotos} album_photo
+    WHERE album_photo.photo_id = ${fileVersions.id} AND album_photo.collection_id = ${collectionId}
+    LIMIT 1
+  )`;
+  return {
+    where: photo => sql`EXISTS (
+      SELECT 1 FROM ${collectionPhotos} album_photo
+      WHERE album_photo.photo_id = ${photo.id} AND album_photo.collection_id = ${collectionId}
+    )`,
+    order: [
+      { value: sql`COALESCE(${albumPhoto('album_photo.sort_order')}, 2147483647)`, type: 'integer', direction: 'asc' },
+      { value: albumPhoto('album_photo.added_at'), type: 'timestamp', direction: 'desc' },
+    ],
+    collapseStacks,
+  };
+}
+
+/**
+ * Runs photo listings in the database, so a page reads only its own rows
+ * however large the library is
+ */
+class PhotoListingService {
+  /**
+   * One page of the listing. A cursor from the previous page takes
+   * precedence over offset; one from another sort order is ignored.
+   */
+  async page(listing: PhotoListing, query: PageQuery, defaultLimit = 100): Promise<Page<ListedPhoto>> {
+    const limit = query.limit ?? defaultLimit;
+    const cursor = query.cursor ? decodeCursor(query.cursor) : null;
+    const resume = cursor && cursor.keys.length === listing.order.length ? cursor : null;
+
+    // One row past the page says whether another page follows
+    const rows = await this.select(listing, limit + 1, resume ? 0 : query.offset ?? 0, resume);
+    const photos = rows.slice(0, limit);
+    const last = rows.length > limit ? photos[photos.length - 1] : null;
+
+    return {
+      photos: photos.map(row => row.photo),
+      totalCount: await this.count(listing),
+      nextCursor: last ? encodeCursor({ keys: last.keys, id: last.photo.id }) : null,
+    };
+  }
+
+  /**
+   * The listing's photos in order, all of them unless a limit is given
+   */
+  async rows(listing: PhotoListing, limit?: number): Promise<ListedPhoto[]> {
+    return (await this.select(listing, limit, 0, null)).map(row => row.photo);
+  }
+
+  async count(listing: PhotoListing): Promise<number> {
+    const [row] = await db
+      .select({ count: sql<number>`count(*)`.mapWith(Number) })
+      .from(fileVersions)
+      .where(and(...this.conditions(listing)));
+    return row?.count ?? 0;
+  }
+
+  async ids(listing: PhotoListing): Promise<string[]> {
+    const rows = await db
+      .select({ id: fileVersions.id })
+      .from(fileVersions)
+      .where(and(...this.conditions(listing)))
+      .orderBy(...this.orderBy(listing));
+    return rows.map(row => row.id);
+  }
+
+  /**
+   * Photos of the listing counted by a value of each, skipping photos
+   * without one. With perElement the value is an array and each of its
+   * elements is counted.
+   */
+  async countByValue(listing: PhotoListing, value: SQL, perElement = false): Promise<Record<string, number>> {
+    const where = and(...this.conditions(listing))!;
+    const result = perElement
+      ? await db.execute(sql`
+          SELECT element::text AS value, COUNT(*)::int AS count
+          FROM ${fileVersions} CROSS JOIN LATERAL unnest(${value}) AS element
+          WHERE ${where}
+          GROUP BY element
+        `)
+      : await db.execute(sql`
+          SELECT (${value})::text AS value, COUNT(*)::int AS count
+          FROM ${fileVersions}
+          WHERE ${where} AND (${value}) IS NOT NULL
+          GROUP BY 1
+        `);
+
+    const counts: Record<string, number> = {};
+    for (const row of result.rows as Array<{ value: string; count: number }>) {
+      counts[row.value] = Number(row.count);
+    }
+    return counts;
+  }
+
+  private async select(listing: PhotoListing, limit: number | undefined, offset: number, after: CursorState | null) {
+    const keys = listing.order.length > 0
+      ? sql`ARRAY[${sql.join(listing.order.map(key => sql`(${key.value})::text`), sql`, `)}]`
+      : sql`ARRAY[]::text[]`;
+    const conditions = this.conditions(listing);
+    if (after) conditions.push(this.after(listing.order, after));
+
+    let query = db
+      .select({
+        photo: fileVersions,
+        mediaAsset: mediaAssets,
+        stackId: photoStackMembers.stackId,
+        stackSize: sql<number | null>`(
+          SELECT COUNT(*)::int FROM ${photoStackMembers} sibling WHERE sibling.stack_id = ${photoStackMembers.stackId}
+        )`,
+        keys: sql<string[]>`${keys}`,
+      })
+      .from(fileVersions)
+      .innerJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id))
+      .leftJoin(photoStackMembers, eq(photoStackMembers.mediaAssetId, fileVersions.mediaAssetId))
+      .where(and(...conditions))
+      .orderBy(...this.orderBy(listing))
+      .$dynamic();
+    if (limit !== undefined) query = query.limit(limit);
+    if (offset > 0) query = query.offset(offset);
+
+    return (await query).map(row => {
+      const photo: ListedPhoto = { ...row.photo, mediaAsset: row.mediaAsset };
+      // Stacks with a single member are ignored
+      if (row.stackId && row.stackSize && row.stackSize > 1) {
+        photo.stackId = row.stackId;
+        photo.stackSize = row.stackSize;
+      }
+      return { photo, keys: row.keys };
+    });
+  }
+
+  private conditions(listing: PhotoListing): SQL[] {
+    const conditions: SQL[] = [isNull(fileVersions.deletedAt)];
+    const where = listing.where?.(fileVersions);
+    if (where) conditions.push(where);
+    if (listing.collapseStacks) conditions.push(this.coversOnly(listing.where));
+    return conditions;
+  }
+
+  /**
+   * Collapsed, a stack is listed once: as its primary photo when that is in
+   * the listing, otherwise as the member placed first in the stack that is
+   */
+  private coversOnly(where?: PhotoCondition): SQL {
+    const listed = where?.(otherPhoto);
+    return sql`NOT EXISTS (
+      SELECT 1 FROM ${photoStackMembers} member
+      JOIN ${photoStacks} stack ON stack.id = member.stack_id
+      JOIN ${photoStackMembers} other ON other.stack_id = member.stack_id AND other.media_asset_id <> member.media_asset_id
+      JOIN ${fileVersions} ${otherPhoto} ON ${otherPhoto.mediaAssetId} = other.media_asset_id AND ${otherPhoto.deletedAt} IS NULL
+      WHERE member.media_asset_id = ${fileVersions.mediaAssetId}
+        AND (other.media_asset_id = stack.primary_asset_id OR (
+          member.media_asset_id <> stack.primary_asset_id
+          AND (other.position, other.media_asset_id) < (member.position, member.media_asset_id)
+        ))
+        ${listed ? sql`AND ${listed}` : sql``}
+    )`;
+  }
+
+  /**
+   * Photos sorted after the cursor's: past it on the first key, or level on
+   * it and past it on the next, down to the id
+   */
+  private after(order: SortKey[], cursor: CursorState): SQL {
+    let condition = sql`${fileVersions.id} > ${cursor.id}`;
+    for (let i = order.length - 1; i >= 0; i--) {
+      const key = order[i];
+      const value = sql`CAST(${cursor.keys[i]} AS ${sql.raw(key.type)})`;
+      const past = key.direction === 'asc' ? sql`${key.value} > ${value}` : sql`${key.value} < ${value}`;
+      condition = sql`(${past} OR (${key.value} = ${value} AND ${condition}))`;
+    }
+    return condition;
+  }
+
+  private orderBy(listing: PhotoListing): SQL[] {
+    return [
+      ...listing.order.map(key => key.direction === 'asc' ? asc(key.value) : desc(key.value)),
+      asc(fileVersions.id),
+    ];
+  }
+}
+
+export const photoListingService = new PhotoListingService();
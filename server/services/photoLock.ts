@@ -0,0 +1,47 @@
+import { z } from "zod";
+import type { FileVersion } from "@shared/schema";
+
+export const setPhotoLockSchema = z.object({
+  locked: z.boolean(),
+});
+
+export type LockedAction = 'delete' | 'demote' | 'edit' | 'write_metadata';
+
+const ACTION_LABELS: Record<LockedAction, string> = {
+  delete: 'deleted',
+  demote: 'demoted',
+  edit: 'edited',
+  write_metadata: 'have metadata written to its file',
+};
+
+// Derived bookkeeping that may still be filled in on a locked photo
+const LOCK_EXEMPT_FIELDS = new Set<string>(['isLocked', 'perceptualHash', 'width', 'height', 'isPanorama', 'environment', 'promotionScore', 'promotionSignals']);
+
+const TIER_ORDER: Record<FileVersion['tier'], number> = { bronze: 0, silver: 1, gold: 2 };
+
+/**
+ * A locked photo was asked to change. Routes answer it with 423 Locked.
+ */
+export class PhotoLockedError extends Error {
+  constructor(public readonly photoId: string, public readonly action: LockedAction) {
+    super(`Photo ${photoId} is locked and cannot be ${ACTION_LABELS[action]}; unlock it first`);
+    this.name = 'PhotoLockedError';
+  }
+}
+
+export function assertUnlocked(photo: Pick<FileVersion, 'id' | 'isLocked'>, action: LockedAction): void {
+  if (photo.isLocked) {
+    throw new PhotoLockedError(photo.id, action);
+  }
+}
+
+/**
+ * Check a column update against the photo's lock. Updates that only touch
+ * derived fields or the lock itself always pass.
+ */
+export function assertUpdateAllowed(photo: FileVersion, updates: Partial<FileVersion>): void {
+  const changed = Object.keys(updates).filter(key => !LOCK_EXEMPT_FIELDS.has(key));
+  if (changed.length === 0) return;
+  const demotes = updates.tier !== undefined && TIER_ORDER[updates.tier] < TIER_ORDER[photo.tier];
+  assertUnlocked(photo, updates.deletedAt ? 'delete' : demotes ? 'demote' : 'edit');
+}
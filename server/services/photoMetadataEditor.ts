@@ -0,0 +1,257 @@
+import crypto from "crypto";
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { metadataEmbedding } from "./metadataEmbedding";
+import { assertUnlocked } from "./photoLock";
+import type { EmbeddedFieldUpdates } from "./metadataBackend";
+import { eventDetectionService } from "./eventDetection";
+import { reverseGeocodingService } from "./reverse-geocoding";
+import { photoEnvironmentService } from "./photoEnvironment";
+import { smartCollectionService } from "./smartCollectionService";
+import { propagationService, propagationScopeSchema } from "./propagation";
+import { getCaptureDate } from "../utils/photoDates";
+import { libraryPath } from "../utils/libraryPaths";
+import type { CombinedMetadata, FileVersion } from "@shared/schema";
+
+// Local wall-clock time, as EXIF stores it: 2024-05-01, 2024-05-01T14:30 or 2024-05-01T14:30:15
+const CAPTURE_DATE_PATTERN = /^(\d{4})-(\d{2})-(\d{2})(?:[T ](\d{2}):(\d{2})(?::(\d{2}))?)?$/;
+
+const writeToFile = z.boolean().optional();
+
+export const setCaptureDateSchema = z.object({
+  captureDate: z.string().refine(value => parseCaptureDate(value) !== null, {
+    message: 'Expected a date between 1900 and today as YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS]',
+  }),
+  writeToFile,
+});
+
+export const setGpsSchema = z.object({
+  latitude: z.number().min(-90).max(90).nullable(),
+  longitude: z.number().min(-180).max(180).nullable(),
+  writeToFile,
+}).refine(value => (value.latitude === null) === (value.longitude === null), {
+  message: 'Latitude and longitude must both be set or both be null',
+});
+
+export const setDescriptionSchema = z.object({
+  description: z.string().max(2000),
+  writeToFile,
+});
+
+export const setKeywordsSchema = z.object({
+  keywords: z.array(z.string().trim().min(1).max(100)).max(200),
+  writeToFile,
+  propagate: propagationScopeSchema.optional(),
+});
+
+export interface MetadataEditResult {
+  photo: FileVersion;
+  fileWritten: boolean;
+  fileSkippedReason?: string;
+  propagatedTo?: string[];
+}
+
+/**
+ * Parse a capture date from the editor; null when it is not a real date,
+ * is before 1900 or lies in the future
+ */
+export function parseCaptureDate(value: string): Date | null {
+  const match = value.trim().match(CAPTURE_DATE_PATTERN);
+  if (!match) return null;
+  const [year, month, day, hours = '0', minutes = '0', seconds = '0'] = match.slice(1);
+  const date = new Date(Number(year), Number(month) - 1, Number(day), Number(hours), Number(minutes), Number(seconds));
+  // Date rolls over invalid values (Feb 30 becomes Mar 2), so check they survived
+  if (date.getFullYear() !== Number(year) || date.getMonth() !== Number(month) - 1 || date.getDate() !== Number(day)
+    || date.getHours() !== Number(hours) || date.getMinutes() !== Number(minutes)) {
+    return null;
+  }
+  // A day of slack for cameras set to a time zone ahead of the server
+  if (date.getFullYear() <= 1900 || date.getTime() > Date.now() + 24 * 60 * 60 * 1000) return null;
+  return date;
+}
+
+function toExifDate(date: Date): string {
+  const pad = (value: number) => String(value).padStart(2, '0');
+  return `${date.getFullYear()}:${pad(date.getMonth() + 1)}:${pad(date.getDate())} ${pad(date.getHours())}:${pad(date.getMinutes())}:${pad(date.getSeconds())}`;
+}
+
+/**
+ * Typed edits of a single photo's capture date, position, description and
+ * keywords. Each edit updates the database, optionally rewrites the field in
+ * the file itself, and refreshes what was derived from the old value:
+ * detected events, the place name, daylight and smart collection membership.
+ */
+class PhotoMetadataEditor {
+  async setCaptureDate(photoId: string, input: z.infer<typeof setCaptureDateSchema>): Promise<MetadataEditResult | null> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return null;
+
+    const captureDate = toExifDate(parseCaptureDate(input.captureDate)!);
+    const file = await this.writeFile(photo, { captureDate }, input.writeToFile);
+    const metadata = this.getMetadata(photo);
+    let updated = await storage.updateFileVersion(photo.id, {
+      ...file.updates,
+      metadata: { ...metadata, exif: { ...(metadata.exif || {}), dateTimeOriginal: captureDate } } as any,
+    });
+
+    updated = await this.refreshEvents(updated);
+    updated = await photoEnvironmentService.refresh(updated);
+    await this.finish(updated, `Capture date set to ${captureDate}`);
+    return { photo: updated, fileWritten: file.written, fileSkippedReason: file.skippedReason };
+  }
+
+  async setGps(photoId: string, input: z.infer<typeof setGpsSchema>): Promise<MetadataEditResult | null> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return null;
+
+    const gps = input.latitude !== null && input.longitude !== null
+      ? { latitude: input.latitude, longitude: input.longitude }
+      : null;
+    const file = await this.writeFile(photo, { gps }, input.writeToFile);
+
+    const metadata = this.getMetadata(photo);
+    const exif = { ...(metadata.exif || {}) };
+    delete exif.gpsLatitude;
+    delete exif.gpsLongitude;
+    if (gps) {
+      exif.gpsLatitude = gps.latitude;
+      exif.gpsLongitude = gps.longitude;
+    }
+    // AI metadata keeps its own copy that embedding reads; it must not contradict the edit
+    const ai = metadata.ai ? { ...metadata.ai } : undefined;
+    if (ai) {
+      if (gps) {
+        ai.gpsCoordinates = gps;
+      } else {
+        delete ai.gpsCoordinates;
+      }
+    }
+
+    const updates: Partial<FileVersion> = { ...file.updates, metadata: { ...metadata, exif, ...(ai && { ai }) } as any };
+    if (gps) {
+      const place = await reverseGeocodingService.reverseGeocode(gps.latitude, gps.longitude);
+      if (place) updates.location = place.placeName;
+    }
+    let updated = await storage.updateFileVersion(photo.id, updates);
+
+    // Custom events tied to a place may now match, or stop matching
+    updated = await this.refreshEvents(updated);
+    updated = await photoEnvironmentService.refresh(updated);
+    await this.finish(updated, gps ? `Location set to ${gps.latitude}, ${gps.longitude}` : 'Location removed');
+    return { photo: updated, fileWritten: file.written, fileSkippedReason: file.skippedReason };
+  }
+
+  async setDescription(photoId: string, input: z.infer<typeof setDescriptionSchema>): Promise<MetadataEditResult | null> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return null;
+
+    const description = input.description.trim();
+    const file = await this.writeFile(photo, { description: description || null }, input.writeToFile);
+    const metadata = this.getMetadata(photo);
+    const exif = { ...(metadata.exif || {}) };
+    if (description) {
+      exif.description = description;
+    } else {
+      delete exif.description;
+    }
+    const updated = await storage.updateFileVersion(photo.id, { ...file.updates, metadata: { ...metadata, exif } as any });
+
+    await this.finish(updated, description ? 'Description updated' : 'Description removed');
+    return { photo: updated, fileWritten: file.written, fileSkippedReason: file.skippedReason };
+  }
+
+  async setKeywords(photoId: string, input: z.infer<typeof setKeywordsSchema>): Promise<MetadataEditResult | null> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return null;
+
+    // Keep the first spelling of keywords that differ only in case
+    const seen = new Set<string>();
+    const keywords = input.keywords.filter(keyword => {
+      const key = keyword.toLowerCase();
+      if (seen.has(key)) return false;
+      seen.add(key);
+      return true;
+    });
+
+    const file = await this.writeFile(photo, { keywords }, input.writeToFile);
+    const updated = await storage.updateFileVersion(photo.id, { ...file.updates, keywords });
+    const propagatedTo = await propagationService.propagateKeywords(updated, keywords, input.propagate);
+
+    await this.finish(updated, `Keywords set to ${keywords.join(', ') || '(none)'}`);
+    return { photo: updated, fileWritten: file.written, fileSkippedReason: file.skippedReason, propagatedTo };
+  }
+
+  /**
+   * Rewrite the field in the photo's file when asked to. The file's hash and
+   * size change with it, so those come back as column updates.
+   */
+  private async writeFile(
+    photo: FileVersion,
+    fields: EmbeddedFieldUpdates,
+    requested?: boolean
+  ): Promise<{ written: boolean; skippedReason?: string; updates: Partial<FileVersion> }> {
+    // Checked before the file is touched; the database update would refuse anyway
+    assertUnlocked(photo, requested ? 'write_metadata' : 'edit');
+    if (!requested) return { written: false, updates: {} };
+
+    const filePath = path.isAbsolute(photo.filePath) ? photo.filePath : libraryPath(photo.filePath);
+    const written = await metadataEmbedding.updateEmbeddedFields(filePath, photo.mimeType, fields);
+    if (!written) {
+      return { written: false, skippedReason: `Writing metadata into ${photo.mimeType} files is not supported`, updates: {} };
+    }
+
+    const buffer = await fs.readFile(filePath);
+    return {
+      written: true,
+      updates: {
+        fileHash: crypto.createHash('md5').update(buffer).digest('hex'),
+        fileSize: buffer.length,
+      },
+    };
+  }
+
+  /**
+   * Re-run event detection after the date or position changed. A confident
+   * match replaces the event; an event that was detected from the old date
+   * (holiday or birthday) is cleared when nothing matches any more.
+   */
+  private async refreshEvents(photo: FileVersion): Promise<FileVersion> {
+    const photoDate = getCaptureDate(photo);
+    if (!photoDate) return photo;
+
+    try {
+      const matches = await eventDetectionService.detectEventsForPhoto(photo, photoDate);
+      const best = matches.reduce<(typeof matches)[number] | null>(
+        (max, match) => (!max || match.confidence > max.confidence ? match : max),
+        null
+      );
+      if (best && best.confidence >= 80) {
+        if (best.eventType !== photo.eventType || best.eventName !== photo.eventName) {
+          return await storage.updateFileVersion(photo.id, { eventType: best.eventType, eventName: best.eventName });
+        }
+      } else if (photo.eventType === 'holiday' || photo.eventType === 'birthday') {
+        return await storage.updateFileVersion(photo.id, { eventType: null, eventName: null });
+      }
+    } catch (error) {
+      console.error('Event detection failed after metadata edit:', error);
+    }
+    return photo;
+  }
+
+  private async finish(photo: FileVersion, details: string): Promise<void> {
+    smartCollectionService.photosChanged([photo.id]);
+    await storage.createAssetHistory({
+      mediaAssetId: photo.mediaAssetId,
+      action: 'METADATA_UPDATED',
+      details,
+    });
+  }
+
+  private getMetadata(photo: FileVersion): CombinedMetadata {
+    return (photo.metadata as CombinedMetadata | null) || {};
+  }
+}
+
+export const photoMetadataEditor = new PhotoMetadataEditor();
@@ -0,0 +1,191 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import type { FileVersion, PhotoStack } from "@shared/schema";
+
+export const createStackSchema = z.object({
+  photoIds: z.array(z.string()).min(2),
+  primaryId: z.string(), // photo shown for the stack when collapsed; one of photoIds
+  kind: z.enum(['burst', 'raw_jpeg', 'edit', 'manual']).default('manual'),
+});
+
+export const setStackPrimarySchema = z.object({
+  primaryId: z.string(), // photo of a member asset
+});
+
+export type CreateStackInput = z.infer<typeof createStackSchema>;
+
+export interface StackDetails extends PhotoStack {
+  memberAssetIds: string[];
+  // Displayed (highest tier) version of each member, in stack order
+  photos: FileVersion[];
+}
+
+export class PhotoStackError extends Error {
+  constructor(message: string, readonly status: number) {
+    super(message);
+  }
+}
+
+const TIER_ORDER: Record<FileVersion['tier'], number> = { bronze: 0, silver: 1, gold: 2 };
+
+export interface StackAnnotation {
+  stackId?: string;
+  stackSize?: number; // members in the whole stack, not just in this listing
+}
+
+export interface StackEntry {
+  stackId: string;
+  primaryAssetId: string;
+  size: number;
+}
+
+/**
+ * Stack-aware listings: by default a stack is shown as a single cover photo
+ * so a 20-frame burst does not crowd out everything else; callers can ask
+ * for every member instead.
+ */
+class PhotoStackService {
+  /**
+   * Stack photos under a primary one. Stacks hold assets, so all versions of
+   * a photo go together; assets already in another stack are moved out of it,
+   * and a stack left with a single member is removed.
+   */
+  async createStack(input: CreateStackInput): Promise<StackDetails> {
+    const photos = await this.loadPhotos(input.photoIds);
+    const primary = photos.get(input.primaryId);
+    if (!primary) throw new PhotoStackError('The primary photo must be one of the stacked photos', 400);
+
+    const assetIds = Array.from(new Set(input.photoIds.map(id => photos.get(id)!.mediaAssetId)));
+    if (assetIds.length < 2) throw new PhotoStackError('A stack needs at least two different photos', 400);
+
+    const previous = new Set<string>();
+    for (const assetId of assetIds) {
+      const stack = await storage.getPhotoStackForAsset(assetId);
+      if (stack) previous.add(stack.id);
+    }
+    // The primary goes first, then the rest in the order given
+    const stack = await storage.createPhotoStack({ primaryAssetId: primary.mediaAssetId, kind: input.kind }, assetIds);
+    for (const stackId of Array.from(previous)) {
+      const remaining = await storage.getPhotoStack(stackId);
+      if (remaining && remaining.memberAssetIds.length < 2) await storage.deletePhotoStack(stackId);
+    }
+    return (await this.getStack(stack.id))!;
+  }
+
+  /**
+   * Dissolve a stack; its photos are listed on their own again
+   */
+  async unstack(stackId: string): Promise<void> {
+    if (!await storage.deletePhotoStack(stackId)) throw new PhotoStackError('Stack not found', 404);
+  }
+
+  async setStackPrimary(stackId: string, primaryId: string): Promise<StackDetails> {
+    const stack = await storage.getPhotoStack(stackId);
+    if (!stack) throw new PhotoStackError('Stack not found', 404);
+    const photo = await storage.getFileVersion(primaryId);
+    if (!photo || photo.deletedAt || !stack.memberAssetIds.includes(photo.mediaAssetId)) {
+      throw new PhotoStackError('The primary photo must be in the stack', 400);
+    }
+    await storage.updatePhotoStack(stack.id, { primaryAssetId: photo.mediaAssetId });
+    return (await this.getStack(stack.id))!;
+  }
+
+  async getStack(stackId: string): Promise<StackDetails | undefined> {
+    const stack = await storage.getPhotoStack(stackId);
+    if (!stack) return undefined;
+    const photos: FileVersion[] = [];
+    for (const assetId of stack.memberAssetIds) {
+      const [displayed] = (await storage.getFileVersionsByAsset(assetId))
+        .sort((a, b) => TIER_ORDER[b.tier] - TIER_ORDER[a.tier]);
+      if (displayed) photos.push(displayed);
+    }
+    return { ...stack, photos };
+  }
+
+  async getStackForPhoto(photoId: string): Promise<StackDetails | undefined> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return undefined;
+    const stack = await storage.getPhotoStackForAsset(photo.mediaAssetId);
+    return stack ? this.getStack(stack.id) : undefined;
+  }
+
+  /**
+   * Whether the request asked for expanded stacks (query string or JSON body flag)
+   */
+  shouldExpand(value: unknown): boolean {
+    return value === true || value === 'true';
+  }
+
+  /**
+   * Stack of every stacked asset; stacks with a single member are ignored
+   */
+  async loadIndex(): Promise<Map<string, StackEntry>> {
+    const index = new Map<string, StackEntry>();
+    for (const stack of await storage.getPhotoStacks()) {
+      if (stack.memberAssetIds.length < 2) continue;
+      const entry = {
+        stackId: stack.id,
+        // The primary may have been moved into another stack since
+        primaryAssetId: stack.memberAssetIds.includes(stack.primaryAssetId) ? stack.primaryAssetId : stack.memberAssetIds[0],
+        size: stack.memberAssetIds.length,
+      };
+      stack.memberAssetIds.forEach(assetId => index.set(assetId, entry));
+    }
+    return index;
+  }
+
+  /**
+   * Collapse or expand the stacks in an already sorted listing. Collapsed,
+   * each stack keeps one entry where its first member appeared: the primary
+   * photo when it is in the listing, otherwise that first member.
+   */
+  async applyStackMode<T extends { mediaAssetId: string }>(
+    photos: T[],
+    expand: boolean,
+    index?: Map<string, StackEntry>
+  ): Promise<Array<T & StackAnnotation>> {
+    const stacks = index ?? await this.loadIndex();
+    if (stacks.size === 0) return photos as Array<T & StackAnnotation>;
+
+    const annotate = (photo: T): T & StackAnnotation => {
+      const entry = stacks.get(photo.mediaAssetId);
+      return entry ? { ...photo, stackId: entry.stackId, stackSize: entry.size } : photo as T & StackAnnotation;
+    };
+    if (expand) return photos.map(annotate);
+
+    const covers = new Map<string, T>();
+    for (const photo of photos) {
+      const entry = stacks.get(photo.mediaAssetId);
+      if (!entry) continue;
+      const current = covers.get(entry.stackId);
+      if (!current || (current.mediaAssetId !== entry.primaryAssetId && photo.mediaAssetId === entry.primaryAssetId)) {
+        covers.set(entry.stackId, photo);
+      }
+    }
+
+    const emitted = new Set<string>();
+    const collapsed: Array<T & StackAnnotation> = [];
+    for (const photo of photos) {
+      const entry = stacks.get(photo.mediaAssetId);
+      if (!entry) {
+        collapsed.push(photo as T & StackAnnotation);
+      } else if (!emitted.has(entry.stackId)) {
+        emitted.add(entry.stackId);
+        collapsed.push(annotate(covers.get(entry.stackId)!));
+      }
+    }
+    return collapsed;
+  }
+
+  private async loadPhotos(photoIds: string[]): Promise<Map<string, FileVersion>> {
+    const photos = new Map<string, FileVersion>();
+    for (const photoId of Array.from(new Set(photoIds))) {
+      const photo = await storage.getFileVersion(photoId);
+      if (!photo || photo.deletedAt) throw new PhotoStackError(`Photo ${photoId} not found`, 404);
+      photos.set(photoId, photo);
+    }
+    return photos;
+  }
+}
+
+export const photoStackService = new PhotoStackService();
@@ -0,0 +1,65 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import type { FileVersion, MediaAsset } from "@shared/schema";
+
+export const textSearchQuerySchema = z.object({
+  q: z.string().min(1),
+  limit: z.coerce.number().int().min(1).max(200).default(50),
+  offset: z.coerce.number().int().min(0).default(0),
+});
+
+export interface TextSearchResult {
+  photos: Array<FileVersion & { mediaAsset: MediaAsset; rank: number }>;
+  totalCount: number;
+}
+
+export class TextSearchError extends Error {}
+
+/**
+ * Turn a typed query into a tsquery. Every word has to match, "quoted words"
+ * have to appear together and in order, and word* matches any word starting
+ * with it. Punctuation only separates words, so no input can break the
+ * tsquery syntax.
+ */
+export function toTsQuery(input: string): string | null {
+  const terms: string[] = [];
+  const pattern = /"([^"]*)"?|(\S+)/g;
+  let match: RegExpExecArray | null;
+  while ((match = pattern.exec(input)) !== null) {
+    const phrase = match[1] !== undefined;
+    const words = lexemes(phrase ? match[1] : match[2]);
+    if (words.length === 0) continue;
+    if (!phrase && match[2].endsWith('*')) {
+      words[words.length - 1] += ':*';
+    }
+    // "st-tropez" or "img_2041" is indexed as neighbouring words
+    terms.push(words.length === 1 ? words[0] : `(${words.join(' <-> ')})`);
+  }
+  return terms.length > 0 ? terms.join(' & ') : null;
+}
+
+function lexemes(text: string): string[] {
+  return text.toLowerCase().split(/[^\p{L}\p{N}]+/u).filter(Boolean);
+}
+
+/**
+ * Full-text search over file names, tags, keywords, people, albums, EXIF
+ * captions and AI descriptions. Documents live in photo_search_index and
+ * are kept current by database triggers, so every write path updates them.
+ */
+class PhotoTextSearchService {
+  async search(query: string, limit: number, offset: number): Promise<TextSearchResult> {
+    const tsquery = toTsQuery(query);
+    if (!tsquery) {
+      throw new TextSearchError('Enter at least one word to search for');
+    }
+    const { photos, total } = await storage.searchPhotoText(tsquery, limit, offset);
+    return { photos, totalCount: total };
+  }
+
+  async rebuild(): Promise<number> {
+    return storage.rebuildPhotoSearchIndex();
+  }
+}
+
+export const photoTextSearchService = new PhotoTextSearchService();
@@ -0,0 +1,276 @@
+import fs from "fs/promises";
+import path from "path";
+import { z } from "zod";
+import { storage } from "../storage";
+import { fileManager } from "./fileManager";
+import { localImportService } from "./localImport";
+import { counterConsistencyService } from "./counterConsistency";
+import type { CancellationToken } from "./operations";
+import { boxOverlap, SAME_FACE_OVERLAP, type FaceBox } from "../utils/faceBoxes";
+import type { FileVersion, Person } from "@shared/schema";
+
+export const picasaImportSchema = z.object({
+  folderPath: z.string().min(1), // searched recursively for .picasa.ini files
+  contactsPath: z.string().min(1).optional(), // Picasa's contacts.xml, for names only kept there
+});
+
+export type PicasaImportOptions = Omit<z.infer<typeof picasaImportSchema>, 'folderPath'>;
+
+export interface PicasaImportResult {
+  folders: number; // with a .picasa.ini
+  images: number; // listed in them
+  imported: number;
+  duplicates: number; // already in the library; their Picasa curation is still applied
+  missing: string[]; // files an ini lists that are not on disk
+  failed: Array<{ path: string; reason: string }>;
+  starred: number;
+  faces: number;
+  peopleCreated: number;
+}
+
+export class PicasaImportError extends Error {}
+
+// Picasa 3 names the file .picasa.ini; Picasa 2 used Picasa.ini
+const INI_NAMES = new Set(['.picasa.ini', 'picasa.ini']);
+// Backups of files Picasa edited, with an ini of their own that describes the edits
+const ORIGINALS_FOLDER = '.picasaoriginals';
+// Contact id Picasa gives faces it found but nobody named
+const UNKNOWN_CONTACT = 'ffffffffffffffff';
+
+type IniSections = Map<string, Map<string, string>>;
+
+interface PicasaFace {
+  box: [number, number, number, number]; // left, top, right, bottom as fractions of the photo
+  contactId: string;
+}
+
+/**
+ * Import the curation Picasa kept in the .picasa.ini of each folder: starred
+ * photos become picks, and named face rectangles become faces of people
+ * (created by name when missing). Files are brought in through the normal
+ * import, so photos already in the library only get their curation applied,
+ * and running it again adds nothing twice.
+ */
+class PicasaImportService {
+  async importFolder(
+    folderPath: string,
+    options: PicasaImportOptions = {},
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<PicasaImportResult> {
+    try {
+      if (!(await fs.stat(folderPath)).isDirectory()) throw new Error();
+    } catch {
+      throw new PicasaImportError(`${folderPath} is not a folder`);
+    }
+    const globalContacts = options.contactsPath ? await this.readContactsXml(options.contactsPath) : new Map<string, string>();
+    const iniPaths = await this.findIniFiles(folderPath);
+
+    const entries: Array<{ filePath: string; section: Map<string, string>; contacts: Map<string, string> }> = [];
+    for (const iniPath of iniPaths) {
+      const sections = this.parseIni(await fs.readFile(iniPath, 'utf8'));
+      const contacts = new Map(globalContacts);
+      this.readIniContacts(sections).forEach((name, id) => contacts.set(id, name));
+
+      const folder = path.dirname(iniPath);
+      const files = new Map((await fs.readdir(folder)).map(name => [name.toLowerCase(), name]));
+      sections.forEach((section, name) => {
+        // [Picasa], [Contacts2] and [.album:token] describe the folder, not a file
+        if (name.startsWith('.') || name.toLowerCase() === 'picasa' || name.toLowerCase().startsWith('contacts')) return;
+        if (section.get('star') !== 'yes' && !section.has('faces')) return;
+        // Picasa wrote the ini on Windows, where names are not case sensitive
+        const fileName = files.get(name.toLowerCase()) ?? name;
+        entries.push({ filePath: path.join(folder, fileName), section, contacts });
+      });
+    }
+
+    const result: PicasaImportResult = {
+      folders: iniPaths.length, images: entries.length, imported: 0, duplicates: 0, missing: [], failed: [],
+      starred: 0, faces: 0, peopleCreated: 0,
+    };
+    const people = new Map((await storage.getPeople()).map(person => [person.name.toLowerCase(), person]));
+
+    for (let index = 0; index < entries.length; index++) {
+      token?.throwIfCancelled();
+      onProgress?.(index, entries.length);
+      const { filePath, section, contacts } = entries[index];
+
+      try {
+        await fs.access(filePath);
+      } catch {
+        result.missing.push(filePath);
+        continue;
+      }
+
+      try {
+        const imported = await localImportService.importFile(filePath, {
+          sourceDevice: 'Picasa',
+          details: `Imported from Picasa folder ${path.dirname(filePath)}`,
+          data: { picasaIni: true },
+        });
+        if (imported.decision === 'imported') result.imported++;
+        else result.duplicates++;
+        const photo = await storage.getFileVersion(imported.photoId);
+        if (!photo) continue;
+
+        // A pick or reject set in Pictallion wins
+        if (section.get('star') === 'yes' && !photo.pickFlag) {
+          await storage.updateFileVersion(photo.id, { pickFlag: 'pick' });
+          result.starred++;
+        }
+
+        const faces = this.parseFaces(section.get('faces'));
+        if (faces.length > 0) {
+          result.faces += await this.importFaces(photo, faces, contacts, people, result);
+        }
+      } catch (error) {
+        console.error(`Failed to import ${filePath} from Picasa:`, error);
+        result.failed.push({ path: filePath, reason: error instanceof Error ? error.message : 'Import failed' });
+      }
+    }
+    onProgress?.(entries.length, entries.length);
+
+    if (result.faces > 0) counterConsistencyService.scheduleCheck();
+    return result;
+  }
+
+  private async importFaces(
+    photo: FileVersion,
+    picasaFaces: PicasaFace[],
+    contacts: Map<string, string>,
+    people: Map<string, Person>,
+    result: PicasaImportResult
+  ): Promise<number> {
+    const dimensions = photo.width && photo.height
+      ? { width: photo.width, height: photo.height }
+      : await fileManager.getImageDimensions(photo.filePath);
+    if (!dimensions) return 0;
+
+    const existing = await storage.getFacesByPhoto(photo.id);
+    let created = 0;
+    for (const face of picasaFaces) {
+      const [left, top, right, bottom] = face.box;
+      const boundingBox: FaceBox = [
+        Math.round(left * dimensions.width),
+        Math.round(top * dimensions.height),
+        Math.round((right - left) * dimensions.width),
+        Math.round((bottom - top) * dimensions.height),
+      ];
+      if (boundingBox[2] <= 0 || boundingBox[3] <= 0) continue;
+
+      let personId: string | null = null;
+      const name = face.contactId !== UNKNOWN_CONTACT ? contacts.get(face.contactId) : undefined;
+      if (name) {
+        let person = people.get(name.toLowerCase());
+        if (!person) {
+          person = await storage.createPerson({ name });
+          people.set(name.toLowerCase(), person);
+          result.peopleCreated++;
+        }
+        personId = person.id;
+      }
+
+      // Already imported on an earlier run, or found by Pictallion's own detection; a name from Picasa fills in an unnamed face
+      const match = existing.find(other => boxOverlap(other.boundingBox as FaceBox, boundingBox) >= SAME_FACE_OVERLAP);
+      if (match) {
+        if (personId && !match.personId && !match.ignored) {
+          await storage.linkFaceToPerson(match.id, personId);
+          match.personId = personId;
+        }
+        continue;
+      }
+
+      existing.push(await storage.createFace({ photoId: photo.id, personId, boundingBox, confidence: 100 }));
+      created++;
+    }
+    return created;
+  }
+
+  private async findIniFiles(folder: string): Promise<string[]> {
+    const found: string[] = [];
+    for (const entry of await fs.readdir(folder, { withFileTypes: true })) {
+      const entryPath = path.join(folder, entry.name);
+      if (entry.isDirectory()) {
+        if (entry.name.toLowerCase() !== ORIGINALS_FOLDER) found.push(...(await this.findIniFiles(entryPath)));
+      } else if (INI_NAMES.has(entry.name.toLowerCase())) {
+        found.push(entryPath);
+      }
+    }
+    return found;
+  }
+
+  private parseIni(text: string): IniSections {
+    const sections: IniSections = new Map();
+    let current: Map<string, string> | null = null;
+    text.replace(/^\uFEFF/, '').split(/\r?\n/).forEach(line => {
+      const header = line.match(/^\s*\[(.+)\]\s*$/);
+      if (header) {
+        current = sections.get(header[1]) ?? new Map();
+        sections.set(header[1], current);
+        return;
+      }
+      const separator = line.indexOf('=');
+      if (current && separator > 0) {
+        current.set(line.slice(0, separator).trim().toLowerCase(), line.slice(separator + 1).trim());
+      }
+    });
+    return sections;
+  }
+
+  // [Contacts2] lines read "8a1b2c3d4e5f6789=Anna Smith;email;"
+  private readIniContacts(sections: IniSections): Map<string, string> {
+    const contacts = new Map<string, string>();
+    sections.forEach((section, name) => {
+      if (!name.toLowerCase().startsWith('contacts')) return;
+      section.forEach((value, id) => {
+        const contactName = value.split(';')[0].trim();
+        if (contactName) contacts.set(id, contactName);
+      });
+    });
+    return contacts;
+  }
+
+  // contacts.xml holds <contact id="…" name="…" …/> elements
+  private async readContactsXml(contactsPath: string): Promise<Map<string, string>> {
+    let xml: string;
+    try {
+      xml = await fs.readFile(contactsPath, 'utf8');
+    } catch {
+      throw new PicasaImportError(`Cannot read Picasa contacts at ${contactsPath}`);
+    }
+    const contacts = new Map<string, string>();
+    for (const element of xml.match(/<contact\b[^>]*>/g) ?? []) {
+      const id = element.match(/\bid="([^"]+)"/)?.[1];
+      const name = element.match(/\bname="([^"]+)"/)?.[1];
+      if (id && name) contacts.set(id.toLowerCase(), this.decodeEntities(name));
+    }
+    return contacts;
+  }
+
+  private decodeEntities(value: string): string {
+    return value
+      .replace(/&#(\d+);/g, (_, code) => String.fromCodePoint(Number(code)))
+      .replace(/&quot;/g, '"').replace(/&apos;/g, "'").replace(/&lt;/g, '<').replace(/&gt;/g, '>').replace(/&amp;/g, '&');
+  }
+
+  /**
+   * "rect64(3f845bcb59418507),8a1b2c3d4e5f6789;rect64(…),…": each rect64 is
+   * four 16-bit fractions of the photo (left, top, right, bottom) in hex,
+   * with leading zeros dropped
+   */
+  private parseFaces(value: string | undefined): PicasaFace[] {
+    if (!value) return [];
+    const faces: PicasaFace[] = [];
+    for (const part of value.split(';')) {
+      const match = part.trim().match(/^rect64\(([0-9a-f]{1,16})\),([0-9a-f]+)$/i);
+      if (!match) continue;
+      const hex = match[1].padStart(16, '0');
+      const box = [0, 4, 8, 12].map(offset => parseInt(hex.slice(offset, offset + 4), 16) / 65535) as PicasaFace['box'];
+      if (box[2] <= box[0] || box[3] <= box[1]) continue;
+      faces.push({ box, contactId: match[2].toLowerCase() });
+    }
+    return faces;
+  }
+}
+
+export const picasaImportService = new PicasaImportService();
@@ -0,0 +1,86 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import type { FileVersion, Person } from "@shared/schema";
+
+// Pathways out of the library that per-person privacy flags can close
+export type PrivacyBoundary = 'export' | 'share' | 'ai';
+
+export const personPrivacySchema = z.object({
+  excludeFromExports: z.boolean().optional(),
+  excludeFromShares: z.boolean().optional(),
+  excludeFromAi: z.boolean().optional(),
+});
+
+const BOUNDARY_FLAGS: Record<PrivacyBoundary, keyof Pick<Person, 'excludeFromExports' | 'excludeFromShares' | 'excludeFromAi'>> = {
+  export: 'excludeFromExports',
+  share: 'excludeFromShares',
+  ai: 'excludeFromAi',
+};
+
+export class PrivacyRestrictedError extends Error {
+  constructor(boundary: PrivacyBoundary, public photoIds: string[]) {
+    super(`${photoIds.length} photo(s) show people who are excluded from ${boundary === 'ai' ? 'AI uploads' : `${boundary}s`}`);
+    this.name = 'PrivacyRestrictedError';
+  }
+}
+
+export interface RestrictedPhotos {
+  ids: Set<string>;
+  paths: Set<string>;
+}
+
+/**
+ * Enforces per-person privacy flags where photos leave the library. Every
+ * export, share and AI upload path asks here first, so a photo of someone
+ * marked private cannot slip out through a pathway that forgot to check the
+ * people in it. A photo is restricted when any version of its asset has a
+ * face assigned to a flagged person.
+ */
+class PrivacyService {
+  async getRestrictedPeople(boundary: PrivacyBoundary): Promise<Person[]> {
+    const flag = BOUNDARY_FLAGS[boundary];
+    // Soft-deleted people keep their flags until purged
+    return (await storage.getPeople(true)).filter(person => person[flag]);
+  }
+
+  async getRestrictedPhotos(boundary: PrivacyBoundary): Promise<RestrictedPhotos> {
+    const personIds = (await this.getRestrictedPeople(boundary)).map(person => person.id);
+    const photos = await storage.getPhotosWithPeople(personIds);
+    return {
+      ids: new Set(photos.map(photo => photo.id)),
+      paths: new Set(photos.map(photo => photo.filePath)),
+    };
+  }
+
+  /**
+   * Split photos into those allowed through the boundary and those held back
+   */
+  async partition<T extends Pick<FileVersion, 'id'>>(photos: T[], boundary: PrivacyBoundary): Promise<{ allowed: T[]; blocked: T[] }> {
+    const restricted = await this.getRestrictedPhotos(boundary);
+    const allowed: T[] = [];
+    const blocked: T[] = [];
+    for (const photo of photos) {
+      (restricted.ids.has(photo.id) ? blocked : allowed).push(photo);
+    }
+    return { allowed, blocked };
+  }
+
+  async isPhotoRestricted(photoId: string, boundary: PrivacyBoundary): Promise<boolean> {
+    return (await this.getRestrictedPhotos(boundary)).ids.has(photoId);
+  }
+
+  // AI analysis only knows the library path of the image it is sending
+  async isPathRestricted(filePath: string, boundary: PrivacyBoundary): Promise<boolean> {
+    return (await this.getRestrictedPhotos(boundary)).paths.has(filePath);
+  }
+
+  async assertAllowed(photoIds: string[], boundary: PrivacyBoundary): Promise<void> {
+    const restricted = await this.getRestrictedPhotos(boundary);
+    const blocked = photoIds.filter(id => restricted.ids.has(id));
+    if (blocked.length > 0) {
+      throw new PrivacyRestrictedError(boundary, blocked);
+    }
+  }
+}
+
+export const privacyService = new PrivacyService();
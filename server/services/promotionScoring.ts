@@ -0,0 +1,197 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { displayImage } from "../utils/colorProfile";
+import { loadLuma, measureExposure, measureSharpness } from "../utils/imageQuality";
+import { aiProviderRegistry, type AiProvider } from "./aiProviders";
+import { privacyService } from "./privacy";
+import { smartCollectionService } from "./smartCollectionService";
+import { volumeStatusService } from "./volumeStatus";
+import type { CancellationToken } from "./operations";
+import type { FileVersion, PromotionSignals } from "@shared/schema";
+
+export const scorePhotosSchema = z.object({
+  photoIds: z.array(z.string()).min(1).optional(), // every Bronze photo when left out
+  aesthetic: z.boolean().default(true), // also ask the configured AI provider for an aesthetic rating
+  force: z.boolean().default(false), // rescore photos that already have a score
+});
+
+export const promotionCandidatesQuerySchema = z.object({
+  tier: z.enum(['bronze', 'silver']).default('bronze'),
+  limit: z.coerce.number().int().min(1).max(500).default(100),
+});
+
+export type ScorePhotosInput = z.infer<typeof scorePhotosSchema>;
+
+export interface PromotionScore {
+  photoId: string;
+  tier: FileVersion['tier'];
+  score: number; // 0-100
+  signals: PromotionSignals;
+  suggestedTier: 'silver' | 'gold' | null;
+}
+
+export interface ScorePhotosResult {
+  scored: PromotionScore[]; // best first
+  skipped: Array<{ photoId: string; reason: string }>;
+}
+
+// Scores at which a photo is suggested for a tier
+export const SILVER_SCORE = 60;
+export const GOLD_SCORE = 80;
+
+const WEIGHTS = { sharpness: 0.35, exposure: 0.2, faces: 0.15, aesthetic: 0.3 };
+
+const AESTHETIC_PROMPT = `Rate the aesthetic and technical quality of this photo as a keeper for a family album: composition, focus, lighting, expressions and moment. Return ONLY a JSON object like {"aesthetic": 7}, where aesthetic is a number from 0 (delete) to 10 (frame it).`;
+
+/**
+ * Ranks photos as candidates for the next tier. Sharpness (variance of the
+ * Laplacian) and exposure (mean brightness and clipping) are measured
+ * locally; faces come from face detection; an aesthetic rating is asked of
+ * the configured AI provider when there is one. The score is stored on the
+ * photo so smart collections and searches can use it.
+ */
+class PromotionScoringService {
+  async scorePhotos(
+    input: ScorePhotosInput,
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<ScorePhotosResult> {
+    const photos = input.photoIds
+      ? (await Promise.all(Array.from(new Set(input.photoIds)).map(id => storage.getFileVersion(id))))
+        .filter((photo): photo is FileVersion => !!photo && !photo.deletedAt)
+      : (await storage.getAllFileVersions()).filter(photo => photo.tier === 'bronze');
+    const provider = input.aesthetic ? await this.findProvider() : null;
+    const result: ScorePhotosResult = { scored: [], skipped: [] };
+    const rescored: string[] = [];
+
+    for (let index = 0; index < photos.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, photos.length);
+      const photo = photos[index];
+      if (photo.promotionSignals && !input.force) {
+        result.scored.push(this.toScore(photo));
+        continue;
+      }
+      if (!photo.mimeType.startsWith('image/')) {
+        result.skipped.push({ photoId: photo.id, reason: 'Not an image' });
+        continue;
+      }
+
+      try {
+        const original = await volumeStatusService.locateOriginal(photo);
+        if (!original.available) {
+          result.skipped.push({ photoId: photo.id, reason: `Original is offline${original.volumeLabel ? ` on ${original.volumeLabel}` : ''}` });
+          continue;
+        }
+        const signals = await this.measure(photo, original.path, provider);
+        const updated = await storage.updateFileVersion(photo.id, {
+          promotionScore: this.combine(signals),
+          promotionSignals: signals,
+        });
+        result.scored.push(this.toScore(updated));
+        rescored.push(photo.id);
+      } catch (error) {
+        console.error(`Promotion scoring failed for photo ${photo.id}:`, error);
+        result.skipped.push({ photoId: photo.id, reason: error instanceof Error ? error.message : 'Scoring failed' });
+      }
+    }
+
+    smartCollectionService.photosChanged(rescored);
+    result.scored.sort((a, b) => b.score - a.score);
+    return result;
+  }
+
+  /**
+   * Scored photos of a tier that would be suggested for a higher one, best first
+   */
+  async getCandidates(tier: 'bronze' | 'silver', limit: number): Promise<PromotionScore[]> {
+    return (await storage.getAllFileVersions())
+      .filter(photo => photo.tier === tier && photo.promotionScore != null)
+      .map(photo => this.toScore(photo))
+      .filter(score => score.suggestedTier !== null)
+      .sort((a, b) => b.score - a.score)
+      .slice(0, limit);
+  }
+
+  suggestTier(photo: Pick<FileVersion, 'tier' | 'pickFlag'>, score: number): 'silver' | 'gold' | null {
+    if (photo.pickFlag === 'reject' || photo.tier === 'gold') return null;
+    if (score >= GOLD_SCORE) return 'gold';
+    if (score >= SILVER_SCORE && photo.tier === 'bronze') return 'silver';
+    return null;
+  }
+
+  private toScore(photo: FileVersion): PromotionScore {
+    const score = photo.promotionScore ?? 0;
+    return {
+      photoId: photo.id,
+      tier: photo.tier,
+      score,
+      signals: photo.promotionSignals!,
+      suggestedTier: this.suggestTier(photo, score),
+    };
+  }
+
+  // Weighted mean of the signals present, 0-100
+  private combine(signals: PromotionSignals): number {
+    const parts: Array<[number, number]> = [
+      [signals.sharpness, WEIGHTS.sharpness],
+      [signals.exposure, WEIGHTS.exposure],
+      [signals.faces, WEIGHTS.faces],
+    ];
+    if (signals.aesthetic !== undefined) parts.push([signals.aesthetic, WEIGHTS.aesthetic]);
+    const weight = parts.reduce((total, [, partWeight]) => total + partWeight, 0);
+    const score = parts.reduce((total, [value, partWeight]) => total + value * partWeight, 0) / weight;
+    return Math.round(score * 1000) / 10;
+  }
+
+  private async measure(photo: FileVersion, filePath: string, provider: AiProvider | null): Promise<PromotionSignals> {
+    const image = await loadLuma(filePath);
+
+    const faces = (await storage.getFacesByPhoto(photo.id)).filter(face => !face.ignored);
+    const named = faces.filter(face => face.personId).length;
+
+    const signals: PromotionSignals = {
+      sharpness: measureSharpness(image),
+      exposure: measureExposure(image),
+      // Photos of people are what get kept; landscapes are not marked down much for having none
+      faces: faces.length === 0 ? 0.4 : Math.min(1, 0.6 + 0.1 * faces.length + 0.1 * named),
+      faceCount: faces.length,
+      scoredAt: new Date().toISOString(),
+    };
+
+    if (provider && !(await privacyService.isPhotoRestricted(photo.id, 'ai'))) {
+      const aesthetic = await this.rateAesthetic(provider, filePath);
+      if (aesthetic !== null) signals.aesthetic = aesthetic;
+    }
+    return signals;
+  }
+
+  private async rateAesthetic(provider: AiProvider, filePath: string): Promise<number | null> {
+    try {
+      const image = await displayImage(filePath)
+        .resize(1024, 1024, { fit: 'inside', withoutEnlargement: true })
+        .jpeg({ quality: 85 })
+        .toBuffer();
+      const reply = JSON.parse(await provider.describeImage({
+        image,
+        systemPrompt: AESTHETIC_PROMPT,
+        userPrompt: 'Rate this photo.',
+        maxTokens: 50,
+      }));
+      const rating = Number(reply.aesthetic);
+      return Number.isFinite(rating) ? Math.min(1, Math.max(0, rating / 10)) : null;
+    } catch (error) {
+      console.error('AI aesthetic rating failed:', error);
+      return null;
+    }
+  }
+
+  private async findProvider(): Promise<AiProvider | null> {
+    for (const provider of aiProviderRegistry.getProviders()) {
+      if (await provider.isAvailable()) return provider;
+    }
+    return null;
+  }
+}
+
+export const promotionScoringService = new PromotionScoringService();
@@ -0,0 +1,256 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { smartCollectionService } from "./smartCollectionService";
+import type { Face, FileVersion } from "@shared/schema";
+
+export const PROPAGATION_SETTING = 'propagation_rules';
+
+export type PropagationAction = 'rating' | 'tags' | 'people';
+
+// Where a change made to one photo is copied to
+export const propagationScopeSchema = z.object({
+  stack: z.boolean().optional(), // from a stack cover to the other members
+  versions: z.boolean().optional(), // to the other tier versions of the same asset
+});
+
+export const propagationRulesSchema = z.object({
+  rating: propagationScopeSchema.optional(),
+  tags: propagationScopeSchema.optional(),
+  people: propagationScopeSchema.optional(),
+});
+
+export type PropagationScope = z.infer<typeof propagationScopeSchema>;
+export type PropagationRules = Record<PropagationAction, Required<PropagationScope>>;
+
+export type TagChange = { action: 'add' | 'remove' | 'set'; tags: string[] };
+
+const DEFAULT_RULES: PropagationRules = {
+  rating: { stack: false, versions: false },
+  tags: { stack: false, versions: false },
+  people: { stack: false, versions: false },
+};
+
+const TIER_ORDER: Record<string, number> = { gold: 3, silver: 2, bronze: 1 };
+const MIN_FACE_OVERLAP = 0.5; // intersection over union of the face boxes
+
+/**
+ * Apply a tag change to a tag list
+ */
+export function applyTagChange(current: string[], change: TagChange): string[] {
+  if (change.action === 'add') return Array.from(new Set([...current, ...change.tags]));
+  if (change.action === 'remove') return current.filter(tag => !change.tags.includes(tag));
+  return [...change.tags];
+}
+
+/**
+ * Rating, tag and person changes that follow a photo to its stack members
+ * and sibling versions. Which of those a change reaches is configured per
+ * action in settings and can be overridden per call; every entry point goes
+ * through here so they all behave the same.
+ */
+class PropagationService {
+  async getRules(): Promise<PropagationRules> {
+    const setting = await storage.getSettingByKey(PROPAGATION_SETTING);
+    if (!setting?.value) return DEFAULT_RULES;
+    try {
+      const parsed = propagationRulesSchema.safeParse(JSON.parse(setting.value));
+      return parsed.success ? this.mergeRules(DEFAULT_RULES, parsed.data) : DEFAULT_RULES;
+    } catch {
+      return DEFAULT_RULES;
+    }
+  }
+
+  async updateRules(updates: z.infer<typeof propagationRulesSchema>): Promise<PropagationRules> {
+    const rules = this.mergeRules(await this.getRules(), updates);
+    const value = JSON.stringify(rules);
+    if (await storage.getSettingByKey(PROPAGATION_SETTING)) {
+      await storage.updateSetting(PROPAGATION_SETTING, value);
+    } else {
+      await storage.createSetting({
+        key: PROPAGATION_SETTING,
+        value,
+        category: 'propagation',
+        description: 'Whether rating, tag and person changes reach stack members and other versions',
+      });
+    }
+    return rules;
+  }
+
+  async setRating(photoId: string, rating: number, override?: PropagationScope): Promise<string[]> {
+    const photo = await this.requirePhoto(photoId);
+    await storage.updateFileVersion(photo.id, { rating });
+    smartCollectionService.photosChanged([photo.id]);
+    return this.propagateRating(photo, rating, override);
+  }
+
+  /**
+   * Copy a rating to the photo's propagation targets. Returns the photos updated.
+   */
+  async propagateRating(photo: FileVersion, rating: number, override?: PropagationScope, exclude: Set<string> = new Set()): Promise<string[]> {
+    const targets = await this.findTargets(photo, 'rating', override, exclude);
+    for (const target of targets) {
+      await storage.updateFileVersion(target.id, { rating });
+    }
+    smartCollectionService.photosChanged(targets.map(target => target.id));
+    return targets.map(target => target.id);
+  }
+
+  async changeTags(photoId: string, change: TagChange, override?: PropagationScope): Promise<{ tags: string[]; propagatedTo: string[] }> {
+    const photo = await this.requirePhoto(photoId);
+    const tags = applyTagChange(this.getTags(photo), change);
+    await storage.updateFileVersion(photo.id, { metadata: this.withTags(photo, tags) });
+    smartCollectionService.photosChanged([photo.id]);
+    const propagatedTo = await this.propagateTags(photo, [change], override);
+    return { tags, propagatedTo };
+  }
+
+  /**
+   * Replay tag changes on each target's own tags, so adding a tag to the
+   * cover does not wipe tags that only a member has
+   */
+  async propagateTags(photo: FileVersion, changes: TagChange[], override?: PropagationScope, exclude: Set<string> = new Set()): Promise<string[]> {
+    const targets = await this.findTargets(photo, 'tags', override, exclude);
+    for (const target of targets) {
+      const tags = changes.reduce((current, change) => applyTagChange(current, change), this.getTags(target));
+      await storage.updateFileVersion(target.id, { metadata: this.withTags(target, tags) });
+    }
+    smartCollectionService.photosChanged(targets.map(target => target.id));
+    return targets.map(target => target.id);
+  }
+
+  /**
+   * Keywords are set as a whole by the metadata editor
+   */
+  async propagateKeywords(photo: FileVersion, keywords: string[], override?: PropagationScope): Promise<string[]> {
+    const targets = await this.findTargets(photo, 'tags', override);
+    for (const target of targets) {
+      await storage.updateFileVersion(target.id, { keywords });
+    }
+    smartCollectionService.photosChanged(targets.map(target => target.id));
+    return targets.map(target => target.id);
+  }
+
+  /**
+   * Assign a face, then the same face in each target photo: the unassigned
+   * face whose box overlaps it most
+   */
+  async assignFace(faceId: string, personId: string, override?: PropagationScope): Promise<string[]> {
+    const face = await storage.getFace(faceId);
+    if (!face) {
+      throw new Error(`Face ${faceId} not found`);
+    }
+    await storage.assignFaceToPerson(face.id, personId);
+
+    const photo = await storage.getFileVersion(face.photoId);
+    if (!photo) return [];
+    const targets = await this.findTargets(photo, 'people', override);
+
+    const assigned: string[] = [];
+    const changedPhotos = [photo.id];
+    for (const target of targets) {
+      const candidates = (await storage.getFacesByPhoto(target.id)).filter(candidate => !candidate.personId && !candidate.ignored);
+      let best: { face: Face; overlap: number } | null = null;
+      for (const candidate of candidates) {
+        const overlap = this.faceOverlap(face, photo, candidate, target);
+        if (overlap >= MIN_FACE_OVERLAP && (!best || overlap > best.overlap)) {
+          best = { face: candidate, overlap };
+        }
+      }
+      if (best) {
+        await storage.assignFaceToPerson(best.face.id, personId);
+        assigned.push(best.face.id);
+        changedPhotos.push(target.id);
+      }
+    }
+    smartCollectionService.photosChanged(changedPhotos);
+    return assigned;
+  }
+
+  /**
+   * Photos a change to this photo should also reach. Stack propagation only
+   * starts from the stack's cover; members get their displayed (highest tier)
+   * version, or every version when version propagation is on too.
+   */
+  async findTargets(
+    photo: FileVersion,
+    action: PropagationAction,
+    override?: PropagationScope,
+    exclude: Set<string> = new Set()
+  ): Promise<FileVersion[]> {
+    const rules = await this.getRules();
+    const scope = { ...rules[action], ...override };
+    const targets = new Map<string, FileVersion>();
+
+    if (scope.versions) {
+      for (const version of await storage.getFileVersionsByAsset(photo.mediaAssetId)) {
+        targets.set(version.id, version);
+      }
+    }
+
+    if (scope.stack) {
+      const stack = await storage.getPhotoStackForAsset(photo.mediaAssetId);
+      if (stack && stack.primaryAssetId === photo.mediaAssetId) {
+        for (const assetId of stack.memberAssetIds) {
+          if (assetId === photo.mediaAssetId) continue;
+          const versions = await storage.getFileVersionsByAsset(assetId);
+          const chosen = scope.versions
+            ? versions
+            : versions.sort((a, b) => (TIER_ORDER[b.tier] ?? 0) - (TIER_ORDER[a.tier] ?? 0)).slice(0, 1);
+          chosen.forEach(version => targets.set(version.id, version));
+        }
+      }
+    }
+
+    targets.delete(photo.id);
+    // Locked photos never receive propagated changes
+    return Array.from(targets.values()).filter(target => !exclude.has(target.id) && !target.isLocked);
+  }
+
+  private mergeRules(base: PropagationRules, updates: z.infer<typeof propagationRulesSchema>): PropagationRules {
+    return {
+      rating: { ...base.rating, ...updates.rating },
+      tags: { ...base.tags, ...updates.tags },
+      people: { ...base.people, ...updates.people },
+    };
+  }
+
+  private async requirePhoto(photoId: string): Promise<FileVersion> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) {
+      throw new Error(`Photo ${photoId} not found`);
+    }
+    return photo;
+  }
+
+  getTags(photo: FileVersion): string[] {
+    return (photo.metadata as any)?.ai?.aiTags ?? [];
+  }
+
+  private withTags(photo: FileVersion, tags: string[]): any {
+    const metadata = (photo.metadata as any) || {};
+    return { ...metadata, ai: { ...(metadata.ai || {}), aiTags: tags } };
+  }
+
+  /**
+   * Intersection over union of two face boxes, compared relative to each
+   * photo's size when known so versions at different resolutions still match
+   */
+  private faceOverlap(face: Face, photo: FileVersion, candidate: Face, candidatePhoto: FileVersion): number {
+    const normalize = (box: unknown, source: FileVersion) => {
+      const [x, y, width, height] = box as [number, number, number, number];
+      const scaleX = photo.width && candidatePhoto.width && source.width ? source.width : 1;
+      const scaleY = photo.height && candidatePhoto.height && source.height ? source.height : 1;
+      return { x: x / scaleX, y: y / scaleY, width: width / scaleX, height: height / scaleY };
+    };
+    const a = normalize(face.boundingBox, photo);
+    const b = normalize(candidate.boundingBox, candidatePhoto);
+
+    const overlapWidth = Math.max(0, Math.min(a.x + a.width, b.x + b.width) - Math.max(a.x, b.x));
+    const overlapHeight = Math.max(0, Math.min(a.y + a.height, b.y + b.height) - Math.max(a.y, b.y));
+    const intersection = overlapWidth * overlapHeight;
+    const union = a.width * a.height + b.width * b.height - intersection;
+    return union > 0 ? intersection / union : 0;
+  }
+}
+
+export const propagationService = new PropagationService();
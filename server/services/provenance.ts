@@ -0,0 +1,78 @@
+import { storage } from "../storage";
+import { aiService } from "./ai";
+import type { AssetHistory, FileVersion } from "@shared/schema";
+
+type PhotoRef = Pick<FileVersion, 'id' | 'mediaAssetId'>;
+
+/**
+ * Provenance log of what touched each photo: where it was imported from,
+ * which models detected its faces or wrote its caption, where copies went.
+ * Entries live in asset history alongside the tier moves, so a photo's
+ * history covers every version of its asset. Recording never fails the work
+ * it describes.
+ */
+class ProvenanceService {
+  async record(photo: PhotoRef, action: string, details: string, data?: Record<string, unknown>): Promise<void> {
+    try {
+      await storage.createAssetHistory({
+        mediaAssetId: photo.mediaAssetId,
+        fileVersionId: photo.id,
+        action,
+        details,
+        data: data ?? null,
+      });
+    } catch (error) {
+      console.error(`Failed to record ${action} for photo ${photo.id}:`, error);
+    }
+  }
+
+  async recordById(photoId: string, action: string, details: string, data?: Record<string, unknown>): Promise<void> {
+    const photo = await storage.getFileVersion(photoId);
+    if (photo) {
+      await this.record(photo, action, details, data);
+    }
+  }
+
+  async recordFaceDetection(
+    photo: PhotoRef,
+    faceCount: number,
+    detection: { method?: string | null; minConfidence?: number } | undefined,
+    embeddingModel: string | null
+  ): Promise<void> {
+    await this.record(photo, 'FACES_DETECTED', `${faceCount} face(s) detected`, {
+      method: detection?.method ?? null,
+      minConfidence: detection?.minConfidence ?? null,
+      embeddingModel,
+      faceCount,
+    });
+  }
+
+  async recordAiAnalysis(photo: PhotoRef, details: string, action: string = 'AI_PROCESSED'): Promise<void> {
+    await this.record(photo, action, details, this.aiModel());
+  }
+
+  async recordExport(photos: PhotoRef[], destination: string, kind: string): Promise<void> {
+    for (const photo of photos) {
+      await this.record(photo, 'EXPORTED', `Exported (${kind}) to ${destination}`, { kind, destination });
+    }
+  }
+
+  /**
+   * Everything recorded for the photo's asset, newest first, or null if the
+   * photo does not exist
+   */
+  async getPhotoHistory(photoId: string): Promise<AssetHistory[] | null> {
+    const photo = await storage.getFileVersion(photoId);
+    if (!photo) return null;
+    return storage.getAssetHistory(photo.mediaAssetId);
+  }
+
+  // The provider and vision model configured when a caption was written
+  private aiModel(): { provider: string; model: string } {
+    const config = aiService.getConfig();
+    const model = config.provider === 'ollama' ? config.ollama.visionModel : config.openai.model;
+    return { provider: config.provider, model };
+  }
+}
+
+export const provenanceService = new ProvenanceService();
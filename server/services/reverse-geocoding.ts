@@ -11,6 +11,8 @@ interface GeocodingResult {
     road?: string;
     neighbourhood?: string;
     suburb?: string;
+    village?: string;
+    town?: string;
     city?: string;
     county?: string;
     state?: string;
@@ -0,0 +1,56 @@
+import { advancedSearch, type SearchFilters, type SearchResult, type SortOptions } from "./advancedSearch";
+
+export interface SearchPreset {
+  id: string;
+  name: string;
+  description: string;
+  filters: SearchFilters;
+  sort: SortOptions;
+}
+
+/**
+ * Built-in technical searches over exposure settings. Each result carries
+ * the filters it ran, so the search page can open them for tweaking.
+ */
+export const TECHNICAL_SEARCH_PRESETS: SearchPreset[] = [
+  {
+    id: 'high-iso',
+    name: 'High ISO noise candidates',
+    description: 'Shot at ISO 3200 or above, likely to benefit from noise reduction',
+    filters: { iso: { min: 3200 } },
+    sort: { field: 'takenAt', direction: 'desc' },
+  },
+  {
+    id: 'long-exposures',
+    name: 'Long exposures',
+    description: 'Shutter open for one second or longer',
+    filters: { exposureTime: { min: 1 } },
+    sort: { field: 'takenAt', direction: 'desc' },
+  },
+  {
+    id: 'wide-aperture-portraits',
+    name: 'Wide aperture portraits',
+    description: 'Portrait focal lengths (50-135mm) at f/2 or wider',
+    filters: { aperture: { max: 2 }, focalLength: { min: 50, max: 135 } },
+    sort: { field: 'rating', direction: 'desc' },
+  },
+];
+
+class SearchPresetService {
+  list(): SearchPreset[] {
+    return TECHNICAL_SEARCH_PRESETS;
+  }
+
+  get(id: string): SearchPreset | undefined {
+    return TECHNICAL_SEARCH_PRESETS.find(preset => preset.id === id);
+  }
+
+  async run(id: string, limit: number, offset: number, expandStacks: boolean): Promise<(SearchResult & { preset: SearchPreset }) | null> {
+    const preset = this.get(id);
+    if (!preset) return null;
+    const results = await advancedSearch.searchPhotos(preset.filters, preset.sort, limit, offset, expandStacks);
+    return { ...results, preset };
+  }
+}
+
+export const searchPresetService = new SearchPresetService();
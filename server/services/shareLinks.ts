@@ -0,0 +1,189 @@
+import crypto from "crypto";
+import { promisify } from "util";
+import { z } from "zod";
+import { storage } from "../storage";
+import { privacyService } from "./privacy";
+import { provenanceService } from "./provenance";
+import { volumeStatusService } from "./volumeStatus";
+import { displayImage } from "../utils/colorProfile";
+import type { FileVersion, Share } from "@shared/schema";
+
+const scrypt = promisify(crypto.scrypt) as (password: string, salt: string, keylen: number) => Promise<Buffer>;
+
+// Long edge of the copies shown when originals are not allowed
+const RESIZED_SIZE = 2048;
+
+export const createShareSchema = z.object({
+  title: z.string().optional(),
+  photoIds: z.array(z.string()).min(1).optional(),
+  collectionId: z.string().optional(),
+  allowOriginals: z.boolean().optional(),
+  password: z.string().min(1).optional(),
+  expiresAt: z.coerce.date().optional(),
+  maxViews: z.number().int().min(1).optional(),
+}).refine(input => !!input.photoIds !== !!input.collectionId, {
+  message: 'Share either photoIds or a collectionId',
+});
+
+export type CreateShareInput = z.infer<typeof createShareSchema>;
+
+export type ShareStatus = 'active' | 'expired' | 'revoked' | 'used_up';
+
+// What the library owner sees of a share; the password itself is never kept
+export type ShareSummary = Omit<Share, 'passwordHash'> & { hasPassword: boolean; status: ShareStatus; path: string };
+
+export class ShareAccessError extends Error {
+  constructor(message: string, readonly status: 401 | 403 | 404 | 410) {
+    super(message);
+  }
+}
+
+/**
+ * Share links served by the local server under /s/<token>. Each link has
+ * its own permissions: originals or resized copies only, an optional
+ * password, an expiry date and a view limit. Photos of people excluded from
+ * shares are left out, and every photo a link serves is logged in that
+ * photo's activity.
+ */
+class ShareLinkService {
+  async createShare(input: CreateShareInput): Promise<ShareSummary> {
+    if (input.collectionId) {
+      const collection = await storage.getCollection(input.collectionId);
+      if (!collection || collection.deletedAt) {
+        throw new ShareAccessError('Collection not found', 404);
+      }
+    }
+
+    const share = await storage.createShare({
+      token: crypto.randomBytes(24).toString('base64url'),
+      title: input.title ?? null,
+      collectionId: input.collectionId ?? null,
+      photoIds: input.photoIds ? Array.from(new Set(input.photoIds)) : null,
+      allowOriginals: input.allowOriginals ?? false,
+      passwordHash: input.password ? await this.hashPassword(input.password) : null,
+      expiresAt: input.expiresAt ?? null,
+      maxViews: input.maxViews ?? null,
+    });
+
+    for (const photo of await this.getSharedPhotos(share)) {
+      await provenanceService.record(photo, 'SHARED', `Shared by link${share.title ? ` "${share.title}"` : ''}`, {
+        shareId: share.id,
+        allowOriginals: share.allowOriginals,
+      });
+    }
+    return this.summarize(share);
+  }
+
+  async listShares(): Promise<ShareSummary[]> {
+    return (await storage.getShares()).map(share => this.summarize(share));
+  }
+
+  async revokeShare(id: string): Promise<ShareSummary | null> {
+    const share = await storage.getShare(id);
+    if (!share) return null;
+    if (share.revokedAt) return this.summarize(share);
+    const revoked = await storage.updateShare(id, { revokedAt: new Date() });
+    return revoked ? this.summarize(revoked) : null;
+  }
+
+  /**
+   * Open a link: check it is still usable and the password, and optionally
+   * count the visit against its view limit
+   */
+  async open(token: string, password: string | undefined, countView: boolean): Promise<Share> {
+    const share = await storage.getShareByToken(token);
+    if (!share || share.revokedAt) {
+      throw new ShareAccessError('Share not found', 404);
+    }
+    const status = this.status(share);
+    if (status === 'expired') throw new ShareAccessError('This share has expired', 410);
+    if (share.passwordHash && !(password && await this.verifyPassword(password, share.passwordHash))) {
+      throw new ShareAccessError(password ? 'Wrong password' : 'This share needs a password', 401);
+    }
+    // Views already used up only stop new visits; a page that is open can still load its photos
+    if (!countView) return share;
+    const counted = await storage.recordShareView(share.id);
+    if (!counted) throw new ShareAccessError('This share has reached its view limit', 410);
+    return counted;
+  }
+
+  /**
+   * Photos the link currently shows, skipping trashed photos and photos of
+   * people excluded from shares
+   */
+  async getSharedPhotos(share: Share): Promise<FileVersion[]> {
+    const photoIds = share.collectionId
+      ? await storage.getCollectionPhotoIds(share.collectionId)
+      : share.photoIds ?? [];
+    const restricted = await privacyService.getRestrictedPhotos('share');
+
+    const photos: FileVersion[] = [];
+    for (const photoId of photoIds) {
+      if (restricted.ids.has(photoId)) continue;
+      const photo = await storage.getFileVersion(photoId);
+      if (photo && !photo.deletedAt) photos.push(photo);
+    }
+    return photos;
+  }
+
+  /**
+   * One photo of the share, resized unless the original is asked for and the link allows it
+   */
+  async getPhotoFile(share: Share, photoId: string, original: boolean): Promise<{ path?: string; image?: Buffer; filename: string }> {
+    if (original && !share.allowOriginals) {
+      throw new ShareAccessError('This share does not allow downloading originals', 403);
+    }
+    const photo = (await this.getSharedPhotos(share)).find(candidate => candidate.id === photoId);
+    if (!photo) {
+      throw new ShareAccessError('Photo not found', 404);
+    }
+    const location = await volumeStatusService.locateOriginal(photo);
+    if (!location.available) {
+      throw new ShareAccessError(`Photo is offline on ${location.volumeLabel}`, 404);
+    }
+
+    const asset = await storage.getMediaAsset(photo.mediaAssetId);
+    const filename = asset?.originalFilename ?? photo.filePath.split('/').pop() ?? photo.id;
+    await provenanceService.record(photo, 'SHARE_ACCESSED', `${original ? 'Original downloaded' : 'Viewed'} through share link`, {
+      shareId: share.id,
+      original,
+    });
+
+    if (original) {
+      return { path: location.path, filename };
+    }
+    const image = await displayImage(location.path)
+      .resize(RESIZED_SIZE, RESIZED_SIZE, { fit: 'inside', withoutEnlargement: true })
+      .jpeg({ quality: 85 })
+      .toBuffer();
+    return { image, filename: filename.replace(/\.[^.]+$/, '') + '.jpg' };
+  }
+
+  status(share: Share): ShareStatus {
+    if (share.revokedAt) return 'revoked';
+    if (share.expiresAt && share.expiresAt.getTime() <= Date.now()) return 'expired';
+    if (share.maxViews !== null && share.viewCount >= share.maxViews) return 'used_up';
+    return 'active';
+  }
+
+  private summarize(share: Share): ShareSummary {
+    const { passwordHash, ...rest } = share;
+    return { ...rest, hasPassword: !!passwordHash, status: this.status(share), path: `/s/${share.token}` };
+  }
+
+  private async hashPassword(password: string): Promise<string> {
+    const salt = crypto.randomBytes(16).toString('hex');
+    const hash = await scrypt(password, salt, 64);
+    return `${salt}:${hash.toString('hex')}`;
+  }
+
+  private async verifyPassword(password: string, stored: string): Promise<boolean> {
+    const [salt, hash] = stored.split(':');
+    if (!salt || !hash) return false;
+    const expected = Buffer.from(hash, 'hex');
+    const actual = await scrypt(password, salt, expected.length);
+    return crypto.timingSafeEqual(actual, expected);
+  }
+}
+
+export const shareLinkService = new ShareLinkService();
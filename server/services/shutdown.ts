@@ -0,0 +1,168 @@
+import fs from "fs/promises";
+import path from "path";
+import type { Server } from "http";
+import type { Response } from "express";
+import { pool } from "../db";
+import { operationRegistry, type OperationInfo } from "./operations";
+import { frameSyncService } from "./frameSync";
+import { aiBatchScheduler } from "./aiBatchScheduler";
+import { jobQueue } from "./jobQueue";
+import { folderWatcherService } from "./folderWatcher";
+import { importSessionService } from "./importSessions";
+import { backgroundService } from "./background";
+import { libraryPath } from "../utils/libraryPaths";
+
+const DRAIN_TIMEOUT_MS = 8000;
+const FORCE_EXIT_MS = 12000;
+
+export interface ShutdownCheckpoint {
+  reason: string;
+  shutdownAt: string;
+  interruptedOperations: OperationInfo[];
+}
+
+export type ShutdownPhase = 'started' | 'completed';
+
+/**
+ * Orderly shutdown: stop schedulers, ask running operations to stop at their
+ * next item boundary, record what was left unfinished so it can be resumed on
+ * the next launch, and drain the database pool before the process exits.
+ */
+class ShutdownService {
+  private get checkpointPath(): string {
+    return libraryPath('shutdown-checkpoint.json');
+  }
+  private eventClients = new Set<Response>();
+  private shutdownPromise: Promise<ShutdownCheckpoint> | null = null;
+
+  get isShuttingDown(): boolean {
+    return this.shutdownPromise !== null;
+  }
+
+  /**
+   * Keep a server-sent events stream open so the frontend hears about shutdown
+   */
+  addEventClient(res: Response): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    res.write(': connected\n\n');
+    this.eventClients.add(res);
+    res.on('close', () => this.eventClients.delete(res));
+  }
+
+  shutdown(reason: string): Promise<ShutdownCheckpoint> {
+    if (!this.shutdownPromise) {
+      this.shutdownPromise = this.runShutdown(reason);
+    }
+    return this.shutdownPromise;
+  }
+
+  /**
+   * Run the shutdown sequence on SIGTERM/SIGINT, then close the HTTP server and exit.
+   * If the sequence was already started over HTTP, this waits for it to finish.
+   */
+  installSignalHandlers(server: Server): void {
+    let exiting = false;
+    const handle = (signal: NodeJS.Signals) => {
+      if (exiting) return;
+      exiting = true;
+      console.log(`Received ${signal}, shutting down...`);
+
+      const forceExit = setTimeout(() => {
+        console.error('Shutdown timed out, exiting');
+        process.exit(1);
+      }, FORCE_EXIT_MS);
+      forceExit.unref();
+
+      this.shutdown(signal)
+        .catch(error => console.error('Shutdown sequence failed:', error))
+        .finally(() => {
+          server.close(() => process.exit(0));
+          // Idle keep-alive connections would otherwise hold close() open
+          server.closeAllConnections?.();
+        });
+    };
+
+    process.on('SIGTERM', handle);
+    process.on('SIGINT', handle);
+  }
+
+  async getInterruptedOperations(): Promise<ShutdownCheckpoint | null> {
+    try {
+      return JSON.parse(await fs.readFile(this.checkpointPath, 'utf8')) as ShutdownCheckpoint;
+    } catch {
+      return null;
+    }
+  }
+
+  async clearInterruptedOperations(): Promise<void> {
+    await fs.rm(this.checkpointPath, { force: true });
+  }
+
+  private async runShutdown(reason: string): Promise<ShutdownCheckpoint> {
+    this.broadcast('started', { reason, graceMs: DRAIN_TIMEOUT_MS });
+
+    // Pause queues so nothing new starts
+    frameSyncService.stopScheduler();
+    aiBatchScheduler.stopScheduler();
+    jobQueue.stop();
+    folderWatcherService.stop();
+    importSessionService.stop();
+    backgroundService.stopMaintenance();
+
+    // Keep hold of the running operations; they leave the registry as they drain
+    // but their info objects still carry the last resume state they recorded
+    const running = operationRegistry.list();
+    operationRegistry.cancelAll();
+    await this.waitForOperations(DRAIN_TIMEOUT_MS);
+
+    const interruptedOperations = running.map(info => ({ ...info }));
+    const checkpoint: ShutdownCheckpoint = {
+      reason,
+      shutdownAt: new Date().toISOString(),
+      interruptedOperations,
+    };
+
+    if (interruptedOperations.length > 0) {
+      try {
+        await fs.mkdir(path.dirname(this.checkpointPath), { recursive: true });
+        const partialPath = `${this.checkpointPath}.partial`;
+        await fs.writeFile(partialPath, JSON.stringify(checkpoint, null, 2));
+        await fs.rename(partialPath, this.checkpointPath);
+      } catch (error) {
+        console.error('Failed to write shutdown checkpoint:', error);
+      }
+    }
+
+    this.broadcast('completed', { reason, interruptedOperations: interruptedOperations.length });
+    this.eventClients.forEach(client => client.end());
+    this.eventClients.clear();
+
+    // Let in-flight queries finish and close connections cleanly
+    try {
+      await pool.end();
+    } catch (error) {
+      console.error('Failed to close database pool:', error);
+    }
+
+    console.log(`Shutdown complete (${interruptedOperations.length} operation(s) interrupted)`);
+    return checkpoint;
+  }
+
+  private async waitForOperations(timeoutMs: number): Promise<void> {
+    const deadline = Date.now() + timeoutMs;
+    while (operationRegistry.list().length > 0 && Date.now() < deadline) {
+      await new Promise(resolve => setTimeout(resolve, 100));
+    }
+  }
+
+  private broadcast(phase: ShutdownPhase, details: Record<string, unknown>): void {
+    const payload = `event: shutdown\ndata: ${JSON.stringify({ phase, ...details })}\n\n`;
+    this.eventClients.forEach(client => client.write(payload));
+  }
+}
+
+export const shutdownService = new ShutdownService();
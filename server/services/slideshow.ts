@@ -0,0 +1,112 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { advancedSearch, type SearchFilters } from "./advancedSearch";
+import { systemAlbumService } from "./systemAlbums";
+import { photoStackService } from "./photoStacks";
+import type { FileVersion, MediaAsset } from "@shared/schema";
+
+interface SlideshowSession {
+  recentIds: string[];
+  repeatWindow: number;
+  lastUsed: number;
+}
+
+const DEFAULT_REPEAT_WINDOW = 50;
+const MAX_REPEAT_WINDOW = 1000;
+const SESSION_TTL_MS = 6 * 60 * 60 * 1000;
+const TIER_WEIGHTS: Record<string, number> = { gold: 3, silver: 1.5, bronze: 1 };
+const TIER_RANK: Record<string, number> = { gold: 3, silver: 2, bronze: 1 };
+
+export const slideshowNextSchema = z.object({
+  filters: z.record(z.any()).default({}),
+  repeatWindow: z.number().int().min(0).max(MAX_REPEAT_WINDOW).optional(), // photos shown before one may repeat
+  expandStacks: z.union([z.boolean(), z.string()]).optional(),
+});
+
+class SlideshowService {
+  private sessions = new Map<string, SlideshowSession>();
+
+  /**
+   * Pick the next photo for a slideshow session: a weighted shuffle that prefers
+   * higher tiers and ratings and avoids repeating recently shown photos.
+   * Each asset is shown as its highest tier version, and only stack covers
+   * are shown unless expandStacks is set; excluded photos are never shown.
+   */
+  async nextPhoto(
+    sessionId: string,
+    filters: SearchFilters = {},
+    repeatWindow: number = DEFAULT_REPEAT_WINDOW,
+    expandStacks: boolean = false,
+    excluded: Set<string> = new Set()
+  ): Promise<(FileVersion & { mediaAsset: MediaAsset }) | null> {
+    this.pruneSessions();
+
+    const session = this.sessions.get(sessionId) ?? { recentIds: [], repeatWindow, lastUsed: Date.now() };
+    session.repeatWindow = repeatWindow;
+    session.lastUsed = Date.now();
+    this.sessions.set(sessionId, session);
+
+    const matchingIds = new Set(await advancedSearch.findMatchingPhotoIds(filters));
+    const excludedAssetIds = await systemAlbumService.getTimelineExcludedAssetIds();
+    const candidates = await photoStackService.applyStackMode(
+      this.highestTierPerAsset((await storage.getAllFileVersionsWithAssets()).filter(photo =>
+        matchingIds.has(photo.id) &&
+        !excluded.has(photo.id) &&
+        photo.mimeType.startsWith('image/') &&
+        !excludedAssetIds.has(photo.mediaAssetId)
+      )),
+      expandStacks
+    );
+    if (candidates.length === 0) return null;
+
+    // Never exclude the whole pool when it is smaller than the window
+    const window = Math.min(session.repeatWindow, candidates.length - 1);
+    const recent = new Set(session.recentIds.slice(-window));
+    const pool = window > 0 ? candidates.filter(photo => !recent.has(photo.id)) : candidates;
+
+    const next = this.weightedPick(pool);
+    session.recentIds.push(next.id);
+    if (session.recentIds.length > session.repeatWindow) {
+      session.recentIds = session.recentIds.slice(-session.repeatWindow);
+    }
+
+    return next;
+  }
+
+  endSession(sessionId: string): boolean {
+    return this.sessions.delete(sessionId);
+  }
+
+  // Tier copies of one asset would otherwise each get a turn
+  private highestTierPerAsset<T extends FileVersion>(photos: T[]): T[] {
+    const byAsset = new Map<string, T>();
+    for (const photo of photos) {
+      const current = byAsset.get(photo.mediaAssetId);
+      if (!current || (TIER_RANK[photo.tier] ?? 0) > (TIER_RANK[current.tier] ?? 0)) {
+        byAsset.set(photo.mediaAssetId, photo);
+      }
+    }
+    return Array.from(byAsset.values());
+  }
+
+  private weightedPick<T extends FileVersion>(photos: T[]): T {
+    const weights = photos.map(photo => (TIER_WEIGHTS[photo.tier] ?? 1) * (1 + (photo.rating || 0) / 2));
+    const total = weights.reduce((sum, weight) => sum + weight, 0);
+
+    let target = Math.random() * total;
+    for (let i = 0; i < photos.length; i++) {
+      target -= weights[i];
+      if (target <= 0) return photos[i];
+    }
+    return photos[photos.length - 1];
+  }
+
+  private pruneSessions(): void {
+    const cutoff = Date.now() - SESSION_TTL_MS;
+    this.sessions.forEach((session, id) => {
+      if (session.lastUsed < cutoff) this.sessions.delete(id);
+    });
+  }
+}
+
+export const slideshowService = new SlideshowService();
@@ -1,277 +1,268 @@
+import { z } from "zod";
+import { storage } from "../storage";
+import { propagationService } from "./propagation";
+import { getPhotoDate, resolveRelativeRange, relativeDateRangeSchema, type DateField } from "../utils/dateRanges";
+import type { Collection, FileVersion } from "@shared/schema";
 
-import { storage } from '../storage.js';
-import type { Photo } from '../../shared/types.js';
+const stringOrList = z.union([z.string().min(1), z.array(z.string().min(1)).min(1)]);
+const dateValue = z.coerce.date();
+const dateRuleField = z.enum(["takenAt", "importedAt", "modifiedAt"]);
 
-interface SmartCollection {
-  id: string;
-  name: string;
-  description: string;
-  type: 'auto' | 'manual';
-  rules: CollectionRule[];
-  isActive: boolean;
-  icon: string;
-  color: string;
+const DATE_RULE_FIELDS: Record<z.infer<typeof dateRuleField>, DateField> = {
+  takenAt: 'capturedAt',
+  importedAt: 'importedAt',
+  modifiedAt: 'modifiedAt',
+};
+
+// One condition on a photo. greater_than and less_than include the value itself ("rating >= 4").
+export const smartCollectionRuleSchema = z.union([
+  z.object({ field: z.literal("tag"), operator: z.enum(["equals", "contains", "in"]), value: stringOrList }),
+  z.object({ field: z.literal("keywords"), operator: z.enum(["contains", "in"]), value: stringOrList }),
+  z.object({ field: z.literal("person"), operator: z.enum(["equals", "in"]), value: stringOrList }), // person ids
+  z.object({ field: z.literal("rating"), operator: z.enum(["equals", "greater_than", "less_than"]), value: z.number().int().min(0).max(5) }),
+  z.object({ field: z.literal("rating"), operator: z.literal("between"), value: z.tuple([z.number().int().min(0).max(5), z.number().int().min(0).max(5)]) }),
+  // Date rules: takenAt is when the photo was taken, importedAt when it was added, modifiedAt the file's EXIF ModifyDate.
+  // "within" takes a relative range ("last_30_days", "this_year") worked out each time the album is evaluated.
+  z.object({ field: dateRuleField, operator: z.enum(["greater_than", "less_than"]), value: dateValue }),
+  z.object({ field: dateRuleField, operator: z.literal("between"), value: z.tuple([dateValue, dateValue]) }),
+  z.object({ field: dateRuleField, operator: z.literal("within"), value: relativeDateRangeSchema }),
+  z.object({ field: z.literal("camera"), operator: z.enum(["equals", "contains"]), value: z.string().min(1) }),
+  z.object({ field: z.literal("eventType"), operator: z.enum(["equals", "in"]), value: stringOrList }),
+  z.object({ field: z.literal("tier"), operator: z.literal("equals"), value: z.enum(["bronze", "silver", "gold"]) }),
+  z.object({ field: z.literal("isReviewed"), operator: z.literal("equals"), value: z.boolean() }),
+  z.object({ field: z.literal("pickFlag"), operator: z.literal("equals"), value: z.enum(["pick", "reject"]).nullable() }),
+  // Stored by promotion scoring; unscored photos never match
+  z.object({ field: z.literal("promotionScore"), operator: z.enum(["greater_than", "less_than"]), value: z.number().min(0).max(100) }),
+  z.object({ field: z.literal("promotionScore"), operator: z.literal("between"), value: z.tuple([z.number().min(0).max(100), z.number().min(0).max(100)]) }),
+]);
+
+export const smartCollectionRulesSchema = z.object({
+  operator: z.enum(["AND", "OR"]),
+  rules: z.array(smartCollectionRuleSchema).min(1),
+});
+
+export type SmartRule = z.infer<typeof smartCollectionRuleSchema>;
+export type SmartRules = z.infer<typeof smartCollectionRulesSchema>;
+
+export interface SmartCollectionEvaluation {
+  collectionId: string;
   photoCount: number;
-  lastUpdated: string;
+  added: number;
+  removed: number;
 }
 
-interface CollectionRule {
-  id: string;
-  field: string;
-  operator: string;
-  value: string;
-  weight: number;
-}
+export class SmartCollectionError extends Error {}
 
-interface OrganizationResult {
-  organized: number;
-  collections: Array<{
-    id: string;
-    name: string;
-    photosAdded: number;
-  }>;
-}
+// Photo changes arrive in bursts (batch edits, imports); re-evaluate once they settle
+const REEVALUATE_DELAY_MS = 2000;
 
+/**
+ * Evaluates smart collection rules (tag, person, rating, date taken,
+ * camera, ...) and keeps each smart collection's membership materialized
+ * in collection_photos, so smart collections list, page and export like any
+ * other album. A whole collection is re-evaluated when its rules change;
+ * after that only the photos that changed are checked again.
+ */
 class SmartCollectionService {
-  
-  async organizeAllPhotos(): Promise<OrganizationResult> {
-    const collections = await storage.getSmartCollections();
-    const activeCollections = collections.filter(c => c.isActive && c.type === 'auto');
-    
-    if (activeCollections.length === 0) {
-      return { organized: 0, collections: [] };
+  private pendingPhotoIds = new Set<string>();
+  private pendingTimer: NodeJS.Timeout | null = null;
+
+  /**
+   * Rules stored on a collection, or null when they are missing or invalid
+   */
+  getRules(collection: Collection): SmartRules | null {
+    if (!collection.isSmartCollection || !collection.smartRules) return null;
+    const parsed = smartCollectionRulesSchema.safeParse(collection.smartRules);
+    return parsed.success ? parsed.data : null;
+  }
+
+  /**
+   * Replace a smart collection's membership with the photos its rules match
+   */
+  async evaluateSmartCollection(collectionId: string): Promise<SmartCollectionEvaluation> {
+    const collection = await storage.getCollection(collectionId);
+    if (!collection || collection.deletedAt) {
+      throw new SmartCollectionError('Collection not found');
+    }
+    const rules = this.getRules(collection);
+    if (!rules) {
+      throw new SmartCollectionError(`${collection.name} is not a smart collection with valid rules`);
     }
 
-    // Get all photos that could be organized
-    const photos = await storage.getPhotosForSmartCollections();
-    const result: OrganizationResult = { organized: 0, collections: [] };
-    
-    for (const collection of activeCollections) {
-      const matchingPhotos = await this.findMatchingPhotos(photos, collection);
-      
-      if (matchingPhotos.length > 0) {
-        // Add photos to collection
-        await storage.addPhotosToSmartCollection(collection.id, matchingPhotos.map(p => p.id));
-        
-        result.collections.push({
-          id: collection.id,
-          name: collection.name,
-          photosAdded: matchingPhotos.length
-        });
-        
-        result.organized += matchingPhotos.length;
-      }
-      
-      // Update collection photo count and last updated
-      await storage.updateSmartCollection(collection.id, {
-        photoCount: await storage.getSmartCollectionPhotoCount(collection.id),
-        lastUpdated: new Date().toISOString()
+    const photos = await storage.getAllFileVersions();
+    const peopleByPhoto = new Map<string, Set<string>>();
+    if (this.usesField(rules, 'person')) {
+      (await storage.getAllFaces()).forEach(face => {
+        if (!face.personId) return;
+        if (!peopleByPhoto.has(face.photoId)) peopleByPhoto.set(face.photoId, new Set());
+        peopleByPhoto.get(face.photoId)!.add(face.personId);
       });
     }
-    
-    return result;
-  }
-
-  async organizePhoto(photoId: string): Promise<void> {
-    const photo = await storage.getFileVersion(photoId);
-    if (!photo) return;
 
-    const collections = await storage.getSmartCollections();
-    const activeCollections = collections.filter(c => c.isActive && c.type === 'auto');
-    
-    for (const collection of activeCollections) {
-      const isMatch = await this.isPhotoMatch(photo, collection);
-      if (isMatch) {
-        await storage.addPhotoToSmartCollection(collection.id, photoId);
-        await storage.updateSmartCollection(collection.id, {
-          photoCount: await storage.getSmartCollectionPhotoCount(collection.id),
-          lastUpdated: new Date().toISOString()
-        });
-      }
+    const matching = new Set(
+      photos.filter(photo => this.matches(photo, rules, peopleByPhoto.get(photo.id) ?? new Set())).map(photo => photo.id)
+    );
+    const current = new Set(await storage.getCollectionPhotoIds(collectionId));
+    const toRemove = Array.from(current).filter(photoId => !matching.has(photoId));
+    for (const photoId of toRemove) {
+      await storage.removePhotoFromCollection(collectionId, photoId);
     }
+    const added = await storage.addPhotosToCollection(collectionId, Array.from(matching));
+
+    return { collectionId, photoCount: matching.size, added, removed: toRemove.length };
   }
 
-  private async findMatchingPhotos(photos: Photo[], collection: SmartCollection): Promise<Photo[]> {
-    const matchingPhotos: Photo[] = [];
-    
-    for (const photo of photos) {
-      const isMatch = await this.isPhotoMatch(photo, collection);
-      if (isMatch) {
-        matchingPhotos.push(photo);
+  async evaluateAll(): Promise<SmartCollectionEvaluation[]> {
+    const results: SmartCollectionEvaluation[] = [];
+    for (const collection of await this.getSmartCollections()) {
+      try {
+        results.push(await this.evaluateSmartCollection(collection.id));
+      } catch (error) {
+        console.error(`Failed to evaluate smart collection ${collection.name}:`, error);
       }
     }
-    
-    return matchingPhotos;
+    return results;
   }
 
-  private async isPhotoMatch(photo: Photo, collection: SmartCollection): Promise<boolean> {
-    let totalScore = 0;
-    let maxPossibleScore = 0;
-    
-    // Get enhanced photo data for matching
-    const photoData = await this.getEnhancedPhotoData(photo);
-    
-    for (const rule of collection.rules) {
-      maxPossibleScore += rule.weight;
-      
-      const ruleScore = this.evaluateRule(rule, photoData);
-      if (ruleScore > 0) {
-        totalScore += rule.weight * ruleScore;
+  /**
+   * Re-evaluate smart collections with relative date rules, whose photos
+   * change as the days pass; run with the daily maintenance
+   */
+  async evaluateRelativeDateCollections(): Promise<SmartCollectionEvaluation[]> {
+    const results: SmartCollectionEvaluation[] = [];
+    for (const collection of await this.getSmartCollections()) {
+      if (!this.getRules(collection)?.rules.some(rule => rule.operator === 'within')) continue;
+      try {
+        results.push(await this.evaluateSmartCollection(collection.id));
+      } catch (error) {
+        console.error(`Failed to evaluate smart collection ${collection.name}:`, error);
       }
     }
-    
-    // Require at least 60% confidence to include in collection
-    const confidence = maxPossibleScore > 0 ? totalScore / maxPossibleScore : 0;
-    return confidence >= 0.6;
+    return results;
   }
 
-  private evaluateRule(rule: CollectionRule, photoData: any): number {
-    const { field, operator, value } = rule;
-    const fieldValue = this.getFieldValue(field, photoData);
-    
-    switch (operator) {
-      case 'contains':
-        if (Array.isArray(fieldValue)) {
-          return fieldValue.some(item => 
-            item.toLowerCase().includes(value.toLowerCase())
-          ) ? 1 : 0;
-        } else if (typeof fieldValue === 'string') {
-          return fieldValue.toLowerCase().includes(value.toLowerCase()) ? 1 : 0;
-        }
-        return 0;
-        
-      case 'equals':
-        return fieldValue === value ? 1 : 0;
-        
-      case '>=':
-        const numValue = parseFloat(value);
-        return typeof fieldValue === 'number' && fieldValue >= numValue ? 1 : 0;
-        
-      case '<=':
-        const maxValue = parseFloat(value);
-        return typeof fieldValue === 'number' && fieldValue <= maxValue ? 1 : 0;
-        
-      case 'time_range':
-        return this.evaluateTimeRange(fieldValue, value);
-        
-      default:
-        return 0;
-    }
+  /**
+   * Note that photos changed (rating, tags, people, ...); smart collections
+   * are brought up to date for them shortly after
+   */
+  photosChanged(photoIds: string[]): void {
+    photoIds.forEach(photoId => this.pendingPhotoIds.add(photoId));
+    if (this.pendingPhotoIds.size === 0 || this.pendingTimer) return;
+    this.pendingTimer = setTimeout(() => {
+      this.pendingTimer = null;
+      const batch = Array.from(this.pendingPhotoIds);
+      this.pendingPhotoIds.clear();
+      this.reevaluatePhotos(batch).catch(error => console.error("Smart collection re-evaluation failed:", error));
+    }, REEVALUATE_DELAY_MS);
+    this.pendingTimer.unref();
   }
 
-  private getFieldValue(field: string, photoData: any): any {
-    switch (field) {
-      case 'aiTags':
-        return photoData.aiTags || [];
-      case 'eventType':
-        return photoData.eventType || '';
-      case 'location':
-        return photoData.location || '';
-      case 'faceCount':
-        return photoData.faceCount || 0;
-      case 'personAge':
-        return photoData.averageAge || 0;
-      case 'metadata.time':
-        return photoData.timeOfDay || '';
-      default:
-        return '';
+  /**
+   * Add or remove the given photos from every smart collection as their rules now say
+   */
+  async reevaluatePhotos(photoIds: string[]): Promise<void> {
+    const collections = (await this.getSmartCollections())
+      .flatMap(collection => {
+        const rules = this.getRules(collection);
+        return rules ? [{ collection, rules }] : [];
+      });
+    if (collections.length === 0) return;
+    const members = new Map<string, Set<string>>();
+    for (const { collection } of collections) {
+      members.set(collection.id, new Set(await storage.getCollectionPhotoIds(collection.id)));
     }
-  }
 
-  private evaluateTimeRange(timeValue: any, rangeValue: string): number {
-    if (!timeValue || !rangeValue) return 0;
-    
-    const ranges = rangeValue.split(',');
-    const photoTime = this.parseTimeFromValue(timeValue);
-    
-    if (!photoTime) return 0;
-    
-    for (const range of ranges) {
-      const [start, end] = range.split('-');
-      if (this.isTimeInRange(photoTime, start, end)) {
-        return 1;
+    for (const photoId of photoIds) {
+      const photo = await storage.getFileVersion(photoId);
+      const people = photo ? new Set((await storage.getFacesByPhoto(photoId)).flatMap(face => face.personId ? [face.personId] : [])) : new Set<string>();
+      for (const { collection, rules } of collections) {
+        const current = members.get(collection.id)!;
+        // Trashed photos drop out, and come back on restore
+        const belongs = !!photo && !photo.deletedAt && this.matches(photo, rules, people);
+        if (belongs && !current.has(photoId)) {
+          await storage.addPhotoToCollection(collection.id, photoId);
+        } else if (!belongs && current.has(photoId)) {
+          await storage.removePhotoFromCollection(collection.id, photoId);
+        }
       }
     }
-    
-    return 0;
   }
 
-  private parseTimeFromValue(value: any): string | null {
-    if (typeof value === 'string' && value.match(/^\d{2}:\d{2}/)) {
-      return value;
-    }
-    
-    if (value instanceof Date) {
-      return value.toTimeString().substring(0, 5);
-    }
-    
-    return null;
+  private async getSmartCollections(): Promise<Collection[]> {
+    return (await storage.getCollections()).filter(collection => collection.isSmartCollection);
   }
 
-  private isTimeInRange(time: string, start: string, end: string): boolean {
-    const timeMinutes = this.timeToMinutes(time);
-    const startMinutes = this.timeToMinutes(start);
-    const endMinutes = this.timeToMinutes(end);
-    
-    if (startMinutes <= endMinutes) {
-      return timeMinutes >= startMinutes && timeMinutes <= endMinutes;
-    } else {
-      // Handle overnight ranges (e.g., 22:00-06:00)
-      return timeMinutes >= startMinutes || timeMinutes <= endMinutes;
-    }
+  private matches(photo: FileVersion, rules: SmartRules, people: Set<string>): boolean {
+    const results = rules.rules.map(rule => this.matchesRule(photo, rule, people));
+    return rules.operator === 'AND' ? results.every(Boolean) : results.some(Boolean);
   }
 
-  private timeToMinutes(time: string): number {
-    const [hours, minutes] = time.split(':').map(Number);
-    return hours * 60 + minutes;
-  }
-
-  private async getEnhancedPhotoData(photo: Photo): Promise<any> {
-    // Get AI tags from metadata
-    const aiTags = photo.metadata?.aiTags || [];
-    
-    // Get face count
-    const faces = await storage.getFacesByPhoto(photo.id);
-    const faceCount = faces.length;
-    
-    // Calculate average age if people are identified
-    let averageAge = 0;
-    const identifiedFaces = faces.filter(f => f.personId);
-    if (identifiedFaces.length > 0) {
-      const ages = [];
-      for (const face of identifiedFaces) {
-        const person = await storage.getPerson(face.personId!);
-        if (person?.birthdate) {
-          const photoDate = new Date(photo.createdAt);
-          const birthDate = new Date(person.birthdate);
-          const age = photoDate.getFullYear() - birthDate.getFullYear();
-          ages.push(age);
+  private matchesRule(photo: FileVersion, rule: SmartRule, people: Set<string>): boolean {
+    switch (rule.field) {
+      case 'tag': {
+        const tags = [...propagationService.getTags(photo), ...(photo.keywords ?? [])].map(tag => tag.toLowerCase());
+        return this.matchesText(tags, rule.operator, rule.value);
+      }
+      case 'keywords':
+        // Keyword rules match whole keywords
+        return this.matchesText((photo.keywords ?? []).map(keyword => keyword.toLowerCase()), 'in', rule.value);
+      case 'person':
+        return this.list(rule.value).some(personId => people.has(personId));
+      case 'rating': {
+        const rating = photo.rating ?? 0;
+        if (rule.operator === 'between') return rating >= rule.value[0] && rating <= rule.value[1];
+        if (rule.operator === 'greater_than') return rating >= rule.value;
+        if (rule.operator === 'less_than') return rating <= rule.value;
+        return rating === rule.value;
+      }
+      case 'takenAt':
+      case 'importedAt':
+      case 'modifiedAt': {
+        const date = getPhotoDate(photo, DATE_RULE_FIELDS[rule.field])?.getTime();
+        if (date === undefined) return false;
+        if (rule.operator === 'within') {
+          const { start, end } = resolveRelativeRange(rule.value);
+          return date >= start.getTime() && date <= end.getTime();
         }
+        if (rule.operator === 'between') return date >= rule.value[0].getTime() && date <= rule.value[1].getTime();
+        if (rule.operator === 'greater_than') return date >= rule.value.getTime();
+        return date <= rule.value.getTime();
       }
-      if (ages.length > 0) {
-        averageAge = ages.reduce((sum, age) => sum + age, 0) / ages.length;
+      case 'camera': {
+        const camera = (photo.camera ?? '').toLowerCase();
+        return rule.operator === 'equals' ? camera === rule.value.toLowerCase() : camera.includes(rule.value.toLowerCase());
+      }
+      case 'eventType':
+        return !!photo.eventType && this.list(rule.value).includes(photo.eventType);
+      case 'tier':
+        return photo.tier === rule.value;
+      case 'isReviewed':
+        return (photo.isReviewed ?? false) === rule.value;
+      case 'pickFlag':
+        return (photo.pickFlag ?? null) === rule.value;
+      case 'promotionScore': {
+        const score = photo.promotionScore;
+        if (score == null) return false;
+        if (rule.operator === 'between') return score >= rule.value[0] && score <= rule.value[1];
+        if (rule.operator === 'greater_than') return score >= rule.value;
+        return score <= rule.value;
       }
     }
-    
-    // Extract time of day from metadata
-    let timeOfDay = '';
-    if (photo.metadata?.exif?.dateTimeOriginal) {
-      const date = new Date(photo.metadata.exif.dateTimeOriginal);
-      timeOfDay = date.toTimeString().substring(0, 5);
-    } else if (photo.createdAt) {
-      const date = new Date(photo.createdAt);
-      timeOfDay = date.toTimeString().substring(0, 5);
-    }
-    
-    return {
-      ...photo,
-      aiTags,
-      faceCount,
-      averageAge,
-      timeOfDay,
-      eventType: photo.eventType || '',
-      location: photo.location || ''
-    };
+  }
+
+  // Tag and keyword matches ignore case; contains matches part of a tag
+  private matchesText(values: string[], operator: 'equals' | 'contains' | 'in', expected: string | string[]): boolean {
+    const wanted = this.list(expected).map(value => value.toLowerCase());
+    if (operator === 'contains') return wanted.some(part => values.some(value => value.includes(part)));
+    return wanted.some(value => values.includes(value));
+  }
+
+  private list(value: string | string[]): string[] {
+    return Array.isArray(value) ? value : [value];
+  }
+
+  private usesField(rules: SmartRules, field: SmartRule['field']): boolean {
+    return rules.rules.some(rule => rule.field === field);
   }
 }
 
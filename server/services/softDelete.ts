@@ -0,0 +1,228 @@
+import fs from "fs/promises";
+import path from "path";
+import { storage } from "../storage";
+import { fileManager } from "./fileManager";
+import { provenanceService } from "./provenance";
+import { smartCollectionService } from "./smartCollectionService";
+import { thumbnailService } from "./thumbnailService";
+import { assertUnlocked } from "./photoLock";
+import { getLibraryRoot, libraryPath } from "../utils/libraryPaths";
+import { getEffectiveDate } from "../utils/photoDates";
+import type { FileVersion } from "@shared/schema";
+
+export const DELETED_RETENTION_SETTING = 'deleted_items_retention_days';
+const DEFAULT_RETENTION_DAYS = 30;
+
+export type DeletableKind = 'person' | 'album' | 'tag' | 'photo';
+
+export interface DeletedItem {
+  kind: DeletableKind;
+  id: string; // the tag itself for tags
+  name: string;
+  deletedAt: Date;
+  purgeAt: Date;
+}
+
+/**
+ * Soft-delete for people, albums, library tags and photos. Deleting only
+ * hides the item: face assignments, album memberships and photo tags stay in
+ * place, so restoring brings it back exactly as it was. A deleted photo's
+ * file is moved under trash/ and put back in its tier on restore. Items are
+ * purged for good on request or once the retention window has passed.
+ */
+class SoftDeleteService {
+  async getRetentionDays(): Promise<number> {
+    const setting = await storage.getSettingByKey(DELETED_RETENTION_SETTING);
+    const days = setting ? parseInt(setting.value) : NaN;
+    return Number.isFinite(days) && days >= 0 ? days : DEFAULT_RETENTION_DAYS;
+  }
+
+  /**
+   * Hide an item; returns false when it does not exist or is already deleted
+   */
+  async delete(kind: DeletableKind, id: string): Promise<boolean> {
+    const now = new Date();
+    switch (kind) {
+      case 'person': {
+        const person = await storage.getPerson(id);
+        if (!person || person.deletedAt) return false;
+        await storage.updatePerson(id, { deletedAt: now });
+        return true;
+      }
+      case 'album': {
+        const album = await storage.getCollection(id);
+        if (!album || album.deletedAt) return false;
+        if (album.systemKey) {
+          throw new Error('System albums cannot be deleted');
+        }
+        await storage.updateCollection(id, { deletedAt: now });
+        return true;
+      }
+      case 'tag':
+        return (await storage.getAllTags()).includes(id) && storage.setTagDeleted(id, now);
+      case 'photo': {
+        const photo = await storage.getFileVersion(id);
+        if (!photo || photo.deletedAt) return false;
+        assertUnlocked(photo, 'delete');
+        const trashPath = await this.moveToTrash(photo);
+        await storage.updateFileVersion(id, { deletedAt: now, trashPath });
+        await provenanceService.record(photo, 'TRASHED', `Moved to trash from ${photo.tier} tier`, { filePath: photo.filePath, trashPath });
+        smartCollectionService.photosChanged([id]);
+        return true;
+      }
+    }
+  }
+
+  async restore(kind: DeletableKind, id: string): Promise<boolean> {
+    switch (kind) {
+      case 'person': {
+        const person = await storage.getPerson(id);
+        if (!person?.deletedAt) return false;
+        await storage.updatePerson(id, { deletedAt: null });
+        return true;
+      }
+      case 'album': {
+        const album = await storage.getCollection(id);
+        if (!album?.deletedAt) return false;
+        await storage.updateCollection(id, { deletedAt: null });
+        return true;
+      }
+      case 'tag':
+        return (await storage.getDeletedTags()).some(entry => entry.tag === id) && storage.setTagDeleted(id, null);
+      case 'photo': {
+        const photo = await storage.getFileVersion(id);
+        if (!photo?.deletedAt) return false;
+        const filePath = await this.moveFromTrash(photo);
+        await storage.updateFileVersion(id, { deletedAt: null, trashPath: null, filePath });
+        await provenanceService.record(photo, 'RESTORED', `Restored from trash to ${photo.tier} tier`, { filePath });
+        smartCollectionService.photosChanged([id]);
+        return true;
+      }
+    }
+  }
+
+  /**
+   * Remove a soft-deleted item for good. A purged person's faces become
+   * unassigned; a purged album's memberships go with it; a purged photo's
+   * file and thumbnails are deleted.
+   */
+  async purge(kind: DeletableKind, id: string): Promise<boolean> {
+    switch (kind) {
+      case 'person': {
+        const person = await storage.getPerson(id);
+        if (!person?.deletedAt) return false;
+        await storage.deletePerson(id);
+        return true;
+      }
+      case 'album': {
+        const album = await storage.getCollection(id);
+        if (!album?.deletedAt) return false;
+        await storage.deleteCollection(id);
+        return true;
+      }
+      case 'tag':
+        return (await storage.getDeletedTags()).some(entry => entry.tag === id) && storage.purgeTag(id);
+      case 'photo': {
+        const photo = await storage.getFileVersion(id);
+        if (!photo?.deletedAt) return false;
+        if (photo.trashPath) {
+          await fs.rm(path.dirname(libraryPath(photo.trashPath)), { recursive: true, force: true });
+        }
+        await thumbnailService.removePhotoThumbnails(id);
+        await provenanceService.record(photo, 'PURGED', 'Deleted permanently from trash', { filePath: photo.filePath });
+        await storage.deleteFileVersion(id);
+        return true;
+      }
+    }
+  }
+
+  async list(kind?: DeletableKind): Promise<DeletedItem[]> {
+    const retentionMs = (await this.getRetentionDays()) * 24 * 60 * 60 * 1000;
+    const item = (kind: DeletableKind, id: string, name: string, deletedAt: Date): DeletedItem =>
+      ({ kind, id, name, deletedAt, purgeAt: new Date(deletedAt.getTime() + retentionMs) });
+
+    const items: DeletedItem[] = [
+      ...(await storage.getPeople(true))
+        .filter(person => person.deletedAt)
+        .map(person => item('person', person.id, person.name, person.deletedAt!)),
+      ...(await storage.getCollections(true))
+        .filter(album => album.deletedAt)
+        .map(album => item('album', album.id, album.name, album.deletedAt!)),
+      ...(await storage.getDeletedTags()).map(entry => item('tag', entry.tag, entry.tag, entry.deletedAt)),
+      ...(await storage.getDeletedFileVersions())
+        .map(photo => item('photo', photo.id, path.basename(photo.filePath), photo.deletedAt!)),
+    ];
+    return items
+      .filter(entry => !kind || entry.kind === kind)
+      .sort((a, b) => b.deletedAt.getTime() - a.deletedAt.getTime());
+  }
+
+  /**
+   * Purge everything soft-deleted, or only items of one kind (emptying the
+   * photo trash)
+   */
+  async purgeAll(kind?: DeletableKind): Promise<number> {
+    let purged = 0;
+    for (const entry of await this.list(kind)) {
+      if (await this.purge(entry.kind, entry.id)) purged++;
+    }
+    return purged;
+  }
+
+  /**
+   * Purge items deleted longer ago than the retention window; run at startup
+   */
+  async purgeExpired(): Promise<number> {
+    const now = Date.now();
+    let purged = 0;
+    for (const entry of await this.list()) {
+      if (entry.purgeAt.getTime() <= now && await this.purge(entry.kind, entry.id)) purged++;
+    }
+    if (purged > 0) {
+      console.log(`Purged ${purged} deleted item(s) past the retention window`);
+    }
+    return purged;
+  }
+
+  /**
+   * Move a photo's file to trash/<photo id>/, returning its library-relative
+   * path, or null when there is no local file (evicted to cold storage)
+   */
+  private async moveToTrash(photo: FileVersion): Promise<string | null> {
+    const source = libraryPath(photo.filePath);
+    if (!(await this.exists(source))) return null;
+    const trashPath = path.join('trash', photo.id, path.basename(photo.filePath));
+    await fs.mkdir(path.dirname(libraryPath(trashPath)), { recursive: true });
+    await fs.rename(source, libraryPath(trashPath));
+    return trashPath;
+  }
+
+  /**
+   * Move a trashed photo's file back into its tier, at its old path unless
+   * something has taken that place since. Returns the library-relative path.
+   */
+  private async moveFromTrash(photo: FileVersion): Promise<string> {
+    if (!photo.trashPath) return photo.filePath;
+    let destination = libraryPath(photo.filePath);
+    if (await this.exists(destination)) {
+      destination = await fileManager.resolveDestination(
+        photo.tier, getEffectiveDate(photo), path.basename(photo.filePath), photo.fileHash
+      );
+    }
+    await fs.mkdir(path.dirname(destination), { recursive: true });
+    await fs.rename(libraryPath(photo.trashPath), destination);
+    await fs.rm(path.dirname(libraryPath(photo.trashPath)), { recursive: true, force: true });
+    return path.relative(getLibraryRoot(), destination);
+  }
+
+  private async exists(filePath: string): Promise<boolean> {
+    try {
+      await fs.access(filePath);
+      return true;
+    } catch {
+      return false;
+    }
+  }
+}
+
+export const softDeleteService = new SoftDeleteService();
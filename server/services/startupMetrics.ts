@@ -0,0 +1,179 @@
+import type { Request, Response, NextFunction } from "express";
+
+// The first gallery page and its thumbnails should be on screen within this
+// long of the process starting
+export const FIRST_GRID_BUDGET_MS = 3000;
+
+// Thumbnails in the first screenful of the grid
+const FIRST_THUMBNAIL_BATCH_SIZE = 24;
+
+export interface StartupPhase {
+  name: string;
+  startMs: number; // since the process started
+  durationMs: number;
+  status: 'completed' | 'failed';
+}
+
+export interface DeferredTask {
+  name: string;
+  status: 'waiting' | 'running' | 'completed' | 'failed';
+  startMs: number | null;
+  durationMs: number | null;
+  error?: string;
+}
+
+export interface FirstRequestMetric {
+  atMs: number; // since the process started, when the response finished
+  durationMs: number; // of the request itself
+}
+
+export interface StartupMetrics {
+  processStartedAt: string;
+  listeningAtMs: number | null;
+  phases: StartupPhase[];
+  deferred: DeferredTask[];
+  firstPhotosPage: FirstRequestMetric | null;
+  firstThumbnailBatch: (FirstRequestMetric & { count: number }) | null;
+  timeToFirstGridMs: number | null;
+  budgetMs: number;
+  withinBudget: boolean | null; // null until the first grid has loaded
+}
+
+/**
+ * Startup instrumentation: how long pool setup, migrations and service
+ * initialization took, when the server started listening, and when the
+ * first photos page and first batch of thumbnails were served. Work the
+ * first gallery paint does not need is deferred until the server is
+ * listening, so it does not compete with those first requests.
+ */
+class StartupMetricsService {
+  private readonly processStart = Date.now() - Math.round(process.uptime() * 1000);
+  private phases: StartupPhase[] = [];
+  private deferred: Array<DeferredTask & { run: () => Promise<unknown> }> = [];
+  private listeningAt: number | null = null;
+  private runningDeferred = false;
+  private firstPhotosPage: FirstRequestMetric | null = null;
+  private thumbnailsServed = 0;
+  private firstThumbnailStart: number | null = null;
+  private firstThumbnailBatch: (FirstRequestMetric & { count: number }) | null = null;
+
+  /**
+   * Time a startup phase; failures are recorded and rethrown
+   */
+  async measure<T>(name: string, action: () => Promise<T>): Promise<T> {
+    const startedAt = Date.now();
+    try {
+      const result = await action();
+      this.addPhase(name, startedAt, Date.now() - startedAt, 'completed');
+      return result;
+    } catch (error) {
+      this.addPhase(name, startedAt, Date.now() - startedAt, 'failed');
+      throw error;
+    }
+  }
+
+  /**
+   * Record a phase timed elsewhere, such as a library initialization step
+   */
+  addPhase(name: string, startedAt: number, durationMs: number, status: StartupPhase['status']): void {
+    this.phases.push({ name, startMs: startedAt - this.processStart, durationMs, status });
+  }
+
+  /**
+   * Run a heavyweight initialization once the server is listening; runs right
+   * away if it already is. Deferred tasks run one after another.
+   */
+  defer(name: string, run: () => Promise<unknown>): void {
+    this.deferred.push({ name, status: 'waiting', startMs: null, durationMs: null, run });
+    if (this.listeningAt !== null) {
+      this.runDeferred().catch(error => console.error('Deferred startup tasks failed:', error));
+    }
+  }
+
+  markListening(): void {
+    if (this.listeningAt !== null) return;
+    this.listeningAt = Date.now();
+    // Let the first requests in before the deferred work starts
+    setImmediate(() => {
+      this.runDeferred().catch(error => console.error('Deferred startup tasks failed:', error));
+    });
+  }
+
+  /**
+   * Express middleware timing the first photos page and thumbnail batch
+   */
+  trackFirstRequests() {
+    return (req: Request, res: Response, next: NextFunction) => {
+      if (req.method !== 'GET' || (this.firstPhotosPage && this.firstThumbnailBatch)) return next();
+      const startedAt = Date.now();
+      const isPhotosPage = req.path === '/api/photos';
+      const isThumbnail = /^\/api\/photos\/[^/]+\/thumbnail$/.test(req.path)
+        || (req.path.startsWith('/api/files/') && (req.query.w !== undefined || req.query.quality !== undefined));
+      if (!isPhotosPage && !isThumbnail) return next();
+
+      res.on('finish', () => {
+        const finishedAt = Date.now();
+        if (isPhotosPage && !this.firstPhotosPage) {
+          this.firstPhotosPage = { atMs: finishedAt - this.processStart, durationMs: finishedAt - startedAt };
+        }
+        if (isThumbnail && !this.firstThumbnailBatch) {
+          this.firstThumbnailStart = this.firstThumbnailStart ?? startedAt;
+          if (++this.thumbnailsServed >= FIRST_THUMBNAIL_BATCH_SIZE) {
+            this.firstThumbnailBatch = {
+              atMs: finishedAt - this.processStart,
+              durationMs: finishedAt - this.firstThumbnailStart,
+              count: this.thumbnailsServed,
+            };
+          }
+        }
+      });
+      next();
+    };
+  }
+
+  getMetrics(): StartupMetrics {
+    // Small libraries never fill a batch; their first grid is done with the photos page
+    const thumbnailsDone = this.firstThumbnailBatch?.atMs ?? null;
+    const timeToFirstGridMs = this.firstPhotosPage
+      ? Math.max(this.firstPhotosPage.atMs, thumbnailsDone ?? 0)
+      : null;
+    return {
+      processStartedAt: new Date(this.processStart).toISOString(),
+      listeningAtMs: this.listeningAt === null ? null : this.listeningAt - this.processStart,
+      phases: [...this.phases],
+      deferred: this.deferred.map(({ run, ...task }) => task),
+      firstPhotosPage: this.firstPhotosPage,
+      firstThumbnailBatch: this.firstThumbnailBatch,
+      timeToFirstGridMs,
+      budgetMs: FIRST_GRID_BUDGET_MS,
+      withinBudget: timeToFirstGridMs === null ? null : timeToFirstGridMs <= FIRST_GRID_BUDGET_MS,
+    };
+  }
+
+  private async runDeferred(): Promise<void> {
+    if (this.runningDeferred) return;
+    this.runningDeferred = true;
+    try {
+      // Tasks deferred while this runs are picked up by the same loop
+      for (const task of this.deferred) {
+        if (task.status !== 'waiting') continue;
+        task.status = 'running';
+        const startedAt = Date.now();
+        task.startMs = startedAt - this.processStart;
+        try {
+          await task.run();
+          task.status = 'completed';
+        } catch (error) {
+          task.status = 'failed';
+          task.error = error instanceof Error ? error.message : String(error);
+          console.error(`Deferred startup task ${task.name} failed:`, error);
+        }
+        task.durationMs = Date.now() - startedAt;
+      }
+    } finally {
+      this.runningDeferred = false;
+    }
+  }
+}
+
+export const startupMetrics = new StartupMetricsService();
@@ -0,0 +1,187 @@
+import { storage } from "../storage";
+import { smartCollectionService } from "./smartCollectionService";
+import type { Collection, CombinedMetadata, FileVersion, MediaAsset } from "@shared/schema";
+
+export type SystemAlbumKey = 'screenshots' | 'documents' | 'receipts' | 'whiteboards';
+
+interface SystemAlbumDefinition {
+  key: SystemAlbumKey;
+  name: string;
+  description: string;
+  filenamePattern: RegExp;
+  tags: string[]; // matched as whole words against AI tags and detected objects
+  textPattern?: RegExp; // matched against OCR text
+}
+
+// Each signal adds to a 0-1 score; a photo joins an album at MATCH_THRESHOLD.
+// System albums hide their photos from the timeline, so a tag alone is not
+// enough: it takes the file name, or a tag backed by the text in the photo.
+const FILENAME_WEIGHT = 0.6;
+const TAG_WEIGHT = 0.4;
+const TEXT_WEIGHT = 0.3;
+const NO_CAMERA_WEIGHT = 0.2; // screenshots only: no camera EXIF backs up a screenshot tag
+const MATCH_THRESHOLD = 0.5;
+
+const SYSTEM_ALBUMS: SystemAlbumDefinition[] = [
+  {
+    key: 'screenshots',
+    name: 'Screenshots',
+    description: 'Screen captures from phones and computers',
+    filenamePattern: /screen ?shot|screen[_ -]?capture|^scrn|^simulator screen/i,
+    tags: ['screenshot', 'screen capture', 'user interface', 'app screen', 'web page'],
+  },
+  {
+    key: 'documents',
+    name: 'Documents',
+    description: 'Photos and scans of letters, forms and other paperwork',
+    filenamePattern: /(^|[^a-z])(scan|scanned|document)([^a-z]|$)|^doc[_ -]?\d+/i,
+    tags: ['document', 'paper', 'letter', 'form', 'certificate', 'page', 'paperwork'],
+    textPattern: /\b(dear|sincerely|signature|page \d+|date of birth|address|policy|account number)\b/i,
+  },
+  {
+    key: 'receipts',
+    name: 'Receipts',
+    description: 'Receipts and invoices',
+    filenamePattern: /receipt|invoice/i,
+    tags: ['receipt', 'invoice', 'bill'],
+    textPattern: /\b(subtotal|total|tax|vat|change due|cash|visa|mastercard|amex)\b[\s\S]*\d+[.,]\d{2}/i,
+  },
+  {
+    key: 'whiteboards',
+    name: 'Whiteboards',
+    description: 'Whiteboards, blackboards and flip charts',
+    filenamePattern: /whiteboard|blackboard|flip ?chart/i,
+    tags: ['whiteboard', 'blackboard', 'chalkboard', 'flip chart', 'flipchart'],
+    textPattern: /[a-z]{3,}/i, // any legible writing on the board
+  },
+];
+
+/**
+ * Built-in smart albums for utility photos. Membership combines filename
+ * heuristics, AI classification tags and OCR text, and is kept up to date by
+ * the processing pipeline rather than by user-defined rules.
+ */
+class SystemAlbumService {
+  /**
+   * Create any missing system albums; safe to call on every startup
+   */
+  async ensureSystemAlbums(): Promise<void> {
+    for (const definition of SYSTEM_ALBUMS) {
+      const existing = await storage.getCollectionBySystemKey(definition.key);
+      if (existing) continue;
+
+      await storage.createCollection({
+        name: definition.name,
+        description: definition.description,
+        systemKey: definition.key,
+        excludeFromTimeline: true,
+      });
+      console.log(`Created system album "${definition.name}"`);
+    }
+  }
+
+  /**
+   * Score a photo against every system album and return the ones it belongs to
+   */
+  classify(photo: FileVersion, asset?: MediaAsset): SystemAlbumKey[] {
+    const metadata = (photo.metadata as CombinedMetadata | null) ?? {};
+    const filename = asset?.originalFilename ?? photo.filePath.split('/').pop() ?? '';
+    const labels = [
+      ...(metadata.ai?.aiTags ?? []),
+      ...(metadata.ai?.detectedObjects ?? []).map(object => object.name),
+      ...(photo.keywords ?? []),
+    ].map(label => label.toLowerCase());
+    const text = metadata.ai?.detectedText ?? '';
+    const hasCameraExif = Boolean(metadata.exif?.camera || metadata.exif?.lens || metadata.exif?.aperture);
+
+    return SYSTEM_ALBUMS.filter(definition => {
+      let score = 0;
+      if (definition.filenamePattern.test(filename)) score += FILENAME_WEIGHT;
+      if (labels.some(label => definition.tags.some(tag => this.hasWord(label, tag)))) score += TAG_WEIGHT;
+      if (definition.textPattern && text && definition.textPattern.test(text)) score += TEXT_WEIGHT;
+
+      // Screenshots never carry camera EXIF; a photo of a screen does
+      if (definition.key === 'screenshots') score += hasCameraExif ? -TAG_WEIGHT : NO_CAMERA_WEIGHT;
+
+      return score >= MATCH_THRESHOLD;
+    }).map(definition => definition.key);
+  }
+
+  // Whole words only, so "form" does not match "uniform" nor "paper" "newspaper"
+  private hasWord(label: string, tag: string): boolean {
+    const escaped = tag.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+    return new RegExp(`(^|[^a-z])${escaped}([^a-z]|$)`).test(label);
+  }
+
+  /**
+   * Pipeline hook: add a newly processed photo to the system albums it matches
+   */
+  async processPhoto(photo: FileVersion): Promise<SystemAlbumKey[]> {
+    // Smart collections follow the same pipeline, on their own schedule
+    smartCollectionService.photosChanged([photo.id]);
+    try {
+      const asset = await storage.getMediaAsset(photo.mediaAssetId);
+      const keys = this.classify(photo, asset);
+
+      for (const key of keys) {
+        const album = await storage.getCollectionBySystemKey(key);
+        if (album) await storage.addPhotosToCollection(album.id, [photo.id]);
+      }
+      return keys;
+    } catch (error) {
+      // Album membership is best-effort and must never fail processing
+      console.error(`Failed to update system albums for photo ${photo.id}:`, error);
+      return [];
+    }
+  }
+
+  /**
+   * Rebuild every system album from the highest-tier version of each asset
+   */
+  async refreshAll(): Promise<Record<SystemAlbumKey, number>> {
+    await this.ensureSystemAlbums();
+
+    const highestVersions = new Map<string, FileVersion & { mediaAsset: MediaAsset }>();
+    for (const photo of await storage.getAllFileVersionsWithAssets()) {
+      if (photo.tier === 'bronze') continue;
+      const current = highestVersions.get(photo.mediaAssetId);
+      if (!current || (current.tier === 'silver' && photo.tier === 'gold')) {
+        highestVersions.set(photo.mediaAssetId, photo);
+      }
+    }
+
+    const members: Record<SystemAlbumKey, string[]> = { screenshots: [], documents: [], receipts: [], whiteboards: [] };
+    highestVersions.forEach(photo => {
+      for (const key of this.classify(photo, photo.mediaAsset)) members[key].push(photo.id);
+    });
+
+    const counts = {} as Record<SystemAlbumKey, number>;
+    for (const key of Object.keys(members) as SystemAlbumKey[]) {
+      const album = await storage.getCollectionBySystemKey(key);
+      if (!album) continue;
+      counts[key] = await storage.replaceCollectionPhotos(album.id, members[key]);
+    }
+    return counts;
+  }
+
+  getSystemAlbums(): Promise<Collection[]> {
+    return storage.getSystemCollections();
+  }
+
+  /**
+   * Media assets hidden from the timeline and memories because they sit in a
+   * system album flagged excludeFromTimeline
+   */
+  async getTimelineExcludedAssetIds(): Promise<Set<string>> {
+    const excluded = new Set<string>();
+    for (const album of await storage.getSystemCollections()) {
+      if (!album.excludeFromTimeline) continue;
+      for (const photo of await storage.getCollectionPhotos(album.id)) {
+        excluded.add(photo.mediaAssetId);
+      }
+    }
+    return excluded;
+  }
+}
+
+export const systemAlbumService = new SystemAlbumService();
@@ -0,0 +1,293 @@
+import { storage } from "../storage";
+import { propagationService } from "./propagation";
+import { smartCollectionService } from "./smartCollectionService";
+import type { FileVersion, GlobalTagLibrary } from "@shared/schema";
+
+export const TAG_SEPARATOR = '/';
+
+export interface TagTreeNode {
+  id: string;
+  tag: string; // full path
+  name: string; // last segment
+  usageCount: number;
+  photoCount: number; // photos with exactly this tag
+  totalPhotoCount: number; // photos with this tag or any tag under it
+  children: TagTreeNode[];
+}
+
+export interface TagRenameResult {
+  from: string;
+  to: string;
+  renamedTags: number;
+  mergedTags: number; // library tags folded into one already there
+  photosUpdated: string[];
+  lockedPhotos: string[]; // locked photos left with the old tag
+}
+
+export interface TagMergeResult {
+  target: string;
+  mergedTags: string[];
+  renamedTags: number;
+  photosUpdated: string[];
+  lockedPhotos: string[];
+}
+
+export class TagHierarchyError extends Error {}
+
+/**
+ * Whether a photo tag is the given tag or nested anywhere under it, so
+ * "animals" matches "animals/dogs/corgi". Case is ignored.
+ */
+export function tagMatches(tag: string, ancestor: string): boolean {
+  const value = tag.toLowerCase();
+  const prefix = ancestor.toLowerCase().replace(/\/+$/, '');
+  return value === prefix || value.startsWith(prefix + TAG_SEPARATOR);
+}
+
+export function parentTag(tag: string): string | null {
+  const index = tag.lastIndexOf(TAG_SEPARATOR);
+  return index > 0 ? tag.slice(0, index) : null;
+}
+
+/**
+ * Nested tags. A tag's place in the tree is its path ("animals/dogs/corgi"
+ * sits under "animals/dogs"), so tagging a photo with a path is all it takes
+ * to file it; the library keeps a parent link for each tag, creating the
+ * parents a path names. Moving, renaming and merging tags rewrite them, and
+ * everything under them, on every photo.
+ */
+class TagHierarchyService {
+  /**
+   * Create missing parent tags and point every library tag at its parent
+   */
+  async syncHierarchy(): Promise<GlobalTagLibrary[]> {
+    const library = await storage.getTagLibrary();
+    const byTag = new Map(library.map(entry => [entry.tag, entry]));
+
+    // Parents named only by their children's paths; they have no photos of their own yet
+    const missing = new Set<string>();
+    library.forEach(entry => {
+      for (let parent = parentTag(entry.tag); parent; parent = parentTag(parent)) {
+        if (!byTag.has(parent)) missing.add(parent);
+      }
+    });
+    for (const tag of Array.from(missing).sort()) {
+      byTag.set(tag, await storage.createLibraryTag(tag, 0));
+    }
+
+    for (const entry of Array.from(byTag.values())) {
+      const parent = parentTag(entry.tag);
+      const parentId = parent ? byTag.get(parent)?.id ?? null : null;
+      if ((entry.parentTagId ?? null) !== parentId) {
+        const updated = await storage.updateLibraryTag(entry.id, { parentTagId: parentId });
+        if (updated) byTag.set(entry.tag, updated);
+      }
+    }
+    return Array.from(byTag.values());
+  }
+
+  /**
+   * The tag library as a tree, with how many photos each tag and its subtree has
+   */
+  async getTagTree(): Promise<TagTreeNode[]> {
+    const library = await this.syncHierarchy();
+    const photos = await storage.getAllFileVersions();
+
+    const direct = new Map<string, number>();
+    const subtree = new Map<string, Set<string>>();
+    photos.forEach(photo => {
+      this.photoTags(photo).forEach(tag => {
+        direct.set(tag, (direct.get(tag) ?? 0) + 1);
+        for (let current: string | null = tag; current; current = parentTag(current)) {
+          if (!subtree.has(current)) subtree.set(current, new Set());
+          subtree.get(current)!.add(photo.id);
+        }
+      });
+    });
+
+    const nodes = new Map<string, TagTreeNode>(library.map(entry => [entry.id, {
+      id: entry.id,
+      tag: entry.tag,
+      name: entry.tag.slice(entry.tag.lastIndexOf(TAG_SEPARATOR) + 1),
+      usageCount: entry.usageCount,
+      photoCount: direct.get(entry.tag.toLowerCase()) ?? 0,
+      totalPhotoCount: subtree.get(entry.tag.toLowerCase())?.size ?? 0,
+      children: [],
+    }]));
+
+    const roots: TagTreeNode[] = [];
+    library.forEach(entry => {
+      const node = nodes.get(entry.id)!;
+      const parent = entry.parentTagId ? nodes.get(entry.parentTagId) : undefined;
+      (parent ? parent.children : roots).push(node);
+    });
+    const sort = (list: TagTreeNode[]) => {
+      list.sort((a, b) => a.name.localeCompare(b.name));
+      list.forEach(node => sort(node.children));
+    };
+    sort(roots);
+    return roots;
+  }
+
+  /**
+   * Move a tag, with everything under it, under another tag (or to the top
+   * level when newParent is null). "animals/dogs" moved under "pets" becomes
+   * "pets/dogs", and "animals/dogs/corgi" becomes "pets/dogs/corgi".
+   */
+  async moveTag(tag: string, newParent: string | null): Promise<TagRenameResult> {
+    const library = await storage.getTagLibrary();
+    const entry = library.find(candidate => candidate.tag === tag);
+    if (!entry) {
+      throw new TagHierarchyError(`Tag ${tag} not found`);
+    }
+    const parent = newParent?.replace(/^\/+|\/+$/g, '') || null;
+    const name = tag.slice(tag.lastIndexOf(TAG_SEPARATOR) + 1);
+    return this.renameSubtree(library, entry, parent ? `${parent}${TAG_SEPARATOR}${name}` : name);
+  }
+
+  /**
+   * Rename a library tag on every photo. A plain name keeps the tag under
+   * its parent ("animals/dog" renamed "dogs" is "animals/dogs"); a path
+   * moves it as well. Tags under it follow.
+   */
+  async renameTag(id: string, newName: string): Promise<TagRenameResult> {
+    const library = await storage.getTagLibrary();
+    const entry = library.find(candidate => candidate.id === id);
+    if (!entry) {
+      throw new TagHierarchyError('Tag not found');
+    }
+    const name = newName.trim().replace(/^\/+|\/+$/g, '');
+    if (!name) {
+      throw new TagHierarchyError('A tag name is required');
+    }
+    const parent = parentTag(entry.tag);
+    return this.renameSubtree(library, entry, name.includes(TAG_SEPARATOR) || !parent ? name : `${parent}${TAG_SEPARATOR}${name}`);
+  }
+
+  /**
+   * Fold near-duplicate tags into one: photos tagged with a source get the
+   * target instead, tags under a source move under the target (joining tags
+   * already there), and the sources leave the library
+   */
+  async mergeTags(sourceIds: string[], targetId: string): Promise<TagMergeResult> {
+    const library = await storage.getTagLibrary();
+    const target = library.find(candidate => candidate.id === targetId);
+    if (!target) {
+      throw new TagHierarchyError('Target tag not found');
+    }
+    const sources = Array.from(new Set(sourceIds)).filter(id => id !== targetId).map(id => {
+      const source = library.find(candidate => candidate.id === id);
+      if (!source) throw new TagHierarchyError(`Tag ${id} not found`);
+      return source;
+    });
+    if (sources.length === 0) {
+      throw new TagHierarchyError('Choose at least one tag to merge into the target');
+    }
+    const nested = sources.find(source => tagMatches(target.tag, source.tag));
+    if (nested) {
+      throw new TagHierarchyError(`${target.tag} is under ${nested.tag} and cannot absorb it`);
+    }
+
+    const { renamedTags, photosUpdated, lockedPhotos } = await this.rewrite(library, sources.map(source => ({ from: source.tag, to: target.tag })), true);
+    return { target: target.tag, mergedTags: sources.map(source => source.tag), renamedTags, photosUpdated, lockedPhotos };
+  }
+
+  /**
+   * Photos that have one of the tags or a tag under one, trashed ones included
+   */
+  async findPhotosWithTags(tags: string[]): Promise<string[]> {
+    return (await storage.getAllFileVersions(true))
+      .filter(photo => propagationService.getTags(photo).some(value => tags.some(tag => tagMatches(value, tag))))
+      .map(photo => photo.id);
+  }
+
+  async findPhotosWithTagIds(ids: string[]): Promise<string[]> {
+    const tags = (await storage.getTagLibrary()).filter(entry => ids.includes(entry.id)).map(entry => entry.tag);
+    return tags.length > 0 ? this.findPhotosWithTags(tags) : [];
+  }
+
+  private async renameSubtree(library: GlobalTagLibrary[], entry: GlobalTagLibrary, destination: string): Promise<TagRenameResult> {
+    if (destination === entry.tag) {
+      return { from: entry.tag, to: destination, renamedTags: 0, mergedTags: 0, photosUpdated: [], lockedPhotos: [] };
+    }
+    if (tagMatches(destination, entry.tag) && destination.toLowerCase() !== entry.tag.toLowerCase()) {
+      throw new TagHierarchyError('A tag cannot be moved under itself');
+    }
+    const result = await this.rewrite(library, [{ from: entry.tag, to: destination }], false);
+    return { from: entry.tag, to: destination, ...result };
+  }
+
+  /**
+   * Rename tags and everything under them, on photos and in the library, in
+   * one transaction. With merge, a new name that is already a library tag
+   * joins it; without, it is an error. Locked photos keep their tags and
+   * are reported.
+   */
+  private async rewrite(
+    library: GlobalTagLibrary[],
+    renames: Array<{ from: string; to: string }>,
+    merge: boolean
+  ): Promise<{ renamedTags: number; mergedTags: number; photosUpdated: string[]; lockedPhotos: string[] }> {
+    // The longest matching source wins when one is nested in another.
+    // Matching ignores case, so the part after the source is the same length on every tag.
+    const ordered = [...renames].sort((a, b) => b.from.length - a.from.length);
+    const rename = (value: string): string | null => {
+      const match = ordered.find(candidate => tagMatches(value, candidate.from));
+      return match ? match.to + value.slice(match.from.length) : null;
+    };
+
+    const moving = library.filter(entry => rename(entry.tag) !== null);
+    const movingIds = new Set(moving.map(entry => entry.id));
+    const staying = new Map(library.filter(entry => !movingIds.has(entry.id)).map(entry => [entry.tag.toLowerCase(), entry]));
+    const deleted = new Set((await storage.getDeletedTags()).map(entry => entry.tag.toLowerCase()));
+    const oldNames = new Set(moving.map(entry => entry.tag.toLowerCase()));
+
+    const libraryRenames: Array<{ id: string; tag: string }> = [];
+    const libraryMerges: Array<{ sourceId: string; targetId: string }> = [];
+    const claimed = new Map<string, string>(); // new name -> id of the moving tag that takes it
+    moving.forEach(entry => {
+      const tag = rename(entry.tag)!;
+      const key = tag.toLowerCase();
+      const existing = staying.get(key) ?? (claimed.has(key) ? { id: claimed.get(key)! } : undefined);
+      if (existing) {
+        if (!merge) throw new TagHierarchyError(`${tag} already exists`);
+        libraryMerges.push({ sourceId: entry.id, targetId: existing.id });
+        return;
+      }
+      if (deleted.has(key)) {
+        throw new TagHierarchyError(`${tag} is a deleted tag; restore or purge it first`);
+      }
+      if (oldNames.has(key) && key !== entry.tag.toLowerCase()) {
+        throw new TagHierarchyError(`${tag} would clash with a tag being renamed`);
+      }
+      claimed.set(key, entry.id);
+      libraryRenames.push({ id: entry.id, tag });
+    });
+
+    const photoTags: Array<{ photoId: string; tags: string[] }> = [];
+    const lockedPhotos: string[] = [];
+    (await storage.getAllFileVersions(true)).forEach(photo => {
+      const tags = propagationService.getTags(photo);
+      if (!tags.some(value => rename(value) !== null)) return;
+      if (photo.isLocked) {
+        lockedPhotos.push(photo.id);
+        return;
+      }
+      photoTags.push({ photoId: photo.id, tags: Array.from(new Set(tags.map(value => rename(value) ?? value))) });
+    });
+
+    await storage.rewriteTags(photoTags, libraryRenames, libraryMerges);
+    const photosUpdated = photoTags.map(entry => entry.photoId);
+    smartCollectionService.photosChanged(photosUpdated);
+    await this.syncHierarchy();
+
+    return { renamedTags: libraryRenames.length, mergedTags: libraryMerges.length, photosUpdated, lockedPhotos };
+  }
+
+  // A photo's tags, lower-cased, for counting
+  private photoTags(photo: FileVersion): string[] {
+    return Array.from(new Set(propagationService.getTags(photo).map(tag => tag.toLowerCase())));
+  }
+}
+
+export const tagHierarchyService = new TagHierarchyService();
@@ -2,6 +2,19 @@ import sharp from 'sharp';
 import path from 'path';
 import fs from 'fs/promises';
 import { createHash } from 'crypto';
+import type { CancellationToken } from './operations';
+import { libraryPath } from '../utils/libraryPaths';
+import { displayImage } from '../utils/colorProfile';
+import { provenanceService } from './provenance';
+import { storage } from '../storage';
+
+export const THUMBNAIL_QUALITY_SETTING = 'thumbnail_max_quality';
+export const THUMBNAIL_CHROMA_SETTING = 'thumbnail_chroma_subsampling';
+export const THUMBNAIL_SHARPEN_SETTING = 'thumbnail_sharpen';
+export const THUMBNAIL_ENCODER_SETTINGS = [THUMBNAIL_QUALITY_SETTING, THUMBNAIL_CHROMA_SETTING, THUMBNAIL_SHARPEN_SETTING];
+
+// Bump when the pipeline itself changes how thumbnails look, so cached ones are regenerated
+const PIPELINE_VERSION = 2;
 
 export interface ThumbnailOptions {
   size: number;
@@ -9,24 +22,80 @@ export interface ThumbnailOptions {
   format?: 'jpeg' | 'webp' | 'png';
 }
 
+export type ThumbnailSharpening = 'off' | 'light' | 'strong';
+
+export interface ThumbnailEncoderSettings {
+  maxQuality: number; // JPEG quality never goes above this, whatever a request asks for
+  chromaSubsampling: '4:2:0' | '4:4:4'; // 4:4:4 keeps fine colour edges at a larger file size
+  sharpen: ThumbnailSharpening; // unsharp mask after downscaling, which softens detail
+}
+
+const DEFAULT_ENCODER: ThumbnailEncoderSettings = { maxQuality: 60, chromaSubsampling: '4:2:0', sharpen: 'off' };
+const SHARPEN_SIGMA: Record<Exclude<ThumbnailSharpening, 'off'>, number> = { light: 0.5, strong: 1 };
+
+export interface ThumbnailSource {
+  path: string;
+  photoId?: string;
+}
+
 export class ThumbnailService {
-  private cacheDir: string;
+  private fixedCacheDir?: string;
+  private encoder: Promise<{ settings: ThumbnailEncoderSettings; version: string }> | null = null;
 
-  constructor(cacheDir: string = 'uploads/thumbnails') {
-    this.cacheDir = cacheDir;
-    this.ensureCacheDir();
+  // Defaults to the library's thumbnails folder so the cache moves with the library
+  constructor(cacheDir?: string) {
+    this.fixedCacheDir = cacheDir;
   }
 
-  private async ensureCacheDir() {
-    try {
-      await fs.mkdir(this.cacheDir, { recursive: true });
-    } catch (error) {
-      console.error('Failed to create thumbnail cache directory:', error);
+  private get cacheDir(): string {
+    return this.fixedCacheDir ?? libraryPath('thumbnails');
+  }
+
+  /**
+   * Encoder settings, read once and kept until the settings change
+   */
+  async getEncoderSettings(): Promise<ThumbnailEncoderSettings> {
+    return (await this.loadEncoder()).settings;
+  }
+
+  /**
+   * Reload the encoder settings; thumbnails cached under others are
+   * regenerated as they are next requested
+   */
+  refreshEncoderSettings(): void {
+    this.encoder = null;
+  }
+
+  private loadEncoder(): Promise<{ settings: ThumbnailEncoderSettings; version: string }> {
+    if (!this.encoder) {
+      this.encoder = (async () => {
+        const [quality, chroma, sharpen] = await Promise.all(THUMBNAIL_ENCODER_SETTINGS.map(key => storage.getSettingByKey(key)));
+        const maxQuality = Number(quality?.value);
+        const settings: ThumbnailEncoderSettings = {
+          maxQuality: maxQuality >= 1 && maxQuality <= 100 ? Math.round(maxQuality) : DEFAULT_ENCODER.maxQuality,
+          chromaSubsampling: chroma?.value === '4:4:4' ? '4:4:4' : DEFAULT_ENCODER.chromaSubsampling,
+          sharpen: sharpen?.value === 'light' || sharpen?.value === 'strong' ? sharpen.value : DEFAULT_ENCODER.sharpen,
+        };
+        const version = createHash('md5').update(JSON.stringify({ PIPELINE_VERSION, ...settings })).digest('hex').slice(0, 8);
+        return { settings, version };
+      })().catch(error => {
+        this.encoder = null;
+        throw error;
+      });
     }
+    return this.encoder;
   }
 
-  private getCacheKey(originalPath: string, options: ThumbnailOptions): string {
-    const data = `${originalPath}-${options.size}-${options.quality}-${options.format || 'jpeg'}`;
+  /**
+   * Thumbnails of known photos are keyed by photo id, so moving or renaming the
+   * file (tier changes, trash, library migration) keeps its cached thumbnails.
+   * The encoder version in the key makes a settings change miss the cache.
+   */
+  private getCacheKey(originalPath: string, options: ThumbnailOptions, version: string, photoId?: string): string {
+    if (photoId) {
+      return `${photoId}-${options.size}-${options.quality}-${version}`;
+    }
+    const data = `${originalPath}-${options.size}-${options.quality}-${options.format || 'jpeg'}-${version}`;
     return createHash('md5').update(data).digest('hex');
   }
 
@@ -36,41 +105,73 @@ export class ThumbnailService {
 
   async generateThumbnail(
     originalPath: string,
-    options: ThumbnailOptions
+    options: ThumbnailOptions,
+    photoId?: string
   ): Promise<string> {
     const { size, quality, format = 'jpeg' } = options;
-    const cacheKey = this.getCacheKey(originalPath, options);
+    const { settings, version } = await this.loadEncoder();
+    const cacheKey = this.getCacheKey(originalPath, options, version, photoId);
     const cachePath = this.getCachePath(cacheKey, format);
 
     try {
-      // Check if cached thumbnail exists
-      await fs.access(cachePath);
+      // Check if cached thumbnail exists; an id-keyed one is stale once the file is edited,
+      // and is all there is once the original has been evicted to cold storage
+      const cached = await fs.stat(cachePath);
+      const original = photoId ? await fs.stat(originalPath).catch(() => null) : null;
+      if (original && original.mtimeMs > cached.mtimeMs) {
+        throw new Error('Cached thumbnail is stale');
+      }
       return cachePath;
     } catch {
       // Generate new thumbnail
       try {
-        let sharpInstance = sharp(originalPath)
+        await fs.mkdir(this.cacheDir, { recursive: true });
+        // Upright and in sRGB; the orientation tag and ICC profile do not survive the resize
+        let sharpInstance = displayImage(originalPath)
           .resize(size, size, {
             fit: 'cover',
             position: 'center',
             kernel: sharp.kernel.nearest // Faster resizing
-          })
-          .jpeg({ 
-            quality: Math.min(quality, 60), // Force lower quality for speed
-            progressive: false, // Faster loading
-            mozjpeg: true, // Better compression
-            optimiseScans: true // Optimize encoding
-          })
-
-        await sharpInstance.toFile(cachePath);
+          });
+        if (settings.sharpen !== 'off') {
+          sharpInstance = sharpInstance.sharpen({ sigma: SHARPEN_SIGMA[settings.sharpen] });
+        }
+        sharpInstance = sharpInstance.jpeg({
+          quality: Math.min(quality, settings.maxQuality),
+          chromaSubsampling: settings.chromaSubsampling,
+          progressive: false, // Faster loading
+          mozjpeg: true, // Better compression
+          optimiseScans: true // Optimize encoding
+        });
+
+        // Write beside the final path and rename, so the cache never holds a partial file
+        const partialPath = `${cachePath}.partial`;
+        await sharpInstance.toFile(partialPath);
+        await fs.rename(partialPath, cachePath);
+        if (photoId) {
+          await provenanceService.recordById(photoId, 'THUMBNAIL_GENERATED', `${size}px thumbnail generated`, { size, quality, format });
+        }
         return cachePath;
       } catch (error) {
+        await fs.rm(`${cachePath}.partial`, { force: true });
+        // An evicted original cannot be re-encoded; a thumbnail from earlier settings still shows it
+        const earlier = photoId ? await this.findEarlierVersion(photoId, options) : null;
+        if (earlier) return earlier;
         console.error('Failed to generate thumbnail:', error);
         throw new Error('Failed to generate thumbnail');
       }
     }
   }
 
+  private async findEarlierVersion(photoId: string, options: ThumbnailOptions): Promise<string | null> {
+    const prefix = `${photoId}-${options.size}-${options.quality}`;
+    const thumbnails = await this.getPhotoThumbnails(photoId);
+    return thumbnails.find(thumbnail => {
+      const name = path.basename(thumbnail, path.extname(thumbnail));
+      return name === prefix || name.startsWith(`${prefix}-`);
+    }) ?? null;
+  }
+
   async getThumbnailStream(
     originalPath: string, 
     options: ThumbnailOptions
@@ -83,6 +184,72 @@ export class ThumbnailService {
     return Readable.from(stream);
   }
 
+  /**
+   * Cached thumbnails of a photo, whatever sizes have been generated
+   */
+  async getPhotoThumbnails(photoId: string): Promise<string[]> {
+    try {
+      const files = await fs.readdir(this.cacheDir);
+      return files
+        .filter(file => file.startsWith(`${photoId}-`) && !file.endsWith('.partial'))
+        .map(file => path.join(this.cacheDir, file));
+    } catch {
+      return [];
+    }
+  }
+
+  /**
+   * Ids of the photos that have at least one cached thumbnail
+   */
+  async getCachedPhotoIds(): Promise<Set<string>> {
+    try {
+      const files = await fs.readdir(this.cacheDir);
+      const ids = new Set<string>();
+      for (const file of files) {
+        // Photo ids are UUIDs; md5 keys of unknown files have no dash
+        const match = file.match(/^([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})-/);
+        if (match && !file.endsWith('.partial')) ids.add(match[1]);
+      }
+      return ids;
+    } catch {
+      return new Set();
+    }
+  }
+
+  async removePhotoThumbnails(photoId: string): Promise<void> {
+    const thumbnails = await this.getPhotoThumbnails(photoId);
+    await Promise.all(thumbnails.map(thumbnail => fs.rm(thumbnail, { force: true })));
+  }
+
+  /**
+   * Pre-generate cached thumbnails, stopping between files when cancelled
+   */
+  async backfillThumbnails(
+    sources: ThumbnailSource[],
+    optionsList: ThumbnailOptions[],
+    token?: CancellationToken,
+    onProgress?: (completed: number, total: number) => void
+  ): Promise<{ generated: number; failed: number; cancelled: boolean }> {
+    let generated = 0;
+    let failed = 0;
+
+    for (let index = 0; index < sources.length; index++) {
+      if (token?.isCancelled) break;
+      onProgress?.(index, sources.length);
+
+      for (const options of optionsList) {
+        try {
+          await this.generateThumbnail(sources[index].path, options, sources[index].photoId);
+          generated++;
+        } catch {
+          failed++;
+        }
+      }
+    }
+
+    return { generated, failed, cancelled: token?.isCancelled ?? false };
+  }
+
   async clearCache(): Promise<void> {
     try {
       const files = await fs.readdir(this.cacheDir);
@@ -5,4 +5,8 @@ declare module 'exif' {
   
   const ExifImage: ExifImageConstructor;
   export default ExifImage;
-}
\ No newline at end of file
+}
+// Browser build of face-api with TensorFlow.js bundled; same API as the node build
+declare module '@vladmandic/face-api/dist/face-api.esm.js' {
+  export * from '@vladmandic/face-api';
+}
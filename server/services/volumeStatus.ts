@@ -0,0 +1,115 @@
+import fs from "fs/promises";
+import path from "path";
+import { storage } from "../storage";
+import { getLibraryRoot, libraryPath } from "../utils/libraryPaths";
+import { guessVolumeLabel } from "../utils/volumes";
+import type { FileVersion } from "@shared/schema";
+
+export type VolumeUse = 'library' | 'cold_storage' | 'watch_folder' | 'frame_target';
+
+export interface VolumePath {
+  path: string;
+  use: VolumeUse;
+  connected: boolean;
+}
+
+export interface VolumeStatus {
+  volumeLabel: string;
+  connected: boolean; // at least one of its paths can be read
+  paths: VolumePath[];
+  photoCount: number; // photos whose original is only on this volume
+}
+
+export interface VolumeStatusReport {
+  volumes: VolumeStatus[];
+  offlinePhotoIds: string[]; // photos on disconnected volumes; show them grayed out
+  checkedAt: Date;
+}
+
+// Where a photo's original is, and whether it can be read right now
+export interface OriginalLocation {
+  path: string;
+  volumeLabel: string;
+  available: boolean;
+}
+
+/**
+ * Tracks the volumes the library, cold storage copies, watch folders and
+ * frame destinations live on, so photos whose original is on a disconnected
+ * drive can be reported as offline rather than failing with IO errors.
+ */
+class VolumeStatusService {
+  async getVolumeStatus(): Promise<VolumeStatusReport> {
+    const volumes = new Map<string, VolumeStatus>();
+    const addPath = async (volumeLabel: string, folder: string, use: VolumeUse): Promise<VolumeStatus> => {
+      let volume = volumes.get(volumeLabel);
+      if (!volume) {
+        volume = { volumeLabel, connected: false, paths: [], photoCount: 0 };
+        volumes.set(volumeLabel, volume);
+      }
+      if (!volume.paths.some(entry => entry.path === folder && entry.use === use)) {
+        const connected = await this.exists(folder);
+        volume.paths.push({ path: folder, use, connected });
+        volume.connected = volume.connected || connected;
+      }
+      return volume;
+    };
+
+    const libraryRoot = getLibraryRoot();
+    const library = await addPath(guessVolumeLabel(libraryRoot), libraryRoot, 'library');
+
+    const evicted = await storage.getColdStorageCopies({ evicted: true });
+    for (const copy of await storage.getColdStorageCopies()) {
+      await addPath(copy.volumeLabel, copy.archiveRoot, 'cold_storage');
+    }
+    for (const folder of await storage.getWatchFolders()) {
+      await addPath(folder.volumeLabel ?? guessVolumeLabel(folder.path), folder.path, 'watch_folder');
+    }
+    for (const target of await storage.getFrameTargets()) {
+      await addPath(target.volumeLabel ?? guessVolumeLabel(target.destinationPath), target.destinationPath, 'frame_target');
+    }
+
+    const offlinePhotoIds: string[] = [];
+    const evictedIds = new Set<string>();
+    for (const copy of evicted) {
+      evictedIds.add(copy.photoId);
+      const volume = volumes.get(copy.volumeLabel)!;
+      volume.photoCount++;
+      if (!volume.connected) offlinePhotoIds.push(copy.photoId);
+    }
+
+    // Everything not evicted lives in the library
+    const libraryPhotoIds = (await storage.getAllFileVersions())
+      .map(photo => photo.id)
+      .filter(id => !evictedIds.has(id));
+    library.photoCount += libraryPhotoIds.length;
+    if (!library.connected) offlinePhotoIds.push(...libraryPhotoIds);
+
+    return { volumes: Array.from(volumes.values()), offlinePhotoIds, checkedAt: new Date() };
+  }
+
+  /**
+   * The file to read for a photo's original: the local file, or the cold
+   * storage copy once the local one has been evicted
+   */
+  async locateOriginal(photo: FileVersion): Promise<OriginalLocation> {
+    const copy = await storage.getColdStorageCopy(photo.id);
+    if (copy?.evictedAt) {
+      const archivePath = path.join(copy.archiveRoot, copy.relativePath);
+      return { path: archivePath, volumeLabel: copy.volumeLabel, available: await this.exists(archivePath) };
+    }
+    const localPath = libraryPath(photo.filePath);
+    return { path: localPath, volumeLabel: guessVolumeLabel(getLibraryRoot()), available: await this.exists(localPath) };
+  }
+
+  private async exists(filePath: string): Promise<boolean> {
+    try {
+      await fs.access(filePath);
+      return true;
+    } catch {
+      return false;
+    }
+  }
+}
+
+export const volumeStatusService = new VolumeStatusService();
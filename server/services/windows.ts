@@ -0,0 +1,100 @@
+import type { Response } from "express";
+import { z } from "zod";
+
+export const WINDOW_KINDS = ['gallery', 'viewer'] as const;
+export type WindowKind = typeof WINDOW_KINDS[number];
+
+// Events windows send each other; the server only routes them
+export const WINDOW_EVENTS = ['photo_shown', 'photo_updated'] as const;
+export type WindowEvent = typeof WINDOW_EVENTS[number];
+
+export const openPhotoWindowSchema = z.object({
+  photoId: z.string().min(1),
+  sourceWindowId: z.string().min(1).optional(),
+});
+
+export const publishWindowEventSchema = z.object({
+  event: z.enum(WINDOW_EVENTS),
+  data: z.record(z.unknown()).default({}),
+  sourceWindowId: z.string().min(1), // never echoed back to the sender
+  targetWindowId: z.string().min(1).optional(), // only this window; all others when omitted
+});
+
+export interface ConnectedWindow {
+  windowId: string;
+  kind: WindowKind;
+  connectedAt: Date;
+}
+
+export interface OpenPhotoWindowResult {
+  photoId: string;
+  windowId?: string; // the viewer that was told to show the photo
+  url?: string; // no viewer is open: the caller opens this one
+}
+
+/**
+ * Open app windows (the gallery and dedicated photo viewers), each with its
+ * own server-sent events stream. Shared state such as the working set and
+ * job queue already lives on the server; this routes window-to-window
+ * events, either to one window or to every window but the sender, so a
+ * viewer on a second monitor can follow and report back to the gallery.
+ */
+class WindowService {
+  private windows = new Map<string, ConnectedWindow & { res: Response }>();
+
+  connect(res: Response, windowId: string, kind: WindowKind): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    // A reload reconnects under the same id; the old stream is dead
+    this.windows.get(windowId)?.res.end();
+    this.windows.set(windowId, { windowId, kind, connectedAt: new Date(), res });
+    this.send(res, 'registered', { windowId, kind });
+    res.on('close', () => {
+      if (this.windows.get(windowId)?.res === res) this.windows.delete(windowId);
+    });
+  }
+
+  listWindows(): ConnectedWindow[] {
+    return Array.from(this.windows.values()).map(({ windowId, kind, connectedAt }) => ({ windowId, kind, connectedAt }));
+  }
+
+  /**
+   * Show a photo in a viewer window: the most recently opened viewer is
+   * reused, otherwise the caller gets the URL of a new one to open
+   */
+  openPhotoWindow(photoId: string, sourceWindowId?: string): OpenPhotoWindowResult {
+    const viewer = Array.from(this.windows.values())
+      .filter(entry => entry.kind === 'viewer' && entry.windowId !== sourceWindowId)
+      .sort((a, b) => b.connectedAt.getTime() - a.connectedAt.getTime())[0];
+    if (viewer) {
+      this.send(viewer.res, 'show_photo', { photoId, sourceWindowId });
+      return { photoId, windowId: viewer.windowId };
+    }
+    return { photoId, url: `/viewer/${encodeURIComponent(photoId)}` };
+  }
+
+  /**
+   * Deliver an event to one window, or to every window except the sender.
+   * Returns how many windows received it.
+   */
+  publish(event: WindowEvent, data: Record<string, unknown>, sourceWindowId: string, targetWindowId?: string): number {
+    const payload = { ...data, sourceWindowId };
+    let delivered = 0;
+    this.windows.forEach(entry => {
+      if (entry.windowId === sourceWindowId) return;
+      if (targetWindowId && entry.windowId !== targetWindowId) return;
+      this.send(entry.res, event, payload);
+      delivered++;
+    });
+    return delivered;
+  }
+
+  private send(res: Response, event: string, data: unknown): void {
+    res.write(`event: ${event}\ndata: ${JSON.stringify(data)}\n\n`);
+  }
+}
+
+export const windowService = new WindowService();
@@ -0,0 +1,90 @@
+import type { Response } from "express";
+import { z } from "zod";
+
+export const workingSetPushSchema = z.object({
+  photoIds: z.array(z.string()).min(1),
+});
+
+export const workingSetPopSchema = z.object({
+  photoIds: z.array(z.string()).min(1).optional(), // defaults to the photo pushed last
+});
+
+export interface WorkingSetState {
+  photoIds: string[]; // in the order they were pushed
+  revision: number; // bumped on every change, so views can ignore stale events
+  updatedAt: Date;
+}
+
+/**
+ * The in-progress "quick collection": photos gathered while browsing, kept
+ * in memory only and shared by every open window. Each change is broadcast
+ * as a "changed" server-sent event carrying the whole set. Save it as a
+ * selection to keep it past a restart.
+ */
+class WorkingSetService {
+  private photoIds: string[] = [];
+  private revision = 0;
+  private updatedAt = new Date();
+  private eventClients = new Set<Response>();
+
+  list(): WorkingSetState {
+    return { photoIds: [...this.photoIds], revision: this.revision, updatedAt: this.updatedAt };
+  }
+
+  /**
+   * Add photos to the end of the set; ones already in it keep their place
+   */
+  push(photoIds: string[]): WorkingSetState {
+    const present = new Set(this.photoIds);
+    const added = photoIds.filter(id => !present.has(id) && present.add(id));
+    if (added.length > 0) {
+      this.photoIds.push(...added);
+      this.changed();
+    }
+    return this.list();
+  }
+
+  /**
+   * Remove the given photos, or the one pushed last
+   */
+  pop(photoIds?: string[]): WorkingSetState {
+    const before = this.photoIds.length;
+    if (photoIds) {
+      const removed = new Set(photoIds);
+      this.photoIds = this.photoIds.filter(id => !removed.has(id));
+    } else {
+      this.photoIds.pop();
+    }
+    if (this.photoIds.length !== before) this.changed();
+    return this.list();
+  }
+
+  clear(): WorkingSetState {
+    if (this.photoIds.length > 0) {
+      this.photoIds = [];
+      this.changed();
+    }
+    return this.list();
+  }
+
+  addEventClient(res: Response): void {
+    res.writeHead(200, {
+      'Content-Type': 'text/event-stream',
+      'Cache-Control': 'no-cache',
+      Connection: 'keep-alive',
+    });
+    // New windows start from the current set
+    res.write(`event: changed\ndata: ${JSON.stringify(this.list())}\n\n`);
+    this.eventClients.add(res);
+    res.on('close', () => this.eventClients.delete(res));
+  }
+
+  private changed(): void {
+    this.revision++;
+    this.updatedAt = new Date();
+    const message = `event: changed\ndata: ${JSON.stringify(this.list())}\n\n`;
+    this.eventClients.forEach(client => client.write(message));
+  }
+}
+
+export const workingSetService = new WorkingSetService();
@@ -7,12 +7,31 @@ import {
   collectionPhotos,
   people,
   faces,
+  faceClusters,
   settings,
   events,
   relationships,
   locations,
   aiPrompts,
   globalTagLibrary,
+  frameTargets,
+  watchFolders,
+  coldStorageCopies,
+  duplicateDecisions,
+  photoSources,
+  selections,
+  selectionPhotos,
+  photoStacks,
+  photoStackMembers,
+  albumSuggestions,
+  bursts,
+  burstFrames,
+  jobs,
+  importSessions,
+  operationJournal,
+  externalAssets,
+  shares,
+  photoSearchIndex,
   type User, 
   type InsertUser,
   type MediaAsset,
@@ -29,6 +48,8 @@ import {
   type Face,
   type InsertFace,
   type Setting,
+  type GlobalTagLibrary,
+  type AnalyticsData,
   type InsertSetting,
   type Event,
   type InsertEvent,
@@ -37,10 +58,45 @@ import {
   type Location,
   type InsertLocation,
   type AIPrompt,
-  type InsertAIPrompt
+  type InsertAIPrompt,
+  type FrameTarget,
+  type InsertFrameTarget,
+  type WatchFolder,
+  type InsertWatchFolder,
+  type ColdStorageCopy,
+  type InsertColdStorageCopy,
+  type DuplicateDecision,
+  type InsertDuplicateDecision,
+  type PhotoSource,
+  type InsertPhotoSource,
+  type Selection,
+  type InsertSelection,
+  type PhotoStack,
+  type InsertPhotoStack,
+  type AlbumSuggestion,
+  type InsertAlbumSuggestion,
+  type Burst,
+  type InsertBurst,
+  type BurstFrame,
+  type BurstWithFrames,
+  type Job,
+  type FaceCluster,
+  type InsertJob,
+  type ImportSession,
+  type InsertImportSession,
+  type JournalEntry,
+  type InsertJournalEntry,
+  type ExternalAsset,
+  type InsertExternalAsset,
+  type Share,
+  type InsertShare,
+  type CombinedMetadata
 } from "@shared/schema";
 import { db } from "./db";
-import { eq, desc, and, count, sql } from "drizzle-orm";
+import { assertUnlocked, assertUpdateAllowed } from "./services/photoLock";
+import { guessVolumeLabel } from "./utils/volumes";
+import { getExifColumns } from "./utils/exifColumns";
+import { eq, desc, and, count, sql, inArray, notInArray, isNotNull, isNull } from "drizzle-orm";
 import path from "path";
 import crypto from 'crypto';
 
@@ -59,11 +115,16 @@ export interface IStorage {
   // File version methods
   createFileVersion(version: InsertFileVersion): Promise<FileVersion>;
   getFileVersion(id: string): Promise<FileVersion | undefined>;
-  getFileVersionsByAsset(assetId: string): Promise<FileVersion[]>;
-  getFileVersionsByTier(tier: "bronze" | "silver" | "gold"): Promise<FileVersion[]>;
-  getAllFileVersions(): Promise<FileVersion[]>;
+  getFileVersionByPath(filePath: string): Promise<FileVersion | undefined>;
+  getFileVersionsByAsset(assetId: string, includeDeleted?: boolean): Promise<FileVersion[]>;
+  getFileVersionsByTier(tier: "bronze" | "silver" | "gold", includeDeleted?: boolean): Promise<FileVersion[]>;
+  getAllFileVersions(includeDeleted?: boolean): Promise<FileVersion[]>;
+  getDeletedFileVersions(): Promise<FileVersion[]>;
   updateFileVersion(id: string, updates: Partial<FileVersion>): Promise<FileVersion>;
   updateFileVersionPerceptualHash(id: string, perceptualHash: string): Promise<void>;
+  rewriteFilePathPrefix(oldPrefix: string, newPrefix: string): Promise<number>;
+  backfillExifColumns(): Promise<number>;
+  backfillDaylightPhases(): Promise<number>;
   getFileByHash(hash: string): Promise<FileVersion | undefined>;
   deleteFileVersion(id: string): Promise<void>;
 
@@ -77,6 +138,16 @@ export interface IStorage {
     silverCount: number;
     goldCount: number;
   }>;
+  getIndexingCounts(): Promise<{
+    photos: number;
+    images: number;
+    metadata: number;
+    perceptualHash: number;
+    faces: number;
+    ai: number;
+  }>;
+  getImagePhotoIds(): Promise<string[]>;
+  getAnalytics(options: { weeks: number; months: number; limit: number }): Promise<AnalyticsData>;
 
   // Recent activity
   getRecentActivity(limit?: number): Promise<AssetHistory[]>;
@@ -86,29 +157,53 @@ export interface IStorage {
 
   // Collections methods
   createCollection(collection: InsertCollection): Promise<Collection>;
-  getCollections(): Promise<Collection[]>;
+  getCollections(includeDeleted?: boolean): Promise<Collection[]>;
   getCollection(id: string): Promise<Collection | undefined>;
   updateCollection(id: string, updates: Partial<Collection>): Promise<Collection>;
   deleteCollection(id: string): Promise<void>;
   addPhotoToCollection(collectionId: string, photoId: string): Promise<void>;
+  removePhotoFromCollection(collectionId: string, photoId: string): Promise<void>;
+  getCollectionPhotoIds(collectionId: string): Promise<string[]>;
   getCollectionPhotos(collectionId: string): Promise<Array<FileVersion & { mediaAsset: MediaAsset }>>;
+  setCollectionCover(collectionId: string, photo: FileVersion | null): Promise<Collection>;
+  reorderCollectionPhotos(collectionId: string, orderedPhotoIds: string[]): Promise<void>;
+  addPhotosToCollection(collectionId: string, photoIds: string[]): Promise<number>;
+  createCollectionWithPhotos(collection: InsertCollection, photoIds: string[]): Promise<Collection>;
+  duplicateCollection(id: string, name: string): Promise<Collection | undefined>;
+  mergeCollections(targetId: string, sourceIds: string[], deleteSources?: boolean): Promise<number>;
+  intersectCollections(name: string, firstId: string, secondId: string): Promise<Collection>;
+  getCollectionBySystemKey(systemKey: string): Promise<Collection | undefined>;
+  getSystemCollections(): Promise<Collection[]>;
+  replaceCollectionPhotos(collectionId: string, photoIds: string[]): Promise<number>;
+  getPersonAlbums(): Promise<Collection[]>;
 
   // People & Faces methods
   createPerson(person: InsertPerson): Promise<Person>;
-  getPeople(): Promise<Person[]>;
+  getPeople(includeDeleted?: boolean): Promise<Person[]>;
   updatePerson(id: string, updates: Partial<Person>): Promise<Person | undefined>;
   deletePerson(id: string): Promise<void>;
   getPersonPhotos?(personId: string): Promise<Array<FileVersion & { mediaAsset: MediaAsset }>>;
   createFace(face: InsertFace): Promise<Face>;
   getAllFaces(): Promise<Face[]>;
+  getAssignedFaceEmbeddings(model: string): Promise<Array<{ id: string; personId: string; embedding: number[] }>>;
   getFacesByPerson(personId: string): Promise<Face[]>;
   getFacesByPhoto(photoId: string): Promise<Face[]>;
+  getPhotosWithPeople(personIds: string[]): Promise<FileVersion[]>;
   getUnassignedFaces(): Promise<Face[]>;
+  getFaceClusters(): Promise<FaceCluster[]>;
+  getFaceCluster(id: string): Promise<FaceCluster | undefined>;
+  createFaceCluster(centroid: number[], faceIds: string[]): Promise<FaceCluster>;
+  updateFaceCluster(id: string, updates: Partial<FaceCluster>): Promise<FaceCluster | null>;
+  deleteFaceCluster(id: string): Promise<boolean>;
+  setFacesCluster(faceIds: string[], clusterId: string | null): Promise<void>;
   linkFaceToPerson(faceId: string, personId: string): Promise<void>;
   assignFaceToPerson?(faceId: string, personId: string): Promise<void>;
   updateFace(id: string, updates: Partial<Face>): Promise<Face>;
   deleteFace(id: string): Promise<void>;
   deleteFacesByPhoto(photoId: string): Promise<void>;
+  getLowConfidenceFaces(threshold: number, limit: number, offset: number): Promise<{ faces: Face[]; total: number }>;
+  confirmFaces(faceIds: string[]): Promise<number>;
+  deleteFaces(faceIds: string[]): Promise<Face[]>;
 
   // Settings methods
   getAllSettings(): Promise<Setting[]>;
@@ -143,6 +238,105 @@ export interface IStorage {
   getActiveAIPrompts(): Promise<AIPrompt[]>;
   resetAIPromptsToDefaults(): Promise<void>;
 
+  // Photo source methods
+  createPhotoSource(source: InsertPhotoSource): Promise<PhotoSource>;
+  getPhotoSources(mediaAssetId: string): Promise<PhotoSource[]>;
+  getAllPhotoSources(): Promise<PhotoSource[]>;
+
+  // Duplicate decision methods
+  getDuplicateDecisions(): Promise<DuplicateDecision[]>;
+  createDuplicateDecision(decision: InsertDuplicateDecision): Promise<DuplicateDecision>;
+  deleteDuplicateDecision(id: string): Promise<boolean>;
+
+  // Frame target methods
+  createFrameTarget(target: InsertFrameTarget): Promise<FrameTarget>;
+  getFrameTargets(): Promise<FrameTarget[]>;
+  getFrameTarget(id: string): Promise<FrameTarget | undefined>;
+  updateFrameTarget(id: string, updates: Partial<FrameTarget>): Promise<FrameTarget | null>;
+  deleteFrameTarget(id: string): Promise<boolean>;
+  createWatchFolder(folder: InsertWatchFolder): Promise<WatchFolder>;
+  getWatchFolders(): Promise<WatchFolder[]>;
+  getWatchFolder(id: string): Promise<WatchFolder | undefined>;
+  updateWatchFolder(id: string, updates: Partial<WatchFolder>): Promise<WatchFolder | null>;
+  deleteWatchFolder(id: string): Promise<boolean>;
+  createColdStorageCopy(copy: InsertColdStorageCopy): Promise<ColdStorageCopy>;
+  getColdStorageCopy(photoId: string): Promise<ColdStorageCopy | undefined>;
+  getColdStorageCopies(filters?: { evicted?: boolean; volumeLabel?: string }): Promise<ColdStorageCopy[]>;
+  updateColdStorageCopy(id: string, updates: Partial<ColdStorageCopy>): Promise<ColdStorageCopy | null>;
+
+  // Selection methods
+  createSelection(selection: InsertSelection, photoIds?: string[]): Promise<Selection>;
+  getSelections(): Promise<Array<Selection & { photoCount: number }>>;
+  getSelection(id: string): Promise<Selection | undefined>;
+  updateSelection(id: string, updates: Partial<Selection>): Promise<Selection | null>;
+  deleteSelection(id: string): Promise<boolean>;
+  addToSelection(selectionId: string, photoIds: string[]): Promise<number>;
+  removeFromSelection(selectionId: string, photoIds: string[]): Promise<number>;
+  clearSelection(selectionId: string): Promise<number>;
+  getSelectionPhotos(selectionId: string): Promise<Array<FileVersion & { mediaAsset: MediaAsset }>>;
+
+  // Photo stack methods
+  createPhotoStack(stack: InsertPhotoStack, mediaAssetIds: string[]): Promise<PhotoStack>;
+  getPhotoStacks(): Promise<Array<PhotoStack & { memberAssetIds: string[] }>>;
+  getPhotoStack(id: string): Promise<(PhotoStack & { memberAssetIds: string[] }) | undefined>;
+  getPhotoStackForAsset(mediaAssetId: string): Promise<(PhotoStack & { memberAssetIds: string[] }) | undefined>;
+  updatePhotoStack(id: string, updates: Partial<InsertPhotoStack>): Promise<PhotoStack | null>;
+  deletePhotoStack(id: string): Promise<boolean>;
+
+  // Burst methods
+  getBursts(): Promise<BurstWithFrames[]>;
+  getBurst(id: string): Promise<BurstWithFrames | undefined>;
+  replaceUnresolvedBursts(detected: Array<{ burst: InsertBurst; frames: Array<Pick<BurstFrame, 'photoId' | 'capturedAt'>> }>, keepIds: string[]): Promise<BurstWithFrames[]>;
+  updateBurst(id: string, updates: Partial<Burst>): Promise<Burst | null>;
+  updateBurstFrame(id: string, updates: Partial<BurstFrame>): Promise<BurstFrame | null>;
+
+  // Album suggestion methods
+  getAlbumSuggestions(status?: AlbumSuggestion['status']): Promise<AlbumSuggestion[]>;
+  getAlbumSuggestion(id: string): Promise<AlbumSuggestion | undefined>;
+  replacePendingAlbumSuggestions(suggestions: InsertAlbumSuggestion[], keepIds: string[]): Promise<AlbumSuggestion[]>;
+  updateAlbumSuggestion(id: string, updates: Partial<AlbumSuggestion>): Promise<AlbumSuggestion | null>;
+
+  // Background job methods
+  createJob(job: InsertJob): Promise<Job>;
+  getJob(id: string): Promise<Job | undefined>;
+  getJobs(limit?: number): Promise<Job[]>;
+  getNextQueuedJob(): Promise<Job | undefined>;
+  updateJob(id: string, updates: Partial<Job>): Promise<Job | null>;
+  requeueRunningJobs(): Promise<number>;
+  createImportSession(session: InsertImportSession): Promise<ImportSession>;
+  getImportSession(id: string): Promise<ImportSession | undefined>;
+  getImportSessions(limit?: number): Promise<ImportSession[]>;
+  getNextQueuedImportSession(): Promise<ImportSession | undefined>;
+  updateImportSession(id: string, updates: Partial<ImportSession>): Promise<ImportSession | null>;
+  requeueRunningImportSessions(): Promise<number>;
+
+  // Operation journal methods
+  createJournalEntry(entry: InsertJournalEntry): Promise<JournalEntry>;
+  getJournalEntries(limit?: number): Promise<JournalEntry[]>;
+  getLastActiveJournalEntry(): Promise<JournalEntry | undefined>;
+  getLastUndoneJournalEntry(): Promise<JournalEntry | undefined>;
+  setJournalEntryUndone(id: string, undoneAt: Date | null): Promise<JournalEntry | null>;
+  clearUndoneJournalEntries(): Promise<number>;
+
+  // External asset methods
+  getExternalAssets(source: ExternalAsset['source'], sourceKey: string): Promise<ExternalAsset[]>;
+  createExternalAsset(asset: InsertExternalAsset): Promise<ExternalAsset>;
+
+  // Share link methods
+  createShare(share: InsertShare): Promise<Share>;
+  getShares(): Promise<Share[]>;
+  getShare(id: string): Promise<Share | undefined>;
+  getShareByToken(token: string): Promise<Share | undefined>;
+  updateShare(id: string, updates: Partial<Share>): Promise<Share | null>;
+  recordShareView(id: string): Promise<Share | null>;
+
+  // Full-text search methods
+  searchPhotoText(tsquery: string, limit: number, offset: number): Promise<{
+    photos: Array<FileVersion & { mediaAsset: MediaAsset; rank: number }>;
+    total: number;
+  }>;
+  rebuildPhotoSearchIndex(): Promise<number>;
+
   // Location methods
   createLocation(location: InsertLocation): Promise<Location>;
   getLocations(): Promise<Location[]>;
@@ -171,6 +365,18 @@ export interface IStorage {
 
   updatePhoto(id: string, updates: any): Promise<any>;
   getAllTags(): Promise<string[]>;
+  getDeletedTags(): Promise<Array<{ tag: string; usageCount: number; deletedAt: Date }>>;
+  setTagDeleted(tag: string, deletedAt: Date | null): Promise<boolean>;
+  purgeTag(tag: string): Promise<boolean>;
+  getTagLibrary(): Promise<GlobalTagLibrary[]>;
+  createLibraryTag(tag: string, usageCount: number): Promise<GlobalTagLibrary>;
+  updateLibraryTag(id: string, updates: Partial<GlobalTagLibrary>): Promise<GlobalTagLibrary | null>;
+  getTagUsage(): Promise<Array<GlobalTagLibrary & { photoCount: number }>>;
+  rewriteTags(
+    photoTags: Array<{ photoId: string; tags: string[] }>,
+    renames: Array<{ id: string; tag: string }>,
+    merges: Array<{ sourceId: string; targetId: string }>
+  ): Promise<void>;
 
   // Smart collection methods
   createSmartCollection?(collection: InsertCollection): Promise<Collection>;
@@ -229,9 +435,11 @@ export class DatabaseStorage implements IStorage {
   }
 
   async createFileVersion(version: InsertFileVersion): Promise<FileVersion> {
+    // Panorama detection rides along in the metadata, so derive the flag from it
+    const isPanorama = version.isPanorama ?? Boolean((version.metadata as CombinedMetadata | null)?.panorama);
     const [fileVersion] = await db
       .insert(fileVersions)
-      .values(version)
+      .values({ ...version, isPanorama, ...getExifColumns(version.metadata) })
       .returning();
     return fileVersion;
   }
@@ -241,35 +449,111 @@ export class DatabaseStorage implements IStorage {
     return version || undefined;
   }
 
-  async getFileVersionsByAsset(assetId: string): Promise<FileVersion[]> {
+  async getFileVersionByPath(filePath: string): Promise<FileVersion | undefined> {
+    const [version] = await db.select().from(fileVersions).where(eq(fileVersions.filePath, filePath));
+    return version || undefined;
+  }
+
+  // Photos in the trash are left out unless asked for
+  async getFileVersionsByAsset(assetId: string, includeDeleted = false): Promise<FileVersion[]> {
     return await db
       .select()
       .from(fileVersions)
-      .where(eq(fileVersions.mediaAssetId, assetId))
+      .where(and(eq(fileVersions.mediaAssetId, assetId), includeDeleted ? undefined : isNull(fileVersions.deletedAt)))
       .orderBy(desc(fileVersions.createdAt));
   }
 
-  async getFileVersionsByTier(tier: "silver" | "gold"): Promise<FileVersion[]> {
+  async getFileVersionsByTier(tier: "bronze" | "silver" | "gold", includeDeleted = false): Promise<FileVersion[]> {
     return await db
       .select()
       .from(fileVersions)
-      .where(eq(fileVersions.tier, tier))
+      .where(and(eq(fileVersions.tier, tier), includeDeleted ? undefined : isNull(fileVersions.deletedAt)))
       .orderBy(desc(fileVersions.createdAt));
   }
 
-  async getAllFileVersions(): Promise<FileVersion[]> {
-    return await db.select().from(fileVersions).orderBy(desc(fileVersions.createdAt));
+  async getAllFileVersions(includeDeleted = false): Promise<FileVersion[]> {
+    return await db
+      .select()
+      .from(fileVersions)
+      .where(includeDeleted ? undefined : isNull(fileVersions.deletedAt))
+      .orderBy(desc(fileVersions.createdAt));
+  }
+
+  async getDeletedFileVersions(): Promise<FileVersion[]> {
+    return await db
+      .select()
+      .from(fileVersions)
+      .where(isNotNull(fileVersions.deletedAt))
+      .orderBy(desc(fileVersions.deletedAt));
   }
 
   async updateFileVersion(id: string, updates: Partial<FileVersion>): Promise<FileVersion> {
+    const current = await this.getFileVersion(id);
+    if (current) {
+      assertUpdateAllowed(current, updates);
+    }
     const [updated] = await db
       .update(fileVersions)
-      .set(updates)
+      .set(updates.metadata !== undefined ? { ...updates, ...getExifColumns(updates.metadata) } : updates)
       .where(eq(fileVersions.id, id))
       .returning();
     return updated;
   }
 
+  /**
+   * Fill the EXIF columns of photos stored before they existed. Derived data
+   * only, so locked photos are included.
+   */
+  async backfillExifColumns(): Promise<number> {
+    const pending = await db
+      .select({ id: fileVersions.id, metadata: fileVersions.metadata })
+      .from(fileVersions)
+      .where(and(isNull(fileVersions.takenAt), sql`${fileVersions.metadata}->'exif' is not null`));
+
+    let updated = 0;
+    for (const row of pending) {
+      const columns = getExifColumns(row.metadata);
+      if (Object.values(columns).every(value => value === null)) continue;
+      await db.update(fileVersions).set(columns).where(eq(fileVersions.id, row.id));
+      updated++;
+    }
+    return updated;
+  }
+
+  /**
+   * Classify the daylight of photos with a capture date and position stored
+   * before the column existed
+   */
+  async backfillDaylightPhases(): Promise<number> {
+    const pending = await db
+      .select({ id: fileVersions.id, metadata: fileVersions.metadata })
+      .from(fileVersions)
+      .where(and(
+        isNull(fileVersions.daylightPhase),
+        isNotNull(fileVersions.captureDate),
+        isNotNull(fileVersions.gpsLatitude),
+        isNotNull(fileVersions.gpsLongitude)
+      ));
+
+    let updated = 0;
+    for (const row of pending) {
+      const { daylightPhase } = getExifColumns(row.metadata);
+      if (!daylightPhase) continue;
+      await db.update(fileVersions).set({ daylightPhase }).where(eq(fileVersions.id, row.id));
+      updated++;
+    }
+    return updated;
+  }
+
+  async rewriteFilePathPrefix(oldPrefix: string, newPrefix: string): Promise<number> {
+    const updated = await db
+      .update(fileVersions)
+      .set({ filePath: sql`${newPrefix} || substr(${fileVersions.filePath}, ${oldPrefix.length + 1})` })
+      .where(sql`starts_with(${fileVersions.filePath}, ${oldPrefix})`)
+      .returning({ id: fileVersions.id });
+    return updated.length;
+  }
+
   async updateFileVersionPerceptualHash(id: string, perceptualHash: string): Promise<void> {
     await db
       .update(fileVersions)
@@ -283,6 +567,10 @@ export class DatabaseStorage implements IStorage {
   }
 
   async deleteFileVersion(id: string): Promise<void> {
+    const current = await this.getFileVersion(id);
+    if (current) {
+      assertUnlocked(current, 'delete');
+    }
     await db.delete(fileVersions).where(eq(fileVersions.id, id));
   }
 
@@ -311,10 +599,12 @@ export class DatabaseStorage implements IStorage {
     const totalPhotosResult = await db.select({ count: count() }).from(mediaAssets);
     const totalPhotos = totalPhotosResult[0]?.count || 0;
 
-    const silverResult = await db.select({ count: count() }).from(fileVersions).where(eq(fileVersions.tier, "silver"));
+    const silverResult = await db.select({ count: count() }).from(fileVersions)
+      .where(and(eq(fileVersions.tier, "silver"), isNull(fileVersions.deletedAt)));
     const silverCount = silverResult[0]?.count || 0;
 
-    const goldResult = await db.select({ count: count() }).from(fileVersions).where(eq(fileVersions.tier, "gold"));
+    const goldResult = await db.select({ count: count() }).from(fileVersions)
+      .where(and(eq(fileVersions.tier, "gold"), isNull(fileVersions.deletedAt)));
     const goldCount = goldResult[0]?.count || 0;
 
     return {
@@ -324,6 +614,133 @@ export class DatabaseStorage implements IStorage {
     };
   }
 
+  /**
+   * How many photos each background indexing step has handled. Hashes and
+   * face detection only apply to images.
+   */
+  async getIndexingCounts() {
+    const isImage = sql`${fileVersions.mimeType} like 'image/%'`;
+    const [row] = await db.select({
+      photos: count(),
+      images: sql<number>`count(*) filter (where ${isImage})`.mapWith(Number),
+      metadata: sql<number>`count(*) filter (where ${fileVersions.metadata}->'exif' is not null)`.mapWith(Number),
+      perceptualHash: sql<number>`count(*) filter (where ${isImage} and ${fileVersions.perceptualHash} is not null)`.mapWith(Number),
+      faces: sql<number>`count(*) filter (where ${isImage} and ${fileVersions.metadata}->'faceDetection' is not null)`.mapWith(Number),
+      ai: sql<number>`count(*) filter (where ${fileVersions.metadata}->'ai' is not null)`.mapWith(Number),
+    }).from(fileVersions);
+    return row ?? { photos: 0, images: 0, metadata: 0, perceptualHash: 0, faces: 0, ai: 0 };
+  }
+
+  async getAnalytics(options: { weeks: number; months: number; limit: number }): Promise<AnalyticsData> {
+    const uploads = await db.execute(sql`
+      SELECT to_char(week, 'YYYY-MM-DD') AS week, COALESCE(counts.count, 0)::int AS count
+      FROM generate_series(
+        date_trunc('week', now()::timestamp) - make_interval(weeks => ${options.weeks - 1}),
+        date_trunc('week', now()::timestamp),
+        interval '1 week'
+      ) AS week
+      LEFT JOIN (
+        SELECT date_trunc('week', created_at) AS started, COUNT(*) AS count
+        FROM media_assets
+        GROUP BY 1
+      ) counts ON counts.started = week
+      ORDER BY week
+    `);
+
+    const tiers = await db.execute(sql`
+      SELECT tier, COUNT(*)::int AS count, COALESCE(SUM(file_size), 0)::bigint AS bytes
+      FROM file_versions
+      WHERE deleted_at IS NULL
+      GROUP BY tier
+      ORDER BY tier
+    `);
+
+    const cameras = await db.execute(sql`
+      SELECT camera, COUNT(DISTINCT media_asset_id)::int AS count
+      FROM file_versions
+      WHERE deleted_at IS NULL AND camera IS NOT NULL
+      GROUP BY camera
+      ORDER BY count DESC, camera
+      LIMIT ${options.limit}
+    `);
+
+    const topPeople = await db.execute(sql`
+      SELECT p.id AS person_id, p.name, COUNT(DISTINCT f.photo_id)::int AS photo_count
+      FROM faces f
+      JOIN people p ON p.id = f.person_id
+      JOIN file_versions fv ON fv.id = f.photo_id
+      WHERE p.deleted_at IS NULL AND fv.deleted_at IS NULL
+      GROUP BY p.id, p.name
+      ORDER BY photo_count DESC, p.name
+      LIMIT ${options.limit}
+    `);
+
+    // The most used tags of the period, counted per capture month
+    const tagUsage = await db.execute(sql`
+      WITH tagged AS (
+        SELECT lower(t.tag) AS tag, to_char(COALESCE(fv.taken_at, fv.created_at), 'YYYY-MM') AS month
+        FROM file_versions fv
+        CROSS JOIN LATERAL jsonb_array_elements_text(
+          CASE WHEN jsonb_typeof(fv.metadata->'ai'->'aiTags') = 'array' THEN fv.metadata->'ai'->'aiTags' ELSE '[]'::jsonb END
+        ) AS t(tag)
+        WHERE fv.deleted_at IS NULL
+          AND COALESCE(fv.taken_at, fv.created_at) >= date_trunc('month', now()) - make_interval(months => ${options.months - 1})
+      ),
+      top_tags AS (
+        SELECT tag FROM tagged GROUP BY tag ORDER BY COUNT(*) DESC, tag LIMIT ${options.limit}
+      )
+      SELECT month, tag, COUNT(*)::int AS count
+      FROM tagged
+      WHERE tag IN (SELECT tag FROM top_tags)
+      GROUP BY month, tag
+      ORDER BY month, count DESC
+    `);
+
+    const [coverage] = (await db.execute(sql`
+      SELECT
+        COUNT(*)::int AS images,
+        COUNT(*) FILTER (WHERE fv.metadata->'faceDetection' IS NOT NULL OR EXISTS (SELECT 1 FROM faces f WHERE f.photo_id = fv.id))::int AS scanned,
+        COUNT(*) FILTER (WHERE EXISTS (SELECT 1 FROM faces f WHERE f.photo_id = fv.id AND NOT f.ignored))::int AS with_faces,
+        COUNT(*) FILTER (WHERE EXISTS (SELECT 1 FROM faces f WHERE f.photo_id = fv.id AND f.person_id IS NOT NULL))::int AS with_named_faces
+      FROM file_versions fv
+      WHERE fv.deleted_at IS NULL AND fv.mime_type LIKE 'image/%'
+    `)).rows as Array<{ images: number; scanned: number; with_faces: number; with_named_faces: number }>;
+
+    const tierRows = tiers.rows as Array<{ tier: string; count: number; bytes: string | number }>;
+    const totalVersions = tierRows.reduce((sum, row) => sum + Number(row.count), 0);
+    const images = Number(coverage?.images ?? 0);
+    const scanned = Number(coverage?.scanned ?? 0);
+    return {
+      uploadsPerWeek: (uploads.rows as Array<{ week: string; count: number }>).map(row => ({ week: row.week, count: Number(row.count) })),
+      tierDistribution: tierRows.map(row => ({
+        tier: row.tier,
+        count: Number(row.count),
+        percentage: totalVersions > 0 ? Math.round((Number(row.count) / totalVersions) * 100) : 0,
+      })),
+      storageByTier: tierRows.map(row => ({ tier: row.tier, bytes: Number(row.bytes) })),
+      topCameras: (cameras.rows as Array<{ camera: string; count: number }>).map(row => ({ camera: row.camera, count: Number(row.count) })),
+      topPeople: (topPeople.rows as Array<{ person_id: string; name: string; photo_count: number }>)
+        .map(row => ({ personId: row.person_id, name: row.name, photoCount: Number(row.photo_count) })),
+      tagUsageOverTime: (tagUsage.rows as Array<{ month: string; tag: string; count: number }>)
+        .map(row => ({ month: row.month, tag: row.tag, count: Number(row.count) })),
+      faceDetectionCoverage: {
+        images,
+        scanned,
+        withFaces: Number(coverage?.with_faces ?? 0),
+        withNamedFaces: Number(coverage?.with_named_faces ?? 0),
+        percentage: images > 0 ? Math.round((scanned / images) * 100) : 0,
+      },
+    };
+  }
+
+  async getImagePhotoIds(): Promise<string[]> {
+    const rows = await db
+      .select({ id: fileVersions.id })
+      .from(fileVersions)
+      .where(sql`${fileVersions.mimeType} like 'image/%'`);
+    return rows.map(row => row.id);
+  }
+
   async getRecentActivity(limit = 10): Promise<AssetHistory[]> {
     return await db
       .select()
@@ -337,6 +754,7 @@ export class DatabaseStorage implements IStorage {
       .select()
       .from(fileVersions)
       .leftJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id))
+      .where(isNull(fileVersions.deletedAt))
       .orderBy(desc(fileVersions.createdAt))
       .limit(limit);
 
@@ -358,8 +776,13 @@ export class DatabaseStorage implements IStorage {
     return newCollection;
   }
 
-  async getCollections(): Promise<Collection[]> {
-    return await db.select().from(collections).orderBy(desc(collections.createdAt));
+  // Soft-deleted albums are left out unless asked for
+  async getCollections(includeDeleted = false): Promise<Collection[]> {
+    return await db
+      .select()
+      .from(collections)
+      .where(includeDeleted ? undefined : isNull(collections.deletedAt))
+      .orderBy(desc(collections.createdAt));
   }
 
   async updateCollection(id: string, updates: Partial<Collection>): Promise<Collection> {
@@ -388,17 +811,39 @@ export class DatabaseStorage implements IStorage {
     await db.insert(collectionPhotos).values({
       collectionId,
       photoId,
+      sortOrder: await this.nextSortOrder(db, collectionId),
     });
   }
 
+  async removePhotoFromCollection(collectionId: string, photoId: string): Promise<void> {
+    await db
+      .delete(collectionPhotos)
+      .where(and(eq(collectionPhotos.collectionId, collectionId), eq(collectionPhotos.photoId, photoId)));
+    // A cover has to be in the album
+    await db
+      .update(collections)
+      .set({ coverPhotoId: null, coverPhoto: null })
+      .where(and(eq(collections.id, collectionId), eq(collections.coverPhotoId, photoId)));
+  }
+
+  // Saved order first, then photos never placed by hand, newest first
+  async getCollectionPhotoIds(collectionId: string): Promise<string[]> {
+    const rows = await db
+      .select({ photoId: collectionPhotos.photoId })
+      .from(collectionPhotos)
+      .where(eq(collectionPhotos.collectionId, collectionId))
+      .orderBy(...this.collectionOrder());
+    return rows.map(row => row.photoId);
+  }
+
   async getCollectionPhotos(collectionId: string): Promise<Array<FileVersion & { mediaAsset: MediaAsset }>> {
     const photos = await db
       .select()
       .from(collectionPhotos)
       .leftJoin(fileVersions, eq(collectionPhotos.photoId, fileVersions.id))
       .leftJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id))
-      .where(eq(collectionPhotos.collectionId, collectionId))
-      .orderBy(desc(collectionPhotos.addedAt));
+      .where(and(eq(collectionPhotos.collectionId, collectionId), isNull(fileVersions.deletedAt)))
+      .orderBy(...this.collectionOrder());
 
     return photos.map(row => ({
       ...row.file_versions!,
@@ -406,6 +851,217 @@ export class DatabaseStorage implements IStorage {
     }));
   }
 
+  async setCollectionCover(collectionId: string, photo: FileVersion | null): Promise<Collection> {
+    return this.updateCollection(collectionId, { coverPhotoId: photo?.id ?? null, coverPhoto: photo?.filePath ?? null });
+  }
+
+  /**
+   * Save a manual order: the given photos first, in that order, then the
+   * album's other photos in the order they were shown in
+   */
+  async reorderCollectionPhotos(collectionId: string, orderedPhotoIds: string[]): Promise<void> {
+    const current = await this.getCollectionPhotoIds(collectionId);
+    const members = new Set(current);
+    const placed = Array.from(new Set(orderedPhotoIds)).filter(photoId => members.has(photoId));
+    const placedIds = new Set(placed);
+    const order = [...placed, ...current.filter(photoId => !placedIds.has(photoId))];
+
+    await db.transaction(async (tx) => {
+      for (let index = 0; index < order.length; index++) {
+        await tx
+          .update(collectionPhotos)
+          .set({ sortOrder: index })
+          .where(and(eq(collectionPhotos.collectionId, collectionId), eq(collectionPhotos.photoId, order[index])));
+      }
+      await tx.update(collections).set({ updatedAt: new Date() }).where(eq(collections.id, collectionId));
+    });
+  }
+
+  private collectionOrder() {
+    return [sql`${collectionPhotos.sortOrder} ASC NULLS LAST`, desc(collectionPhotos.addedAt)];
+  }
+
+  /**
+   * Position for a photo added to a collection: after the last one in an
+   * album that has been ordered by hand, unset otherwise
+   */
+  private async nextSortOrder(executor: Pick<typeof db, 'select'>, collectionId: string): Promise<number | null> {
+    const [row] = await executor
+      .select({ last: sql<number | null>`max(${collectionPhotos.sortOrder})` })
+      .from(collectionPhotos)
+      .where(eq(collectionPhotos.collectionId, collectionId));
+    return row?.last == null ? null : Number(row.last) + 1;
+  }
+
+  /**
+   * Add many photos to a collection in one transaction, skipping photos that are
+   * already members. Returns the number of photos actually added.
+   */
+  async addPhotosToCollection(collectionId: string, photoIds: string[]): Promise<number> {
+    if (photoIds.length === 0) return 0;
+
+    return await db.transaction(async (tx) => {
+      const existing = await tx
+        .select({ photoId: collectionPhotos.photoId })
+        .from(collectionPhotos)
+        .where(eq(collectionPhotos.collectionId, collectionId));
+      const existingIds = new Set(existing.map(row => row.photoId));
+      const toAdd = Array.from(new Set(photoIds)).filter(id => !existingIds.has(id));
+
+      if (toAdd.length > 0) {
+        const first = await this.nextSortOrder(tx, collectionId);
+        await tx.insert(collectionPhotos).values(toAdd.map((photoId, index) => ({
+          collectionId,
+          photoId,
+          sortOrder: first === null ? null : first + index,
+        })));
+      }
+      return toAdd.length;
+    });
+  }
+
+  /**
+   * Create a regular collection and populate it with a snapshot of photo ids in one transaction.
+   */
+  async createCollectionWithPhotos(collection: InsertCollection, photoIds: string[]): Promise<Collection> {
+    return await db.transaction(async (tx) => {
+      const [newCollection] = await tx.insert(collections).values(collection).returning();
+      const uniqueIds = Array.from(new Set(photoIds));
+      if (uniqueIds.length > 0) {
+        await tx.insert(collectionPhotos).values(uniqueIds.map(photoId => ({ collectionId: newCollection.id, photoId })));
+      }
+      return newCollection;
+    });
+  }
+
+  /**
+   * Copy a collection (including smart rules and membership) under a new name.
+   */
+  async duplicateCollection(id: string, name: string): Promise<Collection | undefined> {
+    return await db.transaction(async (tx) => {
+      const [source] = await tx.select().from(collections).where(eq(collections.id, id));
+      if (!source) return undefined;
+
+      const [copy] = await tx
+        .insert(collections)
+        .values({
+          name,
+          description: source.description,
+          isPublic: source.isPublic,
+          coverPhoto: source.coverPhoto,
+          coverPhotoId: source.coverPhotoId,
+          isSmartCollection: source.isSmartCollection,
+          smartRules: source.smartRules,
+        })
+        .returning();
+
+      const members = await tx
+        .select({ photoId: collectionPhotos.photoId, sortOrder: collectionPhotos.sortOrder })
+        .from(collectionPhotos)
+        .where(eq(collectionPhotos.collectionId, id));
+      const byPhoto = new Map(members.map(row => [row.photoId, row.sortOrder]));
+      if (byPhoto.size > 0) {
+        await tx.insert(collectionPhotos).values(
+          Array.from(byPhoto.entries()).map(([photoId, sortOrder]) => ({ collectionId: copy.id, photoId, sortOrder }))
+        );
+      }
+
+      return copy;
+    });
+  }
+
+  /**
+   * Union the photos of the source collections into the target collection.
+   * Optionally deletes the sources afterwards. Returns the number of photos added.
+   */
+  async mergeCollections(targetId: string, sourceIds: string[], deleteSources = false): Promise<number> {
+    const sources = sourceIds.filter(id => id !== targetId);
+    if (sources.length === 0) return 0;
+
+    return await db.transaction(async (tx) => {
+      const sourceMembers = await tx
+        .select({ photoId: collectionPhotos.photoId })
+        .from(collectionPhotos)
+        .where(inArray(collectionPhotos.collectionId, sources));
+      const targetMembers = await tx
+        .select({ photoId: collectionPhotos.photoId })
+        .from(collectionPhotos)
+        .where(eq(collectionPhotos.collectionId, targetId));
+
+      const existingIds = new Set(targetMembers.map(row => row.photoId));
+      const toAdd = Array.from(new Set(sourceMembers.map(row => row.photoId))).filter(id => !existingIds.has(id));
+      if (toAdd.length > 0) {
+        const first = await this.nextSortOrder(tx, targetId);
+        await tx.insert(collectionPhotos).values(toAdd.map((photoId, index) => ({
+          collectionId: targetId,
+          photoId,
+          sortOrder: first === null ? null : first + index,
+        })));
+      }
+
+      if (deleteSources) {
+        await tx.delete(collectionPhotos).where(inArray(collectionPhotos.collectionId, sources));
+        await tx.delete(collections).where(inArray(collections.id, sources));
+      }
+
+      return toAdd.length;
+    });
+  }
+
+  /**
+   * Create a new collection containing only the photos present in both collections.
+   */
+  async intersectCollections(name: string, firstId: string, secondId: string): Promise<Collection> {
+    return await db.transaction(async (tx) => {
+      const first = await tx
+        .select({ photoId: collectionPhotos.photoId })
+        .from(collectionPhotos)
+        .where(eq(collectionPhotos.collectionId, firstId));
+      const second = await tx
+        .select({ photoId: collectionPhotos.photoId })
+        .from(collectionPhotos)
+        .where(eq(collectionPhotos.collectionId, secondId));
+
+      const secondIds = new Set(second.map(row => row.photoId));
+      const shared = Array.from(new Set(first.map(row => row.photoId))).filter(id => secondIds.has(id));
+
+      const [collection] = await tx.insert(collections).values({ name }).returning();
+      if (shared.length > 0) {
+        await tx.insert(collectionPhotos).values(shared.map(photoId => ({ collectionId: collection.id, photoId })));
+      }
+
+      return collection;
+    });
+  }
+
+  async getCollectionBySystemKey(systemKey: string): Promise<Collection | undefined> {
+    const [collection] = await db.select().from(collections).where(eq(collections.systemKey, systemKey));
+    return collection || undefined;
+  }
+
+  async getSystemCollections(): Promise<Collection[]> {
+    return await db.select().from(collections).where(isNotNull(collections.systemKey)).orderBy(collections.name);
+  }
+
+  async getPersonAlbums(): Promise<Collection[]> {
+    return await db.select().from(collections).where(isNotNull(collections.personAlbum));
+  }
+
+  /**
+   * Replace a collection's membership with the given photo ids in one transaction.
+   */
+  async replaceCollectionPhotos(collectionId: string, photoIds: string[]): Promise<number> {
+    const uniqueIds = Array.from(new Set(photoIds));
+    await db.transaction(async (tx) => {
+      await tx.delete(collectionPhotos).where(eq(collectionPhotos.collectionId, collectionId));
+      if (uniqueIds.length > 0) {
+        await tx.insert(collectionPhotos).values(uniqueIds.map(photoId => ({ collectionId, photoId })));
+      }
+      await tx.update(collections).set({ updatedAt: new Date() }).where(eq(collections.id, collectionId));
+    });
+    return uniqueIds.length;
+  }
+
   // People & Faces methods
   async createPerson(person: InsertPerson): Promise<Person> {
     // Convert birthdate string to Date if provided
@@ -421,9 +1077,14 @@ export class DatabaseStorage implements IStorage {
     return newPerson;
   }
 
-  async getPeople(): Promise<Person[]> {
+  // Soft-deleted people are left out unless asked for
+  async getPeople(includeDeleted = false): Promise<Person[]> {
     try {
-      return await db.select().from(people).orderBy(desc(people.createdAt));
+      return await db
+        .select()
+        .from(people)
+        .where(includeDeleted ? undefined : isNull(people.deletedAt))
+        .orderBy(desc(people.createdAt));
     } catch (error) {
       console.error('Error fetching people:', error);
       // Return empty array on database connection errors to prevent UI crashes
@@ -455,6 +1116,9 @@ export class DatabaseStorage implements IStorage {
     // First unassign all faces from this person
     await db.update(faces).set({ personId: null }).where(eq(faces.personId, id));
 
+    // Relationships cannot outlive either side
+    await db.delete(relationships).where(sql`${relationships.person1Id} = ${id} OR ${relationships.person2Id} = ${id}`);
+
     // Then delete the person
     await db.delete(people).where(eq(people.id, id));
   }
@@ -478,6 +1142,16 @@ export class DatabaseStorage implements IStorage {
     return await db.select().from(faces);
   }
 
+  async getAssignedFaceEmbeddings(model: string): Promise<Array<{ id: string; personId: string; embedding: number[] }>> {
+    const rows = await db
+      .select({ id: faces.id, personId: faces.personId, embedding: faces.embedding })
+      .from(faces)
+      .where(and(isNotNull(faces.personId), eq(faces.ignored, false), eq(faces.embeddingModel, model)));
+    return rows
+      .filter(row => Array.isArray(row.embedding))
+      .map(row => ({ id: row.id, personId: row.personId!, embedding: row.embedding as number[] }));
+  }
+
   async getFacesByPerson(personId: string): Promise<Face[]> {
     try {
       return await db.select().from(faces).where(eq(faces.personId, personId));
@@ -492,6 +1166,20 @@ export class DatabaseStorage implements IStorage {
     return await db.select().from(faces).where(eq(faces.photoId, photoId));
   }
 
+  // Every version of the assets these people appear in, not just the version their faces were found on
+  async getPhotosWithPeople(personIds: string[]): Promise<FileVersion[]> {
+    if (personIds.length === 0) return [];
+    const assetIds = db
+      .select({ id: fileVersions.mediaAssetId })
+      .from(faces)
+      .innerJoin(fileVersions, eq(faces.photoId, fileVersions.id))
+      .where(inArray(faces.personId, personIds));
+    return await db
+      .select()
+      .from(fileVersions)
+      .where(and(inArray(fileVersions.mediaAssetId, assetIds), isNull(fileVersions.deletedAt)));
+  }
+
   async linkFaceToPerson(faceId: string, personId: string): Promise<void> {
     await db
       .update(faces)
@@ -517,6 +1205,44 @@ export class DatabaseStorage implements IStorage {
     return await db.select().from(faces).where(sql`${faces.personId} IS NULL AND ${faces.ignored} = false`);
   }
 
+  async getFaceClusters(): Promise<FaceCluster[]> {
+    return await db.select().from(faceClusters).orderBy(desc(faceClusters.faceCount));
+  }
+
+  async getFaceCluster(id: string): Promise<FaceCluster | undefined> {
+    const [cluster] = await db.select().from(faceClusters).where(eq(faceClusters.id, id));
+    return cluster || undefined;
+  }
+
+  async createFaceCluster(centroid: number[], faceIds: string[]): Promise<FaceCluster> {
+    return db.transaction(async (tx) => {
+      const [cluster] = await tx.insert(faceClusters).values({ centroid, faceCount: faceIds.length }).returning();
+      if (faceIds.length > 0) {
+        await tx.update(faces).set({ clusterId: cluster.id }).where(inArray(faces.id, faceIds));
+      }
+      return cluster;
+    });
+  }
+
+  async updateFaceCluster(id: string, updates: Partial<FaceCluster>): Promise<FaceCluster | null> {
+    const [updated] = await db
+      .update(faceClusters)
+      .set({ ...updates, updatedAt: new Date() })
+      .where(eq(faceClusters.id, id))
+      .returning();
+    return updated || null;
+  }
+
+  async deleteFaceCluster(id: string): Promise<boolean> {
+    const result = await db.delete(faceClusters).where(eq(faceClusters.id, id)).returning();
+    return result.length > 0;
+  }
+
+  async setFacesCluster(faceIds: string[], clusterId: string | null): Promise<void> {
+    if (faceIds.length === 0) return;
+    await db.update(faces).set({ clusterId }).where(inArray(faces.id, faceIds));
+  }
+
   async updatePersonFaceCount(personId: string, faceCount: number): Promise<void> {
     await db
       .update(people)
@@ -562,6 +1288,46 @@ export class DatabaseStorage implements IStorage {
     await db.delete(faces).where(eq(faces.id, id));
   }
 
+  // Unnamed, unreviewed faces on photos not in the trash, scored under the threshold or found by the heuristic detector
+  async getLowConfidenceFaces(threshold: number, limit: number, offset: number): Promise<{ faces: Face[]; total: number }> {
+    const condition = and(
+      isNull(faces.personId),
+      eq(faces.ignored, false),
+      isNull(faces.reviewedAt),
+      isNull(fileVersions.deletedAt),
+      sql`(${faces.confidence} < ${threshold} OR ${faces.detector} = 'heuristic')`
+    );
+    const [{ total }] = await db
+      .select({ total: count() })
+      .from(faces)
+      .innerJoin(fileVersions, eq(faces.photoId, fileVersions.id))
+      .where(condition);
+    const rows = await db
+      .select({ face: faces })
+      .from(faces)
+      .innerJoin(fileVersions, eq(faces.photoId, fileVersions.id))
+      .where(condition)
+      .orderBy(faces.confidence, faces.createdAt)
+      .limit(limit)
+      .offset(offset);
+    return { faces: rows.map(row => row.face), total: Number(total) };
+  }
+
+  async confirmFaces(faceIds: string[]): Promise<number> {
+    if (faceIds.length === 0) return 0;
+    const confirmed = await db
+      .update(faces)
+      .set({ reviewedAt: new Date() })
+      .where(inArray(faces.id, faceIds))
+      .returning({ id: faces.id });
+    return confirmed.length;
+  }
+
+  async deleteFaces(faceIds: string[]): Promise<Face[]> {
+    if (faceIds.length === 0) return [];
+    return await db.delete(faces).where(inArray(faces.id, faceIds)).returning();
+  }
+
   async getPersonPhotos(personId: string): Promise<Array<FileVersion & { mediaAsset: MediaAsset }>> {
     const personFaces = await this.getFacesByPerson(personId);
     const photoIds = Array.from(new Set(personFaces.map(face => face.photoId)));
@@ -727,6 +1493,601 @@ export class DatabaseStorage implements IStorage {
     return await db.select().from(aiPrompts).where(eq(aiPrompts.isActive, true));
   }
 
+  // Photo source methods
+  async createPhotoSource(source: InsertPhotoSource): Promise<PhotoSource> {
+    const [newSource] = await db.insert(photoSources).values(source).returning();
+    return newSource;
+  }
+
+  async getPhotoSources(mediaAssetId: string): Promise<PhotoSource[]> {
+    return await db
+      .select()
+      .from(photoSources)
+      .where(eq(photoSources.mediaAssetId, mediaAssetId))
+      .orderBy(photoSources.importedAt);
+  }
+
+  async getAllPhotoSources(): Promise<PhotoSource[]> {
+    return await db.select().from(photoSources).orderBy(photoSources.importedAt);
+  }
+
+  // Duplicate decision methods
+  async getDuplicateDecisions(): Promise<DuplicateDecision[]> {
+    return await db.select().from(duplicateDecisions).orderBy(desc(duplicateDecisions.createdAt));
+  }
+
+  async createDuplicateDecision(decision: InsertDuplicateDecision): Promise<DuplicateDecision> {
+    const [newDecision] = await db.insert(duplicateDecisions).values(decision).returning();
+    return newDecision;
+  }
+
+  async deleteDuplicateDecision(id: string): Promise<boolean> {
+    const result = await db.delete(duplicateDecisions).where(eq(duplicateDecisions.id, id)).returning();
+    return result.length > 0;
+  }
+
+  // Frame target methods
+  async createFrameTarget(target: InsertFrameTarget): Promise<FrameTarget> {
+    const [newTarget] = await db
+      .insert(frameTargets)
+      .values({ ...target, volumeLabel: guessVolumeLabel(target.destinationPath) })
+      .returning();
+    return newTarget;
+  }
+
+  async getFrameTargets(): Promise<FrameTarget[]> {
+    return await db.select().from(frameTargets).orderBy(frameTargets.name);
+  }
+
+  async getFrameTarget(id: string): Promise<FrameTarget | undefined> {
+    const [target] = await db.select().from(frameTargets).where(eq(frameTargets.id, id));
+    return target || undefined;
+  }
+
+  async updateFrameTarget(id: string, updates: Partial<FrameTarget>): Promise<FrameTarget | null> {
+    const [updated] = await db
+      .update(frameTargets)
+      .set({
+        ...updates,
+        ...(updates.destinationPath ? { volumeLabel: guessVolumeLabel(updates.destinationPath) } : {}),
+        updatedAt: new Date(),
+      })
+      .where(eq(frameTargets.id, id))
+      .returning();
+    return updated || null;
+  }
+
+  async deleteFrameTarget(id: string): Promise<boolean> {
+    const result = await db.delete(frameTargets).where(eq(frameTargets.id, id)).returning();
+    return result.length > 0;
+  }
+
+  // Watch folder methods
+  async createWatchFolder(folder: InsertWatchFolder): Promise<WatchFolder> {
+    const [newFolder] = await db
+      .insert(watchFolders)
+      .values({ ...folder, volumeLabel: guessVolumeLabel(folder.path) })
+      .returning();
+    return newFolder;
+  }
+
+  async getWatchFolders(): Promise<WatchFolder[]> {
+    return await db.select().from(watchFolders).orderBy(watchFolders.path);
+  }
+
+  async getWatchFolder(id: string): Promise<WatchFolder | undefined> {
+    const [folder] = await db.select().from(watchFolders).where(eq(watchFolders.id, id));
+    return folder || undefined;
+  }
+
+  async updateWatchFolder(id: string, updates: Partial<WatchFolder>): Promise<WatchFolder | null> {
+    const [updated] = await db.update(watchFolders).set(updates).where(eq(watchFolders.id, id)).returning();
+    return updated || null;
+  }
+
+  async deleteWatchFolder(id: string): Promise<boolean> {
+    const result = await db.delete(watchFolders).where(eq(watchFolders.id, id)).returning();
+    return result.length > 0;
+  }
+
+  // Cold storage methods
+  async createColdStorageCopy(copy: InsertColdStorageCopy): Promise<ColdStorageCopy> {
+    const [newCopy] = await db.insert(coldStorageCopies).values(copy).returning();
+    return newCopy;
+  }
+
+  async getColdStorageCopy(photoId: string): Promise<ColdStorageCopy | undefined> {
+    const [copy] = await db.select().from(coldStorageCopies).where(eq(coldStorageCopies.photoId, photoId));
+    return copy || undefined;
+  }
+
+  async getColdStorageCopies(filters: { evicted?: boolean; volumeLabel?: string } = {}): Promise<ColdStorageCopy[]> {
+    const conditions = [];
+    if (filters.evicted !== undefined) {
+      conditions.push(filters.evicted ? isNotNull(coldStorageCopies.evictedAt) : isNull(coldStorageCopies.evictedAt));
+    }
+    if (filters.volumeLabel) {
+      conditions.push(eq(coldStorageCopies.volumeLabel, filters.volumeLabel));
+    }
+    return await db
+      .select()
+      .from(coldStorageCopies)
+      .where(conditions.length > 0 ? and(...conditions) : undefined)
+      .orderBy(coldStorageCopies.volumeLabel, coldStorageCopies.relativePath);
+  }
+
+  async updateColdStorageCopy(id: string, updates: Partial<ColdStorageCopy>): Promise<ColdStorageCopy | null> {
+    const [updated] = await db.update(coldStorageCopies).set(updates).where(eq(coldStorageCopies.id, id)).returning();
+    return updated || null;
+  }
+
+  // Selection methods
+  async createSelection(selection: InsertSelection, photoIds: string[] = []): Promise<Selection> {
+    return await db.transaction(async (tx) => {
+      const [newSelection] = await tx.insert(selections).values(selection).returning();
+      const uniqueIds = Array.from(new Set(photoIds));
+      if (uniqueIds.length > 0) {
+        await tx.insert(selectionPhotos).values(uniqueIds.map(photoId => ({ selectionId: newSelection.id, photoId })));
+      }
+      return newSelection;
+    });
+  }
+
+  async getSelections(): Promise<Array<Selection & { photoCount: number }>> {
+    const rows = await db
+      .select({ selection: selections, photoCount: count(selectionPhotos.id) })
+      .from(selections)
+      .leftJoin(selectionPhotos, eq(selectionPhotos.selectionId, selections.id))
+      .groupBy(selections.id)
+      .orderBy(desc(selections.updatedAt));
+    return rows.map(row => ({ ...row.selection, photoCount: Number(row.photoCount) }));
+  }
+
+  async getSelection(id: string): Promise<Selection | undefined> {
+    const [selection] = await db.select().from(selections).where(eq(selections.id, id));
+    return selection || undefined;
+  }
+
+  async updateSelection(id: string, updates: Partial<Selection>): Promise<Selection | null> {
+    const [updated] = await db
+      .update(selections)
+      .set({ ...updates, updatedAt: new Date() })
+      .where(eq(selections.id, id))
+      .returning();
+    return updated || null;
+  }
+
+  async deleteSelection(id: string): Promise<boolean> {
+    return await db.transaction(async (tx) => {
+      await tx.delete(selectionPhotos).where(eq(selectionPhotos.selectionId, id));
+      const result = await tx.delete(selections).where(eq(selections.id, id)).returning();
+      return result.length > 0;
+    });
+  }
+
+  /**
+   * Add photos to a selection, skipping ones already in it. Returns the number added.
+   */
+  async addToSelection(selectionId: string, photoIds: string[]): Promise<number> {
+    if (photoIds.length === 0) return 0;
+
+    return await db.transaction(async (tx) => {
+      const existing = await tx
+        .select({ photoId: selectionPhotos.photoId })
+        .from(selectionPhotos)
+        .where(eq(selectionPhotos.selectionId, selectionId));
+      const existingIds = new Set(existing.map(row => row.photoId));
+      const toAdd = Array.from(new Set(photoIds)).filter(id => !existingIds.has(id));
+
+      if (toAdd.length > 0) {
+        await tx.insert(selectionPhotos).values(toAdd.map(photoId => ({ selectionId, photoId })));
+      }
+      await tx.update(selections).set({ updatedAt: new Date() }).where(eq(selections.id, selectionId));
+      return toAdd.length;
+    });
+  }
+
+  async removeFromSelection(selectionId: string, photoIds: string[]): Promise<number> {
+    if (photoIds.length === 0) return 0;
+    const removed = await db
+      .delete(selectionPhotos)
+      .where(and(eq(selectionPhotos.selectionId, selectionId), inArray(selectionPhotos.photoId, photoIds)))
+      .returning();
+    await db.update(selections).set({ updatedAt: new Date() }).where(eq(selections.id, selectionId));
+    return removed.length;
+  }
+
+  async clearSelection(selectionId: string): Promise<number> {
+    const removed = await db.delete(selectionPhotos).where(eq(selectionPhotos.selectionId, selectionId)).returning();
+    await db.update(selections).set({ updatedAt: new Date() }).where(eq(selections.id, selectionId));
+    return removed.length;
+  }
+
+  async getSelectionPhotos(selectionId: string): Promise<Array<FileVersion & { mediaAsset: MediaAsset }>> {
+    const rows = await db
+      .select()
+      .from(selectionPhotos)
+      .innerJoin(fileVersions, eq(selectionPhotos.photoId, fileVersions.id))
+      .innerJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id))
+      .where(and(eq(selectionPhotos.selectionId, selectionId), isNull(fileVersions.deletedAt)))
+      .orderBy(selectionPhotos.addedAt);
+
+    return rows.map(row => ({
+      ...row.file_versions,
+      mediaAsset: row.media_assets,
+    }));
+  }
+
+  // Photo stack methods
+  /**
+   * Stack assets together; assets already in another stack are moved out of it
+   */
+  async createPhotoStack(stack: InsertPhotoStack, mediaAssetIds: string[]): Promise<PhotoStack> {
+    const assetIds = Array.from(new Set([stack.primaryAssetId, ...mediaAssetIds]));
+    return db.transaction(async (tx) => {
+      await tx.delete(photoStackMembers).where(inArray(photoStackMembers.mediaAssetId, assetIds));
+      const [newStack] = await tx.insert(photoStacks).values(stack).returning();
+      await tx.insert(photoStackMembers).values(assetIds.map((mediaAssetId, position) => ({
+        stackId: newStack.id,
+        mediaAssetId,
+        position,
+      })));
+      return newStack;
+    });
+  }
+
+  async getPhotoStacks(): Promise<Array<PhotoStack & { memberAssetIds: string[] }>> {
+    const stacks = await db.select().from(photoStacks);
+    const members = await db.select().from(photoStackMembers).orderBy(photoStackMembers.position);
+    const membersByStack = new Map<string, string[]>();
+    for (const member of members) {
+      const assetIds = membersByStack.get(member.stackId) ?? [];
+      assetIds.push(member.mediaAssetId);
+      membersByStack.set(member.stackId, assetIds);
+    }
+    return stacks.map(stack => ({ ...stack, memberAssetIds: membersByStack.get(stack.id) ?? [] }));
+  }
+
+  async getPhotoStack(id: string): Promise<(PhotoStack & { memberAssetIds: string[] }) | undefined> {
+    const [stack] = await db.select().from(photoStacks).where(eq(photoStacks.id, id));
+    if (!stack) return undefined;
+    const members = await db
+      .select()
+      .from(photoStackMembers)
+      .where(eq(photoStackMembers.stackId, id))
+      .orderBy(photoStackMembers.position);
+    return { ...stack, memberAssetIds: members.map(member => member.mediaAssetId) };
+  }
+
+  async getPhotoStackForAsset(mediaAssetId: string): Promise<(PhotoStack & { memberAssetIds: string[] }) | undefined> {
+    const [member] = await db
+      .select({ stackId: photoStackMembers.stackId })
+      .from(photoStackMembers)
+      .where(eq(photoStackMembers.mediaAssetId, mediaAssetId));
+    return member ? this.getPhotoStack(member.stackId) : undefined;
+  }
+
+  async updatePhotoStack(id: string, updates: Partial<InsertPhotoStack>): Promise<PhotoStack | null> {
+    const [updated] = await db.update(photoStacks).set(updates).where(eq(photoStacks.id, id)).returning();
+    return updated || null;
+  }
+
+  async deletePhotoStack(id: string): Promise<boolean> {
+    return db.transaction(async (tx) => {
+      await tx.delete(photoStackMembers).where(eq(photoStackMembers.stackId, id));
+      const result = await tx.delete(photoStacks).where(eq(photoStacks.id, id)).returning();
+      return result.length > 0;
+    });
+  }
+
+  // Burst methods
+  async getBursts(): Promise<BurstWithFrames[]> {
+    const allBursts = await db.select().from(bursts).orderBy(desc(bursts.startedAt));
+    const frames = await db.select().from(burstFrames).orderBy(burstFrames.position);
+    const framesByBurst = new Map<string, BurstFrame[]>();
+    for (const frame of frames) {
+      const burstFramesList = framesByBurst.get(frame.burstId) ?? [];
+      burstFramesList.push(frame);
+      framesByBurst.set(frame.burstId, burstFramesList);
+    }
+    return allBursts.map(burst => ({ ...burst, frames: framesByBurst.get(burst.id) ?? [] }));
+  }
+
+  async getBurst(id: string): Promise<BurstWithFrames | undefined> {
+    const [burst] = await db.select().from(bursts).where(eq(bursts.id, id));
+    if (!burst) return undefined;
+    const frames = await db
+      .select()
+      .from(burstFrames)
+      .where(eq(burstFrames.burstId, id))
+      .orderBy(burstFrames.position);
+    return { ...burst, frames };
+  }
+
+  /**
+   * Swap the bursts nobody has chosen a best frame for with a fresh detection.
+   * Resolved bursts and the unresolved ones in keepIds are left as they are.
+   */
+  async replaceUnresolvedBursts(
+    detected: Array<{ burst: InsertBurst; frames: Array<Pick<BurstFrame, 'photoId' | 'capturedAt'>> }>,
+    keepIds: string[]
+  ): Promise<BurstWithFrames[]> {
+    return db.transaction(async (tx) => {
+      await tx.delete(bursts).where(and(
+        isNull(bursts.resolvedAt),
+        keepIds.length > 0 ? notInArray(bursts.id, keepIds) : undefined
+      ));
+      const created: BurstWithFrames[] = [];
+      for (const { burst, frames } of detected) {
+        const [newBurst] = await tx.insert(bursts).values(burst).returning();
+        const newFrames = await tx.insert(burstFrames).values(frames.map((frame, position) => ({
+          burstId: newBurst.id,
+          photoId: frame.photoId,
+          capturedAt: frame.capturedAt,
+          position,
+        }))).returning();
+        created.push({ ...newBurst, frames: newFrames.sort((a, b) => a.position - b.position) });
+      }
+      return created;
+    });
+  }
+
+  async updateBurst(id: string, updates: Partial<Burst>): Promise<Burst | null> {
+    const [updated] = await db.update(bursts).set(updates).where(eq(bursts.id, id)).returning();
+    return updated || null;
+  }
+
+  async updateBurstFrame(id: string, updates: Partial<BurstFrame>): Promise<BurstFrame | null> {
+    const [updated] = await db.update(burstFrames).set(updates).where(eq(burstFrames.id, id)).returning();
+    return updated || null;
+  }
+
+  // Album suggestion methods
+  async getAlbumSuggestions(status?: AlbumSuggestion['status']): Promise<AlbumSuggestion[]> {
+    return await db
+      .select()
+      .from(albumSuggestions)
+      .where(status ? eq(albumSuggestions.status, status) : undefined)
+      .orderBy(desc(albumSuggestions.startedAt));
+  }
+
+  async getAlbumSuggestion(id: string): Promise<AlbumSuggestion | undefined> {
+    const [suggestion] = await db.select().from(albumSuggestions).where(eq(albumSuggestions.id, id));
+    return suggestion;
+  }
+
+  /**
+   * Drop pending suggestions other than keepIds and add the new ones;
+   * accepted and dismissed suggestions are left alone
+   */
+  async replacePendingAlbumSuggestions(suggestions: InsertAlbumSuggestion[], keepIds: string[]): Promise<AlbumSuggestion[]> {
+    return db.transaction(async (tx) => {
+      await tx.delete(albumSuggestions).where(and(
+        eq(albumSuggestions.status, 'pending'),
+        keepIds.length > 0 ? notInArray(albumSuggestions.id, keepIds) : undefined
+      ));
+      if (suggestions.length === 0) return [];
+      return await tx.insert(albumSuggestions).values(suggestions).returning();
+    });
+  }
+
+  async updateAlbumSuggestion(id: string, updates: Partial<AlbumSuggestion>): Promise<AlbumSuggestion | null> {
+    const [updated] = await db.update(albumSuggestions).set(updates).where(eq(albumSuggestions.id, id)).returning();
+    return updated || null;
+  }
+
+  // Background job methods
+  async createJob(job: InsertJob): Promise<Job> {
+    const [newJob] = await db.insert(jobs).values(job).returning();
+    return newJob;
+  }
+
+  async getJob(id: string): Promise<Job | undefined> {
+    const [job] = await db.select().from(jobs).where(eq(jobs.id, id));
+    return job || undefined;
+  }
+
+  async getJobs(limit: number = 50): Promise<Job[]> {
+    return await db.select().from(jobs).orderBy(desc(jobs.createdAt)).limit(limit);
+  }
+
+  async getNextQueuedJob(): Promise<Job | undefined> {
+    const [job] = await db.select().from(jobs).where(eq(jobs.status, 'queued')).orderBy(jobs.createdAt).limit(1);
+    return job || undefined;
+  }
+
+  async updateJob(id: string, updates: Partial<Job>): Promise<Job | null> {
+    const [updated] = await db.update(jobs).set(updates).where(eq(jobs.id, id)).returning();
+    return updated || null;
+  }
+
+  // Jobs left running by a crash or shutdown continue from their last completed photo
+  async requeueRunningJobs(): Promise<number> {
+    const result = await db.update(jobs).set({ status: 'queued' }).where(eq(jobs.status, 'running')).returning();
+    return result.length;
+  }
+
+  // Import session methods
+  async createImportSession(session: InsertImportSession): Promise<ImportSession> {
+    const [newSession] = await db.insert(importSessions).values(session).returning();
+    return newSession;
+  }
+
+  async getImportSession(id: string): Promise<ImportSession | undefined> {
+    const [session] = await db.select().from(importSessions).where(eq(importSessions.id, id));
+    return session || undefined;
+  }
+
+  async getImportSessions(limit: number = 50): Promise<ImportSession[]> {
+    return await db.select().from(importSessions).orderBy(desc(importSessions.createdAt)).limit(limit);
+  }
+
+  async getNextQueuedImportSession(): Promise<ImportSession | undefined> {
+    const [session] = await db
+      .select()
+      .from(importSessions)
+      .where(eq(importSessions.status, 'queued'))
+      .orderBy(importSessions.createdAt)
+      .limit(1);
+    return session || undefined;
+  }
+
+  async updateImportSession(id: string, updates: Partial<ImportSession>): Promise<ImportSession | null> {
+    const [updated] = await db.update(importSessions).set(updates).where(eq(importSessions.id, id)).returning();
+    return updated || null;
+  }
+
+  // Sessions left running by a crash or shutdown continue from their last handled file
+  async requeueRunningImportSessions(): Promise<number> {
+    const result = await db
+      .update(importSessions)
+      .set({ status: 'queued' })
+      .where(eq(importSessions.status, 'running'))
+      .returning();
+    return result.length;
+  }
+
+  // Operation journal methods
+  async createJournalEntry(entry: InsertJournalEntry): Promise<JournalEntry> {
+    const [newEntry] = await db.insert(operationJournal).values(entry).returning();
+    return newEntry;
+  }
+
+  async getJournalEntries(limit: number = 50): Promise<JournalEntry[]> {
+    return await db.select().from(operationJournal).orderBy(desc(operationJournal.createdAt)).limit(limit);
+  }
+
+  async getLastActiveJournalEntry(): Promise<JournalEntry | undefined> {
+    const [entry] = await db
+      .select()
+      .from(operationJournal)
+      .where(isNull(operationJournal.undoneAt))
+      .orderBy(desc(operationJournal.createdAt))
+      .limit(1);
+    return entry || undefined;
+  }
+
+  // Redo goes back through undone entries in the reverse of the order they were undone
+  async getLastUndoneJournalEntry(): Promise<JournalEntry | undefined> {
+    const [entry] = await db
+      .select()
+      .from(operationJournal)
+      .where(isNotNull(operationJournal.undoneAt))
+      .orderBy(desc(operationJournal.undoneAt))
+      .limit(1);
+    return entry || undefined;
+  }
+
+  async setJournalEntryUndone(id: string, undoneAt: Date | null): Promise<JournalEntry | null> {
+    const [updated] = await db.update(operationJournal).set({ undoneAt }).where(eq(operationJournal.id, id)).returning();
+    return updated || null;
+  }
+
+  // A new action makes the undone ones impossible to redo
+  async clearUndoneJournalEntries(): Promise<number> {
+    const result = await db.delete(operationJournal).where(isNotNull(operationJournal.undoneAt)).returning();
+    return result.length;
+  }
+
+  // External asset methods
+  async getExternalAssets(source: ExternalAsset['source'], sourceKey: string): Promise<ExternalAsset[]> {
+    return await db
+      .select()
+      .from(externalAssets)
+      .where(and(eq(externalAssets.source, source), eq(externalAssets.sourceKey, sourceKey)));
+  }
+
+  async createExternalAsset(asset: InsertExternalAsset): Promise<ExternalAsset> {
+    const [newAsset] = await db
+      .insert(externalAssets)
+      .values(asset)
+      .onConflictDoUpdate({
+        target: [externalAssets.source, externalAssets.sourceKey, externalAssets.externalId],
+        set: { photoId: asset.photoId },
+      })
+      .returning();
+    return newAsset;
+  }
+
+  // Share link methods
+  async createShare(share: InsertShare): Promise<Share> {
+    const [newShare] = await db.insert(shares).values(share).returning();
+    return newShare;
+  }
+
+  async getShares(): Promise<Share[]> {
+    return await db.select().from(shares).orderBy(desc(shares.createdAt));
+  }
+
+  async getShare(id: string): Promise<Share | undefined> {
+    const [share] = await db.select().from(shares).where(eq(shares.id, id));
+    return share || undefined;
+  }
+
+  async getShareByToken(token: string): Promise<Share | undefined> {
+    const [share] = await db.select().from(shares).where(eq(shares.token, token));
+    return share || undefined;
+  }
+
+  async updateShare(id: string, updates: Partial<Share>): Promise<Share | null> {
+    const [updated] = await db.update(shares).set(updates).where(eq(shares.id, id)).returning();
+    return updated || null;
+  }
+
+  /**
+   * Count a view, unless the share has used up its views; the check and the
+   * count are one statement so concurrent views cannot go over the limit
+   */
+  async recordShareView(id: string): Promise<Share | null> {
+    const [updated] = await db
+      .update(shares)
+      .set({ viewCount: sql`${shares.viewCount} + 1`, lastViewedAt: new Date() })
+      .where(and(eq(shares.id, id), sql`(${shares.maxViews} IS NULL OR ${shares.viewCount} < ${shares.maxViews})`))
+      .returning();
+    return updated || null;
+  }
+
+  // Full-text search methods
+  // Photos outside the trash whose search document matches a tsquery, best matches first
+  async searchPhotoText(tsquery: string, limit: number, offset: number): Promise<{
+    photos: Array<FileVersion & { mediaAsset: MediaAsset; rank: number }>;
+    total: number;
+  }> {
+    const query = sql`to_tsquery('simple', ${tsquery})`;
+    const rank = sql<number>`ts_rank_cd(${photoSearchIndex.document}, ${query})`;
+    const condition = and(sql`${photoSearchIndex.document} @@ ${query}`, isNull(fileVersions.deletedAt));
+
+    const [{ total }] = await db
+      .select({ total: count() })
+      .from(photoSearchIndex)
+      .innerJoin(fileVersions, eq(photoSearchIndex.photoId, fileVersions.id))
+      .where(condition);
+    const rows = await db
+      .select({ photo: fileVersions, mediaAsset: mediaAssets, rank })
+      .from(photoSearchIndex)
+      .innerJoin(fileVersions, eq(photoSearchIndex.photoId, fileVersions.id))
+      .leftJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id))
+      .where(condition)
+      .orderBy(desc(rank), desc(fileVersions.takenAt))
+      .limit(limit)
+      .offset(offset);
+
+    return {
+      photos: rows.map(row => ({ ...row.photo, mediaAsset: row.mediaAsset!, rank: Number(row.rank) })),
+      total: Number(total),
+    };
+  }
+
+  // Recompute every photo's search document, for after changes the triggers do not see
+  async rebuildPhotoSearchIndex(): Promise<number> {
+    const result = await db.execute(sql`
+      INSERT INTO photo_search_index (photo_id, document, updated_at)
+      SELECT id, photo_search_document(id), NOW() FROM file_versions
+      ON CONFLICT (photo_id) DO UPDATE SET document = EXCLUDED.document, updated_at = EXCLUDED.updated_at
+    `);
+    return result.rowCount ?? 0;
+  }
+
   // Location methods
   async createLocation(location: InsertLocation): Promise<Location> {
     const [newLocation] = await db.insert(locations).values(location).returning();
@@ -820,7 +2181,9 @@ export class DatabaseStorage implements IStorage {
     const result = await db
       .select()
       .from(fileVersions)
-      .leftJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id));
+      .leftJoin(mediaAssets, eq(fileVersions.mediaAssetId, mediaAssets.id))
+      .where(isNull(fileVersions.deletedAt))
+      .orderBy(desc(fileVersions.createdAt), fileVersions.id);
 
     return result.map(row => ({
       ...row.file_versions,
@@ -879,7 +2242,7 @@ export class DatabaseStorage implements IStorage {
 
   async getAllTags(): Promise<string[]> {
     try {
-      const result = await db.select().from(globalTagLibrary);
+      const result = await db.select().from(globalTagLibrary).where(isNull(globalTagLibrary.deletedAt));
       return result.map(row => row.tag);
     } catch (error) {
       console.error('Error fetching tags:', error);
@@ -887,6 +2250,95 @@ export class DatabaseStorage implements IStorage {
     }
   }
 
+  async getDeletedTags(): Promise<Array<{ tag: string; usageCount: number; deletedAt: Date }>> {
+    const rows = await db
+      .select({ tag: globalTagLibrary.tag, usageCount: globalTagLibrary.usageCount, deletedAt: globalTagLibrary.deletedAt })
+      .from(globalTagLibrary)
+      .where(isNotNull(globalTagLibrary.deletedAt))
+      .orderBy(desc(globalTagLibrary.deletedAt));
+    return rows.map(row => ({ ...row, deletedAt: row.deletedAt! }));
+  }
+
+  async setTagDeleted(tag: string, deletedAt: Date | null): Promise<boolean> {
+    const result = await db
+      .update(globalTagLibrary)
+      .set({ deletedAt })
+      .where(eq(globalTagLibrary.tag, tag))
+      .returning();
+    return result.length > 0;
+  }
+
+  async purgeTag(tag: string): Promise<boolean> {
+    const result = await db.delete(globalTagLibrary).where(eq(globalTagLibrary.tag, tag)).returning();
+    return result.length > 0;
+  }
+
+  // Library tags that are not deleted, in path order so parents come before their children
+  async getTagLibrary(): Promise<GlobalTagLibrary[]> {
+    return await db.select().from(globalTagLibrary).where(isNull(globalTagLibrary.deletedAt)).orderBy(globalTagLibrary.tag);
+  }
+
+  async createLibraryTag(tag: string, usageCount: number): Promise<GlobalTagLibrary> {
+    const [created] = await db
+      .insert(globalTagLibrary)
+      .values({ tag, usageCount })
+      .onConflictDoUpdate({ target: globalTagLibrary.tag, set: { deletedAt: null } })
+      .returning();
+    return created;
+  }
+
+  async updateLibraryTag(id: string, updates: Partial<GlobalTagLibrary>): Promise<GlobalTagLibrary | null> {
+    const [updated] = await db.update(globalTagLibrary).set(updates).where(eq(globalTagLibrary.id, id)).returning();
+    return updated || null;
+  }
+
+  // Library tags with how many photos outside the trash carry each one, ignoring case
+  async getTagUsage(): Promise<Array<GlobalTagLibrary & { photoCount: number }>> {
+    const result = await db.execute(sql`
+      SELECT lower(t.tag) AS tag, COUNT(DISTINCT fv.id)::int AS photo_count
+      FROM file_versions fv
+      CROSS JOIN LATERAL jsonb_array_elements_text(
+        CASE WHEN jsonb_typeof(fv.metadata->'ai'->'aiTags') = 'array' THEN fv.metadata->'ai'->'aiTags' ELSE '[]'::jsonb END
+      ) AS t(tag)
+      WHERE fv.deleted_at IS NULL
+      GROUP BY lower(t.tag)
+    `);
+    const photoCounts = new Map((result.rows as Array<{ tag: string; photo_count: number }>).map(row => [row.tag, Number(row.photo_count)]));
+    return (await this.getTagLibrary()).map(entry => ({ ...entry, photoCount: photoCounts.get(entry.tag.toLowerCase()) ?? 0 }));
+  }
+
+  // Photo tag lists and library renames and merges from one tag rename or merge, all or nothing
+  async rewriteTags(
+    photoTags: Array<{ photoId: string; tags: string[] }>,
+    renames: Array<{ id: string; tag: string }>,
+    merges: Array<{ sourceId: string; targetId: string }>
+  ): Promise<void> {
+    await db.transaction(async (tx) => {
+      for (const { photoId, tags } of photoTags) {
+        await tx
+          .update(fileVersions)
+          .set({
+            metadata: sql`jsonb_set(
+              COALESCE(${fileVersions.metadata}, '{}'::jsonb), '{ai}',
+              COALESCE(${fileVersions.metadata}->'ai', '{}'::jsonb) || jsonb_build_object('aiTags', ${JSON.stringify(tags)}::jsonb)
+            )`,
+          })
+          .where(eq(fileVersions.id, photoId));
+      }
+      for (const { sourceId, targetId } of merges) {
+        const [source] = await tx.delete(globalTagLibrary).where(eq(globalTagLibrary.id, sourceId)).returning();
+        if (!source) continue;
+        await tx
+          .update(globalTagLibrary)
+          .set({ usageCount: sql`${globalTagLibrary.usageCount} + ${source.usageCount}` })
+          .where(eq(globalTagLibrary.id, targetId));
+      }
+      for (const { id, tag } of renames) {
+        await tx.update(globalTagLibrary).set({ tag }).where(eq(globalTagLibrary.id, id));
+      }
+    });
+  }
+
   async addTagToLibrary(tag: string): Promise<void> {
     try {
       // Insert only if tag doesn't exist (ignore conflicts)
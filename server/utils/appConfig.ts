@@ -0,0 +1,40 @@
+import fs from "fs/promises";
+import path from "path";
+import { getWritableBaseDir } from "./libraryPaths";
+
+const APP_CONFIG_FILE = 'pictallion.config.json';
+
+/**
+ * Settings that must be known before the library and its database settings
+ * are available. Kept in the app data directory, outside any library, so a
+ * library on an external drive can be found again at startup.
+ */
+export interface AppConfig {
+  libraryRoot?: string;
+}
+
+export function getAppConfigPath(): string {
+  return path.join(getWritableBaseDir(), APP_CONFIG_FILE);
+}
+
+/**
+ * Saved app config; empty when none has been written or it cannot be read
+ */
+export async function readAppConfig(): Promise<AppConfig> {
+  try {
+    const parsed = JSON.parse(await fs.readFile(getAppConfigPath(), 'utf8'));
+    return parsed && typeof parsed === 'object' ? parsed : {};
+  } catch {
+    return {};
+  }
+}
+
+export async function writeAppConfig(updates: Partial<AppConfig>): Promise<AppConfig> {
+  const config = { ...(await readAppConfig()), ...updates };
+  const configPath = getAppConfigPath();
+  const partialPath = `${configPath}.partial`;
+  await fs.mkdir(path.dirname(configPath), { recursive: true });
+  await fs.writeFile(partialPath, JSON.stringify(config, null, 2));
+  await fs.rename(partialPath, configPath);
+  return config;
+}
@@ -0,0 +1,85 @@
+import sharp from "sharp";
+
+export interface ColorProfileInfo {
+  colorSpace: string; // "sRGB", "Display P3", "Adobe RGB", "ProPhoto RGB", or the profile's own name
+  profileName: string | null; // description embedded in the ICC profile
+  wideGamut: boolean;
+}
+
+// Profile descriptions as cameras and editors write them, to the name shown in the info panel
+const KNOWN_PROFILES: Array<{ pattern: RegExp; colorSpace: string; wideGamut: boolean }> = [
+  { pattern: /display p3|dci-?p3|\bp3\b/i, colorSpace: 'Display P3', wideGamut: true },
+  { pattern: /adobe ?rgb|compatible with adobe/i, colorSpace: 'Adobe RGB', wideGamut: true },
+  { pattern: /prophoto|romm/i, colorSpace: 'ProPhoto RGB', wideGamut: true },
+  { pattern: /rec\.? ?2020|bt\.? ?2020/i, colorSpace: 'Rec. 2020', wideGamut: true },
+  { pattern: /srgb|iec ?61966-2[.-]1/i, colorSpace: 'sRGB', wideGamut: false },
+];
+
+// EXIF ColorSpace tag: 1 is sRGB, 65535 "uncalibrated" is what cameras write in Adobe RGB mode
+const EXIF_COLOR_SPACES: Record<string, ColorProfileInfo> = {
+  '1': { colorSpace: 'sRGB', profileName: null, wideGamut: false },
+  '65535': { colorSpace: 'Adobe RGB', profileName: null, wideGamut: true },
+};
+
+/**
+ * A sharp pipeline for anything shown on screen: turned upright by its EXIF
+ * orientation, and converted from the embedded ICC profile to sRGB with the
+ * sRGB profile attached, so wide-gamut originals keep their colours instead of
+ * looking washed out when their numbers are read as sRGB
+ */
+export function displayImage(input: string | Buffer): sharp.Sharp {
+  return sharp(input).rotate().withIccProfile('srgb');
+}
+
+/**
+ * Colour space of an image from its ICC profile, falling back to the EXIF
+ * ColorSpace tag for files without a profile. Null when neither says.
+ */
+export async function detectColorProfile(filePath: string, exifColorSpace?: string): Promise<ColorProfileInfo | null> {
+  const { icc, space } = await sharp(filePath).metadata();
+  const profileName = icc ? readProfileDescription(icc) : null;
+  if (profileName) {
+    const known = KNOWN_PROFILES.find(profile => profile.pattern.test(profileName));
+    return known
+      ? { colorSpace: known.colorSpace, profileName, wideGamut: known.wideGamut }
+      : { colorSpace: profileName, profileName, wideGamut: false };
+  }
+  if (exifColorSpace && EXIF_COLOR_SPACES[exifColorSpace]) return EXIF_COLOR_SPACES[exifColorSpace];
+  if (space === 'cmyk') return { colorSpace: 'CMYK', profileName: null, wideGamut: false };
+  return null;
+}
+
+/**
+ * The 'desc' tag of an ICC profile: plain ASCII in version 2 profiles
+ * ('desc' type), UTF-16 records in version 4 ('mluc' type)
+ */
+function readProfileDescription(icc: Buffer): string | null {
+  if (icc.length < 132) return null;
+  const tagCount = icc.readUInt32BE(128);
+  for (let index = 0; index < tagCount; index++) {
+    const entry = 132 + index * 12;
+    if (entry + 12 > icc.length) return null;
+    if (icc.toString('latin1', entry, entry + 4) !== 'desc') continue;
+
+    const offset = icc.readUInt32BE(entry + 4);
+    const size = icc.readUInt32BE(entry + 8);
+    if (offset + size > icc.length || size < 12) return null;
+    const type = icc.toString('latin1', offset, offset + 4);
+
+    if (type === 'desc') {
+      const length = icc.readUInt32BE(offset + 8);
+      const text = icc.toString('latin1', offset + 12, Math.min(offset + 12 + length, offset + size));
+      return text.replace(/\0+$/, '').trim() || null;
+    }
+    if (type === 'mluc' && size >= 28) {
+      // First record; profiles carry English first or only
+      const length = icc.readUInt32BE(offset + 20) & ~1;
+      const start = offset + icc.readUInt32BE(offset + 24);
+      if (start + length > offset + size) return null;
+      const text = Buffer.from(icc.subarray(start, start + length)).swap16().toString('utf16le');
+      return text.replace(/\0+$/, '').trim() || null;
+    }
+    return null;
+  }
+  return null;
+}
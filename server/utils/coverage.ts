@@ -0,0 +1,69 @@
+const DAY_MS = 24 * 60 * 60 * 1000;
+
+export interface CoverageGap {
+  start: string; // last capture date before the gap
+  end: string; // first capture date after the gap
+  days: number;
+  label: string;
+}
+
+export interface YearCoverage {
+  year: number;
+  photoCount: number;
+  monthsCovered: number;
+  weeksCovered: number;
+  coveragePercent: number; // share of weeks with at least one photo
+}
+
+const MONTH_FORMAT = new Intl.DateTimeFormat('en-US', { month: 'short', year: 'numeric' });
+
+/**
+ * Find stretches between consecutive capture dates longer than minGapDays
+ */
+export function findCoverageGaps(dates: Date[], minGapDays: number): CoverageGap[] {
+  const sorted = dates.map(date => date.getTime()).sort((a, b) => a - b);
+  const gaps: CoverageGap[] = [];
+
+  for (let i = 1; i < sorted.length; i++) {
+    const days = Math.floor((sorted[i] - sorted[i - 1]) / DAY_MS);
+    if (days < minGapDays) continue;
+
+    const start = new Date(sorted[i - 1]);
+    const end = new Date(sorted[i]);
+    gaps.push({
+      start: start.toISOString(),
+      end: end.toISOString(),
+      days,
+      label: `No photos between ${MONTH_FORMAT.format(start)} and ${MONTH_FORMAT.format(end)}`,
+    });
+  }
+
+  return gaps.sort((a, b) => b.days - a.days);
+}
+
+/**
+ * Per-year coverage: how many months and weeks contain at least one photo
+ */
+export function calculateYearlyCoverage(dates: Date[]): YearCoverage[] {
+  const years = new Map<number, { count: number; months: Set<number>; weeks: Set<number> }>();
+
+  for (const date of dates) {
+    const year = date.getFullYear();
+    const entry = years.get(year) ?? { count: 0, months: new Set<number>(), weeks: new Set<number>() };
+    entry.count++;
+    entry.months.add(date.getMonth());
+    const dayOfYear = Math.floor((date.getTime() - new Date(year, 0, 1).getTime()) / DAY_MS);
+    entry.weeks.add(Math.min(51, Math.floor(dayOfYear / 7)));
+    years.set(year, entry);
+  }
+
+  return Array.from(years.entries())
+    .sort(([a], [b]) => a - b)
+    .map(([year, entry]) => ({
+      year,
+      photoCount: entry.count,
+      monthsCovered: entry.months.size,
+      weeksCovered: entry.weeks.size,
+      coveragePercent: Math.round((entry.weeks.size / 52) * 100),
+    }));
+}
@@ -0,0 +1,97 @@
+import { z } from "zod";
+import { getEffectiveDate, parseMetadataDate } from "./photoDates";
+
+// Which of a photo's dates a filter applies to: when it was taken, when it
+// was added to the library, or when the file was last changed (EXIF ModifyDate)
+export const DATE_FIELDS = ['capturedAt', 'importedAt', 'modifiedAt'] as const;
+export type DateField = typeof DATE_FIELDS[number];
+
+// Ranges relative to the day a search runs, so saved searches keep meaning the same thing
+export const RELATIVE_DATE_RANGES = [
+  'today', 'yesterday', 'last_7_days', 'last_30_days', 'last_90_days', 'last_365_days',
+  'this_week', 'this_month', 'this_year', 'last_week', 'last_month', 'last_year',
+] as const;
+export type RelativeDateRange = typeof RELATIVE_DATE_RANGES[number];
+
+export const dateFieldSchema = z.enum(DATE_FIELDS);
+export const relativeDateRangeSchema = z.enum(RELATIVE_DATE_RANGES);
+
+type DatedPhoto = { metadata?: unknown; takenAt?: Date | null; createdAt: Date };
+
+/**
+ * The photo's date for the field, or null when it has none (only files
+ * without an EXIF modification date)
+ */
+export function getPhotoDate(photo: DatedPhoto, field: DateField = 'capturedAt'): Date | null {
+  switch (field) {
+    case 'capturedAt':
+      return getEffectiveDate(photo);
+    case 'importedAt':
+      return new Date(photo.createdAt);
+    case 'modifiedAt':
+      return parseMetadataDate((photo.metadata as any)?.exif?.modifyDate);
+  }
+}
+
+function startOfDay(date: Date): Date {
+  return new Date(date.getFullYear(), date.getMonth(), date.getDate());
+}
+
+function addDays(date: Date, days: number): Date {
+  return new Date(date.getFullYear(), date.getMonth(), date.getDate() + days);
+}
+
+/**
+ * Start and end, inclusive, of a relative range in server local time. Weeks
+ * start on Monday. "last_30_days" covers today and the 29 days before it.
+ */
+export function resolveRelativeRange(range: RelativeDateRange, now: Date = new Date()): { start: Date; end: Date } {
+  const today = startOfDay(now);
+  const endOf = (exclusiveEnd: Date) => new Date(exclusiveEnd.getTime() - 1);
+  const mondayOffset = (today.getDay() + 6) % 7;
+
+  switch (range) {
+    case 'today':
+      return { start: today, end: endOf(addDays(today, 1)) };
+    case 'yesterday':
+      return { start: addDays(today, -1), end: endOf(today) };
+    case 'last_7_days':
+      return { start: addDays(today, -6), end: endOf(addDays(today, 1)) };
+    case 'last_30_days':
+      return { start: addDays(today, -29), end: endOf(addDays(today, 1)) };
+    case 'last_90_days':
+      return { start: addDays(today, -89), end: endOf(addDays(today, 1)) };
+    case 'last_365_days':
+      return { start: addDays(today, -364), end: endOf(addDays(today, 1)) };
+    case 'this_week':
+      return { start: addDays(today, -mondayOffset), end: endOf(addDays(today, 7 - mondayOffset)) };
+    case 'last_week':
+      return { start: addDays(today, -mondayOffset - 7), end: endOf(addDays(today, -mondayOffset)) };
+    case 'this_month':
+      return { start: new Date(today.getFullYear(), today.getMonth(), 1), end: endOf(new Date(today.getFullYear(), today.getMonth() + 1, 1)) };
+    case 'last_month':
+      return { start: new Date(today.getFullYear(), today.getMonth() - 1, 1), end: endOf(new Date(today.getFullYear(), today.getMonth(), 1)) };
+    case 'this_year':
+      return { start: new Date(today.getFullYear(), 0, 1), end: endOf(new Date(today.getFullYear() + 1, 0, 1)) };
+    case 'last_year':
+      return { start: new Date(today.getFullYear() - 1, 0, 1), end: endOf(new Date(today.getFullYear(), 0, 1)) };
+  }
+}
+
+/**
+ * Bounds of a date filter in milliseconds. A relative range takes the place
+ * of start and end.
+ */
+export function resolveDateBounds(
+  filter: { start?: Date | string; end?: Date | string; relative?: RelativeDateRange },
+  now: Date = new Date()
+): { start: number; end: number } {
+  if (filter.relative) {
+    const { start, end } = resolveRelativeRange(filter.relative, now);
+    return { start: start.getTime(), end: end.getTime() };
+  }
+  return {
+    start: filter.start ? new Date(filter.start).getTime() : -Infinity,
+    end: filter.end ? new Date(filter.end).getTime() : Infinity,
+  };
+}
@@ -0,0 +1,68 @@
+import type { DaylightPhase, PhotoEnvironment } from "@shared/schema";
+import { sunPosition, sunTimes, estimateUtcOffsetHours } from "./solar";
+
+export type Daylight = NonNullable<PhotoEnvironment['daylight']>;
+
+export interface CaptureContext {
+  instant: Date;
+  localDate: Date; // UTC midnight of the local capture date
+  latitude: number;
+  longitude: number;
+  utcOffsetHours: number;
+}
+
+// How far from solar noon the light still counts as midday, in minutes
+const MIDDAY_WINDOW_MINUTES = 60;
+
+/**
+ * The capture instant and position of a photo, or null when it lacks either.
+ * Capture dates are local wall-clock time, so the zone is estimated.
+ */
+export function getCaptureContext(captured: Date | null, latitude: number | null, longitude: number | null): CaptureContext | null {
+  if (!captured || latitude === null || longitude === null) return null;
+  if (latitude === 0 && longitude === 0) return null; // no fix, not a position
+
+  const utcOffsetHours = estimateUtcOffsetHours(longitude);
+  const wallClock = Date.UTC(captured.getFullYear(), captured.getMonth(), captured.getDate(),
+    captured.getHours(), captured.getMinutes(), captured.getSeconds());
+  return {
+    instant: new Date(wallClock - utcOffsetHours * 3600000),
+    localDate: new Date(Date.UTC(captured.getFullYear(), captured.getMonth(), captured.getDate())),
+    latitude,
+    longitude,
+    utcOffsetHours,
+  };
+}
+
+/**
+ * Sun elevation bands, in degrees: blue hour while the sun is just below the
+ * horizon, golden hour while it is low above it. Above that, the hour either
+ * side of solar noon is midday.
+ */
+export function daylightPhase(elevation: number, minutesFromNoon: number): DaylightPhase {
+  if (elevation < -6) return 'night';
+  if (elevation < -4) return 'blue_hour';
+  if (elevation < 6) return 'golden_hour';
+  if (minutesFromNoon <= MIDDAY_WINDOW_MINUTES) return 'midday';
+  return 'day';
+}
+
+/**
+ * Where the sun was when a photo was taken, worked out locally from its
+ * capture date and position
+ */
+export function computeDaylight(captured: Date | null, latitude: number | null, longitude: number | null): Daylight | null {
+  const context = getCaptureContext(captured, latitude, longitude);
+  if (!context) return null;
+  const position = sunPosition(context.instant, context.latitude, context.longitude);
+  const times = sunTimes(context.localDate, context.latitude, context.longitude);
+  const minutesFromNoon = Math.abs(context.instant.getTime() - times.solarNoon.getTime()) / 60000;
+  return {
+    phase: daylightPhase(position.elevation, minutesFromNoon),
+    sunElevation: Math.round(position.elevation * 10) / 10,
+    sunAzimuth: Math.round(position.azimuth * 10) / 10,
+    sunrise: times.sunrise?.toISOString() ?? null,
+    sunset: times.sunset?.toISOString() ?? null,
+    utcOffsetHours: context.utcOffsetHours,
+  };
+}
@@ -0,0 +1,124 @@
+import type { Event, EventRecurrenceRule } from "@shared/schema";
+
+const DAY_MS = 24 * 60 * 60 * 1000;
+
+export type RecurringEvent = Pick<Event, 'date' | 'isRecurring' | 'recurringType' | 'recurrenceRule' | 'durationDays'>;
+
+/**
+ * The event's recurrence rule, falling back to the older recurringType column
+ */
+export function getRecurrenceRule(event: RecurringEvent): EventRecurrenceRule | null {
+  if (event.recurrenceRule) return event.recurrenceRule as EventRecurrenceRule;
+  if (event.isRecurring && event.recurringType) return { frequency: event.recurringType };
+  return null;
+}
+
+/**
+ * Start dates (local midnight) of every occurrence overlapping [from, to]
+ */
+export function getOccurrencesBetween(event: RecurringEvent, from: Date, to: Date): Date[] {
+  const base = startOfDay(new Date(event.date));
+  const durationDays = Math.max(1, event.durationDays ?? 1);
+  const rule = getRecurrenceRule(event);
+  // An occurrence starting this early can still overlap the range
+  const earliestStart = new Date(startOfDay(from).getTime() - (durationDays - 1) * DAY_MS);
+  const latestStart = startOfDay(to);
+
+  if (!rule) {
+    return base >= earliestStart && base <= latestStart ? [base] : [];
+  }
+
+  const interval = rule.interval ?? 1;
+  const until = rule.until ? startOfDay(new Date(rule.until)) : null;
+  const occurrences: Date[] = [];
+  const accept = (date: Date | null) => {
+    if (!date || date < base || date < earliestStart || date > latestStart) return;
+    if (until && date > until) return;
+    occurrences.push(date);
+  };
+
+  if (rule.frequency === 'yearly') {
+    for (let year = earliestStart.getFullYear(); year <= latestStart.getFullYear(); year++) {
+      if ((year - base.getFullYear()) % interval !== 0) continue;
+      accept(occurrenceInMonth(rule, year, (rule.month ?? base.getMonth() + 1) - 1, base));
+    }
+  } else if (rule.frequency === 'monthly') {
+    const baseIndex = base.getFullYear() * 12 + base.getMonth();
+    const lastIndex = latestStart.getFullYear() * 12 + latestStart.getMonth();
+    for (let index = earliestStart.getFullYear() * 12 + earliestStart.getMonth(); index <= lastIndex; index++) {
+      if ((index - baseIndex) % interval !== 0) continue;
+      accept(occurrenceInMonth(rule, Math.floor(index / 12), index % 12, base));
+    }
+  } else {
+    const weekday = rule.weekday ?? base.getDay();
+    const firstWeek = startOfWeek(base);
+    for (let week = startOfWeek(earliestStart); week <= latestStart; week = addDays(week, 7)) {
+      const weeksSinceBase = Math.round((week.getTime() - firstWeek.getTime()) / (7 * DAY_MS));
+      if (weeksSinceBase % interval !== 0) continue;
+      accept(addDays(week, weekday));
+    }
+  }
+
+  return occurrences;
+}
+
+/**
+ * Start of the occurrence whose span contains the date, if any
+ */
+export function findOccurrence(event: RecurringEvent, date: Date): Date | null {
+  const [occurrence] = getOccurrencesBetween(event, date, date);
+  return occurrence ?? null;
+}
+
+/**
+ * Whole days between the date and the nearest day of an occurrence span (0 when inside one)
+ */
+export function daysFromOccurrence(event: RecurringEvent, date: Date, toleranceDays: number): number | null {
+  const day = startOfDay(date);
+  const durationDays = Math.max(1, event.durationDays ?? 1);
+  const nearby = getOccurrencesBetween(event, addDays(day, -toleranceDays), addDays(day, toleranceDays));
+  let best: number | null = null;
+  for (const start of nearby) {
+    const end = addDays(start, durationDays - 1);
+    const distance = day < start
+      ? Math.round((start.getTime() - day.getTime()) / DAY_MS)
+      : day > end ? Math.round((day.getTime() - end.getTime()) / DAY_MS) : 0;
+    if (best === null || distance < best) best = distance;
+  }
+  return best;
+}
+
+function occurrenceInMonth(rule: EventRecurrenceRule, year: number, month: number, base: Date): Date | null {
+  if (rule.weekOfMonth !== undefined) {
+    return nthWeekdayOfMonth(year, month, rule.weekday ?? base.getDay(), rule.weekOfMonth);
+  }
+  const day = rule.day ?? base.getDate();
+  const date = new Date(year, month, day);
+  // Skip months without the day (e.g. the 31st) instead of rolling into the next month
+  return date.getMonth() === month ? date : null;
+}
+
+/**
+ * The nth weekday of a month (n = -1 for the last), e.g. the first Tuesday of September
+ */
+function nthWeekdayOfMonth(year: number, month: number, weekday: number, n: number): Date | null {
+  if (n === -1) {
+    const last = new Date(year, month + 1, 0);
+    return addDays(last, -((last.getDay() - weekday + 7) % 7));
+  }
+  const first = new Date(year, month, 1);
+  const date = addDays(first, (weekday - first.getDay() + 7) % 7 + (n - 1) * 7);
+  return date.getMonth() === month ? date : null;
+}
+
+function startOfDay(date: Date): Date {
+  return new Date(date.getFullYear(), date.getMonth(), date.getDate());
+}
+
+function startOfWeek(date: Date): Date {
+  return addDays(startOfDay(date), -date.getDay());
+}
+
+function addDays(date: Date, days: number): Date {
+  return new Date(date.getFullYear(), date.getMonth(), date.getDate() + days);
+}
@@ -0,0 +1,54 @@
+import type { ExifMetadata, FileVersion } from "@shared/schema";
+import { getCaptureDate, parseMetadataDate } from "./photoDates";
+import { getExposureSettings } from "./exposure";
+import { computeDaylight } from "./daylight";
+
+export type ExifColumns = Pick<
+  FileVersion,
+  | 'captureDate'
+  | 'takenAt'
+  | 'cameraMake'
+  | 'cameraModel'
+  | 'camera'
+  | 'lens'
+  | 'iso'
+  | 'aperture'
+  | 'exposureTime'
+  | 'focalLength'
+  | 'gpsLatitude'
+  | 'gpsLongitude'
+  | 'daylightPhase'
+>;
+
+function coordinate(value: unknown, limit: number): number | null {
+  return typeof value === 'number' && Number.isFinite(value) && Math.abs(value) <= limit ? value : null;
+}
+
+/**
+ * The queryable EXIF columns of a photo, read from its metadata JSON, and
+ * the daylight its capture time and position imply. Stored alongside the
+ * metadata whenever it is written so the two never disagree.
+ */
+export function getExifColumns(metadata: unknown): ExifColumns {
+  const exif = (metadata as { exif?: ExifMetadata } | null | undefined)?.exif;
+  const exposure = getExposureSettings(exif);
+  const captureDate = getCaptureDate({ metadata });
+  const gpsLatitude = coordinate(exif?.gpsLatitude, 90);
+  const gpsLongitude = coordinate(exif?.gpsLongitude, 180);
+  return {
+    captureDate,
+    // extractMetadata leaves the file modification time in dateTime when EXIF has no date
+    takenAt: captureDate ?? parseMetadataDate(exif?.dateTime),
+    cameraMake: exif?.cameraMake || null,
+    cameraModel: exif?.cameraModel || null,
+    camera: exif?.camera || null,
+    lens: exif?.lens || null,
+    iso: exposure.iso !== undefined ? Math.round(exposure.iso) : null,
+    aperture: exposure.fNumber ?? null,
+    exposureTime: exposure.exposureTime ?? null,
+    focalLength: exposure.focalLength ?? null,
+    gpsLatitude,
+    gpsLongitude,
+    daylightPhase: computeDaylight(captureDate, gpsLatitude, gpsLongitude)?.phase ?? null,
+  };
+}
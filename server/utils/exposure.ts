@@ -0,0 +1,49 @@
+import type { ExifMetadata } from "@shared/schema";
+
+export interface ExposureSettings {
+  iso?: number;
+  exposureTime?: number; // seconds
+  fNumber?: number;
+  focalLength?: number; // mm
+}
+
+// Exposure values are stored the way they are shown ("f/2.8", "1/250s", "35mm")
+function parseNumber(value: unknown): number | undefined {
+  if (typeof value === 'number') return Number.isFinite(value) && value > 0 ? value : undefined;
+  if (typeof value !== 'string') return undefined;
+  const match = value.match(/\d+(\.\d+)?/);
+  if (!match) return undefined;
+  const number = Number(match[0]);
+  return number > 0 ? number : undefined;
+}
+
+function parseExposureTime(value: unknown): number | undefined {
+  if (typeof value === 'string') {
+    const fraction = value.match(/(\d+(\.\d+)?)\s*\/\s*(\d+(\.\d+)?)/);
+    if (fraction) {
+      const denominator = Number(fraction[3]);
+      return denominator > 0 ? Number(fraction[1]) / denominator : undefined;
+    }
+  }
+  return parseNumber(value);
+}
+
+/**
+ * Numeric ISO, shutter time, f-number and focal length from the EXIF block
+ */
+export function getExposureSettings(exif: ExifMetadata | undefined | null): ExposureSettings {
+  if (!exif) return {};
+  return {
+    iso: parseNumber(exif.iso),
+    exposureTime: parseExposureTime(exif.shutter),
+    fNumber: parseNumber(exif.aperture),
+    focalLength: parseNumber(exif.focalLength),
+  };
+}
+
+export function inRange(value: number | undefined, range: { min?: number; max?: number }): boolean {
+  if (value === undefined) return false;
+  if (range.min !== undefined && value < range.min) return false;
+  if (range.max !== undefined && value > range.max) return false;
+  return true;
+}
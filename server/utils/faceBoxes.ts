@@ -0,0 +1,16 @@
+// Face bounding boxes as stored on faces: [x, y, width, height] in the photo's stored pixels
+export type FaceBox = [number, number, number, number];
+
+// Overlap at which two boxes are taken to be the same face
+export const SAME_FACE_OVERLAP = 0.5;
+
+/**
+ * Intersection over union of two face boxes
+ */
+export function boxOverlap([ax, ay, aw, ah]: FaceBox, [bx, by, bw, bh]: FaceBox): number {
+  const width = Math.max(0, Math.min(ax + aw, bx + bw) - Math.max(ax, bx));
+  const height = Math.max(0, Math.min(ay + ah, by + bh) - Math.max(ay, by));
+  const intersection = width * height;
+  const union = aw * ah + bw * bh - intersection;
+  return union > 0 ? intersection / union : 0;
+}
@@ -0,0 +1,29 @@
+import fs from "fs/promises";
+import { createReadStream } from "fs";
+import { pipeline } from "stream/promises";
+import crypto from "crypto";
+
+/**
+ * MD5 of a file, as stored in file_versions.file_hash. The file is streamed
+ * so large videos are never read into memory whole.
+ */
+export async function hashFile(filePath: string): Promise<string> {
+  const hash = crypto.createHash('md5');
+  await pipeline(createReadStream(filePath), hash);
+  return (hash.read() as Buffer).toString('hex');
+}
+
+/**
+ * Copy via a temporary sibling and rename, so an interrupted copy never
+ * leaves a truncated file at the destination
+ */
+export async function copyFileAtomic(source: string, destination: string): Promise<void> {
+  const partialPath = `${destination}.partial`;
+  try {
+    await fs.copyFile(source, partialPath);
+    await fs.rename(partialPath, destination);
+  } catch (error) {
+    await fs.rm(partialPath, { force: true });
+    throw error;
+  }
+}
@@ -0,0 +1,207 @@
+import { eventRecurrenceRuleSchema, type EventRecurrenceRule } from "@shared/schema";
+import { getOccurrencesBetween } from "./eventRecurrence";
+
+const DAY_MS = 24 * 60 * 60 * 1000;
+const WEEKDAYS = ['SU', 'MO', 'TU', 'WE', 'TH', 'FR', 'SA'];
+const FREQUENCIES: Record<string, EventRecurrenceRule['frequency']> = {
+  YEARLY: 'yearly',
+  MONTHLY: 'monthly',
+  WEEKLY: 'weekly',
+};
+
+export interface IcsEvent {
+  uid: string | null;
+  summary: string;
+  start: Date; // local midnight; times and time zones are ignored, holidays are whole days
+  durationDays: number;
+  recurrenceRule: EventRecurrenceRule | null;
+  extraDates: Date[]; // RDATE values
+}
+
+export interface IcsCalendar {
+  name: string | null; // X-WR-CALNAME
+  events: IcsEvent[];
+  skipped: string[]; // events whose recurrence cannot be represented
+}
+
+interface IcsProperty {
+  name: string;
+  params: Record<string, string>;
+  value: string;
+}
+
+/**
+ * Parse the all-day subset of iCalendar used by holiday calendars: DTSTART,
+ * DTEND/DURATION, RDATE and RRULEs that map onto an EventRecurrenceRule
+ */
+export function parseIcs(text: string): IcsCalendar {
+  const calendar: IcsCalendar = { name: null, events: [], skipped: [] };
+  let current: IcsProperty[] | null = null;
+
+  for (const property of unfoldLines(text).map(parseProperty)) {
+    if (!property) continue;
+    if (property.name === 'BEGIN' && property.value.toUpperCase() === 'VEVENT') {
+      current = [];
+    } else if (property.name === 'END' && property.value.toUpperCase() === 'VEVENT') {
+      if (current) {
+        const summary = unescapeText(current.find(p => p.name === 'SUMMARY')?.value ?? 'Untitled');
+        try {
+          calendar.events.push(toEvent(current, summary));
+        } catch (error) {
+          calendar.skipped.push(`${summary}: ${error instanceof Error ? error.message : String(error)}`);
+        }
+      }
+      current = null;
+    } else if (current) {
+      current.push(property);
+    } else if (property.name === 'X-WR-CALNAME') {
+      calendar.name = unescapeText(property.value);
+    }
+  }
+
+  return calendar;
+}
+
+/**
+ * Replace (or add) the calendar's display name
+ */
+export function setIcsCalendarName(text: string, name: string): string {
+  const lines = unfoldLines(text).filter(line => !/^X-WR-CALNAME[;:]/i.test(line));
+  const begin = lines.findIndex(line => /^BEGIN:VCALENDAR$/i.test(line.trim()));
+  lines.splice(begin + 1, 0, `X-WR-CALNAME:${escapeText(name)}`);
+  return lines.join('\r\n') + '\r\n';
+}
+
+function toEvent(properties: IcsProperty[], summary: string): IcsEvent {
+  const get = (name: string) => properties.find(p => p.name === name);
+  const dtstart = get('DTSTART');
+  if (!dtstart) throw new Error('missing DTSTART');
+  const start = parseDate(dtstart.value);
+
+  let durationDays = 1;
+  const dtend = get('DTEND');
+  const duration = get('DURATION');
+  if (dtend) {
+    // DTEND is exclusive for all-day events
+    durationDays = Math.max(1, Math.round((parseDate(dtend.value).getTime() - start.getTime()) / DAY_MS));
+  } else if (duration) {
+    const match = duration.value.match(/^P(?:(\d+)W)?(?:(\d+)D)?/i);
+    if (match) durationDays = Math.max(1, Number(match[1] ?? 0) * 7 + Number(match[2] ?? 0));
+  }
+
+  const extraDates = properties
+    .filter(p => p.name === 'RDATE')
+    .flatMap(p => p.value.split(',').map(value => parseDate(value)));
+
+  const rrule = get('RRULE');
+  const recurrenceRule = rrule ? parseRecurrenceRule(rrule.value, start, durationDays) : null;
+
+  return {
+    uid: get('UID')?.value ?? null,
+    summary,
+    start,
+    durationDays,
+    recurrenceRule,
+    extraDates,
+  };
+}
+
+function parseRecurrenceRule(value: string, start: Date, durationDays: number): EventRecurrenceRule {
+  const parts: Record<string, string> = {};
+  for (const part of value.split(';')) {
+    const [key, partValue] = part.split('=');
+    if (key && partValue !== undefined) parts[key.toUpperCase()] = partValue.toUpperCase();
+  }
+
+  const frequency = FREQUENCIES[parts.FREQ];
+  if (!frequency) throw new Error(`unsupported frequency ${parts.FREQ ?? '(none)'}`);
+  const unsupported = Object.keys(parts).filter(key => !['FREQ', 'INTERVAL', 'UNTIL', 'COUNT', 'BYMONTH', 'BYMONTHDAY', 'BYDAY', 'WKST'].includes(key));
+  if (unsupported.length > 0) throw new Error(`unsupported ${unsupported.join(', ')}`);
+
+  const rule: EventRecurrenceRule = { frequency };
+  if (parts.INTERVAL) rule.interval = Number(parts.INTERVAL);
+  if (parts.BYMONTH) rule.month = Number(single(parts.BYMONTH, 'BYMONTH'));
+  if (parts.BYMONTHDAY) rule.day = Number(single(parts.BYMONTHDAY, 'BYMONTHDAY'));
+  if (parts.BYDAY) {
+    const match = single(parts.BYDAY, 'BYDAY').match(/^([+-]?\d)?(SU|MO|TU|WE|TH|FR|SA)$/);
+    if (!match) throw new Error(`unsupported BYDAY ${parts.BYDAY}`);
+    rule.weekday = WEEKDAYS.indexOf(match[2]);
+    if (match[1]) {
+      rule.weekOfMonth = Number(match[1]) as EventRecurrenceRule['weekOfMonth'];
+    } else if (frequency !== 'weekly') {
+      throw new Error('BYDAY without a position is only supported for weekly rules');
+    }
+  }
+  if (parts.UNTIL) rule.until = toIsoDate(parseDate(parts.UNTIL));
+
+  const parsed = eventRecurrenceRuleSchema.safeParse(rule);
+  if (!parsed.success) throw new Error(`unsupported RRULE ${value}`);
+
+  if (parts.COUNT && !rule.until) {
+    // COUNT becomes the date of the last occurrence
+    const count = Number(parts.COUNT);
+    const horizon = new Date(start.getTime() + count * (rule.interval ?? 1) * 366 * DAY_MS);
+    const occurrences = getOccurrencesBetween(
+      { date: start, isRecurring: false, recurringType: null, recurrenceRule: parsed.data, durationDays },
+      start,
+      horizon
+    );
+    const last = occurrences[Math.min(count, occurrences.length) - 1];
+    if (last) parsed.data.until = toIsoDate(last);
+  }
+
+  return parsed.data;
+}
+
+function single(value: string, name: string): string {
+  if (value.includes(',')) throw new Error(`multiple ${name} values`);
+  return value;
+}
+
+function parseDate(value: string): Date {
+  const match = value.trim().match(/^(\d{4})(\d{2})(\d{2})/);
+  if (!match) throw new Error(`invalid date ${value}`);
+  return new Date(Number(match[1]), Number(match[2]) - 1, Number(match[3]));
+}
+
+function toIsoDate(date: Date): string {
+  return `${date.getFullYear()}-${String(date.getMonth() + 1).padStart(2, '0')}-${String(date.getDate()).padStart(2, '0')}`;
+}
+
+function unfoldLines(text: string): string[] {
+  return text
+    .replace(/\r\n/g, '\n')
+    .replace(/\n[ \t]/g, '')
+    .split('\n')
+    .filter(line => line.trim().length > 0);
+}
+
+function parseProperty(line: string): IcsProperty | null {
+  // The value starts at the first colon outside a quoted parameter
+  let inQuotes = false;
+  let colon = -1;
+  for (let index = 0; index < line.length; index++) {
+    if (line[index] === '"') inQuotes = !inQuotes;
+    if (line[index] === ':' && !inQuotes) {
+      colon = index;
+      break;
+    }
+  }
+  if (colon === -1) return null;
+
+  const [name, ...paramParts] = line.slice(0, colon).split(';');
+  const params: Record<string, string> = {};
+  for (const param of paramParts) {
+    const [key, paramValue = ''] = param.split('=');
+    params[key.toUpperCase()] = paramValue.replace(/^"|"$/g, '');
+  }
+  return { name: name.toUpperCase(), params, value: line.slice(colon + 1) };
+}
+
+function unescapeText(value: string): string {
+  return value.replace(/\\([\\;,nN])/g, (_, char: string) => (char === 'n' || char === 'N' ? '\n' : char));
+}
+
+function escapeText(value: string): string {
+  return value.replace(/[\\;,]/g, char => `\\${char}`).replace(/\n/g, '\\n');
+}
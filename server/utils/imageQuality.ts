@@ -0,0 +1,67 @@
+import { displayImage } from "./colorProfile";
+
+// Long side the measurements are taken at, so they compare across resolutions
+const ANALYSIS_SIZE = 512;
+// Laplacian variance at which sharpness reaches about 63%
+const SHARPNESS_SCALE = 300;
+const MID_GREY = 118;
+
+export interface LumaImage {
+  luma: Uint8Array;
+  width: number;
+  height: number;
+}
+
+/**
+ * The image upright and greyscale, at most ANALYSIS_SIZE on its long side
+ */
+export async function loadLuma(filePath: string): Promise<LumaImage> {
+  const { data, info } = await displayImage(filePath)
+    .resize(ANALYSIS_SIZE, ANALYSIS_SIZE, { fit: 'inside', withoutEnlargement: true })
+    .removeAlpha()
+    .greyscale()
+    .raw()
+    .toBuffer({ resolveWithObject: true });
+  const luma = info.channels === 1 ? data : data.filter((_, index) => index % info.channels === 0);
+  return { luma, width: info.width, height: info.height };
+}
+
+/**
+ * Variance of the 4-neighbour Laplacian, 0-1: edges in focus give large
+ * responses, blur and camera shake flatten them
+ */
+export function measureSharpness({ luma, width, height }: LumaImage): number {
+  let sum = 0;
+  let sumOfSquares = 0;
+  let count = 0;
+  for (let y = 1; y < height - 1; y++) {
+    for (let x = 1; x < width - 1; x++) {
+      const index = y * width + x;
+      const response = luma[index - width] + luma[index + width] + luma[index - 1] + luma[index + 1] - 4 * luma[index];
+      sum += response;
+      sumOfSquares += response * response;
+      count++;
+    }
+  }
+  if (count === 0) return 0;
+  const mean = sum / count;
+  const variance = sumOfSquares / count - mean * mean;
+  return Math.round((1 - Math.exp(-variance / SHARPNESS_SCALE)) * 1000) / 1000;
+}
+
+/**
+ * Exposure, 0-1: penalizes a mean far from mid grey, and crushed shadows or
+ * blown highlights
+ */
+export function measureExposure({ luma }: LumaImage): number {
+  if (luma.length === 0) return 0;
+  let sum = 0;
+  let clipped = 0;
+  for (const value of luma) {
+    sum += value;
+    if (value <= 4 || value >= 251) clipped++;
+  }
+  const offset = Math.abs(sum / luma.length - MID_GREY) / MID_GREY;
+  const score = 1 - 0.8 * offset - 2 * (clipped / luma.length);
+  return Math.round(Math.min(1, Math.max(0, score)) * 1000) / 1000;
+}
@@ -0,0 +1,40 @@
+import path from "path";
+
+/**
+ * Writable per-user directory when running inside the packaged desktop app,
+ * whose working directory is the read-only install folder. Null from a
+ * checkout or container, where the working directory is used.
+ */
+export function getAppDataDir(): string | null {
+  return process.env.PICTALLION_APP_DATA_DIR ? path.resolve(process.env.PICTALLION_APP_DATA_DIR) : null;
+}
+
+export function getWritableBaseDir(): string {
+  return getAppDataDir() ?? process.cwd();
+}
+
+// Photo paths stored in the database are relative to this directory
+let libraryRoot = path.resolve(process.env.PICTALLION_LIBRARY_ROOT || path.join(getWritableBaseDir(), 'data'));
+
+export function getLibraryRoot(): string {
+  return libraryRoot;
+}
+
+export function setLibraryRoot(root: string): void {
+  libraryRoot = path.resolve(root);
+}
+
+/**
+ * Absolute path of a file or folder inside the library root
+ */
+export function libraryPath(...segments: string[]): string {
+  return path.join(libraryRoot, ...segments);
+}
+
+/**
+ * Absolute path inside the scratch folder for uploads being processed and
+ * generated face crops
+ */
+export function scratchPath(...segments: string[]): string {
+  return path.join(getWritableBaseDir(), 'uploads', 'temp', ...segments);
+}
@@ -0,0 +1,52 @@
+import { z } from "zod";
+
+export const MAX_PAGE_SIZE = 500;
+
+export const pageQuerySchema = z.object({
+  limit: z.coerce.number().int().min(1).max(MAX_PAGE_SIZE).optional(),
+  offset: z.coerce.number().int().min(0).optional(),
+  cursor: z.string().min(1).optional(), // nextCursor of the previous page; takes precedence over offset
+});
+
+export type PageQuery = z.infer<typeof pageQuerySchema>;
+
+export interface Page<T> {
+  photos: T[];
+  totalCount: number;
+  nextCursor: string | null; // null on the last page
+}
+
+/**
+ * Where the previous page ended: the sort values of its last photo, as
+ * Postgres text so timestamps keep their microseconds, and that photo's id,
+ * which breaks ties. A page resumes after that point in the sort order, so
+ * photos imported or deleted while the user scrolls do not shift the pages
+ * the way a plain offset would.
+ */
+export interface CursorState {
+  keys: string[];
+  id: string;
+}
+
+/**
+ * Whether the caller asked for a page at all; listings keep returning a plain
+ * array to callers that did not
+ */
+export function wantsPage(query: PageQuery): boolean {
+  return query.limit !== undefined || query.offset !== undefined || query.cursor !== undefined;
+}
+
+export function encodeCursor(state: CursorState): string {
+  return Buffer.from(JSON.stringify(state)).toString('base64url');
+}
+
+export function decodeCursor(cursor: string): CursorState | null {
+  try {
+    const state = JSON.parse(Buffer.from(cursor, 'base64url').toString('utf8'));
+    const valid = typeof state?.id === 'string' && Array.isArray(state?.keys) &&
+      state.keys.every((key: unknown) => typeof key === 'string');
+    return valid ? state : null;
+  } catch {
+    return null;
+  }
+}
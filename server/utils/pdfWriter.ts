@@ -0,0 +1,126 @@
+// A4 in PDF points
+const A4_SHORT = 595.28;
+const A4_LONG = 841.89;
+const PAGE_MARGIN = 36;
+const CAPTION_HEIGHT = 18;
+
+export interface PdfImagePage {
+  jpeg: Buffer; // baseline JPEG data, embedded as-is
+  width: number;
+  height: number;
+  grayscale: boolean;
+  caption?: string;
+}
+
+export interface PdfOptions {
+  title?: string;
+  creationDate?: Date;
+}
+
+const format = (value: number) => (Math.round(value * 100) / 100).toString();
+
+// Literal strings only support Latin-1; escape the delimiters and drop the rest
+function pdfString(value: string): string {
+  return `(${value.replace(/[^\x20-\x7e\xa0-\xff]/g, '?').replace(/([\\()])/g, '\\$1')})`;
+}
+
+function pdfDate(date: Date): string {
+  const pad = (n: number) => String(n).padStart(2, '0');
+  return `D:${date.getUTCFullYear()}${pad(date.getUTCMonth() + 1)}${pad(date.getUTCDate())}${pad(date.getUTCHours())}${pad(date.getUTCMinutes())}${pad(date.getUTCSeconds())}Z`;
+}
+
+/**
+ * Build a PDF with one A4 page per JPEG image, fitted inside the margins with an
+ * optional caption line underneath. Pages follow the image orientation.
+ */
+export function buildImagePdf(pages: PdfImagePage[], options: PdfOptions = {}): Buffer {
+  const objects: Buffer[] = [];
+  const addObject = (body: Buffer | string): number => {
+    objects.push(typeof body === 'string' ? Buffer.from(body, 'latin1') : body);
+    return objects.length;
+  };
+
+  // Reserve ids for the catalog, page tree, font and info dictionaries
+  const catalogId = addObject('');
+  const pagesId = addObject('');
+  const fontId = addObject('<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>');
+  const infoId = addObject('');
+
+  const pageIds: number[] = [];
+  for (const page of pages) {
+    const imageId = addObject(Buffer.concat([
+      Buffer.from(
+        `<< /Type /XObject /Subtype /Image /Width ${page.width} /Height ${page.height} ` +
+        `/ColorSpace /${page.grayscale ? 'DeviceGray' : 'DeviceRGB'} /BitsPerComponent 8 ` +
+        `/Filter /DCTDecode /Length ${page.jpeg.length} >>\nstream\n`,
+        'latin1'
+      ),
+      page.jpeg,
+      Buffer.from('\nendstream', 'latin1'),
+    ]));
+
+    const landscape = page.width > page.height;
+    const pageWidth = landscape ? A4_LONG : A4_SHORT;
+    const pageHeight = landscape ? A4_SHORT : A4_LONG;
+    const boxWidth = pageWidth - PAGE_MARGIN * 2;
+    const boxHeight = pageHeight - PAGE_MARGIN * 2 - (page.caption ? CAPTION_HEIGHT : 0);
+    const scale = Math.min(boxWidth / page.width, boxHeight / page.height);
+    const drawWidth = page.width * scale;
+    const drawHeight = page.height * scale;
+    const x = (pageWidth - drawWidth) / 2;
+    const y = pageHeight - PAGE_MARGIN - drawHeight;
+
+    let content = `q ${format(drawWidth)} 0 0 ${format(drawHeight)} ${format(x)} ${format(y)} cm /Im0 Do Q\n`;
+    if (page.caption) {
+      content += `BT /F1 9 Tf ${format(PAGE_MARGIN)} ${format(PAGE_MARGIN)} Td ${pdfString(page.caption)} Tj ET\n`;
+    }
+    const contentId = addObject(`<< /Length ${Buffer.byteLength(content, 'latin1')} >>\nstream\n${content}endstream`);
+
+    pageIds.push(addObject(
+      `<< /Type /Page /Parent ${pagesId} 0 R /MediaBox [0 0 ${format(pageWidth)} ${format(pageHeight)}] ` +
+      `/Resources << /XObject << /Im0 ${imageId} 0 R >> /Font << /F1 ${fontId} 0 R >> >> /Contents ${contentId} 0 R >>`
+    ));
+  }
+
+  objects[catalogId - 1] = Buffer.from(`<< /Type /Catalog /Pages ${pagesId} 0 R >>`, 'latin1');
+  objects[pagesId - 1] = Buffer.from(
+    `<< /Type /Pages /Kids [${pageIds.map(id => `${id} 0 R`).join(' ')}] /Count ${pageIds.length} >>`,
+    'latin1'
+  );
+  const info = [
+    options.title ? `/Title ${pdfString(options.title)}` : '',
+    `/Producer (Pictallion)`,
+    `/CreationDate (${pdfDate(options.creationDate ?? new Date())})`,
+  ].filter(Boolean).join(' ');
+  objects[infoId - 1] = Buffer.from(`<< ${info} >>`, 'latin1');
+
+  // Serialize with a cross-reference table of byte offsets
+  const chunks: Buffer[] = [Buffer.from('%PDF-1.4\n%\xe2\xe3\xcf\xd3\n', 'latin1')];
+  let offset = chunks[0].length;
+  const offsets: number[] = [];
+  objects.forEach((body, index) => {
+    offsets.push(offset);
+    const chunk = Buffer.concat([
+      Buffer.from(`${index + 1} 0 obj\n`, 'latin1'),
+      body,
+      Buffer.from('\nendobj\n', 'latin1'),
+    ]);
+    chunks.push(chunk);
+    offset += chunk.length;
+  });
+
+  const xref = [
+    'xref',
+    `0 ${objects.length + 1}`,
+    '0000000000 65535 f ',
+    ...offsets.map(value => `${String(value).padStart(10, '0')} 00000 n `),
+    'trailer',
+    `<< /Size ${objects.length + 1} /Root ${catalogId} 0 R /Info ${infoId} 0 R >>`,
+    'startxref',
+    String(offset),
+    '%%EOF',
+  ].join('\n');
+  chunks.push(Buffer.from(xref + '\n', 'latin1'));
+
+  return Buffer.concat(chunks);
+}
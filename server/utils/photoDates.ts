@@ -0,0 +1,126 @@
+/**
+ * Best-known capture date for a photo from its EXIF metadata. `exif.dateTime` is
+ * not used since extractMetadata fills it with the file modification time.
+ */
+export function getCaptureDate(photo: { metadata?: unknown }): Date | null {
+  const exif = (photo.metadata as any)?.exif;
+  if (!exif) return null;
+
+  for (const value of [exif.dateTimeOriginal, exif.createDate, exif.dateTaken]) {
+    const date = parseMetadataDate(value);
+    if (date) return date;
+  }
+  return null;
+}
+
+/**
+ * EXIF DateTimeOriginal with its SubSecTimeOriginal fraction, to the
+ * millisecond. Null when the camera did not record when the shutter fired:
+ * file times and import times are too coarse to order frames by.
+ */
+export function getShutterTime(photo: { metadata?: unknown }): Date | null {
+  const exif = (photo.metadata as any)?.exif;
+  const date = parseMetadataDate(exif?.dateTimeOriginal);
+  if (!date) return null;
+  const subSeconds = exif.subSecTimeOriginal;
+  if (typeof subSeconds !== 'string' || !/^\d+$/.test(subSeconds)) return date;
+  // "5" is half a second and "045" 45 milliseconds, so read it as a decimal fraction
+  const fraction = Number(`0.${subSeconds}`);
+  return new Date(Math.floor(date.getTime() / 1000) * 1000 + Math.round(fraction * 1000));
+}
+
+/**
+ * Parse either an ISO date or the EXIF "YYYY:MM:DD HH:MM:SS" form
+ */
+export function parseMetadataDate(value: unknown): Date | null {
+  if (!value || typeof value !== 'string') return null;
+  const normalized = value.replace(/^(\d{4}):(\d{2}):(\d{2})/, '$1-$2-$3');
+  const date = new Date(normalized);
+  return !isNaN(date.getTime()) && date.getFullYear() > 1900 ? date : null;
+}
+
+/**
+ * When the photo was taken: the stored capture date (or file modification
+ * time), then the EXIF dates, then the library import time
+ */
+export function getEffectiveDate(photo: { metadata?: unknown; takenAt?: Date | null; createdAt: Date }): Date {
+  if (photo.takenAt) return new Date(photo.takenAt);
+  return getCaptureDate(photo) ?? new Date(photo.createdAt);
+}
+
+/**
+ * Photo date from EXIF, then a YYYYMMDD_HHMMSS original filename, then the
+ * import time. Expects the photo joined with its media asset.
+ */
+export function extractPhotoDate(photo: any): Date | undefined {
+  try {
+    // Extract date from photo metadata or filename
+
+    // First try EXIF datetime fields with various formats
+    if (photo.metadata?.exif) {
+      const exif = photo.metadata.exif;
+
+      // Try DateTimeOriginal first (most accurate)
+      if (exif.dateTimeOriginal) {
+        const date = new Date(exif.dateTimeOriginal);
+        if (!isNaN(date.getTime())) {
+          console.log('Using EXIF DateTimeOriginal:', date.toISOString());
+          return date;
+        }
+      }
+
+      // Try CreateDate
+      if (exif.createDate) {
+        const date = new Date(exif.createDate);
+        if (!isNaN(date.getTime())) {
+          console.log('Using EXIF CreateDate:', date.toISOString());
+          return date;
+        }
+      }
+
+      // Try DateTime
+      if (exif.dateTime) {
+        const date = new Date(exif.dateTime);
+        if (!isNaN(date.getTime())) {
+          console.log('Using EXIF DateTime:', date.toISOString());
+          return date;
+        }
+      }
+    }
+
+    // Try to extract from filename if it has timestamp format (YYYYMMDD_HHMMSS)
+    const filename = photo.mediaAsset?.originalFilename || '';
+    const timestampMatch = filename.match(/^(\d{8})_(\d{6})/);
+    if (timestampMatch) {
+      const dateStr = timestampMatch[1]; // YYYYMMDD
+      const timeStr = timestampMatch[2]; // HHMMSS
+      const year = parseInt(dateStr.substring(0, 4));
+      const month = parseInt(dateStr.substring(4, 6)) - 1; // Month is 0-indexed
+      const day = parseInt(dateStr.substring(6, 8));
+      const hour = parseInt(timeStr.substring(0, 2));
+      const minute = parseInt(timeStr.substring(2, 4));
+      const second = parseInt(timeStr.substring(4, 6));
+
+      const extractedDate = new Date(year, month, day, hour, minute, second);
+      if (!isNaN(extractedDate.getTime())) {
+        console.log('Using filename timestamp:', extractedDate.toISOString());
+        return extractedDate;
+      }
+    }
+
+    // Fall back to file creation time
+    if (photo.createdAt) {
+      const date = new Date(photo.createdAt);
+      if (!isNaN(date.getTime())) {
+        console.log('Using createdAt:', date.toISOString());
+        return date;
+      }
+    }
+
+    console.log('No valid date found, returning undefined');
+    return undefined;
+  } catch (error) {
+    console.error('Error extracting photo date:', error);
+    return undefined;
+  }
+}
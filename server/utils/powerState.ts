@@ -0,0 +1,79 @@
+import fs from "fs/promises";
+import path from "path";
+import { execFile } from "child_process";
+
+const COMMAND_TIMEOUT_MS = 5000;
+
+function run(command: string, args: string[]): Promise<string> {
+  return new Promise((resolve, reject) => {
+    execFile(command, args, { timeout: COMMAND_TIMEOUT_MS, windowsHide: true }, (error, stdout) => {
+      if (error) reject(error);
+      else resolve(stdout);
+    });
+  });
+}
+
+/**
+ * Whether the machine is running on battery. Null when it cannot be told,
+ * e.g. desktops without a battery or containers without access to the host.
+ */
+export async function isOnBatteryPower(): Promise<boolean | null> {
+  try {
+    if (process.platform === 'linux') {
+      const supplyDir = '/sys/class/power_supply';
+      const supplies = await fs.readdir(supplyDir);
+      let hasBattery = false;
+      for (const supply of supplies) {
+        const type = (await fs.readFile(path.join(supplyDir, supply, 'type'), 'utf8').catch(() => '')).trim();
+        if (type === 'Mains' || type === 'USB') {
+          const online = (await fs.readFile(path.join(supplyDir, supply, 'online'), 'utf8').catch(() => '')).trim();
+          if (online === '1') return false;
+        } else if (type === 'Battery') {
+          hasBattery = true;
+        }
+      }
+      return hasBattery ? true : null;
+    }
+    if (process.platform === 'darwin') {
+      const output = await run('pmset', ['-g', 'batt']);
+      if (output.includes("'Battery Power'")) return true;
+      if (output.includes("'AC Power'")) return false;
+      return null;
+    }
+    if (process.platform === 'win32') {
+      // BatteryStatus 1 means discharging; no rows means no battery
+      const output = await run('powershell', ['-NoProfile', '-Command', '(Get-CimInstance Win32_Battery).BatteryStatus']);
+      const status = output.trim();
+      return status ? status.split(/\s+/).includes('1') : null;
+    }
+  } catch {
+    // fall through to unknown
+  }
+  return null;
+}
+
+/**
+ * Whether the active network connection is metered (mobile hotspot, capped
+ * plans). Null when the platform does not say.
+ */
+export async function isNetworkMetered(): Promise<boolean | null> {
+  try {
+    if (process.platform === 'linux') {
+      // NetworkManager reports yes, no, or a guess for each device
+      const output = await run('nmcli', ['-t', '-f', 'GENERAL.METERED', 'dev', 'show']);
+      const values = output.split('\n').map(line => line.split(':')[1]?.trim()).filter(Boolean);
+      if (values.length === 0) return null;
+      return values.some(value => value!.startsWith('yes'));
+    }
+    if (process.platform === 'win32') {
+      const output = await run('powershell', ['-NoProfile', '-Command',
+        '[Windows.Networking.Connectivity.NetworkInformation,Windows.Networking.Connectivity,ContentType=WindowsRuntime]::GetInternetConnectionProfile().GetConnectionCost().NetworkCostType']);
+      const cost = output.trim();
+      if (!cost) return null;
+      return cost === 'Fixed' || cost === 'Variable';
+    }
+  } catch {
+    // fall through to unknown
+  }
+  return null;
+}
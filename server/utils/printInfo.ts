@@ -0,0 +1,92 @@
+export const PRINT_DPI_LEVELS = [300, 200, 150] as const;
+
+// Common print formats in inches (short side x long side)
+export const PRINT_FORMATS: Array<{ name: string; width: number; height: number }> = [
+  { name: '4x6', width: 4, height: 6 },
+  { name: '5x7', width: 5, height: 7 },
+  { name: '8x10', width: 8, height: 10 },
+  { name: '8.5x11', width: 8.5, height: 11 },
+  { name: '11x14', width: 11, height: 14 },
+  { name: '12x18', width: 12, height: 18 },
+  { name: '16x20', width: 16, height: 20 },
+  { name: '20x30', width: 20, height: 30 },
+];
+
+export interface PrintSize {
+  dpi: number;
+  widthInches: number;
+  heightInches: number;
+  widthCm: number;
+  heightCm: number;
+}
+
+export interface CropWarning {
+  format: string;
+  cropPercent: number;
+  maxDpi: number;
+  lowResolution: boolean;
+}
+
+export interface PrintInfo {
+  width: number;
+  height: number;
+  megapixels: number;
+  aspectRatio: number;
+  orientation: 'portrait' | 'landscape' | 'square';
+  maxPrintSizes: PrintSize[];
+  cropWarnings: CropWarning[];
+}
+
+const round = (value: number, places = 1) => Math.round(value * 10 ** places) / 10 ** places;
+
+/**
+ * Classify dimensions as portrait/landscape/square, treating near-equal sides as square
+ */
+export function getOrientation(width: number, height: number, squareTolerance = 0.02): PrintInfo['orientation'] {
+  if (Math.abs(width / height - 1) <= squareTolerance) return 'square';
+  return width > height ? 'landscape' : 'portrait';
+}
+
+/**
+ * Compute print-prep information from pixel dimensions
+ */
+export function calculatePrintInfo(width: number, height: number): PrintInfo {
+  const maxPrintSizes = PRINT_DPI_LEVELS.map(dpi => ({
+    dpi,
+    widthInches: round(width / dpi),
+    heightInches: round(height / dpi),
+    widthCm: round((width / dpi) * 2.54),
+    heightCm: round((height / dpi) * 2.54),
+  }));
+
+  const shortSide = Math.min(width, height);
+  const longSide = Math.max(width, height);
+  const imageRatio = longSide / shortSide;
+
+  const cropWarnings: CropWarning[] = [];
+  for (const format of PRINT_FORMATS) {
+    const formatRatio = format.height / format.width;
+    // Fraction of the image lost when cropping to the format's aspect ratio
+    const cropFraction = imageRatio > formatRatio
+      ? 1 - formatRatio / imageRatio
+      : 1 - imageRatio / formatRatio;
+    const cropPercent = round(cropFraction * 100);
+
+    const croppedShort = imageRatio > formatRatio ? shortSide : longSide / formatRatio;
+    const maxDpi = Math.floor(croppedShort / format.width);
+
+    if (cropPercent >= 1 || maxDpi < 150) {
+      cropWarnings.push({ format: format.name, cropPercent, maxDpi, lowResolution: maxDpi < 150 });
+    }
+  }
+
+  return {
+    width,
+    height,
+    megapixels: round((width * height) / 1_000_000),
+    aspectRatio: round(width / height, 3),
+    orientation: getOrientation(width, height),
+    maxPrintSizes,
+    cropWarnings,
+  };
+}
@@ -0,0 +1,68 @@
+/**
+ * Settings whose JSON values hold secrets, by key, with the dotted paths of
+ * the secret fields. The generic settings endpoints never return those
+ * fields, and a write that leaves them out keeps the saved ones, so a
+ * settings page that saves back what it read does not wipe them.
+ */
+const SECRET_FIELDS: Record<string, string[]> = {
+  smtp_config: ['password'],
+  ai_provider_config: ['openai.apiKey'],
+};
+
+/**
+ * The setting's value without its secret fields
+ */
+export function redactSettingValue(key: string, value: string): string {
+  const paths = SECRET_FIELDS[key];
+  const parsed = paths && parseObject(value);
+  if (!parsed) return value;
+  for (const path of paths) {
+    const parts = path.split('.');
+    const parent = walk(parsed, parts.slice(0, -1));
+    if (parent) delete parent[parts[parts.length - 1]];
+  }
+  return JSON.stringify(parsed);
+}
+
+export function redactSetting<T extends { key: string; value: string }>(setting: T): T {
+  return { ...setting, value: redactSettingValue(setting.key, setting.value) };
+}
+
+/**
+ * A new value for the setting with any secret it leaves out taken from the
+ * saved value
+ */
+export function keepSavedSecrets(key: string, value: string, savedValue: string | undefined): string {
+  const paths = SECRET_FIELDS[key];
+  const parsed = paths && parseObject(value);
+  const saved = savedValue !== undefined ? parseObject(savedValue) : null;
+  if (!parsed || !saved) return value;
+  for (const path of paths) {
+    const parts = path.split('.');
+    const field = parts[parts.length - 1];
+    const parent = walk(parsed, parts.slice(0, -1));
+    const savedParent = walk(saved, parts.slice(0, -1));
+    if (parent && savedParent && parent[field] === undefined && savedParent[field] !== undefined) {
+      parent[field] = savedParent[field];
+    }
+  }
+  return JSON.stringify(parsed);
+}
+
+function parseObject(value: string): Record<string, any> | null {
+  try {
+    const parsed = JSON.parse(value);
+    return parsed && typeof parsed === 'object' && !Array.isArray(parsed) ? parsed : null;
+  } catch {
+    return null;
+  }
+}
+
+function walk(object: Record<string, any>, parts: string[]): Record<string, any> | null {
+  let current: any = object;
+  for (const part of parts) {
+    current = current?.[part];
+    if (!current || typeof current !== 'object') return null;
+  }
+  return current;
+}
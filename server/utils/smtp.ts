@@ -0,0 +1,265 @@
+import crypto from "crypto";
+import net from "net";
+import os from "os";
+import tls from "tls";
+
+const COMMAND_TIMEOUT_MS = 60 * 1000;
+const BASE64_LINE = 76;
+
+export interface SmtpConfig {
+  host: string;
+  port: number;
+  secure: boolean; // TLS from the start (port 465); otherwise STARTTLS when the server offers it
+  allowInsecureAuth?: boolean; // send the username and password even when the connection is not encrypted
+  username?: string;
+  password?: string;
+  from: string;
+}
+
+export interface MailAttachment {
+  filename: string;
+  contentType: string;
+  content: Buffer;
+}
+
+export interface MailMessage {
+  to: string[];
+  subject: string;
+  text: string;
+  attachments: MailAttachment[];
+}
+
+export class SmtpError extends Error {
+  constructor(message: string, readonly code?: number) {
+    super(message);
+  }
+}
+
+interface SmtpReply {
+  code: number;
+  lines: string[];
+}
+
+/**
+ * One SMTP session over a socket: reads multi-line replies and sends
+ * commands, upgrading to TLS when asked
+ */
+class SmtpConnection {
+  private buffer = '';
+  private replies: SmtpReply[] = [];
+  private current: string[] = [];
+  private waiting: { resolve: (reply: SmtpReply) => void; reject: (error: Error) => void } | null = null;
+  private failure: Error | null = null;
+
+  constructor(private socket: net.Socket) {
+    this.attach(socket);
+  }
+
+  static async open(config: SmtpConfig): Promise<SmtpConnection> {
+    const socket = await new Promise<net.Socket>((resolve, reject) => {
+      const connected = config.secure
+        ? tls.connect({ host: config.host, port: config.port, servername: config.host }, () => resolve(connected))
+        : net.connect({ host: config.host, port: config.port }, () => resolve(connected));
+      connected.once('error', error => reject(new SmtpError(`Cannot reach ${config.host}:${config.port}: ${error.message}`)));
+    });
+    return new SmtpConnection(socket);
+  }
+
+  async upgrade(host: string): Promise<void> {
+    this.socket.removeAllListeners('data');
+    const secured = await new Promise<tls.TLSSocket>((resolve, reject) => {
+      const upgraded = tls.connect({ socket: this.socket, servername: host }, () => resolve(upgraded));
+      upgraded.once('error', reject);
+    });
+    this.socket = secured;
+    this.attach(secured);
+  }
+
+  async command(line: string, expected: number[]): Promise<SmtpReply> {
+    this.socket.write(`${line}\r\n`);
+    return this.expect(expected, line.startsWith('AUTH') ? 'AUTH' : line.split(' ')[0]);
+  }
+
+  async expect(expected: number[], label: string): Promise<SmtpReply> {
+    const reply = await this.read();
+    if (!expected.includes(reply.code)) {
+      throw new SmtpError(`${label} failed: ${reply.code} ${reply.lines.join(' ')}`, reply.code);
+    }
+    return reply;
+  }
+
+  write(data: string): void {
+    this.socket.write(data);
+  }
+
+  close(): void {
+    this.socket.end();
+  }
+
+  private attach(socket: net.Socket): void {
+    socket.setTimeout(COMMAND_TIMEOUT_MS, () => this.fail(new SmtpError('SMTP server timed out')));
+    socket.on('data', (chunk: Buffer) => this.receive(chunk.toString('utf-8')));
+    socket.on('error', error => this.fail(error));
+    socket.on('close', () => this.fail(new SmtpError('SMTP server closed the connection')));
+  }
+
+  private receive(data: string): void {
+    this.buffer += data;
+    let newline: number;
+    while ((newline = this.buffer.indexOf('\n')) >= 0) {
+      const line = this.buffer.slice(0, newline).replace(/\r$/, '');
+      this.buffer = this.buffer.slice(newline + 1);
+      this.current.push(line.slice(4));
+      // "250-..." continues a reply, "250 ..." ends it
+      if (line[3] !== '-') {
+        const reply = { code: Number(line.slice(0, 3)), lines: this.current };
+        this.current = [];
+        if (this.waiting) {
+          const { resolve } = this.waiting;
+          this.waiting = null;
+          resolve(reply);
+        } else {
+          this.replies.push(reply);
+        }
+      }
+    }
+  }
+
+  private fail(error: Error): void {
+    if (this.failure) return;
+    this.failure = error;
+    if (this.waiting) {
+      const { reject } = this.waiting;
+      this.waiting = null;
+      reject(error);
+    }
+  }
+
+  private read(): Promise<SmtpReply> {
+    const queued = this.replies.shift();
+    if (queued) return Promise.resolve(queued);
+    if (this.failure) return Promise.reject(this.failure);
+    return new Promise((resolve, reject) => {
+      this.waiting = { resolve, reject };
+    });
+  }
+}
+
+/**
+ * Send a message with attachments through an SMTP server, with STARTTLS and
+ * AUTH PLAIN/LOGIN. Credentials are only sent over TLS unless the config
+ * allows otherwise. Returns the Message-ID it was sent under.
+ */
+export async function sendMail(config: SmtpConfig, message: MailMessage): Promise<string> {
+  const connection = await SmtpConnection.open(config);
+  const hostname = os.hostname() || 'localhost';
+  try {
+    await connection.expect([220], 'Greeting');
+    let features = await connection.command(`EHLO ${hostname}`, [250]);
+    let encrypted = config.secure;
+
+    if (!config.secure && features.lines.some(line => line.toUpperCase() === 'STARTTLS')) {
+      await connection.command('STARTTLS', [220]);
+      await connection.upgrade(config.host);
+      features = await connection.command(`EHLO ${hostname}`, [250]);
+      encrypted = true;
+    }
+
+    if (config.username) {
+      if (!encrypted && !config.allowInsecureAuth) {
+        throw new SmtpError(`${config.host} does not offer TLS, so the password would be sent unencrypted; use a TLS port or allow insecure authentication`);
+      }
+      const auth = features.lines.find(line => line.toUpperCase().startsWith('AUTH'))?.toUpperCase() ?? '';
+      if (auth.includes('PLAIN')) {
+        const credentials = Buffer.from(`\0${config.username}\0${config.password ?? ''}`).toString('base64');
+        await connection.command(`AUTH PLAIN ${credentials}`, [235]);
+      } else {
+        await connection.command('AUTH LOGIN', [334]);
+        await connection.command(Buffer.from(config.username).toString('base64'), [334]);
+        await connection.command(Buffer.from(config.password ?? '').toString('base64'), [235]);
+      }
+    }
+
+    await connection.command(`MAIL FROM:<${address(config.from)}>`, [250]);
+    for (const recipient of message.to) {
+      await connection.command(`RCPT TO:<${address(recipient)}>`, [250, 251]);
+    }
+    await connection.command('DATA', [354]);
+
+    const messageId = `<${crypto.randomUUID()}@${hostname}>`;
+    // Lines starting with a dot are doubled so they do not end the message
+    const body = buildMessage(config.from, message, messageId).replace(/\r\n\./g, '\r\n..');
+    connection.write(`${body}\r\n.\r\n`);
+    await connection.expect([250], 'Sending message');
+
+    await connection.command('QUIT', [221]).catch(() => undefined);
+    return messageId;
+  } finally {
+    connection.close();
+  }
+}
+
+// The bare address of "Name <address>"
+function address(mailbox: string): string {
+  const match = mailbox.match(/<([^>]+)>/);
+  return (match ? match[1] : mailbox).trim();
+}
+
+function buildMessage(from: string, message: MailMessage, messageId: string): string {
+  const boundary = `pictallion-${crypto.randomBytes(12).toString('hex')}`;
+  const lines = [
+    `From: ${from}`,
+    `To: ${message.to.join(', ')}`,
+    `Subject: ${encodeHeader(message.subject)}`,
+    `Date: ${new Date().toUTCString()}`,
+    `Message-ID: ${messageId}`,
+    'MIME-Version: 1.0',
+    `Content-Type: multipart/mixed; boundary="${boundary}"`,
+    '',
+    `--${boundary}`,
+    'Content-Type: text/plain; charset=utf-8',
+    'Content-Transfer-Encoding: base64',
+    '',
+    base64Lines(Buffer.from(message.text, 'utf-8')),
+  ];
+
+  message.attachments.forEach(attachment => {
+    lines.push(
+      `--${boundary}`,
+      `Content-Type: ${attachment.contentType}; name="${encodeHeader(attachment.filename)}"`,
+      'Content-Transfer-Encoding: base64',
+      `Content-Disposition: attachment; ${filenameParameter(attachment.filename)}`,
+      '',
+      base64Lines(attachment.content),
+    );
+  });
+  lines.push(`--${boundary}--`);
+  return lines.join('\r\n');
+}
+
+// Non-ASCII header text as an RFC 2047 encoded word
+function encodeHeader(value: string): string {
+  return /^[\x20-\x7e]*$/.test(value) ? value : `=?UTF-8?B?${Buffer.from(value, 'utf-8').toString('base64')}?=`;
+}
+
+function filenameParameter(filename: string): string {
+  if (/^[\x20-\x7e]*$/.test(filename) && !filename.includes('"')) return `filename="${filename}"`;
+  return `filename*=UTF-8''${encodeURIComponent(filename)}`;
+}
+
+function base64Lines(content: Buffer): string {
+  const encoded = content.toString('base64');
+  const lines: string[] = [];
+  for (let offset = 0; offset < encoded.length; offset += BASE64_LINE) {
+    lines.push(encoded.slice(offset, offset + BASE64_LINE));
+  }
+  return lines.join('\r\n');
+}
+
+/**
+ * Size of an attachment once base64 encoded into the message, for size budgets
+ */
+export function encodedSize(bytes: number): number {
+  const encoded = Math.ceil(bytes / 3) * 4;
+  return encoded + Math.ceil(encoded / BASE64_LINE) * 2;
+}
@@ -0,0 +1,89 @@
+// NOAA's solar position equations (fractional-year form), good to about a
+// minute for sunrise and sunset and a fraction of a degree for elevation
+
+// Apparent sunrise/sunset: the sun's upper edge on the horizon, with refraction
+const SUNRISE_ZENITH = 90.833;
+
+const toRadians = (degrees: number) => degrees * Math.PI / 180;
+const toDegrees = (radians: number) => radians * 180 / Math.PI;
+
+export interface SunPosition {
+  elevation: number; // degrees above the horizon; negative below it
+  azimuth: number; // degrees clockwise from north
+}
+
+export interface SunTimes {
+  sunrise: Date | null; // null while the sun stays up (or down) all day
+  sunset: Date | null;
+  solarNoon: Date;
+}
+
+/**
+ * Equation of time (minutes) and declination (radians) at an instant
+ */
+function solarParameters(date: Date): { equationOfTime: number; declination: number } {
+  const startOfYear = Date.UTC(date.getUTCFullYear(), 0, 1);
+  const dayOfYear = Math.floor((date.getTime() - startOfYear) / 86400000) + 1;
+  const daysInYear = date.getUTCFullYear() % 4 === 0 && (date.getUTCFullYear() % 100 !== 0 || date.getUTCFullYear() % 400 === 0) ? 366 : 365;
+  const hours = date.getUTCHours() + date.getUTCMinutes() / 60 + date.getUTCSeconds() / 3600;
+  const gamma = 2 * Math.PI / daysInYear * (dayOfYear - 1 + (hours - 12) / 24);
+
+  const equationOfTime = 229.18 * (0.000075 + 0.001868 * Math.cos(gamma) - 0.032077 * Math.sin(gamma)
+    - 0.014615 * Math.cos(2 * gamma) - 0.040849 * Math.sin(2 * gamma));
+  const declination = 0.006918 - 0.399912 * Math.cos(gamma) + 0.070257 * Math.sin(gamma)
+    - 0.006758 * Math.cos(2 * gamma) + 0.000907 * Math.sin(2 * gamma)
+    - 0.002697 * Math.cos(3 * gamma) + 0.00148 * Math.sin(3 * gamma);
+  return { equationOfTime, declination };
+}
+
+/**
+ * Where the sun was at an instant, seen from a position (longitude east positive)
+ */
+export function sunPosition(date: Date, latitude: number, longitude: number): SunPosition {
+  const { equationOfTime, declination } = solarParameters(date);
+  const minutes = date.getUTCHours() * 60 + date.getUTCMinutes() + date.getUTCSeconds() / 60;
+  const trueSolarTime = minutes + equationOfTime + 4 * longitude;
+  const hourAngle = toRadians(trueSolarTime / 4 - 180);
+  const phi = toRadians(latitude);
+
+  const cosZenith = Math.sin(phi) * Math.sin(declination) + Math.cos(phi) * Math.cos(declination) * Math.cos(hourAngle);
+  const zenith = Math.acos(Math.min(1, Math.max(-1, cosZenith)));
+  const azimuth = toDegrees(Math.atan2(
+    Math.sin(hourAngle),
+    Math.cos(hourAngle) * Math.sin(phi) - Math.tan(declination) * Math.cos(phi)
+  )) + 180;
+
+  return { elevation: 90 - toDegrees(zenith), azimuth: azimuth % 360 };
+}
+
+/**
+ * Sunrise, sunset and solar noon for the day that starts at `day` (UTC
+ * midnight of the local date)
+ */
+export function sunTimes(day: Date, latitude: number, longitude: number): SunTimes {
+  const midnight = Date.UTC(day.getUTCFullYear(), day.getUTCMonth(), day.getUTCDate());
+  const { equationOfTime, declination } = solarParameters(new Date(midnight + 12 * 3600000));
+  const phi = toRadians(latitude);
+  const at = (minutes: number) => new Date(midnight + minutes * 60000);
+
+  const solarNoon = at(720 - 4 * longitude - equationOfTime);
+  const cosHourAngle = Math.cos(toRadians(SUNRISE_ZENITH)) / (Math.cos(phi) * Math.cos(declination)) - Math.tan(phi) * Math.tan(declination);
+  if (cosHourAngle < -1 || cosHourAngle > 1) {
+    return { sunrise: null, sunset: null, solarNoon };
+  }
+  const hourAngle = toDegrees(Math.acos(cosHourAngle));
+  return {
+    sunrise: at(720 - 4 * (longitude + hourAngle) - equationOfTime),
+    sunset: at(720 - 4 * (longitude - hourAngle) - equationOfTime),
+    solarNoon,
+  };
+}
+
+/**
+ * The UTC offset a place most likely keeps, from its longitude. Photos store
+ * local wall-clock time without a zone, so this is the best guess available;
+ * it can be an hour out under daylight saving or along zone borders.
+ */
+export function estimateUtcOffsetHours(longitude: number): number {
+  return Math.min(14, Math.max(-12, Math.round(longitude / 15)));
+}
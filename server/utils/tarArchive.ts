@@ -0,0 +1,141 @@
+import fs, { type FileHandle } from "fs/promises";
+import { createReadStream, createWriteStream } from "fs";
+import { pipeline } from "stream/promises";
+
+// Minimal ustar reader and writer: regular files only, names under 100 bytes
+
+const BLOCK_SIZE = 512;
+
+export interface TarEntry {
+  name: string;
+  size: number;
+  offset: number; // of the entry's data in the archive
+}
+
+function octal(value: number, length: number): string {
+  return value.toString(8).padStart(length - 1, '0') + '\0';
+}
+
+function header(name: string, size: number, mtime: Date): Buffer {
+  const nameBytes = Buffer.from(name, 'utf8');
+  if (nameBytes.length >= 100) throw new Error(`Archive entry name too long: ${name}`);
+
+  const block = Buffer.alloc(BLOCK_SIZE);
+  nameBytes.copy(block, 0);
+  block.write(octal(0o644, 8), 100, 'latin1');
+  block.write(octal(0, 8), 108, 'latin1');
+  block.write(octal(0, 8), 116, 'latin1');
+  block.write(octal(size, 12), 124, 'latin1');
+  block.write(octal(Math.floor(mtime.getTime() / 1000), 12), 136, 'latin1');
+  block.write('        ', 148, 'latin1'); // checksum counts its own field as spaces
+  block.write('0', 156, 'latin1');
+  block.write('ustar\u000000', 257, 'latin1');
+
+  let checksum = 0;
+  for (const byte of block) checksum += byte;
+  block.write(checksum.toString(8).padStart(6, '0') + '\0 ', 148, 'latin1');
+  return block;
+}
+
+function padding(size: number): Buffer {
+  return Buffer.alloc((BLOCK_SIZE - (size % BLOCK_SIZE)) % BLOCK_SIZE);
+}
+
+/**
+ * Writes entries one after another; files are streamed, not read into memory
+ */
+export class TarWriter {
+  private constructor(private readonly handle: FileHandle) {}
+
+  static async create(filePath: string): Promise<TarWriter> {
+    return new TarWriter(await fs.open(filePath, 'w'));
+  }
+
+  async addBuffer(name: string, data: Buffer, mtime: Date = new Date()): Promise<void> {
+    await this.handle.write(header(name, data.length, mtime));
+    await this.handle.write(data);
+    await this.handle.write(padding(data.length));
+  }
+
+  async addFile(name: string, sourcePath: string): Promise<void> {
+    const stats = await fs.stat(sourcePath);
+    await this.handle.write(header(name, stats.size, stats.mtime));
+    let written = 0;
+    for await (const chunk of createReadStream(sourcePath)) {
+      await this.handle.write(chunk as Buffer);
+      written += (chunk as Buffer).length;
+    }
+    if (written !== stats.size) throw new Error(`${sourcePath} changed while it was being archived`);
+    await this.handle.write(padding(stats.size));
+  }
+
+  // Two empty blocks end the archive
+  async finish(): Promise<void> {
+    await this.handle.write(Buffer.alloc(BLOCK_SIZE * 2));
+    await this.handle.close();
+  }
+
+  async abort(): Promise<void> {
+    await this.handle.close().catch(() => undefined);
+  }
+}
+
+/**
+ * Regular file entries of an archive, read from the headers without reading the data
+ */
+export async function listTarEntries(filePath: string): Promise<TarEntry[]> {
+  const handle = await fs.open(filePath, 'r');
+  try {
+    const { size: archiveSize } = await handle.stat();
+    const entries: TarEntry[] = [];
+    const block = Buffer.alloc(BLOCK_SIZE);
+    let position = 0;
+
+    while (position + BLOCK_SIZE <= archiveSize) {
+      await handle.read(block, 0, BLOCK_SIZE, position);
+      if (block.every(byte => byte === 0)) break;
+
+      let checksum = 0;
+      block.forEach((byte, index) => { checksum += index >= 148 && index < 156 ? 32 : byte; });
+      if (checksum !== parseInt(block.toString('latin1', 148, 156).replace(/\0.*$/, '').trim(), 8)) {
+        throw new Error(`Damaged archive header at byte ${position}`);
+      }
+
+      const field = (start: number, length: number) => block.toString('utf8', start, start + length).replace(/\0.*$/s, '');
+      const size = parseInt(field(124, 12).trim() || '0', 8);
+      const type = field(156, 1);
+      const prefix = field(345, 155);
+      const name = prefix ? `${prefix}/${field(0, 100)}` : field(0, 100);
+      const offset = position + BLOCK_SIZE;
+      if (offset + size > archiveSize) throw new Error(`Archive is truncated in ${name}`);
+
+      if (type === '0' || type === '') entries.push({ name, size, offset });
+      position = offset + size + padding(size).length;
+    }
+    return entries;
+  } finally {
+    await handle.close();
+  }
+}
+
+export async function readTarEntry(filePath: string, entry: TarEntry): Promise<Buffer> {
+  const handle = await fs.open(filePath, 'r');
+  try {
+    const data = Buffer.alloc(entry.size);
+    await handle.read(data, 0, entry.size, entry.offset);
+    return data;
+  } finally {
+    await handle.close();
+  }
+}
+
+export async function extractTarEntry(filePath: string, entry: TarEntry, destination: string): Promise<void> {
+  if (entry.size === 0) {
+    await fs.writeFile(destination, Buffer.alloc(0));
+    return;
+  }
+  await pipeline(
+    createReadStream(filePath, { start: entry.offset, end: entry.offset + entry.size - 1 }),
+    createWriteStream(destination)
+  );
+}
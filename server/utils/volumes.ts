@@ -0,0 +1,21 @@
+import path from "path";
+
+/**
+ * Best guess at the name of the volume a folder lives on, from its mount
+ * point: /Volumes/<label> on macOS, /media/<user>/<label> and
+ * /run/media/<user>/<label> on Linux, /mnt/<label>, or the drive letter on
+ * Windows. Falls back to the folder's own name.
+ */
+export function guessVolumeLabel(folder: string): string {
+  const resolved = path.resolve(folder);
+  const parts = resolved.split(/[\\/]+/).filter(Boolean);
+
+  if (/^[a-zA-Z]:$/.test(parts[0] ?? '')) {
+    return parts[0].toUpperCase();
+  }
+  if (parts[0] === 'Volumes' && parts[1]) return parts[1];
+  if (parts[0] === 'media' && parts[2]) return parts[2];
+  if (parts[0] === 'run' && parts[1] === 'media' && parts[3]) return parts[3];
+  if (parts[0] === 'mnt' && parts[1]) return parts[1];
+  return path.basename(resolved) || resolved;
+}
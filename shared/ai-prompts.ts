@@ -35,6 +35,7 @@ Analyze the image and return a JSON object with this structure:
   "detectedFaces": [{"faceId": "uuid", "personName": null, "confidence": 0.95, "boundingBox": [x,y,w,h]}],
   "detectedEvents": [{"eventType": "holiday", "eventName": "Christmas", "confidence": 0.9}],
   "placeName": "Location or place name if identifiable",
+  "detectedText": "Legible text in the image (documents, receipts, screens, signs), empty if none",
   "gpsCoordinates": {"latitude": 40.7128, "longitude": -74.0060},
   "aiConfidenceScores": {"tags": 0.9, "description": 0.85, "objects": 0.8, "faces": 0.7, "events": 0.6, "place": 0.7}
 }
@@ -1,5 +1,5 @@
 import { sql } from "drizzle-orm";
-import { pgTable, text, varchar, timestamp, integer, jsonb, boolean, uuid } from "drizzle-orm/pg-core";
+import { pgTable, text, varchar, timestamp, integer, jsonb, boolean, uuid, real, doublePrecision, customType, type AnyPgColumn } from "drizzle-orm/pg-core";
 import { relations } from "drizzle-orm";
 import { createInsertSchema } from "drizzle-zod";
 import { z } from "zod";
@@ -24,9 +24,26 @@ export const fileVersions = pgTable("file_versions", {
   fileHash: text("file_hash").notNull(),
   fileSize: integer("file_size").notNull(),
   mimeType: text("mime_type").notNull(),
+  width: integer("width"), // pixel dimensions, populated on ingest or lazily backfilled
+  height: integer("height"),
+  isPanorama: boolean("is_panorama").default(false), // spherical panorama (XMP GPano), projection in metadata.panorama
   metadata: jsonb("metadata"),
+  // Key EXIF fields copied out of metadata whenever it is written, so search can filter on them
+  captureDate: timestamp("capture_date"), // DateTimeOriginal, else CreateDate
+  takenAt: timestamp("taken_at"), // capture date, else the file's modification time; what dates filter, sort and group on
+  cameraMake: text("camera_make"),
+  cameraModel: text("camera_model"),
+  camera: text("camera"), // canonical "Make Model", as shown in facets
+  lens: text("lens"),
+  iso: integer("iso"),
+  aperture: real("aperture"), // f-number
+  exposureTime: real("exposure_time"), // seconds
+  focalLength: real("focal_length"), // mm
+  gpsLatitude: doublePrecision("gps_latitude"),
+  gpsLongitude: doublePrecision("gps_longitude"),
   isReviewed: boolean("is_reviewed").default(false),
   rating: integer("rating").default(0), // 0-5 star rating
+  pickFlag: text("pick_flag", { enum: ["pick", "reject"] }), // culling flag; null when unflagged
   keywords: text("keywords").array().default(sql`'{}'`), // searchable keywords
   location: text("location"), // GPS coordinates or place name
   eventType: text("event_type"), // holiday, birthday, vacation, etc.
@@ -34,14 +51,23 @@ export const fileVersions = pgTable("file_versions", {
   perceptualHash: text("perceptual_hash"), // for visual similarity detection
   aiShortDescription: text("ai_short_description"), // 2-3 word AI description in PascalCase
   processingState: text("processing_state", { enum: ["processed", "promoted", "rejected"] }).default("processed"), // State management for files
+  isLocked: boolean("is_locked").default(false), // protected from delete, demotion, edits and metadata write-back
+  environment: jsonb("environment").$type<PhotoEnvironment>(), // daylight and weather when taken, from GPS and capture time
+  daylightPhase: text("daylight_phase", { enum: ["night", "blue_hour", "golden_hour", "day", "midday"] }), // derived from GPS and capture time like the EXIF columns
+  promotionScore: real("promotion_score"), // 0-100, see server/services/promotionScoring.ts; null until scored
+  promotionSignals: jsonb("promotion_signals").$type<PromotionSignals>(),
+  deletedAt: timestamp("deleted_at"), // in the trash, restorable until purged
+  trashPath: text("trash_path"), // where the file sits in the trash; null when there was no local file to move
   createdAt: timestamp("created_at").defaultNow().notNull(),
 });
 
 export const assetHistory = pgTable("asset_history", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
   mediaAssetId: varchar("media_asset_id").references(() => mediaAssets.id).notNull(),
+  fileVersionId: varchar("file_version_id").references(() => fileVersions.id, { onDelete: "set null" }), // version acted on, when known
   action: text("action").notNull(),
   details: text("details"),
+  data: jsonb("data"), // structured provenance: source, model, destination
   timestamp: timestamp("timestamp").defaultNow().notNull(),
 });
 
@@ -51,16 +77,22 @@ export const collections = pgTable("collections", {
   description: text("description"),
   isPublic: boolean("is_public").default(false),
   coverPhoto: text("cover_photo"),
+  coverPhotoId: varchar("cover_photo_id").references(() => fileVersions.id, { onDelete: "set null" }), // chosen cover; coverPhoto holds its path
   isSmartCollection: boolean("is_smart_collection").default(false),
   smartRules: jsonb("smart_rules"), // JSON rules for auto-updating collections
+  systemKey: text("system_key").unique(), // built-in albums maintained by the pipeline (screenshots, documents, ...)
+  excludeFromTimeline: boolean("exclude_from_timeline").default(false),
+  personAlbum: jsonb("person_album"), // { personId, options } for generated person albums, see PersonAlbumConfig
+  deletedAt: timestamp("deleted_at"), // soft-deleted, restorable until purged
   createdAt: timestamp("created_at").defaultNow().notNull(),
   updatedAt: timestamp("updated_at").defaultNow().notNull(),
 });
 
 export const collectionPhotos = pgTable("collection_photos", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
-  collectionId: varchar("collection_id").references(() => collections.id).notNull(),
-  photoId: varchar("photo_id").references(() => fileVersions.id).notNull(),
+  collectionId: varchar("collection_id").references(() => collections.id, { onDelete: "cascade" }).notNull(),
+  photoId: varchar("photo_id").references(() => fileVersions.id, { onDelete: "cascade" }).notNull(),
+  sortOrder: integer("sort_order"), // manual position; null until the album is reordered, then later additions go last
   addedAt: timestamp("added_at").defaultNow().notNull(),
 });
 
@@ -72,6 +104,11 @@ export const people = pgTable("people", {
   faceCount: integer("face_count").default(0),
   representativeFace: text("representative_face"),
   selectedThumbnailFaceId: text("selected_thumbnail_face_id"), // ID of the face to use as thumbnail
+  // Privacy: photos showing this person never leave the library through these pathways
+  excludeFromExports: boolean("exclude_from_exports").default(false).notNull(),
+  excludeFromShares: boolean("exclude_from_shares").default(false).notNull(),
+  excludeFromAi: boolean("exclude_from_ai").default(false).notNull(), // no uploads to AI providers
+  deletedAt: timestamp("deleted_at"), // soft-deleted, restorable until purged
   createdAt: timestamp("created_at").defaultNow().notNull(),
 });
 
@@ -108,34 +145,54 @@ export const events = pgTable("events", {
   recurringType: text("recurring_type", { enum: ["yearly", "monthly", "weekly"] }),
   country: text("country"), // For holidays: US, UK, etc.
   region: text("region"), // For regional holidays
-  personId: varchar("person_id").references(() => people.id), // For birthday events
+  personId: varchar("person_id").references(() => people.id, { onDelete: "set null" }), // For birthday events
+  recurrenceRule: jsonb("recurrence_rule"), // EventRecurrenceRule; takes precedence over recurringType
+  durationDays: integer("duration_days").default(1), // e.g. 7 for "lake trip week"
+  personIds: text("person_ids").array(), // people usually in these photos
+  locationIds: text("location_ids").array(), // places where it usually happens
   isEnabled: boolean("is_enabled").default(true),
   description: text("description"),
   createdAt: timestamp("created_at").defaultNow().notNull(),
 });
 
+// Groups of similar unassigned faces, kept between clustering runs so new faces join existing groups
+export const faceClusters = pgTable("face_clusters", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  centroid: jsonb("centroid").notNull(), // mean embedding of the member faces
+  faceCount: integer("face_count").default(0).notNull(),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+  updatedAt: timestamp("updated_at").defaultNow().notNull(),
+});
+
 export const faces = pgTable("faces", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
-  photoId: varchar("photo_id").references(() => fileVersions.id).notNull(),
-  personId: varchar("person_id").references(() => people.id),
+  photoId: varchar("photo_id").references(() => fileVersions.id, { onDelete: "cascade" }).notNull(),
+  personId: varchar("person_id").references(() => people.id, { onDelete: "set null" }),
   boundingBox: jsonb("bounding_box").notNull(),
   confidence: integer("confidence").notNull(), // 0-100
+  detector: text("detector"), // "face-api" or "heuristic"; null for faces found before it was recorded
   embedding: jsonb("embedding"),
+  embeddingModel: text("embedding_model"), // model that produced the embedding; null for legacy vectors
+  clusterId: varchar("cluster_id").references(() => faceClusters.id, { onDelete: "set null" }), // unassigned faces only
   ignored: boolean("ignored").default(false).notNull(), // Mark face as ignored
+  reviewedAt: timestamp("reviewed_at"), // confirmed as a real face in the low-confidence review queue
   createdAt: timestamp("created_at").defaultNow().notNull(),
 });
 
 export const globalTagLibrary = pgTable("global_tag_library", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
-  tag: text("tag").notNull().unique(),
+  tag: text("tag").notNull().unique(), // full path of a nested tag, "animals/dogs/corgi"
+  // The tag one level up ("animals/dogs"); kept in step with the path by the tag hierarchy service
+  parentTagId: varchar("parent_tag_id").references((): AnyPgColumn => globalTagLibrary.id, { onDelete: "set null" }),
   usageCount: integer("usage_count").default(1).notNull(),
+  deletedAt: timestamp("deleted_at"), // soft-deleted, restorable until purged
   createdAt: timestamp("created_at").defaultNow().notNull(),
 });
 
 export const relationships = pgTable("relationships", {
   id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
-  person1Id: varchar("person1_id").references(() => people.id).notNull(),
-  person2Id: varchar("person2_id").references(() => people.id).notNull(),
+  person1Id: varchar("person1_id").references(() => people.id, { onDelete: "cascade" }).notNull(),
+  person2Id: varchar("person2_id").references(() => people.id, { onDelete: "cascade" }).notNull(),
   relationshipType: text("relationship_type", { 
     enum: ["spouse", "partner", "sibling", "parent", "child", "friend", "relative"] 
   }).notNull(),
@@ -158,6 +215,228 @@ export const locations = pgTable("locations", {
   updatedAt: timestamp("updated_at").defaultNow().notNull(),
 });
 
+export const frameTargets = pgTable("frame_targets", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  name: text("name").notNull(),
+  destinationPath: text("destination_path").notNull(), // USB stick, SD card or synced folder
+  volumeLabel: text("volume_label"), // volume destinationPath is on, recorded when it is set
+  photoCount: integer("photo_count").default(500).notNull(),
+  maxWidth: integer("max_width").default(1920).notNull(), // frame resolution
+  maxHeight: integer("max_height").default(1080).notNull(),
+  filters: jsonb("filters"), // search filters used to pick candidates; defaults to gold tier
+  refreshIntervalDays: integer("refresh_interval_days").default(7).notNull(),
+  isEnabled: boolean("is_enabled").default(true).notNull(),
+  lastSyncedAt: timestamp("last_synced_at"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+  updatedAt: timestamp("updated_at").defaultNow().notNull(),
+});
+
+// Folders watched for new files, which are imported into the bronze tier as they appear
+export const watchFolders = pgTable("watch_folders", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  path: text("path").notNull().unique(),
+  volumeLabel: text("volume_label"), // volume the folder is on, recorded when it is added
+  recursive: boolean("recursive").default(true).notNull(),
+  importExisting: boolean("import_existing").default(true).notNull(), // import files already there when watching starts
+  isEnabled: boolean("is_enabled").default(true).notNull(),
+  lastImportAt: timestamp("last_import_at"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+// Copies of originals on external or cold storage volumes. Once the local
+// original is evicted the photo is offline: thumbnails and metadata remain.
+export const coldStorageCopies = pgTable("cold_storage_copies", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  photoId: varchar("photo_id").references(() => fileVersions.id, { onDelete: "cascade" }).notNull().unique(),
+  volumeLabel: text("volume_label").notNull(),
+  archiveRoot: text("archive_root").notNull(), // folder on the volume the copy was written under
+  relativePath: text("relative_path").notNull(), // copy's path below archiveRoot
+  fileHash: text("file_hash").notNull(),
+  fileSize: integer("file_size").notNull(),
+  archivedAt: timestamp("archived_at").defaultNow().notNull(),
+  evictedAt: timestamp("evicted_at"), // local original removed
+  verifiedAt: timestamp("verified_at"), // last time the copy was found with the expected hash
+});
+
+export const photoSources = pgTable("photo_sources", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  mediaAssetId: varchar("media_asset_id").references(() => mediaAssets.id, { onDelete: "cascade" }).notNull(),
+  sourcePath: text("source_path").notNull(), // path or filename as supplied by the import
+  sourceDevice: text("source_device"), // e.g. "Phone backup", "SD card"
+  fileHash: text("file_hash").notNull(),
+  isDuplicate: boolean("is_duplicate").default(false).notNull(), // true when the import was deduplicated into an existing asset
+  importedAt: timestamp("imported_at").defaultNow().notNull(),
+});
+
+export const duplicateDecisions = pgTable("duplicate_decisions", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  photoIds: text("photo_ids").array().notNull(), // members of the reviewed group
+  mediaAssetIds: text("media_asset_ids").array().notNull(), // their assets, which future scans match on
+  decision: text("decision", { enum: ["not_duplicate", "resolved"] }).notNull(),
+  keptPhotoId: varchar("kept_photo_id"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+// Persistent selection sets for multi-session workflows (select now, export/promote later)
+export const selections = pgTable("selections", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  name: text("name").notNull(),
+  description: text("description"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+  updatedAt: timestamp("updated_at").defaultNow().notNull(),
+});
+
+export const selectionPhotos = pgTable("selection_photos", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  selectionId: varchar("selection_id").references(() => selections.id, { onDelete: "cascade" }).notNull(),
+  photoId: varchar("photo_id").references(() => fileVersions.id, { onDelete: "cascade" }).notNull(),
+  addedAt: timestamp("added_at").defaultNow().notNull(),
+});
+
+// Groups of assets (bursts, RAW+JPEG pairs, edits) shown as one cover photo when collapsed
+export const photoStacks = pgTable("photo_stacks", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  primaryAssetId: varchar("primary_asset_id").references(() => mediaAssets.id, { onDelete: "cascade" }).notNull(), // the cover
+  kind: text("kind", { enum: ["burst", "raw_jpeg", "edit", "manual"] }).default("manual").notNull(),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+export const photoStackMembers = pgTable("photo_stack_members", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  stackId: varchar("stack_id").references(() => photoStacks.id, { onDelete: "cascade" }).notNull(),
+  mediaAssetId: varchar("media_asset_id").references(() => mediaAssets.id, { onDelete: "cascade" }).notNull().unique(), // in at most one stack
+  position: integer("position").default(0).notNull(),
+  addedAt: timestamp("added_at").defaultNow().notNull(),
+});
+
+// Burst sequences found from EXIF capture times; a burst is resolved once its best frame is chosen
+export const bursts = pgTable("bursts", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  camera: text("camera"), // make and model the frames were shot with
+  startedAt: timestamp("started_at", { precision: 3 }).notNull(),
+  endedAt: timestamp("ended_at", { precision: 3 }).notNull(),
+  bestPhotoId: varchar("best_photo_id").references(() => fileVersions.id, { onDelete: "set null" }),
+  stackId: varchar("stack_id").references(() => photoStacks.id, { onDelete: "set null" }),
+  resolvedAt: timestamp("resolved_at"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+export const burstFrames = pgTable("burst_frames", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  burstId: varchar("burst_id").references(() => bursts.id, { onDelete: "cascade" }).notNull(),
+  photoId: varchar("photo_id").references(() => fileVersions.id, { onDelete: "cascade" }).notNull().unique(), // in at most one burst
+  position: integer("position").default(0).notNull(),
+  capturedAt: timestamp("captured_at", { precision: 3 }).notNull(), // DateTimeOriginal with its sub-seconds
+  sharpness: real("sharpness"), // 0-1, measured when the best frame is chosen
+});
+
+// Albums proposed from photos that are in no album yet; accepting one creates the collection
+export const albumSuggestions = pgTable("album_suggestions", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  kind: text("kind", { enum: ["event", "place", "people"] }).notNull(), // what grouped the photos: a stretch of time, a place, or people together
+  name: text("name").notNull(), // e.g. "Weekend in Portland with Sam"
+  photoIds: text("photo_ids").array().notNull(),
+  coverPhotoId: varchar("cover_photo_id").references(() => fileVersions.id, { onDelete: "set null" }),
+  personIds: text("person_ids").array().default(sql`'{}'`).notNull(), // people named in the suggestion
+  placeName: text("place_name"),
+  startedAt: timestamp("started_at").notNull(),
+  endedAt: timestamp("ended_at").notNull(),
+  status: text("status", { enum: ["pending", "accepted", "dismissed"] }).default("pending").notNull(),
+  collectionId: varchar("collection_id").references(() => collections.id, { onDelete: "set null" }), // the album it became
+  resolvedAt: timestamp("resolved_at"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+// Persistent background jobs, picked up again after a restart
+export const jobs = pgTable("jobs", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  kind: text("kind", { enum: ["face_recognition"] }).notNull(),
+  status: text("status", { enum: ["queued", "running", "completed", "failed", "cancelled"] }).default("queued").notNull(),
+  photoIds: text("photo_ids").array().notNull(),
+  completed: integer("completed").default(0).notNull(), // photoIds before this index are done
+  failed: integer("failed").default(0).notNull(),
+  result: jsonb("result"),
+  error: text("error"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+  startedAt: timestamp("started_at"),
+  finishedAt: timestamp("finished_at"),
+});
+
+// Imports of files already on disk. files before `completed` have been
+// handled, so a session interrupted by a restart resumes where it stopped.
+export const importSessions = pgTable("import_sessions", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  status: text("status", { enum: ["queued", "running", "completed", "failed", "cancelled"] }).default("queued").notNull(),
+  paths: text("paths").array().notNull(), // files and folders as requested
+  files: text("files").array().notNull(), // importable files found under paths
+  sourceDevice: text("source_device"),
+  completed: integer("completed").default(0).notNull(),
+  imported: integer("imported").default(0).notNull(),
+  duplicates: integer("duplicates").default(0).notNull(),
+  failed: integer("failed").default(0).notNull(),
+  error: text("error"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+  startedAt: timestamp("started_at"),
+  finishedAt: timestamp("finished_at"),
+});
+
+// Journal of undoable user actions. changes holds each affected item's state
+// before and after, so an entry can be undone and redone.
+export const operationJournal = pgTable("operation_journal", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  kind: text("kind", { enum: ["promotion", "deletion", "tags", "album", "people"] }).notNull(),
+  label: text("label").notNull(),
+  changes: jsonb("changes").notNull(),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+  undoneAt: timestamp("undone_at"), // set while undone; cleared again by redo
+});
+
+// Assets of external services (an Immich server, ...) that imports brought into
+// the library. Re-running an import skips what is mapped here already.
+export const externalAssets = pgTable("external_assets", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  source: text("source", { enum: ["immich"] }).notNull(),
+  sourceKey: text("source_key").notNull(), // which server or account, e.g. the Immich server URL
+  externalId: text("external_id").notNull(),
+  photoId: varchar("photo_id").references(() => fileVersions.id, { onDelete: "cascade" }).notNull(),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+// Links that let someone without access to the library see a set of photos or an album
+export const shares = pgTable("shares", {
+  id: varchar("id").primaryKey().default(sql`gen_random_uuid()`),
+  token: text("token").notNull().unique(), // the secret part of the link
+  title: text("title"),
+  collectionId: varchar("collection_id").references(() => collections.id, { onDelete: "cascade" }), // an album, shared as it changes
+  photoIds: jsonb("photo_ids").$type<string[]>(), // or a fixed set of photos
+  allowOriginals: boolean("allow_originals").default(false).notNull(), // otherwise resized copies only
+  passwordHash: text("password_hash"), // scrypt, "salt:hash"
+  expiresAt: timestamp("expires_at"),
+  maxViews: integer("max_views"),
+  viewCount: integer("view_count").default(0).notNull(),
+  lastViewedAt: timestamp("last_viewed_at"),
+  revokedAt: timestamp("revoked_at"),
+  createdAt: timestamp("created_at").defaultNow().notNull(),
+});
+
+const tsvector = customType<{ data: string }>({
+  dataType: () => "tsvector",
+});
+
+// Full-text document per photo: file name, tags, people, albums, captions and
+// AI descriptions. Database triggers (0034-add-photo-search.sql) keep it current.
+export const photoSearchIndex = pgTable("photo_search_index", {
+  photoId: varchar("photo_id").primaryKey().references(() => fileVersions.id, { onDelete: "cascade" }),
+  document: tsvector("document").notNull(),
+  updatedAt: timestamp("updated_at").defaultNow().notNull(),
+});
+
+// SQL files from server/migrations that initializeLibrary has applied
+export const libraryMigrations = pgTable("library_migrations", {
+  name: text("name").primaryKey(),
+  appliedAt: timestamp("applied_at").defaultNow().notNull(),
+});
+
 // Relations
 export const mediaAssetsRelations = relations(mediaAssets, ({ many }) => ({
   fileVersions: many(fileVersions),
@@ -248,6 +527,21 @@ export const insertMediaAssetSchema = createInsertSchema(mediaAssets).pick({
 export const insertFileVersionSchema = createInsertSchema(fileVersions).omit({
   id: true,
   createdAt: true,
+  captureDate: true,
+  takenAt: true,
+  cameraMake: true,
+  cameraModel: true,
+  camera: true,
+  lens: true,
+  iso: true,
+  aperture: true,
+  exposureTime: true,
+  focalLength: true,
+  gpsLatitude: true,
+  gpsLongitude: true,
+  daylightPhase: true,
+  promotionScore: true,
+  promotionSignals: true,
 });
 
 export const insertAssetHistorySchema = createInsertSchema(assetHistory).omit({
@@ -282,7 +576,21 @@ export const insertSettingSchema = createInsertSchema(settings).omit({
   updatedAt: true,
 });
 
-export const insertEventSchema = createInsertSchema(events).omit({
+export const eventRecurrenceRuleSchema = z.object({
+  frequency: z.enum(["yearly", "monthly", "weekly"]),
+  interval: z.number().int().min(1).optional(),
+  month: z.number().int().min(1).max(12).optional(),
+  day: z.number().int().min(1).max(31).optional(),
+  weekday: z.number().int().min(0).max(6).optional(),
+  weekOfMonth: z.union([z.number().int().min(1).max(5), z.literal(-1)]).optional(),
+  until: z.string().optional(),
+});
+
+export const insertEventSchema = createInsertSchema(events, {
+  date: z.coerce.date(),
+  recurrenceRule: eventRecurrenceRuleSchema.nullable().optional(),
+  durationDays: z.number().int().min(1).max(366).optional(),
+}).omit({
   id: true,
   createdAt: true,
 });
@@ -309,6 +617,82 @@ export const insertAIPromptSchema = createInsertSchema(aiPrompts).omit({
   updatedAt: true,
 });
 
+export const insertPhotoSourceSchema = createInsertSchema(photoSources).omit({
+  id: true,
+  importedAt: true,
+});
+
+export const insertDuplicateDecisionSchema = createInsertSchema(duplicateDecisions).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertFrameTargetSchema = createInsertSchema(frameTargets).omit({
+  id: true,
+  volumeLabel: true,
+  lastSyncedAt: true,
+  createdAt: true,
+  updatedAt: true,
+});
+
+export const insertWatchFolderSchema = createInsertSchema(watchFolders).omit({
+  id: true,
+  volumeLabel: true,
+  lastImportAt: true,
+  createdAt: true,
+});
+
+export const insertColdStorageCopySchema = createInsertSchema(coldStorageCopies).omit({
+  id: true,
+  archivedAt: true,
+});
+
+export const insertSelectionSchema = createInsertSchema(selections).omit({
+  id: true,
+  createdAt: true,
+  updatedAt: true,
+});
+
+export const insertPhotoStackSchema = createInsertSchema(photoStacks).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertAlbumSuggestionSchema = createInsertSchema(albumSuggestions).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertBurstSchema = createInsertSchema(bursts).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertJobSchema = createInsertSchema(jobs).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertImportSessionSchema = createInsertSchema(importSessions).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertOperationJournalSchema = createInsertSchema(operationJournal).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertExternalAssetSchema = createInsertSchema(externalAssets).omit({
+  id: true,
+  createdAt: true,
+});
+
+export const insertShareSchema = createInsertSchema(shares).omit({
+  id: true,
+  createdAt: true,
+});
+
 // Types
 export type User = typeof users.$inferSelect;
 export type InsertUser = typeof insertUserSchema._output;
@@ -338,6 +722,38 @@ export type Location = typeof locations.$inferSelect;
 export type InsertLocation = typeof insertLocationSchema._output;
 export type AIPrompt = typeof aiPrompts.$inferSelect;
 export type InsertAIPrompt = typeof insertAIPromptSchema._output;
+export type PhotoSource = typeof photoSources.$inferSelect;
+export type InsertPhotoSource = typeof insertPhotoSourceSchema._output;
+export type DuplicateDecision = typeof duplicateDecisions.$inferSelect;
+export type InsertDuplicateDecision = typeof insertDuplicateDecisionSchema._output;
+export type FrameTarget = typeof frameTargets.$inferSelect;
+export type InsertFrameTarget = typeof insertFrameTargetSchema._output;
+export type WatchFolder = typeof watchFolders.$inferSelect;
+export type InsertWatchFolder = typeof insertWatchFolderSchema._output;
+export type ColdStorageCopy = typeof coldStorageCopies.$inferSelect;
+export type InsertColdStorageCopy = typeof insertColdStorageCopySchema._output;
+export type Selection = typeof selections.$inferSelect;
+export type InsertSelection = typeof insertSelectionSchema._output;
+export type PhotoStack = typeof photoStacks.$inferSelect;
+export type InsertPhotoStack = typeof insertPhotoStackSchema._output;
+export type PhotoStackMember = typeof photoStackMembers.$inferSelect;
+export type Burst = typeof bursts.$inferSelect;
+export type InsertBurst = typeof insertBurstSchema._output;
+export type BurstFrame = typeof burstFrames.$inferSelect;
+export type BurstWithFrames = Burst & { frames: BurstFrame[] }; // frames in capture order
+export type AlbumSuggestion = typeof albumSuggestions.$inferSelect;
+export type InsertAlbumSuggestion = typeof insertAlbumSuggestionSchema._output;
+export type Job = typeof jobs.$inferSelect;
+export type FaceCluster = typeof faceClusters.$inferSelect;
+export type InsertJob = typeof insertJobSchema._output;
+export type ImportSession = typeof importSessions.$inferSelect;
+export type InsertImportSession = typeof insertImportSessionSchema._output;
+export type JournalEntry = typeof operationJournal.$inferSelect;
+export type InsertJournalEntry = typeof insertOperationJournalSchema._output;
+export type ExternalAsset = typeof externalAssets.$inferSelect;
+export type InsertExternalAsset = typeof insertExternalAssetSchema._output;
+export type Share = typeof shares.$inferSelect;
+export type InsertShare = typeof insertShareSchema._output;
 
 // Metadata interfaces
 export interface AIMetadata {
@@ -361,16 +777,23 @@ export interface AIMetadata {
     confidence: number;
   }>;
   placeName?: string;
+  detectedText?: string; // OCR text read by the vision model
   gpsCoordinates?: {
     latitude: number;
     longitude: number;
   };
+  nsfw?: {
+    flagged: boolean; // nudity, sexual content or graphic violence
+    confidence: number;
+  };
   perceptualHash?: string;
   aiConfidenceScores: Record<string, number>;
 }
 
 export interface ExifMetadata {
   camera?: string;
+  cameraMake?: string; // Make and Model tags as read
+  cameraModel?: string;
   lens?: string;
   aperture?: string;
   shutter?: string;
@@ -382,6 +805,7 @@ export interface ExifMetadata {
   dateTimeOriginal?: string;  // When photo was actually taken (most important)
   createDate?: string;        // When photo was created
   modifyDate?: string;        // When photo was last modified
+  subSecTimeOriginal?: string; // Fractional seconds of dateTimeOriginal, e.g. "045"
   gpsLatitude?: number;
   gpsLongitude?: number;
   // Enhanced metadata fields
@@ -391,26 +815,120 @@ export interface ExifMetadata {
   exposureMode?: string;
   meteringMode?: string;
   sceneType?: string;
-  colorSpace?: string;
+  colorSpace?: string; // "sRGB", "Display P3", "Adobe RGB"...; from the ICC profile when there is one
+  colorProfile?: string; // the embedded ICC profile's description
+  wideGamut?: boolean;
   orientation?: string;
   xResolution?: string;
   yResolution?: string;
   resolutionUnit?: string;
+  // Descriptive fields (TIFF tags, PNG text chunks, XMP)
+  description?: string;
+  title?: string;
+  artist?: string;
+  // Camera and lens strings as read, when canonicalization changed them
+  cameraOriginal?: string;
+  lensOriginal?: string;
+}
+
+// Spherical panorama projection from XMP GPano metadata
+export interface PanoramaMetadata {
+  projectionType: string; // equirectangular, cylindrical, ...
+  fullPanoWidth?: number;
+  fullPanoHeight?: number;
+  croppedAreaWidth?: number;
+  croppedAreaHeight?: number;
+  croppedAreaLeft?: number;
+  croppedAreaTop?: number;
+  poseHeadingDegrees?: number;
+  initialViewHeadingDegrees?: number;
+  initialViewPitchDegrees?: number;
+  initialHorizontalFovDegrees?: number;
 }
 
 export interface CombinedMetadata {
   exif?: ExifMetadata;
   ai?: AIMetadata;
+  panorama?: PanoramaMetadata;
+}
+
+export type DaylightPhase = 'night' | 'blue_hour' | 'golden_hour' | 'day' | 'midday';
+export type WeatherCondition = 'clear' | 'partly_cloudy' | 'cloudy' | 'fog' | 'drizzle' | 'rain' | 'snow' | 'thunderstorm';
+
+// What a photo's promotion score was made from, each 0-1; filled in by server/services/promotionScoring.ts
+export interface PromotionSignals {
+  sharpness: number;
+  exposure: number;
+  faces: number;
+  faceCount: number;
+  aesthetic?: number; // AI rating; left out when no provider was available or the photo is kept from AI
+  scoredAt: string; // ISO
+}
+
+// Conditions a photo was taken in; filled in by server/services/photoEnvironment.ts
+export interface PhotoEnvironment {
+  daylight?: {
+    phase: DaylightPhase;
+    sunElevation: number; // degrees above the horizon
+    sunAzimuth: number; // degrees clockwise from north
+    sunrise: string | null; // ISO; null during polar day or night
+    sunset: string | null;
+    utcOffsetHours: number; // estimated from longitude
+  };
+  weather?: {
+    condition: WeatherCondition;
+    weatherCode: number; // WMO code
+    temperatureC: number | null;
+    precipitationMm: number | null;
+    snowfallCm: number | null;
+    cloudCoverPercent: number | null;
+    windSpeedKmh: number | null;
+    source: string;
+  };
+  enrichedAt: string;
+}
+
+// Recurrence for custom events, counted from the event's date
+export type EventRecurrenceRule = z.infer<typeof eventRecurrenceRuleSchema>;
+
+// Generated "best of" album for a person
+export interface PersonAlbumOptions {
+  limit?: number; // maximum photos in the album
+  minConfidence?: number; // minimum face confidence (0-100)
+  minRating?: number;
+  maxPerYear?: number; // cap photos from any single year
 }
 
-// Smart Collection Rules
+export interface PersonAlbumConfig {
+  personId: string;
+  options: PersonAlbumOptions;
+}
+
+// Smart Collection Rules; validated and evaluated by server/services/smartCollectionService.ts
 export interface SmartCollectionRule {
-  field: string; // rating, keywords, eventType, location, etc.
-  operator: 'equals' | 'contains' | 'greater_than' | 'less_than' | 'between' | 'in';
+  field: string; // tag, keywords, person, rating, takenAt, importedAt, modifiedAt, camera, eventType, tier, isReviewed, pickFlag, promotionScore
+  operator: 'equals' | 'contains' | 'greater_than' | 'less_than' | 'between' | 'in' | 'within'; // within: a relative date range
   value: any;
 }
 
 export interface SmartCollectionRules {
   rules: SmartCollectionRule[];
   operator: 'AND' | 'OR'; // how to combine multiple rules
+}
+
+// Library analytics; computed in SQL by storage.getAnalytics. Photos in the trash are left out.
+export interface AnalyticsData {
+  uploadsPerWeek: Array<{ week: string; count: number }>; // week starts Monday, YYYY-MM-DD; weeks without uploads included
+  tierDistribution: Array<{ tier: string; count: number; percentage: number }>;
+  storageByTier: Array<{ tier: string; bytes: number }>;
+  topCameras: Array<{ camera: string; count: number }>;
+  topPeople: Array<{ personId: string; name: string; photoCount: number }>;
+  tagUsageOverTime: Array<{ month: string; tag: string; count: number }>; // most used tags by capture month, YYYY-MM
+  faceDetectionCoverage: {
+    images: number;
+    scanned: number; // face detection has run on them
+    withFaces: number;
+    withNamedFaces: number;
+    percentage: number; // scanned / images
+  };
 }
\ No newline at end of file
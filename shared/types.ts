@@ -13,7 +13,10 @@ export interface Photo {
   eventType?: string;
   eventName?: string;
   perceptualHash?: string;
+  takenAt?: string | null; // capture date, else file modification time
   createdAt: string;
+  stackId?: string; // set when the photo belongs to a stack
+  stackSize?: number;
   mediaAsset: {
     id: string;
     originalFilename: string;